@@ -0,0 +1,483 @@
+//! Thin layer over the zip and tar crates, so the archive tasks don't need to care about formats
+
+use sd_utils::u64_to_frontend;
+
+use std::{
+	fmt,
+	fs::Metadata,
+	path::{Component, Path, PathBuf},
+};
+
+use async_compression::tokio::{bufread::ZstdDecoder, write::ZstdEncoder};
+use async_zip::{
+	tokio::{read::seek::ZipFileReader, write::ZipFileWriter},
+	Compression, ZipDateTime, ZipEntryBuilder,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use tokio::{
+	fs::{self, File, OpenOptions},
+	io::{self, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+};
+use tokio_tar::{Archive, Builder, Entries, Entry};
+use tokio_util::compat::{FuturesAsyncReadCompatExt, FuturesAsyncWriteCompatExt};
+
+use super::{ArchiveEntry, ArchiveFormat};
+
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+const EMPTY_ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x05, 0x06];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_MAGIC: &[u8] = b"ustar";
+
+#[derive(thiserror::Error, Debug)]
+pub enum CodecError {
+	#[error(transparent)]
+	Io(#[from] io::Error),
+	#[error(transparent)]
+	Zip(#[from] async_zip::error::ZipError),
+}
+
+/// Detect an archive format by its magic bytes, zstd compressed files are assumed to be tarballs
+pub async fn detect_format(
+	path: impl AsRef<Path> + Send,
+) -> Result<Option<ArchiveFormat>, io::Error> {
+	let mut file = File::open(path).await?;
+
+	let mut buf = [0u8; TAR_MAGIC_OFFSET + TAR_MAGIC.len()];
+	let mut read = 0;
+	while read < buf.len() {
+		match file.read(&mut buf[read..]).await? {
+			0 => break,
+			n => read += n,
+		}
+	}
+	let buf = &buf[..read];
+
+	Ok(
+		if buf.starts_with(&ZIP_MAGIC) || buf.starts_with(&EMPTY_ZIP_MAGIC) {
+			Some(ArchiveFormat::Zip)
+		} else if buf.starts_with(&ZSTD_MAGIC) {
+			Some(ArchiveFormat::TarZst)
+		} else if buf
+			.get(TAR_MAGIC_OFFSET..)
+			.is_some_and(|magic| magic.starts_with(TAR_MAGIC))
+		{
+			Some(ArchiveFormat::Tar)
+		} else {
+			None
+		},
+	)
+}
+
+pub enum ArchiveWriter {
+	Zip(ZipFileWriter<BufWriter<File>>),
+	Tar(Builder<BufWriter<File>>),
+	TarZst(Builder<ZstdEncoder<BufWriter<File>>>),
+}
+
+impl fmt::Debug for ArchiveWriter {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Zip(_) => "ArchiveWriter::Zip",
+			Self::Tar(_) => "ArchiveWriter::Tar",
+			Self::TarZst(_) => "ArchiveWriter::TarZst",
+		})
+	}
+}
+
+impl ArchiveWriter {
+	/// Create a new archive, failing if something already exists at `path`
+	pub async fn create(
+		path: impl AsRef<Path> + Send,
+		format: ArchiveFormat,
+	) -> Result<Self, CodecError> {
+		let file = BufWriter::new(
+			OpenOptions::new()
+				.write(true)
+				.create_new(true)
+				.open(path)
+				.await?,
+		);
+
+		Ok(match format {
+			ArchiveFormat::Zip => Self::Zip(ZipFileWriter::with_tokio(file)),
+			ArchiveFormat::Tar => Self::Tar(Builder::new(file)),
+			ArchiveFormat::TarZst => Self::TarZst(Builder::new(ZstdEncoder::new(file))),
+		})
+	}
+
+	pub async fn append_dir(
+		&mut self,
+		source: &Path,
+		metadata: &Metadata,
+		name: &str,
+	) -> Result<(), CodecError> {
+		match self {
+			Self::Zip(writer) => {
+				let mut builder =
+					ZipEntryBuilder::new(format!("{name}/").into(), Compression::Stored);
+				if let Ok(modified) = metadata.modified() {
+					builder = builder.last_modification_date(ZipDateTime::from_chrono(
+						&DateTime::<Utc>::from(modified),
+					));
+				}
+
+				writer.write_entry_whole(builder, &[]).await?;
+			}
+			Self::Tar(builder) => builder.append_dir(name, source).await?,
+			Self::TarZst(builder) => builder.append_dir(name, source).await?,
+		}
+
+		Ok(())
+	}
+
+	pub async fn append_file(
+		&mut self,
+		file: &mut File,
+		metadata: &Metadata,
+		name: &str,
+	) -> Result<(), CodecError> {
+		match self {
+			Self::Zip(writer) => {
+				let mut builder = ZipEntryBuilder::new(name.into(), Compression::Deflate);
+				if let Ok(modified) = metadata.modified() {
+					builder = builder.last_modification_date(ZipDateTime::from_chrono(
+						&DateTime::<Utc>::from(modified),
+					));
+				}
+
+				let mut entry = writer.write_entry_stream(builder).await?;
+				io::copy(file, &mut (&mut entry).compat_write()).await?;
+				entry.close().await?;
+			}
+			Self::Tar(builder) => builder.append_file(name, file).await?,
+			Self::TarZst(builder) => builder.append_file(name, file).await?,
+		}
+
+		Ok(())
+	}
+
+	/// Write the archive trailers and flush everything to disk
+	pub async fn finish(self) -> Result<(), CodecError> {
+		match self {
+			Self::Zip(writer) => writer.close().await?.into_inner().shutdown().await?,
+			Self::Tar(builder) => builder.into_inner().await?.shutdown().await?,
+			Self::TarZst(builder) => builder.into_inner().await?.shutdown().await?,
+		}
+
+		Ok(())
+	}
+}
+
+pub enum ArchiveReader {
+	Zip {
+		reader: ZipFileReader<BufReader<File>>,
+		next_index: usize,
+	},
+	Tar(Entries<BufReader<File>>),
+	TarZst(Entries<ZstdDecoder<BufReader<File>>>),
+}
+
+impl fmt::Debug for ArchiveReader {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Zip { next_index, .. } => f
+				.debug_struct("ArchiveReader::Zip")
+				.field("next_index", next_index)
+				.finish_non_exhaustive(),
+			Self::Tar(_) => f.write_str("ArchiveReader::Tar"),
+			Self::TarZst(_) => f.write_str("ArchiveReader::TarZst"),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum Extracted {
+	Directory(PathBuf),
+	File(PathBuf, u64),
+	/// Entries with absolute paths or `..` components are never extracted
+	Unsafe(PathBuf),
+	/// Failed to write this entry, but the following ones can still be extracted
+	Failed(PathBuf, CodecError),
+}
+
+impl ArchiveReader {
+	pub async fn open(
+		path: impl AsRef<Path> + Send,
+		format: ArchiveFormat,
+	) -> Result<Self, CodecError> {
+		let file = BufReader::new(File::open(path).await?);
+
+		Ok(match format {
+			ArchiveFormat::Zip => Self::Zip {
+				reader: ZipFileReader::with_tokio(file).await?,
+				next_index: 0,
+			},
+			ArchiveFormat::Tar => Self::Tar(Archive::new(file).entries()?),
+			ArchiveFormat::TarZst => Self::TarZst(Archive::new(ZstdDecoder::new(file)).entries()?),
+		})
+	}
+
+	/// Number of entries in the archive, only known upfront for zip files
+	pub fn entries_count(&self) -> Option<usize> {
+		match self {
+			Self::Zip { reader, .. } => Some(reader.file().entries().len()),
+			Self::Tar(_) | Self::TarZst(_) => None,
+		}
+	}
+
+	/// Extract the next entry into `target`, returns `None` when there are no more entries
+	pub async fn extract_next(&mut self, target: &Path) -> Option<Result<Extracted, CodecError>> {
+		match self {
+			Self::Zip { reader, next_index } => {
+				let index = *next_index;
+				if index >= reader.file().entries().len() {
+					return None;
+				}
+				*next_index += 1;
+
+				Some(Ok(extract_zip_entry(reader, index, target).await))
+			}
+			Self::Tar(entries) => Some(extract_tar_entry(entries.next().await?, target).await),
+			Self::TarZst(entries) => Some(extract_tar_entry(entries.next().await?, target).await),
+		}
+	}
+
+	pub async fn list(self) -> Result<Vec<ArchiveEntry>, CodecError> {
+		match self {
+			Self::Zip { reader, .. } => reader
+				.file()
+				.entries()
+				.iter()
+				.map(|entry| {
+					Ok(ArchiveEntry {
+						path: PathBuf::from(entry.filename().as_str()?.trim_end_matches('/')),
+						is_dir: entry.dir()?,
+						size: u64_to_frontend(entry.uncompressed_size()),
+						date_modified: entry.last_modification_date().as_chrono().single(),
+					})
+				})
+				.collect(),
+			Self::Tar(entries) => list_tar(entries).await,
+			Self::TarZst(entries) => list_tar(entries).await,
+		}
+	}
+}
+
+/// Only accept relative paths without `..` components, so entries can't escape the target directory
+fn sanitize_entry_path(name: &str) -> Option<PathBuf> {
+	let path = Path::new(name);
+
+	(path.components().next().is_some()
+		&& path
+			.components()
+			.all(|component| matches!(component, Component::Normal(_))))
+	.then(|| path.to_path_buf())
+}
+
+async fn extract_zip_entry(
+	reader: &mut ZipFileReader<BufReader<File>>,
+	index: usize,
+	target: &Path,
+) -> Extracted {
+	let entry = &reader.file().entries()[index];
+
+	let (name, is_dir) = match entry
+		.filename()
+		.as_str()
+		.and_then(|name| Ok((name.to_string(), entry.dir()?)))
+	{
+		Ok(name_and_is_dir) => name_and_is_dir,
+		Err(e) => return Extracted::Failed(PathBuf::from(format!("<entry #{index}>")), e.into()),
+	};
+
+	let Some(relative_path) = sanitize_entry_path(name.trim_end_matches('/')) else {
+		return Extracted::Unsafe(PathBuf::from(name));
+	};
+
+	match write_zip_entry(reader, index, &target.join(&relative_path), is_dir).await {
+		Ok(_) if is_dir => Extracted::Directory(relative_path),
+		Ok(size) => Extracted::File(relative_path, size),
+		Err(e) => Extracted::Failed(relative_path, e),
+	}
+}
+
+async fn write_zip_entry(
+	reader: &mut ZipFileReader<BufReader<File>>,
+	index: usize,
+	full_path: &Path,
+	is_dir: bool,
+) -> Result<u64, CodecError> {
+	if is_dir {
+		fs::create_dir_all(full_path).await?;
+		return Ok(0);
+	}
+
+	if let Some(parent) = full_path.parent() {
+		fs::create_dir_all(parent).await?;
+	}
+
+	let mut file = OpenOptions::new()
+		.write(true)
+		.create_new(true)
+		.open(full_path)
+		.await?;
+
+	let size = io::copy(
+		&mut reader.reader_with_entry(index).await?.compat(),
+		&mut file,
+	)
+	.await?;
+
+	file.flush().await?;
+
+	Ok(size)
+}
+
+async fn extract_tar_entry<R: AsyncRead + Unpin + Send>(
+	entry: Result<Entry<Archive<R>>, io::Error>,
+	target: &Path,
+) -> Result<Extracted, CodecError> {
+	let mut entry = entry?;
+	let relative_path = entry.path()?.into_owned();
+	let is_dir = entry.header().entry_type().is_dir();
+	let size = entry.header().size()?;
+
+	// `unpack_in` already refuses to write outside of `target`
+	Ok(match entry.unpack_in(target).await {
+		Ok(true) if is_dir => Extracted::Directory(relative_path),
+		Ok(true) => Extracted::File(relative_path, size),
+		Ok(false) => Extracted::Unsafe(relative_path),
+		Err(e) => Extracted::Failed(relative_path, e.into()),
+	})
+}
+
+async fn list_tar<R: AsyncRead + Unpin + Send>(
+	mut entries: Entries<R>,
+) -> Result<Vec<ArchiveEntry>, CodecError> {
+	let mut listed = vec![];
+
+	while let Some(entry) = entries.next().await {
+		let entry = entry?;
+		let header = entry.header();
+
+		listed.push(ArchiveEntry {
+			path: entry.path()?.into_owned(),
+			is_dir: header.entry_type().is_dir(),
+			size: u64_to_frontend(header.size()?),
+			date_modified: header
+				.mtime()
+				.ok()
+				.and_then(|mtime| i64::try_from(mtime).ok())
+				.and_then(|secs| DateTime::from_timestamp(secs, 0)),
+		});
+	}
+
+	Ok(listed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use tempfile::tempdir;
+
+	async fn write_archive(path: &Path, format: ArchiveFormat, files: &[(&str, &[u8])]) {
+		let source_dir = tempdir().unwrap();
+		let mut writer = ArchiveWriter::create(path, format).await.unwrap();
+
+		for (i, (name, contents)) in files.iter().enumerate() {
+			let source = source_dir.path().join(i.to_string());
+			fs::write(&source, contents).await.unwrap();
+
+			let mut file = File::open(&source).await.unwrap();
+			let metadata = file.metadata().await.unwrap();
+			writer
+				.append_file(&mut file, &metadata, name)
+				.await
+				.unwrap();
+		}
+
+		writer.finish().await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn roundtrip_every_format() {
+		for format in [
+			ArchiveFormat::Zip,
+			ArchiveFormat::Tar,
+			ArchiveFormat::TarZst,
+		] {
+			let root = tempdir().unwrap();
+			let archive_path = root.path().join(format!("test.{}", format.extension()));
+
+			write_archive(
+				&archive_path,
+				format,
+				&[("a.txt", b"spacedrive"), ("dir/b.bin", &[7; 4096])],
+			)
+			.await;
+
+			assert_eq!(detect_format(&archive_path).await.unwrap(), Some(format));
+
+			let mut entries = ArchiveReader::open(&archive_path, format)
+				.await
+				.unwrap()
+				.list()
+				.await
+				.unwrap();
+			entries.sort_by(|a, b| a.path.cmp(&b.path));
+			assert_eq!(
+				entries
+					.iter()
+					.map(|entry| (entry.path.clone(), entry.size))
+					.collect::<Vec<_>>(),
+				vec![
+					(PathBuf::from("a.txt"), u64_to_frontend(10)),
+					(PathBuf::from("dir/b.bin"), u64_to_frontend(4096))
+				]
+			);
+
+			let target = root.path().join("extracted");
+			fs::create_dir(&target).await.unwrap();
+
+			let mut reader = ArchiveReader::open(&archive_path, format).await.unwrap();
+			while let Some(res) = reader.extract_next(&target).await {
+				assert!(matches!(res.unwrap(), Extracted::File(_, _)));
+			}
+
+			assert_eq!(fs::read(target.join("a.txt")).await.unwrap(), b"spacedrive");
+			assert_eq!(
+				fs::read(target.join("dir").join("b.bin")).await.unwrap(),
+				[7; 4096]
+			);
+		}
+	}
+
+	#[tokio::test]
+	async fn refuse_entries_outside_of_target() {
+		let root = tempdir().unwrap();
+		let archive_path = root.path().join("evil.zip");
+		let target = root.path().join("extracted");
+		fs::create_dir(&target).await.unwrap();
+
+		write_archive(
+			&archive_path,
+			ArchiveFormat::Zip,
+			&[("../evil.txt", b"evil")],
+		)
+		.await;
+
+		let mut reader = ArchiveReader::open(&archive_path, ArchiveFormat::Zip)
+			.await
+			.unwrap();
+
+		assert!(matches!(
+			reader.extract_next(&target).await,
+			Some(Ok(Extracted::Unsafe(_)))
+		));
+		assert!(reader.extract_next(&target).await.is_none());
+		assert!(!fs::try_exists(root.path().join("evil.txt")).await.unwrap());
+	}
+}
@@ -0,0 +1,398 @@
+use crate::{
+	file_system::{get_location_path, get_many_files_datas, walk_directory},
+	job_system::{
+		job::{Job, JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		DispatcherError, SerializableJob,
+	},
+	Error, JobContext, JobName, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_prisma::prisma::{file_path, location};
+use sd_task_system::{TaskDispatcher, TaskOutput, TaskStatus};
+use sd_utils::db::size_in_bytes_from_db;
+
+use std::{
+	collections::HashMap,
+	hash::{Hash, Hasher},
+	mem,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::fs;
+use tracing::{debug, instrument, trace, warn, Level};
+
+use super::{
+	available_path,
+	codec::ArchiveWriter,
+	tasks::{appender, Appender},
+	ArchiveFormat, BATCH_SIZE,
+};
+
+/// Name used for the archive when many files are selected and no name was provided
+const DEFAULT_ARCHIVE_NAME: &str = "Archive";
+
+/// Packs the selected files and directories into a single archive, placed next to them.
+///
+/// Archives are written sequentially by a chain of [`Appender`] tasks, so this job isn't
+/// resumable: on shutdown or cancellation the partial archive is removed.
+#[derive(Debug)]
+pub struct Compressor {
+	// Received arguments
+	location_id: location::id::Type,
+	file_path_ids: Vec<file_path::id::Type>,
+	format: ArchiveFormat,
+	name: Option<String>,
+
+	// Run data
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+}
+
+impl Hash for Compressor {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location_id.hash(state);
+		self.file_path_ids.hash(state);
+		self.format.hash(state);
+		self.name.hash(state);
+	}
+}
+
+impl Job for Compressor {
+	const NAME: JobName = JobName::Compress;
+
+	#[instrument(skip_all, ret(level = Level::TRACE), err)]
+	async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let (archive_path, chunks) = self.init(&ctx).await?;
+
+		let mut writer = ArchiveWriter::create(&archive_path, self.format)
+			.await
+			.map_err(|e| super::Error::Write(archive_path.clone(), e))?;
+
+		for chunk in chunks {
+			let handle = match dispatcher
+				.dispatch(Appender::new(chunk, archive_path.clone(), writer))
+				.await
+			{
+				Ok(handle) => handle,
+				Err(DispatcherError::JobCanceled(_)) => {
+					return Ok(self.cancel_job(&archive_path, &ctx).await);
+				}
+				Err(DispatcherError::Shutdown(_)) => {
+					return Ok(shutdown_job(&archive_path, &ctx).await);
+				}
+			};
+
+			match handle.await {
+				Ok(TaskStatus::Done((_, TaskOutput::Out(out)))) => {
+					writer = self.process_task_output(out, &ctx).await;
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					unreachable!("Appender task <id='{task_id}'> always returns an output");
+				}
+
+				Ok(TaskStatus::Shutdown(_)) => {
+					return Ok(shutdown_job(&archive_path, &ctx).await);
+				}
+
+				Ok(TaskStatus::Error(e)) => {
+					remove_partial_archive(&archive_path, &ctx).await;
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&archive_path, &ctx).await);
+				}
+
+				Err(e) => {
+					remove_partial_archive(&archive_path, &ctx).await;
+					return Err(e.into());
+				}
+			}
+		}
+
+		if let Err(e) = writer.finish().await {
+			remove_partial_archive(&archive_path, &ctx).await;
+			return Err(super::Error::Write(archive_path, e).into());
+		}
+
+		ctx.invalidate_query("search.paths");
+
+		debug!(
+			archive_path = %archive_path.display(),
+			appended_files = self.metadata.appended_files,
+			"Archive created;",
+		);
+
+		let Self {
+			metadata, errors, ..
+		} = self;
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+}
+
+impl Compressor {
+	#[must_use]
+	pub fn new(
+		location_id: location::id::Type,
+		file_path_ids: Vec<file_path::id::Type>,
+		format: ArchiveFormat,
+		name: Option<String>,
+	) -> Self {
+		Self {
+			location_id,
+			file_path_ids,
+			format,
+			name: name.filter(|name| !name.trim().is_empty()),
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+		}
+	}
+
+	/// Resolve where the archive will be written and every entry that will be appended to it
+	async fn init<OuterCtx: OuterContext>(
+		&mut self,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> Result<(PathBuf, Vec<Vec<appender::Entry>>), super::Error> {
+		let db = ctx.db();
+
+		let location_path = get_location_path(db, self.location_id).await?;
+
+		let files_datas = get_many_files_datas(db, &location_path, &self.file_path_ids).await?;
+
+		let Some(archive_dir) = files_datas
+			.first()
+			.and_then(|file_data| file_data.full_path.parent())
+			.map(Path::to_path_buf)
+		else {
+			return Err(super::Error::NothingToCompress);
+		};
+
+		let mut entries = vec![];
+
+		for file_data in &files_datas {
+			let root = &file_data.full_path;
+			let root_name = root
+				.file_name()
+				.map(|name| name.to_string_lossy().to_string())
+				.unwrap_or_default();
+
+			match file_data.is_dir(&mut self.errors) {
+				Some(true) => {
+					let (directories, files) = walk_directory(root, &mut self.errors).await;
+
+					entries.push(appender::Entry {
+						source: root.clone(),
+						name: root_name.clone(),
+						is_dir: true,
+					});
+
+					entries.extend(directories.into_iter().map(|path| appender::Entry {
+						name: entry_name(&root_name, root, &path),
+						source: path,
+						is_dir: true,
+					}));
+
+					entries.extend(files.into_iter().map(|(path, size)| {
+						self.metadata.total_bytes += size;
+						appender::Entry {
+							name: entry_name(&root_name, root, &path),
+							source: path,
+							is_dir: false,
+						}
+					}));
+				}
+				Some(false) => {
+					self.metadata.total_bytes += file_data
+						.file_path
+						.size_in_bytes_bytes
+						.as_deref()
+						.map_or(0, size_in_bytes_from_db);
+					entries.push(appender::Entry {
+						source: root.clone(),
+						name: root_name,
+						is_dir: false,
+					});
+				}
+				None => {}
+			}
+		}
+
+		if entries.is_empty() {
+			return Err(super::Error::NothingToCompress);
+		}
+
+		let archive_name = self.name.clone().unwrap_or_else(|| {
+			if let [single] = files_datas.as_slice() {
+				single
+					.full_path
+					.file_stem()
+					.map(|stem| stem.to_string_lossy().to_string())
+					.unwrap_or_else(|| DEFAULT_ARCHIVE_NAME.to_string())
+			} else {
+				DEFAULT_ARCHIVE_NAME.to_string()
+			}
+		});
+
+		let archive_path =
+			available_path(&archive_dir, &archive_name, Some(self.format.extension())).await?;
+
+		self.metadata.total_files = entries.iter().filter(|entry| !entry.is_dir).count() as u64;
+
+		let chunks = entries
+			.into_iter()
+			.chunks(BATCH_SIZE)
+			.into_iter()
+			.map(Iterator::collect)
+			.collect::<Vec<Vec<_>>>();
+
+		#[allow(clippy::cast_possible_truncation)]
+		{
+			// SAFETY: we know that `chunks.len()` is a valid u32 as we wouldn't dispatch more than `u32::MAX` tasks
+			self.metadata.total_tasks = chunks.len() as u32;
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+			ProgressUpdate::Message(format!(
+				"Compressing {} files into {}",
+				self.metadata.total_files,
+				archive_path
+					.file_name()
+					.map(|name| name.to_string_lossy())
+					.unwrap_or_default()
+			)),
+		])
+		.await;
+
+		Ok((archive_path, chunks))
+	}
+
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		appender::Output {
+			writer,
+			appended_files,
+			appended_bytes,
+			compress_time,
+			errors,
+		}: appender::Output,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> ArchiveWriter {
+		self.metadata.appended_files += appended_files;
+		self.metadata.appended_bytes += appended_bytes;
+		self.metadata.compress_time += compress_time;
+		self.metadata.completed_tasks += 1;
+
+		if !errors.is_empty() {
+			warn!(?errors, "Non critical errors while compressing files;");
+			self.errors.extend(errors);
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			ProgressUpdate::Message(format!(
+				"Compressed {} of {} files",
+				self.metadata.appended_files, self.metadata.total_files
+			)),
+		])
+		.await;
+
+		writer.expect("Appender tasks always hand back the archive writer when done")
+	}
+
+	async fn cancel_job<OuterCtx: OuterContext>(
+		&mut self,
+		archive_path: &Path,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> ReturnStatus {
+		remove_partial_archive(archive_path, ctx).await;
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+// Never serialized, as a partially written archive can't be continued later
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for Compressor {}
+
+async fn shutdown_job(archive_path: &Path, ctx: &impl OuterContext) -> ReturnStatus {
+	remove_partial_archive(archive_path, ctx).await;
+
+	// Nothing to be saved, a partially written archive can't be continued later
+	ReturnStatus::Shutdown(Ok(None))
+}
+
+/// Path of an entry inside the archive, relative to the selected directory it was found in
+fn entry_name(root_name: &str, root: &Path, path: &Path) -> String {
+	path.strip_prefix(root).unwrap_or(path).components().fold(
+		root_name.to_string(),
+		|mut name, component| {
+			name.push('/');
+			name.push_str(&component.as_os_str().to_string_lossy());
+			name
+		},
+	)
+}
+
+async fn remove_partial_archive(archive_path: &Path, ctx: &impl OuterContext) {
+	if let Err(e) = fs::remove_file(archive_path).await {
+		trace!(archive_path = %archive_path.display(), ?e, "Failed to remove partial archive;");
+	}
+
+	ctx.invalidate_query("search.paths");
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	total_files: u64,
+	total_bytes: u64,
+	appended_files: u64,
+	appended_bytes: u64,
+	compress_time: Duration,
+	total_tasks: u32,
+	completed_tasks: u32,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			total_files,
+			total_bytes,
+			appended_files,
+			appended_bytes,
+			compress_time,
+			total_tasks,
+			completed_tasks,
+		}: Metadata,
+	) -> Self {
+		vec![ReportOutputMetadata::Metrics(HashMap::from([
+			("total_files".into(), json!(total_files)),
+			("total_bytes".into(), json!(total_bytes)),
+			("appended_files".into(), json!(appended_files)),
+			("appended_bytes".into(), json!(appended_bytes)),
+			("compress_time".into(), json!(compress_time)),
+			("total_tasks".into(), json!(total_tasks)),
+			("completed_tasks".into(), json!(completed_tasks)),
+		]))]
+	}
+}
@@ -0,0 +1,351 @@
+use crate::{
+	file_system::{get_location_path, get_many_files_datas},
+	indexer,
+	job_system::{
+		job::{Job, JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		DispatcherError, SerializableJob,
+	},
+	Error, JobContext, JobName, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_core_prisma_helpers::location_with_indexer_rules;
+
+use sd_prisma::prisma::{file_path, location, PrismaClient};
+use sd_task_system::{TaskDispatcher, TaskOutput, TaskStatus};
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::HashMap,
+	hash::{Hash, Hasher},
+	mem,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::fs;
+use tracing::{debug, instrument, warn, Level};
+
+use super::{
+	available_path,
+	codec::ArchiveReader,
+	detect_format,
+	tasks::{unpacker, Unpacker},
+	ArchiveFormat,
+};
+
+/// Unpacks an archive into a new directory next to it, named after the archive.
+///
+/// Archives are read sequentially by a chain of [`Unpacker`] tasks, so this job isn't resumable:
+/// on shutdown or cancellation, the files extracted so far are left in place. When done, the
+/// directory containing the archive is shallow indexed, so the extracted files show up right away.
+#[derive(Debug)]
+pub struct Extractor {
+	// Received arguments
+	location_id: location::id::Type,
+	file_path_id: file_path::id::Type,
+
+	// Run data
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+}
+
+impl Hash for Extractor {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location_id.hash(state);
+		self.file_path_id.hash(state);
+	}
+}
+
+impl Job for Extractor {
+	const NAME: JobName = JobName::Extract;
+
+	#[instrument(skip_all, ret(level = Level::TRACE), err)]
+	async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let (archive_path, target_dir, mut reader) = self.init(&ctx).await?;
+
+		loop {
+			let handle = match dispatcher
+				.dispatch(Unpacker::new(
+					archive_path.clone(),
+					target_dir.clone(),
+					reader,
+				))
+				.await
+			{
+				Ok(handle) => handle,
+				Err(DispatcherError::JobCanceled(_)) => return Ok(self.cancel_job(&ctx)),
+				Err(DispatcherError::Shutdown(_)) => return Ok(shutdown_job(&ctx)),
+			};
+
+			match handle.await {
+				Ok(TaskStatus::Done((_, TaskOutput::Out(out)))) => {
+					match self.process_task_output(out, &ctx).await {
+						Some(next_reader) => reader = next_reader,
+						None => break,
+					}
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					unreachable!("Unpacker task <id='{task_id}'> always returns an output");
+				}
+
+				Ok(TaskStatus::Shutdown(_)) => return Ok(shutdown_job(&ctx)),
+
+				Ok(TaskStatus::Error(e)) => {
+					ctx.invalidate_query("search.paths");
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&ctx));
+				}
+
+				Err(e) => {
+					ctx.invalidate_query("search.paths");
+					return Err(e.into());
+				}
+			}
+		}
+
+		debug!(
+			archive_path = %archive_path.display(),
+			target_dir = %target_dir.display(),
+			extracted_files = self.metadata.extracted_files,
+			"Archive extracted;",
+		);
+
+		self.index_extracted(&archive_path, &dispatcher, &ctx)
+			.await?;
+
+		let Self {
+			metadata, errors, ..
+		} = self;
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+}
+
+impl Extractor {
+	#[must_use]
+	pub fn new(location_id: location::id::Type, file_path_id: file_path::id::Type) -> Self {
+		Self {
+			location_id,
+			file_path_id,
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+		}
+	}
+
+	/// Open the archive and create the directory where its entries will be extracted
+	async fn init<OuterCtx: OuterContext>(
+		&mut self,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> Result<(PathBuf, PathBuf, ArchiveReader), super::Error> {
+		let archive_path = get_archive_path(ctx.db(), self.location_id, self.file_path_id).await?;
+
+		let format = detect_format(&archive_path).await?;
+
+		let reader = ArchiveReader::open(&archive_path, format)
+			.await
+			.map_err(|e| super::Error::Read(archive_path.clone(), e))?;
+
+		let target_dir = available_path(
+			archive_path
+				.parent()
+				.expect("archive file_path always has a parent directory"),
+			&extraction_dir_name(&archive_path, format),
+			None,
+		)
+		.await?;
+
+		fs::create_dir(&target_dir).await.map_err(|e| {
+			FileIOError::from((&target_dir, e, "Failed to create extraction directory"))
+		})?;
+
+		if let Some(entries_count) = reader.entries_count() {
+			self.metadata.total_entries = entries_count as u64;
+		}
+
+		ctx.progress(vec![ProgressUpdate::Message(format!(
+			"Extracting {}",
+			archive_path
+				.file_name()
+				.map(|name| name.to_string_lossy())
+				.unwrap_or_default()
+		))])
+		.await;
+
+		Ok((archive_path, target_dir, reader))
+	}
+
+	/// Returns the reader to be handed over to the next task, or `None` if we're done
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		unpacker::Output {
+			reader,
+			processed_entries,
+			extracted_files,
+			extracted_bytes,
+			extract_time,
+			errors,
+		}: unpacker::Output,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> Option<ArchiveReader> {
+		self.metadata.processed_entries += processed_entries;
+		self.metadata.extracted_files += extracted_files;
+		self.metadata.extracted_bytes += extracted_bytes;
+		self.metadata.extract_time += extract_time;
+
+		if !errors.is_empty() {
+			warn!(?errors, "Non critical errors while extracting archive;");
+			self.errors.extend(errors);
+		}
+
+		let mut updates = vec![ProgressUpdate::Message(format!(
+			"Extracted {} files",
+			self.metadata.extracted_files
+		))];
+
+		// Zip files have a central directory, so we can report progress over all entries
+		if self.metadata.total_entries > 0 {
+			updates.push(ProgressUpdate::TaskCount(self.metadata.total_entries));
+			updates.push(ProgressUpdate::CompletedTaskCount(
+				self.metadata.processed_entries,
+			));
+		}
+
+		ctx.progress(updates).await;
+
+		reader
+	}
+
+	/// Shallow index the directory containing the archive, which picks up the newly created
+	/// extraction directory
+	async fn index_extracted<OuterCtx: OuterContext>(
+		&mut self,
+		archive_path: &Path,
+		dispatcher: &JobTaskDispatcher,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> Result<(), Error> {
+		let location = ctx
+			.db()
+			.location()
+			.find_unique(location::id::equals(self.location_id))
+			.include(location_with_indexer_rules::include())
+			.exec()
+			.await
+			.map_err(indexer::Error::from)?
+			.ok_or(super::Error::LocationNotFound(self.location_id))?;
+
+		let parent_dir = archive_path
+			.parent()
+			.expect("archive file_path always has a parent directory");
+
+		ctx.progress_msg("Indexing extracted files").await;
+
+		self.errors.extend(
+			indexer::shallow(location, parent_dir, dispatcher.base_dispatcher(), ctx).await?,
+		);
+
+		ctx.invalidate_query("search.paths");
+
+		Ok(())
+	}
+
+	fn cancel_job<OuterCtx: OuterContext>(
+		&mut self,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> ReturnStatus {
+		ctx.invalidate_query("search.paths");
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+// Never serialized, as an archive can only be read sequentially from its start
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for Extractor {}
+
+fn shutdown_job(ctx: &impl OuterContext) -> ReturnStatus {
+	ctx.invalidate_query("search.paths");
+
+	// Nothing to be saved, the files extracted so far are left in place
+	ReturnStatus::Shutdown(Ok(None))
+}
+
+async fn get_archive_path(
+	db: &PrismaClient,
+	location_id: location::id::Type,
+	file_path_id: file_path::id::Type,
+) -> Result<PathBuf, super::Error> {
+	let location_path = get_location_path(db, location_id).await?;
+
+	get_many_files_datas(db, location_path, &[file_path_id])
+		.await?
+		.pop()
+		.map(|file_data| file_data.full_path)
+		.ok_or(super::Error::FilePathIdNotFound(file_path_id))
+}
+
+/// The extraction directory is named after the archive, without its extension
+fn extraction_dir_name(archive_path: &Path, format: ArchiveFormat) -> String {
+	let file_name = archive_path
+		.file_name()
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or_default();
+
+	format.strip_extension(&file_name).map_or_else(
+		|| {
+			archive_path
+				.file_stem()
+				.map(|stem| stem.to_string_lossy().to_string())
+				.unwrap_or_default()
+		},
+		ToString::to_string,
+	)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	total_entries: u64,
+	processed_entries: u64,
+	extracted_files: u64,
+	extracted_bytes: u64,
+	extract_time: Duration,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			total_entries,
+			processed_entries,
+			extracted_files,
+			extracted_bytes,
+			extract_time,
+		}: Metadata,
+	) -> Self {
+		vec![ReportOutputMetadata::Metrics(HashMap::from([
+			("total_entries".into(), json!(total_entries)),
+			("processed_entries".into(), json!(processed_entries)),
+			("extracted_files".into(), json!(extracted_files)),
+			("extracted_bytes".into(), json!(extracted_bytes)),
+			("extract_time".into(), json!(extract_time)),
+		]))]
+	}
+}
@@ -0,0 +1,166 @@
+use crate::{file_system, indexer};
+
+use sd_prisma::prisma::{file_path, location};
+use sd_utils::error::FileIOError;
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::fs;
+
+mod codec;
+pub mod compressor;
+pub mod extractor;
+mod tasks;
+
+pub use compressor::Compressor;
+pub use extractor::Extractor;
+
+use codec::{ArchiveReader, CodecError};
+
+/// Maximum number of entries that a single archive task will append or extract
+const BATCH_SIZE: usize = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("file_path not in database: <id='{0}'>")]
+	FilePathIdNotFound(file_path::id::Type),
+	#[error("location not found: <id='{0}'>")]
+	LocationNotFound(location::id::Type),
+	#[error("nothing to compress, every selected file failed to be read")]
+	NothingToCompress,
+	#[error("file is not a supported archive: <path='{}'>", .0.display())]
+	UnsupportedArchive(PathBuf),
+
+	#[error("failed to write archive <path='{}'>: {1}", .0.display())]
+	Write(PathBuf, CodecError),
+	#[error("failed to read archive <path='{}'>: {1}", .0.display())]
+	Read(PathBuf, CodecError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error(transparent)]
+	FileSystem(#[from] file_system::Error),
+	#[error(transparent)]
+	Indexer(#[from] indexer::Error),
+}
+
+impl From<Error> for rspc::Error {
+	fn from(e: Error) -> Self {
+		match e {
+			Error::FilePathIdNotFound(_) | Error::LocationNotFound(_) => {
+				Self::with_cause(ErrorCode::NotFound, e.to_string(), e)
+			}
+			Error::NothingToCompress | Error::UnsupportedArchive(_) | Error::Read(_, _) => {
+				Self::with_cause(ErrorCode::BadRequest, e.to_string(), e)
+			}
+			Error::FileSystem(e) => e.into(),
+			Error::Indexer(e) => e.into(),
+			_ => Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e),
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize, Type, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum NonCriticalArchiveError {
+	#[error("failed to add file to archive <path='{}'>: {1}", .0.display())]
+	Append(PathBuf, String),
+	#[error("failed to extract archive entry <path='{}'>: {1}", .0.display())]
+	Extract(PathBuf, String),
+	#[error("refused to extract archive entry outside of the target directory: <path='{}'>", .0.display())]
+	UnsafeEntryPath(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+	Zip,
+	Tar,
+	TarZst,
+}
+
+impl ArchiveFormat {
+	#[must_use]
+	pub const fn extension(self) -> &'static str {
+		match self {
+			Self::Zip => "zip",
+			Self::Tar => "tar",
+			Self::TarZst => "tar.zst",
+		}
+	}
+
+	/// Strip this format's extension from an archive file name, if it has it
+	fn strip_extension(self, file_name: &str) -> Option<&str> {
+		let extension = self.extension();
+
+		file_name
+			.len()
+			.checked_sub(extension.len() + 1)
+			.filter(|&dot| {
+				dot > 0
+					&& file_name.is_char_boundary(dot)
+					&& file_name[dot..].starts_with('.')
+					&& file_name[dot + 1..].eq_ignore_ascii_case(extension)
+			})
+			.map(|dot| &file_name[..dot])
+	}
+}
+
+/// An entry inside an archive, as listed without extracting it
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ArchiveEntry {
+	pub path: PathBuf,
+	pub is_dir: bool,
+	/// Uncompressed size of the entry
+	pub size: (u32, u32),
+	pub date_modified: Option<DateTime<Utc>>,
+}
+
+/// List every entry of the archive at `path` without extracting anything
+pub async fn list_entries(path: impl AsRef<Path> + Send) -> Result<Vec<ArchiveEntry>, Error> {
+	let path = path.as_ref();
+
+	let format = detect_format(path).await?;
+
+	ArchiveReader::open(path, format)
+		.await
+		.map_err(|e| Error::Read(path.to_path_buf(), e))?
+		.list()
+		.await
+		.map_err(|e| Error::Read(path.to_path_buf(), e))
+}
+
+/// Find a path inside `dir` named `name` (plus `extension`) that doesn't exist yet, appending
+/// ` (1)`, ` (2)`, ... to the name if needed. We don't use
+/// [`find_available_filename_for_duplicate`](file_system::find_available_filename_for_duplicate)
+/// here, as it would split double extensions like `.tar.zst`.
+async fn available_path(dir: &Path, name: &str, extension: Option<&str>) -> Result<PathBuf, Error> {
+	for i in 0..u32::MAX {
+		let candidate_name = match (i, extension) {
+			(0, None) => name.to_string(),
+			(0, Some(extension)) => format!("{name}.{extension}"),
+			(i, None) => format!("{name} ({i})"),
+			(i, Some(extension)) => format!("{name} ({i}).{extension}"),
+		};
+
+		let candidate = dir.join(candidate_name);
+
+		match fs::try_exists(&candidate).await {
+			Ok(false) => return Ok(candidate),
+			Ok(true) => continue,
+			Err(e) => return Err(FileIOError::from((candidate, e)).into()),
+		}
+	}
+
+	Err(file_system::Error::FailedToFindAvailableName(dir.join(name).into_boxed_path()).into())
+}
+
+async fn detect_format(path: &Path) -> Result<ArchiveFormat, Error> {
+	codec::detect_format(path)
+		.await
+		.map_err(|e| FileIOError::from((path, e, "Failed to read archive header")))?
+		.ok_or_else(|| Error::UnsupportedArchive(path.to_path_buf()))
+}
@@ -0,0 +1,198 @@
+use crate::{
+	archive::{
+		self,
+		codec::{ArchiveWriter, CodecError},
+		NonCriticalArchiveError,
+	},
+	Error, NonCriticalError,
+};
+
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, IntoAnyTaskOutput, Task, TaskId,
+};
+
+use std::{
+	collections::VecDeque,
+	fs::Metadata,
+	mem,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use tokio::{
+	fs::{self, File},
+	io,
+	time::Instant,
+};
+use tracing::{instrument, trace, Level};
+
+/// A file or directory to be added to the archive
+#[derive(Debug)]
+pub struct Entry {
+	pub source: PathBuf,
+	/// Path inside the archive, always using `/` as separator
+	pub name: String,
+	pub is_dir: bool,
+}
+
+/// Appends a batch of entries to an archive.
+///
+/// Archives must be written sequentially, so the writer is handed over from one task to the next
+/// through the task [`Output`].
+#[derive(Debug)]
+pub struct Appender {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	entries: VecDeque<Entry>,
+	archive_path: PathBuf,
+	writer: Option<ArchiveWriter>,
+
+	// Out collector
+	output: Output,
+}
+
+/// [`Appender`] task output
+#[derive(Debug, Default)]
+pub struct Output {
+	/// The writer received by the task, to be used by the next one
+	pub writer: Option<ArchiveWriter>,
+	/// Number of files added to the archive
+	pub appended_files: u64,
+	/// Sum of the sizes of the files added to the archive
+	pub appended_bytes: u64,
+	/// Time spent reading files and writing them to the archive
+	pub compress_time: Duration,
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for Appender {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	fn with_priority(&self) -> bool {
+		// Archiving is user driven, so it must complete as soon as possible
+		true
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(
+			task_id = %self.id,
+			archive_path = %self.archive_path.display(),
+			entries_count = %self.entries.len(),
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			entries,
+			archive_path,
+			writer,
+			output:
+				Output {
+					appended_files,
+					appended_bytes,
+					compress_time,
+					errors,
+					..
+				},
+			..
+		} = self;
+
+		let writer = writer
+			.as_mut()
+			.expect("writer is only taken when the task is done");
+
+		let start_time = Instant::now();
+
+		while let Some(entry) = entries.pop_front() {
+			match append_entry(writer, &entry)
+				.await
+				.map_err(|e| archive::Error::Write(archive_path.clone(), e))?
+			{
+				Ok(size) => {
+					trace!(source = %entry.source.display(), name = entry.name, "Entry appended;");
+					if !entry.is_dir {
+						*appended_files += 1;
+						*appended_bytes += size;
+					}
+				}
+				Err(e) => {
+					errors.push(NonCriticalArchiveError::Append(entry.source, e.to_string()).into())
+				}
+			}
+
+			check_interruption!(interrupter, start_time, compress_time);
+		}
+
+		*compress_time += start_time.elapsed();
+
+		self.output.writer = self.writer.take();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl Appender {
+	#[must_use]
+	pub(in crate::archive) fn new(
+		entries: impl IntoIterator<Item = Entry>,
+		archive_path: PathBuf,
+		writer: ArchiveWriter,
+	) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			entries: entries.into_iter().collect(),
+			archive_path,
+			writer: Some(writer),
+			output: Output::default(),
+		}
+	}
+}
+
+/// Failing to read the source file only skips it, while failing to write the archive is fatal,
+/// as it is left in an inconsistent state
+async fn append_entry(
+	writer: &mut ArchiveWriter,
+	Entry {
+		source,
+		name,
+		is_dir,
+	}: &Entry,
+) -> Result<Result<u64, io::Error>, CodecError> {
+	if *is_dir {
+		let metadata = match fs::metadata(source).await {
+			Ok(metadata) => metadata,
+			Err(e) => return Ok(Err(e)),
+		};
+
+		return writer
+			.append_dir(source, &metadata, name)
+			.await
+			.map(|()| Ok(0));
+	}
+
+	let (mut file, metadata) = match open_source(source).await {
+		Ok(file_and_metadata) => file_and_metadata,
+		Err(e) => return Ok(Err(e)),
+	};
+
+	writer
+		.append_file(&mut file, &metadata, name)
+		.await
+		.map(|()| Ok(metadata.len()))
+}
+
+async fn open_source(source: &Path) -> Result<(File, Metadata), io::Error> {
+	let file = File::open(source).await?;
+	let metadata = file.metadata().await?;
+
+	Ok((file, metadata))
+}
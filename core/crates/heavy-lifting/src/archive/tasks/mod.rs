@@ -0,0 +1,5 @@
+pub mod appender;
+pub mod unpacker;
+
+pub use appender::Appender;
+pub use unpacker::Unpacker;
@@ -0,0 +1,151 @@
+use crate::{
+	archive::{
+		self,
+		codec::{ArchiveReader, Extracted},
+		NonCriticalArchiveError, BATCH_SIZE,
+	},
+	Error, NonCriticalError,
+};
+
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, IntoAnyTaskOutput, Task, TaskId,
+};
+
+use std::{mem, path::PathBuf, time::Duration};
+
+use tokio::time::Instant;
+use tracing::{instrument, trace, Level};
+
+/// Extracts a batch of entries from an archive into a directory.
+///
+/// Archives must be read sequentially, so the reader is handed over from one task to the next
+/// through the task [`Output`], until there are no more entries to extract.
+#[derive(Debug)]
+pub struct Unpacker {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	archive_path: PathBuf,
+	target_dir: PathBuf,
+	reader: Option<ArchiveReader>,
+
+	// Out collector
+	output: Output,
+}
+
+/// [`Unpacker`] task output
+#[derive(Debug, Default)]
+pub struct Output {
+	/// The reader received by the task, `None` if the whole archive was extracted
+	pub reader: Option<ArchiveReader>,
+	/// Number of entries read from the archive by this task, including the ones that failed
+	pub processed_entries: u64,
+	/// Number of files extracted
+	pub extracted_files: u64,
+	/// Sum of the sizes of the extracted files
+	pub extracted_bytes: u64,
+	/// Time spent reading the archive and writing files
+	pub extract_time: Duration,
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for Unpacker {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	fn with_priority(&self) -> bool {
+		// Extracting is user driven, so it must complete as soon as possible
+		true
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(
+			task_id = %self.id,
+			archive_path = %self.archive_path.display(),
+			target_dir = %self.target_dir.display(),
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			archive_path,
+			target_dir,
+			reader,
+			output:
+				Output {
+					processed_entries,
+					extracted_files,
+					extracted_bytes,
+					extract_time,
+					errors,
+					..
+				},
+			..
+		} = self;
+
+		let current_reader = reader
+			.as_mut()
+			.expect("reader is only taken when the task is done");
+
+		let start_time = Instant::now();
+
+		while *processed_entries < BATCH_SIZE as u64 {
+			let Some(res) = current_reader.extract_next(target_dir).await else {
+				// No more entries, so we drop the reader and let the job know that we're done
+				reader.take();
+				break;
+			};
+
+			*processed_entries += 1;
+
+			match res.map_err(|e| archive::Error::Read(archive_path.clone(), e))? {
+				Extracted::Directory(path) => {
+					trace!(path = %path.display(), "Directory extracted;");
+				}
+				Extracted::File(path, size) => {
+					trace!(path = %path.display(), size, "File extracted;");
+					*extracted_files += 1;
+					*extracted_bytes += size;
+				}
+				Extracted::Unsafe(path) => {
+					errors.push(NonCriticalArchiveError::UnsafeEntryPath(path).into());
+				}
+				Extracted::Failed(path, e) => {
+					errors.push(NonCriticalArchiveError::Extract(path, e.to_string()).into());
+				}
+			}
+
+			check_interruption!(interrupter, start_time, extract_time);
+		}
+
+		*extract_time += start_time.elapsed();
+
+		self.output.reader = self.reader.take();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl Unpacker {
+	#[must_use]
+	pub(in crate::archive) fn new(
+		archive_path: PathBuf,
+		target_dir: PathBuf,
+		reader: ArchiveReader,
+	) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			archive_path,
+			target_dir,
+			reader: Some(reader),
+			output: Output::default(),
+		}
+	}
+}
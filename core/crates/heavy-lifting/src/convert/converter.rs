@@ -0,0 +1,514 @@
+use crate::{
+	file_identifier,
+	file_system::{get_location_path, get_many_files_datas},
+	indexer,
+	job_system::{
+		job::{Job, JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		utils::cancel_pending_tasks,
+		DispatcherError, JobErrorOrDispatcherError, SerializableJob,
+	},
+	media_processor, Error, JobContext, JobName, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_core_file_path_helper::{
+	filter_existing_file_path_params, join_location_relative_path, IsolatedFilePathData,
+};
+use sd_core_prisma_helpers::location_with_indexer_rules;
+
+use sd_prisma::prisma::{file_path, location, object_derivation};
+use sd_task_system::{AnyTaskOutput, TaskDispatcher, TaskHandle, TaskId, TaskOutput, TaskStatus};
+
+use std::{
+	collections::HashMap,
+	hash::{Hash, Hasher},
+	mem,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use chrono::Utc;
+use futures::{stream::FuturesUnordered, StreamExt};
+use futures_concurrency::future::TryJoin;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, instrument, warn, Level};
+
+use super::{
+	tasks::{
+		file_converter::{self, ConvertEntry, Converted},
+		FileConverter,
+	},
+	ConvertOptions, NonCriticalConvertError, BATCH_SIZE,
+};
+
+/// Converts the selected files to another format, writing the results in a directory of the
+/// target location.
+///
+/// When done, the target directory is shallow indexed, identified and processed, so the new files
+/// show up right away with their thumbnails, and each resulting object is linked to the object it
+/// was derived from. Transcoding a file can't be resumed halfway, so this job isn't resumable: on
+/// shutdown, the files converted so far are left in place.
+#[derive(Debug)]
+pub struct Converter {
+	// Received arguments
+	source_location_id: location::id::Type,
+	target_location_id: location::id::Type,
+	sources_file_path_ids: Vec<file_path::id::Type>,
+	target_location_relative_directory_path: PathBuf,
+	options: ConvertOptions,
+
+	// Run data
+	target_directory: PathBuf,
+	converted: Vec<Converted>,
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+}
+
+impl Hash for Converter {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.source_location_id.hash(state);
+		self.target_location_id.hash(state);
+		self.sources_file_path_ids.hash(state);
+		self.target_location_relative_directory_path.hash(state);
+		self.options.hash(state);
+	}
+}
+
+impl Job for Converter {
+	const NAME: JobName = JobName::Convert;
+
+	#[instrument(skip_all, ret(level = Level::TRACE), err)]
+	async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let mut pending_running_tasks = FuturesUnordered::new();
+
+		match self
+			.init(&mut pending_running_tasks, &ctx, &dispatcher)
+			.await
+		{
+			Ok(()) => { /* Everything is awesome! */ }
+			Err(JobErrorOrDispatcherError::JobError(e)) => {
+				return Err(e.into());
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::JobCanceled(_))) => {
+				return Ok(self.cancel_job(&mut pending_running_tasks).await);
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::Shutdown(_))) => {
+				cancel_pending_tasks(&mut pending_running_tasks).await;
+
+				return Ok(shutdown_job(&ctx));
+			}
+		}
+
+		let mut shutdown = false;
+
+		while let Some(task) = pending_running_tasks.next().await {
+			match task {
+				Ok(TaskStatus::Done((task_id, TaskOutput::Out(out)))) => {
+					self.process_task_output(task_id, out, &ctx).await;
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					warn!(%task_id, "Task returned an empty output");
+				}
+
+				Ok(TaskStatus::Shutdown(_)) => {
+					// The interrupted task removed its partially converted file, the remaining
+					// ones are dropped
+					shutdown = true;
+				}
+
+				Ok(TaskStatus::Error(e)) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&mut pending_running_tasks).await);
+				}
+
+				Err(e) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e.into());
+				}
+			}
+		}
+
+		if shutdown {
+			return Ok(shutdown_job(&ctx));
+		}
+
+		if !self.converted.is_empty() {
+			self.index_converted(&dispatcher, &ctx).await?;
+		}
+
+		let Self {
+			metadata, errors, ..
+		} = self;
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+}
+
+impl Converter {
+	#[must_use]
+	pub fn new(
+		source_location_id: location::id::Type,
+		target_location_id: location::id::Type,
+		sources_file_path_ids: Vec<file_path::id::Type>,
+		target_location_relative_directory_path: impl Into<PathBuf>,
+		options: ConvertOptions,
+	) -> Self {
+		Self {
+			source_location_id,
+			target_location_id,
+			sources_file_path_ids,
+			target_location_relative_directory_path: target_location_relative_directory_path.into(),
+			options,
+			target_directory: PathBuf::new(),
+			converted: Vec::new(),
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+		}
+	}
+
+	async fn init<OuterCtx: OuterContext>(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+	) -> Result<(), JobErrorOrDispatcherError<super::Error>> {
+		let db = ctx.db();
+
+		let (sources_location_path, target_location_path) = (
+			get_location_path(db, self.source_location_id),
+			get_location_path(db, self.target_location_id),
+		)
+			.try_join()
+			.await
+			.map_err(super::Error::from)?;
+
+		self.target_directory = join_location_relative_path(
+			&target_location_path,
+			&self.target_location_relative_directory_path,
+		);
+
+		let mut entries = Vec::with_capacity(self.sources_file_path_ids.len());
+
+		for file_data in
+			get_many_files_datas(db, &sources_location_path, &self.sources_file_path_ids)
+				.await
+				.map_err(super::Error::from)?
+		{
+			match file_data.is_dir(&mut self.errors) {
+				// Directories aren't walked, as we can only link outputs to sources that were
+				// already identified
+				Some(false) if self.options.target.accepts(&file_data.full_path) => {
+					entries.push(ConvertEntry {
+						source: file_data.full_path,
+						source_object_id: file_data.file_path.object_id,
+					});
+				}
+				Some(_) => self
+					.errors
+					.push(NonCriticalConvertError::UnsupportedSource(file_data.full_path).into()),
+				None => {}
+			}
+		}
+
+		if entries.is_empty() {
+			return Err(super::Error::NothingToConvert.into());
+		}
+
+		self.metadata.total_files = entries.len() as u64;
+
+		let tasks = entries
+			.into_iter()
+			.chunks(BATCH_SIZE)
+			.into_iter()
+			.map(|chunk| FileConverter::new(chunk, self.target_directory.clone(), self.options))
+			.collect::<Vec<_>>();
+
+		#[allow(clippy::cast_possible_truncation)]
+		{
+			// SAFETY: we know that `tasks.len()` is a valid u32 as we wouldn't dispatch more than `u32::MAX` tasks
+			self.metadata.total_tasks = tasks.len() as u32;
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+			ProgressUpdate::Message(format!(
+				"Converting {} files to {}",
+				self.metadata.total_files,
+				self.options.target.extension()
+			)),
+		])
+		.await;
+
+		pending_running_tasks.extend(dispatcher.dispatch_many(tasks).await?);
+
+		Ok(())
+	}
+
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		task_id: TaskId,
+		any_task_output: Box<dyn AnyTaskOutput>,
+		ctx: &impl JobContext<OuterCtx>,
+	) {
+		let file_converter::Output {
+			converted,
+			convert_time,
+			errors,
+		} = *any_task_output
+			.downcast::<file_converter::Output>()
+			.expect("Converter job only dispatches FileConverter tasks");
+
+		self.metadata.converted_files += converted.len() as u64;
+		self.metadata.convert_time += convert_time;
+		self.metadata.completed_tasks += 1;
+
+		self.converted.extend(converted);
+
+		if !errors.is_empty() {
+			warn!(?errors, "Non critical errors while converting files;");
+			self.errors.extend(errors);
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			ProgressUpdate::Message(format!(
+				"Converted {} of {} files",
+				self.metadata.converted_files, self.metadata.total_files
+			)),
+		])
+		.await;
+
+		debug!(
+			%task_id,
+			"Processed ({}/{}) convert tasks, took: {convert_time:?};",
+			self.metadata.completed_tasks, self.metadata.total_tasks,
+		);
+	}
+
+	/// Shallow index, identify and process the target directory, so we can link the objects of
+	/// the converted files to the objects they were derived from
+	async fn index_converted<OuterCtx: OuterContext>(
+		&mut self,
+		dispatcher: &JobTaskDispatcher,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> Result<(), Error> {
+		let db = ctx.db();
+
+		let location = db
+			.location()
+			.find_unique(location::id::equals(self.target_location_id))
+			.include(location_with_indexer_rules::include())
+			.exec()
+			.await
+			.map_err(super::Error::from)?
+			.ok_or(super::Error::LocationNotFound(self.target_location_id))?;
+
+		let location_data = location::Data::from(&location);
+		let location_path = location_data.path.clone().map(PathBuf::from);
+
+		ctx.progress_msg("Indexing converted files").await;
+
+		self.errors.extend(
+			indexer::shallow(
+				location,
+				&self.target_directory,
+				dispatcher.base_dispatcher(),
+				ctx,
+			)
+			.await?,
+		);
+
+		self.errors.extend(
+			file_identifier::shallow(
+				location_data.clone(),
+				&self.target_directory,
+				dispatcher.base_dispatcher(),
+				ctx,
+			)
+			.await?,
+		);
+
+		self.errors.extend(
+			media_processor::shallow(
+				location_data,
+				&self.target_directory,
+				dispatcher.base_dispatcher(),
+				ctx,
+			)
+			.await?,
+		);
+
+		if let Some(location_path) = location_path {
+			self.link_to_sources(&location_path, ctx).await;
+		}
+
+		ctx.invalidate_query("search.paths");
+		ctx.invalidate_query("search.objects");
+
+		Ok(())
+	}
+
+	async fn link_to_sources(&mut self, location_path: &Path, ctx: &impl OuterContext) {
+		let db = ctx.db();
+
+		let mut to_link = Vec::with_capacity(self.converted.len());
+
+		for Converted {
+			source_object_id,
+			target,
+		} in mem::take(&mut self.converted)
+		{
+			// Sources that weren't identified yet have nothing to be linked to
+			let Some(source_object_id) = source_object_id else {
+				continue;
+			};
+
+			match IsolatedFilePathData::new(self.target_location_id, location_path, &target, false)
+			{
+				Ok(iso_file_path) => to_link.push((source_object_id, target, iso_file_path)),
+				Err(e) => self
+					.errors
+					.push(NonCriticalConvertError::LinkToSource(target, e.to_string()).into()),
+			}
+		}
+
+		if to_link.is_empty() {
+			return;
+		}
+
+		let derived_objects = match db
+			._batch(
+				to_link
+					.iter()
+					.map(|(_, _, iso_file_path)| {
+						db.file_path()
+							.find_first(filter_existing_file_path_params(iso_file_path))
+							.select(file_path::select!({ object_id }))
+					})
+					.collect::<Vec<_>>(),
+			)
+			.await
+		{
+			Ok(derived_objects) => derived_objects,
+			Err(e) => {
+				self.errors
+					.extend(to_link.into_iter().map(|(_, target, _)| {
+						NonCriticalConvertError::LinkToSource(target, e.to_string()).into()
+					}));
+				return;
+			}
+		};
+
+		let mut links = Vec::with_capacity(to_link.len());
+
+		for ((source_id, target, _), derived) in to_link.into_iter().zip(derived_objects) {
+			if let Some(derived_id) = derived.and_then(|file_path| file_path.object_id) {
+				links.push((source_id, derived_id, target));
+			} else {
+				self.errors.push(
+					NonCriticalConvertError::LinkToSource(
+						target,
+						"converted file wasn't identified".to_string(),
+					)
+					.into(),
+				);
+			}
+		}
+
+		if let Err(e) = db
+			.object_derivation()
+			.create_many(
+				links
+					.iter()
+					.map(
+						|&(source_id, derived_id, _)| object_derivation::CreateUnchecked {
+							source_id,
+							derived_id,
+							_params: vec![object_derivation::date_created::set(Some(
+								Utc::now().into(),
+							))],
+						},
+					)
+					.collect(),
+			)
+			.skip_duplicates()
+			.exec()
+			.await
+		{
+			self.errors.extend(links.into_iter().map(|(_, _, target)| {
+				NonCriticalConvertError::LinkToSource(target, e.to_string()).into()
+			}));
+		}
+	}
+
+	async fn cancel_job(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+	) -> ReturnStatus {
+		cancel_pending_tasks(pending_running_tasks).await;
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+// Never serialized, as a transcoding can't be resumed from where it stopped
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for Converter {}
+
+fn shutdown_job(ctx: &impl OuterContext) -> ReturnStatus {
+	ctx.invalidate_query("search.paths");
+
+	// Nothing to be saved, the files converted so far are left in place
+	ReturnStatus::Shutdown(Ok(None))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	total_files: u64,
+	converted_files: u64,
+	convert_time: Duration,
+	total_tasks: u32,
+	completed_tasks: u32,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			total_files,
+			converted_files,
+			convert_time,
+			total_tasks,
+			completed_tasks,
+		}: Metadata,
+	) -> Self {
+		vec![ReportOutputMetadata::Metrics(HashMap::from([
+			("total_files".into(), json!(total_files)),
+			("converted_files".into(), json!(converted_files)),
+			("convert_time".into(), json!(convert_time)),
+			("total_tasks".into(), json!(total_tasks)),
+			("completed_tasks".into(), json!(completed_tasks)),
+		]))]
+	}
+}
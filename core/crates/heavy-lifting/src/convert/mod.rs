@@ -0,0 +1,262 @@
+use crate::file_system;
+
+use sd_file_ext::extensions::{AudioExtension, VideoExtension};
+use sd_prisma::prisma::location;
+
+use std::{
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+use prisma_client_rust::QueryError;
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+pub mod converter;
+mod tasks;
+
+pub use converter::Converter;
+
+/// Maximum number of files that a single convert task will handle, transcoding a single video
+/// can take a long time, so batches are kept small to spread them over every worker
+const BATCH_SIZE: usize = 4;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("location not found: <id='{0}'>")]
+	LocationNotFound(location::id::Type),
+	#[error("none of the selected files can be converted to the requested format")]
+	NothingToConvert,
+
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+	#[error(transparent)]
+	FileSystem(#[from] file_system::Error),
+}
+
+impl From<Error> for rspc::Error {
+	fn from(e: Error) -> Self {
+		match e {
+			Error::LocationNotFound(_) => Self::with_cause(ErrorCode::NotFound, e.to_string(), e),
+			Error::NothingToConvert => Self::with_cause(ErrorCode::BadRequest, e.to_string(), e),
+			Error::FileSystem(e) => e.into(),
+			Error::Database(_) => {
+				Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e)
+			}
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize, Type, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum NonCriticalConvertError {
+	#[error("file can't be converted to the requested format: <path='{}'>", .0.display())]
+	UnsupportedSource(PathBuf),
+	#[error("failed to find an available name for converted file <path='{}'>: {1}", .0.display())]
+	FindAvailableName(PathBuf, String),
+	#[error("failed to convert file <source='{}', target='{}'>: {2}", .0.display(), .1.display())]
+	Convert(PathBuf, PathBuf, String),
+	#[error("failed to link converted file to its source <path='{}'>: {1}", .0.display())]
+	LinkToSource(PathBuf, String),
+}
+
+/// Everything that defines how the selected files will be converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+pub struct ConvertOptions {
+	pub target: ConvertTarget,
+	pub size: SizePreset,
+	pub quality: QualityPreset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ConvertTarget {
+	Image(ImageFormat),
+	Video(VideoFormat),
+	Audio(AudioFormat),
+}
+
+impl ConvertTarget {
+	#[must_use]
+	pub const fn extension(self) -> &'static str {
+		match self {
+			Self::Image(format) => format.extension(),
+			Self::Video(format) => format.extension(),
+			Self::Audio(format) => format.extension(),
+		}
+	}
+
+	/// Checks the source extension, images must be supported by `sd_images` and audio can also be
+	/// extracted from videos, which both require FFmpeg
+	fn accepts(self, source: &Path) -> bool {
+		let Some(extension) = source
+			.extension()
+			.and_then(|ext| ext.to_str())
+			.map(str::to_lowercase)
+		else {
+			return false;
+		};
+
+		match self {
+			Self::Image(_) => sd_images::all_compatible_extensions().contains(&extension),
+			Self::Video(_) => {
+				cfg!(feature = "ffmpeg") && VideoExtension::from_str(&extension).is_ok()
+			}
+			Self::Audio(_) => {
+				cfg!(feature = "ffmpeg")
+					&& (AudioExtension::from_str(&extension).is_ok()
+						|| VideoExtension::from_str(&extension).is_ok())
+			}
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+	Jpeg,
+	Png,
+	Webp,
+	Gif,
+	Bmp,
+	Tiff,
+}
+
+impl ImageFormat {
+	#[must_use]
+	pub const fn extension(self) -> &'static str {
+		match self {
+			Self::Jpeg => "jpg",
+			Self::Png => "png",
+			Self::Webp => "webp",
+			Self::Gif => "gif",
+			Self::Bmp => "bmp",
+			Self::Tiff => "tiff",
+		}
+	}
+}
+
+/// Video containers, the codecs used are FFmpeg's defaults for each container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoFormat {
+	Mp4,
+	Webm,
+	Mkv,
+}
+
+impl VideoFormat {
+	#[must_use]
+	pub const fn extension(self) -> &'static str {
+		match self {
+			Self::Mp4 => "mp4",
+			Self::Webm => "webm",
+			Self::Mkv => "mkv",
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+	Mp3,
+	M4a,
+	Ogg,
+	Opus,
+	Flac,
+	Wav,
+}
+
+impl AudioFormat {
+	#[must_use]
+	pub const fn extension(self) -> &'static str {
+		match self {
+			Self::Mp3 => "mp3",
+			Self::M4a => "m4a",
+			Self::Ogg => "ogg",
+			Self::Opus => "opus",
+			Self::Flac => "flac",
+			Self::Wav => "wav",
+		}
+	}
+}
+
+/// Upper bound for the output dimensions, files are only ever scaled down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SizePreset {
+	Original,
+	Uhd,
+	FullHd,
+	Hd,
+	Sd,
+}
+
+impl SizePreset {
+	/// Maximum length of the longest side of an image
+	const fn max_image_side(self) -> Option<u32> {
+		match self {
+			Self::Original => None,
+			Self::Uhd => Some(3840),
+			Self::FullHd => Some(1920),
+			Self::Hd => Some(1280),
+			Self::Sd => Some(854),
+		}
+	}
+
+	/// Maximum height of a video
+	#[cfg_attr(not(feature = "ffmpeg"), allow(dead_code))]
+	const fn max_video_height(self) -> Option<u32> {
+		match self {
+			Self::Original => None,
+			Self::Uhd => Some(2160),
+			Self::FullHd => Some(1080),
+			Self::Hd => Some(720),
+			Self::Sd => Some(480),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityPreset {
+	Low,
+	Medium,
+	High,
+	Maximum,
+}
+
+impl QualityPreset {
+	/// Quality used by lossy image encoders, in the [0, 100] range
+	const fn image_quality(self) -> u8 {
+		match self {
+			Self::Low => 60,
+			Self::Medium => 75,
+			Self::High => 90,
+			Self::Maximum => 100,
+		}
+	}
+
+	/// Video bit rate in bits per second
+	#[cfg_attr(not(feature = "ffmpeg"), allow(dead_code))]
+	const fn video_bit_rate(self) -> i64 {
+		match self {
+			Self::Low => 1_000_000,
+			Self::Medium => 2_500_000,
+			Self::High => 5_000_000,
+			Self::Maximum => 10_000_000,
+		}
+	}
+
+	/// Audio bit rate in bits per second
+	#[cfg_attr(not(feature = "ffmpeg"), allow(dead_code))]
+	const fn audio_bit_rate(self) -> i64 {
+		match self {
+			Self::Low => 96_000,
+			Self::Medium => 128_000,
+			Self::High => 192_000,
+			Self::Maximum => 320_000,
+		}
+	}
+}
@@ -0,0 +1,400 @@
+use crate::{
+	convert::{ConvertOptions, ConvertTarget, ImageFormat, NonCriticalConvertError, QualityPreset},
+	file_system::find_available_filename_for_duplicate,
+	Error, NonCriticalError,
+};
+
+use sd_images::{format_image, ConvertibleExtension};
+use sd_media_metadata::exif::Orientation;
+use sd_prisma::prisma::object;
+use sd_task_system::{ExecStatus, Interrupter, InterruptionKind, IntoAnyTaskOutput, Task, TaskId};
+
+use std::{
+	collections::VecDeque,
+	future::IntoFuture,
+	io::Cursor,
+	mem,
+	ops::Deref,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use futures::FutureExt;
+use futures_concurrency::future::Race;
+use image::{codecs::jpeg::JpegEncoder, imageops, ColorType, DynamicImage, ImageOutputFormat};
+use tokio::{
+	fs::{self, OpenOptions},
+	io::AsyncWriteExt,
+	task::spawn_blocking,
+	time::Instant,
+};
+use tracing::{instrument, trace, Level};
+use webp::Encoder;
+
+/// A file to be converted
+#[derive(Debug)]
+pub struct ConvertEntry {
+	pub source: PathBuf,
+	/// Object of the source file, if it was already identified, to link the output to it
+	pub source_object_id: Option<object::id::Type>,
+}
+
+/// A file successfully converted
+#[derive(Debug)]
+pub struct Converted {
+	pub source_object_id: Option<object::id::Type>,
+	pub target: PathBuf,
+}
+
+/// Converts files to another format, writing the results in the target directory
+#[derive(Debug)]
+pub struct FileConverter {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	entries: VecDeque<ConvertEntry>,
+	target_directory: PathBuf,
+	options: ConvertOptions,
+
+	// Out collector
+	output: Output,
+}
+
+/// [`FileConverter`] task output
+#[derive(Debug, Default)]
+pub struct Output {
+	/// Every file successfully converted by this task
+	pub converted: Vec<Converted>,
+	/// Time spent converting files
+	pub convert_time: Duration,
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for FileConverter {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	fn with_priority(&self) -> bool {
+		// Conversions are user driven, so they must complete as soon as possible
+		true
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(
+			task_id = %self.id,
+			entries_count = %self.entries.len(),
+			target_directory = %self.target_directory.display(),
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		enum InterruptRace {
+			Interrupted(InterruptionKind),
+			Converted(Result<(), NonCriticalConvertError>),
+		}
+
+		let Self {
+			entries,
+			target_directory,
+			options,
+			output: Output {
+				converted,
+				convert_time,
+				errors,
+			},
+			..
+		} = self;
+
+		let start_time = Instant::now();
+
+		while let Some(entry) = entries.pop_front() {
+			let target = match available_target(
+				target_directory.join(target_file_name(&entry.source, options.target)),
+			)
+			.await
+			{
+				Ok(target) => target,
+				Err(e) => {
+					errors.push(e.into());
+					continue;
+				}
+			};
+
+			// Converting a single file can take minutes, so we race it against the interrupter
+			// instead of only checking for interruptions between files
+			let abort = Arc::new(AtomicBool::new(false));
+
+			match (
+				convert_file(&entry.source, &target, *options, Arc::clone(&abort))
+					.map(InterruptRace::Converted),
+				interrupter.into_future().map(InterruptRace::Interrupted),
+			)
+				.race()
+				.await
+			{
+				InterruptRace::Converted(Ok(())) => {
+					trace!(
+						source = %entry.source.display(),
+						target = %target.display(),
+						"File converted;",
+					);
+					converted.push(Converted {
+						source_object_id: entry.source_object_id,
+						target,
+					});
+				}
+
+				InterruptRace::Converted(Err(e)) => {
+					remove_partial_file(&target).await;
+					errors.push(e.into());
+				}
+
+				InterruptRace::Interrupted(kind) => {
+					abort.store(true, Ordering::Relaxed);
+					remove_partial_file(&target).await;
+
+					// The file will be converted again from scratch if the task is resumed
+					entries.push_front(entry);
+
+					*convert_time += start_time.elapsed();
+
+					return Ok(match kind {
+						InterruptionKind::Pause => ExecStatus::Paused,
+						InterruptionKind::Cancel => ExecStatus::Canceled,
+					});
+				}
+			}
+		}
+
+		*convert_time += start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl FileConverter {
+	#[must_use]
+	pub(in crate::convert) fn new(
+		entries: impl IntoIterator<Item = ConvertEntry>,
+		target_directory: PathBuf,
+		options: ConvertOptions,
+	) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			entries: entries.into_iter().collect(),
+			target_directory,
+			options,
+			output: Output::default(),
+		}
+	}
+}
+
+/// The converted file keeps the source name, with the extension of the target format
+fn target_file_name(source: &Path, target: ConvertTarget) -> String {
+	format!(
+		"{}.{}",
+		source
+			.file_stem()
+			.map(|stem| stem.to_string_lossy())
+			.unwrap_or_default(),
+		target.extension()
+	)
+}
+
+/// Find a path for the resulting file that doesn't overwrite anything
+async fn available_target(target: PathBuf) -> Result<PathBuf, NonCriticalConvertError> {
+	match fs::try_exists(&target).await {
+		Ok(false) => Ok(target),
+		Ok(true) => find_available_filename_for_duplicate(&target)
+			.await
+			.map_err(|e| NonCriticalConvertError::FindAvailableName(target, e.to_string())),
+		Err(e) => Err(NonCriticalConvertError::FindAvailableName(
+			target,
+			e.to_string(),
+		)),
+	}
+}
+
+async fn convert_file(
+	source: &Path,
+	target: &Path,
+	options: ConvertOptions,
+	abort: Arc<AtomicBool>,
+) -> Result<(), NonCriticalConvertError> {
+	match options.target {
+		ConvertTarget::Image(format) => convert_image(source, target, format, options).await,
+		ConvertTarget::Video(_) | ConvertTarget::Audio(_) => {
+			transcode(source, target, options, abort).await
+		}
+	}
+	.map_err(|e| NonCriticalConvertError::Convert(source.to_path_buf(), target.to_path_buf(), e))
+}
+
+async fn convert_image(
+	source: &Path,
+	target: &Path,
+	format: ImageFormat,
+	ConvertOptions { size, quality, .. }: ConvertOptions,
+) -> Result<(), String> {
+	let bytes = spawn_blocking({
+		let source = source.to_path_buf();
+		move || {
+			let mut image = format_image(&source).map_err(|e| e.to_string())?;
+
+			// The EXIF orientation is lost on conversion, so we apply it to the pixels instead,
+			// except for HEIF images, as they are already rotated on decoding
+			if let Some(orientation) = Orientation::from_path(&source) {
+				if ConvertibleExtension::try_from(source.as_path())
+					.is_ok_and(ConvertibleExtension::should_rotate)
+				{
+					image = orientation.correct_thumbnail(image);
+				}
+			}
+
+			if let Some(max_side) = size.max_image_side() {
+				if image.width() > max_side || image.height() > max_side {
+					image = image.resize(max_side, max_side, imageops::FilterType::Lanczos3);
+				}
+			}
+
+			encode_image(&image, format, quality)
+		}
+	})
+	.await
+	.map_err(|e| e.to_string())??;
+
+	// `create_new` as another file could have been created with the same name in the meantime
+	let mut file = OpenOptions::new()
+		.write(true)
+		.create_new(true)
+		.open(target)
+		.await
+		.map_err(|e| e.to_string())?;
+
+	file.write_all(&bytes).await.map_err(|e| e.to_string())?;
+	file.flush().await.map_err(|e| e.to_string())
+}
+
+fn encode_image(
+	image: &DynamicImage,
+	format: ImageFormat,
+	quality: QualityPreset,
+) -> Result<Vec<u8>, String> {
+	let mut bytes = Cursor::new(vec![]);
+
+	match format {
+		ImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut bytes, quality.image_quality())
+			.encode_image(&image.to_rgb8()),
+
+		ImageFormat::Webp => {
+			// The WebP encoder only accepts 8 bits RGB or RGBA images
+			let image = if image.color().has_alpha() {
+				DynamicImage::ImageRgba8(image.to_rgba8())
+			} else {
+				DynamicImage::ImageRgb8(image.to_rgb8())
+			};
+
+			let encoder = Encoder::from_image(&image).map_err(ToString::to_string)?;
+
+			// Type `WebPMemory` is !Send, so we copy it to a `Vec<u8>` right away
+			return Ok(if quality == QualityPreset::Maximum {
+				encoder.encode_lossless().deref().to_owned()
+			} else {
+				encoder
+					.encode(f32::from(quality.image_quality()))
+					.deref()
+					.to_owned()
+			});
+		}
+
+		ImageFormat::Bmp => {
+			// The BMP encoder only accepts 8 bits images
+			let image = if image.color().has_alpha() {
+				DynamicImage::ImageRgba8(image.to_rgba8())
+			} else {
+				DynamicImage::ImageRgb8(image.to_rgb8())
+			};
+
+			image.write_to(&mut bytes, ImageOutputFormat::Bmp)
+		}
+
+		ImageFormat::Png | ImageFormat::Tiff => {
+			// Lossless formats keep 16 bits images, but don't support floating point ones
+			let converted;
+			let image = if matches!(image.color(), ColorType::Rgb32F | ColorType::Rgba32F) {
+				converted = DynamicImage::ImageRgba16(image.to_rgba16());
+				&converted
+			} else {
+				image
+			};
+
+			image.write_to(
+				&mut bytes,
+				if format == ImageFormat::Png {
+					ImageOutputFormat::Png
+				} else {
+					ImageOutputFormat::Tiff
+				},
+			)
+		}
+
+		ImageFormat::Gif => image.write_to(&mut bytes, ImageOutputFormat::Gif),
+	}
+	.map_err(|e| e.to_string())?;
+
+	Ok(bytes.into_inner())
+}
+
+#[cfg(feature = "ffmpeg")]
+async fn transcode(
+	source: &Path,
+	target: &Path,
+	ConvertOptions {
+		target: convert_target,
+		size,
+		quality,
+	}: ConvertOptions,
+	abort: Arc<AtomicBool>,
+) -> Result<(), String> {
+	sd_ffmpeg::transcode(
+		source,
+		target,
+		sd_ffmpeg::TranscodeOptions {
+			max_height: size.max_video_height(),
+			video_bit_rate: Some(quality.video_bit_rate()),
+			audio_bit_rate: Some(quality.audio_bit_rate()),
+			audio_only: matches!(convert_target, ConvertTarget::Audio(_)),
+		},
+		abort,
+	)
+	.await
+	.map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "ffmpeg"))]
+#[allow(clippy::unused_async)]
+async fn transcode(
+	_source: &Path,
+	_target: &Path,
+	_options: ConvertOptions,
+	_abort: Arc<AtomicBool>,
+) -> Result<(), String> {
+	Err("audio and video conversion requires FFmpeg support".to_string())
+}
+
+async fn remove_partial_file(path: &Path) {
+	if let Err(e) = fs::remove_file(path).await {
+		trace!(path = %path.display(), ?e, "Failed to remove partially converted file;");
+	}
+}
@@ -0,0 +1,3 @@
+pub mod file_converter;
+
+pub use file_converter::FileConverter;
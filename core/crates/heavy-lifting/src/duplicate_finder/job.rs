@@ -0,0 +1,756 @@
+use crate::{
+	duplicate_finder,
+	file_validator::tasks::{checksummer, Checksummer},
+	job_system::{
+		job::{Job, JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		utils::cancel_pending_tasks,
+		DispatcherError, JobErrorOrDispatcherError, SerializableJob, SerializedTasks,
+	},
+	utils::sub_path::maybe_get_iso_file_path_from_sub_path,
+	Error, JobContext, JobName, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_core_file_path_helper::IsolatedFilePathData;
+use sd_core_prisma_helpers::{file_path_for_duplicate_finder, file_path_for_object_validator};
+
+use sd_prisma::prisma::{file_path, location, SortOrder};
+use sd_task_system::{
+	AnyTaskOutput, IntoTask, SerializableTask, Task, TaskDispatcher, TaskHandle, TaskId,
+	TaskOutput, TaskStatus,
+};
+use sd_utils::{db::maybe_missing, u64_to_frontend};
+
+use std::{
+	collections::{HashMap, HashSet},
+	hash::{Hash, Hasher},
+	mem,
+	path::PathBuf,
+	sync::Arc,
+	time::Duration,
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use futures_concurrency::future::TryJoin;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::time::Instant;
+use tracing::{debug, instrument, trace, warn, Level};
+
+use super::{filter_shared_cas_ids, verify_objects, VerifiedObjects, CHUNK_SIZE};
+
+/// The duplicate finder confirms which files of a location (or of a sub path of it) are really duplicates.
+///
+/// Files sharing their sampled `cas_id` with another file of the library get a full content checksum, along with
+/// every file sharing that `cas_id` in any location of the same instance, so duplicates spread across locations are
+/// confirmed too. The checksums are then used to split objects that wrongly grouped different files together and to
+/// report confirmed duplicate sets.
+#[derive(Debug)]
+pub struct DuplicateFinder {
+	// Received arguments
+	location: Arc<location::Data>,
+	location_path: Arc<PathBuf>,
+	sub_path: Option<PathBuf>,
+
+	// Inner state
+	last_file_path_id: Option<file_path::id::Type>,
+	finished_searching: bool,
+
+	// Run data
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	// On shutdown data
+	pending_tasks_on_resume: Vec<TaskHandle<Error>>,
+	tasks_for_shutdown: Vec<Box<dyn Task<Error>>>,
+}
+
+impl Hash for DuplicateFinder {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location.id.hash(state);
+		if let Some(ref sub_path) = self.sub_path {
+			sub_path.hash(state);
+		}
+	}
+}
+
+impl Job for DuplicateFinder {
+	const NAME: JobName = JobName::DuplicateFinder;
+
+	async fn resume_tasks<OuterCtx: OuterContext>(
+		&mut self,
+		dispatcher: &JobTaskDispatcher,
+		ctx: &impl JobContext<OuterCtx>,
+		SerializedTasks(serialized_tasks): SerializedTasks,
+	) -> Result<(), Error> {
+		if let Ok(tasks) = dispatcher
+			.dispatch_many_boxed(
+				rmp_serde::from_slice::<Vec<Vec<u8>>>(&serialized_tasks)
+					.map_err(duplicate_finder::Error::from)?
+					.into_iter()
+					.map(|task_bytes| async move {
+						Checksummer::deserialize(
+							&task_bytes,
+							(Arc::clone(ctx.db()), Arc::clone(ctx.sync())),
+						)
+						.await
+						.map(IntoTask::into_task)
+					})
+					.collect::<Vec<_>>()
+					.try_join()
+					.await
+					.map_err(duplicate_finder::Error::from)?,
+			)
+			.await
+		{
+			self.pending_tasks_on_resume = tasks;
+		} else {
+			warn!("Failed to dispatch tasks to resume as job was already canceled");
+		}
+
+		Ok(())
+	}
+
+	#[instrument(
+		skip_all,
+		fields(
+			location_id = self.location.id,
+			location_path = %self.location_path.display(),
+			sub_path = ?self.sub_path.as_ref().map(|path| path.display()),
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let mut pending_running_tasks = FuturesUnordered::new();
+
+		match self
+			.init_or_resume(&mut pending_running_tasks, &ctx, &dispatcher)
+			.await
+		{
+			Ok(()) => { /* Everything is awesome! */ }
+			Err(JobErrorOrDispatcherError::JobError(e)) => {
+				return Err(e.into());
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::JobCanceled(_))) => {
+				return Ok(self.cancel_job(&mut pending_running_tasks).await);
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::Shutdown(tasks))) => {
+				self.tasks_for_shutdown.extend(tasks);
+
+				if pending_running_tasks.is_empty() {
+					// If no task managed to be dispatched, we can just shutdown
+					// otherwise we have to process handles below and wait for them to be shutdown too
+					return Ok(ReturnStatus::Shutdown(
+						SerializableJob::<OuterCtx>::serialize(self).await,
+					));
+				}
+			}
+		}
+
+		while let Some(task) = pending_running_tasks.next().await {
+			match task {
+				Ok(TaskStatus::Done((task_id, TaskOutput::Out(out)))) => {
+					self.process_task_output(task_id, out, &ctx).await;
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					warn!(%task_id, "Task returned an empty output");
+				}
+
+				Ok(TaskStatus::Shutdown(task)) => {
+					self.tasks_for_shutdown.push(task);
+				}
+
+				Ok(TaskStatus::Error(e)) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&mut pending_running_tasks).await);
+				}
+
+				Err(e) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e.into());
+				}
+			}
+		}
+
+		if !self.tasks_for_shutdown.is_empty() {
+			return Ok(ReturnStatus::Shutdown(
+				SerializableJob::<OuterCtx>::serialize(self).await,
+			));
+		}
+
+		// From this point onward, we are done with the job and it can't be interrupted anymore
+		self.verify(&ctx).await?;
+
+		let Self {
+			metadata, errors, ..
+		} = self;
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+}
+
+impl DuplicateFinder {
+	pub fn new(
+		location: location::Data,
+		sub_path: Option<PathBuf>,
+	) -> Result<Self, duplicate_finder::Error> {
+		Ok(Self {
+			location_path: maybe_missing(&location.path, "location.path")
+				.map(PathBuf::from)
+				.map(Arc::new)?,
+			location: Arc::new(location),
+			sub_path,
+			last_file_path_id: None,
+			finished_searching: false,
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+			pending_tasks_on_resume: Vec::new(),
+			tasks_for_shutdown: Vec::new(),
+		})
+	}
+
+	async fn init_or_resume<OuterCtx: OuterContext>(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+	) -> Result<(), JobErrorOrDispatcherError<duplicate_finder::Error>> {
+		pending_running_tasks.extend(mem::take(&mut self.pending_tasks_on_resume));
+
+		if self.finished_searching {
+			ctx.progress(vec![
+				ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+				ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			])
+			.await;
+
+			debug!(
+				resuming_tasks_count = pending_running_tasks.len(),
+				"Resuming tasks for DuplicateFinder job;",
+			);
+
+			return Ok(());
+		}
+
+		let start = Instant::now();
+
+		let maybe_sub_iso_file_path =
+			maybe_get_iso_file_path_from_sub_path::<duplicate_finder::Error>(
+				self.location.id,
+				self.sub_path.as_ref(),
+				&*self.location_path,
+				ctx.db(),
+			)
+			.await?;
+
+		self.dispatch_checksummer_tasks(
+			maybe_sub_iso_file_path.as_ref(),
+			ctx,
+			dispatcher,
+			pending_running_tasks,
+		)
+		.await?;
+
+		self.finished_searching = true;
+		self.metadata.seeking_files_time += start.elapsed();
+
+		ctx.progress(vec![
+			ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+			ProgressUpdate::Message(format!(
+				"{} possible duplicates to be checked",
+				self.metadata.total_found_files
+			)),
+		])
+		.await;
+
+		Ok(())
+	}
+
+	/// Dispatch checksummer tasks for the files sharing their `cas_id` with the files of the location (or sub path)
+	/// that still don't have a full content checksum, one task for each location they are in
+	async fn dispatch_checksummer_tasks<OuterCtx: OuterContext>(
+		&mut self,
+		maybe_sub_iso_file_path: Option<&IsolatedFilePathData<'static>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+		pending_running_tasks: &FuturesUnordered<TaskHandle<Error>>,
+	) -> Result<(), JobErrorOrDispatcherError<duplicate_finder::Error>> {
+		let db = ctx.db();
+
+		let mut locations_paths =
+			HashMap::from([(self.location.id, Arc::clone(&self.location_path))]);
+		// Files of other locations can share their `cas_id` with files of many chunks
+		let mut dispatched_file_path_ids = HashSet::new();
+
+		loop {
+			#[allow(clippy::cast_possible_wrap)]
+			// SAFETY: we know that CHUNK_SIZE is a valid i64
+			let file_paths = db
+				.file_path()
+				.find_many(sd_utils::chain_optional_iter(
+					[
+						file_path::location_id::equals(Some(self.location.id)),
+						file_path::is_dir::equals(Some(false)),
+						file_path::cas_id::not(None),
+					],
+					[
+						self.last_file_path_id.map(file_path::id::gt),
+						maybe_sub_iso_file_path.and_then(|iso_sub_path| {
+							iso_sub_path
+								.materialized_path_for_children()
+								.map(file_path::materialized_path::starts_with)
+						}),
+					],
+				))
+				.order_by(file_path::id::order(SortOrder::Asc))
+				.take(CHUNK_SIZE as i64)
+				.select(file_path::select!({ id cas_id }))
+				.exec()
+				.await
+				.map_err(duplicate_finder::Error::from)?;
+
+			let Some(last_file_path) = file_paths.last() else {
+				// No other files to check, we can break the loop
+				break;
+			};
+
+			self.last_file_path_id = Some(last_file_path.id);
+
+			let shared = filter_shared_cas_ids(
+				&file_paths
+					.iter()
+					.map(|file_path| file_path.id)
+					.collect::<Vec<_>>(),
+				db,
+			)
+			.await?;
+
+			let shared_cas_ids = file_paths
+				.into_iter()
+				.filter(|file_path| shared.contains(&file_path.id))
+				.filter_map(|file_path| file_path.cas_id)
+				.collect::<HashSet<_>>();
+
+			if shared_cas_ids.is_empty() {
+				continue;
+			}
+
+			// Remote locations can't be checksummed here, so we only look in the locations of this instance
+			let file_paths_by_location = db
+				.file_path()
+				.find_many(vec![
+					file_path::cas_id::in_vec(shared_cas_ids.into_iter().collect()),
+					file_path::is_dir::equals(Some(false)),
+					file_path::integrity_checksum::equals(None),
+					file_path::location::is(vec![location::instance_id::equals(
+						self.location.instance_id,
+					)]),
+				])
+				.select(file_path_for_object_validator::select())
+				.exec()
+				.await
+				.map_err(duplicate_finder::Error::from)?
+				.into_iter()
+				.filter(|file_path| dispatched_file_path_ids.insert(file_path.id))
+				.filter_map(|file_path| file_path.location_id.map(|id| (id, file_path)))
+				.fold(
+					HashMap::<_, Vec<_>>::new(),
+					|mut file_paths_by_location, (location_id, file_path)| {
+						file_paths_by_location
+							.entry(location_id)
+							.or_default()
+							.push(file_path);
+						file_paths_by_location
+					},
+				);
+
+			for (location_id, file_paths) in file_paths_by_location {
+				let location_path = if let Some(location_path) = locations_paths.get(&location_id) {
+					Arc::clone(location_path)
+				} else {
+					let Some(location_path) = db
+						.location()
+						.find_unique(location::id::equals(location_id))
+						.select(location::select!({ path }))
+						.exec()
+						.await
+						.map_err(duplicate_finder::Error::from)?
+						.and_then(|location| location.path)
+						.map(PathBuf::from)
+						.map(Arc::new)
+					else {
+						warn!(%location_id, "Skipping possible duplicates of a location without path;");
+						continue;
+					};
+
+					locations_paths.insert(location_id, Arc::clone(&location_path));
+					location_path
+				};
+
+				trace!(
+					%location_id,
+					files_count = file_paths.len(),
+					"Found possible duplicates to check;"
+				);
+
+				self.metadata.total_found_files += file_paths.len() as u64;
+				self.metadata.total_tasks += 1;
+
+				ctx.progress(vec![
+					ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+					ProgressUpdate::Message(format!(
+						"Found {} possible duplicates to check",
+						self.metadata.total_found_files
+					)),
+				])
+				.await;
+
+				pending_running_tasks.push(
+					dispatcher
+						.dispatch(Checksummer::new(
+							location_id,
+							location_path,
+							file_paths,
+							Arc::clone(db),
+							Arc::clone(ctx.sync()),
+						))
+						.await?,
+				);
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		task_id: TaskId,
+		any_task_output: Box<dyn AnyTaskOutput>,
+		ctx: &impl JobContext<OuterCtx>,
+	) {
+		let checksummer::Output {
+			validated_count,
+			checksum_time,
+			save_db_time,
+			errors,
+		} = *any_task_output
+			.downcast::<checksummer::Output>()
+			.expect("DuplicateFinder job only dispatches Checksummer tasks");
+
+		self.metadata.checksummed_files += validated_count;
+		self.metadata.mean_checksum_time += checksum_time;
+		self.metadata.mean_save_db_time += save_db_time;
+		self.metadata.completed_tasks += 1;
+
+		if !errors.is_empty() {
+			warn!(?errors, "Non critical errors while checksumming files;");
+			self.errors.extend(errors);
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			ProgressUpdate::Message(format!(
+				"Checked {} of {} possible duplicates",
+				self.metadata.checksummed_files, self.metadata.total_found_files
+			)),
+		])
+		.await;
+
+		debug!(
+			%task_id,
+			"Processed ({}/{}) checksummer tasks, took: {:?};",
+			self.metadata.completed_tasks,
+			self.metadata.total_tasks,
+			checksum_time + save_db_time,
+		);
+	}
+
+	/// Verify the objects of every checksummed file in the location (or sub path), splitting the ones wrongly
+	/// grouping different files and collecting the confirmed duplicate sets
+	async fn verify<OuterCtx: OuterContext>(
+		&mut self,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> Result<(), duplicate_finder::Error> {
+		let start = Instant::now();
+		let db = ctx.db();
+
+		ctx.progress_msg("Verifying duplicates").await;
+
+		let maybe_sub_iso_file_path =
+			maybe_get_iso_file_path_from_sub_path::<duplicate_finder::Error>(
+				self.location.id,
+				self.sub_path.as_ref(),
+				&*self.location_path,
+				db,
+			)
+			.await?;
+
+		let mut last_file_path_id = None;
+		let mut verified_object_ids = HashSet::new();
+
+		loop {
+			#[allow(clippy::cast_possible_wrap)]
+			// SAFETY: we know that CHUNK_SIZE is a valid i64
+			let file_paths = db
+				.file_path()
+				.find_many(sd_utils::chain_optional_iter(
+					[
+						file_path::location_id::equals(Some(self.location.id)),
+						file_path::is_dir::equals(Some(false)),
+						file_path::integrity_checksum::not(None),
+						file_path::object_id::not(None),
+					],
+					[
+						last_file_path_id.map(file_path::id::gt),
+						maybe_sub_iso_file_path.as_ref().and_then(|iso_sub_path| {
+							iso_sub_path
+								.materialized_path_for_children()
+								.map(file_path::materialized_path::starts_with)
+						}),
+					],
+				))
+				.order_by(file_path::id::order(SortOrder::Asc))
+				.take(CHUNK_SIZE as i64)
+				.select(file_path::select!({ id object_id }))
+				.exec()
+				.await?;
+
+			let Some(last_file_path) = file_paths.last() else {
+				break;
+			};
+			last_file_path_id = Some(last_file_path.id);
+
+			// Objects can have files in other locations, so we verify each object with all of its files, only once
+			let object_ids = file_paths
+				.into_iter()
+				.filter_map(|file_path| file_path.object_id)
+				.filter(|object_id| verified_object_ids.insert(*object_id))
+				.collect::<Vec<_>>();
+
+			if object_ids.is_empty() {
+				continue;
+			}
+
+			let VerifiedObjects {
+				duplicate_sets_count,
+				wasted_bytes,
+				split_objects_count,
+			} = verify_objects(
+				db.file_path()
+					.find_many(vec![
+						file_path::object_id::in_vec(object_ids),
+						file_path::is_dir::equals(Some(false)),
+					])
+					.select(file_path_for_duplicate_finder::select())
+					.exec()
+					.await?,
+				db,
+				ctx.sync(),
+			)
+			.await?;
+
+			self.metadata.split_objects += split_objects_count;
+			self.metadata.duplicate_sets += duplicate_sets_count;
+			self.metadata.wasted_bytes += wasted_bytes;
+		}
+
+		self.metadata.verify_time = start.elapsed();
+
+		if self.metadata.split_objects > 0 {
+			ctx.invalidate_query("search.objects");
+			ctx.invalidate_query("search.paths");
+		}
+
+		debug!(
+			duplicate_sets = self.metadata.duplicate_sets,
+			split_objects = self.metadata.split_objects,
+			"Verified duplicates;",
+		);
+
+		Ok(())
+	}
+
+	async fn cancel_job(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+	) -> ReturnStatus {
+		cancel_pending_tasks(pending_running_tasks).await;
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	location: Arc<location::Data>,
+	location_path: Arc<PathBuf>,
+	sub_path: Option<PathBuf>,
+
+	last_file_path_id: Option<file_path::id::Type>,
+	finished_searching: bool,
+
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	tasks_for_shutdown_bytes: Option<SerializedTasks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	mean_checksum_time: Duration,
+	mean_save_db_time: Duration,
+	seeking_files_time: Duration,
+	verify_time: Duration,
+	total_found_files: u64,
+	checksummed_files: u64,
+	duplicate_sets: u64,
+	wasted_bytes: u64,
+	split_objects: u64,
+	total_tasks: u32,
+	completed_tasks: u32,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			mut mean_checksum_time,
+			mut mean_save_db_time,
+			seeking_files_time,
+			verify_time,
+			total_found_files,
+			checksummed_files,
+			duplicate_sets,
+			wasted_bytes,
+			split_objects,
+			total_tasks,
+			completed_tasks,
+		}: Metadata,
+	) -> Self {
+		// To avoid division by zero
+		mean_checksum_time /= u32::max(completed_tasks, 1);
+		mean_save_db_time /= u32::max(completed_tasks, 1);
+
+		vec![
+			ReportOutputMetadata::DuplicateFinder {
+				duplicate_sets: u64_to_frontend(duplicate_sets),
+				wasted_bytes: u64_to_frontend(wasted_bytes),
+				split_objects: u64_to_frontend(split_objects),
+			},
+			ReportOutputMetadata::Metrics(HashMap::from([
+				("mean_checksum_time".into(), json!(mean_checksum_time)),
+				("mean_save_db_time".into(), json!(mean_save_db_time)),
+				("seeking_files_time".into(), json!(seeking_files_time)),
+				("verify_time".into(), json!(verify_time)),
+				(
+					"total_found_files".into(),
+					json!(u64_to_frontend(total_found_files)),
+				),
+				(
+					"checksummed_files".into(),
+					json!(u64_to_frontend(checksummed_files)),
+				),
+				("total_tasks".into(), json!(total_tasks)),
+				("completed_tasks".into(), json!(completed_tasks)),
+			])),
+		]
+	}
+}
+
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for DuplicateFinder {
+	async fn serialize(self) -> Result<Option<Vec<u8>>, rmp_serde::encode::Error> {
+		let Self {
+			location,
+			location_path,
+			sub_path,
+			last_file_path_id,
+			finished_searching,
+			metadata,
+			errors,
+			tasks_for_shutdown,
+			..
+		} = self;
+
+		let serialized_tasks = tasks_for_shutdown
+			.into_iter()
+			.map(|task| async move {
+				task.downcast::<Checksummer>()
+					.expect("DuplicateFinder job only dispatches Checksummer tasks")
+					.serialize()
+					.await
+			})
+			.collect::<Vec<_>>()
+			.try_join()
+			.await?;
+
+		let tasks_for_shutdown_bytes = if serialized_tasks.is_empty() {
+			None
+		} else {
+			Some(SerializedTasks(rmp_serde::to_vec_named(&serialized_tasks)?))
+		};
+
+		rmp_serde::to_vec_named(&SaveState {
+			location,
+			location_path,
+			sub_path,
+			last_file_path_id,
+			finished_searching,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		})
+		.map(Some)
+	}
+
+	async fn deserialize(
+		serialized_job: &[u8],
+		_: &OuterCtx,
+	) -> Result<Option<(Self, Option<SerializedTasks>)>, rmp_serde::decode::Error> {
+		let SaveState {
+			location,
+			location_path,
+			sub_path,
+			last_file_path_id,
+			finished_searching,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		} = rmp_serde::from_slice::<SaveState>(serialized_job)?;
+
+		Ok(Some((
+			Self {
+				location,
+				location_path,
+				sub_path,
+				last_file_path_id,
+				finished_searching,
+				metadata,
+				errors,
+				pending_tasks_on_resume: Vec::new(),
+				tasks_for_shutdown: Vec::new(),
+			},
+			tasks_for_shutdown_bytes,
+		)))
+	}
+}
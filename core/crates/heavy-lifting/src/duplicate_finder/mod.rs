@@ -0,0 +1,346 @@
+use crate::{file_identifier::tasks::connect_file_path_to_object, utils::sub_path};
+
+use sd_core_file_path_helper::FilePathError;
+use sd_core_prisma_helpers::{file_path_for_duplicate_finder, FilePathPubId, ObjectPubId};
+use sd_core_sync::Manager as SyncManager;
+
+use sd_prisma::{
+	prisma::{file_path, object, PrismaClient},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::{db::MissingFieldError, msgpack, u64_to_frontend};
+
+use std::{
+	cmp::Reverse,
+	collections::{HashMap, HashSet},
+};
+
+use prisma_client_rust::{raw, PrismaValue, QueryError};
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tracing::{instrument, trace};
+
+pub mod job;
+
+pub use job::DuplicateFinder;
+
+// we break these tasks into chunks of 100 to improve performance
+const CHUNK_SIZE: usize = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("missing field on database: {0}")]
+	MissingField(#[from] MissingFieldError),
+	#[error("failed to deserialized stored tasks for job resume: {0}")]
+	DeserializeTasks(#[from] rmp_serde::decode::Error),
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+
+	#[error(transparent)]
+	FilePathError(#[from] FilePathError),
+	#[error(transparent)]
+	SubPath(#[from] sub_path::Error),
+	#[error(transparent)]
+	Sync(#[from] sd_core_sync::Error),
+}
+
+impl From<Error> for rspc::Error {
+	fn from(e: Error) -> Self {
+		match e {
+			Error::SubPath(sub_path_err) => sub_path_err.into(),
+
+			_ => Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e),
+		}
+	}
+}
+
+/// A set of files confirmed to have exactly the same content by their full content checksum
+#[derive(Debug, Serialize, Type)]
+pub struct DuplicateSet {
+	pub integrity_checksum: String,
+	pub file_path_ids: Vec<file_path::id::Type>,
+	/// Size of each one of the files in the set
+	pub size_in_bytes: (u32, u32),
+	/// Bytes that would be freed by keeping only one of the files in the set
+	pub wasted_bytes: (u32, u32),
+}
+
+impl DuplicateSet {
+	fn new(
+		integrity_checksum: String,
+		file_paths: &[file_path_for_duplicate_finder::Data],
+	) -> Self {
+		let size_in_bytes = size_in_bytes(file_paths);
+
+		Self {
+			integrity_checksum,
+			file_path_ids: file_paths.iter().map(|file_path| file_path.id).collect(),
+			size_in_bytes: u64_to_frontend(size_in_bytes),
+			wasted_bytes: u64_to_frontend(wasted_bytes(size_in_bytes, file_paths.len())),
+		}
+	}
+}
+
+/// Files with the same content have the same size, so we just take the first one we know
+fn size_in_bytes(file_paths: &[file_path_for_duplicate_finder::Data]) -> u64 {
+	file_paths
+		.iter()
+		.find_map(|file_path| {
+			file_path
+				.size_in_bytes_bytes
+				.as_deref()
+				.and_then(|bytes| bytes.try_into().ok())
+				.map(u64::from_be_bytes)
+		})
+		.unwrap_or(0)
+}
+
+const fn wasted_bytes(size_in_bytes: u64, files_count: usize) -> u64 {
+	size_in_bytes * (files_count as u64).saturating_sub(1)
+}
+
+#[derive(Deserialize)]
+struct RawFilePathId {
+	id: file_path::id::Type,
+}
+
+#[derive(Deserialize)]
+struct RawDuplicateChecksum {
+	integrity_checksum: String,
+}
+
+/// Fetch up to `take` confirmed duplicate sets of the library, ordered by their checksum.
+///
+/// Only files that already had their full content checksum generated are considered, which is done by the
+/// [`DuplicateFinder`] job. The checksum of the last received set can be used as `cursor` to fetch the next ones.
+pub async fn find_duplicate_sets(
+	db: &PrismaClient,
+	cursor: Option<String>,
+	take: u32,
+) -> Result<Vec<DuplicateSet>, Error> {
+	// PCR doesn't support grouping, so we fetch the duplicated checksums with a raw query
+	let checksums = db
+		._query_raw::<RawDuplicateChecksum>(raw!(
+			"SELECT integrity_checksum
+			FROM file_path
+			WHERE
+				integrity_checksum IS NOT NULL
+				AND is_dir = FALSE
+				AND integrity_checksum > {}
+			GROUP BY integrity_checksum
+			HAVING COUNT(*) > 1
+			ORDER BY integrity_checksum ASC
+			LIMIT {}",
+			PrismaValue::String(cursor.unwrap_or_default()),
+			PrismaValue::Int(i64::from(take))
+		))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|RawDuplicateChecksum { integrity_checksum }| integrity_checksum)
+		.collect::<Vec<_>>();
+
+	let mut file_paths_by_checksum = db
+		.file_path()
+		.find_many(vec![
+			file_path::integrity_checksum::in_vec(checksums.clone()),
+			file_path::is_dir::equals(Some(false)),
+		])
+		.select(file_path_for_duplicate_finder::select())
+		.exec()
+		.await?
+		.into_iter()
+		.filter_map(|file_path| {
+			file_path
+				.integrity_checksum
+				.clone()
+				.map(|checksum| (checksum, file_path))
+		})
+		.fold(
+			HashMap::<_, Vec<_>>::with_capacity(checksums.len()),
+			|mut file_paths_by_checksum, (checksum, file_path)| {
+				file_paths_by_checksum
+					.entry(checksum)
+					.or_default()
+					.push(file_path);
+				file_paths_by_checksum
+			},
+		);
+
+	Ok(checksums
+		.into_iter()
+		.filter_map(|checksum| {
+			file_paths_by_checksum
+				.remove(&checksum)
+				.map(|file_paths| DuplicateSet::new(checksum, &file_paths))
+		})
+		.collect())
+}
+
+/// Result of verifying the objects of a batch of files with their full content checksums
+#[derive(Debug, Default)]
+struct VerifiedObjects {
+	/// Number of sets of files that are confirmed to be duplicates
+	duplicate_sets_count: u64,
+	/// Bytes that would be freed by keeping only one file of each set
+	wasted_bytes: u64,
+	/// Number of objects that were created to split files that were wrongly grouped together
+	split_objects_count: u64,
+}
+
+/// Objects group files by their sampled `cas_id`, so files that only differ in the regions that weren't sampled
+/// end up wrongly sharing one. Their full content checksums tell them apart, so the biggest group of files with the same
+/// checksum keeps the object, and every other group is moved to a new object of its own.
+///
+/// All the `file_paths` of each object must be received, files without a checksum are left untouched.
+#[instrument(skip_all, fields(file_paths_count = file_paths.len()), err)]
+async fn verify_objects(
+	file_paths: Vec<file_path_for_duplicate_finder::Data>,
+	db: &PrismaClient,
+	sync: &SyncManager,
+) -> Result<VerifiedObjects, Error> {
+	let mut groups_by_object = HashMap::<
+		_,
+		(
+			file_path_for_duplicate_finder::object::Data,
+			HashMap<_, Vec<_>>,
+		),
+	>::new();
+
+	for file_path in file_paths {
+		let (Some(object), Some(checksum)) = (
+			file_path.object.clone(),
+			file_path.integrity_checksum.clone(),
+		) else {
+			continue;
+		};
+
+		groups_by_object
+			.entry(object.id)
+			.or_insert_with(|| (object, HashMap::new()))
+			.1
+			.entry(checksum)
+			.or_default()
+			.push(file_path);
+	}
+
+	let mut verified = VerifiedObjects::default();
+	let mut object_create_args = vec![];
+	let mut file_path_update_args = vec![];
+
+	for (object, groups) in groups_by_object.into_values() {
+		let mut groups = groups.into_iter().collect::<Vec<_>>();
+		// The biggest group keeps the object, ties are broken by the checksum to be deterministic
+		groups.sort_by(|(checksum_a, group_a), (checksum_b, group_b)| {
+			(Reverse(group_a.len()), checksum_a).cmp(&(Reverse(group_b.len()), checksum_b))
+		});
+
+		for (i, (checksum, group)) in groups.into_iter().enumerate() {
+			if i > 0 {
+				trace!(object_id = object.id, %checksum, "Splitting files from object;");
+
+				let object_pub_id = ObjectPubId::new();
+				let (sync_params, db_params) = [
+					(
+						(object::date_created::NAME, msgpack!(object.date_created)),
+						object::date_created::set(object.date_created),
+					),
+					(
+						(object::kind::NAME, msgpack!(object.kind)),
+						object::kind::set(object.kind),
+					),
+				]
+				.into_iter()
+				.unzip::<_, _, Vec<_>, Vec<_>>();
+
+				object_create_args.push((
+					sync.shared_create(
+						prisma_sync::object::SyncId {
+							pub_id: object_pub_id.to_db(),
+						},
+						sync_params,
+					),
+					object::create_unchecked(object_pub_id.to_db(), db_params),
+				));
+
+				file_path_update_args.extend(group.iter().map(|file_path| {
+					connect_file_path_to_object(
+						&FilePathPubId::from(&file_path.pub_id),
+						&object_pub_id,
+						db,
+						sync,
+					)
+				}));
+
+				verified.split_objects_count += 1;
+			}
+
+			if group.len() > 1 {
+				verified.duplicate_sets_count += 1;
+				verified.wasted_bytes += wasted_bytes(size_in_bytes(&group), group.len());
+			}
+		}
+	}
+
+	if object_create_args.is_empty() {
+		return Ok(verified);
+	}
+
+	sync.write_ops(db, {
+		let (sync, db_params) = object_create_args
+			.into_iter()
+			.unzip::<_, _, Vec<_>, Vec<_>>();
+
+		(
+			sync.into_iter().flatten().collect(),
+			db.object().create_many(db_params),
+		)
+	})
+	.await?;
+
+	sync.write_ops(
+		db,
+		file_path_update_args
+			.into_iter()
+			.unzip::<_, _, Vec<_>, Vec<_>>(),
+	)
+	.await?;
+
+	Ok(verified)
+}
+
+/// Filter the received file paths down to the ones sharing their `cas_id` with another file in the library
+async fn filter_shared_cas_ids(
+	file_path_ids: &[file_path::id::Type],
+	db: &PrismaClient,
+) -> Result<HashSet<file_path::id::Type>, Error> {
+	if file_path_ids.is_empty() {
+		return Ok(HashSet::new());
+	}
+
+	// FIXME: Had to use format! macro because PCR doesn't support IN with Vec for SQLite
+	// We have no data coming from the user, so this is sql injection safe
+	Ok(db
+		._query_raw::<RawFilePathId>(raw!(&format!(
+			"SELECT candidate.id
+			FROM file_path AS candidate
+			WHERE
+				candidate.id IN ({})
+				AND EXISTS (
+					SELECT 1 FROM file_path AS other
+					WHERE other.cas_id = candidate.cas_id AND other.id <> candidate.id
+				)",
+			file_path_ids
+				.iter()
+				.map(ToString::to_string)
+				.collect::<Vec<_>>()
+				.join(",")
+		)))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|RawFilePathId { id }| id)
+		.collect())
+}
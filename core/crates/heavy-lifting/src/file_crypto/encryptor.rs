@@ -0,0 +1,61 @@
+use crate::{
+	job_system::{
+		job::{Job, JobTaskDispatcher, ReturnStatus},
+		SerializableJob,
+	},
+	Error, JobContext, JobName, OuterContext,
+};
+
+use sd_crypto::container::RootKey;
+use sd_prisma::prisma::{file_path, location};
+
+use std::{
+	hash::{Hash, Hasher},
+	sync::Arc,
+};
+
+use tracing::{instrument, Level};
+
+use super::{job::FileCryptoJob, Operation};
+
+/// Encrypts the selected files and every file inside the selected directories
+#[derive(Debug)]
+pub struct Encryptor(FileCryptoJob);
+
+impl Hash for Encryptor {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.0.hash(state);
+	}
+}
+
+impl Job for Encryptor {
+	const NAME: JobName = JobName::Encrypt;
+
+	#[instrument(skip_all, ret(level = Level::TRACE), err)]
+	async fn run<OuterCtx: OuterContext>(
+		self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		self.0.run(dispatcher, ctx).await
+	}
+}
+
+impl Encryptor {
+	#[must_use]
+	pub fn new(
+		location_id: location::id::Type,
+		file_path_ids: Vec<file_path::id::Type>,
+		root_key: Arc<RootKey>,
+	) -> Self {
+		Self(FileCryptoJob::new(
+			location_id,
+			file_path_ids,
+			root_key,
+			Operation::Encrypt,
+		))
+	}
+}
+
+// Never serialized, as that would require persisting the key
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for Encryptor {}
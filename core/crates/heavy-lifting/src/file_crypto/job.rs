@@ -0,0 +1,326 @@
+use crate::{
+	file_system::{get_location_path, get_many_files_datas, walk_directory},
+	job_system::{
+		job::{JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		utils::cancel_pending_tasks,
+		DispatcherError, JobErrorOrDispatcherError,
+	},
+	Error, JobContext, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_crypto::container::RootKey;
+use sd_file_ext::extensions::EncryptedExtension;
+use sd_prisma::prisma::{file_path, location};
+use sd_task_system::{AnyTaskOutput, TaskHandle, TaskId, TaskOutput, TaskStatus};
+
+use std::{
+	collections::HashMap,
+	hash::{Hash, Hasher},
+	mem,
+	path::Path,
+	sync::Arc,
+	time::Duration,
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, warn};
+
+use super::{
+	tasks::{file_cryptor, FileCryptor},
+	Operation, BATCH_SIZE,
+};
+
+/// State shared by the [`Encryptor`](super::Encryptor) and [`Decryptor`](super::Decryptor) jobs.
+///
+/// These jobs are never serialized, as doing so would require persisting the [`RootKey`], so
+/// they are simply discarded on shutdown and must be dispatched again by the user.
+#[derive(Debug)]
+pub(super) struct FileCryptoJob {
+	// Received arguments
+	location_id: location::id::Type,
+	file_path_ids: Vec<file_path::id::Type>,
+	root_key: Arc<RootKey>,
+	operation: Operation,
+
+	// Run data
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+}
+
+impl Hash for FileCryptoJob {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location_id.hash(state);
+		self.file_path_ids.hash(state);
+		self.root_key.to_hash().as_bytes().hash(state);
+		self.operation.hash(state);
+	}
+}
+
+impl FileCryptoJob {
+	pub(super) fn new(
+		location_id: location::id::Type,
+		file_path_ids: Vec<file_path::id::Type>,
+		root_key: Arc<RootKey>,
+		operation: Operation,
+	) -> Self {
+		Self {
+			location_id,
+			file_path_ids,
+			root_key,
+			operation,
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+		}
+	}
+
+	pub(super) async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let mut pending_running_tasks = FuturesUnordered::new();
+
+		match self
+			.init(&mut pending_running_tasks, &ctx, &dispatcher)
+			.await
+		{
+			Ok(()) => { /* Everything is awesome! */ }
+			Err(JobErrorOrDispatcherError::JobError(e)) => {
+				return Err(e.into());
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::JobCanceled(_))) => {
+				return Ok(self.cancel_job(&mut pending_running_tasks).await);
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::Shutdown(_))) => {
+				cancel_pending_tasks(&mut pending_running_tasks).await;
+
+				// Nothing to be saved, as we can't persist the key
+				return Ok(ReturnStatus::Shutdown(Ok(None)));
+			}
+		}
+
+		let mut shutdown = false;
+
+		while let Some(task) = pending_running_tasks.next().await {
+			match task {
+				Ok(TaskStatus::Done((task_id, TaskOutput::Out(out)))) => {
+					self.process_task_output(task_id, out, &ctx).await;
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					warn!(%task_id, "Task returned an empty output");
+				}
+
+				Ok(TaskStatus::Shutdown(_)) => {
+					// The interrupted task left its current file complete, the remaining ones are dropped
+					shutdown = true;
+				}
+
+				Ok(TaskStatus::Error(e)) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&mut pending_running_tasks).await);
+				}
+
+				Err(e) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e.into());
+				}
+			}
+		}
+
+		ctx.invalidate_query("search.paths");
+
+		if shutdown {
+			return Ok(ReturnStatus::Shutdown(Ok(None)));
+		}
+
+		let Self {
+			metadata, errors, ..
+		} = self;
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+
+	async fn init<OuterCtx: OuterContext>(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+	) -> Result<(), JobErrorOrDispatcherError<super::Error>> {
+		let db = ctx.db();
+
+		let location_path = get_location_path(db, self.location_id)
+			.await
+			.map_err(super::Error::from)?;
+
+		let mut files = vec![];
+
+		for file_data in get_many_files_datas(db, &location_path, &self.file_path_ids)
+			.await
+			.map_err(super::Error::from)?
+		{
+			match file_data.is_dir(&mut self.errors) {
+				Some(true) => {
+					let (_, dir_files) =
+						walk_directory(&file_data.full_path, &mut self.errors).await;
+					files.extend(
+						dir_files
+							.into_iter()
+							.map(|(path, _)| path)
+							.filter(|path| self.wants_from_directory(path)),
+					);
+				}
+				Some(false) => files.push(file_data.full_path),
+				None => {}
+			}
+		}
+
+		self.metadata.total_files = files.len() as u64;
+
+		let tasks = files
+			.into_iter()
+			.chunks(BATCH_SIZE)
+			.into_iter()
+			.map(|chunk| FileCryptor::new(chunk, Arc::clone(&self.root_key), self.operation))
+			.collect::<Vec<_>>();
+
+		#[allow(clippy::cast_possible_truncation)]
+		{
+			// SAFETY: we know that `tasks.len()` is a valid u32 as we wouldn't dispatch more than `u32::MAX` tasks
+			self.metadata.total_tasks = tasks.len() as u32;
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+			ProgressUpdate::Message(format!(
+				"{} {} files",
+				self.operation.verb(),
+				self.metadata.total_files
+			)),
+		])
+		.await;
+
+		pending_running_tasks.extend(dispatcher.dispatch_many(tasks).await?);
+
+		Ok(())
+	}
+
+	/// When a whole directory is selected, we skip files that are already in the desired state
+	fn wants_from_directory(&self, path: &Path) -> bool {
+		let is_container = path
+			.extension()
+			.and_then(|ext| ext.to_str())
+			.is_some_and(|ext| ext.eq_ignore_ascii_case(&EncryptedExtension::Sdenc.to_string()));
+
+		match self.operation {
+			Operation::Encrypt => !is_container,
+			Operation::Decrypt => is_container,
+		}
+	}
+
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		task_id: TaskId,
+		any_task_output: Box<dyn AnyTaskOutput>,
+		ctx: &impl JobContext<OuterCtx>,
+	) {
+		let file_cryptor::Output {
+			processed_count,
+			crypto_time,
+			errors,
+		} = *any_task_output
+			.downcast::<file_cryptor::Output>()
+			.expect("File crypto jobs only dispatch FileCryptor tasks");
+
+		self.metadata.processed_files += processed_count;
+		self.metadata.mean_crypto_time += crypto_time;
+		self.metadata.completed_tasks += 1;
+
+		if !errors.is_empty() {
+			warn!(
+				?errors,
+				"Non critical errors while handling encrypted files;"
+			);
+			self.errors.extend(errors);
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			ProgressUpdate::Message(format!(
+				"{} {} of {} files",
+				self.operation.past_verb(),
+				self.metadata.processed_files,
+				self.metadata.total_files
+			)),
+		])
+		.await;
+
+		debug!(
+			%task_id,
+			operation = ?self.operation,
+			"Processed ({}/{}) file crypto tasks, took: {crypto_time:?};",
+			self.metadata.completed_tasks, self.metadata.total_tasks,
+		);
+	}
+
+	async fn cancel_job(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+	) -> ReturnStatus {
+		cancel_pending_tasks(pending_running_tasks).await;
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	total_files: u64,
+	processed_files: u64,
+	mean_crypto_time: Duration,
+	total_tasks: u32,
+	completed_tasks: u32,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			total_files,
+			processed_files,
+			mut mean_crypto_time,
+			total_tasks,
+			completed_tasks,
+		}: Metadata,
+	) -> Self {
+		// To avoid division by zero
+		mean_crypto_time /= u32::max(completed_tasks, 1);
+
+		vec![ReportOutputMetadata::Metrics(HashMap::from([
+			("total_files".into(), json!(total_files)),
+			("processed_files".into(), json!(processed_files)),
+			("mean_crypto_time".into(), json!(mean_crypto_time)),
+			("total_tasks".into(), json!(total_tasks)),
+			("completed_tasks".into(), json!(completed_tasks)),
+		]))]
+	}
+}
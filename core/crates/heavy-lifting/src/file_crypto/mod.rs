@@ -0,0 +1,108 @@
+use crate::file_system;
+
+use sd_file_ext::extensions::EncryptedExtension;
+use sd_prisma::prisma::location;
+
+use std::{
+	ffi::OsString,
+	path::{Path, PathBuf},
+};
+
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+pub mod decryptor;
+pub mod encryptor;
+mod job;
+mod tasks;
+
+pub use decryptor::Decryptor;
+pub use encryptor::Encryptor;
+
+/// Maximum number of files that a single crypto task will handle
+const BATCH_SIZE: usize = 10;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("location not found: <id='{0}'>")]
+	LocationNotFound(location::id::Type),
+
+	#[error("failed to initialize random number generator: {0}")]
+	Rng(#[from] sd_crypto::Error),
+
+	#[error(transparent)]
+	FileSystem(#[from] file_system::Error),
+}
+
+impl From<Error> for rspc::Error {
+	fn from(e: Error) -> Self {
+		match e {
+			Error::LocationNotFound(_) => Self::with_cause(ErrorCode::NotFound, e.to_string(), e),
+			Error::Rng(_) => Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e),
+			Error::FileSystem(e) => e.into(),
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize, Type, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum NonCriticalFileCryptoError {
+	#[error("failed to find an available name for encrypted or decrypted file <path='{}'>: {1}", .0.display())]
+	FindAvailableName(PathBuf, String),
+	#[error("failed to encrypt file <source='{}', target='{}'>: {2}", .0.display(), .1.display())]
+	Encrypt(PathBuf, PathBuf, String),
+	#[error("failed to decrypt file <source='{}', target='{}'>: {2}", .0.display(), .1.display())]
+	Decrypt(PathBuf, PathBuf, String),
+	#[error("file is not a Spacedrive encrypted container: <path='{}'>", .0.display())]
+	NotAContainer(PathBuf),
+	#[error("file was encrypted with a different key: <path='{}'>", .0.display())]
+	DifferentKey(PathBuf),
+}
+
+/// Direction of the work done by the crypto tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Operation {
+	Encrypt,
+	Decrypt,
+}
+
+impl Operation {
+	const fn verb(self) -> &'static str {
+		match self {
+			Self::Encrypt => "Encrypting",
+			Self::Decrypt => "Decrypting",
+		}
+	}
+
+	const fn past_verb(self) -> &'static str {
+		match self {
+			Self::Encrypt => "Encrypted",
+			Self::Decrypt => "Decrypted",
+		}
+	}
+}
+
+/// Where a file will be written after being encrypted, it is placed next to the original file
+/// with an extra `.sdenc` extension
+fn encrypted_target_path(path: &Path) -> PathBuf {
+	let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+	file_name.push(".");
+	file_name.push(EncryptedExtension::Sdenc.to_string());
+
+	path.with_file_name(file_name)
+}
+
+/// Where a file will be written after being decrypted, it is placed next to the encrypted file,
+/// without the `.sdenc` extension if it has one
+fn decrypted_target_path(path: &Path) -> PathBuf {
+	if path
+		.extension()
+		.and_then(|ext| ext.to_str())
+		.is_some_and(|ext| ext.eq_ignore_ascii_case(&EncryptedExtension::Sdenc.to_string()))
+	{
+		path.with_extension("")
+	} else {
+		path.to_path_buf()
+	}
+}
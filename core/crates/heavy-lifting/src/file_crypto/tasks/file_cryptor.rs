@@ -0,0 +1,242 @@
+use crate::{
+	file_crypto::{self, NonCriticalFileCryptoError},
+	file_system::find_available_filename_for_duplicate,
+	Error, NonCriticalError,
+};
+
+use sd_crypto::{
+	container::{self, Header, RootKey},
+	CryptoRng,
+};
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, IntoAnyTaskOutput, Task, TaskId,
+};
+
+use std::{
+	collections::VecDeque,
+	mem,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
+};
+
+use tokio::{
+	fs::{self, File, OpenOptions},
+	io::{self, BufReader, BufWriter},
+	time::Instant,
+};
+use tracing::{instrument, trace, Level};
+
+use super::super::Operation;
+
+/// Encrypts or decrypts files, writing the result next to each one of them
+#[derive(Debug)]
+pub struct FileCryptor {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	files: VecDeque<PathBuf>,
+	root_key: Arc<RootKey>,
+	operation: Operation,
+
+	// Out collector
+	output: Output,
+}
+
+/// [`FileCryptor`] task output
+#[derive(Debug, Default)]
+pub struct Output {
+	/// Number of files successfully encrypted or decrypted
+	pub processed_count: u64,
+	/// Time spent encrypting or decrypting files
+	pub crypto_time: Duration,
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for FileCryptor {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	fn with_priority(&self) -> bool {
+		// Encryption and decryption are user driven, so they must complete as soon as possible
+		true
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(task_id = %self.id, files_count = %self.files.len(), operation = ?self.operation),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			files,
+			root_key,
+			operation,
+			output: Output {
+				processed_count,
+				crypto_time,
+				errors,
+			},
+			..
+		} = self;
+
+		let mut rng = CryptoRng::new().map_err(file_crypto::Error::from)?;
+
+		let start_time = Instant::now();
+
+		while let Some(path) = files.pop_front() {
+			let res = match operation {
+				Operation::Encrypt => encrypt_file(&path, root_key, &mut rng).await,
+				Operation::Decrypt => decrypt_file(&path, root_key).await,
+			};
+
+			match res {
+				Ok(target) => {
+					trace!(
+						source = %path.display(),
+						target = %target.display(),
+						"File processed;",
+					);
+					*processed_count += 1;
+				}
+				Err(e) => errors.push(e.into()),
+			}
+
+			check_interruption!(interrupter, start_time, crypto_time);
+		}
+
+		*crypto_time += start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl FileCryptor {
+	#[must_use]
+	pub(in crate::file_crypto) fn new(
+		files: impl IntoIterator<Item = PathBuf>,
+		root_key: Arc<RootKey>,
+		operation: Operation,
+	) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			files: files.into_iter().collect(),
+			root_key,
+			operation,
+			output: Output::default(),
+		}
+	}
+}
+
+/// Find a path for the resulting file that doesn't overwrite anything
+async fn available_target(target: PathBuf) -> Result<PathBuf, NonCriticalFileCryptoError> {
+	match fs::try_exists(&target).await {
+		Ok(false) => Ok(target),
+		Ok(true) => find_available_filename_for_duplicate(&target)
+			.await
+			.map_err(|e| NonCriticalFileCryptoError::FindAvailableName(target, e.to_string())),
+		Err(e) => Err(NonCriticalFileCryptoError::FindAvailableName(
+			target,
+			e.to_string(),
+		)),
+	}
+}
+
+async fn encrypt_file(
+	source: &Path,
+	root_key: &RootKey,
+	rng: &mut CryptoRng,
+) -> Result<PathBuf, NonCriticalFileCryptoError> {
+	let target = available_target(file_crypto::encrypted_target_path(source)).await?;
+
+	let reader = File::open(source).await.map_err(|e| {
+		NonCriticalFileCryptoError::Encrypt(source.to_path_buf(), target.clone(), e.to_string())
+	})?;
+
+	let writer = create_target(&target).await.map_err(|e| {
+		NonCriticalFileCryptoError::Encrypt(source.to_path_buf(), target.clone(), e.to_string())
+	})?;
+
+	if let Err(e) = container::encrypt(root_key, reader, BufWriter::new(writer), rng).await {
+		remove_partial_file(&target).await;
+
+		return Err(NonCriticalFileCryptoError::Encrypt(
+			source.to_path_buf(),
+			target,
+			e.to_string(),
+		));
+	}
+
+	Ok(target)
+}
+
+async fn decrypt_file(
+	source: &Path,
+	root_key: &RootKey,
+) -> Result<PathBuf, NonCriticalFileCryptoError> {
+	let mut reader = BufReader::new(File::open(source).await.map_err(|e| {
+		NonCriticalFileCryptoError::Decrypt(source.to_path_buf(), PathBuf::new(), e.to_string())
+	})?);
+
+	let header = match Header::read(&mut reader).await {
+		Ok(header) => header,
+		Err(sd_crypto::Error::NotAContainer) => {
+			return Err(NonCriticalFileCryptoError::NotAContainer(
+				source.to_path_buf(),
+			))
+		}
+		Err(e) => {
+			return Err(NonCriticalFileCryptoError::Decrypt(
+				source.to_path_buf(),
+				PathBuf::new(),
+				e.to_string(),
+			))
+		}
+	};
+
+	if !header.is_wrapped_by(root_key) {
+		return Err(NonCriticalFileCryptoError::DifferentKey(
+			source.to_path_buf(),
+		));
+	}
+
+	let target = available_target(file_crypto::decrypted_target_path(source)).await?;
+
+	let writer = create_target(&target).await.map_err(|e| {
+		NonCriticalFileCryptoError::Decrypt(source.to_path_buf(), target.clone(), e.to_string())
+	})?;
+
+	if let Err(e) = container::decrypt(root_key, &header, reader, writer).await {
+		// We never leave partially decrypted data around, as it wasn't fully authenticated
+		remove_partial_file(&target).await;
+
+		return Err(NonCriticalFileCryptoError::Decrypt(
+			source.to_path_buf(),
+			target,
+			e.to_string(),
+		));
+	}
+
+	Ok(target)
+}
+
+/// Create the resulting file, failing if something was created at the same path in the meantime
+async fn create_target(target: &Path) -> Result<File, io::Error> {
+	OpenOptions::new()
+		.write(true)
+		.create_new(true)
+		.open(target)
+		.await
+}
+
+async fn remove_partial_file(path: &Path) {
+	if let Err(e) = fs::remove_file(path).await {
+		trace!(path = %path.display(), ?e, "Failed to remove partially written file;");
+	}
+}
@@ -0,0 +1,3 @@
+pub mod file_cryptor;
+
+pub use file_cryptor::FileCryptor;
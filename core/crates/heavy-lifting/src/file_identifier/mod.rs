@@ -27,7 +27,7 @@ use uuid::Uuid;
 mod cas_id;
 pub mod job;
 mod shallow;
-mod tasks;
+pub(crate) mod tasks;
 
 pub use cas_id::generate_cas_id;
 
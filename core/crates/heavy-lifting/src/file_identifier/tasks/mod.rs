@@ -34,7 +34,7 @@ pub(super) struct FilePathToCreateOrLinkObject {
 }
 
 #[instrument(skip(sync, db))]
-fn connect_file_path_to_object<'db>(
+pub(crate) fn connect_file_path_to_object<'db>(
 	file_path_pub_id: &FilePathPubId,
 	object_pub_id: &ObjectPubId,
 	db: &'db PrismaClient,
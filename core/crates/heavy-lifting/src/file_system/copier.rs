@@ -0,0 +1,630 @@
+use crate::{
+	file_system,
+	job_system::{
+		job::{Job, JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		utils::cancel_pending_tasks,
+		DispatcherError, JobErrorOrDispatcherError, SerializableJob, SerializedTasks,
+	},
+	Error, JobContext, JobName, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_core_file_path_helper::join_location_relative_path;
+
+use sd_prisma::prisma::{file_path, location};
+use sd_task_system::{
+	AnyTaskOutput, IntoTask, SerializableTask, Task, TaskDispatcher, TaskHandle, TaskId,
+	TaskOutput, TaskStatus,
+};
+use sd_utils::{error::FileIOError, u64_to_frontend};
+
+use std::{
+	collections::HashMap,
+	hash::{Hash, Hasher},
+	mem,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use futures_concurrency::future::TryJoin;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{fs, io, time::Instant};
+use tracing::{debug, instrument, trace, warn, Level};
+
+use super::{
+	find_available_filename_for_duplicate, get_location_path, get_many_files_datas,
+	tasks::{file_copier, FileCopier},
+	walk_directory, NonCriticalFileSystemError,
+};
+
+/// Maximum amount of files that a single [`FileCopier`] task will copy
+const MAX_FILES_PER_TASK: usize = 20;
+
+/// Maximum amount of bytes that a single [`FileCopier`] task will copy, unless a single file is
+/// bigger than this, in which case it will be copied alone in its own task
+const MAX_TOTAL_SIZE_PER_TASK: u64 = 1024 * 1024 * 800; // 800 MiB
+
+#[derive(Debug)]
+pub struct Copier {
+	// Received arguments
+	source_location_id: location::id::Type,
+	target_location_id: location::id::Type,
+	sources_file_path_ids: Vec<file_path::id::Type>,
+	target_location_relative_directory_path: PathBuf,
+
+	// Inner state
+	tasks_dispatched: bool,
+
+	// Run data
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	// On shutdown data
+	pending_tasks_on_resume: Vec<TaskHandle<Error>>,
+	tasks_for_shutdown: Vec<Box<dyn Task<Error>>>,
+}
+
+impl Hash for Copier {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.source_location_id.hash(state);
+		self.target_location_id.hash(state);
+		self.sources_file_path_ids.hash(state);
+		self.target_location_relative_directory_path.hash(state);
+	}
+}
+
+impl Job for Copier {
+	const NAME: JobName = JobName::Copy;
+
+	async fn resume_tasks<OuterCtx: OuterContext>(
+		&mut self,
+		dispatcher: &JobTaskDispatcher,
+		_: &impl JobContext<OuterCtx>,
+		SerializedTasks(serialized_tasks): SerializedTasks,
+	) -> Result<(), Error> {
+		if let Ok(tasks) = dispatcher
+			.dispatch_many_boxed(
+				rmp_serde::from_slice::<Vec<Vec<u8>>>(&serialized_tasks)
+					.map_err(file_system::Error::from)?
+					.into_iter()
+					.map(|task_bytes| async move {
+						FileCopier::deserialize(&task_bytes, ())
+							.await
+							.map(IntoTask::into_task)
+					})
+					.collect::<Vec<_>>()
+					.try_join()
+					.await
+					.map_err(file_system::Error::from)?,
+			)
+			.await
+		{
+			self.pending_tasks_on_resume = tasks;
+		} else {
+			warn!("Failed to dispatch tasks to resume as job was already canceled");
+		}
+
+		Ok(())
+	}
+
+	#[instrument(
+		skip_all,
+		fields(
+			source_location_id = self.source_location_id,
+			target_location_id = self.target_location_id,
+			target_directory = %self.target_location_relative_directory_path.display(),
+			sources_count = self.sources_file_path_ids.len(),
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let mut pending_running_tasks = FuturesUnordered::new();
+
+		match self
+			.init_or_resume(&mut pending_running_tasks, &ctx, &dispatcher)
+			.await
+		{
+			Ok(()) => { /* Everything is awesome! */ }
+			Err(JobErrorOrDispatcherError::JobError(e)) => {
+				return Err(e.into());
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::JobCanceled(_))) => {
+				return Ok(self.cancel_job(&mut pending_running_tasks).await);
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::Shutdown(tasks))) => {
+				self.tasks_for_shutdown.extend(tasks);
+
+				if pending_running_tasks.is_empty() {
+					// If no task managed to be dispatched, we can just shutdown
+					// otherwise we have to process handles below and wait for them to be shutdown too
+					return Ok(ReturnStatus::Shutdown(
+						SerializableJob::<OuterCtx>::serialize(self).await,
+					));
+				}
+			}
+		}
+
+		while let Some(task) = pending_running_tasks.next().await {
+			match task {
+				Ok(TaskStatus::Done((task_id, TaskOutput::Out(out)))) => {
+					self.process_task_output(task_id, out, &ctx).await;
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					warn!(%task_id, "Task returned an empty output");
+				}
+
+				Ok(TaskStatus::Shutdown(task)) => {
+					self.tasks_for_shutdown.push(task);
+				}
+
+				Ok(TaskStatus::Error(e)) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&mut pending_running_tasks).await);
+				}
+
+				Err(e) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e.into());
+				}
+			}
+		}
+
+		if !self.tasks_for_shutdown.is_empty() {
+			return Ok(ReturnStatus::Shutdown(
+				SerializableJob::<OuterCtx>::serialize(self).await,
+			));
+		}
+
+		// From this point onward, we are done with the job and it can't be interrupted anymore
+		let Self {
+			metadata, errors, ..
+		} = self;
+
+		ctx.invalidate_query("search.paths");
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+}
+
+impl Copier {
+	#[must_use]
+	pub fn new(
+		source_location_id: location::id::Type,
+		target_location_id: location::id::Type,
+		sources_file_path_ids: Vec<file_path::id::Type>,
+		target_location_relative_directory_path: PathBuf,
+	) -> Self {
+		Self {
+			source_location_id,
+			target_location_id,
+			sources_file_path_ids,
+			target_location_relative_directory_path,
+			tasks_dispatched: false,
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+			pending_tasks_on_resume: Vec::new(),
+			tasks_for_shutdown: Vec::new(),
+		}
+	}
+
+	async fn init_or_resume<OuterCtx: OuterContext>(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+	) -> Result<(), JobErrorOrDispatcherError<file_system::Error>> {
+		if self.tasks_dispatched {
+			pending_running_tasks.extend(mem::take(&mut self.pending_tasks_on_resume));
+
+			ctx.progress(vec![
+				ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+				ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			])
+			.await;
+
+			debug!(
+				resuming_tasks_count = pending_running_tasks.len(),
+				"Resuming tasks for Copier job;",
+			);
+
+			return Ok(());
+		}
+
+		let start = Instant::now();
+
+		ctx.progress_msg("Preparing files to be copied").await;
+
+		let entries = self.plan_copy(ctx).await?;
+
+		self.metadata.total_files = entries.len() as u64;
+		self.metadata.total_bytes = entries.iter().map(|entry| entry.size).sum();
+
+		let tasks = batch_copy_entries(entries)
+			.into_iter()
+			.map(FileCopier::new)
+			.collect::<Vec<_>>();
+
+		#[allow(clippy::cast_possible_truncation)]
+		{
+			// SAFETY: we know that `tasks.len()` is a valid u32 as we wouldn't dispatch more than `u32::MAX` tasks
+			self.metadata.total_tasks = tasks.len() as u32;
+		}
+
+		self.metadata.planning_time = start.elapsed();
+		self.tasks_dispatched = true;
+
+		ctx.progress(vec![
+			ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+			ProgressUpdate::Message(format!(
+				"Copying {} files ({} bytes)",
+				self.metadata.total_files, self.metadata.total_bytes
+			)),
+		])
+		.await;
+
+		pending_running_tasks.extend(dispatcher.dispatch_many(tasks).await?);
+
+		Ok(())
+	}
+
+	/// Resolve every source to its target path, creating the directory structure on the target
+	/// and collecting every file that must be copied
+	async fn plan_copy<OuterCtx: OuterContext>(
+		&mut self,
+		ctx: &impl JobContext<OuterCtx>,
+	) -> Result<Vec<file_copier::CopyEntry>, file_system::Error> {
+		let db = ctx.db();
+
+		let (sources_location_path, targets_location_path) = (
+			get_location_path(db, self.source_location_id),
+			get_location_path(db, self.target_location_id),
+		)
+			.try_join()
+			.await?;
+
+		let files_datas =
+			get_many_files_datas(db, &sources_location_path, &self.sources_file_path_ids).await?;
+
+		let target_directory = join_location_relative_path(
+			&targets_location_path,
+			&self.target_location_relative_directory_path,
+		);
+
+		let mut entries = Vec::with_capacity(files_datas.len());
+
+		for file_data in files_datas {
+			let Some(is_dir) = file_data.is_dir(&mut self.errors) else {
+				continue;
+			};
+
+			let target = resolve_target_path(
+				&file_data.full_path,
+				target_directory.join(file_data.target_file_name()?),
+			)
+			.await?;
+
+			if is_dir {
+				self.plan_directory_copy(&file_data.full_path, &target, &mut entries)
+					.await;
+			} else {
+				match fs::metadata(&file_data.full_path).await {
+					Ok(metadata) => entries.push(file_copier::CopyEntry {
+						source: file_data.full_path,
+						target,
+						size: metadata.len(),
+					}),
+					Err(e) => self.errors.push(
+						NonCriticalFileSystemError::Copy(
+							file_data.full_path,
+							target,
+							e.to_string(),
+						)
+						.into(),
+					),
+				}
+			}
+		}
+
+		Ok(entries)
+	}
+
+	async fn plan_directory_copy(
+		&mut self,
+		source: &Path,
+		target: &Path,
+		entries: &mut Vec<file_copier::CopyEntry>,
+	) {
+		if target.starts_with(source) {
+			self.errors.push(
+				NonCriticalFileSystemError::Copy(
+					source.to_path_buf(),
+					target.to_path_buf(),
+					"can't copy a directory into itself".to_string(),
+				)
+				.into(),
+			);
+			return;
+		}
+
+		let (directories, files) = walk_directory(source, &mut self.errors).await;
+
+		for directory in std::iter::once(target.to_path_buf()).chain(
+			directories
+				.into_iter()
+				.filter_map(|dir| dir.strip_prefix(source).ok().map(|rel| target.join(rel))),
+		) {
+			trace!(path = %directory.display(), "Creating directory;");
+			if let Err(e) = fs::create_dir_all(&directory).await {
+				self.errors.push(
+					NonCriticalFileSystemError::CreateDirectory(directory, e.to_string()).into(),
+				);
+			}
+		}
+
+		entries.extend(files.into_iter().filter_map(|(file, size)| {
+			file.strip_prefix(source)
+				.ok()
+				.map(|rel| target.join(rel))
+				.map(|target| file_copier::CopyEntry {
+					source: file,
+					target,
+					size,
+				})
+		}));
+	}
+
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		task_id: TaskId,
+		any_task_output: Box<dyn AnyTaskOutput>,
+		ctx: &impl JobContext<OuterCtx>,
+	) {
+		let file_copier::Output {
+			copied_count,
+			copied_bytes,
+			copy_time,
+			errors,
+		} = *any_task_output
+			.downcast::<file_copier::Output>()
+			.expect("Copier job only dispatches FileCopier tasks");
+
+		self.metadata.copied_files += copied_count;
+		self.metadata.copied_bytes += copied_bytes;
+		self.metadata.mean_copy_time += copy_time;
+		self.metadata.completed_tasks += 1;
+
+		if !errors.is_empty() {
+			warn!(?errors, "Non critical errors while copying files;");
+			self.errors.extend(errors);
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			ProgressUpdate::Message(format!(
+				"Copied {} of {} files",
+				self.metadata.copied_files, self.metadata.total_files
+			)),
+		])
+		.await;
+
+		debug!(
+			%task_id,
+			"Processed ({}/{}) copier tasks, took: {copy_time:?};",
+			self.metadata.completed_tasks, self.metadata.total_tasks,
+		);
+	}
+
+	async fn cancel_job(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+	) -> ReturnStatus {
+		cancel_pending_tasks(pending_running_tasks).await;
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+/// If the target path is already taken, or if we're copying a file into its own directory,
+/// we find a new available name following the `name (1).ext` pattern
+async fn resolve_target_path(
+	source: &Path,
+	target: PathBuf,
+) -> Result<PathBuf, file_system::Error> {
+	if source == target {
+		return find_available_filename_for_duplicate(target).await;
+	}
+
+	match fs::metadata(&target).await {
+		Ok(_) => find_available_filename_for_duplicate(target).await,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(target),
+		Err(e) => Err(FileIOError::from((target, e)).into()),
+	}
+}
+
+/// Group copy entries in batches, sorted by size, so each task gets at most
+/// [`MAX_FILES_PER_TASK`] files or [`MAX_TOTAL_SIZE_PER_TASK`] bytes
+fn batch_copy_entries(
+	mut entries: Vec<file_copier::CopyEntry>,
+) -> Vec<Vec<file_copier::CopyEntry>> {
+	entries.sort_unstable_by_key(|entry| entry.size);
+
+	let mut batches = vec![];
+	let mut current_batch = vec![];
+	let mut current_batch_size = 0;
+
+	for entry in entries {
+		if !current_batch.is_empty()
+			&& (current_batch.len() >= MAX_FILES_PER_TASK
+				|| current_batch_size + entry.size > MAX_TOTAL_SIZE_PER_TASK)
+		{
+			batches.push(mem::take(&mut current_batch));
+			current_batch_size = 0;
+		}
+
+		current_batch_size += entry.size;
+		current_batch.push(entry);
+	}
+
+	if !current_batch.is_empty() {
+		batches.push(current_batch);
+	}
+
+	batches
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	source_location_id: location::id::Type,
+	target_location_id: location::id::Type,
+	sources_file_path_ids: Vec<file_path::id::Type>,
+	target_location_relative_directory_path: PathBuf,
+
+	tasks_dispatched: bool,
+
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	tasks_for_shutdown_bytes: Option<SerializedTasks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	total_files: u64,
+	total_bytes: u64,
+	copied_files: u64,
+	copied_bytes: u64,
+	mean_copy_time: Duration,
+	planning_time: Duration,
+	total_tasks: u32,
+	completed_tasks: u32,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			total_files,
+			total_bytes,
+			copied_files,
+			copied_bytes,
+			mut mean_copy_time,
+			planning_time,
+			total_tasks,
+			completed_tasks,
+		}: Metadata,
+	) -> Self {
+		// To avoid division by zero
+		mean_copy_time /= u32::max(completed_tasks, 1);
+
+		vec![ReportOutputMetadata::Metrics(HashMap::from([
+			("total_files".into(), json!(u64_to_frontend(total_files))),
+			("total_bytes".into(), json!(u64_to_frontend(total_bytes))),
+			("copied_files".into(), json!(u64_to_frontend(copied_files))),
+			("copied_bytes".into(), json!(u64_to_frontend(copied_bytes))),
+			("mean_copy_time".into(), json!(mean_copy_time)),
+			("planning_time".into(), json!(planning_time)),
+			("total_tasks".into(), json!(total_tasks)),
+			("completed_tasks".into(), json!(completed_tasks)),
+		]))]
+	}
+}
+
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for Copier {
+	async fn serialize(self) -> Result<Option<Vec<u8>>, rmp_serde::encode::Error> {
+		let Self {
+			source_location_id,
+			target_location_id,
+			sources_file_path_ids,
+			target_location_relative_directory_path,
+			tasks_dispatched,
+			metadata,
+			errors,
+			tasks_for_shutdown,
+			..
+		} = self;
+
+		let serialized_tasks = tasks_for_shutdown
+			.into_iter()
+			.map(|task| async move {
+				task.downcast::<FileCopier>()
+					.expect("Copier job only dispatches FileCopier tasks")
+					.serialize()
+					.await
+			})
+			.collect::<Vec<_>>()
+			.try_join()
+			.await?;
+
+		let tasks_for_shutdown_bytes = if serialized_tasks.is_empty() {
+			None
+		} else {
+			Some(SerializedTasks(rmp_serde::to_vec_named(&serialized_tasks)?))
+		};
+
+		rmp_serde::to_vec_named(&SaveState {
+			source_location_id,
+			target_location_id,
+			sources_file_path_ids,
+			target_location_relative_directory_path,
+			tasks_dispatched,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		})
+		.map(Some)
+	}
+
+	async fn deserialize(
+		serialized_job: &[u8],
+		_: &OuterCtx,
+	) -> Result<Option<(Self, Option<SerializedTasks>)>, rmp_serde::decode::Error> {
+		let SaveState {
+			source_location_id,
+			target_location_id,
+			sources_file_path_ids,
+			target_location_relative_directory_path,
+			tasks_dispatched,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		} = rmp_serde::from_slice::<SaveState>(serialized_job)?;
+
+		Ok(Some((
+			Self {
+				source_location_id,
+				target_location_id,
+				sources_file_path_ids,
+				target_location_relative_directory_path,
+				tasks_dispatched,
+				metadata,
+				errors,
+				pending_tasks_on_resume: Vec::new(),
+				tasks_for_shutdown: Vec::new(),
+			},
+			tasks_for_shutdown_bytes,
+		)))
+	}
+}
@@ -0,0 +1,454 @@
+use crate::{
+	file_system,
+	job_system::{
+		job::{Job, JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		utils::cancel_pending_tasks,
+		DispatcherError, JobErrorOrDispatcherError, SerializableJob, SerializedTasks,
+	},
+	Error, JobContext, JobName, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_prisma::prisma::{file_path, location};
+use sd_task_system::{
+	AnyTaskOutput, IntoTask, SerializableTask, Task, TaskDispatcher, TaskHandle, TaskId,
+	TaskOutput, TaskStatus,
+};
+
+use std::{
+	collections::HashMap,
+	hash::{Hash, Hasher},
+	mem,
+	sync::Arc,
+	time::Duration,
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use futures_concurrency::future::TryJoin;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, instrument, warn, Level};
+
+use super::{
+	get_location_path, get_many_files_datas,
+	tasks::{file_deleter, FileDeleter},
+	BATCH_SIZE,
+};
+
+#[derive(Debug)]
+pub struct Deleter {
+	// Received arguments
+	location_id: location::id::Type,
+	file_path_ids: Vec<file_path::id::Type>,
+
+	// Inner state
+	tasks_dispatched: bool,
+
+	// Run data
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	// On shutdown data
+	pending_tasks_on_resume: Vec<TaskHandle<Error>>,
+	tasks_for_shutdown: Vec<Box<dyn Task<Error>>>,
+}
+
+impl Hash for Deleter {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location_id.hash(state);
+		self.file_path_ids.hash(state);
+	}
+}
+
+impl Job for Deleter {
+	const NAME: JobName = JobName::Delete;
+
+	async fn resume_tasks<OuterCtx: OuterContext>(
+		&mut self,
+		dispatcher: &JobTaskDispatcher,
+		ctx: &impl JobContext<OuterCtx>,
+		SerializedTasks(serialized_tasks): SerializedTasks,
+	) -> Result<(), Error> {
+		if let Ok(tasks) = dispatcher
+			.dispatch_many_boxed(
+				rmp_serde::from_slice::<Vec<Vec<u8>>>(&serialized_tasks)
+					.map_err(file_system::Error::from)?
+					.into_iter()
+					.map(|task_bytes| async move {
+						FileDeleter::deserialize(
+							&task_bytes,
+							(Arc::clone(ctx.db()), Arc::clone(ctx.sync())),
+						)
+						.await
+						.map(IntoTask::into_task)
+					})
+					.collect::<Vec<_>>()
+					.try_join()
+					.await
+					.map_err(file_system::Error::from)?,
+			)
+			.await
+		{
+			self.pending_tasks_on_resume = tasks;
+		} else {
+			warn!("Failed to dispatch tasks to resume as job was already canceled");
+		}
+
+		Ok(())
+	}
+
+	#[instrument(
+		skip_all,
+		fields(
+			location_id = self.location_id,
+			file_paths_count = self.file_path_ids.len(),
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let mut pending_running_tasks = FuturesUnordered::new();
+
+		match self
+			.init_or_resume(&mut pending_running_tasks, &ctx, &dispatcher)
+			.await
+		{
+			Ok(()) => { /* Everything is awesome! */ }
+			Err(JobErrorOrDispatcherError::JobError(e)) => {
+				return Err(e.into());
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::JobCanceled(_))) => {
+				return Ok(self.cancel_job(&mut pending_running_tasks).await);
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::Shutdown(tasks))) => {
+				self.tasks_for_shutdown.extend(tasks);
+
+				if pending_running_tasks.is_empty() {
+					// If no task managed to be dispatched, we can just shutdown
+					// otherwise we have to process handles below and wait for them to be shutdown too
+					return Ok(ReturnStatus::Shutdown(
+						SerializableJob::<OuterCtx>::serialize(self).await,
+					));
+				}
+			}
+		}
+
+		while let Some(task) = pending_running_tasks.next().await {
+			match task {
+				Ok(TaskStatus::Done((task_id, TaskOutput::Out(out)))) => {
+					self.process_task_output(task_id, out, &ctx).await;
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					warn!(%task_id, "Task returned an empty output");
+				}
+
+				Ok(TaskStatus::Shutdown(task)) => {
+					self.tasks_for_shutdown.push(task);
+				}
+
+				Ok(TaskStatus::Error(e)) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&mut pending_running_tasks).await);
+				}
+
+				Err(e) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e.into());
+				}
+			}
+		}
+
+		if !self.tasks_for_shutdown.is_empty() {
+			return Ok(ReturnStatus::Shutdown(
+				SerializableJob::<OuterCtx>::serialize(self).await,
+			));
+		}
+
+		// From this point onward, we are done with the job and it can't be interrupted anymore
+		let Self {
+			metadata, errors, ..
+		} = self;
+
+		ctx.invalidate_query("search.paths");
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+}
+
+impl Deleter {
+	#[must_use]
+	pub fn new(location_id: location::id::Type, file_path_ids: Vec<file_path::id::Type>) -> Self {
+		Self {
+			location_id,
+			file_path_ids,
+			tasks_dispatched: false,
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+			pending_tasks_on_resume: Vec::new(),
+			tasks_for_shutdown: Vec::new(),
+		}
+	}
+
+	async fn init_or_resume<OuterCtx: OuterContext>(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+	) -> Result<(), JobErrorOrDispatcherError<file_system::Error>> {
+		if self.tasks_dispatched {
+			pending_running_tasks.extend(mem::take(&mut self.pending_tasks_on_resume));
+
+			ctx.progress(vec![
+				ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+				ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			])
+			.await;
+
+			debug!(
+				resuming_tasks_count = pending_running_tasks.len(),
+				"Resuming tasks for Deleter job;",
+			);
+
+			return Ok(());
+		}
+
+		let db = ctx.db();
+
+		let location_path = get_location_path(db, self.location_id).await?;
+
+		let entries = get_many_files_datas(db, &location_path, &self.file_path_ids)
+			.await?
+			.into_iter()
+			.filter_map(|file_data| {
+				file_data
+					.is_dir(&mut self.errors)
+					.map(|is_dir| file_deleter::DeleteEntry {
+						file_path_id: file_data.file_path.id,
+						file_path_pub_id: file_data.file_path.pub_id,
+						full_path: file_data.full_path,
+						is_dir,
+					})
+			})
+			.collect::<Vec<_>>();
+
+		self.metadata.total_entries = entries.len() as u64;
+
+		let tasks = entries
+			.into_iter()
+			.chunks(BATCH_SIZE)
+			.into_iter()
+			.map(|chunk| FileDeleter::new(chunk, Arc::clone(db), Arc::clone(ctx.sync())))
+			.collect::<Vec<_>>();
+
+		#[allow(clippy::cast_possible_truncation)]
+		{
+			// SAFETY: we know that `tasks.len()` is a valid u32 as we wouldn't dispatch more than `u32::MAX` tasks
+			self.metadata.total_tasks = tasks.len() as u32;
+		}
+
+		self.tasks_dispatched = true;
+
+		ctx.progress(vec![
+			ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+			ProgressUpdate::Message(format!("Deleting {} files", self.metadata.total_entries)),
+		])
+		.await;
+
+		pending_running_tasks.extend(dispatcher.dispatch_many(tasks).await?);
+
+		Ok(())
+	}
+
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		task_id: TaskId,
+		any_task_output: Box<dyn AnyTaskOutput>,
+		ctx: &impl JobContext<OuterCtx>,
+	) {
+		let file_deleter::Output {
+			deleted_count,
+			removed_from_db_count,
+			delete_time,
+			errors,
+		} = *any_task_output
+			.downcast::<file_deleter::Output>()
+			.expect("Deleter job only dispatches FileDeleter tasks");
+
+		self.metadata.deleted_entries += deleted_count;
+		self.metadata.removed_from_db_entries += removed_from_db_count;
+		self.metadata.mean_delete_time += delete_time;
+		self.metadata.completed_tasks += 1;
+
+		if !errors.is_empty() {
+			warn!(?errors, "Non critical errors while deleting files;");
+			self.errors.extend(errors);
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			ProgressUpdate::Message(format!(
+				"Deleted {} of {} files",
+				self.metadata.deleted_entries + self.metadata.removed_from_db_entries,
+				self.metadata.total_entries
+			)),
+		])
+		.await;
+
+		debug!(
+			%task_id,
+			"Processed ({}/{}) deleter tasks, took: {delete_time:?};",
+			self.metadata.completed_tasks, self.metadata.total_tasks,
+		);
+	}
+
+	async fn cancel_job(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+	) -> ReturnStatus {
+		cancel_pending_tasks(pending_running_tasks).await;
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	location_id: location::id::Type,
+	file_path_ids: Vec<file_path::id::Type>,
+
+	tasks_dispatched: bool,
+
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	tasks_for_shutdown_bytes: Option<SerializedTasks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	total_entries: u64,
+	deleted_entries: u64,
+	removed_from_db_entries: u64,
+	mean_delete_time: Duration,
+	total_tasks: u32,
+	completed_tasks: u32,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			total_entries,
+			deleted_entries,
+			removed_from_db_entries,
+			mut mean_delete_time,
+			total_tasks,
+			completed_tasks,
+		}: Metadata,
+	) -> Self {
+		// To avoid division by zero
+		mean_delete_time /= u32::max(completed_tasks, 1);
+
+		vec![ReportOutputMetadata::Metrics(HashMap::from([
+			("total_entries".into(), json!(total_entries)),
+			("deleted_entries".into(), json!(deleted_entries)),
+			(
+				"removed_from_db_entries".into(),
+				json!(removed_from_db_entries),
+			),
+			("mean_delete_time".into(), json!(mean_delete_time)),
+			("total_tasks".into(), json!(total_tasks)),
+			("completed_tasks".into(), json!(completed_tasks)),
+		]))]
+	}
+}
+
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for Deleter {
+	async fn serialize(self) -> Result<Option<Vec<u8>>, rmp_serde::encode::Error> {
+		let Self {
+			location_id,
+			file_path_ids,
+			tasks_dispatched,
+			metadata,
+			errors,
+			tasks_for_shutdown,
+			..
+		} = self;
+
+		let serialized_tasks = tasks_for_shutdown
+			.into_iter()
+			.map(|task| async move {
+				task.downcast::<FileDeleter>()
+					.expect("Deleter job only dispatches FileDeleter tasks")
+					.serialize()
+					.await
+			})
+			.collect::<Vec<_>>()
+			.try_join()
+			.await?;
+
+		let tasks_for_shutdown_bytes = if serialized_tasks.is_empty() {
+			None
+		} else {
+			Some(SerializedTasks(rmp_serde::to_vec_named(&serialized_tasks)?))
+		};
+
+		rmp_serde::to_vec_named(&SaveState {
+			location_id,
+			file_path_ids,
+			tasks_dispatched,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		})
+		.map(Some)
+	}
+
+	async fn deserialize(
+		serialized_job: &[u8],
+		_: &OuterCtx,
+	) -> Result<Option<(Self, Option<SerializedTasks>)>, rmp_serde::decode::Error> {
+		let SaveState {
+			location_id,
+			file_path_ids,
+			tasks_dispatched,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		} = rmp_serde::from_slice::<SaveState>(serialized_job)?;
+
+		Ok(Some((
+			Self {
+				location_id,
+				file_path_ids,
+				tasks_dispatched,
+				metadata,
+				errors,
+				pending_tasks_on_resume: Vec::new(),
+				tasks_for_shutdown: Vec::new(),
+			},
+			tasks_for_shutdown_bytes,
+		)))
+	}
+}
@@ -0,0 +1,590 @@
+use crate::{
+	file_system,
+	job_system::{
+		job::{Job, JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		utils::cancel_pending_tasks,
+		DispatcherError, JobErrorOrDispatcherError, SerializableJob, SerializedTasks,
+	},
+	Error, JobContext, JobName, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_prisma::prisma::{file_path, location};
+use sd_task_system::{
+	AnyTaskOutput, IntoTask, SerializableTask, Task, TaskDispatcher, TaskHandle, TaskId,
+	TaskOutput, TaskStatus,
+};
+use sd_utils::u64_to_frontend;
+
+use std::{
+	collections::HashMap,
+	hash::{Hash, Hasher},
+	mem,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use futures_concurrency::future::TryJoin;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::fs;
+use tracing::{debug, instrument, trace, warn, Level};
+
+use super::{
+	get_location_path, get_many_files_datas,
+	tasks::{
+		file_eraser::{self, ErasedFile},
+		FileEraser,
+	},
+	walk_directory, NonCriticalFileSystemError, BATCH_SIZE,
+};
+
+/// File systems that write modified blocks to new places on disk, leaving the old contents behind
+const COPY_ON_WRITE_FILE_SYSTEMS: [&str; 5] = ["apfs", "btrfs", "zfs", "refs", "bcachefs"];
+
+/// How many erased files are kept in the job report, the others are only counted
+const MAX_SAMPLED_FILES: usize = 100;
+
+/// A mounted volume, used to warn when the erased files live where overwriting them in place
+/// can't guarantee that their contents are gone
+#[derive(Debug, Clone)]
+pub struct EraseVolume {
+	pub mount_point: PathBuf,
+	pub file_system: Option<String>,
+	pub is_ssd: bool,
+}
+
+impl EraseVolume {
+	fn unreliable_erasure_reason(&self) -> Option<String> {
+		if let Some(file_system) = self.file_system.as_deref().filter(|file_system| {
+			COPY_ON_WRITE_FILE_SYSTEMS
+				.iter()
+				.any(|cow| cow.eq_ignore_ascii_case(file_system))
+		}) {
+			Some(format!(
+				"{file_system} is a copy-on-write file system, previous file contents may persist"
+			))
+		} else if self.is_ssd {
+			Some(
+				"volume is backed by an SSD, wear leveling may keep previous file contents"
+					.to_string(),
+			)
+		} else {
+			None
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Eraser {
+	// Received arguments
+	location_id: location::id::Type,
+	file_path_ids: Vec<file_path::id::Type>,
+	passes: u32,
+	rename_before_unlink: bool,
+	/// Only needed to check the location volume before dispatching tasks, so it isn't serialized
+	volumes: Vec<EraseVolume>,
+
+	// Inner state
+	tasks_dispatched: bool,
+	/// Directories are only removed after all their files were erased
+	directories_to_remove: Vec<PathBuf>,
+
+	// Run data
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	// On shutdown data
+	pending_tasks_on_resume: Vec<TaskHandle<Error>>,
+	tasks_for_shutdown: Vec<Box<dyn Task<Error>>>,
+}
+
+impl Hash for Eraser {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location_id.hash(state);
+		self.file_path_ids.hash(state);
+		self.passes.hash(state);
+		self.rename_before_unlink.hash(state);
+	}
+}
+
+impl Job for Eraser {
+	const NAME: JobName = JobName::Erase;
+
+	async fn resume_tasks<OuterCtx: OuterContext>(
+		&mut self,
+		dispatcher: &JobTaskDispatcher,
+		_: &impl JobContext<OuterCtx>,
+		SerializedTasks(serialized_tasks): SerializedTasks,
+	) -> Result<(), Error> {
+		if let Ok(tasks) = dispatcher
+			.dispatch_many_boxed(
+				rmp_serde::from_slice::<Vec<Vec<u8>>>(&serialized_tasks)
+					.map_err(file_system::Error::from)?
+					.into_iter()
+					.map(|task_bytes| async move {
+						FileEraser::deserialize(&task_bytes, ())
+							.await
+							.map(IntoTask::into_task)
+					})
+					.collect::<Vec<_>>()
+					.try_join()
+					.await
+					.map_err(file_system::Error::from)?,
+			)
+			.await
+		{
+			self.pending_tasks_on_resume = tasks;
+		} else {
+			warn!("Failed to dispatch tasks to resume as job was already canceled");
+		}
+
+		Ok(())
+	}
+
+	#[instrument(
+		skip_all,
+		fields(
+			location_id = self.location_id,
+			file_paths_count = self.file_path_ids.len(),
+			passes = self.passes,
+			rename_before_unlink = self.rename_before_unlink,
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let mut pending_running_tasks = FuturesUnordered::new();
+
+		match self
+			.init_or_resume(&mut pending_running_tasks, &ctx, &dispatcher)
+			.await
+		{
+			Ok(()) => { /* Everything is awesome! */ }
+			Err(JobErrorOrDispatcherError::JobError(e)) => {
+				return Err(e.into());
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::JobCanceled(_))) => {
+				return Ok(self.cancel_job(&mut pending_running_tasks).await);
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::Shutdown(tasks))) => {
+				self.tasks_for_shutdown.extend(tasks);
+
+				if pending_running_tasks.is_empty() {
+					// If no task managed to be dispatched, we can just shutdown
+					// otherwise we have to process handles below and wait for them to be shutdown too
+					return Ok(ReturnStatus::Shutdown(
+						SerializableJob::<OuterCtx>::serialize(self).await,
+					));
+				}
+			}
+		}
+
+		while let Some(task) = pending_running_tasks.next().await {
+			match task {
+				Ok(TaskStatus::Done((task_id, TaskOutput::Out(out)))) => {
+					self.process_task_output(task_id, out, &ctx).await;
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					warn!(%task_id, "Task returned an empty output");
+				}
+
+				Ok(TaskStatus::Shutdown(task)) => {
+					self.tasks_for_shutdown.push(task);
+				}
+
+				Ok(TaskStatus::Error(e)) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&mut pending_running_tasks).await);
+				}
+
+				Err(e) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e.into());
+				}
+			}
+		}
+
+		if !self.tasks_for_shutdown.is_empty() {
+			return Ok(ReturnStatus::Shutdown(
+				SerializableJob::<OuterCtx>::serialize(self).await,
+			));
+		}
+
+		// From this point onward, we are done with the job and it can't be interrupted anymore
+		let Self {
+			directories_to_remove,
+			mut metadata,
+			mut errors,
+			..
+		} = self;
+
+		for directory in directories_to_remove {
+			trace!(path = %directory.display(), "Removing erased directory;");
+			if let Err(e) = fs::remove_dir_all(&directory).await {
+				errors.push(NonCriticalFileSystemError::Erase(directory, e.to_string()).into());
+			} else {
+				metadata.removed_directories += 1;
+			}
+		}
+
+		ctx.invalidate_query("search.paths");
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+}
+
+impl Eraser {
+	#[must_use]
+	pub fn new(
+		location_id: location::id::Type,
+		file_path_ids: Vec<file_path::id::Type>,
+		passes: u32,
+		rename_before_unlink: bool,
+	) -> Self {
+		Self {
+			location_id,
+			file_path_ids,
+			passes,
+			rename_before_unlink,
+			volumes: Vec::new(),
+			tasks_dispatched: false,
+			directories_to_remove: Vec::new(),
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+			pending_tasks_on_resume: Vec::new(),
+			tasks_for_shutdown: Vec::new(),
+		}
+	}
+
+	/// Mounted volumes to check for copy-on-write file systems or SSDs, where a secure erasure
+	/// can't be guaranteed, being reported as non critical errors
+	#[must_use]
+	pub fn with_volumes(mut self, volumes: impl IntoIterator<Item = EraseVolume>) -> Self {
+		self.volumes = volumes.into_iter().collect();
+		self
+	}
+
+	async fn init_or_resume<OuterCtx: OuterContext>(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+	) -> Result<(), JobErrorOrDispatcherError<file_system::Error>> {
+		if self.tasks_dispatched {
+			pending_running_tasks.extend(mem::take(&mut self.pending_tasks_on_resume));
+
+			ctx.progress(vec![
+				ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+				ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			])
+			.await;
+
+			debug!(
+				resuming_tasks_count = pending_running_tasks.len(),
+				"Resuming tasks for Eraser job;",
+			);
+
+			return Ok(());
+		}
+
+		let db = ctx.db();
+
+		let location_path = get_location_path(db, self.location_id).await?;
+
+		if self.passes > 0 {
+			self.check_volume(&location_path);
+		}
+
+		let mut files_to_erase = vec![];
+
+		for file_data in get_many_files_datas(db, &location_path, &self.file_path_ids).await? {
+			match file_data.is_dir(&mut self.errors) {
+				Some(true) => {
+					let (_, files) = walk_directory(&file_data.full_path, &mut self.errors).await;
+					files_to_erase.extend(files.into_iter().map(|(path, _)| path));
+					self.directories_to_remove.push(file_data.full_path);
+				}
+				Some(false) => files_to_erase.push(file_data.full_path),
+				None => {}
+			}
+		}
+
+		self.metadata.total_files = files_to_erase.len() as u64;
+
+		let tasks = files_to_erase
+			.into_iter()
+			.chunks(BATCH_SIZE)
+			.into_iter()
+			.map(|chunk| FileEraser::new(chunk, self.passes, self.rename_before_unlink))
+			.collect::<Vec<_>>();
+
+		#[allow(clippy::cast_possible_truncation)]
+		{
+			// SAFETY: we know that `tasks.len()` is a valid u32 as we wouldn't dispatch more than `u32::MAX` tasks
+			self.metadata.total_tasks = tasks.len() as u32;
+		}
+
+		self.tasks_dispatched = true;
+
+		ctx.progress(vec![
+			ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+			ProgressUpdate::Message(format!("Erasing {} files", self.metadata.total_files)),
+		])
+		.await;
+
+		pending_running_tasks.extend(dispatcher.dispatch_many(tasks).await?);
+
+		Ok(())
+	}
+
+	fn check_volume(&mut self, location_path: &Path) {
+		// The volume with the longest mount point containing the location is the one it lives in
+		let Some(volume) = self
+			.volumes
+			.iter()
+			.filter(|volume| location_path.starts_with(&volume.mount_point))
+			.max_by_key(|volume| volume.mount_point.components().count())
+		else {
+			return;
+		};
+
+		if let Some(reason) = volume.unreliable_erasure_reason() {
+			warn!(
+				location_path = %location_path.display(),
+				mount_point = %volume.mount_point.display(),
+				%reason,
+				"Secure erasure can't be guaranteed;",
+			);
+
+			self.errors.push(
+				NonCriticalFileSystemError::UnreliableErasure(location_path.to_path_buf(), reason)
+					.into(),
+			);
+		}
+	}
+
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		task_id: TaskId,
+		any_task_output: Box<dyn AnyTaskOutput>,
+		ctx: &impl JobContext<OuterCtx>,
+	) {
+		let file_eraser::Output {
+			erased,
+			erase_time,
+			errors,
+		} = *any_task_output
+			.downcast::<file_eraser::Output>()
+			.expect("Eraser job only dispatches FileEraser tasks");
+
+		self.metadata.erased_files += erased.len() as u64;
+		self.metadata.bytes_overwritten += erased
+			.iter()
+			.map(|erased_file| erased_file.bytes_overwritten)
+			.sum::<u64>();
+
+		let sample_room = MAX_SAMPLED_FILES.saturating_sub(self.metadata.sampled_files.len());
+		self.metadata
+			.sampled_files
+			.extend(erased.into_iter().take(sample_room));
+		self.metadata.mean_erase_time += erase_time;
+		self.metadata.completed_tasks += 1;
+
+		if !errors.is_empty() {
+			warn!(?errors, "Non critical errors while erasing files;");
+			self.errors.extend(errors);
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			ProgressUpdate::Message(format!(
+				"Erased {} of {} files",
+				self.metadata.erased_files, self.metadata.total_files
+			)),
+		])
+		.await;
+
+		debug!(
+			%task_id,
+			"Processed ({}/{}) eraser tasks, took: {erase_time:?};",
+			self.metadata.completed_tasks, self.metadata.total_tasks,
+		);
+	}
+
+	async fn cancel_job(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+	) -> ReturnStatus {
+		cancel_pending_tasks(pending_running_tasks).await;
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	location_id: location::id::Type,
+	file_path_ids: Vec<file_path::id::Type>,
+	passes: u32,
+	rename_before_unlink: bool,
+
+	tasks_dispatched: bool,
+	directories_to_remove: Vec<PathBuf>,
+
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	tasks_for_shutdown_bytes: Option<SerializedTasks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	total_files: u64,
+	erased_files: u64,
+	removed_directories: u64,
+	mean_erase_time: Duration,
+	/// Random bytes written over all erased files
+	bytes_overwritten: u64,
+	total_tasks: u32,
+	completed_tasks: u32,
+	/// Results for the first erased files, the report would grow without bound if we kept all of them
+	sampled_files: Vec<ErasedFile>,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			total_files,
+			erased_files,
+			removed_directories,
+			mut mean_erase_time,
+			bytes_overwritten,
+			total_tasks,
+			completed_tasks,
+			sampled_files,
+		}: Metadata,
+	) -> Self {
+		// To avoid division by zero
+		mean_erase_time /= u32::max(completed_tasks, 1);
+
+		vec![ReportOutputMetadata::Metrics(HashMap::from([
+			("total_files".into(), json!(total_files)),
+			("erased_files".into(), json!(erased_files)),
+			("removed_directories".into(), json!(removed_directories)),
+			("mean_erase_time".into(), json!(mean_erase_time)),
+			("total_tasks".into(), json!(total_tasks)),
+			("completed_tasks".into(), json!(completed_tasks)),
+			(
+				"bytes_overwritten".into(),
+				json!(u64_to_frontend(bytes_overwritten)),
+			),
+			("sampled_files".into(), json!(sampled_files)),
+		]))]
+	}
+}
+
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for Eraser {
+	async fn serialize(self) -> Result<Option<Vec<u8>>, rmp_serde::encode::Error> {
+		let Self {
+			location_id,
+			file_path_ids,
+			passes,
+			rename_before_unlink,
+			tasks_dispatched,
+			directories_to_remove,
+			metadata,
+			errors,
+			tasks_for_shutdown,
+			..
+		} = self;
+
+		let serialized_tasks = tasks_for_shutdown
+			.into_iter()
+			.map(|task| async move {
+				task.downcast::<FileEraser>()
+					.expect("Eraser job only dispatches FileEraser tasks")
+					.serialize()
+					.await
+			})
+			.collect::<Vec<_>>()
+			.try_join()
+			.await?;
+
+		let tasks_for_shutdown_bytes = if serialized_tasks.is_empty() {
+			None
+		} else {
+			Some(SerializedTasks(rmp_serde::to_vec_named(&serialized_tasks)?))
+		};
+
+		rmp_serde::to_vec_named(&SaveState {
+			location_id,
+			file_path_ids,
+			passes,
+			rename_before_unlink,
+			tasks_dispatched,
+			directories_to_remove,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		})
+		.map(Some)
+	}
+
+	async fn deserialize(
+		serialized_job: &[u8],
+		_: &OuterCtx,
+	) -> Result<Option<(Self, Option<SerializedTasks>)>, rmp_serde::decode::Error> {
+		let SaveState {
+			location_id,
+			file_path_ids,
+			passes,
+			rename_before_unlink,
+			tasks_dispatched,
+			directories_to_remove,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		} = rmp_serde::from_slice::<SaveState>(serialized_job)?;
+
+		Ok(Some((
+			Self {
+				location_id,
+				file_path_ids,
+				passes,
+				rename_before_unlink,
+				volumes: Vec::new(),
+				tasks_dispatched,
+				directories_to_remove,
+				metadata,
+				errors,
+				pending_tasks_on_resume: Vec::new(),
+				tasks_for_shutdown: Vec::new(),
+			},
+			tasks_for_shutdown_bytes,
+		)))
+	}
+}
@@ -0,0 +1,326 @@
+use crate::NonCriticalError;
+
+use sd_core_file_path_helper::{FilePathError, IsolatedFilePathData};
+use sd_core_prisma_helpers::file_path_with_object;
+
+use sd_prisma::prisma::{file_path, location, PrismaClient};
+use sd_utils::{
+	db::{maybe_missing, MissingFieldError},
+	error::{FileIOError, NonUtf8PathError},
+};
+
+use std::{
+	ffi::OsStr,
+	path::{Path, PathBuf},
+};
+
+use once_cell::sync::Lazy;
+use prisma_client_rust::QueryError;
+use regex::Regex;
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::{fs, io};
+use tracing::trace;
+
+pub mod copier;
+pub mod deleter;
+pub mod eraser;
+pub mod mover;
+mod tasks;
+
+pub use copier::Copier;
+pub use deleter::Deleter;
+pub use eraser::{EraseVolume, Eraser};
+pub use mover::Mover;
+
+/// Maximum number of files that a single file system task will handle
+const BATCH_SIZE: usize = 100;
+
+static DUPLICATE_PATTERN: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r" \(\d+\)").expect("Failed to compile hardcoded regex"));
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	// Not Found errors
+	#[error("location not found: <id='{0}'>")]
+	LocationNotFound(location::id::Type),
+	#[error("file_path not in database: <id='{0}'>")]
+	FilePathIdNotFound(file_path::id::Type),
+
+	// User errors
+	#[error("no parent for path, which is supposed to be directory: <path='{}'>", .0.display())]
+	MissingParentPath(Box<Path>),
+	#[error("no stem on file path, but it's supposed to be a file: <path='{}'>", .0.display())]
+	MissingFileStem(Box<Path>),
+	#[error("failed to find an available name to avoid duplication: <path='{}'>", .0.display())]
+	FailedToFindAvailableName(Box<Path>),
+
+	// Internal Errors
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+	#[error("missing field on database: {0}")]
+	MissingField(#[from] MissingFieldError),
+	#[error("failed to deserialized stored tasks for job resume: {0}")]
+	DeserializeTasks(#[from] rmp_serde::decode::Error),
+	#[error(transparent)]
+	FilePathError(#[from] FilePathError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error(transparent)]
+	NonUtf8Path(#[from] NonUtf8PathError),
+	#[error(transparent)]
+	Sync(#[from] sd_core_sync::Error),
+}
+
+impl From<Error> for rspc::Error {
+	fn from(e: Error) -> Self {
+		match e {
+			Error::LocationNotFound(_) | Error::FilePathIdNotFound(_) => {
+				Self::with_cause(ErrorCode::NotFound, e.to_string(), e)
+			}
+
+			Error::MissingParentPath(_)
+			| Error::MissingFileStem(_)
+			| Error::FailedToFindAvailableName(_) => {
+				Self::with_cause(ErrorCode::BadRequest, e.to_string(), e)
+			}
+
+			_ => Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e),
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize, Type, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum NonCriticalFileSystemError {
+	#[error("file path without is_dir field: <file_path_id='{0}'>")]
+	FilePathWithoutIsDirField(file_path::id::Type),
+	#[error("failed to read directory <path='{}'>: {1}", .0.display())]
+	ReadDirectory(PathBuf, String),
+	#[error("failed to create directory <path='{}'>: {1}", .0.display())]
+	CreateDirectory(PathBuf, String),
+	#[error("failed to find an available name to avoid duplication <path='{}'>: {1}", .0.display())]
+	FindAvailableName(PathBuf, String),
+	#[error("action would overwrite another file: <path='{}'>", .0.display())]
+	WouldOverwrite(PathBuf),
+	#[error("failed to copy file <source='{}', target='{}'>: {2}", .0.display(), .1.display())]
+	Copy(PathBuf, PathBuf, String),
+	#[error("failed to move file <source='{}', target='{}'>: {2}", .0.display(), .1.display())]
+	Move(PathBuf, PathBuf, String),
+	#[error("failed to delete file <path='{}'>: {1}", .0.display())]
+	Delete(PathBuf, String),
+	#[error("failed to erase file <path='{}'>: {1}", .0.display())]
+	Erase(PathBuf, String),
+	#[error("secure erasure can't be guaranteed <path='{}'>: {1}", .0.display())]
+	UnreliableErasure(PathBuf, String),
+	#[error("failed to remove deleted file path from database <path='{}'>: {1}", .0.display())]
+	RemoveFromDatabase(PathBuf, String),
+}
+
+/// A `file_path` already fetched from database with its full path on disk resolved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileData {
+	pub file_path: file_path_with_object::Data,
+	pub full_path: PathBuf,
+}
+
+impl FileData {
+	pub(crate) fn is_dir(&self, errors: &mut Vec<NonCriticalError>) -> Option<bool> {
+		self.file_path.is_dir.or_else(|| {
+			errors.push(
+				NonCriticalFileSystemError::FilePathWithoutIsDirField(self.file_path.id).into(),
+			);
+			None
+		})
+	}
+
+	/// Build the file name that this file will have when copied or moved to another directory
+	fn target_file_name(&self) -> Result<String, Error> {
+		let name = maybe_missing(&self.file_path.name, "file_path.name")?;
+
+		Ok(
+			match (
+				*maybe_missing(&self.file_path.is_dir, "file_path.is_dir")?,
+				self.file_path.extension.as_deref(),
+			) {
+				(true, _) | (false, None | Some("")) => name.clone(),
+				(false, Some(extension)) => format!("{name}.{extension}"),
+			},
+		)
+	}
+}
+
+/// Get the [`FileData`] related to every `file_path_id`
+pub async fn get_many_files_datas(
+	db: &PrismaClient,
+	location_path: impl AsRef<Path> + Send,
+	file_path_ids: &[file_path::id::Type],
+) -> Result<Vec<FileData>, Error> {
+	let location_path = location_path.as_ref();
+
+	db._batch(
+		file_path_ids
+			.iter()
+			.map(|file_path_id| {
+				db.file_path()
+					.find_unique(file_path::id::equals(*file_path_id))
+					.include(file_path_with_object::include())
+			})
+			// FIXME:(fogodev -> Brendonovich) this collect is a workaround to a weird higher ranker lifetime error on
+			// the _batch function, it should be removed once the error is fixed
+			.collect::<Vec<_>>(),
+	)
+	.await?
+	.into_iter()
+	.zip(file_path_ids.iter())
+	.map(|(maybe_file_path, file_path_id)| {
+		maybe_file_path
+			.ok_or(Error::FilePathIdNotFound(*file_path_id))
+			.and_then(|file_path| {
+				Ok(FileData {
+					full_path: location_path.join(IsolatedFilePathData::try_from(&file_path)?),
+					file_path,
+				})
+			})
+	})
+	.collect()
+}
+
+pub async fn get_location_path(
+	db: &PrismaClient,
+	location_id: location::id::Type,
+) -> Result<PathBuf, Error> {
+	db.location()
+		.find_unique(location::id::equals(location_id))
+		.select(location::select!({ path }))
+		.exec()
+		.await?
+		.ok_or(Error::LocationNotFound(location_id))
+		.and_then(|location| {
+			maybe_missing(location.path, "location.path")
+				.map(PathBuf::from)
+				.map_err(Into::into)
+		})
+}
+
+pub fn append_digit_to_filename(
+	final_path: &mut PathBuf,
+	file_name: &str,
+	ext: Option<&str>,
+	current_int: u32,
+) {
+	let new_file_name = if let Some(found) = DUPLICATE_PATTERN.find_iter(file_name).last() {
+		&file_name[..found.start()]
+	} else {
+		file_name
+	};
+
+	if let Some(ext) = ext {
+		final_path.push(format!("{new_file_name} ({current_int}).{ext}"));
+	} else {
+		final_path.push(format!("{new_file_name} ({current_int})"));
+	}
+}
+
+pub async fn find_available_filename_for_duplicate(
+	target_path: impl AsRef<Path> + Send,
+) -> Result<PathBuf, Error> {
+	let target_path = target_path.as_ref();
+
+	let new_file_name = target_path
+		.file_stem()
+		.ok_or_else(|| Error::MissingFileStem(target_path.to_path_buf().into_boxed_path()))?
+		.to_str()
+		.ok_or_else(|| NonUtf8PathError(target_path.to_path_buf().into_boxed_path()))?;
+
+	let new_file_full_path_without_suffix = target_path
+		.parent()
+		.map(Path::to_path_buf)
+		.ok_or_else(|| Error::MissingParentPath(target_path.to_path_buf().into_boxed_path()))?;
+
+	for i in 1..u32::MAX {
+		let mut new_file_full_path_candidate = new_file_full_path_without_suffix.clone();
+
+		append_digit_to_filename(
+			&mut new_file_full_path_candidate,
+			new_file_name,
+			target_path.extension().and_then(OsStr::to_str),
+			i,
+		);
+
+		match fs::metadata(&new_file_full_path_candidate).await {
+			Ok(_) => {
+				// This candidate already exists, so we try the next one
+				continue;
+			}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {
+				trace!(
+					old_name = %target_path.display(),
+					new_name = %new_file_full_path_candidate.display(),
+					"duplicated file name, file renamed;",
+				);
+				return Ok(new_file_full_path_candidate);
+			}
+			Err(e) => return Err(FileIOError::from((new_file_full_path_candidate, e)).into()),
+		}
+	}
+
+	Err(Error::FailedToFindAvailableName(
+		target_path.to_path_buf().into_boxed_path(),
+	))
+}
+
+/// Recursively collect every directory and file (with its size) inside `root`, the root itself
+/// is not included. Directories that we fail to read are reported as non critical errors.
+pub(crate) async fn walk_directory(
+	root: impl AsRef<Path> + Send,
+	errors: &mut Vec<NonCriticalError>,
+) -> (Vec<PathBuf>, Vec<(PathBuf, u64)>) {
+	let mut to_walk = vec![root.as_ref().to_path_buf()];
+	let mut directories = vec![];
+	let mut files = vec![];
+
+	while let Some(current_dir) = to_walk.pop() {
+		let mut read_dir = match fs::read_dir(&current_dir).await {
+			Ok(read_dir) => read_dir,
+			Err(e) => {
+				errors.push(
+					NonCriticalFileSystemError::ReadDirectory(current_dir, e.to_string()).into(),
+				);
+				continue;
+			}
+		};
+
+		loop {
+			match read_dir.next_entry().await {
+				Ok(Some(entry)) => {
+					let path = entry.path();
+					match entry.metadata().await {
+						Ok(metadata) if metadata.is_dir() => {
+							directories.push(path.clone());
+							to_walk.push(path);
+						}
+						Ok(metadata) => files.push((path, metadata.len())),
+						Err(e) => errors.push(
+							NonCriticalFileSystemError::ReadDirectory(path, e.to_string()).into(),
+						),
+					}
+				}
+				Ok(None) => break,
+				Err(e) => {
+					errors.push(
+						NonCriticalFileSystemError::ReadDirectory(
+							current_dir.clone(),
+							e.to_string(),
+						)
+						.into(),
+					);
+					break;
+				}
+			}
+		}
+	}
+
+	(directories, files)
+}
@@ -0,0 +1,475 @@
+use crate::{
+	file_system,
+	job_system::{
+		job::{Job, JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		utils::cancel_pending_tasks,
+		DispatcherError, JobErrorOrDispatcherError, SerializableJob, SerializedTasks,
+	},
+	Error, JobContext, JobName, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_core_file_path_helper::join_location_relative_path;
+
+use sd_prisma::prisma::{file_path, location};
+use sd_task_system::{
+	AnyTaskOutput, IntoTask, SerializableTask, Task, TaskDispatcher, TaskHandle, TaskId,
+	TaskOutput, TaskStatus,
+};
+
+use std::{
+	collections::HashMap,
+	hash::{Hash, Hasher},
+	mem,
+	path::PathBuf,
+	time::Duration,
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use futures_concurrency::future::TryJoin;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, instrument, warn, Level};
+
+use super::{
+	get_location_path, get_many_files_datas,
+	tasks::{file_mover, FileMover},
+	BATCH_SIZE,
+};
+
+#[derive(Debug)]
+pub struct Mover {
+	// Received arguments
+	source_location_id: location::id::Type,
+	target_location_id: location::id::Type,
+	sources_file_path_ids: Vec<file_path::id::Type>,
+	target_location_relative_directory_path: PathBuf,
+
+	// Inner state
+	tasks_dispatched: bool,
+
+	// Run data
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	// On shutdown data
+	pending_tasks_on_resume: Vec<TaskHandle<Error>>,
+	tasks_for_shutdown: Vec<Box<dyn Task<Error>>>,
+}
+
+impl Hash for Mover {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.source_location_id.hash(state);
+		self.target_location_id.hash(state);
+		self.sources_file_path_ids.hash(state);
+		self.target_location_relative_directory_path.hash(state);
+	}
+}
+
+impl Job for Mover {
+	const NAME: JobName = JobName::Move;
+
+	async fn resume_tasks<OuterCtx: OuterContext>(
+		&mut self,
+		dispatcher: &JobTaskDispatcher,
+		_: &impl JobContext<OuterCtx>,
+		SerializedTasks(serialized_tasks): SerializedTasks,
+	) -> Result<(), Error> {
+		if let Ok(tasks) = dispatcher
+			.dispatch_many_boxed(
+				rmp_serde::from_slice::<Vec<Vec<u8>>>(&serialized_tasks)
+					.map_err(file_system::Error::from)?
+					.into_iter()
+					.map(|task_bytes| async move {
+						FileMover::deserialize(&task_bytes, ())
+							.await
+							.map(IntoTask::into_task)
+					})
+					.collect::<Vec<_>>()
+					.try_join()
+					.await
+					.map_err(file_system::Error::from)?,
+			)
+			.await
+		{
+			self.pending_tasks_on_resume = tasks;
+		} else {
+			warn!("Failed to dispatch tasks to resume as job was already canceled");
+		}
+
+		Ok(())
+	}
+
+	#[instrument(
+		skip_all,
+		fields(
+			source_location_id = self.source_location_id,
+			target_location_id = self.target_location_id,
+			target_directory = %self.target_location_relative_directory_path.display(),
+			sources_count = self.sources_file_path_ids.len(),
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let mut pending_running_tasks = FuturesUnordered::new();
+
+		match self
+			.init_or_resume(&mut pending_running_tasks, &ctx, &dispatcher)
+			.await
+		{
+			Ok(()) => { /* Everything is awesome! */ }
+			Err(JobErrorOrDispatcherError::JobError(e)) => {
+				return Err(e.into());
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::JobCanceled(_))) => {
+				return Ok(self.cancel_job(&mut pending_running_tasks).await);
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::Shutdown(tasks))) => {
+				self.tasks_for_shutdown.extend(tasks);
+
+				if pending_running_tasks.is_empty() {
+					// If no task managed to be dispatched, we can just shutdown
+					// otherwise we have to process handles below and wait for them to be shutdown too
+					return Ok(ReturnStatus::Shutdown(
+						SerializableJob::<OuterCtx>::serialize(self).await,
+					));
+				}
+			}
+		}
+
+		while let Some(task) = pending_running_tasks.next().await {
+			match task {
+				Ok(TaskStatus::Done((task_id, TaskOutput::Out(out)))) => {
+					self.process_task_output(task_id, out, &ctx).await;
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					warn!(%task_id, "Task returned an empty output");
+				}
+
+				Ok(TaskStatus::Shutdown(task)) => {
+					self.tasks_for_shutdown.push(task);
+				}
+
+				Ok(TaskStatus::Error(e)) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&mut pending_running_tasks).await);
+				}
+
+				Err(e) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e.into());
+				}
+			}
+		}
+
+		if !self.tasks_for_shutdown.is_empty() {
+			return Ok(ReturnStatus::Shutdown(
+				SerializableJob::<OuterCtx>::serialize(self).await,
+			));
+		}
+
+		// From this point onward, we are done with the job and it can't be interrupted anymore
+		let Self {
+			metadata, errors, ..
+		} = self;
+
+		ctx.invalidate_query("search.paths");
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+}
+
+impl Mover {
+	#[must_use]
+	pub fn new(
+		source_location_id: location::id::Type,
+		target_location_id: location::id::Type,
+		sources_file_path_ids: Vec<file_path::id::Type>,
+		target_location_relative_directory_path: PathBuf,
+	) -> Self {
+		Self {
+			source_location_id,
+			target_location_id,
+			sources_file_path_ids,
+			target_location_relative_directory_path,
+			tasks_dispatched: false,
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+			pending_tasks_on_resume: Vec::new(),
+			tasks_for_shutdown: Vec::new(),
+		}
+	}
+
+	async fn init_or_resume<OuterCtx: OuterContext>(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+	) -> Result<(), JobErrorOrDispatcherError<file_system::Error>> {
+		if self.tasks_dispatched {
+			pending_running_tasks.extend(mem::take(&mut self.pending_tasks_on_resume));
+
+			ctx.progress(vec![
+				ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+				ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			])
+			.await;
+
+			debug!(
+				resuming_tasks_count = pending_running_tasks.len(),
+				"Resuming tasks for Mover job;",
+			);
+
+			return Ok(());
+		}
+
+		let db = ctx.db();
+
+		let (sources_location_path, targets_location_path) = (
+			get_location_path(db, self.source_location_id),
+			get_location_path(db, self.target_location_id),
+		)
+			.try_join()
+			.await?;
+
+		let target_directory = join_location_relative_path(
+			&targets_location_path,
+			&self.target_location_relative_directory_path,
+		);
+
+		let entries = get_many_files_datas(db, &sources_location_path, &self.sources_file_path_ids)
+			.await?
+			.into_iter()
+			.map(|file_data| {
+				file_data
+					.target_file_name()
+					.map(|file_name| file_mover::MoveEntry {
+						source: file_data.full_path,
+						target: target_directory.join(file_name),
+					})
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		self.metadata.total_entries = entries.len() as u64;
+
+		let tasks = entries
+			.into_iter()
+			.chunks(BATCH_SIZE)
+			.into_iter()
+			.map(FileMover::new)
+			.collect::<Vec<_>>();
+
+		#[allow(clippy::cast_possible_truncation)]
+		{
+			// SAFETY: we know that `tasks.len()` is a valid u32 as we wouldn't dispatch more than `u32::MAX` tasks
+			self.metadata.total_tasks = tasks.len() as u32;
+		}
+
+		self.tasks_dispatched = true;
+
+		ctx.progress(vec![
+			ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+			ProgressUpdate::Message(format!("Moving {} files", self.metadata.total_entries)),
+		])
+		.await;
+
+		pending_running_tasks.extend(dispatcher.dispatch_many(tasks).await?);
+
+		Ok(())
+	}
+
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		task_id: TaskId,
+		any_task_output: Box<dyn AnyTaskOutput>,
+		ctx: &impl JobContext<OuterCtx>,
+	) {
+		let file_mover::Output {
+			moved_count,
+			move_time,
+			errors,
+		} = *any_task_output
+			.downcast::<file_mover::Output>()
+			.expect("Mover job only dispatches FileMover tasks");
+
+		self.metadata.moved_entries += moved_count;
+		self.metadata.mean_move_time += move_time;
+		self.metadata.completed_tasks += 1;
+
+		if !errors.is_empty() {
+			warn!(?errors, "Non critical errors while moving files;");
+			self.errors.extend(errors);
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			ProgressUpdate::Message(format!(
+				"Moved {} of {} files",
+				self.metadata.moved_entries, self.metadata.total_entries
+			)),
+		])
+		.await;
+
+		debug!(
+			%task_id,
+			"Processed ({}/{}) mover tasks, took: {move_time:?};",
+			self.metadata.completed_tasks, self.metadata.total_tasks,
+		);
+	}
+
+	async fn cancel_job(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+	) -> ReturnStatus {
+		cancel_pending_tasks(pending_running_tasks).await;
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	source_location_id: location::id::Type,
+	target_location_id: location::id::Type,
+	sources_file_path_ids: Vec<file_path::id::Type>,
+	target_location_relative_directory_path: PathBuf,
+
+	tasks_dispatched: bool,
+
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	tasks_for_shutdown_bytes: Option<SerializedTasks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	total_entries: u64,
+	moved_entries: u64,
+	mean_move_time: Duration,
+	total_tasks: u32,
+	completed_tasks: u32,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			total_entries,
+			moved_entries,
+			mut mean_move_time,
+			total_tasks,
+			completed_tasks,
+		}: Metadata,
+	) -> Self {
+		// To avoid division by zero
+		mean_move_time /= u32::max(completed_tasks, 1);
+
+		vec![ReportOutputMetadata::Metrics(HashMap::from([
+			("total_entries".into(), json!(total_entries)),
+			("moved_entries".into(), json!(moved_entries)),
+			("mean_move_time".into(), json!(mean_move_time)),
+			("total_tasks".into(), json!(total_tasks)),
+			("completed_tasks".into(), json!(completed_tasks)),
+		]))]
+	}
+}
+
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for Mover {
+	async fn serialize(self) -> Result<Option<Vec<u8>>, rmp_serde::encode::Error> {
+		let Self {
+			source_location_id,
+			target_location_id,
+			sources_file_path_ids,
+			target_location_relative_directory_path,
+			tasks_dispatched,
+			metadata,
+			errors,
+			tasks_for_shutdown,
+			..
+		} = self;
+
+		let serialized_tasks = tasks_for_shutdown
+			.into_iter()
+			.map(|task| async move {
+				task.downcast::<FileMover>()
+					.expect("Mover job only dispatches FileMover tasks")
+					.serialize()
+					.await
+			})
+			.collect::<Vec<_>>()
+			.try_join()
+			.await?;
+
+		let tasks_for_shutdown_bytes = if serialized_tasks.is_empty() {
+			None
+		} else {
+			Some(SerializedTasks(rmp_serde::to_vec_named(&serialized_tasks)?))
+		};
+
+		rmp_serde::to_vec_named(&SaveState {
+			source_location_id,
+			target_location_id,
+			sources_file_path_ids,
+			target_location_relative_directory_path,
+			tasks_dispatched,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		})
+		.map(Some)
+	}
+
+	async fn deserialize(
+		serialized_job: &[u8],
+		_: &OuterCtx,
+	) -> Result<Option<(Self, Option<SerializedTasks>)>, rmp_serde::decode::Error> {
+		let SaveState {
+			source_location_id,
+			target_location_id,
+			sources_file_path_ids,
+			target_location_relative_directory_path,
+			tasks_dispatched,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		} = rmp_serde::from_slice::<SaveState>(serialized_job)?;
+
+		Ok(Some((
+			Self {
+				source_location_id,
+				target_location_id,
+				sources_file_path_ids,
+				target_location_relative_directory_path,
+				tasks_dispatched,
+				metadata,
+				errors,
+				pending_tasks_on_resume: Vec::new(),
+				tasks_for_shutdown: Vec::new(),
+			},
+			tasks_for_shutdown_bytes,
+		)))
+	}
+}
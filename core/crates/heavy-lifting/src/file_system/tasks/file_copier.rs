@@ -0,0 +1,451 @@
+use crate::{file_system::NonCriticalFileSystemError, Error, NonCriticalError};
+
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, InterruptionKind, IntoAnyTaskOutput,
+	SerializableTask, Task, TaskId,
+};
+
+use std::{
+	collections::VecDeque,
+	io::SeekFrom,
+	mem,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+	fs::{self, File, OpenOptions},
+	io::{self, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+	time::Instant,
+};
+use tracing::{instrument, trace, warn, Level};
+
+/// Files up to this size are copied in a single go, bigger ones are copied in chunks
+/// so we're able to pause the task in the middle of a copy and resume it later
+const CHUNKED_COPY_THRESHOLD: u64 = 16 * 1024 * 1024; // 16 MiB
+
+const CHUNK_SIZE: usize = 4 * 1024 * 1024; // 4 MiB
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CopyEntry {
+	pub source: PathBuf,
+	pub target: PathBuf,
+	pub size: u64,
+}
+
+#[derive(Debug)]
+pub struct FileCopier {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	entries: VecDeque<CopyEntry>,
+
+	// Inner state
+	/// How many bytes of the first entry in the queue were already copied
+	current_entry_offset: u64,
+
+	// Out collector
+	output: Output,
+}
+
+/// [`FileCopier`] task output
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Output {
+	/// Number of files copied
+	pub copied_count: u64,
+	/// Number of bytes copied
+	pub copied_bytes: u64,
+	/// Time spent copying files
+	pub copy_time: Duration,
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+enum ChunkedCopyStatus {
+	Done,
+	Interrupted(InterruptionKind),
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for FileCopier {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	fn with_priority(&self) -> bool {
+		// Copy and paste operations are user driven, so they must complete as soon as possible
+		true
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(
+			task_id = %self.id,
+			entries_count = %self.entries.len(),
+			current_entry_offset = %self.current_entry_offset,
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			entries,
+			current_entry_offset,
+			output: Output {
+				copied_count,
+				copied_bytes,
+				copy_time,
+				errors,
+			},
+			..
+		} = self;
+
+		let start_time = Instant::now();
+
+		while let Some(CopyEntry {
+			source,
+			target,
+			size,
+		}) = entries.front()
+		{
+			if *size <= CHUNKED_COPY_THRESHOLD && *current_entry_offset == 0 {
+				match copy_whole_file(source, target).await {
+					Ok(copied) => {
+						*copied_count += 1;
+						*copied_bytes += copied;
+					}
+					Err(e) => errors.push(e.into()),
+				}
+			} else {
+				match copy_in_chunks(source, target, current_entry_offset, interrupter).await {
+					Ok(ChunkedCopyStatus::Done) => {
+						*copied_count += 1;
+						*copied_bytes += *current_entry_offset;
+					}
+
+					Ok(ChunkedCopyStatus::Interrupted(kind)) => {
+						*copy_time += start_time.elapsed();
+
+						return Ok(match kind {
+							InterruptionKind::Pause => {
+								trace!(
+									copied_bytes = *current_entry_offset,
+									"Paused in the middle of a file copy;"
+								);
+								ExecStatus::Paused
+							}
+							InterruptionKind::Cancel => {
+								// We don't want to leave a partial file behind
+								if let Err(e) = fs::remove_file(target).await {
+									warn!(
+										?e,
+										target = %target.display(),
+										"Failed to remove partially copied file on cancel;"
+									);
+								}
+								ExecStatus::Canceled
+							}
+						});
+					}
+
+					Err(e) => errors.push(e.into()),
+				}
+			}
+
+			entries.pop_front();
+			*current_entry_offset = 0;
+
+			check_interruption!(interrupter, start_time, copy_time);
+		}
+
+		*copy_time += start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl FileCopier {
+	#[must_use]
+	pub fn new(entries: impl IntoIterator<Item = CopyEntry>) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			entries: entries.into_iter().collect(),
+			current_entry_offset: 0,
+			output: Output::default(),
+		}
+	}
+}
+
+async fn ensure_target_parent(
+	source: &Path,
+	target: &Path,
+) -> Result<(), NonCriticalFileSystemError> {
+	if let Some(parent) = target.parent() {
+		fs::create_dir_all(parent).await.map_err(|e| {
+			NonCriticalFileSystemError::Copy(
+				source.to_path_buf(),
+				target.to_path_buf(),
+				e.to_string(),
+			)
+		})?;
+	}
+
+	Ok(())
+}
+
+async fn copy_whole_file(source: &Path, target: &Path) -> Result<u64, NonCriticalFileSystemError> {
+	match fs::metadata(target).await {
+		Ok(_) => {
+			return Err(NonCriticalFileSystemError::WouldOverwrite(
+				target.to_path_buf(),
+			))
+		}
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {
+			// Everything is awesome!
+		}
+		Err(e) => {
+			return Err(NonCriticalFileSystemError::Copy(
+				source.to_path_buf(),
+				target.to_path_buf(),
+				e.to_string(),
+			))
+		}
+	}
+
+	ensure_target_parent(source, target).await?;
+
+	trace!(
+		source = %source.display(),
+		target = %target.display(),
+		"Copying source -> target;",
+	);
+
+	fs::copy(source, target).await.map_err(|e| {
+		NonCriticalFileSystemError::Copy(source.to_path_buf(), target.to_path_buf(), e.to_string())
+	})
+}
+
+async fn copy_in_chunks(
+	source: &Path,
+	target: &Path,
+	offset: &mut u64,
+	interrupter: &Interrupter,
+) -> Result<ChunkedCopyStatus, NonCriticalFileSystemError> {
+	async fn inner(
+		source: &Path,
+		target: &Path,
+		offset: &mut u64,
+		interrupter: &Interrupter,
+	) -> io::Result<ChunkedCopyStatus> {
+		let mut source_file = File::open(source).await?;
+
+		let mut target_file = if *offset == 0 {
+			OpenOptions::new()
+				.write(true)
+				.create_new(true)
+				.open(target)
+				.await?
+		} else {
+			// Resuming a previously interrupted copy, so we discard anything written after the
+			// last offset that we know for sure was copied
+			let mut target_file = OpenOptions::new().write(true).open(target).await?;
+			target_file.set_len(*offset).await?;
+			target_file.seek(SeekFrom::Start(*offset)).await?;
+			source_file.seek(SeekFrom::Start(*offset)).await?;
+			target_file
+		};
+
+		let mut buffer = vec![0; CHUNK_SIZE].into_boxed_slice();
+
+		loop {
+			let read_count = source_file.read(&mut buffer).await?;
+			if read_count == 0 {
+				break;
+			}
+
+			target_file.write_all(&buffer[..read_count]).await?;
+			*offset += read_count as u64;
+
+			if let Some(kind) = interrupter.try_check_interrupt() {
+				target_file.flush().await?;
+				return Ok(ChunkedCopyStatus::Interrupted(kind));
+			}
+		}
+
+		target_file.flush().await?;
+
+		fs::set_permissions(target, source_file.metadata().await?.permissions()).await?;
+
+		Ok(ChunkedCopyStatus::Done)
+	}
+
+	if *offset == 0 {
+		ensure_target_parent(source, target).await?;
+	}
+
+	trace!(
+		source = %source.display(),
+		target = %target.display(),
+		offset = *offset,
+		"Copying source -> target in chunks;",
+	);
+
+	inner(source, target, offset, interrupter)
+		.await
+		.map_err(|e| match e.kind() {
+			io::ErrorKind::AlreadyExists => {
+				NonCriticalFileSystemError::WouldOverwrite(target.to_path_buf())
+			}
+			_ => NonCriticalFileSystemError::Copy(
+				source.to_path_buf(),
+				target.to_path_buf(),
+				e.to_string(),
+			),
+		})
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	id: TaskId,
+	entries: VecDeque<CopyEntry>,
+	current_entry_offset: u64,
+	output: Output,
+}
+
+impl SerializableTask<Error> for FileCopier {
+	type SerializeError = rmp_serde::encode::Error;
+
+	type DeserializeError = rmp_serde::decode::Error;
+
+	type DeserializeCtx = ();
+
+	async fn serialize(self) -> Result<Vec<u8>, Self::SerializeError> {
+		let Self {
+			id,
+			entries,
+			current_entry_offset,
+			output,
+		} = self;
+
+		rmp_serde::to_vec_named(&SaveState {
+			id,
+			entries,
+			current_entry_offset,
+			output,
+		})
+	}
+
+	async fn deserialize(
+		data: &[u8],
+		(): Self::DeserializeCtx,
+	) -> Result<Self, Self::DeserializeError> {
+		rmp_serde::from_slice(data).map(
+			|SaveState {
+			     id,
+			     entries,
+			     current_entry_offset,
+			     output,
+			 }| Self {
+				id,
+				entries,
+				current_entry_offset,
+				output,
+			},
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sd_task_system::{TaskOutput, TaskStatus, TaskSystem};
+
+	use tempfile::tempdir;
+	use tracing_test::traced_test;
+
+	async fn run_copier(entries: Vec<CopyEntry>) -> Output {
+		let system = TaskSystem::<Error>::new();
+
+		let handle = system.dispatch(FileCopier::new(entries)).await.unwrap();
+
+		let TaskStatus::Done((_, TaskOutput::Out(out))) = handle.await.unwrap() else {
+			panic!("unexpected task status");
+		};
+
+		system.shutdown().await;
+
+		*out.downcast::<Output>().unwrap()
+	}
+
+	#[tokio::test]
+	#[traced_test]
+	async fn copy_small_and_chunked_files() {
+		let root = tempdir().unwrap();
+		let small_source = root.path().join("small.txt");
+		let big_source = root.path().join("big.bin");
+
+		fs::write(&small_source, b"spacedrive").await.unwrap();
+
+		#[allow(clippy::cast_possible_truncation)]
+		let big_contents = (0..(CHUNKED_COPY_THRESHOLD as usize + CHUNK_SIZE / 2))
+			.map(|i| (i % 251) as u8)
+			.collect::<Vec<_>>();
+		fs::write(&big_source, &big_contents).await.unwrap();
+
+		let small_target = root.path().join("target").join("small.txt");
+		let big_target = root.path().join("target").join("nested").join("big.bin");
+
+		let output = run_copier(vec![
+			CopyEntry {
+				source: small_source,
+				target: small_target.clone(),
+				size: 10,
+			},
+			CopyEntry {
+				source: big_source,
+				target: big_target.clone(),
+				size: big_contents.len() as u64,
+			},
+		])
+		.await;
+
+		assert!(output.errors.is_empty(), "{:#?}", output.errors);
+		assert_eq!(output.copied_count, 2);
+		assert_eq!(output.copied_bytes, 10 + big_contents.len() as u64);
+		assert_eq!(fs::read(&small_target).await.unwrap(), b"spacedrive");
+		assert_eq!(fs::read(&big_target).await.unwrap(), big_contents);
+	}
+
+	#[tokio::test]
+	#[traced_test]
+	async fn never_overwrite_existing_files() {
+		let root = tempdir().unwrap();
+		let source = root.path().join("source.txt");
+		let target = root.path().join("target.txt");
+
+		fs::write(&source, b"new contents").await.unwrap();
+		fs::write(&target, b"old contents").await.unwrap();
+
+		let output = run_copier(vec![
+			CopyEntry {
+				source: source.clone(),
+				target: target.clone(),
+				size: 12,
+			},
+			CopyEntry {
+				source: root.path().join("missing.txt"),
+				target: root.path().join("missing_copy.txt"),
+				size: 0,
+			},
+		])
+		.await;
+
+		assert_eq!(output.copied_count, 0);
+		assert_eq!(output.errors.len(), 2);
+		assert_eq!(fs::read(&target).await.unwrap(), b"old contents");
+	}
+}
@@ -0,0 +1,243 @@
+use crate::{file_system::NonCriticalFileSystemError, Error, NonCriticalError};
+
+use sd_core_sync::Manager as SyncManager;
+
+use sd_prisma::{
+	prisma::{file_path, PrismaClient},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, IntoAnyTaskOutput, SerializableTask, Task, TaskId,
+};
+
+use std::{collections::VecDeque, mem, path::PathBuf, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io, time::Instant};
+use tracing::{instrument, trace, warn, Level};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteEntry {
+	pub file_path_id: file_path::id::Type,
+	pub file_path_pub_id: file_path::pub_id::Type,
+	pub full_path: PathBuf,
+	pub is_dir: bool,
+}
+
+#[derive(Debug)]
+pub struct FileDeleter {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	entries: VecDeque<DeleteEntry>,
+
+	// Inner state
+	/// Entries that were already gone from the file system, so we only need to remove them from db
+	not_found_on_disk: Vec<DeleteEntry>,
+
+	// Out collector
+	output: Output,
+
+	// Dependencies
+	db: Arc<PrismaClient>,
+	sync: Arc<SyncManager>,
+}
+
+/// [`FileDeleter`] task output
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Output {
+	/// Number of files and directories deleted from the file system
+	pub deleted_count: u64,
+	/// Number of `file_path`s removed from database because they were already missing on disk
+	pub removed_from_db_count: u64,
+	/// Time spent deleting files
+	pub delete_time: Duration,
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for FileDeleter {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	fn with_priority(&self) -> bool {
+		// Deletions are user driven, so they must complete as soon as possible
+		true
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(task_id = %self.id, entries_count = %self.entries.len()),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			entries,
+			not_found_on_disk,
+			output:
+				Output {
+					deleted_count,
+					removed_from_db_count,
+					delete_time,
+					errors,
+				},
+			db,
+			sync,
+			..
+		} = self;
+
+		let start_time = Instant::now();
+
+		while let Some(entry) = entries.pop_front() {
+			trace!(path = %entry.full_path.display(), is_dir = entry.is_dir, "Deleting;");
+
+			match if entry.is_dir {
+				fs::remove_dir_all(&entry.full_path).await
+			} else {
+				fs::remove_file(&entry.full_path).await
+			} {
+				Ok(()) => *deleted_count += 1,
+
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {
+					warn!(
+						path = %entry.full_path.display(),
+						"File not found in the file system, will remove from database;",
+					);
+					not_found_on_disk.push(entry);
+				}
+
+				Err(e) => errors.push(
+					NonCriticalFileSystemError::Delete(entry.full_path, e.to_string()).into(),
+				),
+			}
+
+			check_interruption!(interrupter, start_time, delete_time);
+		}
+
+		if !not_found_on_disk.is_empty() {
+			let to_remove = mem::take(not_found_on_disk);
+
+			match remove_from_db(&to_remove, db, sync).await {
+				Ok(count) => *removed_from_db_count += count,
+				Err(e) => errors.extend(to_remove.into_iter().map(|entry| {
+					NonCriticalFileSystemError::RemoveFromDatabase(entry.full_path, e.to_string())
+						.into()
+				})),
+			}
+		}
+
+		*delete_time += start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl FileDeleter {
+	#[must_use]
+	pub fn new(
+		entries: impl IntoIterator<Item = DeleteEntry>,
+		db: Arc<PrismaClient>,
+		sync: Arc<SyncManager>,
+	) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			entries: entries.into_iter().collect(),
+			not_found_on_disk: Vec::new(),
+			output: Output::default(),
+			db,
+			sync,
+		}
+	}
+}
+
+async fn remove_from_db(
+	to_remove: &[DeleteEntry],
+	db: &PrismaClient,
+	sync: &SyncManager,
+) -> Result<u64, sd_core_sync::Error> {
+	let (sync_params, db_params): (Vec<_>, Vec<_>) = to_remove
+		.iter()
+		.map(|entry| {
+			(
+				sync.shared_delete(prisma_sync::file_path::SyncId {
+					pub_id: entry.file_path_pub_id.clone(),
+				}),
+				entry.file_path_id,
+			)
+		})
+		.unzip();
+
+	sync.write_ops(
+		db,
+		(
+			sync_params,
+			db.file_path()
+				.delete_many(vec![file_path::id::in_vec(db_params)]),
+		),
+	)
+	.await
+	.map(
+		#[allow(clippy::cast_sign_loss)]
+		|count| count as u64,
+	)
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	id: TaskId,
+	entries: VecDeque<DeleteEntry>,
+	not_found_on_disk: Vec<DeleteEntry>,
+	output: Output,
+}
+
+impl SerializableTask<Error> for FileDeleter {
+	type SerializeError = rmp_serde::encode::Error;
+
+	type DeserializeError = rmp_serde::decode::Error;
+
+	type DeserializeCtx = (Arc<PrismaClient>, Arc<SyncManager>);
+
+	async fn serialize(self) -> Result<Vec<u8>, Self::SerializeError> {
+		let Self {
+			id,
+			entries,
+			not_found_on_disk,
+			output,
+			..
+		} = self;
+
+		rmp_serde::to_vec_named(&SaveState {
+			id,
+			entries,
+			not_found_on_disk,
+			output,
+		})
+	}
+
+	async fn deserialize(
+		data: &[u8],
+		(db, sync): Self::DeserializeCtx,
+	) -> Result<Self, Self::DeserializeError> {
+		rmp_serde::from_slice(data).map(
+			|SaveState {
+			     id,
+			     entries,
+			     not_found_on_disk,
+			     output,
+			 }| Self {
+				id,
+				entries,
+				not_found_on_disk,
+				output,
+				db,
+				sync,
+			},
+		)
+	}
+}
@@ -0,0 +1,355 @@
+use crate::{file_system::NonCriticalFileSystemError, Error, NonCriticalError};
+
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, IntoAnyTaskOutput, SerializableTask, Task, TaskId,
+};
+
+use std::{
+	collections::VecDeque,
+	mem,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+	fs::{self, File, OpenOptions},
+	io,
+	time::Instant,
+};
+use tracing::{instrument, trace, Level};
+use uuid::Uuid;
+
+/// How many times a file is renamed to a random name before being unlinked
+const RENAME_PASSES: usize = 3;
+
+#[derive(Debug)]
+pub struct FileEraser {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	files: VecDeque<PathBuf>,
+	passes: u32,
+	rename_before_unlink: bool,
+
+	// Out collector
+	output: Output,
+}
+
+/// A file successfully erased
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErasedFile {
+	pub path: PathBuf,
+	/// Overwrite passes completed, each one followed by a fsync
+	pub passes: u32,
+	/// Random bytes written to the file over all passes
+	pub bytes_overwritten: u64,
+	/// If the file was renamed to random names before being unlinked
+	pub renamed: bool,
+}
+
+/// [`FileEraser`] task output
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Output {
+	/// Every file successfully erased by this task
+	pub erased: Vec<ErasedFile>,
+	/// Time spent erasing files
+	pub erase_time: Duration,
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for FileEraser {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	fn with_priority(&self) -> bool {
+		// Erasures are user driven, so they must complete as soon as possible
+		true
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(task_id = %self.id, files_count = %self.files.len(), passes = %self.passes),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			files,
+			passes,
+			rename_before_unlink,
+			output: Output {
+				erased,
+				erase_time,
+				errors,
+			},
+			..
+		} = self;
+
+		let start_time = Instant::now();
+
+		while let Some(path) = files.pop_front() {
+			match erase_file(&path, *passes, *rename_before_unlink).await {
+				Ok(erased_file) => erased.push(erased_file),
+				Err(e) => {
+					errors.push(NonCriticalFileSystemError::Erase(path, e.to_string()).into());
+				}
+			}
+
+			check_interruption!(interrupter, start_time, erase_time);
+		}
+
+		*erase_time += start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl FileEraser {
+	#[must_use]
+	pub fn new(
+		files: impl IntoIterator<Item = PathBuf>,
+		passes: u32,
+		rename_before_unlink: bool,
+	) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			files: files.into_iter().collect(),
+			passes,
+			rename_before_unlink,
+			output: Output::default(),
+		}
+	}
+}
+
+async fn erase_file(
+	path: &Path,
+	passes: u32,
+	rename_before_unlink: bool,
+) -> Result<ErasedFile, io::Error> {
+	let bytes_overwritten = {
+		let mut file = OpenOptions::new().read(true).write(true).open(path).await?;
+
+		trace!(path = %path.display(), passes, "Overwriting file;");
+
+		let bytes_overwritten = overwrite_file(&mut file, passes).await?;
+
+		file.set_len(0).await?;
+		file.sync_all().await?;
+
+		bytes_overwritten
+	};
+
+	let path_to_remove = if rename_before_unlink {
+		rename_randomly(path).await?
+	} else {
+		path.to_path_buf()
+	};
+
+	fs::remove_file(&path_to_remove).await?;
+
+	Ok(ErasedFile {
+		path: path.to_path_buf(),
+		passes,
+		bytes_overwritten,
+		renamed: rename_before_unlink,
+	})
+}
+
+/// Overwrites the whole file with random bytes `passes` times, returning how many bytes were written
+async fn overwrite_file(file: &mut File, passes: u32) -> Result<u64, io::Error> {
+	let size = usize::try_from(file.metadata().await?.len())
+		.map_err(|e| io::Error::new(io::ErrorKind::Unsupported, e))?;
+
+	let mut bytes_overwritten = 0;
+
+	for _ in 0..passes {
+		bytes_overwritten += sd_crypto::erase::erase(file, size, 1)
+			.await
+			.map_err(io::Error::other)? as u64;
+
+		// Making sure each pass reaches the disk, otherwise the OS could coalesce them
+		file.sync_all().await?;
+	}
+
+	Ok(bytes_overwritten)
+}
+
+/// Renames the file a few times in the same directory, so its original name doesn't linger in
+/// the directory entries after unlinking it, returning the last random path
+async fn rename_randomly(path: &Path) -> Result<PathBuf, io::Error> {
+	let parent = path
+		.parent()
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path without parent"))?;
+
+	let mut current = path.to_path_buf();
+
+	for _ in 0..RENAME_PASSES {
+		let random = parent.join(Uuid::new_v4().simple().to_string());
+		fs::rename(&current, &random).await?;
+		current = random;
+	}
+
+	// Directories can't be opened for syncing on Windows, so the rename is only flushed on unix
+	#[cfg(unix)]
+	fs::File::open(parent).await?.sync_all().await?;
+
+	Ok(current)
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	id: TaskId,
+	files: VecDeque<PathBuf>,
+	passes: u32,
+	rename_before_unlink: bool,
+	output: Output,
+}
+
+impl SerializableTask<Error> for FileEraser {
+	type SerializeError = rmp_serde::encode::Error;
+
+	type DeserializeError = rmp_serde::decode::Error;
+
+	type DeserializeCtx = ();
+
+	async fn serialize(self) -> Result<Vec<u8>, Self::SerializeError> {
+		let Self {
+			id,
+			files,
+			passes,
+			rename_before_unlink,
+			output,
+		} = self;
+
+		rmp_serde::to_vec_named(&SaveState {
+			id,
+			files,
+			passes,
+			rename_before_unlink,
+			output,
+		})
+	}
+
+	async fn deserialize(
+		data: &[u8],
+		(): Self::DeserializeCtx,
+	) -> Result<Self, Self::DeserializeError> {
+		rmp_serde::from_slice(data).map(
+			|SaveState {
+			     id,
+			     files,
+			     passes,
+			     rename_before_unlink,
+			     output,
+			 }| Self {
+				id,
+				files,
+				passes,
+				rename_before_unlink,
+				output,
+			},
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sd_task_system::{TaskOutput, TaskStatus, TaskSystem};
+
+	use tempfile::tempdir;
+	use tracing_test::traced_test;
+
+	// Not a multiple of the erasure block size, so the last partial block is overwritten too
+	const FILE_SIZE: usize = 100_000;
+
+	fn contents() -> Vec<u8> {
+		(0..=250).cycle().take(FILE_SIZE).collect()
+	}
+
+	#[tokio::test]
+	#[traced_test]
+	async fn overwrite_contents() {
+		let root = tempdir().unwrap();
+		let path = root.path().join("secret.bin");
+
+		fs::write(&path, contents()).await.unwrap();
+
+		let mut file = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open(&path)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			overwrite_file(&mut file, 2).await.unwrap(),
+			2 * FILE_SIZE as u64
+		);
+		drop(file);
+
+		let overwritten = fs::read(&path).await.unwrap();
+		assert_eq!(overwritten.len(), FILE_SIZE);
+		assert_ne!(overwritten, contents());
+		// Random bytes can't keep a meaningful part of the original contents
+		assert!(
+			overwritten
+				.iter()
+				.zip(contents())
+				.filter(|(overwritten, original)| **overwritten == *original)
+				.count() < FILE_SIZE / 100
+		);
+	}
+
+	#[tokio::test]
+	#[traced_test]
+	async fn erase_and_remove_files() {
+		let root = tempdir().unwrap();
+		let paths = [root.path().join("a.bin"), root.path().join("b.bin")];
+
+		for path in &paths {
+			fs::write(path, contents()).await.unwrap();
+		}
+
+		let system = TaskSystem::<Error>::new();
+
+		let handle = system
+			.dispatch(FileEraser::new(paths.clone(), 3, true))
+			.await
+			.unwrap();
+
+		let TaskStatus::Done((_, TaskOutput::Out(out))) = handle.await.unwrap() else {
+			panic!("unexpected task status");
+		};
+
+		system.shutdown().await;
+
+		let output = *out.downcast::<Output>().unwrap();
+
+		assert!(output.errors.is_empty(), "{:#?}", output.errors);
+		assert_eq!(output.erased.len(), 2);
+
+		for erased_file in &output.erased {
+			assert!(paths.contains(&erased_file.path));
+			assert_eq!(erased_file.passes, 3);
+			assert_eq!(erased_file.bytes_overwritten, 3 * FILE_SIZE as u64);
+			assert!(erased_file.renamed);
+		}
+
+		// Neither the original files nor their random names are left behind
+		assert!(fs::read_dir(root.path())
+			.await
+			.unwrap()
+			.next_entry()
+			.await
+			.unwrap()
+			.is_none());
+	}
+}
@@ -0,0 +1,169 @@
+use crate::{file_system::NonCriticalFileSystemError, Error, NonCriticalError};
+
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, IntoAnyTaskOutput, SerializableTask, Task, TaskId,
+};
+
+use std::{collections::VecDeque, mem, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io, time::Instant};
+use tracing::{instrument, trace, Level};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveEntry {
+	pub source: PathBuf,
+	pub target: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct FileMover {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	entries: VecDeque<MoveEntry>,
+
+	// Out collector
+	output: Output,
+}
+
+/// [`FileMover`] task output
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Output {
+	/// Number of files and directories moved
+	pub moved_count: u64,
+	/// Time spent moving files
+	pub move_time: Duration,
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for FileMover {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	fn with_priority(&self) -> bool {
+		// Cut and paste operations are user driven, so they must complete as soon as possible
+		true
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(task_id = %self.id, entries_count = %self.entries.len()),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			entries,
+			output: Output {
+				moved_count,
+				move_time,
+				errors,
+			},
+			..
+		} = self;
+
+		let start_time = Instant::now();
+
+		while let Some(MoveEntry { source, target }) = entries.pop_front() {
+			if source == target {
+				trace!(path = %source.display(), "Source and target are the same, skipping;");
+				continue;
+			}
+
+			match fs::metadata(&target).await {
+				Ok(_) => errors.push(NonCriticalFileSystemError::WouldOverwrite(target).into()),
+
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {
+					trace!(
+						source = %source.display(),
+						target = %target.display(),
+						"Moving source -> target;",
+					);
+
+					if let Err(e) = fs::rename(&source, &target).await {
+						errors.push(
+							NonCriticalFileSystemError::Move(source, target, e.to_string()).into(),
+						);
+					} else {
+						*moved_count += 1;
+					}
+				}
+
+				Err(e) => {
+					errors.push(
+						NonCriticalFileSystemError::Move(source, target, e.to_string()).into(),
+					);
+				}
+			}
+
+			check_interruption!(interrupter, start_time, move_time);
+		}
+
+		*move_time += start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl FileMover {
+	#[must_use]
+	pub fn new(entries: impl IntoIterator<Item = MoveEntry>) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			entries: entries.into_iter().collect(),
+			output: Output::default(),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	id: TaskId,
+	entries: VecDeque<MoveEntry>,
+	output: Output,
+}
+
+impl SerializableTask<Error> for FileMover {
+	type SerializeError = rmp_serde::encode::Error;
+
+	type DeserializeError = rmp_serde::decode::Error;
+
+	type DeserializeCtx = ();
+
+	async fn serialize(self) -> Result<Vec<u8>, Self::SerializeError> {
+		let Self {
+			id,
+			entries,
+			output,
+		} = self;
+
+		rmp_serde::to_vec_named(&SaveState {
+			id,
+			entries,
+			output,
+		})
+	}
+
+	async fn deserialize(
+		data: &[u8],
+		(): Self::DeserializeCtx,
+	) -> Result<Self, Self::DeserializeError> {
+		rmp_serde::from_slice(data).map(
+			|SaveState {
+			     id,
+			     entries,
+			     output,
+			 }| Self {
+				id,
+				entries,
+				output,
+			},
+		)
+	}
+}
@@ -0,0 +1,9 @@
+pub mod file_copier;
+pub mod file_deleter;
+pub mod file_eraser;
+pub mod file_mover;
+
+pub use file_copier::FileCopier;
+pub use file_deleter::FileDeleter;
+pub use file_eraser::FileEraser;
+pub use file_mover::FileMover;
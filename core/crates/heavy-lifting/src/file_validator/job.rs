@@ -0,0 +1,547 @@
+use crate::{
+	file_validator,
+	job_system::{
+		job::{Job, JobReturn, JobTaskDispatcher, ReturnStatus},
+		report::ReportOutputMetadata,
+		utils::cancel_pending_tasks,
+		DispatcherError, JobErrorOrDispatcherError, SerializableJob, SerializedTasks,
+	},
+	utils::sub_path::maybe_get_iso_file_path_from_sub_path,
+	Error, JobContext, JobName, NonCriticalError, OuterContext, ProgressUpdate,
+};
+
+use sd_core_file_path_helper::IsolatedFilePathData;
+use sd_core_prisma_helpers::file_path_for_object_validator;
+
+use sd_prisma::prisma::{file_path, location, SortOrder};
+use sd_task_system::{
+	AnyTaskOutput, IntoTask, SerializableTask, Task, TaskDispatcher, TaskHandle, TaskId,
+	TaskOutput, TaskStatus,
+};
+use sd_utils::{db::maybe_missing, u64_to_frontend};
+
+use std::{
+	collections::HashMap,
+	hash::{Hash, Hasher},
+	mem,
+	path::PathBuf,
+	sync::Arc,
+	time::Duration,
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use futures_concurrency::future::TryJoin;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::time::Instant;
+use tracing::{debug, instrument, trace, warn, Level};
+
+use super::{
+	tasks::{checksummer, Checksummer},
+	CHUNK_SIZE,
+};
+
+/// The validator generates a full content checksum for every file in a location (or in a
+/// sub path of it) that still doesn't have one, storing it as the `file_path`'s integrity checksum
+#[derive(Debug)]
+pub struct FileValidator {
+	// Received arguments
+	location: Arc<location::Data>,
+	location_path: Arc<PathBuf>,
+	sub_path: Option<PathBuf>,
+
+	// Inner state
+	last_file_path_id: Option<file_path::id::Type>,
+	finished_searching: bool,
+
+	// Run data
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	// On shutdown data
+	pending_tasks_on_resume: Vec<TaskHandle<Error>>,
+	tasks_for_shutdown: Vec<Box<dyn Task<Error>>>,
+}
+
+impl Hash for FileValidator {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location.id.hash(state);
+		if let Some(ref sub_path) = self.sub_path {
+			sub_path.hash(state);
+		}
+	}
+}
+
+impl Job for FileValidator {
+	const NAME: JobName = JobName::FileValidator;
+
+	async fn resume_tasks<OuterCtx: OuterContext>(
+		&mut self,
+		dispatcher: &JobTaskDispatcher,
+		ctx: &impl JobContext<OuterCtx>,
+		SerializedTasks(serialized_tasks): SerializedTasks,
+	) -> Result<(), Error> {
+		if let Ok(tasks) = dispatcher
+			.dispatch_many_boxed(
+				rmp_serde::from_slice::<Vec<Vec<u8>>>(&serialized_tasks)
+					.map_err(file_validator::Error::from)?
+					.into_iter()
+					.map(|task_bytes| async move {
+						Checksummer::deserialize(
+							&task_bytes,
+							(Arc::clone(ctx.db()), Arc::clone(ctx.sync())),
+						)
+						.await
+						.map(IntoTask::into_task)
+					})
+					.collect::<Vec<_>>()
+					.try_join()
+					.await
+					.map_err(file_validator::Error::from)?,
+			)
+			.await
+		{
+			self.pending_tasks_on_resume = tasks;
+		} else {
+			warn!("Failed to dispatch tasks to resume as job was already canceled");
+		}
+
+		Ok(())
+	}
+
+	#[instrument(
+		skip_all,
+		fields(
+			location_id = self.location.id,
+			location_path = %self.location_path.display(),
+			sub_path = ?self.sub_path.as_ref().map(|path| path.display()),
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	async fn run<OuterCtx: OuterContext>(
+		mut self,
+		dispatcher: JobTaskDispatcher,
+		ctx: impl JobContext<OuterCtx>,
+	) -> Result<ReturnStatus, Error> {
+		let mut pending_running_tasks = FuturesUnordered::new();
+
+		match self
+			.init_or_resume(&mut pending_running_tasks, &ctx, &dispatcher)
+			.await
+		{
+			Ok(()) => { /* Everything is awesome! */ }
+			Err(JobErrorOrDispatcherError::JobError(e)) => {
+				return Err(e.into());
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::JobCanceled(_))) => {
+				return Ok(self.cancel_job(&mut pending_running_tasks).await);
+			}
+			Err(JobErrorOrDispatcherError::Dispatcher(DispatcherError::Shutdown(tasks))) => {
+				self.tasks_for_shutdown.extend(tasks);
+
+				if pending_running_tasks.is_empty() {
+					// If no task managed to be dispatched, we can just shutdown
+					// otherwise we have to process handles below and wait for them to be shutdown too
+					return Ok(ReturnStatus::Shutdown(
+						SerializableJob::<OuterCtx>::serialize(self).await,
+					));
+				}
+			}
+		}
+
+		while let Some(task) = pending_running_tasks.next().await {
+			match task {
+				Ok(TaskStatus::Done((task_id, TaskOutput::Out(out)))) => {
+					self.process_task_output(task_id, out, &ctx).await;
+				}
+
+				Ok(TaskStatus::Done((task_id, TaskOutput::Empty))) => {
+					warn!(%task_id, "Task returned an empty output");
+				}
+
+				Ok(TaskStatus::Shutdown(task)) => {
+					self.tasks_for_shutdown.push(task);
+				}
+
+				Ok(TaskStatus::Error(e)) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e);
+				}
+
+				Ok(TaskStatus::Canceled | TaskStatus::ForcedAbortion) => {
+					return Ok(self.cancel_job(&mut pending_running_tasks).await);
+				}
+
+				Err(e) => {
+					cancel_pending_tasks(&mut pending_running_tasks).await;
+
+					return Err(e.into());
+				}
+			}
+		}
+
+		if !self.tasks_for_shutdown.is_empty() {
+			return Ok(ReturnStatus::Shutdown(
+				SerializableJob::<OuterCtx>::serialize(self).await,
+			));
+		}
+
+		// From this point onward, we are done with the job and it can't be interrupted anymore
+		let Self {
+			metadata, errors, ..
+		} = self;
+
+		Ok(ReturnStatus::Completed(
+			JobReturn::builder()
+				.with_metadata(metadata)
+				.with_non_critical_errors(errors)
+				.build(),
+		))
+	}
+}
+
+impl FileValidator {
+	pub fn new(
+		location: location::Data,
+		sub_path: Option<PathBuf>,
+	) -> Result<Self, file_validator::Error> {
+		Ok(Self {
+			location_path: maybe_missing(&location.path, "location.path")
+				.map(PathBuf::from)
+				.map(Arc::new)?,
+			location: Arc::new(location),
+			sub_path,
+			last_file_path_id: None,
+			finished_searching: false,
+			metadata: Metadata::default(),
+			errors: Vec::new(),
+			pending_tasks_on_resume: Vec::new(),
+			tasks_for_shutdown: Vec::new(),
+		})
+	}
+
+	async fn init_or_resume<OuterCtx: OuterContext>(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+	) -> Result<(), JobErrorOrDispatcherError<file_validator::Error>> {
+		pending_running_tasks.extend(mem::take(&mut self.pending_tasks_on_resume));
+
+		if self.finished_searching {
+			ctx.progress(vec![
+				ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+				ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			])
+			.await;
+
+			debug!(
+				resuming_tasks_count = pending_running_tasks.len(),
+				"Resuming tasks for FileValidator job;",
+			);
+
+			return Ok(());
+		}
+
+		let start = Instant::now();
+
+		let maybe_sub_iso_file_path =
+			maybe_get_iso_file_path_from_sub_path::<file_validator::Error>(
+				self.location.id,
+				self.sub_path.as_ref(),
+				&*self.location_path,
+				ctx.db(),
+			)
+			.await?;
+
+		self.dispatch_checksummer_tasks(
+			maybe_sub_iso_file_path.as_ref(),
+			ctx,
+			dispatcher,
+			pending_running_tasks,
+		)
+		.await?;
+
+		self.finished_searching = true;
+		self.metadata.seeking_files_time += start.elapsed();
+
+		ctx.progress(vec![
+			ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+			ProgressUpdate::Message(format!(
+				"{} files to be validated",
+				self.metadata.total_found_files
+			)),
+		])
+		.await;
+
+		Ok(())
+	}
+
+	async fn dispatch_checksummer_tasks<OuterCtx: OuterContext>(
+		&mut self,
+		maybe_sub_iso_file_path: Option<&IsolatedFilePathData<'static>>,
+		ctx: &impl JobContext<OuterCtx>,
+		dispatcher: &JobTaskDispatcher,
+		pending_running_tasks: &FuturesUnordered<TaskHandle<Error>>,
+	) -> Result<(), JobErrorOrDispatcherError<file_validator::Error>> {
+		let db = ctx.db();
+
+		loop {
+			#[allow(clippy::cast_possible_wrap)]
+			// SAFETY: we know that CHUNK_SIZE is a valid i64
+			let file_paths = db
+				.file_path()
+				.find_many(sd_utils::chain_optional_iter(
+					[
+						file_path::location_id::equals(Some(self.location.id)),
+						file_path::is_dir::equals(Some(false)),
+						file_path::integrity_checksum::equals(None),
+					],
+					[
+						self.last_file_path_id.map(file_path::id::gt),
+						maybe_sub_iso_file_path.and_then(|iso_sub_path| {
+							iso_sub_path
+								.materialized_path_for_children()
+								.map(file_path::materialized_path::starts_with)
+						}),
+					],
+				))
+				.order_by(file_path::id::order(SortOrder::Asc))
+				.take(CHUNK_SIZE as i64)
+				.select(file_path_for_object_validator::select())
+				.exec()
+				.await
+				.map_err(file_validator::Error::from)?;
+
+			trace!(files_count = file_paths.len(), "Found files to validate;");
+
+			let Some(last_file_path) = file_paths.last() else {
+				// No other files to validate, we can break the loop
+				break;
+			};
+
+			self.last_file_path_id = Some(last_file_path.id);
+			self.metadata.total_found_files += file_paths.len() as u64;
+			self.metadata.total_tasks += 1;
+
+			ctx.progress(vec![
+				ProgressUpdate::TaskCount(u64::from(self.metadata.total_tasks)),
+				ProgressUpdate::Message(format!(
+					"Found {} files to validate",
+					self.metadata.total_found_files
+				)),
+			])
+			.await;
+
+			pending_running_tasks.push(
+				dispatcher
+					.dispatch(Checksummer::new(
+						self.location.id,
+						Arc::clone(&self.location_path),
+						file_paths,
+						Arc::clone(db),
+						Arc::clone(ctx.sync()),
+					))
+					.await?,
+			);
+		}
+
+		Ok(())
+	}
+
+	async fn process_task_output<OuterCtx: OuterContext>(
+		&mut self,
+		task_id: TaskId,
+		any_task_output: Box<dyn AnyTaskOutput>,
+		ctx: &impl JobContext<OuterCtx>,
+	) {
+		let checksummer::Output {
+			validated_count,
+			checksum_time,
+			save_db_time,
+			errors,
+		} = *any_task_output
+			.downcast::<checksummer::Output>()
+			.expect("FileValidator job only dispatches Checksummer tasks");
+
+		self.metadata.validated_files += validated_count;
+		self.metadata.mean_checksum_time += checksum_time;
+		self.metadata.mean_save_db_time += save_db_time;
+		self.metadata.completed_tasks += 1;
+
+		if !errors.is_empty() {
+			warn!(?errors, "Non critical errors while validating files;");
+			self.errors.extend(errors);
+		}
+
+		ctx.progress(vec![
+			ProgressUpdate::CompletedTaskCount(u64::from(self.metadata.completed_tasks)),
+			ProgressUpdate::Message(format!(
+				"Validated {} of {} files",
+				self.metadata.validated_files, self.metadata.total_found_files
+			)),
+		])
+		.await;
+
+		debug!(
+			%task_id,
+			"Processed ({}/{}) checksummer tasks, took: {:?};",
+			self.metadata.completed_tasks,
+			self.metadata.total_tasks,
+			checksum_time + save_db_time,
+		);
+	}
+
+	async fn cancel_job(
+		&mut self,
+		pending_running_tasks: &mut FuturesUnordered<TaskHandle<Error>>,
+	) -> ReturnStatus {
+		cancel_pending_tasks(pending_running_tasks).await;
+
+		ReturnStatus::Canceled(
+			JobReturn::builder()
+				.with_metadata(mem::take(&mut self.metadata))
+				.with_non_critical_errors(mem::take(&mut self.errors))
+				.build(),
+		)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	location: Arc<location::Data>,
+	location_path: Arc<PathBuf>,
+	sub_path: Option<PathBuf>,
+
+	last_file_path_id: Option<file_path::id::Type>,
+	finished_searching: bool,
+
+	metadata: Metadata,
+	errors: Vec<NonCriticalError>,
+
+	tasks_for_shutdown_bytes: Option<SerializedTasks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Metadata {
+	mean_checksum_time: Duration,
+	mean_save_db_time: Duration,
+	seeking_files_time: Duration,
+	total_found_files: u64,
+	validated_files: u64,
+	total_tasks: u32,
+	completed_tasks: u32,
+}
+
+impl From<Metadata> for Vec<ReportOutputMetadata> {
+	fn from(
+		Metadata {
+			mut mean_checksum_time,
+			mut mean_save_db_time,
+			seeking_files_time,
+			total_found_files,
+			validated_files,
+			total_tasks,
+			completed_tasks,
+		}: Metadata,
+	) -> Self {
+		// To avoid division by zero
+		mean_checksum_time /= u32::max(completed_tasks, 1);
+		mean_save_db_time /= u32::max(completed_tasks, 1);
+
+		vec![ReportOutputMetadata::Metrics(HashMap::from([
+			("mean_checksum_time".into(), json!(mean_checksum_time)),
+			("mean_save_db_time".into(), json!(mean_save_db_time)),
+			("seeking_files_time".into(), json!(seeking_files_time)),
+			(
+				"total_found_files".into(),
+				json!(u64_to_frontend(total_found_files)),
+			),
+			(
+				"validated_files".into(),
+				json!(u64_to_frontend(validated_files)),
+			),
+			("total_tasks".into(), json!(total_tasks)),
+			("completed_tasks".into(), json!(completed_tasks)),
+		]))]
+	}
+}
+
+impl<OuterCtx: OuterContext> SerializableJob<OuterCtx> for FileValidator {
+	async fn serialize(self) -> Result<Option<Vec<u8>>, rmp_serde::encode::Error> {
+		let Self {
+			location,
+			location_path,
+			sub_path,
+			last_file_path_id,
+			finished_searching,
+			metadata,
+			errors,
+			tasks_for_shutdown,
+			..
+		} = self;
+
+		let serialized_tasks = tasks_for_shutdown
+			.into_iter()
+			.map(|task| async move {
+				task.downcast::<Checksummer>()
+					.expect("FileValidator job only dispatches Checksummer tasks")
+					.serialize()
+					.await
+			})
+			.collect::<Vec<_>>()
+			.try_join()
+			.await?;
+
+		let tasks_for_shutdown_bytes = if serialized_tasks.is_empty() {
+			None
+		} else {
+			Some(SerializedTasks(rmp_serde::to_vec_named(&serialized_tasks)?))
+		};
+
+		rmp_serde::to_vec_named(&SaveState {
+			location,
+			location_path,
+			sub_path,
+			last_file_path_id,
+			finished_searching,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		})
+		.map(Some)
+	}
+
+	async fn deserialize(
+		serialized_job: &[u8],
+		_: &OuterCtx,
+	) -> Result<Option<(Self, Option<SerializedTasks>)>, rmp_serde::decode::Error> {
+		let SaveState {
+			location,
+			location_path,
+			sub_path,
+			last_file_path_id,
+			finished_searching,
+			metadata,
+			errors,
+			tasks_for_shutdown_bytes,
+		} = rmp_serde::from_slice::<SaveState>(serialized_job)?;
+
+		Ok(Some((
+			Self {
+				location,
+				location_path,
+				sub_path,
+				last_file_path_id,
+				finished_searching,
+				metadata,
+				errors,
+				pending_tasks_on_resume: Vec::new(),
+				tasks_for_shutdown: Vec::new(),
+			},
+			tasks_for_shutdown_bytes,
+		)))
+	}
+}
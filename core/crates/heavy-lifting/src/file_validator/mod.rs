@@ -0,0 +1,81 @@
+use crate::utils::sub_path;
+
+use sd_core_file_path_helper::FilePathError;
+
+use sd_prisma::prisma::file_path;
+use sd_utils::db::MissingFieldError;
+
+use std::path::Path;
+
+use blake3::Hasher;
+use prisma_client_rust::QueryError;
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::{
+	fs::File,
+	io::{self, AsyncReadExt},
+};
+
+pub mod job;
+pub(crate) mod tasks;
+
+pub use job::FileValidator;
+
+// we break these tasks into chunks of 100 to improve performance
+const CHUNK_SIZE: usize = 100;
+
+const BLOCK_LEN: usize = 1_048_576; // 1 MiB
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("missing field on database: {0}")]
+	MissingField(#[from] MissingFieldError),
+	#[error("failed to deserialized stored tasks for job resume: {0}")]
+	DeserializeTasks(#[from] rmp_serde::decode::Error),
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+
+	#[error(transparent)]
+	FilePathError(#[from] FilePathError),
+	#[error(transparent)]
+	SubPath(#[from] sub_path::Error),
+	#[error(transparent)]
+	Sync(#[from] sd_core_sync::Error),
+}
+
+impl From<Error> for rspc::Error {
+	fn from(e: Error) -> Self {
+		match e {
+			Error::SubPath(sub_path_err) => sub_path_err.into(),
+
+			_ => Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e),
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize, Type, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum NonCriticalFileValidatorError {
+	#[error("failed to extract isolated file path data: <file_path_id='{0}'>: {1}")]
+	FailedToExtractIsolatedFilePathData(file_path::id::Type, String),
+	#[error("failed to generate checksum <path='{}'>: {1}", .0.display())]
+	FailedToGenerateChecksum(Box<Path>, String),
+}
+
+/// Generate a full content checksum for the file at the received path, using blake3
+pub async fn file_checksum(path: impl AsRef<Path> + Send) -> Result<String, io::Error> {
+	let mut reader = File::open(path).await?;
+	let mut context = Hasher::new();
+	let mut buffer = vec![0; BLOCK_LEN].into_boxed_slice();
+
+	loop {
+		let read_count = reader.read(&mut buffer).await?;
+		if read_count == 0 {
+			break;
+		}
+		context.update(&buffer[..read_count]);
+	}
+
+	Ok(context.finalize().to_hex().to_string())
+}
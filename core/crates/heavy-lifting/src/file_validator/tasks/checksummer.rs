@@ -0,0 +1,268 @@
+use crate::{
+	file_validator::{self, file_checksum, NonCriticalFileValidatorError},
+	Error, NonCriticalError,
+};
+
+use sd_core_file_path_helper::IsolatedFilePathData;
+use sd_core_prisma_helpers::{file_path_for_object_validator, file_path_id};
+use sd_core_sync::Manager as SyncManager;
+
+use sd_prisma::{
+	prisma::{file_path, location, PrismaClient},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, IntoAnyTaskOutput, SerializableTask, Task, TaskId,
+};
+use sd_utils::msgpack;
+
+use std::{collections::VecDeque, mem, path::PathBuf, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::{instrument, trace, Level};
+
+#[derive(Debug)]
+pub struct Checksummer {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	location_id: location::id::Type,
+	location_path: Arc<PathBuf>,
+	file_paths: VecDeque<file_path_for_object_validator::Data>,
+
+	// Inner state
+	checksums: Vec<(file_path::pub_id::Type, String)>,
+
+	// Out collector
+	output: Output,
+
+	// Dependencies
+	db: Arc<PrismaClient>,
+	sync: Arc<SyncManager>,
+}
+
+/// Output from the `[Checksummer]` task
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Output {
+	/// Total number of files that got a new integrity checksum
+	pub validated_count: u64,
+
+	/// Collected metric about time elapsed reading files to generate checksums
+	pub checksum_time: Duration,
+
+	/// Collected metric about time spent saving checksums on database
+	pub save_db_time: Duration,
+
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for Checksummer {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(
+			task_id = %self.id,
+			location_id = %self.location_id,
+			location_path = %self.location_path.display(),
+			files_count = %self.file_paths.len(),
+		),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			location_id,
+			location_path,
+			file_paths,
+			checksums,
+			output:
+				Output {
+					validated_count,
+					checksum_time,
+					save_db_time,
+					errors,
+				},
+			db,
+			sync,
+			..
+		} = self;
+
+		let start_time = Instant::now();
+
+		while let Some(file_path) = file_paths.pop_front() {
+			match IsolatedFilePathData::try_from((*location_id, &file_path)) {
+				Ok(iso_file_path) => {
+					let full_path = location_path.join(iso_file_path);
+
+					match file_checksum(&full_path).await {
+						Ok(checksum) => {
+							trace!(path = %full_path.display(), %checksum, "Generated checksum;");
+							checksums.push((file_path.pub_id, checksum));
+						}
+						Err(e) => errors.push(
+							NonCriticalFileValidatorError::FailedToGenerateChecksum(
+								full_path.into_boxed_path(),
+								e.to_string(),
+							)
+							.into(),
+						),
+					}
+				}
+
+				Err(e) => errors.push(
+					NonCriticalFileValidatorError::FailedToExtractIsolatedFilePathData(
+						file_path.id,
+						e.to_string(),
+					)
+					.into(),
+				),
+			}
+
+			check_interruption!(interrupter, start_time, checksum_time);
+		}
+
+		*checksum_time += start_time.elapsed();
+
+		let start_time = Instant::now();
+
+		*validated_count = save_checksums(mem::take(checksums), db, sync).await?;
+
+		*save_db_time = start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl Checksummer {
+	#[must_use]
+	pub fn new(
+		location_id: location::id::Type,
+		location_path: Arc<PathBuf>,
+		file_paths: Vec<file_path_for_object_validator::Data>,
+		db: Arc<PrismaClient>,
+		sync: Arc<SyncManager>,
+	) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			location_id,
+			location_path,
+			file_paths: file_paths.into(),
+			checksums: Vec::new(),
+			output: Output::default(),
+			db,
+			sync,
+		}
+	}
+}
+
+#[instrument(skip_all, err, fields(checksums_count = checksums.len()))]
+async fn save_checksums(
+	checksums: Vec<(file_path::pub_id::Type, String)>,
+	db: &PrismaClient,
+	sync: &SyncManager,
+) -> Result<u64, file_validator::Error> {
+	if checksums.is_empty() {
+		return Ok(0);
+	}
+
+	sync.write_ops(
+		db,
+		checksums
+			.into_iter()
+			.map(|(pub_id, checksum)| {
+				(
+					sync.shared_update(
+						prisma_sync::file_path::SyncId {
+							pub_id: pub_id.clone(),
+						},
+						file_path::integrity_checksum::NAME,
+						msgpack!(&checksum),
+					),
+					db.file_path()
+						.update(
+							file_path::pub_id::equals(pub_id),
+							vec![file_path::integrity_checksum::set(Some(checksum))],
+						)
+						// selecting just id to avoid fetching the whole object
+						.select(file_path_id::select()),
+				)
+			})
+			.unzip::<_, _, Vec<_>, Vec<_>>(),
+	)
+	.await
+	.map(|updated| updated.len() as u64)
+	.map_err(Into::into)
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+	id: TaskId,
+	location_id: location::id::Type,
+	location_path: Arc<PathBuf>,
+	file_paths: VecDeque<file_path_for_object_validator::Data>,
+	checksums: Vec<(file_path::pub_id::Type, String)>,
+	output: Output,
+}
+
+impl SerializableTask<Error> for Checksummer {
+	type SerializeError = rmp_serde::encode::Error;
+
+	type DeserializeError = rmp_serde::decode::Error;
+
+	type DeserializeCtx = (Arc<PrismaClient>, Arc<SyncManager>);
+
+	async fn serialize(self) -> Result<Vec<u8>, Self::SerializeError> {
+		let Self {
+			id,
+			location_id,
+			location_path,
+			file_paths,
+			checksums,
+			output,
+			..
+		} = self;
+
+		rmp_serde::to_vec_named(&SaveState {
+			id,
+			location_id,
+			location_path,
+			file_paths,
+			checksums,
+			output,
+		})
+	}
+
+	async fn deserialize(
+		data: &[u8],
+		(db, sync): Self::DeserializeCtx,
+	) -> Result<Self, Self::DeserializeError> {
+		rmp_serde::from_slice(data).map(
+			|SaveState {
+			     id,
+			     location_id,
+			     location_path,
+			     file_paths,
+			     checksums,
+			     output,
+			 }| Self {
+				id,
+				location_id,
+				location_path,
+				file_paths,
+				checksums,
+				output,
+				db,
+				sync,
+			},
+		)
+	}
+}
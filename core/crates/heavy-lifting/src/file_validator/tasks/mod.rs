@@ -0,0 +1,3 @@
+pub mod checksummer;
+
+pub use checksummer::Checksummer;
@@ -49,6 +49,14 @@ impl MetadataForIndexerRules for InnerMetadata {
 	fn is_dir(&self) -> bool {
 		self.is_dir
 	}
+
+	fn size_in_bytes(&self) -> u64 {
+		self.size_in_bytes
+	}
+
+	fn modified_at(&self) -> Option<DateTime<Utc>> {
+		Some(self.modified_at)
+	}
 }
 
 impl From<InnerMetadata> for FilePathMetadata {
@@ -176,6 +176,7 @@ fn reject_path(
 ) -> bool {
 	IndexerRuler::rejected_by_reject_glob(acceptance_per_rule_kind)
 		|| IndexerRuler::rejected_by_git_ignore(acceptance_per_rule_kind)
+		|| IndexerRuler::rejected_by_file_properties(acceptance_per_rule_kind)
 		|| (metadata.is_dir()
 			&& process_and_maybe_reject_by_directory_rules(
 				current_path,
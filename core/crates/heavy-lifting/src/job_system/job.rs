@@ -60,6 +60,12 @@ pub enum JobName {
 	Delete,
 	Erase,
 	FileValidator,
+	DuplicateFinder,
+	Encrypt,
+	Decrypt,
+	Compress,
+	Extract,
+	Convert,
 }
 
 pub enum ReturnStatus {
@@ -1153,6 +1159,12 @@ impl JobTaskDispatcher {
 		)
 	}
 
+	/// Dispatcher not bound to this job's running state, used for work that must happen after the
+	/// job produced its results, like shallow indexing a directory where new files were written
+	pub(crate) const fn base_dispatcher(&self) -> &BaseTaskDispatcher<Error> {
+		&self.dispatcher
+	}
+
 	async fn wait_for_dispatch_approval(&self) -> DispatchApproval {
 		{
 			let mut running_state_rx = self.running_state.lock().await;
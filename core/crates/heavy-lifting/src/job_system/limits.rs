@@ -0,0 +1,83 @@
+use crate::Error;
+
+use sd_task_system::{BaseTaskDispatcher, ConcurrencyLimit};
+
+use std::{
+	collections::HashMap,
+	num::NonZeroUsize,
+	sync::{Arc, RwLock},
+};
+
+use super::job::JobName;
+
+/// Per [`JobName`] caps on how many tasks can run at the same time, shared by all jobs with that
+/// name, so a heavy job can't take every worker of the task system.
+#[derive(Debug, Clone, Default)]
+pub struct JobConcurrencyLimits(Arc<RwLock<HashMap<JobName, Arc<ConcurrencyLimit>>>>);
+
+impl JobConcurrencyLimits {
+	/// Sets the maximum number of tasks that jobs with this name can run at the same time, [`None`]
+	/// removes the cap. Jobs already running are also affected.
+	///
+	/// # Panics
+	///
+	/// Panics if the internal lock is poisoned
+	pub fn set(&self, job_name: JobName, max_tasks: Option<NonZeroUsize>) {
+		let max_tasks = max_tasks.unwrap_or(NonZeroUsize::MAX);
+
+		let mut limits = self
+			.0
+			.write()
+			.expect("job concurrency limits lock poisoned");
+
+		// Running jobs hold the limit, so we update it in place instead of replacing it
+		if let Some(limit) = limits.get(&job_name) {
+			limit.set_max(max_tasks);
+		} else {
+			limits.insert(job_name, ConcurrencyLimit::new(max_tasks));
+		}
+	}
+
+	/// Replaces all caps at once, jobs missing from `limits` become uncapped
+	///
+	/// # Panics
+	///
+	/// Panics if the internal lock is poisoned
+	pub fn replace(&self, limits: impl IntoIterator<Item = (JobName, NonZeroUsize)>) {
+		let limits = limits.into_iter().collect::<HashMap<_, _>>();
+
+		let uncapped = self
+			.0
+			.read()
+			.expect("job concurrency limits lock poisoned")
+			.keys()
+			.filter(|job_name| !limits.contains_key(job_name))
+			.copied()
+			.collect::<Vec<_>>();
+
+		uncapped
+			.into_iter()
+			.map(|job_name| (job_name, None))
+			.chain(
+				limits
+					.into_iter()
+					.map(|(job_name, max)| (job_name, Some(max))),
+			)
+			.for_each(|(job_name, max_tasks)| self.set(job_name, max_tasks));
+	}
+
+	pub(super) fn dispatcher_for(
+		&self,
+		job_name: JobName,
+		base_dispatcher: &BaseTaskDispatcher<Error>,
+	) -> BaseTaskDispatcher<Error> {
+		self.0
+			.read()
+			.expect("job concurrency limits lock poisoned")
+			.get(&job_name)
+			.map_or_else(
+				|| base_dispatcher.clone(),
+				|limit| base_dispatcher.with_concurrency_limit(Arc::clone(limit)),
+			)
+	}
+}
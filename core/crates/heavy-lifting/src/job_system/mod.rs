@@ -21,6 +21,7 @@ use uuid::Uuid;
 
 mod error;
 pub mod job;
+mod limits;
 pub mod report;
 mod runner;
 mod store;
@@ -28,6 +29,7 @@ pub mod utils;
 
 pub use error::{DispatcherError, JobErrorOrDispatcherError, JobSystemError};
 use job::{IntoJob, Job, JobName, JobOutput, OuterContext};
+pub use limits::JobConcurrencyLimits;
 use report::Report;
 use runner::{run, JobSystemRunner, RunnerMessage};
 use store::{load_jobs, StoredJobEntry};
@@ -50,6 +52,7 @@ pub struct JobSystem<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> {
 	msgs_tx: chan::Sender<RunnerMessage<OuterCtx, JobCtx>>,
 	job_outputs_rx: chan::Receiver<(JobId, Result<JobOutput, Error>)>,
 	store_jobs_file: Arc<PathBuf>,
+	concurrency_limits: JobConcurrencyLimits,
 	runner_handle: RefCell<Option<JoinHandle<()>>>,
 }
 
@@ -64,13 +67,17 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystem<OuterCtx, J
 
 		let store_jobs_file = Arc::new(data_directory.as_ref().join(PENDING_JOBS_FILE));
 
+		let concurrency_limits = JobConcurrencyLimits::default();
+
 		let runner_handle = RefCell::new(Some(spawn({
 			let store_jobs_file = Arc::clone(&store_jobs_file);
+			let concurrency_limits = concurrency_limits.clone();
 			async move {
 				trace!("Job System Runner starting...");
 				while let Err(e) = spawn({
 					let store_jobs_file = Arc::clone(&store_jobs_file);
 					let base_dispatcher = base_dispatcher.clone();
+					let concurrency_limits = concurrency_limits.clone();
 					let job_return_status_tx = job_done_tx.clone();
 					let job_done_rx = job_done_rx.clone();
 					let job_outputs_tx = job_outputs_tx.clone();
@@ -80,6 +87,7 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystem<OuterCtx, J
 						run(
 							JobSystemRunner::new(
 								base_dispatcher,
+								concurrency_limits,
 								job_return_status_tx,
 								job_outputs_tx,
 							),
@@ -109,10 +117,16 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystem<OuterCtx, J
 			msgs_tx,
 			job_outputs_rx,
 			store_jobs_file,
+			concurrency_limits,
 			runner_handle,
 		}
 	}
 
+	/// Caps on how many tasks each kind of job can run at the same time
+	pub const fn concurrency_limits(&self) -> &JobConcurrencyLimits {
+		&self.concurrency_limits
+	}
+
 	pub async fn init(
 		&self,
 		previously_existing_contexts: &HashMap<Uuid, OuterCtx>,
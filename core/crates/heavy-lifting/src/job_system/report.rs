@@ -111,6 +111,11 @@ pub enum ReportOutputMetadata {
 		location_id: location::id::Type,
 		sub_path: Option<PathBuf>,
 	},
+	DuplicateFinder {
+		duplicate_sets: (u32, u32),
+		wasted_bytes: (u32, u32),
+		split_objects: (u32, u32),
+	},
 }
 
 impl From<ReportInputMetadata> for ReportMetadata {
@@ -33,7 +33,7 @@ use super::{
 	job::{DynJob, JobHandle, JobName, JobOutput, OuterContext, ReturnStatus},
 	report::{self, ReportOutputMetadata},
 	store::{StoredJob, StoredJobEntry},
-	Command, JobId, JobSystemError, SerializedTasks,
+	Command, JobConcurrencyLimits, JobId, JobSystemError, SerializedTasks,
 };
 
 const JOBS_INITIAL_CAPACITY: usize = 32;
@@ -86,6 +86,7 @@ struct JobsWorktables {
 pub(super) struct JobSystemRunner<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> {
 	on_shutdown_mode: bool,
 	base_dispatcher: BaseTaskDispatcher<Error>,
+	concurrency_limits: JobConcurrencyLimits,
 	handles: HashMap<JobId, JobHandle<OuterCtx, JobCtx>>,
 	worktables: JobsWorktables,
 	job_return_status_tx: chan::Sender<(JobId, Result<ReturnStatus, Error>)>,
@@ -95,12 +96,14 @@ pub(super) struct JobSystemRunner<OuterCtx: OuterContext, JobCtx: JobContext<Out
 impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<OuterCtx, JobCtx> {
 	pub(super) fn new(
 		base_dispatcher: BaseTaskDispatcher<Error>,
+		concurrency_limits: JobConcurrencyLimits,
 		job_return_status_tx: chan::Sender<(JobId, Result<ReturnStatus, Error>)>,
 		job_outputs_tx: chan::Sender<(JobId, Result<JobOutput, Error>)>,
 	) -> Self {
 		Self {
 			on_shutdown_mode: false,
 			base_dispatcher,
+			concurrency_limits,
 			handles: HashMap::with_capacity(JOBS_INITIAL_CAPACITY),
 			worktables: JobsWorktables {
 				job_hashes: HashMap::with_capacity(JOBS_INITIAL_CAPACITY),
@@ -124,6 +127,7 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 	) -> Result<(), JobSystemError> {
 		let Self {
 			base_dispatcher,
+			concurrency_limits,
 			handles,
 			worktables:
 				JobsWorktables {
@@ -156,14 +160,14 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 
 		let mut handle = if maybe_existing_tasks.is_some() {
 			dyn_job.resume(
-				base_dispatcher.clone(),
+				concurrency_limits.dispatcher_for(job_name, base_dispatcher),
 				ctx.clone(),
 				maybe_existing_tasks,
 				job_return_status_tx.clone(),
 			)
 		} else {
 			dyn_job.dispatch(
-				base_dispatcher.clone(),
+				concurrency_limits.dispatcher_for(job_name, base_dispatcher),
 				ctx.clone(),
 				job_return_status_tx.clone(),
 			)
@@ -265,6 +269,7 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 			job_outputs_tx,
 			job_return_status_tx,
 			base_dispatcher,
+			concurrency_limits,
 			..
 		} = self;
 
@@ -298,7 +303,8 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 				try_dispatch_next_job(
 					&mut handle,
 					location_id,
-					base_dispatcher.clone(),
+					base_dispatcher,
+					concurrency_limits,
 					worktables,
 					handles,
 					job_return_status_tx.clone(),
@@ -534,7 +540,8 @@ async fn serialize_next_jobs_to_shutdown<OuterCtx: OuterContext, JobCtx: JobCont
 async fn try_dispatch_next_job<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>>(
 	handle: &mut JobHandle<OuterCtx, JobCtx>,
 	location_id: location::id::Type,
-	base_dispatcher: BaseTaskDispatcher<Error>,
+	base_dispatcher: &BaseTaskDispatcher<Error>,
+	concurrency_limits: &JobConcurrencyLimits,
 	JobsWorktables {
 		job_hashes,
 		job_hashes_by_id,
@@ -559,7 +566,7 @@ async fn try_dispatch_next_job<OuterCtx: OuterContext, JobCtx: JobContext<OuterC
 			running_jobs_set.insert((next_name, location_id));
 
 			let mut next_handle = next.dispatch(
-				base_dispatcher,
+				concurrency_limits.dispatcher_for(next_name, base_dispatcher),
 				handle.ctx.get_outer_ctx(),
 				job_return_status_tx,
 			);
@@ -1,4 +1,7 @@
-use crate::{file_identifier, indexer, media_processor, JobContext};
+use crate::{
+	archive, convert, duplicate_finder, file_crypto, file_identifier, file_system, file_validator,
+	indexer, media_processor, JobContext,
+};
 
 use sd_prisma::prisma::{job, location};
 use sd_utils::uuid_to_bytes;
@@ -226,6 +229,17 @@ async fn load_job<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>>(
 			indexer::job::Indexer,
 			file_identifier::job::FileIdentifier,
 			media_processor::job::MediaProcessor,
+			file_system::copier::Copier,
+			file_system::mover::Mover,
+			file_system::deleter::Deleter,
+			file_system::eraser::Eraser,
+			file_validator::job::FileValidator,
+			duplicate_finder::job::DuplicateFinder,
+			file_crypto::Encryptor,
+			file_crypto::Decryptor,
+			archive::Compressor,
+			archive::Extractor,
+			convert::Converter,
 			// TODO: Add more jobs here
 		]
 	)
@@ -35,9 +35,16 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 use thiserror::Error;
 
+pub mod archive;
+pub mod convert;
+pub mod duplicate_finder;
+pub mod file_crypto;
 pub mod file_identifier;
+pub mod file_system;
+pub mod file_validator;
 pub mod indexer;
 pub mod job_system;
+pub mod maintenance;
 pub mod media_processor;
 pub mod utils;
 
@@ -49,7 +56,7 @@ pub use job_system::{
 		ProgressUpdate,
 	},
 	report::Report,
-	JobId, JobSystem, JobSystemError,
+	JobConcurrencyLimits, JobId, JobSystem, JobSystemError,
 };
 
 #[derive(Error, Debug)]
@@ -60,6 +67,20 @@ pub enum Error {
 	FileIdentifier(#[from] file_identifier::Error),
 	#[error(transparent)]
 	MediaProcessor(#[from] media_processor::Error),
+	#[error(transparent)]
+	FileSystem(#[from] file_system::Error),
+	#[error(transparent)]
+	FileValidator(#[from] file_validator::Error),
+	#[error(transparent)]
+	DuplicateFinder(#[from] duplicate_finder::Error),
+	#[error(transparent)]
+	FileCrypto(#[from] file_crypto::Error),
+	#[error(transparent)]
+	Archive(#[from] archive::Error),
+	#[error(transparent)]
+	Convert(#[from] convert::Error),
+	#[error(transparent)]
+	Maintenance(#[from] maintenance::Error),
 
 	#[error(transparent)]
 	TaskSystem(#[from] TaskSystemError),
@@ -74,6 +95,13 @@ impl From<Error> for rspc::Error {
 			Error::Indexer(e) => e.into(),
 			Error::FileIdentifier(e) => e.into(),
 			Error::MediaProcessor(e) => e.into(),
+			Error::FileSystem(e) => e.into(),
+			Error::FileValidator(e) => e.into(),
+			Error::DuplicateFinder(e) => e.into(),
+			Error::FileCrypto(e) => e.into(),
+			Error::Archive(e) => e.into(),
+			Error::Convert(e) => e.into(),
+			Error::Maintenance(e) => e.into(),
 			Error::TaskSystem(e) => {
 				Self::with_cause(rspc::ErrorCode::InternalServerError, e.to_string(), e)
 			}
@@ -92,6 +120,18 @@ pub enum NonCriticalError {
 	FileIdentifier(#[from] file_identifier::NonCriticalFileIdentifierError),
 	#[error(transparent)]
 	MediaProcessor(#[from] media_processor::NonCriticalMediaProcessorError),
+	#[error(transparent)]
+	FileSystem(#[from] file_system::NonCriticalFileSystemError),
+	#[error(transparent)]
+	FileValidator(#[from] file_validator::NonCriticalFileValidatorError),
+	#[error(transparent)]
+	FileCrypto(#[from] file_crypto::NonCriticalFileCryptoError),
+	#[error(transparent)]
+	Archive(#[from] archive::NonCriticalArchiveError),
+	#[error(transparent)]
+	Convert(#[from] convert::NonCriticalConvertError),
+	#[error(transparent)]
+	Maintenance(#[from] maintenance::NonCriticalMaintenanceError),
 }
 
 #[repr(i32)]
@@ -0,0 +1,43 @@
+use sd_utils::error::FileIOError;
+
+use std::path::PathBuf;
+
+use prisma_client_rust::QueryError;
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+mod tasks;
+
+pub use tasks::{
+	database_optimizer::{self, DatabaseOptimizer},
+	orphan_remover::{self, OrphanRemover},
+	thumbnails_cleaner::{self, ThumbnailsCleaner},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+	#[error("failed to serialize sync id: {0}")]
+	Serialization(#[from] rmp_serde::encode::Error),
+	#[error(transparent)]
+	Sync(#[from] sd_core_sync::Error),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+}
+
+impl From<Error> for rspc::Error {
+	fn from(e: Error) -> Self {
+		Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e)
+	}
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize, Type, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum NonCriticalMaintenanceError {
+	#[error("failed to read thumbnails directory <path='{}'>: {1}", .0.display())]
+	ReadThumbnailsDirectory(PathBuf, String),
+	#[error("failed to remove stale thumbnail <path='{}'>: {1}", .0.display())]
+	RemoveThumbnail(PathBuf, String),
+}
@@ -0,0 +1,126 @@
+use crate::{maintenance, Error};
+
+use sd_prisma::prisma::PrismaClient;
+use sd_task_system::{ExecStatus, Interrupter, IntoAnyTaskOutput, Task, TaskId};
+
+use std::{mem, sync::Arc, time::Duration};
+
+use prisma_client_rust::raw;
+use serde::Deserialize;
+use tokio::time::{sleep, Instant};
+use tracing::{instrument, warn, Level};
+
+/// `VACUUM` fails while the database is being written to, so we retry a few times
+const VACUUM_ATTEMPTS: usize = 5;
+const VACUUM_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Rebuilds a library database with `VACUUM` to reclaim unused pages and refreshes the query
+/// planner statistics with `ANALYZE`
+#[derive(Debug)]
+pub struct DatabaseOptimizer {
+	// Task control
+	id: TaskId,
+
+	// Out collector
+	output: Output,
+
+	// Dependencies
+	db: Arc<PrismaClient>,
+}
+
+/// [`DatabaseOptimizer`] task output
+#[derive(Debug, Default)]
+pub struct Output {
+	/// Database size before optimizing it
+	pub size_before: u64,
+	/// Database size after optimizing it
+	pub size_after: u64,
+	/// Time spent optimizing the database
+	pub optimize_time: Duration,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for DatabaseOptimizer {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	#[instrument(
+		skip(self, _interrupter),
+		fields(task_id = %self.id),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, _interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		// Both statements run in a single step on sqlite, so there is nothing to interrupt here
+		let Self {
+			output: Output {
+				size_before,
+				size_after,
+				optimize_time,
+			},
+			db,
+			..
+		} = self;
+
+		let start_time = Instant::now();
+
+		*size_before = database_size(db).await?;
+
+		for attempt in 1..=VACUUM_ATTEMPTS {
+			match db._execute_raw(raw!("VACUUM;")).exec().await {
+				Ok(_) => break,
+				Err(e) if attempt < VACUUM_ATTEMPTS => {
+					warn!(?e, attempt, "Failed to vacuum database, retrying...;");
+					sleep(VACUUM_RETRY_DELAY).await;
+				}
+				Err(e) => return Err(maintenance::Error::from(e).into()),
+			}
+		}
+
+		db._execute_raw(raw!("ANALYZE;"))
+			.exec()
+			.await
+			.map_err(maintenance::Error::from)?;
+
+		*size_after = database_size(db).await?;
+
+		*optimize_time = start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl DatabaseOptimizer {
+	#[must_use]
+	pub fn new(db: Arc<PrismaClient>) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			output: Output::default(),
+			db,
+		}
+	}
+}
+
+async fn database_size(db: &PrismaClient) -> Result<u64, maintenance::Error> {
+	#[derive(Deserialize)]
+	struct DatabaseSize {
+		size: i64,
+	}
+
+	Ok(db
+		._query_raw::<DatabaseSize>(raw!(
+			"SELECT page_count * page_size AS size FROM pragma_page_count(), pragma_page_size()"
+		))
+		.exec()
+		.await?
+		.first()
+		.map_or(0, |DatabaseSize { size }| {
+			#[allow(clippy::cast_sign_loss)]
+			// SAFETY: sqlite sizes are never negative
+			{
+				*size as u64
+			}
+		}))
+}
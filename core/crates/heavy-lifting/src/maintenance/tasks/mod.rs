@@ -0,0 +1,3 @@
+pub mod database_optimizer;
+pub mod orphan_remover;
+pub mod thumbnails_cleaner;
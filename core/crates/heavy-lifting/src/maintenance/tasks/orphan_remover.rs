@@ -0,0 +1,356 @@
+use crate::{maintenance, Error};
+
+use sd_core_sync::{Manager as SyncManager, NTP64};
+
+use sd_prisma::{
+	prisma::{
+		crdt_operation, label_on_object, object, object_in_album, object_in_space, tag_on_object,
+		PrismaClient, SortOrder,
+	},
+	prisma_sync,
+};
+use sd_sync::{OperationFactory, OperationKind};
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, IntoAnyTaskOutput, Task, TaskId,
+};
+use sd_utils::msgpack;
+
+use std::{
+	collections::HashMap,
+	mem,
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
+
+use tokio::time::Instant;
+use tracing::{instrument, trace, Level};
+
+/// How many orphans are looked at on each database round trip
+const BATCH_SIZE: i64 = 512;
+
+/// How long an object must exist before it's removed for being an orphan, as the file paths
+/// pointing to it may be written after it, e.g. while identifying files
+const GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Removes objects that no longer have any file path pointing to them.
+///
+/// Only objects created by this instance are removed, as the ones created by others may be
+/// ingested before the file paths pointing to them. Removals are synced, so other instances drop
+/// the same objects.
+#[derive(Debug)]
+pub struct OrphanRemover {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	last_checked_object_id: object::id::Type,
+
+	// Out collector
+	output: Output,
+
+	// Dependencies
+	db: Arc<PrismaClient>,
+	sync: Arc<SyncManager>,
+}
+
+/// [`OrphanRemover`] task output
+#[derive(Debug, Default)]
+pub struct Output {
+	/// Number of orphaned objects removed from the database
+	pub removed_objects: u64,
+	/// Time spent looking for and removing orphans
+	pub remove_time: Duration,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for OrphanRemover {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(task_id = %self.id),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			last_checked_object_id,
+			output: Output {
+				removed_objects,
+				remove_time,
+			},
+			db,
+			sync,
+			..
+		} = self;
+
+		let start_time = Instant::now();
+
+		// Each batch is committed on its own, so resuming after a pause continues from the last one
+		loop {
+			check_interruption!(interrupter, start_time, remove_time);
+
+			let Some(removed) = remove_batch(db, sync, last_checked_object_id).await? else {
+				break;
+			};
+
+			trace!(removed, "Removed orphaned objects;");
+
+			*removed_objects += removed;
+		}
+
+		*remove_time += start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl OrphanRemover {
+	#[must_use]
+	pub fn new(db: Arc<PrismaClient>, sync: Arc<SyncManager>) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			last_checked_object_id: 0,
+			output: Output::default(),
+			db,
+			sync,
+		}
+	}
+}
+
+/// Removes the orphans among the next batch of them, returning how many were removed, or `None`
+/// once every orphan was looked at
+async fn remove_batch(
+	db: &PrismaClient,
+	sync: &SyncManager,
+	last_checked_object_id: &mut object::id::Type,
+) -> Result<Option<u64>, maintenance::Error> {
+	let orphans = db
+		.object()
+		.find_many(vec![
+			object::file_paths::none(vec![]),
+			object::id::gt(*last_checked_object_id),
+		])
+		.order_by(object::id::order(SortOrder::Asc))
+		.take(BATCH_SIZE)
+		.select(object::select!({ id pub_id date_created }))
+		.exec()
+		.await?;
+
+	let Some(last_orphan) = orphans.last() else {
+		return Ok(None);
+	};
+
+	*last_checked_object_id = last_orphan.id;
+
+	let removable = removable_orphans(db, sync, orphans).await?;
+
+	if removable.is_empty() {
+		return Ok(Some(0));
+	}
+
+	let object_ids = removable.keys().copied().collect::<Vec<_>>();
+
+	let object_sync_id = |object_id| prisma_sync::object::SyncId {
+		pub_id: removable[&object_id].clone(),
+	};
+
+	// Relations to objects don't cascade, so they must go before the objects themselves
+	let tags = db
+		.tag_on_object()
+		.find_many(vec![tag_on_object::object_id::in_vec(object_ids.clone())])
+		.select(tag_on_object::select!({ object_id tag: select { pub_id } }))
+		.exec()
+		.await?;
+
+	if !tags.is_empty() {
+		sync.write_ops(
+			db,
+			(
+				tags.into_iter()
+					.map(|t_o| {
+						sync.relation_delete(prisma_sync::tag_on_object::SyncId {
+							tag: prisma_sync::tag::SyncId {
+								pub_id: t_o.tag.pub_id,
+							},
+							object: object_sync_id(t_o.object_id),
+						})
+					})
+					.collect(),
+				db.tag_on_object()
+					.delete_many(vec![tag_on_object::object_id::in_vec(object_ids.clone())]),
+			),
+		)
+		.await?;
+	}
+
+	let labels = db
+		.label_on_object()
+		.find_many(vec![label_on_object::object_id::in_vec(object_ids.clone())])
+		.select(label_on_object::select!({ object_id label: select { name } }))
+		.exec()
+		.await?;
+
+	if !labels.is_empty() {
+		sync.write_ops(
+			db,
+			(
+				labels
+					.into_iter()
+					.map(|l_o| {
+						sync.relation_delete(prisma_sync::label_on_object::SyncId {
+							label: prisma_sync::label::SyncId {
+								name: l_o.label.name,
+							},
+							object: object_sync_id(l_o.object_id),
+						})
+					})
+					.collect(),
+				db.label_on_object()
+					.delete_many(vec![label_on_object::object_id::in_vec(object_ids.clone())]),
+			),
+		)
+		.await?;
+	}
+
+	let albums = db
+		.object_in_album()
+		.find_many(vec![object_in_album::object_id::in_vec(object_ids.clone())])
+		.select(object_in_album::select!({ object_id album: select { pub_id } }))
+		.exec()
+		.await?;
+
+	if !albums.is_empty() {
+		sync.write_ops(
+			db,
+			(
+				albums
+					.into_iter()
+					.map(|o_a| {
+						sync.relation_delete(prisma_sync::object_in_album::SyncId {
+							album: prisma_sync::album::SyncId {
+								pub_id: o_a.album.pub_id,
+							},
+							object: object_sync_id(o_a.object_id),
+						})
+					})
+					.collect(),
+				db.object_in_album()
+					.delete_many(vec![object_in_album::object_id::in_vec(object_ids.clone())]),
+			),
+		)
+		.await?;
+	}
+
+	let spaces = db
+		.object_in_space()
+		.find_many(vec![object_in_space::object_id::in_vec(object_ids.clone())])
+		.select(object_in_space::select!({ object_id space: select { pub_id } }))
+		.exec()
+		.await?;
+
+	if !spaces.is_empty() {
+		sync.write_ops(
+			db,
+			(
+				spaces
+					.into_iter()
+					.map(|o_s| {
+						sync.relation_delete(prisma_sync::object_in_space::SyncId {
+							space: prisma_sync::space::SyncId {
+								pub_id: o_s.space.pub_id,
+							},
+							object: object_sync_id(o_s.object_id),
+						})
+					})
+					.collect(),
+				db.object_in_space()
+					.delete_many(vec![object_in_space::object_id::in_vec(object_ids.clone())]),
+			),
+		)
+		.await?;
+	}
+
+	let removed = sync
+		.write_ops(
+			db,
+			(
+				object_ids
+					.iter()
+					.map(|object_id| sync.shared_delete(object_sync_id(*object_id)))
+					.collect(),
+				db.object()
+					.delete_many(vec![object::id::in_vec(object_ids)]),
+			),
+		)
+		.await?;
+
+	#[allow(clippy::cast_sign_loss)]
+	// SAFETY: a count of deleted rows is never negative
+	Ok(Some(removed as u64))
+}
+
+/// Keeps the orphans created by this instance longer than [`GRACE_PERIOD`] ago, as told by their
+/// `Create` operations. Objects without one were created before sync was enabled, so their own
+/// creation date is checked against the grace period instead, and they're kept when it's unknown.
+async fn removable_orphans(
+	db: &PrismaClient,
+	sync: &SyncManager,
+	orphans: Vec<object::select::Data>,
+) -> Result<HashMap<object::id::Type, object::pub_id::Type>, maintenance::Error> {
+	let mut orphans_by_record_id = orphans
+		.into_iter()
+		.map(|orphan| {
+			rmp_serde::to_vec(&msgpack!(prisma_sync::object::SyncId {
+				pub_id: orphan.pub_id.clone()
+			}))
+			.map(|record_id| (record_id, orphan))
+		})
+		.collect::<Result<HashMap<_, _>, _>>()?;
+
+	let creations = db
+		.crdt_operation()
+		.find_many(vec![
+			crdt_operation::model::equals(i32::from(prisma_sync::object::MODEL_ID)),
+			crdt_operation::kind::equals(OperationKind::Create.to_string()),
+			crdt_operation::record_id::in_vec(orphans_by_record_id.keys().cloned().collect()),
+		])
+		.select(crdt_operation::select!({ record_id timestamp instance: select { pub_id } }))
+		.exec()
+		.await?;
+
+	let instance_pub_id = sync.get_instance();
+	let grace_start = SystemTime::now() - GRACE_PERIOD;
+
+	let mut removable = HashMap::with_capacity(orphans_by_record_id.len());
+
+	for creation in creations {
+		let Some(orphan) = orphans_by_record_id.remove(&creation.record_id) else {
+			continue;
+		};
+
+		#[allow(clippy::cast_sign_loss)]
+		// SAFETY: timestamps are stored as i64 due to SQLite limitations, but they're always positive
+		let created_at = NTP64(creation.timestamp as u64).to_system_time();
+
+		if creation.instance.pub_id == instance_pub_id.as_bytes() && created_at <= grace_start {
+			removable.insert(orphan.id, orphan.pub_id);
+		}
+	}
+
+	removable.extend(
+		orphans_by_record_id
+			.into_values()
+			.filter(|orphan| {
+				orphan
+					.date_created
+					.is_some_and(|date_created| SystemTime::from(date_created) <= grace_start)
+			})
+			.map(|orphan| (orphan.id, orphan.pub_id)),
+	);
+
+	Ok(removable)
+}
@@ -0,0 +1,276 @@
+use crate::{
+	maintenance::{self, NonCriticalMaintenanceError},
+	media_processor::{VideoPreviewFile, EPHEMERAL_DIR, WEBP_EXTENSION},
+	Error, NonCriticalError,
+};
+
+use sd_prisma::prisma::{file_path, PrismaClient};
+use sd_task_system::{
+	check_interruption, ExecStatus, Interrupter, IntoAnyTaskOutput, Task, TaskId,
+};
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::HashSet,
+	mem,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
+
+use tokio::{fs, io, time::Instant};
+use tracing::{instrument, trace, Level};
+use uuid::Uuid;
+
+/// Ephemeral thumbnails are generated again on demand, so the ones that weren't touched for this
+/// long are considered stale
+const EPHEMERAL_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug)]
+enum Target {
+	/// Thumbnails of a library whose `cas_id` isn't used by any file path anymore
+	Indexed {
+		library_id: Uuid,
+		db: Arc<PrismaClient>,
+	},
+	/// Ephemeral thumbnails older than [`EPHEMERAL_MAX_AGE`]
+	Ephemeral,
+}
+
+/// Removes stale thumbnails from a thumbnails directory, along with the previews of videos
+/// stored next to them
+#[derive(Debug)]
+pub struct ThumbnailsCleaner {
+	// Task control
+	id: TaskId,
+
+	// Received input args
+	thumbnails_directory: PathBuf,
+	target: Target,
+
+	// Out collector
+	output: Output,
+}
+
+/// [`ThumbnailsCleaner`] task output
+#[derive(Debug, Default)]
+pub struct Output {
+	/// Library the removed thumbnails belonged to, `None` for ephemeral thumbnails
+	pub library_id: Option<Uuid>,
+	/// Number of stale thumbnails removed
+	pub removed_thumbnails: u64,
+	/// Bytes freed by removing stale thumbnails
+	pub freed_bytes: u64,
+	/// Time spent cleaning up thumbnails
+	pub clean_up_time: Duration,
+	/// Non critical errors that happened during the task execution
+	pub errors: Vec<NonCriticalError>,
+}
+
+#[async_trait::async_trait]
+impl Task<Error> for ThumbnailsCleaner {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	#[instrument(
+		skip(self, interrupter),
+		fields(task_id = %self.id, thumbnails_directory = %self.thumbnails_directory.display()),
+		ret(level = Level::TRACE),
+		err,
+	)]
+	#[allow(clippy::blocks_in_conditions)] // Due to `err` on `instrument` macro above
+	async fn run(&mut self, interrupter: &Interrupter) -> Result<ExecStatus, Error> {
+		let Self {
+			thumbnails_directory,
+			target,
+			output:
+				Output {
+					removed_thumbnails,
+					freed_bytes,
+					clean_up_time,
+					errors,
+					..
+				},
+			..
+		} = self;
+
+		let start_time = Instant::now();
+
+		// Indexed thumbnails are stale when no file path uses them, ephemeral ones when they are old
+		let (directory, existing_thumbs) = match target {
+			Target::Indexed { library_id, db } => (
+				thumbnails_directory.join(library_id.to_string()),
+				Some(existing_thumbs(db).await?),
+			),
+			Target::Ephemeral => (thumbnails_directory.join(EPHEMERAL_DIR), None),
+		};
+
+		let now = SystemTime::now();
+
+		// Resuming after a pause just lists the directories again, as removed files are already gone
+		for shard_path in shard_directories(&directory).await? {
+			check_interruption!(interrupter, start_time, clean_up_time);
+
+			let mut read_shard_dir = match fs::read_dir(&shard_path).await {
+				Ok(read_dir) => read_dir,
+				Err(e) => {
+					errors.push(
+						NonCriticalMaintenanceError::ReadThumbnailsDirectory(
+							shard_path,
+							e.to_string(),
+						)
+						.into(),
+					);
+					continue;
+				}
+			};
+
+			loop {
+				let thumb_entry = match read_shard_dir.next_entry().await {
+					Ok(Some(thumb_entry)) => thumb_entry,
+					Ok(None) => break,
+					Err(e) => {
+						errors.push(
+							NonCriticalMaintenanceError::ReadThumbnailsDirectory(
+								shard_path.clone(),
+								e.to_string(),
+							)
+							.into(),
+						);
+						break;
+					}
+				};
+
+				let thumb_path = thumb_entry.path();
+				let Some(cas_id) = thumb_cas_id(&thumb_path) else {
+					continue;
+				};
+
+				let Ok(metadata) = thumb_entry.metadata().await else {
+					continue;
+				};
+
+				let is_stale = existing_thumbs.as_ref().map_or_else(
+					|| {
+						metadata
+							.modified()
+							.ok()
+							.and_then(|modified| now.duration_since(modified).ok())
+							.is_some_and(|age| age > EPHEMERAL_MAX_AGE)
+					},
+					|existing_thumbs| !existing_thumbs.contains(cas_id),
+				);
+
+				if !is_stale {
+					continue;
+				}
+
+				trace!(thumb_path = %thumb_path.display(), "Removing stale thumbnail;");
+
+				match fs::remove_file(&thumb_path).await {
+					Ok(()) => {
+						*removed_thumbnails += 1;
+						*freed_bytes += metadata.len();
+					}
+					Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+					Err(e) => errors.push(
+						NonCriticalMaintenanceError::RemoveThumbnail(thumb_path, e.to_string())
+							.into(),
+					),
+				}
+			}
+		}
+
+		*clean_up_time += start_time.elapsed();
+
+		Ok(ExecStatus::Done(mem::take(&mut self.output).into_output()))
+	}
+}
+
+impl ThumbnailsCleaner {
+	#[must_use]
+	pub fn new_indexed(
+		thumbnails_directory: PathBuf,
+		library_id: Uuid,
+		db: Arc<PrismaClient>,
+	) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			thumbnails_directory,
+			target: Target::Indexed { library_id, db },
+			output: Output {
+				library_id: Some(library_id),
+				..Default::default()
+			},
+		}
+	}
+
+	#[must_use]
+	pub fn new_ephemeral(thumbnails_directory: PathBuf) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			thumbnails_directory,
+			target: Target::Ephemeral,
+			output: Output::default(),
+		}
+	}
+}
+
+/// Every `cas_id` still in use by the library
+async fn existing_thumbs(db: &PrismaClient) -> Result<HashSet<String>, maintenance::Error> {
+	Ok(db
+		.file_path()
+		.find_many(vec![file_path::cas_id::not(None)])
+		.select(file_path::select!({ cas_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.filter_map(|file_path| file_path.cas_id)
+		.collect())
+}
+
+/// The `cas_id` a file in the thumbnails directory belongs to, `None` for anything that isn't
+/// a thumbnail or a video preview
+fn thumb_cas_id(thumb_path: &Path) -> Option<&str> {
+	let (cas_id, extension) = thumb_path.file_name()?.to_str()?.split_once('.')?;
+
+	[
+		WEBP_EXTENSION,
+		VideoPreviewFile::ThumbstripSheet.extension(),
+		VideoPreviewFile::ThumbstripIndex.extension(),
+		VideoPreviewFile::Animated.extension(),
+		VideoPreviewFile::Failed.extension(),
+	]
+	.contains(&extension)
+	.then_some(cas_id)
+}
+
+/// Thumbnails are sharded in sub directories, a missing thumbnails directory just means
+/// that there is nothing to clean up
+async fn shard_directories(directory: &Path) -> Result<Vec<PathBuf>, maintenance::Error> {
+	let mut read_dir = match fs::read_dir(directory).await {
+		Ok(read_dir) => read_dir,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+		Err(e) => return Err(FileIOError::from((directory, e)).into()),
+	};
+
+	let mut shards = vec![];
+
+	while let Some(entry) = read_dir
+		.next_entry()
+		.await
+		.map_err(|e| FileIOError::from((directory, e)))?
+	{
+		if entry
+			.file_type()
+			.await
+			.map_err(|e| FileIOError::from((entry.path(), e)))?
+			.is_dir()
+		{
+			shards.push(entry.path());
+		}
+	}
+
+	Ok(shards)
+}
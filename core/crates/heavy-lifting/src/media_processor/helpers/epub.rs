@@ -0,0 +1,247 @@
+//! Just enough of EPUB to find the cover of a book: the container points at the package document,
+//! which lists the cover among the book resources.
+
+use std::{borrow::Cow, collections::HashMap, path::Path};
+
+use async_zip::tokio::read::seek::ZipFileReader;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tokio::{
+	fs::File,
+	io::{self, AsyncReadExt, BufReader},
+};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+
+/// Covers are images made for screens, anything bigger than this is most likely something else
+const MAX_ENTRY_SIZE: u64 = 32 * 1024 * 1024;
+
+static ROOTFILE_TAG: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"<(?:\w+:)?rootfile\b[^>]*>").expect("valid rootfile regex"));
+static ITEM_TAG: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"<(?:\w+:)?item\b[^>]*>").expect("valid item regex"));
+static META_TAG: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"<(?:\w+:)?meta\b[^>]*>").expect("valid meta regex"));
+static ATTRIBUTE: Lazy<Regex> = Lazy::new(|| {
+	Regex::new(r#"([\w:.-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid attribute regex")
+});
+
+#[derive(thiserror::Error, Debug)]
+pub enum EpubError {
+	#[error(transparent)]
+	Io(#[from] io::Error),
+	#[error(transparent)]
+	Zip(#[from] async_zip::error::ZipError),
+	#[error("missing package document")]
+	MissingPackage,
+}
+
+/// Reads the cover image of an EPUB file, returns `None` if the book doesn't have one
+pub async fn extract_cover(path: impl AsRef<Path> + Send) -> Result<Option<Vec<u8>>, EpubError> {
+	let mut reader = ZipFileReader::with_tokio(BufReader::new(File::open(path).await?)).await?;
+
+	let container = read_entry(&mut reader, CONTAINER_PATH)
+		.await?
+		.ok_or(EpubError::MissingPackage)?;
+
+	let package_path = ROOTFILE_TAG
+		.find_iter(&String::from_utf8_lossy(&container))
+		.find_map(|tag| attributes(tag.as_str()).remove("full-path"))
+		.ok_or(EpubError::MissingPackage)?;
+
+	let package = read_entry(&mut reader, &package_path)
+		.await?
+		.ok_or(EpubError::MissingPackage)?;
+
+	let Some(href) = cover_href(&String::from_utf8_lossy(&package)) else {
+		return Ok(None);
+	};
+
+	read_entry(&mut reader, &resolve_href(&package_path, &href)).await
+}
+
+async fn read_entry(
+	reader: &mut ZipFileReader<BufReader<File>>,
+	name: &str,
+) -> Result<Option<Vec<u8>>, EpubError> {
+	let Some((index, size)) = reader
+		.file()
+		.entries()
+		.iter()
+		.enumerate()
+		.find(|(_, entry)| {
+			entry
+				.filename()
+				.as_str()
+				.is_ok_and(|filename| filename == name)
+		})
+		.map(|(index, entry)| (index, entry.uncompressed_size()))
+	else {
+		return Ok(None);
+	};
+
+	if size > MAX_ENTRY_SIZE {
+		return Ok(None);
+	}
+
+	let mut data = vec![];
+	reader
+		.reader_with_entry(index)
+		.await?
+		.compat()
+		.take(MAX_ENTRY_SIZE)
+		.read_to_end(&mut data)
+		.await?;
+
+	Ok(Some(data))
+}
+
+/// Finds the cover in the package document, as declared by EPUB 3 or EPUB 2, falling back
+/// to any image named like a cover
+fn cover_href(package: &str) -> Option<String> {
+	let items = ITEM_TAG
+		.find_iter(package)
+		.map(|tag| attributes(tag.as_str()))
+		.collect::<Vec<_>>();
+
+	let cover_id = META_TAG
+		.find_iter(package)
+		.map(|tag| attributes(tag.as_str()))
+		.find(|meta| meta.get("name").map(String::as_str) == Some("cover"))
+		.and_then(|mut meta| meta.remove("content"));
+
+	let is_image = |item: &&HashMap<String, String>| {
+		item.get("media-type")
+			.is_some_and(|media_type| media_type.starts_with("image/"))
+	};
+
+	items
+		.iter()
+		.find(|item| {
+			item.get("properties")
+				.is_some_and(|properties| properties.split_whitespace().any(|p| p == "cover-image"))
+		})
+		.or_else(|| {
+			cover_id
+				.and_then(|cover_id| items.iter().find(|item| item.get("id") == Some(&cover_id)))
+		})
+		.or_else(|| {
+			items.iter().filter(is_image).find(|item| {
+				["id", "href"].into_iter().any(|name| {
+					item.get(name)
+						.is_some_and(|value| value.to_lowercase().contains("cover"))
+				})
+			})
+		})
+		.and_then(|item| item.get("href").cloned())
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+	ATTRIBUTE
+		.captures_iter(tag)
+		.filter_map(|captures| {
+			let value = captures.get(2).or_else(|| captures.get(3))?;
+
+			Some((
+				captures[1].to_string(),
+				value.as_str().replace("&amp;", "&"),
+			))
+		})
+		.collect()
+}
+
+/// Hrefs are URLs relative to the package document, while zip entries are plain paths from
+/// the root of the archive
+fn resolve_href(package_path: &str, href: &str) -> String {
+	let href = href.split(['#', '?']).next().unwrap_or_default();
+
+	let mut segments = package_path
+		.rsplit_once('/')
+		.map(|(directory, _)| directory.split('/').collect::<Vec<_>>())
+		.unwrap_or_default();
+
+	let href = percent_decode(href);
+
+	for segment in href.split('/') {
+		match segment {
+			"" | "." => {}
+			".." => {
+				segments.pop();
+			}
+			segment => segments.push(segment),
+		}
+	}
+
+	segments.join("/")
+}
+
+fn percent_decode(value: &str) -> Cow<'_, str> {
+	if !value.contains('%') {
+		return Cow::Borrowed(value);
+	}
+
+	let bytes = value.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut idx = 0;
+
+	while idx < bytes.len() {
+		if bytes[idx] == b'%' {
+			if let Some(byte) = value
+				.get(idx + 1..idx + 3)
+				.and_then(|hex| u8::from_str_radix(hex, 16).ok())
+			{
+				decoded.push(byte);
+				idx += 3;
+				continue;
+			}
+		}
+
+		decoded.push(bytes[idx]);
+		idx += 1;
+	}
+
+	Cow::Owned(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_cover_href() {
+		let epub3 = r#"<package version="3.0"><manifest>
+			<item id="css" href="style.css" media-type="text/css"/>
+			<item id="img" href="images/front%20cover.jpg" media-type="image/jpeg" properties="cover-image"/>
+		</manifest></package>"#;
+		assert_eq!(
+			cover_href(epub3).as_deref(),
+			Some("images/front%20cover.jpg")
+		);
+
+		let epub2 = r#"<opf:package><opf:metadata><opf:meta name="cover" content="img-1"/></opf:metadata>
+			<opf:manifest><opf:item href='../Images/1.png' id='img-1' media-type='image/png'/></opf:manifest>
+		</opf:package>"#;
+		assert_eq!(cover_href(epub2).as_deref(), Some("../Images/1.png"));
+
+		let named =
+			r#"<manifest><item id="c" href="Cover.jpeg" media-type="image/jpeg"/></manifest>"#;
+		assert_eq!(cover_href(named).as_deref(), Some("Cover.jpeg"));
+
+		let none = r#"<manifest><item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/></manifest>"#;
+		assert_eq!(cover_href(none), None);
+	}
+
+	#[test]
+	fn test_resolve_href() {
+		assert_eq!(
+			resolve_href("OEBPS/content.opf", "images/front%20cover.jpg"),
+			"OEBPS/images/front cover.jpg"
+		);
+		assert_eq!(
+			resolve_href("OEBPS/Text/content.opf", "../Images/1.png#page"),
+			"OEBPS/Images/1.png"
+		);
+		assert_eq!(resolve_href("content.opf", "./cover.jpg"), "cover.jpg");
+	}
+}
@@ -4,7 +4,7 @@ use sd_core_prisma_helpers::ObjectPubId;
 use sd_core_sync::Manager as SyncManager;
 
 use sd_file_ext::extensions::{Extension, ImageExtension, ALL_IMAGE_EXTENSIONS};
-use sd_media_metadata::ExifMetadata;
+use sd_media_metadata::{exif::MediaLocation, ExifMetadata};
 use sd_prisma::{
 	prisma::{exif_data, object, PrismaClient},
 	prisma_sync,
@@ -53,6 +53,8 @@ fn to_query(
 	}: ExifMetadata,
 	object_id: exif_data::object_id::Type,
 ) -> (Vec<(&'static str, rmpv::Value)>, exif_data::Create) {
+	let coordinates = location.as_ref().map(MediaLocation::coordinates);
+
 	let (sync_params, db_params) = chain_optional_iter(
 		[],
 		[
@@ -74,6 +76,8 @@ fn to_query(
 				date_taken.map(|x| x.unix_timestamp()),
 				exif_data::epoch_time
 			),
+			option_sync_db_entry!(coordinates.map(|(lat, _)| lat), exif_data::latitude),
+			option_sync_db_entry!(coordinates.map(|(_, long)| long), exif_data::longitude),
 		],
 	)
 	.into_iter()
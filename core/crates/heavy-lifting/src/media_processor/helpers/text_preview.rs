@@ -0,0 +1,721 @@
+//! Previews of text files: a snippet of their content for quick-look panels, and a rendering of
+//! their first screenful, syntax highlighted, for thumbnails.
+
+use sd_file_ext::{
+	extensions::{CodeExtension, ConfigExtension, Extension},
+	text::{decode, is_text},
+};
+use sd_utils::error::FileIOError;
+
+use std::{fmt::Write, path::Path};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::{
+	fs::File,
+	io::{AsyncReadExt, BufReader},
+};
+
+/// How much of a file we read to render its thumbnail, way more than a screenful of text
+pub const PREVIEW_READ_LENGTH: usize = 16 * 1024;
+
+/// Upper bound for the snippets sent to the frontend
+pub const MAX_SNIPPET_LENGTH: usize = 256 * 1024;
+
+const LINES: usize = 32;
+const COLUMNS: usize = 72;
+const TAB_WIDTH: usize = 4;
+
+const FONT_SIZE: f32 = 14.0;
+const LINE_HEIGHT: f32 = 18.0;
+const PADDING: f32 = 20.0;
+const SIZE: f32 = 640.0;
+
+const FONT_FAMILY: &str =
+	"Menlo, Consolas, 'DejaVu Sans Mono', 'Liberation Mono', 'Courier New', monospace";
+const BACKGROUND_COLOR: &str = "#1c1d25";
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+pub struct TextSnippet {
+	/// Content of the file converted to UTF-8
+	pub text: String,
+	/// Charset the file was written in, as detected from its content
+	pub encoding: String,
+	/// Whether the file is bigger than what we read from it
+	pub truncated: bool,
+}
+
+/// Reads up to `max_length` bytes from the start of the file, returns `None` if it isn't text
+pub async fn read_snippet(
+	path: impl AsRef<Path> + Send,
+	max_length: usize,
+) -> Result<Option<TextSnippet>, FileIOError> {
+	let path = path.as_ref();
+
+	let file = File::open(path)
+		.await
+		.map_err(|e| FileIOError::from((path, e, "Failed to open file for text snippet")))?;
+
+	let size = file
+		.metadata()
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?
+		.len();
+
+	let mut data =
+		Vec::with_capacity(usize::try_from(size).map_or(max_length, |size| size.min(max_length)));
+
+	BufReader::new(file)
+		.take(max_length as u64)
+		.read_to_end(&mut data)
+		.await
+		.map_err(|e| FileIOError::from((path, e, "Failed to read text snippet")))?;
+
+	let truncated = size > data.len() as u64;
+
+	if data.is_empty() {
+		return Ok(Some(TextSnippet {
+			text: String::new(),
+			encoding: "utf-8".to_string(),
+			truncated,
+		}));
+	}
+
+	Ok(is_text(&data, truncated).and_then(|encoding| {
+		decode(&data, encoding).map(|text| TextSnippet {
+			text,
+			encoding: encoding.to_string(),
+			truncated,
+		})
+	}))
+}
+
+/// Renders the first screenful of text as a SVG document, highlighted if we know the syntax of
+/// the file's extension
+#[must_use]
+pub fn render_svg(text: &str, extension: &Extension) -> String {
+	let lines = text
+		.lines()
+		.take(LINES)
+		.map(expand_tabs)
+		.collect::<Vec<_>>();
+
+	let highlighted = syntax_for(extension).map_or_else(
+		|| {
+			lines
+				.iter()
+				.map(|line| vec![(Token::Plain, line.clone())])
+				.collect()
+		},
+		|syntax| highlight(&lines, syntax),
+	);
+
+	let mut svg = format!(
+		r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SIZE}" height="{SIZE}" viewBox="0 0 {SIZE} {SIZE}"><rect width="{SIZE}" height="{SIZE}" fill="{BACKGROUND_COLOR}"/><text font-family="{FONT_FAMILY}" font-size="{FONT_SIZE}" fill="{}" xml:space="preserve">"#,
+		Token::Plain.color()
+	);
+
+	for (idx, spans) in highlighted.iter().enumerate() {
+		if spans.iter().all(|(_, text)| text.trim().is_empty()) {
+			continue;
+		}
+
+		#[allow(clippy::cast_precision_loss)]
+		let y = PADDING + LINE_HEIGHT * (idx + 1) as f32 - (LINE_HEIGHT - FONT_SIZE);
+
+		// Writing to a String never fails
+		let _ = write!(svg, r#"<tspan x="{PADDING}" y="{y}">"#);
+
+		let mut columns = 0;
+		for (token, text) in spans {
+			if columns >= COLUMNS {
+				break;
+			}
+
+			let text = text.chars().take(COLUMNS - columns).collect::<String>();
+			columns += text.chars().count();
+
+			if matches!(token, Token::Plain) {
+				escape_into(&mut svg, &text);
+			} else {
+				let _ = write!(svg, r#"<tspan fill="{}">"#, token.color());
+				escape_into(&mut svg, &text);
+				svg.push_str("</tspan>");
+			}
+		}
+
+		svg.push_str("</tspan>");
+	}
+
+	svg.push_str("</text></svg>");
+
+	svg
+}
+
+/// Expands the tabs of a line into spaces, cutting it at the last column we render
+fn expand_tabs(line: &str) -> String {
+	let mut expanded = String::with_capacity(line.len().min(COLUMNS));
+	let mut column = 0;
+
+	for c in line.chars() {
+		if column >= COLUMNS {
+			break;
+		}
+
+		if c == '\t' {
+			let width = (TAB_WIDTH - column % TAB_WIDTH).min(COLUMNS - column);
+			expanded.extend(std::iter::repeat(' ').take(width));
+			column += width;
+		} else {
+			expanded.push(c);
+			column += 1;
+		}
+	}
+
+	expanded
+}
+
+fn escape_into(svg: &mut String, text: &str) {
+	for c in text.chars() {
+		match c {
+			'&' => svg.push_str("&amp;"),
+			'<' => svg.push_str("&lt;"),
+			'>' => svg.push_str("&gt;"),
+			// Characters that aren't allowed in XML documents
+			c if c.is_control() || c == '\u{fffe}' || c == '\u{ffff}' => svg.push(' '),
+			c => svg.push(c),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+	Plain,
+	Keyword,
+	String,
+	Number,
+	Comment,
+}
+
+impl Token {
+	const fn color(self) -> &'static str {
+		match self {
+			Self::Plain => "#d4d4d8",
+			Self::Keyword => "#c586c0",
+			Self::String => "#ce9178",
+			Self::Number => "#b5cea8",
+			Self::Comment => "#6a9955",
+		}
+	}
+}
+
+/// Just enough of a language's grammar to tell comments, strings, numbers and keywords apart
+struct Syntax {
+	line_comments: &'static [&'static str],
+	block_comment: Option<(&'static str, &'static str)>,
+	quotes: &'static [char],
+	keywords: &'static [&'static str],
+	case_insensitive: bool,
+}
+
+const C_LIKE_KEYWORDS: &[&str] = &[
+	"abstract",
+	"as",
+	"async",
+	"await",
+	"break",
+	"case",
+	"catch",
+	"class",
+	"const",
+	"continue",
+	"def",
+	"default",
+	"defer",
+	"do",
+	"else",
+	"enum",
+	"export",
+	"extends",
+	"extern",
+	"false",
+	"final",
+	"finally",
+	"fn",
+	"for",
+	"from",
+	"func",
+	"function",
+	"go",
+	"if",
+	"impl",
+	"implements",
+	"import",
+	"in",
+	"interface",
+	"let",
+	"loop",
+	"match",
+	"mod",
+	"module",
+	"mut",
+	"namespace",
+	"new",
+	"nil",
+	"null",
+	"object",
+	"of",
+	"override",
+	"package",
+	"private",
+	"protected",
+	"pub",
+	"public",
+	"return",
+	"self",
+	"static",
+	"struct",
+	"super",
+	"switch",
+	"this",
+	"throw",
+	"throws",
+	"trait",
+	"true",
+	"try",
+	"type",
+	"typeof",
+	"union",
+	"use",
+	"using",
+	"val",
+	"var",
+	"void",
+	"where",
+	"while",
+	"yield",
+];
+
+const SCRIPT_KEYWORDS: &[&str] = &[
+	"and", "as", "begin", "break", "case", "class", "continue", "def", "del", "do", "done", "elif",
+	"else", "elsif", "end", "esac", "except", "export", "false", "fi", "finally", "for", "foreach",
+	"from", "function", "global", "if", "import", "in", "is", "lambda", "local", "module", "my",
+	"next", "nil", "None", "not", "or", "param", "pass", "proc", "raise", "require", "rescue",
+	"return", "self", "sub", "then", "True", "true", "False", "try", "unless", "until", "use",
+	"var", "while", "with", "yield",
+];
+
+const SQL_KEYWORDS: &[&str] = &[
+	"add",
+	"alter",
+	"and",
+	"as",
+	"asc",
+	"between",
+	"by",
+	"case",
+	"create",
+	"delete",
+	"desc",
+	"distinct",
+	"drop",
+	"else",
+	"end",
+	"exists",
+	"from",
+	"group",
+	"having",
+	"in",
+	"index",
+	"inner",
+	"insert",
+	"into",
+	"is",
+	"join",
+	"key",
+	"left",
+	"like",
+	"limit",
+	"not",
+	"null",
+	"on",
+	"or",
+	"order",
+	"primary",
+	"references",
+	"right",
+	"select",
+	"set",
+	"table",
+	"then",
+	"union",
+	"update",
+	"values",
+	"view",
+	"when",
+	"where",
+	"with",
+];
+
+const ML_KEYWORDS: &[&str] = &[
+	"case",
+	"class",
+	"data",
+	"deriving",
+	"do",
+	"else",
+	"end",
+	"exception",
+	"fun",
+	"function",
+	"if",
+	"import",
+	"in",
+	"instance",
+	"let",
+	"match",
+	"module",
+	"of",
+	"open",
+	"rec",
+	"then",
+	"type",
+	"val",
+	"where",
+	"with",
+];
+
+const LUA_KEYWORDS: &[&str] = &[
+	"and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+	"nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+const C_LIKE: Syntax = Syntax {
+	line_comments: &["//"],
+	block_comment: Some(("/*", "*/")),
+	quotes: &['"', '\'', '`'],
+	keywords: C_LIKE_KEYWORDS,
+	case_insensitive: false,
+};
+
+const CSS: Syntax = Syntax {
+	line_comments: &["//"],
+	block_comment: Some(("/*", "*/")),
+	quotes: &['"', '\''],
+	keywords: &["@import", "@media", "@mixin", "@include", "!important"],
+	case_insensitive: false,
+};
+
+const SCRIPT: Syntax = Syntax {
+	line_comments: &["#"],
+	block_comment: None,
+	quotes: &['"', '\''],
+	keywords: SCRIPT_KEYWORDS,
+	case_insensitive: false,
+};
+
+const POWERSHELL: Syntax = Syntax {
+	line_comments: &["#"],
+	block_comment: Some(("<#", "#>")),
+	quotes: &['"', '\''],
+	keywords: SCRIPT_KEYWORDS,
+	case_insensitive: true,
+};
+
+const PHP: Syntax = Syntax {
+	line_comments: &["//", "#"],
+	block_comment: Some(("/*", "*/")),
+	quotes: &['"', '\''],
+	keywords: C_LIKE_KEYWORDS,
+	case_insensitive: true,
+};
+
+const SQL: Syntax = Syntax {
+	line_comments: &["--"],
+	block_comment: Some(("/*", "*/")),
+	quotes: &['\''],
+	keywords: SQL_KEYWORDS,
+	case_insensitive: true,
+};
+
+const LUA: Syntax = Syntax {
+	line_comments: &["--"],
+	block_comment: Some(("--[[", "]]")),
+	quotes: &['"', '\''],
+	keywords: LUA_KEYWORDS,
+	case_insensitive: false,
+};
+
+const HASKELL: Syntax = Syntax {
+	line_comments: &["--"],
+	block_comment: Some(("{-", "-}")),
+	quotes: &['"'],
+	keywords: ML_KEYWORDS,
+	case_insensitive: false,
+};
+
+const OCAML: Syntax = Syntax {
+	line_comments: &[],
+	block_comment: Some(("(*", "*)")),
+	quotes: &['"'],
+	keywords: ML_KEYWORDS,
+	case_insensitive: false,
+};
+
+const APPLESCRIPT: Syntax = Syntax {
+	line_comments: &["--", "#"],
+	block_comment: Some(("(*", "*)")),
+	quotes: &['"'],
+	keywords: &[
+		"end", "else", "if", "on", "repeat", "return", "set", "tell", "then", "to", "with",
+	],
+	case_insensitive: true,
+};
+
+const MARKUP: Syntax = Syntax {
+	line_comments: &[],
+	block_comment: Some(("<!--", "-->")),
+	quotes: &['"', '\''],
+	keywords: &[],
+	case_insensitive: false,
+};
+
+const JSON: Syntax = Syntax {
+	line_comments: &["//"],
+	block_comment: Some(("/*", "*/")),
+	quotes: &['"'],
+	keywords: &["true", "false", "null"],
+	case_insensitive: false,
+};
+
+const PROPERTIES: Syntax = Syntax {
+	line_comments: &["#", ";"],
+	block_comment: None,
+	quotes: &['"', '\''],
+	keywords: &["true", "false", "yes", "no", "on", "off", "null"],
+	case_insensitive: false,
+};
+
+const fn syntax_for(extension: &Extension) -> Option<&'static Syntax> {
+	match extension {
+		Extension::Code(code) => Some(match code {
+			CodeExtension::Scpt | CodeExtension::Scptd | CodeExtension::Applescript => &APPLESCRIPT,
+			CodeExtension::Sh
+			| CodeExtension::Zsh
+			| CodeExtension::Fish
+			| CodeExtension::Bash
+			| CodeExtension::Rb
+			| CodeExtension::Cr
+			| CodeExtension::Dockerfile
+			| CodeExtension::Make
+			| CodeExtension::Nim
+			| CodeExtension::Nims
+			| CodeExtension::Pl
+			| CodeExtension::Py
+			| CodeExtension::R => &SCRIPT,
+			CodeExtension::Ps1 | CodeExtension::Psd1 | CodeExtension::Psm1 => &POWERSHELL,
+			CodeExtension::Php
+			| CodeExtension::Php1
+			| CodeExtension::Php2
+			| CodeExtension::Php3
+			| CodeExtension::Php4
+			| CodeExtension::Php5
+			| CodeExtension::Php6
+			| CodeExtension::Phps
+			| CodeExtension::Phpt
+			| CodeExtension::Phtml => &PHP,
+			CodeExtension::Css
+			| CodeExtension::Sass
+			| CodeExtension::Scss
+			| CodeExtension::Less => &CSS,
+			CodeExtension::Html
+			| CodeExtension::Vue
+			| CodeExtension::Astro
+			| CodeExtension::Mdx => &MARKUP,
+			CodeExtension::Sql => &SQL,
+			CodeExtension::Lua => &LUA,
+			CodeExtension::Hs => &HASKELL,
+			CodeExtension::Ml | CodeExtension::Mli | CodeExtension::Mll | CodeExtension::Mly => {
+				&OCAML
+			}
+			_ => &C_LIKE,
+		}),
+		Extension::Config(config) => match config {
+			ConfigExtension::Json | ConfigExtension::Tsconfig => Some(&JSON),
+			ConfigExtension::Ini
+			| ConfigExtension::Cfg
+			| ConfigExtension::Toml
+			| ConfigExtension::Yaml
+			| ConfigExtension::Yml
+			| ConfigExtension::Compose => Some(&PROPERTIES),
+			ConfigExtension::Xml | ConfigExtension::Mathml | ConfigExtension::Rss => Some(&MARKUP),
+			ConfigExtension::Csv => None,
+		},
+		_ => None,
+	}
+}
+
+/// Splits every line in spans of the same token, keeping track of block comments across lines
+fn highlight(lines: &[String], syntax: &Syntax) -> Vec<Vec<(Token, String)>> {
+	let mut block_comment_end = None;
+
+	lines
+		.iter()
+		.map(|line| {
+			let mut spans = Vec::<(Token, String)>::new();
+			let mut push = |token: Token, text: &str| match spans.last_mut() {
+				Some((last, last_text)) if *last == token => last_text.push_str(text),
+				_ => spans.push((token, text.to_string())),
+			};
+
+			let mut rest = line.as_str();
+
+			while let Some(c) = rest.chars().next() {
+				if let Some(end) = block_comment_end {
+					if let Some(idx) = rest.find(end) {
+						push(Token::Comment, &rest[..idx + end.len()]);
+						rest = &rest[idx + end.len()..];
+						block_comment_end = None;
+					} else {
+						push(Token::Comment, rest);
+						break;
+					}
+				} else if let Some((start, end)) = syntax
+					.block_comment
+					.filter(|(start, _)| rest.starts_with(start))
+				{
+					push(Token::Comment, start);
+					rest = &rest[start.len()..];
+					block_comment_end = Some(end);
+				} else if syntax
+					.line_comments
+					.iter()
+					.any(|prefix| rest.starts_with(prefix))
+				{
+					push(Token::Comment, rest);
+					break;
+				} else if let Some(end) = syntax
+					.quotes
+					.contains(&c)
+					.then(|| string_end(rest, c))
+					.flatten()
+				{
+					push(Token::String, &rest[..end]);
+					rest = &rest[end..];
+				} else if c.is_ascii_digit() {
+					let end = rest
+						.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+						.unwrap_or(rest.len());
+					push(Token::Number, &rest[..end]);
+					rest = &rest[end..];
+				} else if c.is_alphabetic() || c == '_' || c == '@' || c == '!' {
+					let end = rest
+						.char_indices()
+						.skip(1)
+						.find(|&(_, c)| !(c.is_alphanumeric() || c == '_'))
+						.map_or(rest.len(), |(idx, _)| idx);
+					let word = &rest[..end];
+
+					let is_keyword = syntax.keywords.iter().any(|keyword| {
+						if syntax.case_insensitive {
+							keyword.eq_ignore_ascii_case(word)
+						} else {
+							*keyword == word
+						}
+					});
+
+					push(
+						if is_keyword {
+							Token::Keyword
+						} else {
+							Token::Plain
+						},
+						word,
+					);
+					rest = &rest[end..];
+				} else {
+					push(Token::Plain, &rest[..c.len_utf8()]);
+					rest = &rest[c.len_utf8()..];
+				}
+			}
+
+			spans
+		})
+		.collect()
+}
+
+/// Index right after the closing quote, unterminated quotes are most likely something else,
+/// like Rust lifetimes or apostrophes in comments
+fn string_end(text: &str, quote: char) -> Option<usize> {
+	let mut escaped = false;
+
+	for (idx, c) in text.char_indices().skip(1) {
+		match c {
+			_ if escaped => escaped = false,
+			'\\' => escaped = true,
+			c if c == quote => return Some(idx + c.len_utf8()),
+			_ => {}
+		}
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_highlight() {
+		let lines = [
+			"fn main<'a>() { // entry",
+			"\tlet s = \"a \\\" b\"; /* multi",
+			"line */ 42",
+		]
+		.map(expand_tabs);
+
+		assert_eq!(
+			highlight(&lines, &C_LIKE),
+			vec![
+				vec![
+					(Token::Keyword, "fn".to_string()),
+					(Token::Plain, " main<'a>() { ".to_string()),
+					(Token::Comment, "// entry".to_string()),
+				],
+				vec![
+					(Token::Plain, "    ".to_string()),
+					(Token::Keyword, "let".to_string()),
+					(Token::Plain, " s = ".to_string()),
+					(Token::String, "\"a \\\" b\"".to_string()),
+					(Token::Plain, "; ".to_string()),
+					(Token::Comment, "/* multi".to_string()),
+				],
+				vec![
+					(Token::Comment, "line */".to_string()),
+					(Token::Plain, " ".to_string()),
+					(Token::Number, "42".to_string()),
+				],
+			]
+		);
+	}
+
+	#[test]
+	fn test_expand_tabs() {
+		assert_eq!(expand_tabs("a\tbc\td"), "a   bc  d");
+		assert_eq!(expand_tabs(&"\t".repeat(COLUMNS)), " ".repeat(COLUMNS));
+		assert_eq!(expand_tabs(&"x".repeat(COLUMNS * 2)), "x".repeat(COLUMNS));
+	}
+
+	#[test]
+	fn test_render_svg() {
+		let svg = render_svg(
+			"<a href=\"x\">&</a>\u{0}",
+			&Extension::Config(ConfigExtension::Xml),
+		);
+
+		assert!(
+			svg.contains("&lt;a href=<tspan fill=\"#ce9178\">\"x\"</tspan>&gt;&amp;&lt;/a&gt; ")
+		);
+		assert!(svg.ends_with("</tspan></text></svg>"));
+	}
+}
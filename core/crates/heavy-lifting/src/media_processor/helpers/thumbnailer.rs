@@ -1,16 +1,28 @@
 use crate::media_processor::thumbnailer;
 
+use super::{epub, text_preview};
+
 use sd_core_prisma_helpers::CasId;
 
 use sd_file_ext::extensions::{
-	DocumentExtension, Extension, ImageExtension, ALL_DOCUMENT_EXTENSIONS, ALL_IMAGE_EXTENSIONS,
+	BookExtension, CodeExtension, ConfigExtension, DocumentExtension, Extension, ImageExtension,
+	TextExtension, ALL_CODE_EXTENSIONS, ALL_CONFIG_EXTENSIONS, ALL_DOCUMENT_EXTENSIONS,
+	ALL_IMAGE_EXTENSIONS, ALL_TEXT_EXTENSIONS,
 };
-use sd_images::{format_image, scale_dimensions, ConvertibleExtension};
+use sd_images::{format_image, render_svg, scale_dimensions, ConvertibleExtension};
 use sd_media_metadata::exif::Orientation;
 use sd_utils::error::FileIOError;
 
 #[cfg(feature = "ffmpeg")]
-use sd_file_ext::extensions::{VideoExtension, ALL_VIDEO_EXTENSIONS};
+use super::video_preview;
+
+#[cfg(feature = "ffmpeg")]
+use tracing::warn;
+
+#[cfg(feature = "ffmpeg")]
+use sd_file_ext::extensions::{
+	AudioExtension, VideoExtension, ALL_AUDIO_EXTENSIONS, ALL_VIDEO_EXTENSIONS,
+};
 
 use std::{
 	ops::Deref,
@@ -50,6 +62,11 @@ pub const TARGET_QUALITY: f32 = 60.0;
 /// How much time we allow for the thumbnailer task to complete before we give up.
 pub const THUMBNAILER_TASK_TIMEOUT: Duration = Duration::from_secs(60 * 5);
 
+/// How long a video we failed to generate previews for waits before they are tried again, as
+/// failures can come from transient issues like the file being unreachable for a moment
+#[cfg(feature = "ffmpeg")]
+const VIDEO_PREVIEWS_RETRY_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 pub fn get_thumbnails_directory(data_directory: impl AsRef<Path>) -> PathBuf {
 	data_directory.as_ref().join(THUMBNAIL_CACHE_DIR_NAME)
 }
@@ -64,6 +81,16 @@ pub static THUMBNAILABLE_VIDEO_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
 		.collect()
 });
 
+#[cfg(feature = "ffmpeg")]
+pub static THUMBNAILABLE_AUDIO_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
+	ALL_AUDIO_EXTENSIONS
+		.iter()
+		.copied()
+		.filter(|&ext| can_generate_thumbnail_for_audio(ext))
+		.map(Extension::Audio)
+		.collect()
+});
+
 pub static THUMBNAILABLE_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
 	ALL_IMAGE_EXTENSIONS
 		.iter()
@@ -77,6 +104,10 @@ pub static THUMBNAILABLE_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
 				.filter(|&ext| can_generate_thumbnail_for_document(ext))
 				.map(Extension::Document),
 		)
+		.chain([Extension::Book(BookExtension::Epub)])
+		.chain(ALL_TEXT_EXTENSIONS.iter().copied().map(Extension::Text))
+		.chain(ALL_CODE_EXTENSIONS.iter().copied().map(Extension::Code))
+		.chain(ALL_CONFIG_EXTENSIONS.iter().copied().map(Extension::Config))
 		.collect()
 });
 
@@ -86,6 +117,7 @@ pub static ALL_THUMBNAILABLE_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
 		.iter()
 		.cloned()
 		.chain(THUMBNAILABLE_VIDEO_EXTENSIONS.iter().cloned())
+		.chain(THUMBNAILABLE_AUDIO_EXTENSIONS.iter().cloned())
 		.collect();
 
 	#[cfg(not(feature = "ffmpeg"))]
@@ -156,6 +188,38 @@ impl ThumbnailKind {
 	}
 }
 
+/// Files generated for videos to scrub through them, stored next to the video thumbnail
+/// and named after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoPreviewFile {
+	/// Sprite sheet with frames evenly spaced along the video
+	ThumbstripSheet,
+	/// JSON index telling where each frame is in the sheet, and when it happens in the video
+	ThumbstripIndex,
+	/// Short animated webp going through the same frames
+	Animated,
+	/// Empty marker left when the previews can't be generated, so they aren't tried again until
+	/// it's older than a retry interval
+	Failed,
+}
+
+impl VideoPreviewFile {
+	#[must_use]
+	pub const fn extension(self) -> &'static str {
+		match self {
+			Self::ThumbstripSheet => "strip.webp",
+			Self::ThumbstripIndex => "strip.json",
+			Self::Animated => "preview.webp",
+			Self::Failed => "preview.failed",
+		}
+	}
+
+	#[must_use]
+	pub fn path_from_thumbnail(self, thumbnail_path: impl AsRef<Path>) -> PathBuf {
+		thumbnail_path.as_ref().with_extension(self.extension())
+	}
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GenerateThumbnailArgs<'cas_id> {
 	pub extension: String,
@@ -198,15 +262,36 @@ pub const fn can_generate_thumbnail_for_video(video_extension: VideoExtension) -
 	!matches!(video_extension, Mpg | Swf | M2v | Hevc | M2ts | Mts | Ts)
 }
 
+/// Only formats that usually have cover art, which is the only thing we can make a thumbnail from
+#[cfg(feature = "ffmpeg")]
+#[must_use]
+pub const fn can_generate_thumbnail_for_audio(audio_extension: AudioExtension) -> bool {
+	use AudioExtension::{Aif, Aiff, Flac, M4a, Mp3, Oga, Ogg, Opus, Wma, Wv};
+
+	matches!(
+		audio_extension,
+		Mp3 | M4a | Flac | Ogg | Oga | Opus | Wma | Aiff | Aif | Wv
+	)
+}
+
 #[must_use]
 pub const fn can_generate_thumbnail_for_image(image_extension: ImageExtension) -> bool {
 	use ImageExtension::{
-		Avif, Bmp, Gif, Heic, Heics, Heif, Heifs, Ico, Jpeg, Jpg, Png, Svg, Webp,
+		Arw, Avif, Bmp, Cr2, Dcr, Dng, Gif, Heic, Heics, Heif, Heifs, Ico, Jpeg, Jpg, Nef, Nwr,
+		Png, Rw2, Svg, Tiff, Webp,
 	};
 
+	// Camera RAW files and TIFF are handled through their embedded previews, when they have one
 	matches!(
 		image_extension,
-		Jpg | Jpeg | Png | Webp | Gif | Svg | Heic | Heics | Heif | Heifs | Avif | Bmp | Ico
+		Jpg | Jpeg
+			| Png | Webp | Gif
+			| Svg | Heic | Heics
+			| Heif | Heifs
+			| Avif | Bmp | Ico
+			| Tiff | Dng | Cr2
+			| Dcr | Nwr | Nef
+			| Arw | Rw2
 	)
 }
 
@@ -217,10 +302,26 @@ pub const fn can_generate_thumbnail_for_document(document_extension: DocumentExt
 	matches!(document_extension, Pdf)
 }
 
+/// Every text, code and config file can have a thumbnail, rendered from its content
+fn text_extension(extension: &str) -> Option<Extension> {
+	TextExtension::from_str(extension)
+		.map(Extension::Text)
+		.or_else(|_| CodeExtension::from_str(extension).map(Extension::Code))
+		.or_else(|_| ConfigExtension::from_str(extension).map(Extension::Config))
+		.ok()
+}
+
 #[derive(Debug)]
 pub enum GenerationStatus {
-	Generated,
+	/// `with_video_previews` tells if the thumbnail of a video came along with its previews
+	Generated {
+		with_video_previews: bool,
+	},
+	/// The thumbnail already existed, but the previews of its video were missing and generated
+	GeneratedVideoPreviews,
 	Skipped,
+	/// The file has nothing to make a thumbnail from, like an audio file without cover art
+	Unavailable,
 }
 
 #[instrument(skip(thumbnails_directory, cas_id, should_regenerate, kind))]
@@ -258,13 +359,26 @@ pub async fn generate_thumbnail(
 		}
 	// Otherwise we good, thumbnail doesn't exist so we can generate it
 	} else if !should_regenerate {
-		trace!("Skipping thumbnail generation because it already exists");
+		// Thumbnails made before videos had previews only get the previews, not a new thumbnail
+		#[cfg(feature = "ffmpeg")]
+		let status = if missing_video_previews(extension, &output_path, kind).await
+			&& generate_video_previews(path, &output_path).await
+		{
+			GenerationStatus::GeneratedVideoPreviews
+		} else {
+			trace!("Skipping thumbnail generation because it already exists");
+			GenerationStatus::Skipped
+		};
+
+		#[cfg(not(feature = "ffmpeg"))]
+		let status = {
+			trace!("Skipping thumbnail generation because it already exists");
+			GenerationStatus::Skipped
+		};
+
 		return (
 			start.elapsed(),
-			Ok((
-				ThumbKey::new(cas_id.to_owned(), kind),
-				GenerationStatus::Skipped,
-			)),
+			Ok((ThumbKey::new(cas_id.to_owned(), kind), status)),
 		);
 	}
 
@@ -284,12 +398,49 @@ pub async fn generate_thumbnail(
 			}
 			trace!("Generating document thumbnail");
 		}
+	} else if let Ok(BookExtension::Epub) = BookExtension::from_str(extension) {
+		trace!("Generating book cover thumbnail");
+		match generate_book_cover_thumbnail(&path, &output_path).await {
+			Ok(true) => trace!("Generated book cover thumbnail"),
+			Ok(false) => {
+				trace!("Book has no cover");
+				return (
+					start.elapsed(),
+					Ok((
+						ThumbKey::new(cas_id.to_owned(), kind),
+						GenerationStatus::Unavailable,
+					)),
+				);
+			}
+			Err(e) => return (start.elapsed(), Err(e)),
+		}
+	} else if let Some(extension) = text_extension(extension) {
+		trace!("Generating text preview thumbnail");
+		match generate_text_thumbnail(&path, &output_path, extension).await {
+			Ok(true) => trace!("Generated text preview thumbnail"),
+			Ok(false) => {
+				trace!("File has no text to preview");
+				return (
+					start.elapsed(),
+					Ok((
+						ThumbKey::new(cas_id.to_owned(), kind),
+						GenerationStatus::Unavailable,
+					)),
+				);
+			}
+			Err(e) => return (start.elapsed(), Err(e)),
+		}
 	}
 
+	#[cfg_attr(not(feature = "ffmpeg"), allow(unused_mut))]
+	let mut with_video_previews = false;
+
 	#[cfg(feature = "ffmpeg")]
 	{
-		use crate::media_processor::helpers::thumbnailer::can_generate_thumbnail_for_video;
-		use sd_file_ext::extensions::VideoExtension;
+		use crate::media_processor::helpers::thumbnailer::{
+			can_generate_thumbnail_for_audio, can_generate_thumbnail_for_video,
+		};
+		use sd_file_ext::extensions::{AudioExtension, VideoExtension};
 
 		if let Ok(extension) = VideoExtension::from_str(extension) {
 			if can_generate_thumbnail_for_video(extension) {
@@ -298,6 +449,30 @@ pub async fn generate_thumbnail(
 					return (start.elapsed(), Err(e));
 				}
 				trace!("Generated video thumbnail");
+
+				// Scrubbing through videos is only a thing for libraries, ephemeral thumbnails
+				// are meant to be cheap
+				if matches!(kind, ThumbnailKind::Indexed(_)) {
+					with_video_previews = generate_video_previews(path, &output_path).await;
+				}
+			}
+		} else if let Ok(extension) = AudioExtension::from_str(extension) {
+			if can_generate_thumbnail_for_audio(extension) {
+				trace!("Generating audio cover art thumbnail");
+				match generate_audio_thumbnail(&path, &output_path).await {
+					Ok(true) => trace!("Generated audio cover art thumbnail"),
+					Ok(false) => {
+						trace!("Audio file has no cover art");
+						return (
+							start.elapsed(),
+							Ok((
+								ThumbKey::new(cas_id.to_owned(), kind),
+								GenerationStatus::Unavailable,
+							)),
+						);
+					}
+					Err(e) => return (start.elapsed(), Err(e)),
+				}
 			}
 		}
 	}
@@ -308,18 +483,110 @@ pub async fn generate_thumbnail(
 		start.elapsed(),
 		Ok((
 			ThumbKey::new(cas_id.to_owned(), kind),
-			GenerationStatus::Generated,
+			GenerationStatus::Generated {
+				with_video_previews,
+			},
 		)),
 	)
 }
 
+/// Previews aren't needed to show a video, so failing to generate them doesn't fail its thumbnail,
+/// it only leaves a marker for them not to be tried again before [`VIDEO_PREVIEWS_RETRY_INTERVAL`].
+///
+/// Returns if the previews were generated.
+#[cfg(feature = "ffmpeg")]
+async fn generate_video_previews(file_path: &Path, thumbnail_path: &Path) -> bool {
+	trace!("Generating video previews");
+
+	let marker_path = VideoPreviewFile::Failed.path_from_thumbnail(thumbnail_path);
+
+	match video_preview::generate_video_previews(file_path, thumbnail_path).await {
+		Ok(()) => {
+			trace!("Generated video previews");
+
+			// A retry after an earlier failure succeeded
+			if let Err(e) = fs::remove_file(&marker_path).await {
+				if e.kind() != io::ErrorKind::NotFound {
+					error!(
+						e = ?FileIOError::from((marker_path, e)),
+						"Failed to remove the failed video previews marker;",
+					);
+				}
+			}
+
+			true
+		}
+		Err(e) => {
+			warn!(?e, "Failed to generate video previews;");
+
+			// Rewriting the marker restarts the retry interval
+			if let Err(e) = fs::write(&marker_path, []).await {
+				error!(
+					e = ?FileIOError::from((marker_path, e)),
+					"Failed to mark video previews as failed;",
+				);
+			}
+
+			false
+		}
+	}
+}
+
+/// Video thumbnails in libraries come with previews, older ones get them generated
+#[cfg(feature = "ffmpeg")]
+async fn missing_video_previews(
+	extension: &str,
+	thumbnail_path: &Path,
+	kind: &ThumbnailKind,
+) -> bool {
+	if !matches!(kind, ThumbnailKind::Indexed(_))
+		|| !VideoExtension::from_str(extension).is_ok_and(can_generate_thumbnail_for_video)
+	{
+		return false;
+	}
+
+	// The index is written last
+	if fs::metadata(VideoPreviewFile::ThumbstripIndex.path_from_thumbnail(thumbnail_path))
+		.await
+		.is_ok()
+	{
+		return false;
+	}
+
+	// Videos we failed to make previews for are only tried again after a while
+	fs::metadata(VideoPreviewFile::Failed.path_from_thumbnail(thumbnail_path))
+		.await
+		.and_then(|metadata| metadata.modified())
+		.map_or(true, |failed_at| {
+			failed_at
+				.elapsed()
+				.map_or(true, |elapsed| elapsed >= VIDEO_PREVIEWS_RETRY_INTERVAL)
+		})
+}
+
 fn inner_generate_image_thumbnail(
 	file_path: &PathBuf,
 ) -> Result<Vec<u8>, thumbnailer::NonCriticalThumbnailerError> {
-	let mut img = format_image(file_path).map_err(|e| {
+	let mut img = scale_image(format_image(file_path).map_err(|e| {
 		thumbnailer::NonCriticalThumbnailerError::FormatImage(file_path.clone(), e.to_string())
-	})?;
+	})?);
 
+	// this corrects the rotation/flip of the image based on the *available* exif data
+	// not all images have exif data, so we don't error. we also don't rotate HEIF as that's against the spec
+	if let Some(orientation) = Orientation::from_path(file_path) {
+		if ConvertibleExtension::try_from(file_path.as_ref())
+			// Cameras store RAW previews unrotated, so the orientation of the RAW file applies to them
+			.map_or(true, ConvertibleExtension::should_rotate)
+		{
+			img = orientation.correct_thumbnail(img);
+		}
+	}
+
+	encode_webp(&img, file_path)
+}
+
+/// Resizes the image to our thumbnails target size
+fn scale_image(mut img: DynamicImage) -> DynamicImage {
 	let (w, h) = img.dimensions();
 
 	#[allow(clippy::cast_precision_loss)]
@@ -335,21 +602,17 @@ fn inner_generate_image_thumbnail(
 		));
 	}
 
-	// this corrects the rotation/flip of the image based on the *available* exif data
-	// not all images have exif data, so we don't error. we also don't rotate HEIF as that's against the spec
-	if let Some(orientation) = Orientation::from_path(file_path) {
-		if ConvertibleExtension::try_from(file_path.as_ref())
-			.expect("we already checked if the image was convertible")
-			.should_rotate()
-		{
-			img = orientation.correct_thumbnail(img);
-		}
-	}
+	img
+}
 
+pub(super) fn encode_webp(
+	img: &DynamicImage,
+	file_path: &Path,
+) -> Result<Vec<u8>, thumbnailer::NonCriticalThumbnailerError> {
 	// Create the WebP encoder for the above image
-	let encoder = Encoder::from_image(&img).map_err(|reason| {
+	let encoder = Encoder::from_image(img).map_err(|reason| {
 		thumbnailer::NonCriticalThumbnailerError::WebPEncoding(
-			file_path.clone(),
+			file_path.to_path_buf(),
 			reason.to_string(),
 		)
 	})?;
@@ -415,8 +678,14 @@ async fn generate_image_thumbnail(
 
 	trace!("Generated thumbnail bytes");
 
-	let output_path = output_path.as_ref();
+	write_thumbnail(file_path, output_path.as_ref(), &webp).await
+}
 
+pub(super) async fn write_thumbnail(
+	file_path: PathBuf,
+	output_path: &Path,
+	webp: &[u8],
+) -> Result<(), thumbnailer::NonCriticalThumbnailerError> {
 	if let Some(shard_dir) = output_path.parent() {
 		fs::create_dir_all(shard_dir).await.map_err(|e| {
 			thumbnailer::NonCriticalThumbnailerError::CreateShardDirectory(
@@ -429,7 +698,7 @@ async fn generate_image_thumbnail(
 
 	trace!("Created shard directory and writing it to disk");
 
-	let res = fs::write(output_path, &webp).await.map_err(|e| {
+	let res = fs::write(output_path, webp).await.map_err(|e| {
 		thumbnailer::NonCriticalThumbnailerError::SaveThumbnail(
 			file_path,
 			FileIOError::from((output_path, e)).to_string(),
@@ -471,6 +740,146 @@ async fn generate_video_thumbnail(
 	})
 }
 
+/// Returns `false` if the book has no cover
+#[instrument(
+	skip_all,
+	fields(
+		input_path = %file_path.as_ref().display(),
+		output_path = %output_path.as_ref().display()
+	)
+)]
+async fn generate_book_cover_thumbnail(
+	file_path: impl AsRef<Path> + Send,
+	output_path: impl AsRef<Path> + Send,
+) -> Result<bool, thumbnailer::NonCriticalThumbnailerError> {
+	let file_path = file_path.as_ref().to_path_buf();
+
+	let Some(cover) = epub::extract_cover(&file_path).await.map_err(|e| {
+		thumbnailer::NonCriticalThumbnailerError::BookCoverExtraction(
+			file_path.clone(),
+			e.to_string(),
+		)
+	})?
+	else {
+		return Ok(false);
+	};
+
+	let webp = spawn_blocking({
+		let file_path = file_path.clone();
+		move || {
+			let img = image::load_from_memory(&cover).map_err(|e| {
+				thumbnailer::NonCriticalThumbnailerError::FormatImage(
+					file_path.clone(),
+					e.to_string(),
+				)
+			})?;
+
+			encode_webp(&scale_image(img), &file_path)
+		}
+	})
+	.await
+	.map_err(|e| {
+		thumbnailer::NonCriticalThumbnailerError::PanicWhileGeneratingThumbnail(
+			file_path.clone(),
+			e.to_string(),
+		)
+	})??;
+
+	write_thumbnail(file_path, output_path.as_ref(), &webp)
+		.await
+		.map(|()| true)
+}
+
+/// Returns `false` if the file isn't text or is blank
+#[instrument(
+	skip_all,
+	fields(
+		input_path = %file_path.as_ref().display(),
+		output_path = %output_path.as_ref().display()
+	)
+)]
+async fn generate_text_thumbnail(
+	file_path: impl AsRef<Path> + Send,
+	output_path: impl AsRef<Path> + Send,
+	extension: Extension,
+) -> Result<bool, thumbnailer::NonCriticalThumbnailerError> {
+	let file_path = file_path.as_ref().to_path_buf();
+
+	let Some(snippet) = text_preview::read_snippet(&file_path, text_preview::PREVIEW_READ_LENGTH)
+		.await
+		.map_err(|e| {
+			thumbnailer::NonCriticalThumbnailerError::TextPreview(file_path.clone(), e.to_string())
+		})?
+		.filter(|snippet| !snippet.text.trim().is_empty())
+	else {
+		return Ok(false);
+	};
+
+	let webp = spawn_blocking({
+		let file_path = file_path.clone();
+		move || {
+			let svg = text_preview::render_svg(&snippet.text, &extension);
+
+			// Rendered pages are already at a good size for a thumbnail, so we don't scale them
+			let img = render_svg(svg.as_bytes()).map_err(|e| {
+				thumbnailer::NonCriticalThumbnailerError::TextPreview(
+					file_path.clone(),
+					e.to_string(),
+				)
+			})?;
+
+			encode_webp(&img, &file_path)
+		}
+	})
+	.await
+	.map_err(|e| {
+		thumbnailer::NonCriticalThumbnailerError::PanicWhileGeneratingThumbnail(
+			file_path.clone(),
+			e.to_string(),
+		)
+	})??;
+
+	write_thumbnail(file_path, output_path.as_ref(), &webp)
+		.await
+		.map(|()| true)
+}
+
+/// Returns `false` if the file has no cover art
+#[instrument(
+	skip_all,
+	fields(
+		input_path = %file_path.as_ref().display(),
+		output_path = %output_path.as_ref().display()
+	)
+)]
+#[cfg(feature = "ffmpeg")]
+async fn generate_audio_thumbnail(
+	file_path: impl AsRef<Path> + Send,
+	output_path: impl AsRef<Path> + Send,
+) -> Result<bool, thumbnailer::NonCriticalThumbnailerError> {
+	use sd_ffmpeg::{attached_picture_to_thumbnail, Error, ThumbnailSize};
+
+	let file_path = file_path.as_ref();
+
+	match attached_picture_to_thumbnail(
+		file_path,
+		output_path,
+		ThumbnailSize::Scale(1024),
+		TARGET_QUALITY,
+	)
+	.await
+	{
+		Ok(()) => Ok(true),
+		Err(Error::NoAttachedPicture) => Ok(false),
+		Err(e) => Err(
+			thumbnailer::NonCriticalThumbnailerError::AudioThumbnailGenerationFailed(
+				file_path.to_path_buf(),
+				e.to_string(),
+			),
+		),
+	}
+}
+
 const HALF_SEC: Duration = Duration::from_millis(500);
 static LAST_SINGLE_THUMB_GENERATED_LOCK: Lazy<Mutex<Instant>> =
 	Lazy::new(|| Mutex::new(Instant::now()));
@@ -506,7 +915,10 @@ pub async fn generate_single_thumbnail(
 
 	let (_thumb_key, status) = res?;
 
-	if matches!(status, GenerationStatus::Generated) {
+	if matches!(
+		status,
+		GenerationStatus::Generated { .. } | GenerationStatus::GeneratedVideoPreviews
+	) {
 		*last_single_thumb_generated_guard = Instant::now();
 		drop(last_single_thumb_generated_guard); // Clippy was weirdly complaining about not doing an "early" drop here
 	}
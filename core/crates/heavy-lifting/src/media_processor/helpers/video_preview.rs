@@ -0,0 +1,256 @@
+use crate::media_processor::thumbnailer;
+
+use super::thumbnailer::{encode_webp, write_thumbnail, VideoPreviewFile};
+
+use sd_ffmpeg::{to_frames, Frame, ThumbnailSize};
+
+use std::{ops::Deref, path::Path};
+
+use image::{imageops, DynamicImage, GenericImageView, RgbImage};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::task::spawn_blocking;
+use tracing::instrument;
+use webp::{AnimEncoder, AnimFrame, WebPConfig};
+
+/// How many frames we take from each video, for both the thumbstrip and the animated preview
+const FRAMES_COUNT: usize = 20;
+
+/// Frames are laid out in rows of this many frames in the thumbstrip sheet
+const THUMBSTRIP_COLUMNS: u32 = 5;
+const THUMBSTRIP_FRAME_WIDTH: u32 = 160;
+
+/// Largest side of the animated preview
+const PREVIEW_SIZE: u32 = 320;
+const PREVIEW_FRAME_DURATION_MS: i32 = 400;
+/// Previews are played on hover, they don't need to look as good as a thumbnail
+const PREVIEW_QUALITY: f32 = 40.0;
+
+/// Written next to the thumbstrip sheet, so the frontend knows which frame to show while
+/// scrubbing through a video
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+pub struct ThumbstripIndex {
+	pub frame_width: u32,
+	pub frame_height: u32,
+	pub columns: u32,
+	pub rows: u32,
+	pub frames: Vec<ThumbstripFrame>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+pub struct ThumbstripFrame {
+	/// Position of the frame in the video, in seconds
+	pub timestamp: f64,
+	/// Top left corner of the frame in the sheet, in pixels
+	pub x: u32,
+	pub y: u32,
+}
+
+#[instrument(
+	skip_all,
+	fields(
+		input_path = %file_path.as_ref().display(),
+		thumbnail_path = %thumbnail_path.as_ref().display()
+	)
+)]
+pub async fn generate_video_previews(
+	file_path: impl AsRef<Path> + Send,
+	thumbnail_path: impl AsRef<Path> + Send,
+) -> Result<(), thumbnailer::NonCriticalThumbnailerError> {
+	let file_path = file_path.as_ref().to_path_buf();
+	let thumbnail_path = thumbnail_path.as_ref();
+
+	let frames = to_frames(&file_path, FRAMES_COUNT, ThumbnailSize::Scale(PREVIEW_SIZE))
+		.await
+		.map_err(|e| {
+			thumbnailer::NonCriticalThumbnailerError::VideoPreviewGenerationFailed(
+				file_path.clone(),
+				e.to_string(),
+			)
+		})?;
+
+	let (sheet, index, preview) = spawn_blocking({
+		let file_path = file_path.clone();
+		move || {
+			let preview_generation_failed = |reason: String| {
+				thumbnailer::NonCriticalThumbnailerError::VideoPreviewGenerationFailed(
+					file_path.clone(),
+					reason,
+				)
+			};
+
+			let (sheet, index) = build_thumbstrip(&frames).ok_or_else(|| {
+				preview_generation_failed("no frames to build the thumbstrip".to_string())
+			})?;
+
+			let index =
+				serde_json::to_vec(&index).map_err(|e| preview_generation_failed(e.to_string()))?;
+
+			let preview = encode_animated_preview(&frames).map_err(preview_generation_failed)?;
+
+			Ok::<_, thumbnailer::NonCriticalThumbnailerError>((
+				encode_webp(&sheet, &file_path)?,
+				index,
+				preview,
+			))
+		}
+	})
+	.await
+	.map_err(|e| {
+		thumbnailer::NonCriticalThumbnailerError::PanicWhileGeneratingThumbnail(
+			file_path.clone(),
+			e.to_string(),
+		)
+	})??;
+
+	write_thumbnail(
+		file_path.clone(),
+		&VideoPreviewFile::Animated.path_from_thumbnail(thumbnail_path),
+		&preview,
+	)
+	.await?;
+
+	write_thumbnail(
+		file_path.clone(),
+		&VideoPreviewFile::ThumbstripSheet.path_from_thumbnail(thumbnail_path),
+		&sheet,
+	)
+	.await?;
+
+	// The index goes last, as its existence is what tells us that the previews are complete
+	write_thumbnail(
+		file_path,
+		&VideoPreviewFile::ThumbstripIndex.path_from_thumbnail(thumbnail_path),
+		&index,
+	)
+	.await
+}
+
+/// Lays out the frames in a grid, scaled down to the same width
+fn build_thumbstrip(frames: &[Frame]) -> Option<(DynamicImage, ThumbstripIndex)> {
+	// Frames all come from the same video, so they have the same aspect ratio
+	let (width, height) = frames.first()?.image.dimensions();
+
+	let frame_width = THUMBSTRIP_FRAME_WIDTH;
+	let frame_height = (frame_width * height / width.max(1)).max(1);
+
+	let count = u32::try_from(frames.len()).ok()?;
+	let columns = THUMBSTRIP_COLUMNS.min(count);
+	let rows = count.div_ceil(columns);
+
+	let mut sheet = RgbImage::new(columns * frame_width, rows * frame_height);
+
+	let frames = (0..count)
+		.zip(frames)
+		.map(|(idx, frame)| {
+			let x = (idx % columns) * frame_width;
+			let y = (idx / columns) * frame_height;
+
+			imageops::overlay(
+				&mut sheet,
+				&imageops::resize(
+					&frame.image.to_rgb8(),
+					frame_width,
+					frame_height,
+					imageops::FilterType::Triangle,
+				),
+				i64::from(x),
+				i64::from(y),
+			);
+
+			ThumbstripFrame {
+				timestamp: frame.timestamp,
+				x,
+				y,
+			}
+		})
+		.collect();
+
+	Some((
+		DynamicImage::ImageRgb8(sheet),
+		ThumbstripIndex {
+			frame_width,
+			frame_height,
+			columns,
+			rows,
+			frames,
+		},
+	))
+}
+
+fn encode_animated_preview(frames: &[Frame]) -> Result<Vec<u8>, String> {
+	let (width, height) = frames
+		.first()
+		.ok_or_else(|| "no frames to animate".to_string())?
+		.image
+		.dimensions();
+
+	// Every frame of an animation must have the same size as the canvas
+	let images = frames
+		.iter()
+		.map(|frame| {
+			if frame.image.dimensions() == (width, height) {
+				frame.image.to_rgb8()
+			} else {
+				imageops::resize(
+					&frame.image.to_rgb8(),
+					width,
+					height,
+					imageops::FilterType::Triangle,
+				)
+			}
+		})
+		.collect::<Vec<_>>();
+
+	let mut config = WebPConfig::new().map_err(|()| "failed to create webp config".to_string())?;
+	config.quality = PREVIEW_QUALITY;
+
+	let mut encoder = AnimEncoder::new(width, height, &config);
+
+	let mut timestamp = 0;
+	for image in &images {
+		encoder.add_frame(AnimFrame::from_rgb(image, width, height, timestamp));
+		timestamp += PREVIEW_FRAME_DURATION_MS;
+	}
+
+	// The duration of a frame comes from the timestamp of the next one, so we repeat the last
+	// frame to give it the same duration as the others
+	if let Some(last) = images.last() {
+		encoder.add_frame(AnimFrame::from_rgb(last, width, height, timestamp));
+	}
+
+	// Type `WebPMemory` is !Send, so we copy it to a `Vec<u8>` like we do for thumbnails
+	Ok(encoder.encode().deref().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_build_thumbstrip() {
+		let frames = (0..7)
+			.map(|idx| Frame {
+				timestamp: f64::from(idx) * 1.5,
+				image: DynamicImage::ImageRgb8(RgbImage::new(320, 180)),
+			})
+			.collect::<Vec<_>>();
+
+		let (sheet, index) = build_thumbstrip(&frames).expect("frames to build the thumbstrip");
+
+		assert_eq!((index.frame_width, index.frame_height), (160, 90));
+		assert_eq!((index.columns, index.rows), (5, 2));
+		assert_eq!(sheet.dimensions(), (800, 180));
+		assert_eq!(index.frames.len(), 7);
+		assert_eq!(
+			(
+				index.frames[6].x,
+				index.frames[6].y,
+				index.frames[6].timestamp
+			),
+			(160, 90, 9.0)
+		);
+
+		assert!(build_thumbstrip(&[]).is_none());
+	}
+}
@@ -526,12 +526,25 @@ impl MediaProcessor {
 			let thumbnailer::Output {
 				generated,
 				skipped,
+				with_video_previews,
 				errors,
 				total_time,
 				mean_time_acc,
 				std_dev_acc,
 			} = *any_task_output.downcast().expect("just checked");
 
+			if let Err(e) = media_processor::mark_objects_with_video_previews(
+				with_video_previews,
+				job_ctx.db(),
+				job_ctx.sync(),
+			)
+			.await
+			{
+				error!(?e, "Failed to mark objects with their video previews;");
+				self.errors
+					.push(media_processor::NonCriticalMediaProcessorError::from(e).into());
+			}
+
 			self.metadata.thumbnailer_metrics_acc.generated += generated;
 			self.metadata.thumbnailer_metrics_acc.skipped += skipped;
 			self.metadata.thumbnailer_metrics_acc.mean_total_time += total_time;
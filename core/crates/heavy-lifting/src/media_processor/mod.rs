@@ -2,10 +2,15 @@ use crate::{utils::sub_path, OuterContext, UpdateEvent};
 
 use sd_core_file_path_helper::{FilePathError, IsolatedFilePathData};
 use sd_core_prisma_helpers::file_path_for_media_processor;
+use sd_core_sync::Manager as SyncManager;
 
 use sd_file_ext::extensions::Extension;
-use sd_prisma::prisma::{file_path, object, PrismaClient};
-use sd_utils::db::MissingFieldError;
+use sd_prisma::{
+	prisma::{file_path, object, PrismaClient},
+	prisma_sync,
+};
+use sd_sync::OperationFactory;
+use sd_utils::{db::MissingFieldError, msgpack};
 
 use std::{collections::HashMap, fmt};
 
@@ -24,11 +29,11 @@ pub use tasks::{
 };
 
 pub use helpers::{
-	exif_media_data, ffmpeg_media_data,
+	exif_media_data, ffmpeg_media_data, text_preview,
 	thumbnailer::{
 		can_generate_thumbnail_for_document, can_generate_thumbnail_for_image,
 		generate_single_thumbnail, get_shard_hex, get_thumbnails_directory, GenerateThumbnailArgs,
-		ThumbKey, ThumbnailKind, WEBP_EXTENSION,
+		ThumbKey, ThumbnailKind, VideoPreviewFile, EPHEMERAL_DIR, WEBP_EXTENSION,
 	},
 };
 
@@ -96,6 +101,71 @@ impl<OuterCtx: OuterContext> NewThumbnailReporter for NewThumbnailsReporter<Oute
 	}
 }
 
+/// Marks the objects of the given file paths as having a thumbstrip and an animated preview, once
+/// a thumbnailer task generated them for their videos
+async fn mark_objects_with_video_previews(
+	file_path_ids: Vec<file_path::id::Type>,
+	db: &PrismaClient,
+	sync: &SyncManager,
+) -> Result<(), NonCriticalThumbnailerError> {
+	if file_path_ids.is_empty() {
+		return Ok(());
+	}
+
+	let objects = db
+		.object()
+		.find_many(vec![object::file_paths::some(vec![file_path::id::in_vec(
+			file_path_ids,
+		)])])
+		.select(object::select!({ id pub_id }))
+		.exec()
+		.await
+		.map_err(|e| NonCriticalThumbnailerError::MarkVideoPreviews(e.to_string()))?;
+
+	let (sync_params, object_ids) = objects
+		.into_iter()
+		.map(|object| {
+			let object_sync_id = prisma_sync::object::SyncId {
+				pub_id: object.pub_id,
+			};
+
+			(
+				[
+					sync.shared_update(
+						object_sync_id.clone(),
+						object::has_thumbstrip::NAME,
+						msgpack!(true),
+					),
+					sync.shared_update(
+						object_sync_id,
+						object::has_video_preview::NAME,
+						msgpack!(true),
+					),
+				],
+				object.id,
+			)
+		})
+		.unzip::<_, _, Vec<_>, Vec<_>>();
+
+	sync.write_ops(
+		db,
+		(
+			sync_params.into_iter().flatten().collect(),
+			db.object().update_many(
+				vec![object::id::in_vec(object_ids)],
+				vec![
+					object::has_thumbstrip::set(Some(true)),
+					object::has_video_preview::set(Some(true)),
+				],
+			),
+		),
+	)
+	.await
+	.map_err(|e| NonCriticalThumbnailerError::MarkVideoPreviews(e.to_string()))?;
+
+	Ok(())
+}
+
 #[derive(Deserialize)]
 struct RawFilePathForMediaProcessor {
 	id: file_path::id::Type,
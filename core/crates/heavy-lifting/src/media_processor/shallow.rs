@@ -21,11 +21,12 @@ use std::{
 use futures::{stream::FuturesUnordered, StreamExt};
 use futures_concurrency::future::TryJoin;
 use itertools::Itertools;
-use tracing::{debug, warn};
+use tracing::{debug, error, warn};
 
 use super::{
 	get_direct_children_files_by_extensions,
 	helpers::{self, exif_media_data, ffmpeg_media_data, thumbnailer::THUMBNAIL_CACHE_DIR_NAME},
+	mark_objects_with_video_previews,
 	tasks::{
 		self, media_data_extractor,
 		thumbnailer::{self, NewThumbnailReporter},
@@ -119,12 +120,22 @@ pub async fn shallow(
 				} else if out.is::<thumbnailer::Output>() {
 					let thumbnailer::Output {
 						total_time,
+						with_video_previews,
 						errors: new_errors,
 						..
 					} = *out.downcast::<thumbnailer::Output>().expect("just checked");
 
 					errors.extend(new_errors);
 
+					if let Err(e) =
+						mark_objects_with_video_previews(with_video_previews, ctx.db(), ctx.sync())
+							.await
+					{
+						error!(?e, "Failed to mark objects with their video previews;");
+						errors
+							.push(media_processor::NonCriticalMediaProcessorError::from(e).into());
+					}
+
 					completed_thumbnailer_tasks += 1;
 
 					debug!(
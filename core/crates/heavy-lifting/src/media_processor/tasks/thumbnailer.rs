@@ -6,7 +6,11 @@
 //! │     └── <`cas_id`>.webp
 //! └── <`library_id`>/ # we segregate thumbnails by library
 //!    └── <`cas_id`>[0..3]/ # sharding
-//!       └── <`cas_id`>.webp
+//!       ├── <`cas_id`>.webp
+//!       ├── <`cas_id`>.strip.webp # videos only, frames to scrub through them
+//!       ├── <`cas_id`>.strip.json # videos only, index of the frames in the strip
+//!       ├── <`cas_id`>.preview.webp # videos only, animated preview
+//!       └── <`cas_id`>.preview.failed # videos only, marker of previews that failed to generate
 
 use crate::{
 	media_processor::{
@@ -199,6 +203,8 @@ impl Task<Error> for Thumbnailer {
 pub struct Output {
 	pub generated: u64,
 	pub skipped: u64,
+	/// File paths of videos that got their previews generated, to mark their objects
+	pub with_video_previews: Vec<file_path::id::Type>,
 	pub errors: Vec<crate::NonCriticalError>,
 	pub total_time: Duration,
 	pub mean_time_acc: f64,
@@ -213,6 +219,14 @@ pub enum NonCriticalThumbnailerError {
 	FailedToExtractIsolatedFilePathData(file_path::id::Type, String),
 	#[error("failed to generate video file thumbnail <path='{}'>: {1}", .0.display())]
 	VideoThumbnailGenerationFailed(PathBuf, String),
+	#[error("failed to generate video previews <path='{}'>: {1}", .0.display())]
+	VideoPreviewGenerationFailed(PathBuf, String),
+	#[error("failed to generate audio file thumbnail <path='{}'>: {1}", .0.display())]
+	AudioThumbnailGenerationFailed(PathBuf, String),
+	#[error("failed to extract book cover <path='{}'>: {1}", .0.display())]
+	BookCoverExtraction(PathBuf, String),
+	#[error("failed to render text preview <path='{}'>: {1}", .0.display())]
+	TextPreview(PathBuf, String),
 	#[error("failed to format image <path='{}'>: {1}", .0.display())]
 	FormatImage(PathBuf, String),
 	#[error("failed to encode webp image <path='{}'>: {1}", .0.display())]
@@ -225,6 +239,8 @@ pub enum NonCriticalThumbnailerError {
 	SaveThumbnail(PathBuf, String),
 	#[error("task timed out: {0}")]
 	TaskTimeout(TaskId),
+	#[error("failed to mark objects as having video previews: {0}")]
+	MarkVideoPreviews(String),
 }
 
 impl Thumbnailer {
@@ -364,6 +380,7 @@ fn process_thumbnail_generation_output(
 	Output {
 		generated,
 		skipped,
+		with_video_previews,
 		errors,
 		mean_time_acc: mean_generation_time_accumulator,
 		std_dev_acc: std_dev_accumulator,
@@ -377,10 +394,23 @@ fn process_thumbnail_generation_output(
 	match res {
 		Ok((thumb_key, status)) => {
 			match status {
-				GenerationStatus::Generated => {
+				GenerationStatus::Generated {
+					with_video_previews: true,
+				}
+				| GenerationStatus::GeneratedVideoPreviews => {
+					*generated += 1;
+
+					#[allow(clippy::cast_possible_wrap)]
+					// SAFETY: indexed thumbnails are identified by their file path id, which was
+					// never negative
+					with_video_previews.push(id as file_path::id::Type);
+				}
+				GenerationStatus::Generated {
+					with_video_previews: false,
+				} => {
 					*generated += 1;
 				}
-				GenerationStatus::Skipped => {
+				GenerationStatus::Skipped | GenerationStatus::Unavailable => {
 					*skipped += 1;
 				}
 			}
@@ -391,7 +421,7 @@ fn process_thumbnail_generation_output(
 			// humongous bottleneck in the frontend lol, since it doesn't even knows
 			// what to do with thumbnails for inner directories lol
 			// - fogodev
-			if with_priority {
+			if with_priority && !matches!(status, GenerationStatus::Unavailable) {
 				reporter.new_thumbnail(thumb_key);
 			}
 		}
@@ -27,19 +27,24 @@
 #![forbid(deprecated_in_future)]
 #![allow(clippy::missing_errors_doc)]
 
+use sd_file_ext::{extensions::Extension, kind::ObjectKind, magic::ExtensionPossibility};
 use sd_prisma::prisma::{indexer_rule, PrismaClient};
 use sd_utils::{
 	db::{maybe_missing, MissingFieldError},
 	error::{FileIOError, NonUtf8PathError},
 };
 use seed::SystemIndexerRule;
-use serde::{Deserialize, Serialize};
+use serde::{de::IntoDeserializer, Deserialize, Serialize};
 
 use std::{
 	collections::{HashMap, HashSet},
+	ffi::OsStr,
+	fmt::Display,
 	fs::Metadata,
 	path::{Path, PathBuf},
+	str::FromStr,
 	sync::Arc,
+	time::Duration,
 };
 
 use chrono::{DateTime, Utc};
@@ -65,6 +70,8 @@ pub enum Error {
 	InvalidRuleKindInt(i32),
 	#[error("glob builder error: {0}")]
 	Glob(#[from] globset::Error),
+	#[error("invalid parameters for indexer rule <kind='{0:?}'>: {1}")]
+	InvalidRuleParameters(RuleKind, String),
 	#[error(transparent)]
 	NonUtf8Path(#[from] NonUtf8PathError),
 
@@ -86,9 +93,10 @@ pub enum Error {
 impl From<Error> for rspc::Error {
 	fn from(e: Error) -> Self {
 		match e {
-			Error::InvalidRuleKindInt(_) | Error::Glob(_) | Error::NonUtf8Path(_) => {
-				Self::with_cause(ErrorCode::BadRequest, e.to_string(), e)
-			}
+			Error::InvalidRuleKindInt(_)
+			| Error::Glob(_)
+			| Error::InvalidRuleParameters(..)
+			| Error::NonUtf8Path(_) => Self::with_cause(ErrorCode::BadRequest, e.to_string(), e),
 
 			_ => Self::with_cause(ErrorCode::InternalServerError, e.to_string(), e),
 		}
@@ -103,6 +111,13 @@ impl From<Error> for rspc::Error {
 ///
 /// In case of `RuleKind::AcceptIfChildrenDirectoriesArePresent` or `RuleKind::RejectIfChildrenDirectoriesArePresent` the
 /// `parameters` field must be a vector of strings containing the names of the directories.
+///
+/// In case of `RuleKind::RejectFilesLargerThan` it must hold a single size in bytes, and in case of
+/// `RuleKind::AcceptFilesModifiedWithin` a single number of seconds.
+///
+/// In case of `RuleKind::RejectFilesWithExtendedAttributes` it must hold the attribute names, like
+/// `com.apple.quarantine` or `user.sd.ignore`, and in case of `RuleKind::RejectFilesByKind` the
+/// object kind names, like `Executable`.
 #[derive(Type, Deserialize)]
 pub struct IndexerRuleCreateArgs {
 	pub name: String,
@@ -148,6 +163,18 @@ impl IndexerRuleCreateArgs {
 					RuleKind::IgnoredByGit => {
 						Ok(RulePerKind::IgnoredByGit(PathBuf::new(), Search::default()))
 					}
+					RuleKind::RejectFilesLargerThan => {
+						RulePerKind::new_reject_files_larger_than_str(parameters)
+					}
+					RuleKind::AcceptFilesModifiedWithin => {
+						RulePerKind::new_accept_files_modified_within_str(parameters)
+					}
+					RuleKind::RejectFilesWithExtendedAttributes => {
+						RulePerKind::new_reject_files_with_extended_attributes_str(parameters)
+					}
+					RuleKind::RejectFilesByKind => {
+						RulePerKind::new_reject_files_by_kind_str(parameters)
+					}
 				})
 				.collect::<Result<Vec<_>, _>>()?,
 		)?;
@@ -184,13 +211,17 @@ pub enum RuleKind {
 	AcceptIfChildrenDirectoriesArePresent = 2,
 	RejectIfChildrenDirectoriesArePresent = 3,
 	IgnoredByGit = 4,
+	RejectFilesLargerThan = 5,
+	AcceptFilesModifiedWithin = 6,
+	RejectFilesWithExtendedAttributes = 7,
+	RejectFilesByKind = 8,
 }
 
 impl RuleKind {
 	#[must_use]
 	pub const fn variant_count() -> usize {
 		// TODO: Use https://doc.rust-lang.org/std/mem/fn.variant_count.html if it ever gets stabilized
-		5
+		9
 	}
 }
 
@@ -202,16 +233,24 @@ impl RuleKind {
 /// In case of `ParametersPerKind::AcceptIfChildrenDirectoriesArePresent` or
 /// `ParametersPerKind::RejectIfChildrenDirectoriesArePresent`
 /// first we change the data structure to a vector, then we serialize it.
+///
+/// Size, modification date and kind rules only apply to files, directories are always accepted by
+/// them so their contents can still be walked.
 #[derive(Debug, Clone)]
 pub enum RulePerKind {
-	// TODO: Add an indexer rule that filter files based on their extended attributes
-	// https://learn.microsoft.com/en-us/windows/win32/fileio/file-attribute-constants
-	// https://en.wikipedia.org/wiki/Extended_file_attributes
 	AcceptFilesByGlob(Vec<Glob>, GlobSet),
 	RejectFilesByGlob(Vec<Glob>, GlobSet),
 	AcceptIfChildrenDirectoriesArePresent(HashSet<String>),
 	RejectIfChildrenDirectoriesArePresent(HashSet<String>),
 	IgnoredByGit(PathBuf, Search),
+	/// Maximum size in bytes
+	RejectFilesLargerThan(u64),
+	/// How long ago files must have been modified at most, stored as seconds
+	AcceptFilesModifiedWithin(Duration),
+	/// Extended attribute names, Windows file attributes aren't supported yet
+	/// <https://en.wikipedia.org/wiki/Extended_file_attributes>
+	RejectFilesWithExtendedAttributes(HashSet<String>),
+	RejectFilesByKind(Vec<ObjectKind>),
 }
 
 impl RulePerKind {
@@ -248,16 +287,106 @@ impl RulePerKind {
 	) -> Result<Self, Error> {
 		Self::new_files_by_globs_str_and_kind(globs_str, Self::RejectFilesByGlob)
 	}
+
+	pub fn new_reject_files_larger_than_str(
+		parameters: impl IntoIterator<Item = impl AsRef<str>>,
+	) -> Result<Self, Error> {
+		single_parameter(RuleKind::RejectFilesLargerThan, parameters)
+			.map(Self::RejectFilesLargerThan)
+	}
+
+	pub fn new_accept_files_modified_within_str(
+		parameters: impl IntoIterator<Item = impl AsRef<str>>,
+	) -> Result<Self, Error> {
+		single_parameter(RuleKind::AcceptFilesModifiedWithin, parameters)
+			.map(|secs| Self::AcceptFilesModifiedWithin(Duration::from_secs(secs)))
+	}
+
+	pub fn new_reject_files_with_extended_attributes_str(
+		names: impl IntoIterator<Item = impl AsRef<str>>,
+	) -> Result<Self, Error> {
+		let names = names
+			.into_iter()
+			.map(|name| name.as_ref().trim().to_string())
+			.filter(|name| !name.is_empty())
+			.collect::<HashSet<_>>();
+
+		if names.is_empty() {
+			return Err(Error::InvalidRuleParameters(
+				RuleKind::RejectFilesWithExtendedAttributes,
+				"at least one attribute name is required".to_string(),
+			));
+		}
+
+		Ok(Self::RejectFilesWithExtendedAttributes(names))
+	}
+
+	pub fn new_reject_files_by_kind_str(
+		kinds: impl IntoIterator<Item = impl AsRef<str>>,
+	) -> Result<Self, Error> {
+		let kinds = kinds
+			.into_iter()
+			.map(|kind| {
+				ObjectKind::deserialize(kind.as_ref().trim().into_deserializer()).map_err(
+					|e: serde::de::value::Error| {
+						Error::InvalidRuleParameters(RuleKind::RejectFilesByKind, e.to_string())
+					},
+				)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		if kinds.is_empty() {
+			return Err(Error::InvalidRuleParameters(
+				RuleKind::RejectFilesByKind,
+				"at least one kind is required".to_string(),
+			));
+		}
+
+		Ok(Self::RejectFilesByKind(kinds))
+	}
+}
+
+fn single_parameter<T>(
+	kind: RuleKind,
+	parameters: impl IntoIterator<Item = impl AsRef<str>>,
+) -> Result<T, Error>
+where
+	T: FromStr,
+	T::Err: Display,
+{
+	let mut parameters = parameters.into_iter();
+
+	match (parameters.next(), parameters.next()) {
+		(Some(parameter), None) => parameter
+			.as_ref()
+			.trim()
+			.parse()
+			.map_err(|e: T::Err| Error::InvalidRuleParameters(kind, e.to_string())),
+		_ => Err(Error::InvalidRuleParameters(
+			kind,
+			"expected a single parameter".to_string(),
+		)),
+	}
 }
 
 pub trait MetadataForIndexerRules: Send + Sync + 'static {
 	fn is_dir(&self) -> bool;
+	fn size_in_bytes(&self) -> u64;
+	fn modified_at(&self) -> Option<DateTime<Utc>>;
 }
 
 impl MetadataForIndexerRules for Metadata {
 	fn is_dir(&self) -> bool {
 		self.is_dir()
 	}
+
+	fn size_in_bytes(&self) -> u64 {
+		self.len()
+	}
+
+	fn modified_at(&self) -> Option<DateTime<Utc>> {
+		self.modified().ok().map(Into::into)
+	}
 }
 
 impl RulePerKind {
@@ -290,10 +419,82 @@ impl RulePerKind {
 				RuleKind::IgnoredByGit,
 				accept_by_git_pattern(source, base_dir, patterns),
 			)),
+			Self::RejectFilesLargerThan(max_size) => Ok((
+				RuleKind::RejectFilesLargerThan,
+				metadata.is_dir() || metadata.size_in_bytes() <= *max_size,
+			)),
+			Self::AcceptFilesModifiedWithin(max_age) => Ok((
+				RuleKind::AcceptFilesModifiedWithin,
+				accept_by_modified_date(metadata, *max_age),
+			)),
+			Self::RejectFilesWithExtendedAttributes(names) => Ok((
+				RuleKind::RejectFilesWithExtendedAttributes,
+				reject_by_extended_attributes(source, names),
+			)),
+			Self::RejectFilesByKind(kinds) => Ok((
+				RuleKind::RejectFilesByKind,
+				reject_by_kind(source, metadata, kinds).await,
+			)),
+		}
+	}
+}
+
+fn accept_by_modified_date(metadata: &impl MetadataForIndexerRules, max_age: Duration) -> bool {
+	metadata.is_dir()
+		|| metadata.modified_at().map_or(true, |modified_at| {
+			// Files modified in the future have a negative age, so they are accepted too
+			(Utc::now() - modified_at)
+				.to_std()
+				.map_or(true, |age| age <= max_age)
+		})
+}
+
+/// Listing attributes is a single syscall, so it isn't worth moving to a blocking thread.
+/// Paths whose attributes can't be listed, like on file systems without support for them, are
+/// accepted.
+fn reject_by_extended_attributes(source: impl AsRef<Path>, names: &HashSet<String>) -> bool {
+	let source = source.as_ref();
+
+	match xattr::list(source) {
+		Ok(mut attributes) => !attributes.any(|attribute| {
+			attribute
+				.to_str()
+				.is_some_and(|attribute| names.contains(attribute))
+		}),
+		Err(e) => {
+			trace!(?e, source = %source.display(), "Failed to list extended attributes;");
+			true
 		}
 	}
 }
 
+async fn reject_by_kind(
+	source: impl AsRef<Path> + Send,
+	metadata: &impl MetadataForIndexerRules,
+	kinds: &[ObjectKind],
+) -> bool {
+	if metadata.is_dir() {
+		return true;
+	}
+
+	let source = source.as_ref();
+
+	let kind = match source
+		.extension()
+		.and_then(OsStr::to_str)
+		.and_then(Extension::from_str)
+	{
+		Some(ExtensionPossibility::Known(extension)) => extension.into(),
+		// Same as the file identifier, conflicting extensions need a look at the file contents
+		Some(ExtensionPossibility::Conflicts(_)) => Extension::resolve_conflicting(source, false)
+			.await
+			.map_or(ObjectKind::Unknown, Into::into),
+		None => ObjectKind::Unknown,
+	};
+
+	!kinds.contains(&kind)
+}
+
 fn accept_by_git_pattern(
 	source: impl AsRef<Path>,
 	base_dir: impl AsRef<Path>,
@@ -458,10 +659,45 @@ impl IndexerRuler {
 	) -> bool {
 		Self::rejected_by_reject_glob(acceptance_per_rule_kind)
 			|| Self::rejected_by_git_ignore(acceptance_per_rule_kind)
+			|| Self::rejected_by_file_properties(acceptance_per_rule_kind)
 			|| (is_dir && Self::rejected_by_children_directories(acceptance_per_rule_kind))
 			|| Self::rejected_by_accept_glob(acceptance_per_rule_kind)
 	}
 
+	/// Size, modification date, extended attributes and kind rules
+	pub fn rejected_by_file_properties(
+		acceptance_per_rule_kind: &HashMap<RuleKind, Vec<bool>>,
+	) -> bool {
+		let rejected_by_any = |kind| {
+			acceptance_per_rule_kind
+				.get(&kind)
+				.map_or(false, |reject_results| {
+					reject_results.iter().any(|reject| !reject)
+				})
+		};
+
+		let res = [
+			RuleKind::RejectFilesLargerThan,
+			RuleKind::RejectFilesWithExtendedAttributes,
+			RuleKind::RejectFilesByKind,
+		]
+		.into_iter()
+		.find(|&kind| rejected_by_any(kind))
+		.or_else(|| {
+			// Like accept globs, passing any of the accept rules is enough
+			acceptance_per_rule_kind
+				.get(&RuleKind::AcceptFilesModifiedWithin)
+				.filter(|accept_rules| accept_rules.iter().all(|accept| !accept))
+				.map(|_| RuleKind::AcceptFilesModifiedWithin)
+		});
+
+		if let Some(kind) = res {
+			trace!("Rejected by `RuleKind::{kind:?}`");
+		}
+
+		res.is_some()
+	}
+
 	pub fn rejected_by_accept_glob(
 		acceptance_per_rule_kind: &HashMap<RuleKind, Vec<bool>>,
 	) -> bool {
@@ -708,6 +944,44 @@ mod tests {
 			.all(|(_kind, res)| res)
 	}
 
+	struct FakeMetadata {
+		is_dir: bool,
+		size_in_bytes: u64,
+		modified_at: Option<DateTime<Utc>>,
+	}
+
+	impl FakeMetadata {
+		const fn file(size_in_bytes: u64, modified_at: Option<DateTime<Utc>>) -> Self {
+			Self {
+				is_dir: false,
+				size_in_bytes,
+				modified_at,
+			}
+		}
+
+		const fn dir() -> Self {
+			Self {
+				is_dir: true,
+				size_in_bytes: 0,
+				modified_at: None,
+			}
+		}
+	}
+
+	impl MetadataForIndexerRules for FakeMetadata {
+		fn is_dir(&self) -> bool {
+			self.is_dir
+		}
+
+		fn size_in_bytes(&self) -> u64 {
+			self.size_in_bytes
+		}
+
+		fn modified_at(&self) -> Option<DateTime<Utc>> {
+			self.modified_at
+		}
+	}
+
 	#[tokio::test]
 	async fn test_reject_hidden_file() {
 		let hidden = Path::new(".hidden.txt");
@@ -884,6 +1158,160 @@ mod tests {
 		);
 	}
 
+	#[tokio::test]
+	async fn test_reject_larger_files() {
+		const FOUR_GIB: u64 = 4 * 1024 * 1024 * 1024;
+
+		let rule = IndexerRule::new(
+			"no huge files".to_string(),
+			false,
+			vec![RulePerKind::RejectFilesLargerThan(FOUR_GIB)],
+		);
+
+		let path = Path::new("/test/file.bin");
+
+		assert!(check_rule_with_metadata(&rule, path, &FakeMetadata::file(FOUR_GIB, None)).await);
+		assert!(
+			!check_rule_with_metadata(&rule, path, &FakeMetadata::file(FOUR_GIB + 1, None)).await
+		);
+		// Directories sizes aren't their contents, so they are never rejected
+		assert!(check_rule_with_metadata(&rule, Path::new("/test"), &FakeMetadata::dir()).await);
+	}
+
+	#[tokio::test]
+	async fn test_accept_recently_modified_files() {
+		let rule = IndexerRule::new(
+			"last year only".to_string(),
+			false,
+			vec![RulePerKind::AcceptFilesModifiedWithin(Duration::from_secs(
+				365 * 24 * 60 * 60,
+			))],
+		);
+
+		let path = Path::new("/test/file.txt");
+		let now = Utc::now();
+
+		assert!(
+			check_rule_with_metadata(
+				&rule,
+				path,
+				&FakeMetadata::file(0, Some(now - chrono::Duration::days(30)))
+			)
+			.await
+		);
+		assert!(
+			!check_rule_with_metadata(
+				&rule,
+				path,
+				&FakeMetadata::file(0, Some(now - chrono::Duration::days(400)))
+			)
+			.await
+		);
+		assert!(
+			check_rule_with_metadata(
+				&rule,
+				path,
+				&FakeMetadata::file(0, Some(now + chrono::Duration::days(1)))
+			)
+			.await
+		);
+		assert!(check_rule_with_metadata(&rule, path, &FakeMetadata::file(0, None)).await);
+		assert!(check_rule_with_metadata(&rule, Path::new("/test"), &FakeMetadata::dir()).await);
+
+		// As with accept globs, passing any of the rules is enough for the ruler
+		let acceptance = HashMap::from([(RuleKind::AcceptFilesModifiedWithin, vec![false, true])]);
+		assert!(!IndexerRuler::rejected_by_file_properties(&acceptance));
+		let acceptance = HashMap::from([(RuleKind::AcceptFilesModifiedWithin, vec![false])]);
+		assert!(IndexerRuler::rejected_by_file_properties(&acceptance));
+	}
+
+	#[tokio::test]
+	async fn test_reject_by_extended_attributes() {
+		let root = tempdir().unwrap();
+
+		let ignored = root.path().join("ignored.txt");
+		let quarantined = root.path().join("quarantined.txt");
+		let normal = root.path().join("normal.txt");
+
+		for path in [&ignored, &quarantined, &normal] {
+			fs::write(path, b"content").await.unwrap();
+		}
+
+		if xattr::set(&ignored, "user.sd.ignore", b"1").is_err() {
+			// Extended attributes aren't supported by the temporary directory file system
+			return;
+		}
+		xattr::set(&quarantined, "user.quarantine", b"1").unwrap();
+
+		let rule = IndexerRule::new(
+			"ignored by attributes".to_string(),
+			false,
+			vec![RulePerKind::new_reject_files_with_extended_attributes_str([
+				"user.sd.ignore",
+				"user.quarantine",
+			])
+			.unwrap()],
+		);
+
+		for (path, accepted) in [(&ignored, false), (&quarantined, false), (&normal, true)] {
+			assert_eq!(
+				check_rule_with_metadata(&rule, path, &fs::metadata(path).await.unwrap()).await,
+				accepted,
+				"{}",
+				path.display()
+			);
+		}
+	}
+
+	#[tokio::test]
+	async fn test_reject_by_kind() {
+		let root = tempdir().unwrap();
+
+		let executable = root.path().join("setup.exe");
+		let photo = root.path().join("photo.png");
+		let unknown = root.path().join("no_extension");
+
+		for path in [&executable, &photo, &unknown] {
+			fs::write(path, b"content").await.unwrap();
+		}
+
+		let rule = IndexerRule::new(
+			"no executables".to_string(),
+			false,
+			vec![RulePerKind::new_reject_files_by_kind_str(["Executable"]).unwrap()],
+		);
+
+		for (path, accepted) in [(&executable, false), (&photo, true), (&unknown, true)] {
+			assert_eq!(
+				check_rule_with_metadata(&rule, path, &fs::metadata(path).await.unwrap()).await,
+				accepted,
+				"{}",
+				path.display()
+			);
+		}
+
+		assert!(RulePerKind::new_reject_files_by_kind_str(["NotAKind"]).is_err());
+		assert!(RulePerKind::new_reject_files_by_kind_str(Vec::<String>::new()).is_err());
+	}
+
+	#[test]
+	fn test_single_parameter() {
+		assert_eq!(
+			single_parameter::<u64>(RuleKind::RejectFilesLargerThan, vec![" 1024 ".to_string()])
+				.unwrap(),
+			1024
+		);
+		assert!(
+			single_parameter::<u64>(RuleKind::RejectFilesLargerThan, vec!["-1".to_string()])
+				.is_err()
+		);
+		assert!(single_parameter::<u64>(
+			RuleKind::AcceptFilesModifiedWithin,
+			vec!["1".to_string(), "2".to_string()]
+		)
+		.is_err());
+	}
+
 	impl PartialEq for RulePerKind {
 		fn eq(&self, other: &Self) -> bool {
 			match (self, other) {
@@ -905,6 +1333,25 @@ mod tests {
 					Self::RejectIfChildrenDirectoriesArePresent(other_childrens),
 				) => self_childrens == other_childrens,
 
+				(
+					Self::RejectFilesLargerThan(self_size),
+					Self::RejectFilesLargerThan(other_size),
+				) => self_size == other_size,
+
+				(
+					Self::AcceptFilesModifiedWithin(self_age),
+					Self::AcceptFilesModifiedWithin(other_age),
+				) => self_age == other_age,
+
+				(
+					Self::RejectFilesWithExtendedAttributes(self_names),
+					Self::RejectFilesWithExtendedAttributes(other_names),
+				) => self_names == other_names,
+
+				(Self::RejectFilesByKind(self_kinds), Self::RejectFilesByKind(other_kinds)) => {
+					self_kinds == other_kinds
+				}
+
 				_ => false,
 			}
 		}
@@ -944,4 +1391,27 @@ mod tests {
 
 		assert_eq!(actual, expected);
 	}
+
+	#[test]
+	fn serde_file_properties_rules() {
+		let actual = IndexerRule::new(
+			"File properties".to_string(),
+			false,
+			vec![
+				RulePerKind::RejectFilesLargerThan(4 * 1024 * 1024 * 1024),
+				RulePerKind::AcceptFilesModifiedWithin(Duration::from_secs(60 * 60)),
+				RulePerKind::RejectFilesWithExtendedAttributes(HashSet::from([
+					"com.apple.quarantine".to_string(),
+					"user.sd.ignore".to_string(),
+				])),
+				RulePerKind::RejectFilesByKind(vec![ObjectKind::Executable, ObjectKind::Archive]),
+			],
+		);
+
+		let expected =
+			rmp_serde::from_slice::<IndexerRule>(&rmp_serde::to_vec_named(&actual).unwrap())
+				.unwrap();
+
+		assert_eq!(actual, expected);
+	}
 }
@@ -1,4 +1,6 @@
-use std::{collections::HashSet, marker::PhantomData};
+use sd_file_ext::kind::ObjectKind;
+
+use std::{collections::HashSet, marker::PhantomData, time::Duration};
 
 use globset::{Glob, GlobSetBuilder};
 use serde::{de, ser, Deserialize, Serialize};
@@ -34,6 +36,31 @@ impl Serialize for RulePerKind {
 			Self::IgnoredByGit(_, _) => {
 				unreachable!("git ignore rules are dynamic and not serialized")
 			}
+			Self::RejectFilesLargerThan(max_size) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				5,
+				"RejectFilesLargerThan",
+				&max_size,
+			),
+			Self::AcceptFilesModifiedWithin(max_age) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				6,
+				"AcceptFilesModifiedWithin",
+				&max_age.as_secs(),
+			),
+			Self::RejectFilesWithExtendedAttributes(ref names) => serializer
+				.serialize_newtype_variant(
+					"ParametersPerKind",
+					7,
+					"RejectFilesWithExtendedAttributes",
+					names,
+				),
+			Self::RejectFilesByKind(ref kinds) => serializer.serialize_newtype_variant(
+				"ParametersPerKind",
+				8,
+				"RejectFilesByKind",
+				kinds,
+			),
 		}
 	}
 }
@@ -49,6 +76,10 @@ impl<'de> Deserialize<'de> for RulePerKind {
 			"RejectFilesByGlob",
 			"AcceptIfChildrenDirectoriesArePresent",
 			"RejectIfChildrenDirectoriesArePresent",
+			"RejectFilesLargerThan",
+			"AcceptFilesModifiedWithin",
+			"RejectFilesWithExtendedAttributes",
+			"RejectFilesByKind",
 		];
 
 		enum Fields {
@@ -56,6 +87,10 @@ impl<'de> Deserialize<'de> for RulePerKind {
 			RejectFilesByGlob,
 			AcceptIfChildrenDirectoriesArePresent,
 			RejectIfChildrenDirectoriesArePresent,
+			RejectFilesLargerThan,
+			AcceptFilesModifiedWithin,
+			RejectFilesWithExtendedAttributes,
+			RejectFilesByKind,
 		}
 
 		struct FieldsVisitor;
@@ -68,7 +103,11 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					"`AcceptFilesByGlob` \
 				or `RejectFilesByGlob` \
 				or `AcceptIfChildrenDirectoriesArePresent` \
-				or `RejectIfChildrenDirectoriesArePresent`",
+				or `RejectIfChildrenDirectoriesArePresent` \
+				or `RejectFilesLargerThan` \
+				or `AcceptFilesModifiedWithin` \
+				or `RejectFilesWithExtendedAttributes` \
+				or `RejectFilesByKind`",
 				)
 			}
 
@@ -81,9 +120,14 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					1 => Ok(Fields::RejectFilesByGlob),
 					2 => Ok(Fields::AcceptIfChildrenDirectoriesArePresent),
 					3 => Ok(Fields::RejectIfChildrenDirectoriesArePresent),
+					// 4 is `IgnoredByGit`, which is never serialized
+					5 => Ok(Fields::RejectFilesLargerThan),
+					6 => Ok(Fields::AcceptFilesModifiedWithin),
+					7 => Ok(Fields::RejectFilesWithExtendedAttributes),
+					8 => Ok(Fields::RejectFilesByKind),
 					_ => Err(de::Error::invalid_value(
 						de::Unexpected::Unsigned(value),
-						&"variant index 0 <= i < 3",
+						&"variant index 0 <= i < 9",
 					)),
 				}
 			}
@@ -100,6 +144,12 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					"RejectIfChildrenDirectoriesArePresent" => {
 						Ok(Fields::RejectIfChildrenDirectoriesArePresent)
 					}
+					"RejectFilesLargerThan" => Ok(Fields::RejectFilesLargerThan),
+					"AcceptFilesModifiedWithin" => Ok(Fields::AcceptFilesModifiedWithin),
+					"RejectFilesWithExtendedAttributes" => {
+						Ok(Fields::RejectFilesWithExtendedAttributes)
+					}
+					"RejectFilesByKind" => Ok(Fields::RejectFilesByKind),
 					_ => Err(de::Error::unknown_variant(value, VARIANTS)),
 				}
 			}
@@ -116,6 +166,12 @@ impl<'de> Deserialize<'de> for RulePerKind {
 					b"RejectIfChildrenDirectoriesArePresent" => {
 						Ok(Fields::RejectIfChildrenDirectoriesArePresent)
 					}
+					b"RejectFilesLargerThan" => Ok(Fields::RejectFilesLargerThan),
+					b"AcceptFilesModifiedWithin" => Ok(Fields::AcceptFilesModifiedWithin),
+					b"RejectFilesWithExtendedAttributes" => {
+						Ok(Fields::RejectFilesWithExtendedAttributes)
+					}
+					b"RejectFilesByKind" => Ok(Fields::RejectFilesByKind),
 					_ => Err(de::Error::unknown_variant(
 						&String::from_utf8_lossy(bytes),
 						VARIANTS,
@@ -201,6 +257,28 @@ impl<'de> Deserialize<'de> for RulePerKind {
 						reject_if_children_directories_are_present,
 					)
 					.map(Self::Value::RejectIfChildrenDirectoriesArePresent),
+					(Fields::RejectFilesLargerThan, reject_files_larger_than) => {
+						de::VariantAccess::newtype_variant::<u64>(reject_files_larger_than)
+							.map(Self::Value::RejectFilesLargerThan)
+					}
+					(Fields::AcceptFilesModifiedWithin, accept_files_modified_within) => {
+						de::VariantAccess::newtype_variant::<u64>(accept_files_modified_within).map(
+							|secs| {
+								Self::Value::AcceptFilesModifiedWithin(Duration::from_secs(secs))
+							},
+						)
+					}
+					(
+						Fields::RejectFilesWithExtendedAttributes,
+						reject_files_with_extended_attributes,
+					) => de::VariantAccess::newtype_variant::<HashSet<String>>(
+						reject_files_with_extended_attributes,
+					)
+					.map(Self::Value::RejectFilesWithExtendedAttributes),
+					(Fields::RejectFilesByKind, reject_files_by_kind) => {
+						de::VariantAccess::newtype_variant::<Vec<ObjectKind>>(reject_files_by_kind)
+							.map(Self::Value::RejectFilesByKind)
+					}
 				})
 			}
 		}
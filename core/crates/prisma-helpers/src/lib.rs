@@ -56,13 +56,27 @@ file_path::select!(file_path_for_file_identifier {
 	object_id
 });
 file_path::select!(file_path_for_object_validator {
+	id
 	pub_id
+	location_id
 	materialized_path
 	is_dir
 	name
 	extension
 	integrity_checksum
 });
+file_path::select!(file_path_for_duplicate_finder {
+	id
+	pub_id
+	integrity_checksum
+	size_in_bytes_bytes
+	object: select {
+		id
+		pub_id
+		kind
+		date_created
+	}
+});
 file_path::select!(file_path_for_media_processor {
 	id
 	materialized_path
@@ -117,6 +131,10 @@ file_path::select!(file_path_to_handle_custom_uri {
 	is_dir
 	name
 	extension
+	cas_id
+	integrity_checksum
+	size_in_bytes_bytes
+	date_modified
 	location: select {
 		id
 		path
@@ -270,6 +288,12 @@ impl From<location_with_indexer_rules::Data> for location::Data {
 			hidden: data.hidden,
 			date_created: data.date_created,
 			scan_state: data.scan_state,
+			volume_identifier: data.volume_identifier,
+			volume_path: data.volume_path,
+			rescan_schedule: data.rescan_schedule,
+			rescan_last_attempt_at: data.rescan_last_attempt_at,
+			rescan_failures: data.rescan_failures,
+			last_verified_at: data.last_verified_at,
 			file_paths: None,
 			indexer_rules: None,
 			instance: None,
@@ -294,6 +318,12 @@ impl From<&location_with_indexer_rules::Data> for location::Data {
 			hidden: data.hidden,
 			date_created: data.date_created,
 			scan_state: data.scan_state,
+			volume_identifier: data.volume_identifier.clone(),
+			volume_path: data.volume_path.clone(),
+			rescan_schedule: data.rescan_schedule.clone(),
+			rescan_last_attempt_at: data.rescan_last_attempt_at,
+			rescan_failures: data.rescan_failures,
+			last_verified_at: data.last_verified_at,
 			file_paths: None,
 			indexer_rules: None,
 			instance: None,
@@ -1,12 +1,13 @@
 use sd_prisma::{
 	prisma::{
-		crdt_operation, exif_data, file_path, instance, label, label_on_object, location, object,
-		tag, tag_on_object, PrismaClient, SortOrder,
+		album, crdt_operation, exif_data, file_path, instance, label, label_on_object, location,
+		object, object_in_album, object_in_space, space, tag, tag_on_object, PrismaClient,
+		SortOrder,
 	},
 	prisma_sync,
 };
 use sd_sync::{option_sync_entry, sync_entry, OperationFactory};
-use sd_utils::chain_optional_iter;
+use sd_utils::{chain_optional_iter, msgpack};
 
 use std::future::Future;
 
@@ -36,6 +37,7 @@ pub async fn backfill_operations(
 				.await?;
 
 			paginate_tags(&db, sync, instance_id).await?;
+			paginate_tag_parents(&db, sync, instance_id).await?;
 			paginate_locations(&db, sync, instance_id).await?;
 			paginate_objects(&db, sync, instance_id).await?;
 			paginate_exif_datas(&db, sync, instance_id).await?;
@@ -43,6 +45,10 @@ pub async fn backfill_operations(
 			paginate_tags_on_objects(&db, sync, instance_id).await?;
 			paginate_labels(&db, sync, instance_id).await?;
 			paginate_labels_on_objects(&db, sync, instance_id).await?;
+			paginate_albums(&db, sync, instance_id).await?;
+			paginate_objects_in_albums(&db, sync, instance_id).await?;
+			paginate_spaces(&db, sync, instance_id).await?;
+			paginate_objects_in_spaces(&db, sync, instance_id).await?;
 
 			debug!(elapsed = ?start.elapsed(), "backfill ended");
 
@@ -151,6 +157,44 @@ async fn paginate_tags(
 	.await
 }
 
+/// Parents are synced once every tag was created, as a tag can be nested under a newer one
+#[instrument(skip(db, sync), err)]
+async fn paginate_tag_parents(
+	db: &PrismaClient,
+	sync: &crate::Manager,
+	instance_id: instance::id::Type,
+) -> Result<(), Error> {
+	use tag::{id, include, parent, parent_id};
+
+	paginate(
+		|cursor| {
+			db.tag()
+				.find_many(vec![id::gt(cursor), parent_id::not(None)])
+				.order_by(id::order(SortOrder::Asc))
+				.take(1000)
+				.include(include!({ parent: select { pub_id } }))
+				.exec()
+		},
+		|tag| tag.id,
+		|tags| {
+			tags.into_iter()
+				.filter_map(|t| {
+					t.parent.map(|p| {
+						sync.shared_update(
+							prisma_sync::tag::SyncId { pub_id: t.pub_id },
+							parent::NAME,
+							msgpack!(prisma_sync::tag::SyncId { pub_id: p.pub_id }),
+						)
+					})
+				})
+				.map(|o| crdt_op_unchecked_db(&o, instance_id))
+				.collect::<Result<Vec<_>, _>>()
+				.map(|creates| db.crdt_operation().create_many(creates).exec())
+		},
+	)
+	.await
+}
+
 #[instrument(skip(db, sync), err)]
 async fn paginate_locations(
 	db: &PrismaClient,
@@ -159,7 +203,8 @@ async fn paginate_locations(
 ) -> Result<(), Error> {
 	use location::{
 		available_capacity, date_created, generate_preview_media, hidden, id, include, instance,
-		is_archived, name, path, size_in_bytes, sync_preview_media, total_capacity,
+		is_archived, name, path, rescan_schedule, size_in_bytes, sync_preview_media,
+		total_capacity, volume_identifier, volume_path,
 	};
 
 	paginate(
@@ -199,6 +244,9 @@ async fn paginate_locations(
 								option_sync_entry!(l.sync_preview_media, sync_preview_media),
 								option_sync_entry!(l.hidden, hidden),
 								option_sync_entry!(l.date_created, date_created),
+								option_sync_entry!(l.volume_identifier, volume_identifier),
+								option_sync_entry!(l.volume_path, volume_path),
+								option_sync_entry!(l.rescan_schedule, rescan_schedule),
 								option_sync_entry!(
 									l.instance.map(|i| {
 										prisma_sync::instance::SyncId { pub_id: i.pub_id }
@@ -270,7 +318,7 @@ async fn paginate_exif_datas(
 ) -> Result<(), Error> {
 	use exif_data::{
 		artist, camera_data, copyright, description, epoch_time, exif_version, id, include,
-		media_date, media_location, resolution,
+		latitude, longitude, media_date, media_location, resolution,
 	};
 
 	paginate(
@@ -307,6 +355,8 @@ async fn paginate_exif_datas(
 								option_sync_entry!(ed.copyright, copyright),
 								option_sync_entry!(ed.exif_version, exif_version),
 								option_sync_entry!(ed.epoch_time, epoch_time),
+								option_sync_entry!(ed.latitude, latitude),
+								option_sync_entry!(ed.longitude, longitude),
 							],
 						),
 					)
@@ -518,3 +568,201 @@ async fn paginate_labels_on_objects(
 	)
 	.await
 }
+
+#[instrument(skip(db, sync), err)]
+async fn paginate_albums(
+	db: &PrismaClient,
+	sync: &crate::Manager,
+	instance_id: instance::id::Type,
+) -> Result<(), Error> {
+	use album::{cover_object, date_created, date_modified, id, include, is_hidden, name};
+
+	paginate(
+		|cursor| {
+			db.album()
+				.find_many(vec![id::gt(cursor)])
+				.order_by(id::order(SortOrder::Asc))
+				.take(1000)
+				.include(include!({ cover_object: select { pub_id } }))
+				.exec()
+		},
+		|album| album.id,
+		|albums| {
+			albums
+				.into_iter()
+				.flat_map(|a| {
+					sync.shared_create(
+						prisma_sync::album::SyncId { pub_id: a.pub_id },
+						chain_optional_iter(
+							[],
+							[
+								option_sync_entry!(a.name, name),
+								option_sync_entry!(a.is_hidden, is_hidden),
+								option_sync_entry!(a.date_created, date_created),
+								option_sync_entry!(a.date_modified, date_modified),
+								option_sync_entry!(
+									a.cover_object
+										.map(|o| prisma_sync::object::SyncId { pub_id: o.pub_id }),
+									cover_object
+								),
+							],
+						),
+					)
+				})
+				.map(|o| crdt_op_unchecked_db(&o, instance_id))
+				.collect::<Result<Vec<_>, _>>()
+				.map(|creates| db.crdt_operation().create_many(creates).exec())
+		},
+	)
+	.await
+}
+
+#[instrument(skip(db, sync), err)]
+async fn paginate_objects_in_albums(
+	db: &PrismaClient,
+	sync: &crate::Manager,
+	instance_id: instance::id::Type,
+) -> Result<(), Error> {
+	use object_in_album::{album_id, date_created, include, object_id, position};
+
+	paginate_relation(
+		|group_id, item_id| {
+			db.object_in_album()
+				.find_many(vec![album_id::gt(group_id), object_id::gt(item_id)])
+				.order_by(album_id::order(SortOrder::Asc))
+				.order_by(object_id::order(SortOrder::Asc))
+				.include(include!({
+					album: select { pub_id }
+					object: select { pub_id }
+				}))
+				.exec()
+		},
+		|o_a| (o_a.album_id, o_a.object_id),
+		|objects_in_albums| {
+			objects_in_albums
+				.into_iter()
+				.flat_map(|o_a| {
+					sync.relation_create(
+						prisma_sync::object_in_album::SyncId {
+							album: prisma_sync::album::SyncId {
+								pub_id: o_a.album.pub_id,
+							},
+							object: prisma_sync::object::SyncId {
+								pub_id: o_a.object.pub_id,
+							},
+						},
+						chain_optional_iter(
+							[],
+							[
+								option_sync_entry!(o_a.date_created, date_created),
+								option_sync_entry!(o_a.position, position),
+							],
+						),
+					)
+				})
+				.map(|o| crdt_op_unchecked_db(&o, instance_id))
+				.collect::<Result<Vec<_>, _>>()
+				.map(|creates| db.crdt_operation().create_many(creates).exec())
+		},
+	)
+	.await
+}
+
+#[instrument(skip(db, sync), err)]
+async fn paginate_spaces(
+	db: &PrismaClient,
+	sync: &crate::Manager,
+	instance_id: instance::id::Type,
+) -> Result<(), Error> {
+	use space::{cover_object, date_created, date_modified, description, id, include, name};
+
+	paginate(
+		|cursor| {
+			db.space()
+				.find_many(vec![id::gt(cursor)])
+				.order_by(id::order(SortOrder::Asc))
+				.take(1000)
+				.include(include!({ cover_object: select { pub_id } }))
+				.exec()
+		},
+		|space| space.id,
+		|spaces| {
+			spaces
+				.into_iter()
+				.flat_map(|s| {
+					sync.shared_create(
+						prisma_sync::space::SyncId { pub_id: s.pub_id },
+						chain_optional_iter(
+							[],
+							[
+								option_sync_entry!(s.name, name),
+								option_sync_entry!(s.description, description),
+								option_sync_entry!(s.date_created, date_created),
+								option_sync_entry!(s.date_modified, date_modified),
+								option_sync_entry!(
+									s.cover_object
+										.map(|o| prisma_sync::object::SyncId { pub_id: o.pub_id }),
+									cover_object
+								),
+							],
+						),
+					)
+				})
+				.map(|o| crdt_op_unchecked_db(&o, instance_id))
+				.collect::<Result<Vec<_>, _>>()
+				.map(|creates| db.crdt_operation().create_many(creates).exec())
+		},
+	)
+	.await
+}
+
+#[instrument(skip(db, sync), err)]
+async fn paginate_objects_in_spaces(
+	db: &PrismaClient,
+	sync: &crate::Manager,
+	instance_id: instance::id::Type,
+) -> Result<(), Error> {
+	use object_in_space::{date_created, include, object_id, position, space_id};
+
+	paginate_relation(
+		|group_id, item_id| {
+			db.object_in_space()
+				.find_many(vec![space_id::gt(group_id), object_id::gt(item_id)])
+				.order_by(space_id::order(SortOrder::Asc))
+				.order_by(object_id::order(SortOrder::Asc))
+				.include(include!({
+					space: select { pub_id }
+					object: select { pub_id }
+				}))
+				.exec()
+		},
+		|o_s| (o_s.space_id, o_s.object_id),
+		|objects_in_spaces| {
+			objects_in_spaces
+				.into_iter()
+				.flat_map(|o_s| {
+					sync.relation_create(
+						prisma_sync::object_in_space::SyncId {
+							space: prisma_sync::space::SyncId {
+								pub_id: o_s.space.pub_id,
+							},
+							object: prisma_sync::object::SyncId {
+								pub_id: o_s.object.pub_id,
+							},
+						},
+						chain_optional_iter(
+							[],
+							[
+								option_sync_entry!(o_s.date_created, date_created),
+								option_sync_entry!(o_s.position, position),
+							],
+						),
+					)
+				})
+				.map(|o| crdt_op_unchecked_db(&o, instance_id))
+				.collect::<Result<Vec<_>, _>>()
+				.map(|creates| db.crdt_operation().create_many(creates).exec())
+		},
+	)
+	.await
+}
@@ -0,0 +1,333 @@
+//! Compaction of the `crdt_operation` log.
+//!
+//! Every instance tells us which operations it already has when it asks for new ones, these
+//! acknowledgements give us a watermark for each origin instance: the timestamp up to which every
+//! other known instance has its operations. Below that watermark nobody will ask for those
+//! operations again, so we can drop the ones that don't change the final state:
+//! - `Update`s superseded by a newer operation on the same `(model, record_id, field)`;
+//! - every operation of records that were deleted, including the `Delete` itself.
+//!
+//! `Create`s of live records are always kept, as ingesting an `Update` requires one, so instances
+//! joining later still rebuild the same state from the compacted log. A `Create` is only removed
+//! once its record was deleted, so a live record without one was never synced, and code looking
+//! for the origin of a record, like the orphan remover, must not take it as created locally
+//! without checking its own dates.
+
+use sd_prisma::prisma::{crdt_acknowledgement, instance, PrismaClient};
+use sd_sync::OperationKind;
+use sd_utils::from_bytes_to_uuid;
+
+use std::collections::HashMap;
+
+use prisma_client_rust::raw;
+use serde::Deserialize;
+use tracing::{debug, instrument, warn};
+use uuid::Uuid;
+
+use super::{Error, Manager, NTP64};
+
+/// What a compaction of the operations log removed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionReport {
+	pub removed_operations: u64,
+	/// Size of the removed operations, the database file only shrinks once vacuumed
+	pub reclaimed_bytes: u64,
+}
+
+impl Manager {
+	/// Records that `instance` has every operation up to the given clocks, as sent by it when
+	/// requesting new operations.
+	///
+	/// Origins missing from the clocks are forgotten, the instance doesn't have any of their
+	/// operations, e.g. after it was reset.
+	#[instrument(skip(self, clocks), err)]
+	pub async fn acknowledge(&self, instance: Uuid, clocks: &[(Uuid, NTP64)]) -> Result<(), Error> {
+		let instance_ids = instance_ids(&self.db).await?;
+
+		let Some(&instance_id) = instance_ids.get(&instance) else {
+			warn!("Received acknowledgement from an unknown instance;");
+			return Ok(());
+		};
+
+		let acknowledgements = clocks
+			.iter()
+			.filter_map(|(origin, timestamp)| {
+				instance_ids.get(origin).map(|&origin_id| {
+					(origin_id, {
+						#[allow(clippy::cast_possible_wrap)]
+						// SAFETY: we had to store using i64 due to SQLite limitations
+						{
+							timestamp.as_u64() as i64
+						}
+					})
+				})
+			})
+			.collect::<Vec<_>>();
+
+		self.db
+			._batch((
+				self.db.crdt_acknowledgement().delete_many(vec![
+					crdt_acknowledgement::instance_id::equals(instance_id),
+					crdt_acknowledgement::origin_id::not_in_vec(
+						acknowledgements
+							.iter()
+							.map(|(origin_id, _)| *origin_id)
+							.collect(),
+					),
+				]),
+				acknowledgements
+					.into_iter()
+					.map(|(origin_id, timestamp)| {
+						self.db.crdt_acknowledgement().upsert(
+							crdt_acknowledgement::instance_id_origin_id(instance_id, origin_id),
+							crdt_acknowledgement::create_unchecked(
+								instance_id,
+								origin_id,
+								timestamp,
+								vec![],
+							),
+							vec![crdt_acknowledgement::timestamp::set(timestamp)],
+						)
+					})
+					.collect::<Vec<_>>(),
+			))
+			.await?;
+
+		Ok(())
+	}
+
+	/// Removes the operations that every known instance already has and that no longer affect
+	/// the state they describe.
+	///
+	/// Holds the timestamp lock, so it never runs concurrently with writes or a backfill.
+	#[instrument(skip(self), err)]
+	pub async fn compact_operations(&self) -> Result<CompactionReport, Error> {
+		let _lock = self.timestamp_lock.lock().await;
+
+		let instance_ids = instance_ids(&self.db).await?;
+
+		let Some(&local_id) = instance_ids.get(&self.instance) else {
+			warn!("Local instance not found, skipping compaction;");
+			return Ok(CompactionReport::default());
+		};
+
+		let acknowledgements = self
+			.db
+			.crdt_acknowledgement()
+			.find_many(vec![])
+			.exec()
+			.await?
+			.into_iter()
+			.map(|ack| ((ack.instance_id, ack.origin_id), ack.timestamp))
+			.collect();
+
+		let watermarks = watermarks(
+			local_id,
+			&instance_ids.values().copied().collect::<Vec<_>>(),
+			&acknowledgements,
+		);
+
+		if watermarks.is_empty() {
+			debug!("No operations acknowledged by every instance yet;");
+			return Ok(CompactionReport::default());
+		}
+
+		let watermarks = watermarks
+			.into_iter()
+			.map(|(instance_id, timestamp)| format!("({instance_id}, {timestamp})"))
+			.collect::<Vec<_>>()
+			.join(", ");
+
+		self.db
+			._transaction()
+			.with_timeout(9_999_999_999)
+			.run(|db| async move {
+				let deleted_records =
+					remove_operations(&db, &watermarks, DELETED_RECORDS_OPERATIONS).await?;
+				let superseded_updates =
+					remove_operations(&db, &watermarks, SUPERSEDED_UPDATES).await?;
+
+				debug!(
+					?deleted_records,
+					?superseded_updates,
+					"Compacted sync operations;"
+				);
+
+				Ok(CompactionReport {
+					removed_operations: deleted_records.removed_operations
+						+ superseded_updates.removed_operations,
+					reclaimed_bytes: deleted_records.reclaimed_bytes
+						+ superseded_updates.reclaimed_bytes,
+				})
+			})
+			.await
+	}
+}
+
+/// Operations of records whose latest `Delete` is below the watermark, along with everything
+/// else that happened to them
+const DELETED_RECORDS_OPERATIONS: &str = "deleted AS (
+	SELECT model, record_id, MAX(timestamp) AS deleted_at
+	FROM crdt_operation
+	WHERE kind = '{delete}'
+	GROUP BY model, record_id
+),
+removable AS (
+	SELECT d.model, d.record_id
+	FROM deleted d
+	WHERE NOT EXISTS (
+		SELECT 1
+		FROM crdt_operation op
+		LEFT JOIN bound b ON b.instance_id = op.instance_id
+		WHERE
+			op.model = d.model
+			AND op.record_id = d.record_id
+			AND (b.timestamp IS NULL OR op.timestamp > b.timestamp OR op.timestamp > d.deleted_at)
+	)
+),
+target AS (
+	SELECT op.id, LENGTH(op.data) + LENGTH(op.record_id) + LENGTH(op.kind) AS size
+	FROM crdt_operation op
+	JOIN removable r ON r.model = op.model AND r.record_id = op.record_id
+)";
+
+/// `Update`s below the watermark with a newer operation on the same field
+const SUPERSEDED_UPDATES: &str = "target AS (
+	SELECT op.id, LENGTH(op.data) + LENGTH(op.record_id) + LENGTH(op.kind) AS size
+	FROM crdt_operation op
+	JOIN bound b ON b.instance_id = op.instance_id
+	WHERE
+		op.kind LIKE '{update}%'
+		AND op.timestamp <= b.timestamp
+		AND EXISTS (
+			SELECT 1
+			FROM crdt_operation newer
+			WHERE
+				newer.model = op.model
+				AND newer.record_id = op.record_id
+				AND newer.kind = op.kind
+				AND newer.timestamp > op.timestamp
+		)
+)";
+
+/// Removes the operations selected by the `target` table of `query`, which can use the `bound`
+/// table holding the highest removable timestamp of each instance
+async fn remove_operations(
+	db: &PrismaClient,
+	watermarks: &str,
+	query: &str,
+) -> Result<CompactionReport, Error> {
+	#[derive(Deserialize)]
+	struct Removable {
+		operations: i64,
+		bytes: i64,
+	}
+
+	// The latest operation of each instance is always kept, as sync clocks are restored from it
+	let query = format!(
+		"WITH watermark(instance_id, timestamp) AS (VALUES {watermarks}),
+		bound AS (
+			SELECT w.instance_id, MIN(w.timestamp, MAX(op.timestamp) - 1) AS timestamp
+			FROM watermark w
+			JOIN crdt_operation op ON op.instance_id = w.instance_id
+			GROUP BY w.instance_id
+		),
+		{}",
+		query
+			.replace("{delete}", &OperationKind::Delete.to_string())
+			.replace("{update}", &OperationKind::Update("").to_string())
+	);
+
+	let Some(Removable { operations, bytes }) = db
+		._query_raw::<Removable>(raw!(&format!(
+			"{query} SELECT COUNT(*) AS operations, COALESCE(SUM(size), 0) AS bytes FROM target"
+		)))
+		.exec()
+		.await?
+		.into_iter()
+		.next()
+	else {
+		return Ok(CompactionReport::default());
+	};
+
+	if operations == 0 {
+		return Ok(CompactionReport::default());
+	}
+
+	db._execute_raw(raw!(&format!(
+		"{query} DELETE FROM crdt_operation WHERE id IN (SELECT id FROM target)"
+	)))
+	.exec()
+	.await?;
+
+	#[allow(clippy::cast_sign_loss)]
+	// SAFETY: counts and sizes are never negative
+	Ok(CompactionReport {
+		removed_operations: operations as u64,
+		reclaimed_bytes: bytes as u64,
+	})
+}
+
+async fn instance_ids(db: &PrismaClient) -> Result<HashMap<Uuid, instance::id::Type>, Error> {
+	Ok(db
+		.instance()
+		.find_many(vec![])
+		.select(instance::select!({ id pub_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|instance| (from_bytes_to_uuid(&instance.pub_id), instance.id))
+		.collect())
+}
+
+/// Timestamp up to which every other known instance has the operations of each origin instance.
+///
+/// Origins missing from the result can't be compacted yet, as some instance never acknowledged
+/// any of their operations. Without other instances, everything can be compacted.
+fn watermarks(
+	local_id: instance::id::Type,
+	instance_ids: &[instance::id::Type],
+	acknowledgements: &HashMap<(instance::id::Type, instance::id::Type), i64>,
+) -> HashMap<instance::id::Type, i64> {
+	instance_ids
+		.iter()
+		.filter_map(|&origin_id| {
+			instance_ids
+				.iter()
+				.filter(|&&instance_id| instance_id != local_id && instance_id != origin_id)
+				.try_fold(i64::MAX, |watermark, &instance_id| {
+					acknowledgements
+						.get(&(instance_id, origin_id))
+						.map(|&timestamp| watermark.min(timestamp))
+				})
+				.map(|watermark| (origin_id, watermark))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_watermarks() {
+		// Alone, there is nobody to wait for
+		assert_eq!(
+			watermarks(1, &[1], &HashMap::new()),
+			HashMap::from([(1, i64::MAX)])
+		);
+
+		// Instance 2 never acknowledged anything from 3
+		let acknowledgements = HashMap::from([((2, 1), 10), ((3, 1), 5), ((3, 2), 0)]);
+		assert_eq!(
+			watermarks(1, &[1, 2, 3], &acknowledgements),
+			HashMap::from([(1, 5), (2, 0)]),
+		);
+
+		let acknowledgements = HashMap::from([((2, 1), 10), ((3, 1), 5)]);
+		assert_eq!(
+			watermarks(1, &[1, 2, 3], &acknowledgements),
+			HashMap::from([(1, 5)]),
+		);
+	}
+}
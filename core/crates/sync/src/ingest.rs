@@ -32,7 +32,7 @@ use uuid::Uuid;
 use super::{
 	actor::{create_actor_io, ActorIO, ActorTypes, HandlerIO},
 	db_operation::write_crdt_op_to_db,
-	Error, SharedState,
+	Error, SharedState, SyncMessage, WrittenRecords,
 };
 
 #[derive(Debug)]
@@ -193,19 +193,31 @@ impl Actor {
 			"Ingesting operations;",
 		);
 
+		let mut ingested = WrittenRecords::default();
+
 		for (instance, data) in event.messages.0 {
 			for (model, data) in data {
 				for (record, ops) in data {
-					if let Err(e) = self
-						.process_crdt_operations(instance, model, record, ops)
+					match self
+						.process_crdt_operations(instance, model, record.clone(), ops)
 						.await
 					{
-						error!(?e, "Failed to ingest CRDT operations;");
+						Ok(()) => ingested.insert(model, record),
+						Err(e) => error!(?e, "Failed to ingest CRDT operations;"),
 					}
 				}
 			}
 		}
 
+		if !ingested.is_empty()
+			&& self
+				.tx
+				.send(SyncMessage::Ingested(Arc::new(ingested)))
+				.is_err()
+		{
+			trace!("No subscribers for ingested records");
+		}
+
 		if let Some(tx) = event.wait_tx {
 			if tx.send(()).is_err() {
 				warn!("Failed to send wait_tx signal");
@@ -614,6 +626,7 @@ mod test {
 			active: AtomicBool::default(),
 			active_notify: Notify::default(),
 			actors: Arc::default(),
+			tx: tokio::sync::broadcast::channel(1).0,
 		});
 
 		(Actor::declare(Arc::clone(&shared)).await, shared)
@@ -35,23 +35,65 @@ use std::{
 	sync::{atomic::AtomicBool, Arc},
 };
 
-use tokio::sync::{Notify, RwLock};
+use tokio::sync::{broadcast, Notify, RwLock};
 use uuid::Uuid;
 
 mod actor;
 pub mod backfill;
+mod compaction;
 mod db_operation;
 pub mod ingest;
 mod manager;
 
+pub use compaction::CompactionReport;
 pub use ingest::*;
 pub use manager::*;
 pub use uhlc::NTP64;
 
 #[derive(Clone, Debug)]
 pub enum SyncMessage {
-	Ingested,
-	Created,
+	Ingested(Arc<WrittenRecords>),
+	Created(Arc<WrittenRecords>),
+}
+
+/// Records written by a batch of sync operations, either created locally or ingested, grouped by
+/// the id of their model.
+///
+/// Record ids are the same msgpack encoded sync ids carried by the operations.
+#[derive(Debug, Default)]
+pub struct WrittenRecords(HashMap<u16, Vec<rmpv::Value>>);
+
+impl WrittenRecords {
+	pub(crate) fn insert(&mut self, model: u16, record_id: rmpv::Value) {
+		let records = self.0.entry(model).or_default();
+		if !records.contains(&record_id) {
+			records.push(record_id);
+		}
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	pub fn models(&self) -> impl Iterator<Item = u16> + '_ {
+		self.0.keys().copied()
+	}
+
+	#[must_use]
+	pub fn records(&self, model: u16) -> &[rmpv::Value] {
+		self.0.get(&model).map(Vec::as_slice).unwrap_or_default()
+	}
+}
+
+impl<'op> FromIterator<&'op CRDTOperation> for WrittenRecords {
+	fn from_iter<I: IntoIterator<Item = &'op CRDTOperation>>(ops: I) -> Self {
+		let mut written = Self::default();
+		for op in ops {
+			written.insert(op.model, op.record_id.clone());
+		}
+		written
+	}
 }
 
 pub type Timestamps = Arc<RwLock<HashMap<Uuid, NTP64>>>;
@@ -65,6 +107,7 @@ pub struct SharedState {
 	pub active: AtomicBool,
 	pub active_notify: Notify,
 	pub actors: Arc<sd_actors::Actors>,
+	pub tx: broadcast::Sender<SyncMessage>,
 }
 
 #[derive(thiserror::Error, Debug)]
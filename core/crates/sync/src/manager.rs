@@ -120,6 +120,7 @@ impl Manager {
 			active: AtomicBool::default(),
 			active_notify: Notify::default(),
 			actors,
+			tx: tx.clone(),
 		});
 
 		let ingest = ingest::Actor::declare(shared.clone()).await;
@@ -171,7 +172,11 @@ impl Manager {
 					.insert(self.instance, last.timestamp);
 			}
 
-			if self.tx.send(SyncMessage::Created).is_err() {
+			if self
+				.tx
+				.send(SyncMessage::Created(Arc::new(ops.iter().collect())))
+				.is_err()
+			{
 				warn!("failed to send created message on `write_ops`");
 			}
 
@@ -201,7 +206,11 @@ impl Manager {
 
 			let ret = tx._batch((crdt_op_db(&op)?.to_query(tx), query)).await?.1;
 
-			if self.tx.send(SyncMessage::Created).is_err() {
+			if self
+				.tx
+				.send(SyncMessage::Created(Arc::new([&op].into_iter().collect())))
+				.is_err()
+			{
 				warn!("failed to send created message on `write_op`");
 			}
 
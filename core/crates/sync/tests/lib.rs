@@ -130,14 +130,27 @@ async fn operations_send_and_ingest() -> Result<(), Box<dyn std::error::Error>>
 
 	info!("Paired instances!");
 
+	let mut instance1_sync_rx = instance1.sync_rx.resubscribe();
+
 	write_test_location(&instance1).await;
 
 	info!("Created mock location!");
 
-	assert!(matches!(
-		instance2_sync_rx.recv().await?,
-		SyncMessage::Ingested
-	));
+	let SyncMessage::Created(created) = instance1_sync_rx.recv().await? else {
+		panic!("expected created records");
+	};
+
+	assert_eq!(created.records(prisma_sync::location::MODEL_ID).len(), 1);
+
+	let SyncMessage::Ingested(ingested) = instance2_sync_rx.recv().await? else {
+		panic!("expected ingested records");
+	};
+
+	assert_eq!(
+		ingested.models().collect::<Vec<_>>(),
+		vec![prisma_sync::location::MODEL_ID]
+	);
+	assert_eq!(ingested.records(prisma_sync::location::MODEL_ID).len(), 1);
 
 	let out = instance2
 		.sync
@@ -173,7 +186,7 @@ async fn no_update_after_delete() -> Result<(), Box<dyn std::error::Error>> {
 
 	assert!(matches!(
 		instance2_sync_rx.recv().await?,
-		SyncMessage::Ingested
+		SyncMessage::Ingested(_)
 	));
 
 	instance2
@@ -189,7 +202,7 @@ async fn no_update_after_delete() -> Result<(), Box<dyn std::error::Error>> {
 
 	assert!(matches!(
 		instance1.sync_rx.resubscribe().recv().await?,
-		SyncMessage::Ingested
+		SyncMessage::Ingested(_)
 	));
 
 	instance1
@@ -221,6 +234,110 @@ async fn no_update_after_delete() -> Result<(), Box<dyn std::error::Error>> {
 	Ok(())
 }
 
+#[tokio::test]
+#[traced_test]
+async fn compacts_superseded_and_deleted_operations() -> Result<(), Box<dyn std::error::Error>> {
+	let instance = Instance::new(Uuid::new_v4()).await;
+
+	let location = write_test_location(&instance).await;
+	let sync_id = prisma_sync::location::SyncId {
+		pub_id: location.pub_id.clone(),
+	};
+
+	for (field, value) in [
+		("name", msgpack!("Location 1")),
+		("name", msgpack!("Location 2")),
+		("total_capacity", msgpack!(2048)),
+	] {
+		instance
+			.sync
+			.write_op(
+				&instance.db,
+				instance.sync.shared_update(sync_id.clone(), field, value),
+				instance.db.location().find_many(vec![]),
+			)
+			.await?;
+	}
+
+	// The first name and total capacity updates are superseded
+	let report = instance.sync.compact_operations().await?;
+	assert_eq!(report.removed_operations, 2);
+	assert_eq!(instance.db.crdt_operation().count(vec![]).exec().await?, 4);
+
+	instance
+		.sync
+		.write_op(
+			&instance.db,
+			instance.sync.shared_delete(sync_id),
+			instance.db.location().delete_many(vec![]),
+		)
+		.await?;
+
+	// The latest operation of an instance is always kept, so the delete must not be the last one
+	write_test_location(&instance).await;
+
+	let report = instance.sync.compact_operations().await?;
+	assert_eq!(report.removed_operations, 5);
+	assert_eq!(instance.db.crdt_operation().count(vec![]).exec().await?, 3);
+
+	instance.teardown().await;
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn compaction_waits_for_acknowledgements() -> Result<(), Box<dyn std::error::Error>> {
+	let instance1 = Instance::new(Uuid::new_v4()).await;
+	let instance2 = Instance::new(Uuid::new_v4()).await;
+
+	Instance::pair(&instance1, &instance2).await;
+
+	let location = write_test_location(&instance1).await;
+
+	instance1
+		.sync
+		.write_op(
+			&instance1.db,
+			instance1.sync.shared_update(
+				prisma_sync::location::SyncId {
+					pub_id: location.pub_id.clone(),
+				},
+				"total_capacity",
+				msgpack!(2048),
+			),
+			instance1.db.location().find_many(vec![]),
+		)
+		.await?;
+
+	// Instance 2 never told us which operations it has
+	let report = instance1.sync.compact_operations().await?;
+	assert_eq!(report.removed_operations, 0);
+
+	let latest = instance1
+		.sync
+		.get_ops(GetOpsArgs {
+			clocks: vec![],
+			count: 100,
+		})
+		.await?
+		.last()
+		.map(|op| op.timestamp)
+		.expect("operations were written");
+
+	instance1
+		.sync
+		.acknowledge(instance2.id, &[(instance1.id, latest)])
+		.await?;
+
+	let report = instance1.sync.compact_operations().await?;
+	assert_eq!(report.removed_operations, 1);
+
+	instance1.teardown().await;
+	instance2.teardown().await;
+
+	Ok(())
+}
+
 fn assert_locations_equality(l1: &location::Data, l2: &location::Data) {
 	assert_eq!(l1.pub_id, l2.pub_id, "pub id");
 	assert_eq!(l1.name, l2.name, "name");
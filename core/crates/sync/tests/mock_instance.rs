@@ -95,7 +95,7 @@ impl Instance {
 				async move {
 					while let Ok(msg) = sync_rx_left.recv().await {
 						info!(?msg, "sync_rx_left received message");
-						if matches!(msg, SyncMessage::Created) {
+						if matches!(msg, SyncMessage::Created(_)) {
 							right
 								.sync
 								.ingest
@@ -145,9 +145,7 @@ impl Instance {
 									warn!("failed to send ack to instance 1");
 								}
 							}
-							ingest::Request::FinishedIngesting => {
-								right.sync.tx.send(SyncMessage::Ingested).unwrap();
-							}
+							ingest::Request::FinishedIngesting => {}
 						}
 					}
 				}
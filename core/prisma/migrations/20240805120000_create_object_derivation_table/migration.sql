@@ -0,0 +1,10 @@
+-- CreateTable
+CREATE TABLE "object_derivation" (
+    "source_id" INTEGER NOT NULL,
+    "derived_id" INTEGER NOT NULL,
+    "date_created" DATETIME,
+
+    PRIMARY KEY ("source_id", "derived_id"),
+    CONSTRAINT "object_derivation_source_id_fkey" FOREIGN KEY ("source_id") REFERENCES "object" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "object_derivation_derived_id_fkey" FOREIGN KEY ("derived_id") REFERENCES "object" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
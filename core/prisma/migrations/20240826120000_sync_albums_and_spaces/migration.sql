@@ -0,0 +1,39 @@
+-- AlterTable
+ALTER TABLE "object_in_album" ADD COLUMN "position" INTEGER;
+
+-- AlterTable
+ALTER TABLE "object_in_space" ADD COLUMN "date_created" DATETIME;
+ALTER TABLE "object_in_space" ADD COLUMN "position" INTEGER;
+
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_album" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "pub_id" BLOB NOT NULL,
+    "name" TEXT,
+    "is_hidden" BOOLEAN,
+    "date_created" DATETIME,
+    "date_modified" DATETIME,
+    "cover_object_id" INTEGER,
+    CONSTRAINT "album_cover_object_id_fkey" FOREIGN KEY ("cover_object_id") REFERENCES "object" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+INSERT INTO "new_album" ("date_created", "date_modified", "id", "is_hidden", "name", "pub_id") SELECT "date_created", "date_modified", "id", "is_hidden", "name", "pub_id" FROM "album";
+DROP TABLE "album";
+ALTER TABLE "new_album" RENAME TO "album";
+CREATE UNIQUE INDEX "album_pub_id_key" ON "album"("pub_id");
+CREATE TABLE "new_space" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "pub_id" BLOB NOT NULL,
+    "name" TEXT,
+    "description" TEXT,
+    "date_created" DATETIME,
+    "date_modified" DATETIME,
+    "cover_object_id" INTEGER,
+    CONSTRAINT "space_cover_object_id_fkey" FOREIGN KEY ("cover_object_id") REFERENCES "object" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+INSERT INTO "new_space" ("date_created", "date_modified", "description", "id", "name", "pub_id") SELECT "date_created", "date_modified", "description", "id", "name", "pub_id" FROM "space";
+DROP TABLE "space";
+ALTER TABLE "new_space" RENAME TO "space";
+CREATE UNIQUE INDEX "space_pub_id_key" ON "space"("pub_id");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
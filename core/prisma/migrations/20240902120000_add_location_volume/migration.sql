@@ -0,0 +1,3 @@
+-- AlterTable
+ALTER TABLE "location" ADD COLUMN "volume_identifier" TEXT;
+ALTER TABLE "location" ADD COLUMN "volume_path" TEXT;
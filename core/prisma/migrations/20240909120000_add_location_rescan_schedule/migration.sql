@@ -0,0 +1,5 @@
+-- AlterTable
+ALTER TABLE "location" ADD COLUMN "rescan_schedule" TEXT;
+ALTER TABLE "location" ADD COLUMN "rescan_last_attempt_at" DATETIME;
+ALTER TABLE "location" ADD COLUMN "rescan_failures" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "location" ADD COLUMN "last_verified_at" DATETIME;
@@ -0,0 +1,16 @@
+-- CreateTable
+CREATE TABLE "crdt_acknowledgement" (
+    "instance_id" INTEGER NOT NULL,
+    "origin_id" INTEGER NOT NULL,
+    "timestamp" BIGINT NOT NULL,
+
+    PRIMARY KEY ("instance_id", "origin_id"),
+    CONSTRAINT "crdt_acknowledgement_instance_id_fkey" FOREIGN KEY ("instance_id") REFERENCES "instance" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "crdt_acknowledgement_origin_id_fkey" FOREIGN KEY ("origin_id") REFERENCES "instance" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- AlterTable
+ALTER TABLE "statistics" ADD COLUMN "sync_operations_reclaimed_bytes" TEXT NOT NULL DEFAULT '0';
+
+-- CreateIndex
+CREATE INDEX "crdt_operation_model_record_id_kind_timestamp_idx" ON "crdt_operation"("model", "record_id", "kind", "timestamp");
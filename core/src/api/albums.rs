@@ -0,0 +1,468 @@
+use crate::{invalidate_query, library::Library};
+
+use sd_prisma::{
+	prisma::{album, object, object_in_album, PrismaClient, SortOrder},
+	prisma_sync,
+};
+use sd_sync::{option_sync_db_entry, sync_db_entry, sync_entry, OperationFactory};
+use sd_utils::{msgpack, uuid_to_bytes};
+
+use chrono::Utc;
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::Deserialize;
+use specta::Type;
+use uuid::Uuid;
+
+use super::{
+	object_collections::{cover_object_pub_id, objects_pub_ids, objects_to_add, reorder},
+	utils::library,
+	Ctx, R,
+};
+
+#[derive(Type, Deserialize)]
+pub struct AlbumObjectsArgs {
+	pub id: album::id::Type,
+	pub object_ids: Vec<object::id::Type>,
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library
+					.db
+					.album()
+					.find_many(vec![])
+					.order_by(album::date_created::order(SortOrder::Asc))
+					.exec()
+					.await?)
+			})
+		})
+		.procedure("get", {
+			R.with2(library())
+				.query(|(_, library), album_id: album::id::Type| async move {
+					Ok(library
+						.db
+						.album()
+						.find_unique(album::id::equals(album_id))
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("getForObject", {
+			R.with2(library())
+				.query(|(_, library), object_id: object::id::Type| async move {
+					Ok(library
+						.db
+						.album()
+						.find_many(vec![album::objects::some(vec![
+							object_in_album::object_id::equals(object_id),
+						])])
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("getObjects", {
+			R.with2(library())
+				.query(|(_, library), album_id: album::id::Type| async move {
+					Ok(library
+						.db
+						.object_in_album()
+						.find_many(vec![object_in_album::album_id::equals(album_id)])
+						.order_by(object_in_album::position::order(SortOrder::Asc))
+						.include(object_in_album::include!({ object }))
+						.exec()
+						.await?
+						.into_iter()
+						.map(|object_in_album| object_in_album.object)
+						.collect::<Vec<_>>())
+				})
+		})
+		.procedure("create", {
+			#[derive(Type, Deserialize)]
+			pub struct AlbumCreateArgs {
+				pub name: String,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumCreateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = uuid_to_bytes(&Uuid::new_v4());
+
+					let (sync_params, db_params): (Vec<_>, Vec<_>) = [
+						sync_db_entry!(args.name, album::name),
+						sync_db_entry!(false, album::is_hidden),
+						sync_db_entry!(Utc::now(), album::date_created),
+					]
+					.into_iter()
+					.unzip();
+
+					let album = sync
+						.write_ops(
+							db,
+							(
+								sync.shared_create(
+									prisma_sync::album::SyncId {
+										pub_id: pub_id.clone(),
+									},
+									sync_params,
+								),
+								db.album().create(pub_id, db_params),
+							),
+						)
+						.await?;
+
+					invalidate_query!(library, "albums.list");
+
+					Ok(album)
+				})
+		})
+		.procedure("update", {
+			#[derive(Type, Deserialize)]
+			pub struct AlbumUpdateArgs {
+				pub id: album::id::Type,
+				pub name: Option<String>,
+				pub is_hidden: Option<bool>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumUpdateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = album_pub_id(db, args.id).await?;
+
+					let (sync_params, db_params): (Vec<_>, Vec<_>) = [
+						option_sync_db_entry!(args.name, album::name),
+						option_sync_db_entry!(args.is_hidden, album::is_hidden),
+						Some(sync_db_entry!(Utc::now(), album::date_modified)),
+					]
+					.into_iter()
+					.flatten()
+					.unzip();
+
+					sync.write_ops(
+						db,
+						(
+							sync_params
+								.into_iter()
+								.map(|(k, v)| {
+									sync.shared_update(
+										prisma_sync::album::SyncId {
+											pub_id: pub_id.clone(),
+										},
+										k,
+										v,
+									)
+								})
+								.collect(),
+							db.album().update(album::id::equals(args.id), db_params),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.list");
+
+					Ok(())
+				})
+		})
+		.procedure("setCover", {
+			#[derive(Type, Deserialize)]
+			pub struct AlbumSetCoverArgs {
+				pub id: album::id::Type,
+				/// Removes the cover when missing
+				pub object_id: Option<object::id::Type>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumSetCoverArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album_sync_id = prisma_sync::album::SyncId {
+						pub_id: album_pub_id(db, args.id).await?,
+					};
+
+					match args.object_id {
+						Some(object_id) => {
+							let object_pub_id = cover_object_pub_id(db, object_id).await?;
+
+							sync.write_op(
+								db,
+								sync.shared_update(
+									album_sync_id,
+									album::cover_object::NAME,
+									msgpack!(prisma_sync::object::SyncId {
+										pub_id: object_pub_id
+									}),
+								),
+								db.album().update(
+									album::id::equals(args.id),
+									vec![album::cover_object::connect(object::id::equals(
+										object_id,
+									))],
+								),
+							)
+							.await?;
+						}
+						None => {
+							sync.write_op(
+								db,
+								sync.shared_update(
+									album_sync_id,
+									album::cover_object_id::NAME,
+									msgpack!(nil),
+								),
+								db.album().update(
+									album::id::equals(args.id),
+									vec![album::cover_object::disconnect()],
+								),
+							)
+							.await?;
+						}
+					}
+
+					invalidate_query!(library, "albums.list");
+
+					Ok(())
+				})
+		})
+		.procedure("addObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album_pub_id = album_pub_id(db, args.id).await?;
+
+					let members = db
+						.object_in_album()
+						.find_many(vec![object_in_album::album_id::equals(args.id)])
+						.select(object_in_album::select!({ object_id position }))
+						.exec()
+						.await?;
+
+					let objects = objects_to_add(
+						db,
+						&args.object_ids,
+						members
+							.iter()
+							.map(|member| (member.object_id, member.position)),
+					)
+					.await?;
+
+					if objects.is_empty() {
+						return Ok(());
+					}
+
+					let date_created = Utc::now();
+
+					let (sync_ops, db_creates) = objects.into_iter().fold(
+						(vec![], vec![]),
+						|(mut sync_ops, mut db_creates), (object, position)| {
+							sync_ops.extend(sync.relation_create(
+								prisma_sync::object_in_album::SyncId {
+									album: prisma_sync::album::SyncId {
+										pub_id: album_pub_id.clone(),
+									},
+									object: prisma_sync::object::SyncId {
+										pub_id: object.pub_id,
+									},
+								},
+								[
+									sync_entry!(date_created, object_in_album::date_created),
+									sync_entry!(position, object_in_album::position),
+								],
+							));
+
+							db_creates.push(object_in_album::CreateUnchecked {
+								album_id: args.id,
+								object_id: object.id,
+								_params: vec![
+									object_in_album::date_created::set(Some(date_created.into())),
+									object_in_album::position::set(Some(position)),
+								],
+							});
+
+							(sync_ops, db_creates)
+						},
+					);
+
+					sync.write_ops(
+						db,
+						(
+							sync_ops,
+							db.object_in_album()
+								.create_many(db_creates)
+								.skip_duplicates(),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.getObjects");
+					invalidate_query!(library, "albums.getForObject");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("removeObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album_pub_id = album_pub_id(db, args.id).await?;
+
+					let objects_pub_ids = objects_pub_ids(db, args.object_ids.clone()).await?;
+
+					sync.write_ops(
+						db,
+						(
+							objects_pub_ids
+								.into_iter()
+								.map(|object_pub_id| {
+									sync.relation_delete(prisma_sync::object_in_album::SyncId {
+										album: prisma_sync::album::SyncId {
+											pub_id: album_pub_id.clone(),
+										},
+										object: prisma_sync::object::SyncId {
+											pub_id: object_pub_id,
+										},
+									})
+								})
+								.collect(),
+							db.object_in_album().delete_many(vec![
+								object_in_album::album_id::equals(args.id),
+								object_in_album::object_id::in_vec(args.object_ids),
+							]),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.getObjects");
+					invalidate_query!(library, "albums.getForObject");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("reorder", {
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album_pub_id = album_pub_id(db, args.id).await?;
+
+					let members = db
+						.object_in_album()
+						.find_many(vec![object_in_album::album_id::equals(args.id)])
+						.order_by(object_in_album::position::order(SortOrder::Asc))
+						.select(object_in_album::select!({
+							position
+							object: select { id pub_id }
+						}))
+						.exec()
+						.await?;
+
+					let (sync_ops, db_updates): (Vec<_>, Vec<_>) =
+						reorder(members, args.object_ids, |member| {
+							(member.object.id, member.position)
+						})
+						.into_iter()
+						.map(|(member, position)| {
+							(
+								sync.relation_update(
+									prisma_sync::object_in_album::SyncId {
+										album: prisma_sync::album::SyncId {
+											pub_id: album_pub_id.clone(),
+										},
+										object: prisma_sync::object::SyncId {
+											pub_id: member.object.pub_id,
+										},
+									},
+									object_in_album::position::NAME,
+									msgpack!(position),
+								),
+								db.object_in_album().update(
+									object_in_album::album_id_object_id(args.id, member.object.id),
+									vec![object_in_album::position::set(Some(position))],
+								),
+							)
+						})
+						.unzip();
+
+					if !sync_ops.is_empty() {
+						sync.write_ops(db, (sync_ops, db_updates)).await?;
+					}
+
+					invalidate_query!(library, "albums.getObjects");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), album_id: album::id::Type| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let album = db
+						.album()
+						.find_unique(album::id::equals(album_id))
+						.select(album::select!({
+							pub_id
+							objects: select { object: select { pub_id } }
+						}))
+						.exec()
+						.await?
+						.ok_or_else(|| {
+							rspc::Error::new(ErrorCode::NotFound, "Album not found".to_string())
+						})?;
+
+					let album_sync_id = prisma_sync::album::SyncId {
+						pub_id: album.pub_id,
+					};
+
+					sync.write_ops(
+						db,
+						(
+							album
+								.objects
+								.into_iter()
+								.map(|object_in_album| {
+									sync.relation_delete(prisma_sync::object_in_album::SyncId {
+										album: album_sync_id.clone(),
+										object: prisma_sync::object::SyncId {
+											pub_id: object_in_album.object.pub_id,
+										},
+									})
+								})
+								.chain([sync.shared_delete(album_sync_id)])
+								.collect(),
+							(
+								db.object_in_album()
+									.delete_many(vec![object_in_album::album_id::equals(album_id)]),
+								db.album().delete(album::id::equals(album_id)),
+							),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.getForObject");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+}
+
+async fn album_pub_id(
+	db: &PrismaClient,
+	album_id: album::id::Type,
+) -> Result<album::pub_id::Type, rspc::Error> {
+	db.album()
+		.find_unique(album::id::equals(album_id))
+		.select(album::select!({ pub_id }))
+		.exec()
+		.await?
+		.map(|album| album.pub_id)
+		.ok_or_else(|| rspc::Error::new(ErrorCode::NotFound, "Album not found".to_string()))
+}
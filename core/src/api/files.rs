@@ -1,21 +1,28 @@
 use crate::{
 	api::utils::library,
+	context::NodeContext,
 	invalidate_query,
 	library::Library,
 	location::{get_location_path_from_location_id, LocationError},
 	object::{
-		fs::{
-			error::FileSystemJobsError, find_available_filename_for_duplicate,
-			old_copy::OldFileCopierJobInit, old_cut::OldFileCutterJobInit,
-			old_delete::OldFileDeleterJobInit, old_erase::OldFileEraserJobInit,
-		},
+		fs::{error::FileSystemJobsError, find_available_filename_for_duplicate},
 		// media::{exif_media_data_from_prisma_data, ffmpeg_data_from_prisma_data},
 	},
-	old_job::OldJob,
+	volume::{get_volumes, DiskType},
 };
 
 use sd_core_file_path_helper::{FilePathError, IsolatedFilePathData};
-use sd_core_heavy_lifting::media_processor::{exif_media_data, ffmpeg_media_data};
+use sd_core_heavy_lifting::{
+	archive::{self, ArchiveFormat, Compressor, Extractor},
+	convert::{ConvertOptions, Converter},
+	duplicate_finder::find_duplicate_sets,
+	file_crypto::{Decryptor, Encryptor},
+	file_system::{Copier, Deleter, EraseVolume, Eraser, Mover},
+	media_processor::{
+		exif_media_data, ffmpeg_media_data,
+		text_preview::{self, MAX_SNIPPET_LENGTH},
+	},
+};
 use sd_core_prisma_helpers::{
 	file_path_to_isolate, file_path_to_isolate_with_id, object_with_file_paths,
 	object_with_media_data,
@@ -47,6 +54,7 @@ use tokio::{fs, io, task::spawn_blocking};
 use tracing::{error, warn};
 #[cfg(not(any(target_os = "ios", target_os = "android")))]
 use trash;
+use uuid::Uuid;
 
 use super::{Ctx, R};
 
@@ -67,6 +75,60 @@ pub(crate) enum MediaData {
 	FFmpeg(FFmpegMetadata),
 }
 
+#[derive(Type, Deserialize)]
+pub struct DeleteFilesArgs {
+	pub location_id: location::id::Type,
+	pub file_path_ids: Vec<file_path::id::Type>,
+}
+
+#[derive(Type, Deserialize)]
+pub struct CryptoFilesArgs {
+	pub location_id: location::id::Type,
+	pub file_path_ids: Vec<file_path::id::Type>,
+	pub key_id: Uuid,
+}
+
+#[derive(Type, Deserialize)]
+pub struct CompressFilesArgs {
+	pub location_id: location::id::Type,
+	pub file_path_ids: Vec<file_path::id::Type>,
+	pub format: ArchiveFormat,
+	pub name: Option<String>,
+}
+
+#[derive(Type, Deserialize)]
+pub struct ExtractArchiveArgs {
+	pub location_id: location::id::Type,
+	pub file_path_id: file_path::id::Type,
+}
+
+#[derive(Type, Deserialize)]
+pub struct EraseFilesArgs {
+	pub location_id: location::id::Type,
+	pub file_path_ids: Vec<file_path::id::Type>,
+	pub passes: u32,
+	/// Rename files to random names before unlinking them, so their names don't linger on disk
+	#[serde(default)]
+	pub rename_before_unlink: bool,
+}
+
+#[derive(Type, Deserialize)]
+pub struct CopyOrMoveFilesArgs {
+	pub source_location_id: location::id::Type,
+	pub target_location_id: location::id::Type,
+	pub sources_file_path_ids: Vec<file_path::id::Type>,
+	pub target_location_relative_directory_path: PathBuf,
+}
+
+#[derive(Type, Deserialize)]
+pub struct ConvertFilesArgs {
+	pub source_location_id: location::id::Type,
+	pub target_location_id: location::id::Type,
+	pub sources_file_path_ids: Vec<file_path::id::Type>,
+	pub target_location_relative_directory_path: PathBuf,
+	pub options: ConvertOptions,
+}
+
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
 		.procedure("get", {
@@ -171,6 +233,75 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.map(|str| str.to_string()))
 				})
 		})
+		.procedure("archiveEntries", {
+			R.with2(library())
+				.query(|(_, library), id: file_path::id::Type| async move {
+					let isolated_path = IsolatedFilePathData::try_from(
+						library
+							.db
+							.file_path()
+							.find_unique(file_path::id::equals(id))
+							.select(file_path_to_isolate::select())
+							.exec()
+							.await?
+							.ok_or(LocationError::FilePath(FilePathError::IdNotFound(id)))?,
+					)
+					.map_err(LocationError::MissingField)?;
+
+					let location_path = get_location_path_from_location_id(
+						&library.db,
+						isolated_path.location_id(),
+					)
+					.await?;
+
+					archive::list_entries(Path::new(&location_path).join(&isolated_path))
+						.await
+						.map_err(Into::into)
+				})
+		})
+		.procedure("getTextSnippet", {
+			R.with2(library())
+				.query(|(_, library), id: file_path::id::Type| async move {
+					let isolated_path = IsolatedFilePathData::try_from(
+						library
+							.db
+							.file_path()
+							.find_unique(file_path::id::equals(id))
+							.select(file_path_to_isolate::select())
+							.exec()
+							.await?
+							.ok_or(LocationError::FilePath(FilePathError::IdNotFound(id)))?,
+					)
+					.map_err(LocationError::MissingField)?;
+
+					let location_path = get_location_path_from_location_id(
+						&library.db,
+						isolated_path.location_id(),
+					)
+					.await?;
+
+					// `None` for files that aren't text, so the frontend can fall back to an icon
+					text_preview::read_snippet(
+						Path::new(&location_path).join(&isolated_path),
+						MAX_SNIPPET_LENGTH,
+					)
+					.await
+					.map_err(Into::into)
+				})
+		})
+		.procedure("duplicates", {
+			#[derive(Type, Deserialize)]
+			pub struct DuplicatesArgs {
+				pub cursor: Option<String>,
+				pub take: u32,
+			}
+
+			R.with2(library()).query(
+				|(_, library), DuplicatesArgs { cursor, take }: DuplicatesArgs| async move {
+					Ok(find_duplicate_sets(&library.db, cursor, take).await?)
+				},
+			)
+		})
 		.procedure("setNote", {
 			#[derive(Type, Deserialize)]
 			pub struct SetNoteArgs {
@@ -419,21 +550,105 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					Ok(())
 				})
 		})
-		// .procedure("encryptFiles", {
-		// 	R.with2(library())
-		// 		.mutation(|(node, library), args: FileEncryptorJobInit| async move {
-		// 			Job::new(args).spawn(&node, &library).await.map_err(Into::into)
-		// 		})
-		// })
-		// .procedure("decryptFiles", {
-		// 	R.with2(library())
-		// 		.mutation(|(node, library), args: FileDecryptorJobInit| async move {
-		// 			Job::new(args).spawn(&node, &library).await.map_err(Into::into)
-		// 		})
-		// })
+		.procedure("encryptFiles", {
+			R.with2(library()).mutation(
+				|(node, library),
+				 CryptoFilesArgs {
+				     location_id,
+				     file_path_ids,
+				     key_id,
+				 }: CryptoFilesArgs| async move {
+					let root_key = library.keyring.get(key_id).await?;
+
+					node.job_system
+						.dispatch(
+							Encryptor::new(location_id, file_path_ids, root_key),
+							location_id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
+						.await
+						.map(|_| ())
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("decryptFiles", {
+			R.with2(library()).mutation(
+				|(node, library),
+				 CryptoFilesArgs {
+				     location_id,
+				     file_path_ids,
+				     key_id,
+				 }: CryptoFilesArgs| async move {
+					let root_key = library.keyring.get(key_id).await?;
+
+					node.job_system
+						.dispatch(
+							Decryptor::new(location_id, file_path_ids, root_key),
+							location_id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
+						.await
+						.map(|_| ())
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("compressFiles", {
+			R.with2(library()).mutation(
+				|(node, library),
+				 CompressFilesArgs {
+				     location_id,
+				     file_path_ids,
+				     format,
+				     name,
+				 }: CompressFilesArgs| async move {
+					node.job_system
+						.dispatch(
+							Compressor::new(location_id, file_path_ids, format, name),
+							location_id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
+						.await
+						.map(|_| ())
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("extractArchive", {
+			R.with2(library()).mutation(
+				|(node, library),
+				 ExtractArchiveArgs {
+				     location_id,
+				     file_path_id,
+				 }: ExtractArchiveArgs| async move {
+					node.job_system
+						.dispatch(
+							Extractor::new(location_id, file_path_id),
+							location_id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
+						.await
+						.map(|_| ())
+						.map_err(Into::into)
+				},
+			)
+		})
 		.procedure("deleteFiles", {
 			R.with2(library())
-				.mutation(|(node, library), args: OldFileDeleterJobInit| async move {
+				.mutation(|(node, library), args: DeleteFilesArgs| async move {
 					match args.file_path_ids.len() {
 						0 => Ok(()),
 						1 => {
@@ -496,16 +711,25 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 								}
 							}
 						}
-						_ => OldJob::new(args)
-							.spawn(&node, &library)
+						_ => node
+							.job_system
+							.dispatch(
+								Deleter::new(args.location_id, args.file_path_ids),
+								args.location_id,
+								NodeContext {
+									node: Arc::clone(&node),
+									library,
+								},
+							)
 							.await
+							.map(|_| ())
 							.map_err(Into::into),
 					}
 				})
 		})
 		.procedure("moveToTrash", {
 			R.with2(library())
-				.mutation(|(node, library), args: OldFileDeleterJobInit| async move {
+				.mutation(|(node, library), args: DeleteFilesArgs| async move {
 					if cfg!(target_os = "ios") || cfg!(target_os = "android") {
 						return Err(rspc::Error::new(
 							ErrorCode::MethodNotSupported,
@@ -561,9 +785,18 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 
 							Ok(())
 						}
-						_ => OldJob::new(args)
-							.spawn(&node, &library)
+						_ => node
+							.job_system
+							.dispatch(
+								Deleter::new(args.location_id, args.file_path_ids),
+								args.location_id,
+								NodeContext {
+									node: Arc::clone(&node),
+									library,
+								},
+							)
 							.await
+							.map(|_| ())
 							.map_err(Into::into),
 					}
 				})
@@ -702,35 +935,138 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					Ok(())
 				})
 		})
+		.procedure("convertFiles", {
+			R.with2(library()).mutation(
+				|(node, library),
+				 ConvertFilesArgs {
+				     source_location_id,
+				     target_location_id,
+				     sources_file_path_ids,
+				     target_location_relative_directory_path,
+				     options,
+				 }: ConvertFilesArgs| async move {
+					node.job_system
+						.dispatch(
+							Converter::new(
+								source_location_id,
+								target_location_id,
+								sources_file_path_ids,
+								target_location_relative_directory_path,
+								options,
+							),
+							source_location_id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
+						.await
+						.map(|_| ())
+						.map_err(Into::into)
+				},
+			)
+		})
 		.procedure("getConvertibleImageExtensions", {
 			R.query(|_, _: ()| async move { Ok(sd_images::all_compatible_extensions()) })
 		})
 		.procedure("eraseFiles", {
-			R.with2(library())
-				.mutation(|(node, library), args: OldFileEraserJobInit| async move {
-					OldJob::new(args)
-						.spawn(&node, &library)
+			R.with2(library()).mutation(
+				|(node, library),
+				 EraseFilesArgs {
+				     location_id,
+				     file_path_ids,
+				     passes,
+				     rename_before_unlink,
+				 }: EraseFilesArgs| async move {
+					let volumes = get_volumes()
 						.await
+						.into_iter()
+						.flat_map(|volume| {
+							let is_ssd = volume.disk_type == DiskType::SSD;
+							volume
+								.mount_points
+								.into_iter()
+								.map(move |mount_point| EraseVolume {
+									mount_point,
+									file_system: volume.file_system.clone(),
+									is_ssd,
+								})
+						})
+						.collect::<Vec<_>>();
+
+					node.job_system
+						.dispatch(
+							Eraser::new(location_id, file_path_ids, passes, rename_before_unlink)
+								.with_volumes(volumes),
+							location_id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
+						.await
+						.map(|_| ())
 						.map_err(Into::into)
-				})
+				},
+			)
 		})
 		.procedure("copyFiles", {
-			R.with2(library())
-				.mutation(|(node, library), args: OldFileCopierJobInit| async move {
-					OldJob::new(args)
-						.spawn(&node, &library)
+			R.with2(library()).mutation(
+				|(node, library),
+				 CopyOrMoveFilesArgs {
+				     source_location_id,
+				     target_location_id,
+				     sources_file_path_ids,
+				     target_location_relative_directory_path,
+				 }: CopyOrMoveFilesArgs| async move {
+					node.job_system
+						.dispatch(
+							Copier::new(
+								source_location_id,
+								target_location_id,
+								sources_file_path_ids,
+								target_location_relative_directory_path,
+							),
+							source_location_id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
 						.await
+						.map(|_| ())
 						.map_err(Into::into)
-				})
+				},
+			)
 		})
 		.procedure("cutFiles", {
-			R.with2(library())
-				.mutation(|(node, library), args: OldFileCutterJobInit| async move {
-					OldJob::new(args)
-						.spawn(&node, &library)
+			R.with2(library()).mutation(
+				|(node, library),
+				 CopyOrMoveFilesArgs {
+				     source_location_id,
+				     target_location_id,
+				     sources_file_path_ids,
+				     target_location_relative_directory_path,
+				 }: CopyOrMoveFilesArgs| async move {
+					node.job_system
+						.dispatch(
+							Mover::new(
+								source_location_id,
+								target_location_id,
+								sources_file_path_ids,
+								target_location_relative_directory_path,
+							),
+							source_location_id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
 						.await
+						.map(|_| ())
 						.map_err(Into::into)
-				})
+				},
+			)
 		})
 		.procedure("renameFile", {
 			#[derive(Type, Deserialize)]
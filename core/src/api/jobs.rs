@@ -2,13 +2,13 @@ use crate::{
 	context::NodeContext,
 	invalidate_query,
 	location::{find_location, LocationError},
-	object::validation::old_validator_job::OldObjectValidatorJobInit,
-	old_job::{JobStatus, OldJob, OldJobReport},
+	old_job::{JobStatus, OldJobReport},
 };
 
 use sd_core_heavy_lifting::{
-	file_identifier::FileIdentifier, job_system::report, media_processor::job::MediaProcessor,
-	JobId, JobSystemError, Report,
+	duplicate_finder::DuplicateFinder, file_identifier::FileIdentifier,
+	file_validator::FileValidator, job_system::report, media_processor::job::MediaProcessor, JobId,
+	JobSystemError, Report,
 };
 
 use sd_prisma::prisma::{job, location, SortOrder};
@@ -369,20 +369,52 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				pub path: PathBuf,
 			}
 
-			R.with2(library())
-				.mutation(|(node, library), args: ObjectValidatorArgs| async move {
-					let Some(location) = find_location(&library, args.id).exec().await? else {
-						return Err(LocationError::IdNotFound(args.id).into());
+			R.with2(library()).mutation(
+				|(node, library), ObjectValidatorArgs { id, path }: ObjectValidatorArgs| async move {
+					let Some(location) = find_location(&library, id).exec().await? else {
+						return Err(LocationError::IdNotFound(id).into());
 					};
 
-					OldJob::new(OldObjectValidatorJobInit {
-						location,
-						sub_path: Some(args.path),
-					})
-					.spawn(&node, &library)
-					.await
-					.map_err(Into::into)
-				})
+					node.job_system
+						.dispatch(
+							FileValidator::new(location, Some(path))?,
+							id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("findDuplicates", {
+			#[derive(Type, Deserialize)]
+			pub struct FindDuplicatesArgs {
+				pub id: location::id::Type,
+				pub path: PathBuf,
+			}
+
+			R.with2(library()).mutation(
+				|(node, library), FindDuplicatesArgs { id, path }: FindDuplicatesArgs| async move {
+					let Some(location) = find_location(&library, id).exec().await? else {
+						return Err(LocationError::IdNotFound(id).into());
+					};
+
+					node.job_system
+						.dispatch(
+							DuplicateFinder::new(location, Some(path))?,
+							id,
+							NodeContext {
+								node: Arc::clone(&node),
+								library,
+							},
+						)
+						.await
+						.map_err(Into::into)
+				},
+			)
 		})
 		.procedure("identifyUniqueFiles", {
 			#[derive(Type, Deserialize)]
@@ -413,13 +445,21 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 		})
 		.procedure("newThumbnail", {
 			R.with2(library())
-				.subscription(|(node, _), _: ()| async move {
-					// TODO: Only return event for the library that was subscribed to
+				.subscription(|(node, library), _: ()| async move {
 					let mut event_bus_rx = node.event_bus.0.subscribe();
 					async_stream::stream! {
 						while let Ok(event) = event_bus_rx.recv().await {
 							match event {
-								CoreEvent::NewThumbnail { thumb_key } => yield thumb_key,
+								// Indexed thumbnails live in a directory named after their library,
+								// ephemeral ones are shared by every library
+								CoreEvent::NewThumbnail { thumb_key }
+									if thumb_key
+										.base_directory_str
+										.parse::<Uuid>()
+										.map_or(true, |library_id| library_id == library.id) =>
+								{
+									yield thumb_key
+								}
 								_ => {}
 							}
 						}
@@ -428,13 +468,12 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 		})
 		.procedure("newFilePathIdentified", {
 			R.with2(library())
-				.subscription(|(node, _), _: ()| async move {
-					// TODO: Only return event for the library that was subscribed to
+				.subscription(|(node, library), _: ()| async move {
 					let mut event_bus_rx = node.event_bus.0.subscribe();
 					async_stream::stream! {
 						while let Ok(event) = event_bus_rx.recv().await {
 							match event {
-								CoreEvent::NewIdentifiedObjects { file_path_ids } => yield file_path_ids,
+								CoreEvent::NewIdentifiedObjects { file_path_ids, library_id } if library_id == library.id => yield file_path_ids,
 								_ => {}
 							}
 						}
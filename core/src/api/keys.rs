@@ -0,0 +1,78 @@
+use crate::invalidate_query;
+
+use sd_crypto::Protected;
+
+use rspc::alpha::AlphaRouter;
+use serde::Deserialize;
+use specta::Type;
+use uuid::Uuid;
+
+use super::{utils::library, Ctx, R};
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.keyring.list().await) })
+		})
+		.procedure("add", {
+			#[derive(Type, Deserialize)]
+			pub struct AddKeyArgs {
+				pub name: String,
+				pub passphrase: String,
+			}
+
+			R.with2(library()).mutation(
+				|(_, library), AddKeyArgs { name, passphrase }: AddKeyArgs| async move {
+					let id = library
+						.keyring
+						.add(name, Protected::new(passphrase.into_bytes()))
+						.await?;
+
+					invalidate_query!(library, "keys.list");
+
+					Ok(id)
+				},
+			)
+		})
+		.procedure("unlock", {
+			#[derive(Type, Deserialize)]
+			pub struct UnlockKeyArgs {
+				pub id: Uuid,
+				pub passphrase: String,
+			}
+
+			R.with2(library()).mutation(
+				|(_, library), UnlockKeyArgs { id, passphrase }: UnlockKeyArgs| async move {
+					library
+						.keyring
+						.unlock(id, Protected::new(passphrase.into_bytes()))
+						.await?;
+
+					invalidate_query!(library, "keys.list");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("lock", {
+			R.with2(library())
+				.mutation(|(_, library), id: Uuid| async move {
+					library.keyring.lock(id).await;
+
+					invalidate_query!(library, "keys.list");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), id: Uuid| async move {
+					library.keyring.delete(id).await?;
+
+					invalidate_query!(library, "keys.list");
+
+					Ok(())
+				})
+		})
+}
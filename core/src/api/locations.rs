@@ -219,6 +219,8 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				pub sync_preview_media: Option<bool>,
 				pub hidden: Option<bool>,
 				pub date_created: Option<DateTime<FixedOffset>>,
+				pub rescan_schedule: Option<String>,
+				pub last_verified_at: Option<DateTime<FixedOffset>>,
 				pub instance_id: Option<i32>,
 				pub indexer_rules: Vec<indexer_rule::Data>,
 			}
@@ -238,6 +240,8 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						sync_preview_media: value.sync_preview_media,
 						hidden: value.hidden,
 						date_created: value.date_created,
+						rescan_schedule: value.rescan_schedule,
+						last_verified_at: value.last_verified_at,
 						instance_id: value.instance_id,
 						indexer_rules: value
 							.indexer_rules
@@ -343,8 +347,6 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 							.await?;
 
 						debug!(%count, "Disconnected file paths from objects;");
-
-						// library.orphan_remover.invoke().await;
 					}
 
 					let location = find_location(&library, location_id)
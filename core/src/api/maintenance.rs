@@ -0,0 +1,35 @@
+use crate::node::maintenance::{self, MaintenanceTrigger};
+
+use rspc::alpha::AlphaRouter;
+use tokio::spawn;
+use tracing::error;
+
+use super::{Ctx, R};
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("reports", {
+			R.query(|node, _: ()| async move { Ok(node.maintenance.reports().await) })
+		})
+		.procedure("isRunning", {
+			R.query(|node, _: ()| async move { Ok(node.maintenance.is_running()) })
+		})
+		.procedure("run", {
+			R.mutation(|node, _: ()| async move {
+				if node.maintenance.is_running() {
+					return Err(rspc::Error::from(
+						maintenance::MaintenanceError::AlreadyRunning,
+					));
+				}
+
+				// Vacuuming big libraries can take a while, the report is picked up through invalidation
+				spawn(async move {
+					if let Err(e) = maintenance::run(&node, MaintenanceTrigger::Manual).await {
+						error!(?e, "Failed to run maintenance;");
+					}
+				});
+
+				Ok(())
+			})
+		})
+}
@@ -21,21 +21,26 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 use uuid::Uuid;
 
+mod albums;
 mod auth;
 mod backups;
 mod cloud;
 mod ephemeral_files;
 mod files;
 mod jobs;
+mod keys;
 mod labels;
 mod libraries;
 pub mod locations;
+mod maintenance;
 mod models;
 mod nodes;
 pub mod notifications;
+mod object_collections;
 mod p2p;
 mod preferences;
 pub(crate) mod search;
+mod spaces;
 mod sync;
 mod tags;
 pub mod utils;
@@ -59,6 +64,7 @@ pub enum CoreEvent {
 	},
 	NewIdentifiedObjects {
 		file_path_ids: Vec<file_path::id::Type>,
+		library_id: LibraryId,
 	},
 	UpdatedKindStatistic(KindStatistic, LibraryId),
 	JobProgress(JobProgressEvent),
@@ -198,10 +204,13 @@ pub(crate) fn mount() -> Arc<Router> {
 		.merge("volumes.", volumes::mount())
 		.merge("tags.", tags::mount())
 		.merge("labels.", labels::mount())
+		.merge("albums.", albums::mount())
+		.merge("spaces.", spaces::mount())
 		.merge("locations.", locations::mount())
 		.merge("ephemeralFiles.", ephemeral_files::mount())
 		.merge("files.", files::mount())
 		.merge("jobs.", jobs::mount())
+		.merge("keys.", keys::mount())
 		.merge("p2p.", p2p::mount())
 		.merge("models.", models::mount())
 		.merge("nodes.", nodes::mount())
@@ -209,6 +218,7 @@ pub(crate) fn mount() -> Arc<Router> {
 		.merge("preferences.", preferences::mount())
 		.merge("notifications.", notifications::mount())
 		.merge("backups.", backups::mount())
+		.merge("maintenance.", maintenance::mount())
 		.merge("invalidation.", utils::mount_invalidate())
 		.sd_patch_types_dangerously(|type_map| {
 			let def =
@@ -1,8 +1,10 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, thread::available_parallelism};
 
 use crate::{
 	invalidate_query,
-	node::config::{P2PDiscoveryState, Port},
+	node::config::{
+		BackupPreferences, MaintenancePreferences, P2PDiscoveryState, Port, TaskSystemPreferences,
+	},
 };
 
 use sd_prisma::prisma::{instance, location};
@@ -27,6 +29,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				pub p2p_relay_disabled: Option<bool>,
 				pub p2p_discovery: Option<P2PDiscoveryState>,
 				pub p2p_remote_access: Option<bool>,
+				pub p2p_remote_access_allowlist: Option<HashSet<String>>,
 				pub p2p_manual_peers: Option<HashSet<String>>,
 				#[cfg(feature = "ai")]
 				pub image_labeler_version: Option<String>,
@@ -68,6 +71,9 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						if let Some(remote_access) = args.p2p_remote_access {
 							config.p2p.enable_remote_access = remote_access;
 						};
+						if let Some(remote_access_allowlist) = args.p2p_remote_access_allowlist {
+							config.p2p.remote_access_allowlist = remote_access_allowlist;
+						};
 						if let Some(manual_peers) = args.p2p_manual_peers {
 							config.p2p.manual_peers = manual_peers;
 						};
@@ -190,20 +196,110 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			}
 			R.mutation(
 				|node, UpdateThumbnailerPreferences { .. }: UpdateThumbnailerPreferences| async move {
-					node.config
-						.update_preferences(|_| {
-							// TODO(fogodev): introduce configurable workers count to task system
-						})
-						.await
-						.map_err(|e| {
-							error!(?e, "Failed to update thumbnailer preferences;");
-							rspc::Error::with_cause(
-								ErrorCode::InternalServerError,
-								"Failed to update thumbnailer preferences".to_string(),
-								e,
-							)
-						})
+					node.config.update_preferences(|_| {}).await.map_err(|e| {
+						error!(?e, "Failed to update thumbnailer preferences;");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update thumbnailer preferences".to_string(),
+							e,
+						)
+					})
 				},
 			)
 		})
+		.procedure("updateTaskSystemPreferences", {
+			R.mutation(|node, preferences: TaskSystemPreferences| async move {
+				let max_workers_count = available_parallelism().map_or(1, |count| count.get());
+
+				if preferences.workers_count.is_some_and(|workers_count| {
+					workers_count == 0 || workers_count as usize > max_workers_count
+				}) {
+					return Err(rspc::Error::new(
+						ErrorCode::BadRequest,
+						format!("workers count must be between 1 and {max_workers_count}"),
+					));
+				}
+
+				if preferences
+					.job_concurrency_limits
+					.values()
+					.any(|&max_tasks| max_tasks == 0)
+				{
+					return Err(rspc::Error::new(
+						ErrorCode::BadRequest,
+						"job concurrency limits must be greater than 0".to_string(),
+					));
+				}
+
+				// Applied right away, workers being removed finish their running tasks
+				// and the pending ones are taken by the remaining workers
+				node.task_system
+					.resize_workers(preferences.task_system_config().workers_count);
+				node.job_system
+					.concurrency_limits()
+					.replace(preferences.job_concurrency_limits());
+
+				node.config
+					.update_preferences(|node_preferences| {
+						node_preferences.task_system = preferences;
+					})
+					.await
+					.map_err(|e| {
+						error!(?e, "Failed to update task system preferences;");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update task system preferences".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "nodeState");
+
+				Ok(())
+			})
+		})
+		.procedure("updateBackupPreferences", {
+			R.mutation(|node, preferences: BackupPreferences| async move {
+				// Picked up by the backup scheduler on its next check
+				node.config
+					.update_preferences(|node_preferences| {
+						node_preferences.backups = preferences;
+					})
+					.await
+					.map_err(|e| {
+						error!(?e, "Failed to update backup preferences;");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update backup preferences".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "nodeState");
+
+				Ok(())
+			})
+		})
+		.procedure("updateMaintenancePreferences", {
+			R.mutation(|node, preferences: MaintenancePreferences| async move {
+				// Picked up by the maintenance scheduler on its next check
+				node.config
+					.update_preferences(|node_preferences| {
+						node_preferences.maintenance = preferences;
+					})
+					.await
+					.map_err(|e| {
+						error!(?e, "Failed to update maintenance preferences;");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update maintenance preferences".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "nodeState");
+
+				Ok(())
+			})
+		})
 }
@@ -0,0 +1,104 @@
+//! Helpers shared by the routers of ordered object collections, albums and spaces
+
+use sd_prisma::prisma::{object, PrismaClient};
+
+use std::collections::{HashMap, HashSet};
+
+use rspc::ErrorCode;
+
+object::select!(object_ids { id pub_id });
+
+/// Fetches the objects to append to a collection, in the order they were given in and paired with
+/// their new position, skipping the ones already in it
+pub(super) async fn objects_to_add(
+	db: &PrismaClient,
+	object_ids: &[object::id::Type],
+	members: impl IntoIterator<Item = (object::id::Type, Option<i32>)>,
+) -> Result<Vec<(object_ids::Data, i32)>, rspc::Error> {
+	let mut already_added = HashSet::new();
+	let mut last_position = None;
+
+	for (object_id, position) in members {
+		already_added.insert(object_id);
+		last_position = last_position.max(position);
+	}
+
+	let mut objects = db
+		.object()
+		.find_many(vec![object::id::in_vec(
+			object_ids
+				.iter()
+				.copied()
+				.filter(|id| !already_added.contains(id))
+				.collect(),
+		)])
+		.select(object_ids::select())
+		.exec()
+		.await?;
+
+	objects.sort_by_key(|object| object_ids.iter().position(|id| *id == object.id));
+
+	Ok(objects
+		.into_iter()
+		.zip(last_position.map_or(0, |position| position + 1)..)
+		.collect())
+}
+
+/// Orders the members of a collection as given by `object_ids`, returning the members whose
+/// position changed along with their new position.
+///
+/// Members missing from `object_ids` keep their relative order after the given ones.
+pub(super) fn reorder<T>(
+	mut members: Vec<T>,
+	object_ids: Vec<object::id::Type>,
+	member_key: impl Fn(&T) -> (object::id::Type, Option<i32>),
+) -> Vec<(T, i32)> {
+	let new_order = object_ids
+		.into_iter()
+		.enumerate()
+		.map(|(index, id)| (id, index))
+		.collect::<HashMap<_, _>>();
+
+	members.sort_by_key(|member| {
+		new_order
+			.get(&member_key(member).0)
+			.copied()
+			.unwrap_or(usize::MAX)
+	});
+
+	members
+		.into_iter()
+		.zip(0..)
+		.filter(|(member, position)| member_key(member).1 != Some(*position))
+		.collect()
+}
+
+/// Fetches the `pub_id` of an object to use as a collection cover
+pub(super) async fn cover_object_pub_id(
+	db: &PrismaClient,
+	object_id: object::id::Type,
+) -> Result<object::pub_id::Type, rspc::Error> {
+	db.object()
+		.find_unique(object::id::equals(object_id))
+		.select(object::select!({ pub_id }))
+		.exec()
+		.await?
+		.map(|object| object.pub_id)
+		.ok_or_else(|| rspc::Error::new(ErrorCode::NotFound, "Object not found".to_string()))
+}
+
+/// Fetches the `pub_id`s of the objects to remove from a collection, to sync their removal
+pub(super) async fn objects_pub_ids(
+	db: &PrismaClient,
+	object_ids: Vec<object::id::Type>,
+) -> Result<Vec<object::pub_id::Type>, rspc::Error> {
+	Ok(db
+		.object()
+		.find_many(vec![object::id::in_vec(object_ids)])
+		.select(object::select!({ pub_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|object| object.pub_id)
+		.collect())
+}
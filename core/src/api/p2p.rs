@@ -9,7 +9,7 @@ use std::{path::PathBuf, sync::PoisonError};
 use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
-use super::{Ctx, R};
+use super::{utils::library, Ctx, R};
 
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
@@ -145,6 +145,26 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			R.mutation(|node, id: Uuid| async move {
 				node.p2p.cancel_spacedrop(id).await;
 
+				Ok(())
+			})
+		})
+		.procedure("pair", {
+			R.with2(library())
+				.mutation(|(node, library), identity: RemoteIdentity| async move {
+					operations::pair(node, identity, library)
+						.await
+						.map_err(|pairing_err| {
+							rspc::Error::new(
+								ErrorCode::InternalServerError,
+								pairing_err.to_string(),
+							)
+						})
+				})
+		})
+		.procedure("acceptPairing", {
+			R.mutation(|node, (id, accepted): (Uuid, bool)| async move {
+				node.p2p.decide_pairing(id, accepted);
+
 				Ok(())
 			})
 		})
@@ -1,9 +1,32 @@
-use sd_prisma::prisma::{self, exif_data};
+use sd_prisma::prisma::{self, exif_data, PrismaClient};
 
+use std::collections::HashMap;
+
+use prisma_client_rust::{and, operator, or, raw, PrismaValue};
+use rspc::ErrorCode;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
-use super::utils::*;
+use super::{merge_filters, utils::*, SearchFilterArgs};
+
+/// Mean radius of the Earth, used for distances between coordinates
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// Each side of a map tile is split in this many grid cells when clustering
+const CLUSTER_CELLS_PER_TILE: f64 = 4.0;
+
+const MAX_CLUSTER_ZOOM: u8 = 22;
+
+/// A radius search is split in this many strips of latitude, each one with its own range of
+/// longitudes, to leave only a thin border around the circle to check distances for
+const RADIUS_STRIPS: u32 = 32;
+
+/// Points in the border of a radius search that are outside of the circle are excluded by id, in
+/// lists of at most this many ids to keep each of them under SQLite's bound variables limit
+const MAX_EXCLUDED_POINTS: usize = 10_000;
+
+/// Coordinates are clustered in chunks of this many, when they can't be aggregated by the database
+const CLUSTER_CHUNK_SIZE: i64 = 1000;
 
 #[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase", tag = "field", content = "value")]
@@ -27,3 +50,623 @@ impl ExifDataOrder {
 		}
 	}
 }
+
+/// Area of the map, in degrees. `west` is greater than `east` when the area crosses the
+/// antimeridian.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, PartialEq)]
+pub struct GeoBoundingBox {
+	pub north: f64,
+	pub south: f64,
+	pub east: f64,
+	pub west: f64,
+}
+
+impl GeoBoundingBox {
+	fn validate(&self) -> Result<(), rspc::Error> {
+		if !(is_latitude(self.north) && is_latitude(self.south) && self.south <= self.north)
+			|| !(is_longitude(self.east) && is_longitude(self.west))
+		{
+			return Err(rspc::Error::new(
+				ErrorCode::BadRequest,
+				"Invalid bounding box".to_string(),
+			));
+		}
+
+		Ok(())
+	}
+
+	fn into_params(self) -> Vec<exif_data::WhereParam> {
+		use exif_data::*;
+
+		vec![
+			latitude::gte(self.south),
+			latitude::lte(self.north),
+			if self.west <= self.east {
+				and![longitude::gte(self.west), longitude::lte(self.east)]
+			} else {
+				or![longitude::gte(self.west), longitude::lte(self.east)]
+			},
+		]
+	}
+}
+
+/// Circle around a point, with its radius in meters
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoRadius {
+	pub latitude: f64,
+	pub longitude: f64,
+	pub radius_meters: f64,
+}
+
+impl GeoRadius {
+	fn validate(&self) -> Result<(), rspc::Error> {
+		if !is_latitude(self.latitude)
+			|| !is_longitude(self.longitude)
+			|| !(self.radius_meters.is_finite() && self.radius_meters > 0.0)
+		{
+			return Err(rspc::Error::new(
+				ErrorCode::BadRequest,
+				"Invalid search radius".to_string(),
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Smallest box containing the whole circle
+	fn bounding_box(&self) -> GeoBoundingBox {
+		let angular_radius = self.radius_meters / EARTH_RADIUS_METERS;
+
+		let north = (self.latitude + angular_radius.to_degrees()).min(90.0);
+		let south = (self.latitude - angular_radius.to_degrees()).max(-90.0);
+
+		// The circle covers every longitude if it reaches a pole
+		let longitude_ratio = angular_radius.sin() / self.latitude.to_radians().cos();
+		if north >= 90.0 || south <= -90.0 || longitude_ratio >= 1.0 {
+			return GeoBoundingBox {
+				north,
+				south,
+				east: 180.0,
+				west: -180.0,
+			};
+		}
+
+		let longitude_delta = longitude_ratio.asin().to_degrees();
+
+		GeoBoundingBox {
+			north,
+			south,
+			east: normalize_longitude(self.longitude + longitude_delta),
+			west: normalize_longitude(self.longitude - longitude_delta),
+		}
+	}
+
+	fn contains(&self, latitude: f64, longitude: f64) -> bool {
+		haversine_distance((self.latitude, self.longitude), (latitude, longitude))
+			<= self.radius_meters
+	}
+
+	/// Half of the range of longitudes inside the circle at the given latitude, in degrees
+	fn longitude_delta_at(&self, latitude: f64) -> f64 {
+		let angular_radius = self.radius_meters / EARTH_RADIUS_METERS;
+		let haversine = |angle: f64| (angle / 2.0).sin().powi(2);
+
+		let ratio = (haversine(angular_radius)
+			- haversine((latitude - self.latitude).to_radians()))
+			/ (self.latitude.to_radians().cos() * latitude.to_radians().cos());
+
+		if ratio.is_nan() || ratio >= 1.0 {
+			180.0
+		} else if ratio <= 0.0 {
+			0.0
+		} else {
+			(2.0 * ratio.sqrt().asin()).to_degrees()
+		}
+	}
+
+	/// Splits the circle in strips of latitude. The outer box of each strip contains the part of the
+	/// circle in that strip, and its inner box, if any, is fully inside of the circle.
+	fn strips(&self) -> Vec<(GeoBoundingBox, Option<GeoBoundingBox>)> {
+		let angular_radius = self.radius_meters / EARTH_RADIUS_METERS;
+
+		let GeoBoundingBox { north, south, .. } = self.bounding_box();
+		let strip_height = (north - south) / f64::from(RADIUS_STRIPS);
+
+		// The range of longitudes is the widest at the latitude where the circle's edge is
+		// tangent to a meridian
+		let widest_latitude = (self.latitude.to_radians().sin() / angular_radius.cos())
+			.asin()
+			.to_degrees();
+
+		let boxed = |strip_south: f64, strip_north: f64, delta: f64| {
+			if delta >= 180.0 {
+				GeoBoundingBox {
+					north: strip_north,
+					south: strip_south,
+					east: 180.0,
+					west: -180.0,
+				}
+			} else {
+				GeoBoundingBox {
+					north: strip_north,
+					south: strip_south,
+					east: normalize_longitude(self.longitude + delta),
+					west: normalize_longitude(self.longitude - delta),
+				}
+			}
+		};
+
+		(0..RADIUS_STRIPS)
+			.map(|strip| {
+				let strip_south = south + strip_height * f64::from(strip);
+				let strip_north = if strip == RADIUS_STRIPS - 1 {
+					north
+				} else {
+					strip_south + strip_height
+				};
+
+				// The range of longitudes grows up to the widest latitude and then shrinks, so the
+				// narrowest one is at an edge of the strip
+				let south_delta = self.longitude_delta_at(strip_south);
+				let north_delta = self.longitude_delta_at(strip_north);
+
+				let mut widest_delta = south_delta.max(north_delta);
+				if (strip_south..=strip_north).contains(&widest_latitude) {
+					widest_delta = widest_delta.max(self.longitude_delta_at(widest_latitude));
+				}
+				let narrowest_delta = south_delta.min(north_delta);
+
+				(
+					boxed(strip_south, strip_north, widest_delta),
+					(narrowest_delta > 0.0)
+						.then(|| boxed(strip_south, strip_north, narrowest_delta)),
+				)
+			})
+			.collect()
+	}
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum GeoFilter {
+	BoundingBox(GeoBoundingBox),
+	Radius(GeoRadius),
+}
+
+impl GeoFilter {
+	pub async fn into_params(
+		self,
+		db: &PrismaClient,
+	) -> Result<Vec<exif_data::WhereParam>, rspc::Error> {
+		match self {
+			Self::BoundingBox(bounding_box) => {
+				bounding_box.validate()?;
+
+				Ok(bounding_box.into_params())
+			}
+			Self::Radius(radius) => {
+				radius.validate()?;
+
+				let strips = radius.strips();
+
+				// SQLite has no trigonometric functions by default, so the database filters by the
+				// strips around the circle, and we only check distances for the coordinates in the
+				// thin border between the outer and inner boxes of the strips
+				let outside_ids = db
+					.exif_data()
+					.find_many(vec![operator::or(
+						strips
+							.iter()
+							.map(|(outer, inner)| {
+								let mut params = outer.into_params();
+								if let Some(inner) = inner {
+									params.push(operator::not(inner.into_params()));
+								}
+								operator::and(params)
+							})
+							.collect(),
+					)])
+					.select(exif_data::select!({ id latitude longitude }))
+					.exec()
+					.await?
+					.into_iter()
+					.filter_map(|data| match (data.latitude, data.longitude) {
+						(Some(latitude), Some(longitude))
+							if !radius.contains(latitude, longitude) =>
+						{
+							Some(data.id)
+						}
+						_ => None,
+					})
+					.collect::<Vec<_>>();
+
+				let mut params = vec![operator::or(
+					strips
+						.iter()
+						.map(|(outer, _)| operator::and(outer.into_params()))
+						.collect(),
+				)];
+
+				params.extend(
+					outside_ids
+						.chunks(MAX_EXCLUDED_POINTS)
+						.map(|ids| exif_data::id::not_in_vec(ids.to_vec())),
+				);
+
+				Ok(params)
+			}
+		}
+	}
+}
+
+/// Photos grouped in a cell of the clustering grid
+#[derive(Serialize, Type, Debug, Clone, PartialEq)]
+pub struct GeoCluster {
+	/// Mean latitude of the photos in this cell
+	pub latitude: f64,
+	/// Mean longitude of the photos in this cell
+	pub longitude: f64,
+	pub count: u32,
+	/// Area of the grid cell, to search for the photos in it
+	pub bounds: GeoBoundingBox,
+}
+
+/// Grid over latitude and longitude, where each map tile at the given zoom level is split in
+/// [`CLUSTER_CELLS_PER_TILE`] cells per side
+struct GeoCells {
+	cell_size: f64,
+	/// Count and sums of the latitudes and longitudes of the coordinates in each cell
+	cells: HashMap<(i64, i64), (u32, f64, f64)>,
+}
+
+impl GeoCells {
+	fn new(zoom: u8) -> Self {
+		Self {
+			cell_size: 360.0
+				/ 2f64.powi(i32::from(zoom.min(MAX_CLUSTER_ZOOM)))
+				/ CLUSTER_CELLS_PER_TILE,
+			cells: HashMap::new(),
+		}
+	}
+
+	fn add(&mut self, latitude: f64, longitude: f64) {
+		if !is_latitude(latitude) || !is_longitude(longitude) {
+			return;
+		}
+
+		self.add_cell(
+			(
+				((latitude + 90.0) / self.cell_size).floor() as i64,
+				((longitude + 180.0) / self.cell_size).floor() as i64,
+			),
+			1,
+			latitude,
+			longitude,
+		);
+	}
+
+	fn add_cell(&mut self, cell: (i64, i64), count: u32, latitude_sum: f64, longitude_sum: f64) {
+		let (cell_count, cell_latitude_sum, cell_longitude_sum) =
+			self.cells.entry(cell).or_default();
+		*cell_count += count;
+		*cell_latitude_sum += latitude_sum;
+		*cell_longitude_sum += longitude_sum;
+	}
+
+	/// Clusters are sorted by count, biggest first
+	fn into_clusters(self) -> Vec<GeoCluster> {
+		let Self { cell_size, cells } = self;
+
+		let mut clusters = cells
+			.into_iter()
+			.map(
+				|((latitude_cell, longitude_cell), (count, latitude_sum, longitude_sum))| {
+					let south = latitude_cell as f64 * cell_size - 90.0;
+					let west = longitude_cell as f64 * cell_size - 180.0;
+
+					GeoCluster {
+						latitude: latitude_sum / f64::from(count),
+						longitude: longitude_sum / f64::from(count),
+						count,
+						bounds: GeoBoundingBox {
+							north: (south + cell_size).min(90.0),
+							south,
+							east: (west + cell_size).min(180.0),
+							west,
+						},
+					}
+				},
+			)
+			.collect::<Vec<_>>();
+
+		clusters.sort_by(|a, b| {
+			b.count
+				.cmp(&a.count)
+				.then(a.latitude.total_cmp(&b.latitude))
+				.then(a.longitude.total_cmp(&b.longitude))
+		});
+
+		clusters
+	}
+}
+
+#[derive(Deserialize)]
+struct RawGeoCell {
+	latitude_cell: i64,
+	longitude_cell: i64,
+	count: i64,
+	latitude_sum: f64,
+	longitude_sum: f64,
+}
+
+/// Clusters the coordinates of every photo in the library inside `bounds`, grouping them by grid
+/// cell in the database
+pub async fn cluster_library_coordinates(
+	db: &PrismaClient,
+	zoom: u8,
+	bounds: Option<GeoBoundingBox>,
+) -> Result<Vec<GeoCluster>, rspc::Error> {
+	let bounds = bounds.unwrap_or(GeoBoundingBox {
+		north: 90.0,
+		south: -90.0,
+		east: 180.0,
+		west: -180.0,
+	});
+	bounds.validate()?;
+
+	let mut cells = GeoCells::new(zoom);
+
+	// PCR doesn't support grouping, so we aggregate the cells with a raw query.
+	// Coordinates are never negative after being offset, so casting to integer floors them
+	for RawGeoCell {
+		latitude_cell,
+		longitude_cell,
+		count,
+		latitude_sum,
+		longitude_sum,
+	} in db
+		._query_raw::<RawGeoCell>(raw!(
+			&format!(
+				"SELECT
+					CAST((latitude + 90.0) / {{}} AS INTEGER) AS latitude_cell,
+					CAST((longitude + 180.0) / {{}} AS INTEGER) AS longitude_cell,
+					COUNT(*) AS count,
+					SUM(latitude) AS latitude_sum,
+					SUM(longitude) AS longitude_sum
+				FROM exif_data
+				WHERE
+					latitude >= {{}} AND latitude <= {{}}
+					AND (longitude >= {{}} {} longitude <= {{}})
+				GROUP BY latitude_cell, longitude_cell",
+				// Areas crossing the antimeridian are made of two ranges of longitudes
+				if bounds.west <= bounds.east {
+					"AND"
+				} else {
+					"OR"
+				}
+			),
+			PrismaValue::Float(cells.cell_size),
+			PrismaValue::Float(cells.cell_size),
+			PrismaValue::Float(bounds.south),
+			PrismaValue::Float(bounds.north),
+			PrismaValue::Float(bounds.west),
+			PrismaValue::Float(bounds.east)
+		))
+		.exec()
+		.await?
+	{
+		cells.add_cell(
+			(latitude_cell, longitude_cell),
+			u32::try_from(count).unwrap_or(u32::MAX),
+			latitude_sum,
+			longitude_sum,
+		);
+	}
+
+	Ok(cells.into_clusters())
+}
+
+/// Clusters the coordinates of the photos inside `bounds` matching the search `filters`. Filters
+/// can't be used in a raw query, so the coordinates are fetched in chunks and only the grid cells
+/// are kept in memory.
+pub async fn cluster_filtered_coordinates(
+	db: &PrismaClient,
+	zoom: u8,
+	bounds: Option<GeoBoundingBox>,
+	filters: Vec<SearchFilterArgs>,
+) -> Result<Vec<GeoCluster>, rspc::Error> {
+	let mut filter_params = vec![
+		exif_data::latitude::not(None),
+		exif_data::longitude::not(None),
+	];
+
+	if let Some(bounds) = bounds {
+		filter_params.extend(GeoFilter::BoundingBox(bounds).into_params(db).await?);
+	}
+
+	// Filters may query the database themselves, like the border of a radius search, so they're
+	// only built once for every chunk
+	let (fp, mut obj) = merge_filters(filters, db).await?;
+
+	if !fp.is_empty() {
+		obj.push(prisma::object::file_paths::some(fp));
+	}
+
+	if !obj.is_empty() {
+		filter_params.push(exif_data::object::is(obj));
+	}
+
+	let mut cells = GeoCells::new(zoom);
+	let mut cursor = None;
+
+	loop {
+		let mut params = filter_params.clone();
+
+		if let Some(cursor) = cursor {
+			params.push(exif_data::id::gt(cursor));
+		}
+
+		let coordinates = db
+			.exif_data()
+			.find_many(params)
+			.order_by(exif_data::id::order(prisma::SortOrder::Asc))
+			.take(CLUSTER_CHUNK_SIZE)
+			.select(exif_data::select!({ id latitude longitude }))
+			.exec()
+			.await?;
+
+		let Some(last) = coordinates.last() else {
+			break;
+		};
+		cursor = Some(last.id);
+
+		for data in coordinates {
+			if let (Some(latitude), Some(longitude)) = (data.latitude, data.longitude) {
+				cells.add(latitude, longitude);
+			}
+		}
+	}
+
+	Ok(cells.into_clusters())
+}
+
+fn is_latitude(value: f64) -> bool {
+	(-90.0..=90.0).contains(&value)
+}
+
+fn is_longitude(value: f64) -> bool {
+	(-180.0..=180.0).contains(&value)
+}
+
+fn normalize_longitude(longitude: f64) -> f64 {
+	(longitude + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Great-circle distance in meters between two `(latitude, longitude)` pairs
+fn haversine_distance((lat_a, long_a): (f64, f64), (lat_b, long_b): (f64, f64)) -> f64 {
+	let (lat_a, lat_b) = (lat_a.to_radians(), lat_b.to_radians());
+	let lat_delta = lat_b - lat_a;
+	let long_delta = (long_b - long_a).to_radians();
+
+	let h = (lat_delta / 2.0).sin().powi(2)
+		+ lat_a.cos() * lat_b.cos() * (long_delta / 2.0).sin().powi(2);
+
+	2.0 * EARTH_RADIUS_METERS * h.sqrt().min(1.0).asin()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn radius_contains_close_points() {
+		// Lisbon and Porto are about 274km apart
+		let radius = GeoRadius {
+			latitude: 38.7223,
+			longitude: -9.1393,
+			radius_meters: 300_000.0,
+		};
+
+		assert!(radius.contains(41.1579, -8.6291));
+		assert!(!GeoRadius {
+			radius_meters: 250_000.0,
+			..radius
+		}
+		.contains(41.1579, -8.6291));
+	}
+
+	#[test]
+	fn radius_bounding_box_crosses_antimeridian() {
+		let bounding_box = GeoRadius {
+			latitude: 0.0,
+			longitude: 179.9,
+			radius_meters: 50_000.0,
+		}
+		.bounding_box();
+
+		assert!(bounding_box.west > bounding_box.east);
+		assert!(bounding_box.west < 179.9);
+		assert!(bounding_box.east > -180.0 && bounding_box.east < -179.0);
+	}
+
+	fn cluster_coordinates(coordinates: &[(f64, f64)], zoom: u8) -> Vec<GeoCluster> {
+		let mut cells = GeoCells::new(zoom);
+
+		for &(latitude, longitude) in coordinates {
+			cells.add(latitude, longitude);
+		}
+
+		cells.into_clusters()
+	}
+
+	#[test]
+	fn radius_strips_cover_circle() {
+		for radius in [
+			GeoRadius {
+				latitude: 38.7223,
+				longitude: -9.1393,
+				radius_meters: 300_000.0,
+			},
+			GeoRadius {
+				latitude: -60.0,
+				longitude: 179.5,
+				radius_meters: 100_000.0,
+			},
+		] {
+			let strips = radius.strips();
+			let contains = |bounding_box: &GeoBoundingBox, latitude: f64, longitude: f64| {
+				(bounding_box.south..=bounding_box.north).contains(&latitude)
+					&& if bounding_box.west <= bounding_box.east {
+						(bounding_box.west..=bounding_box.east).contains(&longitude)
+					} else {
+						longitude >= bounding_box.west || longitude <= bounding_box.east
+					}
+			};
+
+			let outer = radius.bounding_box();
+			for step_latitude in 0..=100 {
+				for step_longitude in 0..=100 {
+					let latitude = outer.south
+						+ (outer.north - outer.south) * f64::from(step_latitude) / 100.0;
+					let longitude = normalize_longitude(
+						radius.longitude - 5.0 + 10.0 * f64::from(step_longitude) / 100.0,
+					);
+
+					let inside = radius.contains(latitude, longitude);
+
+					// Every point of the circle is in an outer box, and every point of an inner box
+					// is in the circle
+					if inside {
+						assert!(strips
+							.iter()
+							.any(|(outer, _)| contains(outer, latitude, longitude)));
+					}
+					if strips.iter().any(|(_, inner)| {
+						inner
+							.as_ref()
+							.is_some_and(|inner| contains(inner, latitude, longitude))
+					}) {
+						assert!(inside);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn clusters_group_by_cell() {
+		let clusters = cluster_coordinates(&[(10.0, 10.0), (10.1, 10.1), (-40.0, 100.0)], 2);
+
+		assert_eq!(clusters.len(), 2);
+		assert_eq!(clusters[0].count, 2);
+		assert!((clusters[0].latitude - 10.05).abs() < 1e-9);
+		assert_eq!(clusters[1].count, 1);
+
+		// Every point falls in a cell of its own at the deepest zoom
+		assert_eq!(
+			cluster_coordinates(&[(10.0, 10.0), (10.1, 10.1)], MAX_CLUSTER_ZOOM).len(),
+			2
+		);
+	}
+}
@@ -25,7 +25,7 @@ pub mod object;
 pub mod saved;
 mod utils;
 
-pub use self::{file_path::*, object::*, utils::*};
+pub use self::{exif_data::*, file_path::*, object::*, utils::*};
 
 use super::{Ctx, R};
 
@@ -53,7 +53,7 @@ impl SearchFilterArgs {
 	) -> Result<(), rspc::Error> {
 		match self {
 			Self::FilePath(v) => file_path.extend(v.into_params(db).await?),
-			Self::Object(v) => object.extend(v.into_params()),
+			Self::Object(v) => object.extend(v.into_params(db).await?),
 		};
 		Ok(())
 	}
@@ -390,6 +390,35 @@ pub fn mount() -> AlphaRouter<Ctx> {
 						.await? as u32)
 				})
 		})
+		.procedure("geoClusters", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			#[specta(inline)]
+			struct Args {
+				zoom: u8,
+				#[specta(optional)]
+				bounds: Option<GeoBoundingBox>,
+				#[serde(default)]
+				filters: Vec<SearchFilterArgs>,
+			}
+
+			R.with2(library()).query(
+				|(_, library),
+				 Args {
+				     zoom,
+				     bounds,
+				     filters,
+				 }| async move {
+					let Library { db, .. } = library.as_ref();
+
+					if filters.is_empty() {
+						cluster_library_coordinates(db, zoom, bounds).await
+					} else {
+						cluster_filtered_coordinates(db, zoom, bounds, filters).await
+					}
+				},
+			)
+		})
 		.merge("saved.", saved::mount())
 }
 
@@ -1,6 +1,10 @@
 // use crate::library::Category;
 
-use sd_prisma::prisma::{self, label_on_object, object, tag_on_object};
+use crate::object::tag::with_descendants;
+
+use sd_prisma::prisma::{
+	self, label_on_object, object, object_in_album, tag_on_object, PrismaClient,
+};
 
 use chrono::{DateTime, FixedOffset};
 use prisma_client_rust::{not, or, OrderByQuery, PaginatedQuery, WhereQuery};
@@ -106,25 +110,60 @@ impl ObjectHiddenFilter {
 	}
 }
 
+/// Older saved searches hold just the tags, without the `includeDescendants` flag
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+#[serde(untagged)]
+pub enum TagsFilter {
+	Nested {
+		tags: InOrNotIn<i32>,
+		#[serde(rename = "includeDescendants", default)]
+		include_descendants: bool,
+	},
+	Flat(InOrNotIn<i32>),
+}
+
+impl TagsFilter {
+	async fn into_in_or_not_in(self, db: &PrismaClient) -> Result<InOrNotIn<i32>, rspc::Error> {
+		Ok(match self {
+			Self::Nested {
+				tags: InOrNotIn::In(v),
+				include_descendants: true,
+			} => InOrNotIn::In(with_descendants(db, v).await?),
+			Self::Nested {
+				tags: InOrNotIn::NotIn(v),
+				include_descendants: true,
+			} => InOrNotIn::NotIn(with_descendants(db, v).await?),
+			Self::Nested { tags, .. } | Self::Flat(tags) => tags,
+		})
+	}
+}
+
 #[derive(Serialize, Deserialize, Type, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum ObjectFilterArgs {
 	Favorite(bool),
 	Hidden(ObjectHiddenFilter),
 	Kind(InOrNotIn<i32>),
-	Tags(InOrNotIn<i32>),
+	Tags(TagsFilter),
 	Labels(InOrNotIn<i32>),
+	Albums(InOrNotIn<i32>),
 	DateAccessed(Range<chrono::DateTime<FixedOffset>>),
+	MediaLocation(GeoFilter),
 }
 
 impl ObjectFilterArgs {
-	pub fn into_params(self) -> Vec<object::WhereParam> {
+	pub async fn into_params(
+		self,
+		db: &PrismaClient,
+	) -> Result<Vec<object::WhereParam>, rspc::Error> {
 		use object::*;
 
-		match self {
+		Ok(match self {
 			Self::Favorite(v) => vec![favorite::equals(Some(v))],
 			Self::Hidden(v) => v.to_param().map(|v| vec![v]).unwrap_or_default(),
 			Self::Tags(v) => v
+				.into_in_or_not_in(db)
+				.await?
 				.into_param(
 					|v| tags::some(vec![tag_on_object::tag_id::in_vec(v)]),
 					|v| tags::none(vec![tag_on_object::tag_id::in_vec(v)]),
@@ -138,6 +177,13 @@ impl ObjectFilterArgs {
 				)
 				.map(|v| vec![v])
 				.unwrap_or_default(),
+			Self::Albums(v) => v
+				.into_param(
+					|v| albums::some(vec![object_in_album::album_id::in_vec(v)]),
+					|v| albums::none(vec![object_in_album::album_id::in_vec(v)]),
+				)
+				.map(|v| vec![v])
+				.unwrap_or_default(),
 			Self::Kind(v) => v
 				.into_param(kind::in_vec, kind::not_in_vec)
 				.map(|v| vec![v])
@@ -151,7 +197,8 @@ impl ObjectFilterArgs {
 					},
 				]
 			}
-		}
+			Self::MediaLocation(v) => vec![exif_data::is(v.into_params(db).await?)],
+		})
 	}
 }
 
@@ -0,0 +1,471 @@
+use crate::{invalidate_query, library::Library};
+
+use sd_prisma::{
+	prisma::{object, object_in_space, space, PrismaClient, SortOrder},
+	prisma_sync,
+};
+use sd_sync::{option_sync_db_entry, sync_db_entry, sync_entry, OperationFactory};
+use sd_utils::{msgpack, uuid_to_bytes};
+
+use chrono::Utc;
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::Deserialize;
+use specta::Type;
+use uuid::Uuid;
+
+use super::{
+	object_collections::{cover_object_pub_id, objects_pub_ids, objects_to_add, reorder},
+	utils::library,
+	Ctx, R,
+};
+
+#[derive(Type, Deserialize)]
+pub struct SpaceObjectsArgs {
+	pub id: space::id::Type,
+	pub object_ids: Vec<object::id::Type>,
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library
+					.db
+					.space()
+					.find_many(vec![])
+					.order_by(space::date_created::order(SortOrder::Asc))
+					.exec()
+					.await?)
+			})
+		})
+		.procedure("get", {
+			R.with2(library())
+				.query(|(_, library), space_id: space::id::Type| async move {
+					Ok(library
+						.db
+						.space()
+						.find_unique(space::id::equals(space_id))
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("getForObject", {
+			R.with2(library())
+				.query(|(_, library), object_id: object::id::Type| async move {
+					Ok(library
+						.db
+						.space()
+						.find_many(vec![space::objects::some(vec![
+							object_in_space::object_id::equals(object_id),
+						])])
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("getObjects", {
+			R.with2(library())
+				.query(|(_, library), space_id: space::id::Type| async move {
+					Ok(library
+						.db
+						.object_in_space()
+						.find_many(vec![object_in_space::space_id::equals(space_id)])
+						.order_by(object_in_space::position::order(SortOrder::Asc))
+						.include(object_in_space::include!({ object }))
+						.exec()
+						.await?
+						.into_iter()
+						.map(|object_in_space| object_in_space.object)
+						.collect::<Vec<_>>())
+				})
+		})
+		.procedure("create", {
+			#[derive(Type, Deserialize)]
+			pub struct SpaceCreateArgs {
+				pub name: String,
+				#[serde(default)]
+				pub description: Option<String>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceCreateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = uuid_to_bytes(&Uuid::new_v4());
+
+					let (sync_params, db_params): (Vec<_>, Vec<_>) = [
+						Some(sync_db_entry!(args.name, space::name)),
+						option_sync_db_entry!(args.description, space::description),
+						Some(sync_db_entry!(Utc::now(), space::date_created)),
+					]
+					.into_iter()
+					.flatten()
+					.unzip();
+
+					let space = sync
+						.write_ops(
+							db,
+							(
+								sync.shared_create(
+									prisma_sync::space::SyncId {
+										pub_id: pub_id.clone(),
+									},
+									sync_params,
+								),
+								db.space().create(pub_id, db_params),
+							),
+						)
+						.await?;
+
+					invalidate_query!(library, "spaces.list");
+
+					Ok(space)
+				})
+		})
+		.procedure("update", {
+			#[derive(Type, Deserialize)]
+			pub struct SpaceUpdateArgs {
+				pub id: space::id::Type,
+				pub name: Option<String>,
+				pub description: Option<String>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceUpdateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = space_pub_id(db, args.id).await?;
+
+					let (sync_params, db_params): (Vec<_>, Vec<_>) = [
+						option_sync_db_entry!(args.name, space::name),
+						option_sync_db_entry!(args.description, space::description),
+						Some(sync_db_entry!(Utc::now(), space::date_modified)),
+					]
+					.into_iter()
+					.flatten()
+					.unzip();
+
+					sync.write_ops(
+						db,
+						(
+							sync_params
+								.into_iter()
+								.map(|(k, v)| {
+									sync.shared_update(
+										prisma_sync::space::SyncId {
+											pub_id: pub_id.clone(),
+										},
+										k,
+										v,
+									)
+								})
+								.collect(),
+							db.space().update(space::id::equals(args.id), db_params),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "spaces.list");
+
+					Ok(())
+				})
+		})
+		.procedure("setCover", {
+			#[derive(Type, Deserialize)]
+			pub struct SpaceSetCoverArgs {
+				pub id: space::id::Type,
+				/// Removes the cover when missing
+				pub object_id: Option<object::id::Type>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceSetCoverArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space_sync_id = prisma_sync::space::SyncId {
+						pub_id: space_pub_id(db, args.id).await?,
+					};
+
+					match args.object_id {
+						Some(object_id) => {
+							let object_pub_id = cover_object_pub_id(db, object_id).await?;
+
+							sync.write_op(
+								db,
+								sync.shared_update(
+									space_sync_id,
+									space::cover_object::NAME,
+									msgpack!(prisma_sync::object::SyncId {
+										pub_id: object_pub_id
+									}),
+								),
+								db.space().update(
+									space::id::equals(args.id),
+									vec![space::cover_object::connect(object::id::equals(
+										object_id,
+									))],
+								),
+							)
+							.await?;
+						}
+						None => {
+							sync.write_op(
+								db,
+								sync.shared_update(
+									space_sync_id,
+									space::cover_object_id::NAME,
+									msgpack!(nil),
+								),
+								db.space().update(
+									space::id::equals(args.id),
+									vec![space::cover_object::disconnect()],
+								),
+							)
+							.await?;
+						}
+					}
+
+					invalidate_query!(library, "spaces.list");
+
+					Ok(())
+				})
+		})
+		.procedure("addObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space_pub_id = space_pub_id(db, args.id).await?;
+
+					let members = db
+						.object_in_space()
+						.find_many(vec![object_in_space::space_id::equals(args.id)])
+						.select(object_in_space::select!({ object_id position }))
+						.exec()
+						.await?;
+
+					let objects = objects_to_add(
+						db,
+						&args.object_ids,
+						members
+							.iter()
+							.map(|member| (member.object_id, member.position)),
+					)
+					.await?;
+
+					if objects.is_empty() {
+						return Ok(());
+					}
+
+					let date_created = Utc::now();
+
+					let (sync_ops, db_creates) = objects.into_iter().fold(
+						(vec![], vec![]),
+						|(mut sync_ops, mut db_creates), (object, position)| {
+							sync_ops.extend(sync.relation_create(
+								prisma_sync::object_in_space::SyncId {
+									space: prisma_sync::space::SyncId {
+										pub_id: space_pub_id.clone(),
+									},
+									object: prisma_sync::object::SyncId {
+										pub_id: object.pub_id,
+									},
+								},
+								[
+									sync_entry!(date_created, object_in_space::date_created),
+									sync_entry!(position, object_in_space::position),
+								],
+							));
+
+							db_creates.push(object_in_space::CreateUnchecked {
+								space_id: args.id,
+								object_id: object.id,
+								_params: vec![
+									object_in_space::date_created::set(Some(date_created.into())),
+									object_in_space::position::set(Some(position)),
+								],
+							});
+
+							(sync_ops, db_creates)
+						},
+					);
+
+					sync.write_ops(
+						db,
+						(
+							sync_ops,
+							db.object_in_space()
+								.create_many(db_creates)
+								.skip_duplicates(),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "spaces.getObjects");
+					invalidate_query!(library, "spaces.getForObject");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("removeObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space_pub_id = space_pub_id(db, args.id).await?;
+
+					let objects_pub_ids = objects_pub_ids(db, args.object_ids.clone()).await?;
+
+					sync.write_ops(
+						db,
+						(
+							objects_pub_ids
+								.into_iter()
+								.map(|object_pub_id| {
+									sync.relation_delete(prisma_sync::object_in_space::SyncId {
+										space: prisma_sync::space::SyncId {
+											pub_id: space_pub_id.clone(),
+										},
+										object: prisma_sync::object::SyncId {
+											pub_id: object_pub_id,
+										},
+									})
+								})
+								.collect(),
+							db.object_in_space().delete_many(vec![
+								object_in_space::space_id::equals(args.id),
+								object_in_space::object_id::in_vec(args.object_ids),
+							]),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "spaces.getObjects");
+					invalidate_query!(library, "spaces.getForObject");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("reorder", {
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space_pub_id = space_pub_id(db, args.id).await?;
+
+					let members = db
+						.object_in_space()
+						.find_many(vec![object_in_space::space_id::equals(args.id)])
+						.order_by(object_in_space::position::order(SortOrder::Asc))
+						.select(object_in_space::select!({
+							position
+							object: select { id pub_id }
+						}))
+						.exec()
+						.await?;
+
+					let (sync_ops, db_updates): (Vec<_>, Vec<_>) =
+						reorder(members, args.object_ids, |member| {
+							(member.object.id, member.position)
+						})
+						.into_iter()
+						.map(|(member, position)| {
+							(
+								sync.relation_update(
+									prisma_sync::object_in_space::SyncId {
+										space: prisma_sync::space::SyncId {
+											pub_id: space_pub_id.clone(),
+										},
+										object: prisma_sync::object::SyncId {
+											pub_id: member.object.pub_id,
+										},
+									},
+									object_in_space::position::NAME,
+									msgpack!(position),
+								),
+								db.object_in_space().update(
+									object_in_space::space_id_object_id(args.id, member.object.id),
+									vec![object_in_space::position::set(Some(position))],
+								),
+							)
+						})
+						.unzip();
+
+					if !sync_ops.is_empty() {
+						sync.write_ops(db, (sync_ops, db_updates)).await?;
+					}
+
+					invalidate_query!(library, "spaces.getObjects");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), space_id: space::id::Type| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let space = db
+						.space()
+						.find_unique(space::id::equals(space_id))
+						.select(space::select!({
+							pub_id
+							objects: select { object: select { pub_id } }
+						}))
+						.exec()
+						.await?
+						.ok_or_else(|| {
+							rspc::Error::new(ErrorCode::NotFound, "Space not found".to_string())
+						})?;
+
+					let space_sync_id = prisma_sync::space::SyncId {
+						pub_id: space.pub_id,
+					};
+
+					sync.write_ops(
+						db,
+						(
+							space
+								.objects
+								.into_iter()
+								.map(|object_in_space| {
+									sync.relation_delete(prisma_sync::object_in_space::SyncId {
+										space: space_sync_id.clone(),
+										object: prisma_sync::object::SyncId {
+											pub_id: object_in_space.object.pub_id,
+										},
+									})
+								})
+								.chain([sync.shared_delete(space_sync_id)])
+								.collect(),
+							(
+								db.object_in_space()
+									.delete_many(vec![object_in_space::space_id::equals(space_id)]),
+								db.space().delete(space::id::equals(space_id)),
+							),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "spaces.list");
+					invalidate_query!(library, "spaces.getForObject");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+}
+
+async fn space_pub_id(
+	db: &PrismaClient,
+	space_id: space::id::Type,
+) -> Result<space::pub_id::Type, rspc::Error> {
+	db.space()
+		.find_unique(space::id::equals(space_id))
+		.select(space::select!({ pub_id }))
+		.exec()
+		.await?
+		.map(|space| space.pub_id)
+		.ok_or_else(|| rspc::Error::new(ErrorCode::NotFound, "Space not found".to_string()))
+}
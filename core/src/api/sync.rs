@@ -2,8 +2,6 @@ use rspc::alpha::AlphaRouter;
 use sd_core_sync::GetOpsArgs;
 use std::sync::atomic::Ordering;
 
-use crate::util::MaybeUndefined;
-
 use super::{utils::library, Ctx, R};
 
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
@@ -37,33 +35,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 		.procedure("backfill", {
 			R.with2(library())
 				.mutation(|(node, library), _: ()| async move {
-					if library
-						.config()
-						.await
-						.generate_sync_operations
-						.load(Ordering::Relaxed)
-					{
-						return Ok(());
-					}
-
-					sd_core_sync::backfill::backfill_operations(
-						&library.db,
-						&library.sync,
-						library.config().await.instance_id,
-					)
-					.await?;
-
-					node.libraries
-						.edit(
-							library.id,
-							None,
-							MaybeUndefined::Undefined,
-							MaybeUndefined::Undefined,
-							Some(true),
-						)
-						.await?;
-
-					Ok(())
+					Ok(node.libraries.enable_sync(&library).await?)
 				})
 		})
 		.procedure("enabled", {
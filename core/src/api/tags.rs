@@ -1,4 +1,8 @@
-use crate::{invalidate_query, library::Library, object::tag::TagCreateArgs};
+use crate::{
+	invalidate_query,
+	library::Library,
+	object::tag::{with_descendants, TagCreateArgs},
+};
 
 use sd_prisma::{
 	prisma::{file_path, object, tag, tag_on_object},
@@ -88,11 +92,30 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 		.procedure("create", {
 			R.with2(library())
 				.mutation(|(_, library), args: TagCreateArgs| async move {
-					// Check if tag with the same name already exists
+					if let Some(parent_id) = args.parent_id {
+						library
+							.db
+							.tag()
+							.find_unique(tag::id::equals(parent_id))
+							.select(tag::select!({ id }))
+							.exec()
+							.await?
+							.ok_or_else(|| {
+								rspc::Error::new(
+									ErrorCode::NotFound,
+									"Parent tag not found".to_string(),
+								)
+							})?;
+					}
+
+					// Check if tag with the same name already exists under the same parent
 					let existing_tag = library
 						.db
 						.tag()
-						.find_many(vec![tag::name::equals(Some(args.name.clone()))])
+						.find_many(vec![
+							tag::name::equals(Some(args.name.clone())),
+							tag::parent_id::equals(args.parent_id),
+						])
 						.select(tag::select!({ id }))
 						.exec()
 						.await?;
@@ -357,25 +380,186 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					Ok(())
 				})
 		})
+		.procedure("setParent", {
+			#[derive(Type, Deserialize)]
+			pub struct TagSetParentArgs {
+				pub id: tag::id::Type,
+				/// Makes the tag a root one when missing
+				pub parent_id: Option<tag::id::Type>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: TagSetParentArgs| async move {
+					let Library { sync, db, .. } = library.as_ref();
+
+					let tag = db
+						.tag()
+						.find_unique(tag::id::equals(args.id))
+						.select(tag::select!({ pub_id }))
+						.exec()
+						.await?
+						.ok_or_else(|| {
+							rspc::Error::new(ErrorCode::NotFound, "Tag not found".to_string())
+						})?;
+
+					let tag_sync_id = prisma_sync::tag::SyncId { pub_id: tag.pub_id };
+
+					match args.parent_id {
+						Some(parent_id) => {
+							// A tag can't be nested under itself or one of its own descendants
+							if with_descendants(db, [args.id]).await?.contains(&parent_id) {
+								return Err(rspc::Error::new(
+									ErrorCode::BadRequest,
+									"Tag can't be nested under itself or its descendants"
+										.to_string(),
+								));
+							}
+
+							let parent = db
+								.tag()
+								.find_unique(tag::id::equals(parent_id))
+								.select(tag::select!({ pub_id }))
+								.exec()
+								.await?
+								.ok_or_else(|| {
+									rspc::Error::new(
+										ErrorCode::NotFound,
+										"Parent tag not found".to_string(),
+									)
+								})?;
+
+							sync.write_op(
+								db,
+								sync.shared_update(
+									tag_sync_id,
+									tag::parent::NAME,
+									msgpack!(prisma_sync::tag::SyncId {
+										pub_id: parent.pub_id
+									}),
+								),
+								db.tag().update(
+									tag::id::equals(args.id),
+									vec![tag::parent::connect(tag::id::equals(parent_id))],
+								),
+							)
+							.await?;
+						}
+						None => {
+							sync.write_op(
+								db,
+								sync.shared_update(
+									tag_sync_id,
+									tag::parent_id::NAME,
+									msgpack!(nil),
+								),
+								db.tag().update(
+									tag::id::equals(args.id),
+									vec![tag::parent::disconnect()],
+								),
+							)
+							.await?;
+						}
+					}
+
+					invalidate_query!(library, "tags.list");
+
+					Ok(())
+				})
+		})
 		.procedure(
 			"delete",
 			R.with2(library())
 				.mutation(|(_, library), tag_id: i32| async move {
-					library
-						.db
-						.tag_on_object()
-						.delete_many(vec![tag_on_object::tag_id::equals(tag_id)])
-						.exec()
-						.await?;
+					let Library { sync, db, .. } = library.as_ref();
 
-					library
-						.db
+					let tag = db
 						.tag()
-						.delete(tag::id::equals(tag_id))
+						.find_unique(tag::id::equals(tag_id))
+						.select(tag::select!({
+							pub_id
+							parent: select { id pub_id }
+							children: select { id pub_id }
+							tag_objects: select { object: select { pub_id } }
+						}))
 						.exec()
-						.await?;
+						.await?
+						.ok_or_else(|| {
+							rspc::Error::new(ErrorCode::NotFound, "Tag not found".to_string())
+						})?;
+
+					// Children are moved up to the parent of the deleted tag instead of being deleted
+					let (children_sync_ops, children_db_ops): (Vec<_>, Vec<_>) = tag
+						.children
+						.into_iter()
+						.map(|child| {
+							let child_sync_id = prisma_sync::tag::SyncId {
+								pub_id: child.pub_id,
+							};
+
+							match &tag.parent {
+								Some(parent) => (
+									sync.shared_update(
+										child_sync_id,
+										tag::parent::NAME,
+										msgpack!(prisma_sync::tag::SyncId {
+											pub_id: parent.pub_id.clone()
+										}),
+									),
+									db.tag().update(
+										tag::id::equals(child.id),
+										vec![tag::parent::connect(tag::id::equals(parent.id))],
+									),
+								),
+								None => (
+									sync.shared_update(
+										child_sync_id,
+										tag::parent_id::NAME,
+										msgpack!(nil),
+									),
+									db.tag().update(
+										tag::id::equals(child.id),
+										vec![tag::parent::disconnect()],
+									),
+								),
+							}
+						})
+						.unzip();
+
+					if !children_sync_ops.is_empty() {
+						sync.write_ops(db, (children_sync_ops, children_db_ops))
+							.await?;
+					}
+
+					let tag_sync_id = prisma_sync::tag::SyncId { pub_id: tag.pub_id };
+
+					sync.write_ops(
+						db,
+						(
+							tag.tag_objects
+								.into_iter()
+								.map(|tag_object| {
+									sync.relation_delete(prisma_sync::tag_on_object::SyncId {
+										tag: tag_sync_id.clone(),
+										object: prisma_sync::object::SyncId {
+											pub_id: tag_object.object.pub_id,
+										},
+									})
+								})
+								.chain([sync.shared_delete(tag_sync_id)])
+								.collect(),
+							(
+								db.tag_on_object()
+									.delete_many(vec![tag_on_object::tag_id::equals(tag_id)]),
+								db.tag().delete(tag::id::equals(tag_id)),
+							),
+						),
+					)
+					.await?;
 
 					invalidate_query!(library, "tags.list");
+					invalidate_query!(library, "tags.getForObject");
+					invalidate_query!(library, "tags.getWithObjects");
+					invalidate_query!(library, "search.objects");
 
 					Ok(())
 				}),
@@ -3,7 +3,7 @@ use crate::api::{CoreEvent, Ctx, Router, R};
 use async_stream::stream;
 use rspc::alpha::AlphaRouter;
 use serde::Serialize;
-use serde_hashkey::to_key;
+use serde_hashkey::{to_key, Key};
 use serde_json::Value;
 use specta::{DataType, Type};
 use std::{
@@ -16,6 +16,7 @@ use std::{
 };
 use tokio::sync::broadcast;
 use tracing::{debug, warn};
+use uuid::Uuid;
 
 #[cfg(debug_assertions)]
 use std::sync::Mutex;
@@ -32,24 +33,42 @@ pub struct SingleInvalidateOperationEvent {
 	pub key: &'static str,
 	arg: Value,
 	result: Option<Value>,
+	/// The library the invalidated query belongs to, `None` for node wide queries.
+	library_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Type)]
 #[serde(tag = "type", content = "data", rename_all = "camelCase")]
 pub enum InvalidateOperationEvent {
 	Single(SingleInvalidateOperationEvent),
-	// TODO: A temporary hack used with Brendan's sync system until the v2 invalidation system is implemented.
-	All,
 }
 
 impl InvalidateOperationEvent {
 	/// If you are using this function, your doing it wrong.
 	pub fn dangerously_create(key: &'static str, arg: Value, result: Option<Value>) -> Self {
-		Self::Single(SingleInvalidateOperationEvent { key, arg, result })
+		Self::Single(SingleInvalidateOperationEvent {
+			key,
+			arg,
+			result,
+			library_id: None,
+		})
 	}
 
-	pub fn all() -> Self {
-		Self::All
+	/// Scopes the event to a library, so only clients subscribed to that library receive it.
+	#[must_use]
+	pub fn with_library(self, library_id: Uuid) -> Self {
+		match self {
+			Self::Single(event) => Self::Single(SingleInvalidateOperationEvent {
+				library_id: Some(library_id),
+				..event
+			}),
+		}
+	}
+
+	pub fn library_id(&self) -> Option<Uuid> {
+		match self {
+			Self::Single(event) => event.library_id,
+		}
 	}
 }
 
@@ -142,6 +161,7 @@ macro_rules! invalidate_query {
 		// The error are ignored here because they aren't mission critical. If they fail the UI might be outdated for a bit.
 		ctx.emit($crate::api::CoreEvent::InvalidateOperation(
 			$crate::api::utils::InvalidateOperationEvent::dangerously_create(query, serde_json::Value::Null, None)
+				.with_library(ctx.id)
 		))
 	}};
 
@@ -170,6 +190,7 @@ macro_rules! invalidate_query {
 		// The error are ignored here because they aren't mission critical. If they fail the UI might be outdated for a bit.
 		ctx.emit($crate::api::CoreEvent::InvalidateOperation(
 			$crate::api::utils::InvalidateOperationEvent::dangerously_create($key, serde_json::Value::Null, None)
+				.with_library(ctx.id)
 		))
 	}};
 	(node; $ctx:expr, $key:literal) => {{
@@ -235,7 +256,8 @@ macro_rules! invalidate_query {
 		let _ = serde_json::to_value($arg)
 			.map(|v|
 				ctx.emit($crate::api::CoreEvent::InvalidateOperation(
-					$crate::api::utils::InvalidateOperationEvent::dangerously_create($key, v, None),
+					$crate::api::utils::InvalidateOperationEvent::dangerously_create($key, v, None)
+						.with_library(ctx.id),
 				))
 			)
 			.map_err(|_| {
@@ -277,7 +299,8 @@ macro_rules! invalidate_query {
 				serde_json::to_value($result)
 				.map(|result|
 					ctx.emit($crate::api::CoreEvent::InvalidateOperation(
-						$crate::api::utils::InvalidateOperationEvent::dangerously_create($key, arg, Some(result)),
+						$crate::api::utils::InvalidateOperationEvent::dangerously_create($key, arg, Some(result))
+							.with_library(ctx.id),
 					))
 				)
 			)
@@ -291,7 +314,6 @@ pub(crate) fn mount_invalidate() -> AlphaRouter<Ctx> {
 	let (tx, _) = broadcast::channel(100);
 	let manager_thread_active = Arc::new(AtomicBool::new(false));
 
-	// TODO: Scope the invalidate queries to a specific library (filtered server side)
 	let r = if cfg!(debug_assertions) {
 		let count = Arc::new(std::sync::atomic::AtomicU16::new(0));
 
@@ -312,7 +334,9 @@ pub(crate) fn mount_invalidate() -> AlphaRouter<Ctx> {
 	};
 
 	r.procedure("listen", {
-		R.subscription(move |ctx, _: ()| {
+		// Clients subscribe with the library they are currently viewing and only receive
+		// invalidations for that library, plus the node wide ones.
+		R.subscription(move |ctx, library_id: Option<Uuid>| {
 			// This thread is used to deal with batching and deduplication.
 			// Their is only ever one of these management threads per Node but we spawn it like this so we can steal the event bus from the rspc context.
 			// Batching is important because when refetching data on the frontend rspc can fetch all invalidated queries in a single round trip.
@@ -329,30 +353,9 @@ pub(crate) fn mount_invalidate() -> AlphaRouter<Ctx> {
 							continue;
 						};
 
-						let mut buf =
-							match &first_event {
-								InvalidateOperationEvent::All => None,
-								InvalidateOperationEvent::Single(
-									SingleInvalidateOperationEvent { key, arg, .. },
-								) => {
-									let key = match to_key(&(key, arg)) {
-										Ok(key) => key,
-										Err(e) => {
-											warn!(
-												?first_event,
-												?e,
-												"Error deriving key for invalidate operation;"
-											);
-											continue;
-										}
-									};
-
-									let mut map = HashMap::with_capacity(20);
-									map.insert(key, first_event);
+						let mut buf = HashMap::with_capacity(20);
+						insert_deduplicated(&mut buf, first_event);
 
-									Some(map)
-								}
-							};
 						let batch_time = tokio::time::Instant::now() + Duration::from_millis(10);
 
 						loop {
@@ -371,39 +374,17 @@ pub(crate) fn mount_invalidate() -> AlphaRouter<Ctx> {
 
 									let CoreEvent::InvalidateOperation(op) = event else { continue; };
 
-									match (&op, &mut buf) {
-										(InvalidateOperationEvent::All, Some(_)) => buf = None,
-										(InvalidateOperationEvent::Single(SingleInvalidateOperationEvent { key, arg, .. }), Some(buf)) => {
-											// Newer data replaces older data in the buffer
-											match to_key(&(key, &arg)) {
-												Ok(key) => {
-													buf.insert(key, op);
-												},
-												Err(e) => {
-													warn!(
-														?op,
-														?e,
-														"Error deriving key for invalidate operation;",
-													);
-												},
-											}
-										},
-										_ => {}
-									}
+									// Newer data replaces older data in the buffer
+									insert_deduplicated(&mut buf, op);
 								},
 							}
 						}
 
-						let events = match buf {
-							None => vec![InvalidateOperationEvent::all()],
-							Some(buf) => buf.into_values().collect::<Vec<_>>(),
-						};
-
-						if events.is_empty() {
-							break;
+						if buf.is_empty() {
+							continue;
 						}
 
-						match tx.send(events) {
+						match tx.send(Arc::new(buf.into_values().collect::<Vec<_>>())) {
 							Ok(_) => {}
 							// All receivers are shutdown means that all clients are disconnected.
 							Err(_) => {
@@ -421,10 +402,43 @@ pub(crate) fn mount_invalidate() -> AlphaRouter<Ctx> {
 
 			let mut rx = tx.subscribe();
 			stream! {
-				while let Ok(msg) = rx.recv().await {
-					yield msg;
+				while let Ok(events) = rx.recv().await {
+					let events = events
+						.iter()
+						.filter(|event| {
+							event
+								.library_id()
+								.map_or(true, |id| Some(id) == library_id)
+						})
+						.cloned()
+						.collect::<Vec<_>>();
+
+					if !events.is_empty() {
+						yield events;
+					}
 				}
 			}
 		})
 	})
 }
+
+fn insert_deduplicated(
+	buf: &mut HashMap<Key, InvalidateOperationEvent>,
+	op: InvalidateOperationEvent,
+) {
+	let InvalidateOperationEvent::Single(SingleInvalidateOperationEvent {
+		key,
+		arg,
+		library_id,
+		..
+	}) = &op;
+
+	match to_key(&(key, arg, library_id)) {
+		Ok(key) => {
+			buf.insert(key, op);
+		}
+		Err(e) => {
+			warn!(?op, ?e, "Error deriving key for invalidate operation;");
+		}
+	}
+}
@@ -148,7 +148,7 @@ pub async fn run_actor(
 async fn wait_notification(mut rx: broadcast::Receiver<SyncMessage>) -> RaceNotifiedOrStopped {
 	// wait until Created message comes in
 	loop {
-		if let Ok(SyncMessage::Created) = rx.recv().await {
+		if let Ok(SyncMessage::Created(_)) = rx.recv().await {
 			break;
 		};
 	}
@@ -68,7 +68,10 @@ impl OuterContext for NodeContext {
 		let event = match update {
 			UpdateEvent::NewThumbnail { thumb_key } => CoreEvent::NewThumbnail { thumb_key },
 			UpdateEvent::NewIdentifiedObjects { file_path_ids } => {
-				CoreEvent::NewIdentifiedObjects { file_path_ids }
+				CoreEvent::NewIdentifiedObjects {
+					file_path_ids,
+					library_id: self.library.id,
+				}
 			}
 		};
 		self.node.emit(event);
@@ -7,7 +7,7 @@ use crate::{
 };
 
 use sd_core_file_path_helper::IsolatedFilePathData;
-use sd_core_heavy_lifting::media_processor::WEBP_EXTENSION;
+use sd_core_heavy_lifting::media_processor::{VideoPreviewFile, WEBP_EXTENSION};
 use sd_core_prisma_helpers::file_path_to_handle_custom_uri;
 
 use sd_file_ext::text::is_text;
@@ -38,6 +38,7 @@ use axum::{
 	Router,
 };
 use bytes::Bytes;
+use chrono::{DateTime, FixedOffset, Utc};
 use http_body::combinators::UnsyncBoxBody;
 use hyper::{header, upgrade::OnUpgrade};
 use mini_moka::sync::Cache;
@@ -48,7 +49,10 @@ use tokio::{
 use tracing::{error, warn};
 use uuid::Uuid;
 
-use self::{serve_file::serve_file, utils::*};
+use self::{
+	serve_file::{checksum_etag, partial_content, requested_range, serve_file, RequestedRange},
+	utils::*,
+};
 
 mod async_read_body;
 mod mpsc_to_async_write;
@@ -64,9 +68,31 @@ struct CacheValue {
 	name: PathBuf,
 	ext: String,
 	file_path_pub_id: Uuid,
+	/// Derived from the file's content checksum, if it was already computed
+	etag: Option<HeaderValue>,
+	size: Option<u64>,
+	date_modified: Option<DateTime<FixedOffset>>,
 	serve_from: ServeFrom,
 }
 
+impl CacheValue {
+	/// The checksum based ETag only holds while the file on disk is still the one that was indexed,
+	/// as the checksum isn't updated until the file is indexed again.
+	fn matches_indexed_file(&self, metadata: &Metadata) -> bool {
+		let Ok(modified) = metadata.modified() else {
+			return false;
+		};
+
+		// Datetimes stored in DB loses a bit of precision, so we need to check against a delta
+		self.size == Some(metadata.len())
+			&& self.date_modified.is_some_and(|date_modified| {
+				(DateTime::<Utc>::from(modified) - date_modified.with_timezone(&Utc))
+					.num_milliseconds()
+					.abs() <= 1
+			})
+	}
+}
+
 const MAX_TEXT_READ_LENGTH: usize = 10 * 1024; // 10KB
 
 #[derive(Debug, Clone)]
@@ -147,7 +173,7 @@ async fn request_to_remote_node(
 async fn get_or_init_lru_entry(
 	state: &LocalState,
 	extract::Path((lib_id, loc_id, path_id)): ExtractedPath,
-) -> Result<(CacheValue, Arc<Library>), Response<BoxBody>> {
+) -> Result<(CacheKey, CacheValue, Arc<Library>), Response<BoxBody>> {
 	let library_id = Uuid::from_str(&lib_id).map_err(bad_request)?;
 	let location_id = loc_id.parse::<location::id::Type>().map_err(bad_request)?;
 	let file_path_id = path_id
@@ -163,7 +189,7 @@ async fn get_or_init_lru_entry(
 		.ok_or_else(|| internal_server_error(()))?;
 
 	if let Some(entry) = state.file_metadata_cache.get(&lru_cache_key) {
-		Ok((entry, library))
+		Ok((lru_cache_key, entry, library))
 	} else {
 		let file_path = library
 			.db
@@ -201,6 +227,17 @@ async fn get_or_init_lru_entry(
 			name: path,
 			ext: maybe_missing(file_path.extension, "extension").map_err(not_found)?,
 			file_path_pub_id: Uuid::from_slice(&file_path.pub_id).map_err(internal_server_error)?,
+			etag: file_path
+				.integrity_checksum
+				.as_deref()
+				.or(file_path.cas_id.as_deref())
+				.and_then(checksum_etag),
+			size: file_path
+				.size_in_bytes_bytes
+				.as_deref()
+				.and_then(|bytes| bytes.try_into().ok())
+				.map(u64::from_be_bytes),
+			date_modified: file_path.date_modified,
 			serve_from: if library_identity == library.identity.to_remote_identity() {
 				ServeFrom::Local
 			} else {
@@ -216,10 +253,68 @@ async fn get_or_init_lru_entry(
 			.file_metadata_cache
 			.insert(lru_cache_key, lru_entry.clone());
 
-		Ok((lru_entry, library))
+		Ok((lru_cache_key, lru_entry, library))
 	}
 }
 
+/// Serves a file from the thumbnails directory, `path` always points to a thumbnail, ending with
+/// `.webp`, or to a JSON index, ending with `.json`. `preview_file` picks which file to serve
+/// instead of the thumbnail itself, telling if the request was for an index.
+async fn serve_thumbnails_file(
+	state: &LocalState,
+	path: String,
+	request: Request<Body>,
+	preview_file: impl FnOnce(bool) -> Option<VideoPreviewFile> + Send,
+) -> Result<Response<BoxBody>, Response<BoxBody>> {
+	let thumbnail_path = state.node.config.data_directory().join("thumbnails");
+	let mut path = thumbnail_path.join(path);
+
+	// Prevent directory traversal attacks (Eg. requesting `../../../etc/passwd`)
+	// For now we only support `webp` thumbnails.
+	if !path.starts_with(&thumbnail_path) {
+		return Err(not_found(()));
+	}
+
+	let is_index = path.extension() == Some("json".as_ref());
+	if !is_index && path.extension() != Some(WEBP_EXTENSION.as_ref()) {
+		return Err(not_found(()));
+	}
+
+	match preview_file(is_index) {
+		Some(preview_file) => {
+			path = preview_file.path_from_thumbnail(path.with_extension(WEBP_EXTENSION));
+		}
+		None if is_index => return Err(not_found(())),
+		None => {}
+	}
+
+	let file = File::open(&path).await.map_err(|e| {
+		InfallibleResponse::builder()
+			.status(if e.kind() == io::ErrorKind::NotFound {
+				StatusCode::NOT_FOUND
+			} else {
+				StatusCode::INTERNAL_SERVER_ERROR
+			})
+			.body(body::boxed(Full::from("")))
+	})?;
+	let metadata = file.metadata().await;
+	serve_file(
+		file,
+		metadata,
+		request.into_parts().0,
+		InfallibleResponse::builder().header(
+			"Content-Type",
+			HeaderValue::from_static(if is_index {
+				"application/json"
+			} else {
+				"image/webp"
+			}),
+		),
+		None,
+	)
+	.await
+}
+
 pub fn base_router() -> Router<LocalState> {
 	Router::new()
 		.route(
@@ -228,33 +323,38 @@ pub fn base_router() -> Router<LocalState> {
 				|State(state): State<LocalState>,
 				 extract::Path(path): extract::Path<String>,
 				 request: Request<Body>| async move {
-					let thumbnail_path = state.node.config.data_directory().join("thumbnails");
-					let path = thumbnail_path.join(path);
-
-					// Prevent directory traversal attacks (Eg. requesting `../../../etc/passwd`)
-					// For now we only support `webp` thumbnails.
-					(path.starts_with(&thumbnail_path)
-						&& path.extension() == Some(WEBP_EXTENSION.as_ref()))
-					.then_some(())
-					.ok_or_else(|| not_found(()))?;
-
-					let file = File::open(&path).await.map_err(|e| {
-						InfallibleResponse::builder()
-							.status(if e.kind() == io::ErrorKind::NotFound {
-								StatusCode::NOT_FOUND
-							} else {
-								StatusCode::INTERNAL_SERVER_ERROR
-							})
-							.body(body::boxed(Full::from("")))
-					})?;
-					let metadata = file.metadata().await;
-					serve_file(
-						file,
-						metadata,
-						request.into_parts().0,
-						InfallibleResponse::builder()
-							.header("Content-Type", HeaderValue::from_static("image/webp")),
-					)
+					serve_thumbnails_file(&state, path, request, |_| None).await
+				},
+			),
+		)
+		// Video previews are requested with the path of the video thumbnail, so the frontend
+		// can use the same `ThumbKey` for all of them
+		.route(
+			"/thumbstrip/*path",
+			get(
+				|State(state): State<LocalState>,
+				 extract::Path(path): extract::Path<String>,
+				 request: Request<Body>| async move {
+					serve_thumbnails_file(&state, path, request, |index| {
+						Some(if index {
+							VideoPreviewFile::ThumbstripIndex
+						} else {
+							VideoPreviewFile::ThumbstripSheet
+						})
+					})
+					.await
+				},
+			),
+		)
+		.route(
+			"/video-preview/*path",
+			get(
+				|State(state): State<LocalState>,
+				 extract::Path(path): extract::Path<String>,
+				 request: Request<Body>| async move {
+					serve_thumbnails_file(&state, path, request, |index| {
+						(!index).then_some(VideoPreviewFile::Animated)
+					})
 					.await
 				},
 			),
@@ -263,27 +363,37 @@ pub fn base_router() -> Router<LocalState> {
 			"/file/:lib_id/:loc_id/:path_id",
 			get(
 				|State(state): State<LocalState>, path: ExtractedPath, request: Request<Body>| async move {
-					let (
-						CacheValue {
-							name: file_path_full_path,
-							ext: extension,
-							file_path_pub_id,
-							serve_from,
-							..
-						},
-						_library,
-					) = get_or_init_lru_entry(&state, path).await?;
-
-					match serve_from {
+					let (lru_cache_key, lru_entry, _library) =
+						get_or_init_lru_entry(&state, path).await?;
+
+					match lru_entry.serve_from.clone() {
 						ServeFrom::Local => {
-							let metadata = fs::metadata(&file_path_full_path)
+							let CacheValue {
+								name: file_path_full_path,
+								ext: extension,
+								etag,
+								..
+							} = &lru_entry;
+
+							let metadata = fs::metadata(file_path_full_path)
 								.await
 								.map_err(internal_server_error)?;
 							(!metadata.is_dir())
 								.then_some(())
 								.ok_or_else(|| not_found(()))?;
 
-							let mut file = File::open(&file_path_full_path).await.map_err(|e| {
+							// The file changed since it was indexed, so we fallback to an ETag derived from
+							// its modified time and reload the entry, as the DB may already be up to date
+							let etag = if lru_entry.matches_indexed_file(&metadata) {
+								etag.clone()
+							} else {
+								if etag.is_some() {
+									state.file_metadata_cache.invalidate(&lru_cache_key);
+								}
+								None
+							};
+
+							let mut file = File::open(file_path_full_path).await.map_err(|e| {
 								InfallibleResponse::builder()
 									.status(if e.kind() == io::ErrorKind::NotFound {
 										StatusCode::NOT_FOUND
@@ -296,7 +406,7 @@ pub fn base_router() -> Router<LocalState> {
 							let resp = InfallibleResponse::builder().header(
 								"Content-Type",
 								HeaderValue::from_str(
-									&infer_the_mime_type(&extension, &mut file, &metadata).await?,
+									&infer_the_mime_type(extension, &mut file, &metadata).await?,
 								)
 								.map_err(|e| {
 									error!(?e, "Error converting mime-type into header value;");
@@ -304,43 +414,82 @@ pub fn base_router() -> Router<LocalState> {
 								})?,
 							);
 
-							serve_file(file, Ok(metadata), request.into_parts().0, resp).await
+							serve_file(file, Ok(metadata), request.into_parts().0, resp, etag).await
 						}
 						ServeFrom::Remote {
 							library_identity: _,
 							node_identity,
 							library,
 						} => {
-							// TODO: Support `Range` requests and `ETag` headers
+							// Remote entries are evicted when synced file paths are ingested, so their
+							// checksum is as up to date as our copy of the remote file path
+							let CacheValue {
+								file_path_pub_id,
+								etag,
+								size,
+								..
+							} = lru_entry;
+
+							let request = request.into_parts().0;
+							let mut resp = InfallibleResponse::builder();
+							if let Some(etag) = &etag {
+								resp = resp.header(header::ETAG, etag.clone());
+							}
+
+							// We can only map the request onto a range of the remote file if we know its size
+							let (range, resp) = match size {
+								Some(size) if size > 0 => {
+									let resp = resp.header(
+										header::ACCEPT_RANGES,
+										HeaderValue::from_static("bytes"),
+									);
+
+									match requested_range(&request, size, etag.as_ref())? {
+										RequestedRange::NotModified => {
+											return Ok(resp
+												.status(StatusCode::NOT_MODIFIED)
+												.body(body::boxed(Full::from(""))));
+										}
+										RequestedRange::Full => {
+											(Range::Full, resp.status(StatusCode::OK))
+										}
+										RequestedRange::Partial { start, length } => (
+											Range::Partial(start..start + length),
+											partial_content(resp, start, length, size)?,
+										),
+									}
+								}
+								_ => (Range::Full, resp.status(StatusCode::OK)),
+							};
 
 							let (tx, mut rx) = tokio::sync::mpsc::channel::<io::Result<Bytes>>(150);
-							request_file(
-								state.node.p2p.p2p.clone(),
-								*node_identity,
-								&library.identity,
-								file_path_pub_id,
-								Range::Full,
-								MpscToAsyncWrite::new(PollSender::new(tx)),
-							)
-							.await
-							.map_err(|e| {
-								error!(
-									%file_path_pub_id,
-									node_identity = ?library.identity.to_remote_identity(),
-									?e,
-									"Error requesting file from other node;",
-								);
-								internal_server_error(())
-							})?;
+							// The file is streamed to the response while it's received, so the transfer can't block the handler
+							tokio::spawn(async move {
+								if let Err(e) = request_file(
+									state.node.p2p.p2p.clone(),
+									*node_identity,
+									&library,
+									file_path_pub_id,
+									range,
+									MpscToAsyncWrite::new(PollSender::new(tx)),
+								)
+								.await
+								{
+									error!(
+										%file_path_pub_id,
+										node_identity = ?library.identity.to_remote_identity(),
+										?e,
+										"Error requesting file from other node;",
+									);
+								}
+							});
 
 							// TODO: Content Type
-							Ok(InfallibleResponse::builder().status(StatusCode::OK).body(
-								body::boxed(StreamBody::new(stream! {
-									while let Some(item) = rx.recv().await {
-										yield item;
-									}
-								})),
-							))
+							Ok(resp.body(body::boxed(StreamBody::new(stream! {
+								while let Some(item) = rx.recv().await {
+									yield item;
+								}
+							}))))
 						}
 					}
 				},
@@ -379,7 +528,7 @@ pub fn base_router() -> Router<LocalState> {
 						})?,
 					);
 
-					serve_file(file, Ok(metadata), request.into_parts().0, resp).await
+					serve_file(file, Ok(metadata), request.into_parts().0, resp, None).await
 				},
 			),
 		)
@@ -393,18 +542,12 @@ pub fn with_state(node: Arc<Node>) -> LocalState {
 		let mut tx = node.event_bus.0.subscribe();
 		async move {
 			while let Ok(event) = tx.recv().await {
-				if let CoreEvent::InvalidateOperation(e) = event {
-					match e {
-						InvalidateOperationEvent::Single(event) => {
-							// TODO: This is inefficient as any change will invalidate who cache. We need the new invalidation system!!!
-							// TODO: It's also error prone and a fine-grained resource based invalidation system would avoid that.
-							if event.key == "search.objects" || event.key == "search.paths" {
-								file_metadata_cache.invalidate_all();
-							}
-						}
-						InvalidateOperationEvent::All => {
-							file_metadata_cache.invalidate_all();
-						}
+				if let CoreEvent::InvalidateOperation(InvalidateOperationEvent::Single(event)) =
+					event
+				{
+					// TODO: This is inefficient as any change will invalidate who cache.
+					if event.key == "search.objects" || event.key == "search.paths" {
+						file_metadata_cache.invalidate_all();
 					}
 				}
 			}
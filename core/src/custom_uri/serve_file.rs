@@ -19,10 +19,141 @@ use super::{async_read_body::AsyncReadBody, utils::*};
 // default capacity 64KiB
 const DEFAULT_CAPACITY: usize = 65536;
 
+/// How a request for a file should be answered, once its conditional and `Range` headers are taken into account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RequestedRange {
+	/// The client already holds the current version of the file
+	NotModified,
+	/// The whole file must be sent
+	Full,
+	/// Only `length` bytes starting at `start` must be sent
+	Partial { start: u64, length: u64 },
+}
+
+/// Build an ETag from a checksum of the file's content, like its `cas_id` or `integrity_checksum`.
+pub(crate) fn checksum_etag(checksum: &str) -> Option<HeaderValue> {
+	HeaderValue::from_str(&format!(r#""{checksum}""#))
+		.map_err(|e| error!(?e, "Failed to convert ETag into header value;"))
+		.ok()
+}
+
+/// Build an ETag from the file's modified time, for files we don't know the content checksum of.
+fn modified_etag(metadata: &Metadata) -> Option<HeaderValue> {
+	let time = metadata.modified().ok()?;
+
+	HeaderValue::from_str(&format!(
+		r#""{}""#,
+		// The ETag's can be any value so we just use the modified time to make it easy.
+		time.duration_since(UNIX_EPOCH)
+			.expect("are you a time traveler? cause that's the only explanation for this error")
+			.as_millis()
+	))
+	.map_err(|e| error!(?e, "Failed to convert ETag into header value;"))
+	.ok()
+}
+
+/// Whether an `If-None-Match` header matches `etag`.
+///
+/// The header is a list of ETags, which may be weak validators (`W/"..."`), or `*` to match any ETag.
+/// `If-None-Match` uses the weak comparison, so the `W/` prefix is ignored on both sides.
+fn if_none_match_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+	fn opaque_tag(tag: &str) -> &str {
+		tag.trim().trim_start_matches("W/")
+	}
+
+	let Ok(if_none_match) = if_none_match.to_str() else {
+		return false;
+	};
+	let Ok(etag) = etag.to_str() else {
+		return false;
+	};
+
+	let etag = opaque_tag(etag);
+
+	if_none_match.trim() == "*"
+		|| if_none_match
+			.split(',')
+			.any(|candidate| opaque_tag(candidate) == etag)
+}
+
+/// Evaluate the `If-None-Match`, `If-Range` and `Range` headers of a request for a file of `len` bytes.
+///
+/// Errors with a `416 Range Not Satisfiable` response if the requested range is out of the file's bounds.
+pub(crate) fn requested_range(
+	req: &request::Parts,
+	len: u64,
+	etag: Option<&HeaderValue>,
+) -> Result<RequestedRange, Response<BoxBody>> {
+	// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/If-None-Match
+	if let (Some(etag), Some(if_none_match)) = (etag, req.headers.get(header::IF_NONE_MATCH)) {
+		if if_none_match_matches(if_none_match, etag) {
+			return Ok(RequestedRange::NotModified);
+		}
+	}
+
+	// https://developer.mozilla.org/en-US/docs/Web/HTTP/Range_requests
+	let Some(range) = req.headers.get(header::RANGE) else {
+		return Ok(RequestedRange::Full);
+	};
+	if req.method != Method::GET {
+		return Ok(RequestedRange::Full);
+	}
+
+	// Used checking if the resource has been modified since starting the download
+	// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/If-Range
+	if let Some(if_range) = req.headers.get(header::IF_RANGE) {
+		if Some(if_range) != etag {
+			return Ok(RequestedRange::Full);
+		}
+	}
+
+	let ranges = HttpRange::parse(range.to_str().map_err(bad_request)?, len)
+		.map_err(|_| range_not_satisfiable(len))?;
+
+	// TODO: Multipart requests are not support, yet
+	match ranges.as_slice() {
+		[range] if range.start + range.length <= len => Ok(RequestedRange::Partial {
+			start: range.start,
+			length: range.length,
+		}),
+		_ => Err(range_not_satisfiable(len)),
+	}
+}
+
+/// Add the headers of a `206 Partial Content` response for `length` bytes starting at `start` of a file of `len` bytes.
+pub(crate) fn partial_content(
+	resp: InfallibleResponse,
+	start: u64,
+	length: u64,
+	len: u64,
+) -> Result<InfallibleResponse, Response<BoxBody>> {
+	Ok(resp
+		.status(StatusCode::PARTIAL_CONTENT)
+		.header(
+			header::CONTENT_RANGE,
+			HeaderValue::from_str(&format!("bytes {}-{}/{len}", start, start + length - 1))
+				.map_err(internal_server_error)?,
+		)
+		.header(
+			header::CONTENT_LENGTH,
+			HeaderValue::from_str(&length.to_string()).map_err(internal_server_error)?,
+		))
+}
+
+fn range_not_satisfiable(len: u64) -> Response<BoxBody> {
+	InfallibleResponse::builder()
+		.header(
+			header::CONTENT_RANGE,
+			HeaderValue::from_str(&format!("bytes */{len}")).expect("number won't fail conversion"),
+		)
+		.status(StatusCode::RANGE_NOT_SATISFIABLE)
+		.body(body::boxed(Full::from("")))
+}
+
 /// Serve a Tokio file as a HTTP response.
 ///
 /// This function takes care of:
-///  - 304 Not Modified using ETag's
+///  - 304 Not Modified using ETag's, derived from the modified time if no `etag` is provided
 ///  - Range requests for partial content
 ///
 /// BE AWARE this function does not do any path traversal protection so that's up to the caller!
@@ -31,6 +162,7 @@ pub(crate) async fn serve_file(
 	metadata: io::Result<Metadata>,
 	req: request::Parts,
 	mut resp: InfallibleResponse,
+	etag: Option<HeaderValue>,
 ) -> Result<Response<BoxBody>, Response<BoxBody>> {
 	if let Ok(metadata) = metadata {
 		// We only accept range queries if `files.metadata() == Ok(_)`
@@ -51,103 +183,61 @@ pub(crate) async fn serve_file(
 				.body(body::boxed(Full::from(""))));
 		}
 
-		// ETag
-		let mut status_code = StatusCode::PARTIAL_CONTENT;
-		if let Ok(time) = metadata.modified() {
-			let etag_header =
-				format!(
-				r#""{}""#,
-				// The ETag's can be any value so we just use the modified time to make it easy.
-				time.duration_since(UNIX_EPOCH)
-					.expect("are you a time traveler? cause that's the only explanation for this error")
-					.as_millis()
-			);
-
-			// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/ETag
-			if let Ok(etag_header) = HeaderValue::from_str(&etag_header) {
-				resp = resp.header("etag", etag_header);
-			} else {
-				error!("Failed to convert ETag into header value!");
-			}
-
-			// Used for normal requests
-			if let Some(etag) = req.headers.get("If-None-Match") {
-				if etag.as_bytes() == etag_header.as_bytes() {
-					return Ok(resp
-						.status(StatusCode::NOT_MODIFIED)
-						.body(body::boxed(Full::from(""))));
-				}
-			}
+		// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/ETag
+		let etag = etag.or_else(|| modified_etag(&metadata));
+		if let Some(etag) = &etag {
+			resp = resp.header(header::ETAG, etag.clone());
+		}
 
-			// Used checking if the resource has been modified since starting the download
-			if let Some(if_range) = req.headers.get("If-Range") {
-				// https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/If-Range
-				if if_range.as_bytes() != etag_header.as_bytes() {
-					status_code = StatusCode::OK
-				}
+		match requested_range(&req, metadata.len(), etag.as_ref())? {
+			RequestedRange::NotModified => {
+				return Ok(resp
+					.status(StatusCode::NOT_MODIFIED)
+					.body(body::boxed(Full::from(""))));
 			}
-		};
-
-		// https://developer.mozilla.org/en-US/docs/Web/HTTP/Range_requests
-		if req.method == Method::GET {
-			if let Some(range) = req.headers.get("range") {
-				// TODO: Error handling
-				let ranges = HttpRange::parse(range.to_str().map_err(bad_request)?, metadata.len())
-					.map_err(bad_request)?;
-
-				// TODO: Multipart requests are not support, yet
-				if ranges.len() != 1 {
-					return Ok(resp
-						.header(
-							header::CONTENT_RANGE,
-							HeaderValue::from_str(&format!("bytes */{}", metadata.len()))
-								.map_err(internal_server_error)?,
-						)
-						.status(StatusCode::RANGE_NOT_SATISFIABLE)
-						.body(body::boxed(Full::from(""))));
-				}
-				let range = ranges.first().expect("checked above");
-
-				if (range.start + range.length) > metadata.len() {
-					return Ok(resp
-						.header(
-							header::CONTENT_RANGE,
-							HeaderValue::from_str(&format!("bytes */{}", metadata.len()))
-								.map_err(internal_server_error)?,
-						)
-						.status(StatusCode::RANGE_NOT_SATISFIABLE)
-						.body(body::boxed(Full::from(""))));
-				}
-
-				file.seek(SeekFrom::Start(range.start))
+			RequestedRange::Full => {}
+			RequestedRange::Partial { start, length } => {
+				file.seek(SeekFrom::Start(start))
 					.await
 					.map_err(internal_server_error)?;
 
-				return Ok(resp
-					.status(status_code)
-					.header(
-						"Content-Range",
-						HeaderValue::from_str(&format!(
-							"bytes {}-{}/{}",
-							range.start,
-							range.start + range.length - 1,
-							metadata.len()
-						))
-						.map_err(internal_server_error)?,
-					)
-					.header(
-						"Content-Length",
-						HeaderValue::from_str(&range.length.to_string())
-							.map_err(internal_server_error)?,
-					)
-					.body(body::boxed(AsyncReadBody::with_capacity_limited(
+				return Ok(partial_content(resp, start, length, metadata.len())?.body(
+					body::boxed(AsyncReadBody::with_capacity_limited(
 						file,
 						DEFAULT_CAPACITY,
-						range.length,
-					))));
+						length,
+					)),
+				));
 			}
 		}
 	}
 
 	Ok(resp.body(body::boxed(StreamBody::new(ReaderStream::new(file)))))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_if_none_match_matches() {
+		let etag = HeaderValue::from_static(r#""abc""#);
+
+		for (header, expected) in [
+			(r#""abc""#, true),
+			(r#"W/"abc""#, true),
+			(r#""xyz", W/"abc""#, true),
+			(r#""xyz","abc""#, true),
+			("*", true),
+			(r#""xyz""#, false),
+			(r#""ab""#, false),
+			("", false),
+		] {
+			assert_eq!(
+				if_none_match_matches(&HeaderValue::from_static(header), &etag),
+				expected,
+				"{header}"
+			);
+		}
+	}
+}
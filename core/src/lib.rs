@@ -74,6 +74,7 @@ pub struct Node {
 	pub event_bus: (broadcast::Sender<CoreEvent>, broadcast::Receiver<CoreEvent>),
 	pub notifications: Notifications,
 	pub cloud_sync_flag: Arc<AtomicBool>,
+	pub maintenance: node::maintenance::Maintenance,
 	pub env: Arc<env::Env>,
 	pub http: reqwest::Client,
 	pub task_system: TaskSystem<sd_core_heavy_lifting::Error>,
@@ -126,7 +127,9 @@ impl Node {
 		let (old_jobs, jobs_actor) = old_job::OldJobs::new();
 		let libraries = library::Libraries::new(data_dir.join("libraries")).await?;
 
-		let task_system = TaskSystem::new();
+		let task_system_preferences = config.get().await.preferences.task_system;
+
+		let task_system = TaskSystem::with_config(task_system_preferences.task_system_config());
 
 		let (p2p, start_p2p) = p2p::P2PManager::new(config.clone(), libraries.clone())
 			.await
@@ -146,6 +149,7 @@ impl Node {
 				cfg!(target_os = "ios") || cfg!(target_os = "android"),
 			)),
 			http: reqwest::Client::new(),
+			maintenance: node::maintenance::Maintenance::new(data_dir).await,
 			env,
 			#[cfg(feature = "ai")]
 			old_image_labeller: OldImageLabeler::new(
@@ -162,6 +166,10 @@ impl Node {
 			.ok(),
 		});
 
+		node.job_system
+			.concurrency_limits()
+			.replace(task_system_preferences.job_concurrency_limits());
+
 		// Restore backend feature flags
 		for feature in node.config.get().await.features {
 			feature.restore(&node);
@@ -221,6 +229,9 @@ impl Node {
 		);
 
 		save_storage_statistics(&node);
+		library::backup::start_scheduler(&node);
+		node::maintenance::start_scheduler(&node);
+		location::rescan::start_scheduler(&node);
 
 		info!("Spacedrive online!");
 		Ok((node, router))
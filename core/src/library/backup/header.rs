@@ -0,0 +1,218 @@
+use sd_utils::error::FileIOError;
+
+use std::{cmp, path::Path};
+
+use serde::{Serialize, Serializer};
+use specta::Type;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use uuid::Uuid;
+
+use super::BackupError;
+
+/// Backups written before incremental backups existed, always full ones
+const MAGIC_V1: &[u8; 6] = b"sdbkp1";
+const MAGIC_V2: &[u8; 6] = b"sdbkp2";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupKind {
+	/// Holds the whole library database
+	Full,
+	/// Only holds the database pages changed since its parent backup
+	Incremental,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Type)]
+pub struct Header {
+	// Backup unique id
+	pub id: Uuid,
+	// Time since epoch the backup was created at
+	#[specta(type = String)]
+	#[serde(serialize_with = "as_string")]
+	pub timestamp: u128,
+	// Library id
+	pub library_id: Uuid,
+	// Library display name
+	pub library_name: String,
+	pub kind: BackupKind,
+	// Backup this one was made on top of, only for incremental backups
+	pub parent_id: Option<Uuid>,
+}
+
+fn as_string<T: ToString, S>(x: &T, s: S) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	s.serialize_str(&x.to_string())
+}
+
+impl Header {
+	pub async fn write(&self, file: &mut (impl AsyncWrite + Unpin)) -> Result<(), io::Error> {
+		// For future versioning we can bump `2` to `3` and match on it in the decoder.
+		file.write_all(MAGIC_V2).await?;
+		file.write_all(&self.id.to_bytes_le()).await?;
+		file.write_all(&self.timestamp.to_le_bytes()).await?;
+		file.write_all(&self.library_id.to_bytes_le()).await?;
+		file.write_all(&[match self.kind {
+			BackupKind::Full => 0,
+			BackupKind::Incremental => 1,
+		}])
+		.await?;
+		file.write_all(&self.parent_id.unwrap_or_default().to_bytes_le())
+			.await?;
+		{
+			let bytes = &self.library_name.as_bytes()
+				[..cmp::min(u32::MAX as usize, self.library_name.len())];
+			file.write_all(&(bytes.len() as u32).to_le_bytes()).await?;
+			file.write_all(bytes).await?;
+		}
+
+		Ok(())
+	}
+
+	pub async fn read(
+		file: &mut (impl AsyncRead + Unpin),
+		path: impl AsRef<Path>,
+	) -> Result<Self, BackupError> {
+		let path = path.as_ref();
+
+		let mut magic = [0u8; 6];
+		file.read_exact(&mut magic)
+			.await
+			.map_err(|e| FileIOError::from((path, e)))?;
+
+		let is_v1 = match &magic {
+			MAGIC_V1 => true,
+			MAGIC_V2 => false,
+			_ => return Err(BackupError::MalformedHeader),
+		};
+
+		let mut buf = vec![0u8; 16 + 16 + 16];
+		file.read_exact(&mut buf)
+			.await
+			.map_err(|e| FileIOError::from((path, e)))?;
+
+		let (kind, parent_id) = if is_v1 {
+			(BackupKind::Full, None)
+		} else {
+			let mut kind_and_parent = [0u8; 1 + 16];
+			file.read_exact(&mut kind_and_parent)
+				.await
+				.map_err(|e| FileIOError::from((path, e)))?;
+
+			let parent_id = Uuid::from_bytes_le(
+				kind_and_parent[1..]
+					.try_into()
+					.map_err(|_| BackupError::MalformedHeader)?,
+			);
+
+			match kind_and_parent[0] {
+				0 => (BackupKind::Full, None),
+				1 if !parent_id.is_nil() => (BackupKind::Incremental, Some(parent_id)),
+				_ => return Err(BackupError::MalformedHeader),
+			}
+		};
+
+		Ok(Self {
+			id: Uuid::from_bytes_le(
+				buf[..16]
+					.try_into()
+					.map_err(|_| BackupError::MalformedHeader)?,
+			),
+			timestamp: u128::from_le_bytes(
+				buf[16..32]
+					.try_into()
+					.map_err(|_| BackupError::MalformedHeader)?,
+			),
+			library_id: Uuid::from_bytes_le(
+				buf[32..48]
+					.try_into()
+					.map_err(|_| BackupError::MalformedHeader)?,
+			),
+
+			library_name: {
+				let mut len = [0u8; 4];
+				file.read_exact(&mut len)
+					.await
+					.map_err(|e| FileIOError::from((path, e)))?;
+
+				let mut name = vec![0; u32::from_le_bytes(len) as usize];
+				file.read_exact(&mut name)
+					.await
+					.map_err(|e| FileIOError::from((path, e)))?;
+
+				String::from_utf8(name).map_err(|_| BackupError::MalformedHeader)?
+			},
+			kind,
+			parent_id,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_backup_header() {
+		let original = Header {
+			id: Uuid::new_v4(),
+			timestamp: 1234567890,
+			library_id: Uuid::new_v4(),
+			library_name: "Test Library".to_string(),
+			kind: BackupKind::Full,
+			parent_id: None,
+		};
+
+		let mut buf = Vec::new();
+		original.write(&mut buf).await.unwrap();
+
+		let decoded = Header::read(&mut buf.as_slice(), "").await.unwrap();
+		assert_eq!(original, decoded);
+	}
+
+	#[tokio::test]
+	async fn test_incremental_backup_header() {
+		let original = Header {
+			id: Uuid::new_v4(),
+			timestamp: 1234567890,
+			library_id: Uuid::new_v4(),
+			library_name: "Test Library".to_string(),
+			kind: BackupKind::Incremental,
+			parent_id: Some(Uuid::new_v4()),
+		};
+
+		let mut buf = Vec::new();
+		original.write(&mut buf).await.unwrap();
+
+		let decoded = Header::read(&mut buf.as_slice(), "").await.unwrap();
+		assert_eq!(original, decoded);
+	}
+
+	#[tokio::test]
+	async fn test_v1_backup_header() {
+		let id = Uuid::new_v4();
+		let library_id = Uuid::new_v4();
+
+		let mut buf = Vec::new();
+		buf.extend_from_slice(MAGIC_V1);
+		buf.extend_from_slice(&id.to_bytes_le());
+		buf.extend_from_slice(&1234567890u128.to_le_bytes());
+		buf.extend_from_slice(&library_id.to_bytes_le());
+		buf.extend_from_slice(&4u32.to_le_bytes());
+		buf.extend_from_slice(b"Test");
+
+		let decoded = Header::read(&mut buf.as_slice(), "").await.unwrap();
+		assert_eq!(
+			decoded,
+			Header {
+				id,
+				timestamp: 1234567890,
+				library_id,
+				library_name: "Test".to_string(),
+				kind: BackupKind::Full,
+				parent_id: None,
+			}
+		);
+	}
+}
@@ -0,0 +1,931 @@
+use crate::{
+	invalidate_query,
+	library::{Library, LibraryManagerError},
+	Node,
+};
+
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::HashMap,
+	io::{self, BufWriter, Read, Write},
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures_concurrency::future::Join;
+use serde::Serialize;
+use specta::Type;
+use tar::Archive;
+use tempfile::tempdir;
+use thiserror::Error;
+use tokio::{
+	fs::{self, File},
+	io::{AsyncWriteExt, BufWriter as AsyncBufWriter},
+	spawn,
+	task::{spawn_blocking, JoinError},
+	time::{interval, sleep, Instant},
+};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+mod header;
+mod pages;
+mod retention;
+
+pub use header::{BackupKind, Header};
+pub use retention::RetentionPolicy;
+
+use pages::{PageDelta, PageManifest};
+
+const CONFIG_ENTRY: &str = "library.sdlibrary";
+const DB_ENTRY: &str = "library.db";
+const DB_DELTA_ENTRY: &str = "library.db.delta";
+const MANIFEST_ENTRY: &str = "library.db.pages";
+
+/// Incremental backups made on top of each other before a new full one is made instead,
+/// as restoring one requires applying every backup in its chain
+const MAX_INCREMENTAL_CHAIN: usize = 30;
+
+/// How often the scheduler checks if any library is due for a backup
+const SCHEDULER_TICK: Duration = Duration::from_secs(15 * 60);
+
+/// How long a restore waits for an unloaded library to close its database before leaving
+/// the restored files to be applied on the next start
+const DATABASE_CLOSE_TIMEOUT: Duration = Duration::from_secs(10);
+const DATABASE_CLOSE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+	#[error("library manager error: {0}")]
+	LibraryManager(#[from] LibraryManagerError),
+	#[error("malformed header")]
+	MalformedHeader,
+	#[error("backup is missing the '{0}' entry")]
+	MissingEntry(&'static str),
+	#[error("parent backup not found: <id='{0}'>")]
+	MissingParent(Uuid),
+	#[error("restored database doesn't match the backup")]
+	IntegrityCheck,
+	#[error("backup task failed: {0}")]
+	Join(#[from] JoinError),
+
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct Backup {
+	#[serde(flatten)]
+	pub header: Header,
+	pub path: PathBuf,
+}
+
+/// Result of checking a backup without restoring it
+#[derive(Debug, Serialize, Type)]
+pub struct Verification {
+	pub header: Header,
+	pub entries: Vec<String>,
+	/// Every problem found, the backup can be restored only if there are none
+	pub problems: Vec<String>,
+}
+
+pub fn backups_dir(node: &Node) -> PathBuf {
+	node.data_dir.join("backups")
+}
+
+pub async fn list_backups(path: impl AsRef<Path>) -> Result<Vec<Backup>, BackupError> {
+	let path = path.as_ref();
+
+	let mut read_dir = match fs::read_dir(path).await {
+		Ok(read_dir) => read_dir,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+		Err(e) => {
+			return Err(FileIOError::from((&path, e, "Failed to read backups directory")).into())
+		}
+	};
+
+	let mut backups = vec![];
+
+	while let Some(entry) = read_dir
+		.next_entry()
+		.await
+		.map_err(|e| FileIOError::from((path, e, "Failed to read next entry to backup")))?
+	{
+		let entry_path = entry.path();
+
+		let metadata = entry.metadata().await.map_err(|e| {
+			FileIOError::from((&entry_path, e, "Failed to read metadata from backup entry"))
+		})?;
+
+		if metadata.is_file() {
+			backups.push(async move {
+				let header = match File::open(&entry_path).await {
+					Ok(mut file) => Header::read(&mut file, &entry_path).await,
+					Err(e) => Err(FileIOError::from((&entry_path, e)).into()),
+				};
+
+				match header {
+					Ok(header) => Some(Backup {
+						header,
+						path: entry_path,
+					}),
+					// Backups can be listed from any directory, so other files are expected
+					Err(e) => {
+						debug!(path = %entry_path.display(), ?e, "Skipping file that isn't a backup;");
+						None
+					}
+				}
+			});
+		}
+	}
+
+	Ok(backups.join().await.into_iter().flatten().collect())
+}
+
+/// Backs up the library, making an incremental backup on top of its latest one when asked to and
+/// possible, or a full one otherwise
+pub async fn do_backup(
+	id: Uuid,
+	node: &Node,
+	library: &Library,
+	kind: BackupKind,
+) -> Result<PathBuf, BackupError> {
+	let backups_dir = backups_dir(node);
+	fs::create_dir_all(&backups_dir)
+		.await
+		.map_err(|e| FileIOError::from((&backups_dir, e)))?;
+
+	let parent = if kind == BackupKind::Incremental {
+		incremental_parent(&backups_dir, library.id).await?
+	} else {
+		None
+	};
+
+	let timestamp = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.expect("Time went backwards")
+		.as_millis();
+
+	let bkp_path = backups_dir.join(format!("{id}.bkp"));
+	let res = async {
+		let mut bkp_file = AsyncBufWriter::new(
+			File::create(&bkp_path)
+				.await
+				.map_err(|e| FileIOError::from((&bkp_path, e, "Failed to create backup file")))?,
+		);
+
+		// Header. We do this so the file is self-sufficient.
+		Header {
+			id,
+			timestamp,
+			library_id: library.id,
+			library_name: library.config().await.name.to_string(),
+			kind: if parent.is_some() {
+				BackupKind::Incremental
+			} else {
+				BackupKind::Full
+			},
+			parent_id: parent.as_ref().map(|(parent, _)| parent.id),
+		}
+		.write(&mut bkp_file)
+		.await
+		.map_err(|e| FileIOError::from((&bkp_path, e, "Failed to create backup file")))?;
+
+		bkp_file
+			.flush()
+			.await
+			.map_err(|e| FileIOError::from((&bkp_path, e, "Failed to create backup file")))?;
+
+		let bkp_file = bkp_file.into_inner().into_std().await;
+
+		let library_config_path = node
+			.libraries
+			.libraries_dir
+			.join(format!("{}.sdlibrary", library.id));
+
+		let library_db_path = node
+			.libraries
+			.libraries_dir
+			.join(format!("{}.db", library.id));
+
+		// Copying the database first, so its page manifest matches the pages we store
+		let temp_dir = temp_dir()?;
+		let db_snapshot_path = temp_dir.path().join(DB_ENTRY);
+		fs::copy(&library_db_path, &db_snapshot_path)
+			.await
+			.map_err(|e| {
+				FileIOError::from((
+					library_db_path,
+					e,
+					"Failed to copy library database file to do a backup",
+				))
+			})?;
+
+		spawn_blocking({
+			let bkp_path = bkp_path.clone();
+			move || {
+				let map_err = |context: &'static str| {
+					let bkp_path = bkp_path.clone();
+					move |e: io::Error| FileIOError::from((bkp_path, e, context))
+				};
+
+				// Regular tar.gz encoded data
+				let mut tar = tar::Builder::new(GzEncoder::new(
+					BufWriter::new(bkp_file),
+					Compression::default(),
+				));
+
+				tar.append_file(
+					CONFIG_ENTRY,
+					&mut std::fs::File::open(&library_config_path).map_err(|e| {
+						FileIOError::from((
+							library_config_path,
+							e,
+							"Failed to open library config file to do a backup",
+						))
+					})?,
+				)
+				.map_err(map_err(
+					"Failed to append library config file to out backup tar.gz file",
+				))?;
+
+				// The manifest goes before the database, so it can be read without decompressing it
+				if let Some((_, parent_manifest)) = parent {
+					let (delta, manifest) = PageDelta::diff(&db_snapshot_path, &parent_manifest)
+						.map_err(map_err("Failed to diff library database pages"))?;
+
+					debug!(
+						changed_pages = delta.pages.len(),
+						total_pages = manifest.hashes.len(),
+						"Incremental backup diffed;",
+					);
+
+					append_bytes(&mut tar, MANIFEST_ENTRY, &manifest.to_bytes())
+						.map_err(map_err("Failed to append page manifest to backup"))?;
+
+					let mut delta_bytes = vec![];
+					delta
+						.write(&mut delta_bytes)
+						.map_err(map_err("Failed to serialize database pages delta"))?;
+
+					append_bytes(&mut tar, DB_DELTA_ENTRY, &delta_bytes)
+						.map_err(map_err("Failed to append database pages delta to backup"))?;
+				} else {
+					let manifest = PageManifest::from_db(&db_snapshot_path)
+						.map_err(map_err("Failed to hash library database pages"))?;
+
+					append_bytes(&mut tar, MANIFEST_ENTRY, &manifest.to_bytes())
+						.map_err(map_err("Failed to append page manifest to backup"))?;
+
+					tar.append_file(
+						DB_ENTRY,
+						&mut std::fs::File::open(&db_snapshot_path).map_err(|e| {
+							FileIOError::from((
+								&db_snapshot_path,
+								e,
+								"Failed to open library database file to do a backup",
+							))
+						})?,
+					)
+					.map_err(map_err(
+						"Failed to append library database file to out backup tar.gz file",
+					))?;
+				}
+
+				tar.into_inner()
+					.and_then(GzEncoder::finish)
+					.and_then(|mut writer| writer.flush())
+					.map_err(map_err("Failed to finish backup file"))?;
+
+				Ok::<_, BackupError>(())
+			}
+		})
+		.await??;
+
+		Ok::<_, BackupError>(())
+	}
+	.await;
+
+	// A partial backup would break the incremental backups made on top of it
+	if res.is_err() {
+		if let Err(e) = fs::remove_file(&bkp_path).await {
+			warn!(path = %bkp_path.display(), ?e, "Failed to remove partial backup file;");
+		}
+	}
+
+	res.map(|()| bkp_path)
+}
+
+/// Latest backup of the library and its page manifest, if an incremental backup can be made on it
+async fn incremental_parent(
+	backups_dir: &Path,
+	library_id: Uuid,
+) -> Result<Option<(Header, PageManifest)>, BackupError> {
+	let backups = library_backups(backups_dir, library_id).await?;
+
+	let Some(latest) = backups.first() else {
+		return Ok(None);
+	};
+
+	if chain(&backups, &latest.header).map_or(true, |chain| chain.len() > MAX_INCREMENTAL_CHAIN) {
+		return Ok(None);
+	}
+
+	// Backups from before incremental backups existed don't have a manifest
+	Ok(read_manifest(&latest.path)
+		.await?
+		.map(|manifest| (latest.header.clone(), manifest)))
+}
+
+/// Backups of a library, from the latest to the oldest
+async fn library_backups(backups_dir: &Path, library_id: Uuid) -> Result<Vec<Backup>, BackupError> {
+	let mut backups = list_backups(backups_dir)
+		.await?
+		.into_iter()
+		.filter(|backup| backup.header.library_id == library_id)
+		.collect::<Vec<_>>();
+
+	backups.sort_by(|a, b| b.header.timestamp.cmp(&a.header.timestamp));
+
+	Ok(backups)
+}
+
+/// Backups needed to restore `header`, from its full backup to itself
+fn chain<'b>(backups: &'b [Backup], header: &Header) -> Result<Vec<&'b Backup>, BackupError> {
+	let by_id = backups
+		.iter()
+		.map(|backup| (backup.header.id, backup))
+		.collect::<HashMap<_, _>>();
+
+	let mut chain = vec![*by_id
+		.get(&header.id)
+		.ok_or(BackupError::MissingParent(header.id))?];
+
+	while let Some(parent_id) = chain[chain.len() - 1].header.parent_id {
+		// Guarding against cycles on tampered headers
+		if chain.len() > backups.len() {
+			return Err(BackupError::MalformedHeader);
+		}
+
+		chain.push(
+			by_id
+				.get(&parent_id)
+				.ok_or(BackupError::MissingParent(parent_id))?,
+		);
+	}
+
+	chain.reverse();
+
+	Ok(chain)
+}
+
+/// Opens a backup file, returning its header and the file positioned at the start of its archive
+async fn open_backup(path: &Path) -> Result<(Header, std::fs::File), BackupError> {
+	let mut file = File::open(path).await.map_err(|e| {
+		FileIOError::from((path, e, "Failed trying to open backup file to be restored"))
+	})?;
+
+	let header = Header::read(&mut file, path).await?;
+
+	Ok((header, file.into_std().await))
+}
+
+async fn read_manifest(path: &Path) -> Result<Option<PageManifest>, BackupError> {
+	let (_, file) = open_backup(path).await?;
+	let path = path.to_path_buf();
+
+	spawn_blocking(move || {
+		let map_err =
+			|e: io::Error| FileIOError::from((&path, e, "Failed to read backup page manifest"));
+
+		let mut archive = Archive::new(GzDecoder::new(io::BufReader::new(file)));
+
+		for entry in archive.entries().map_err(map_err)? {
+			let mut entry = entry.map_err(map_err)?;
+
+			if entry.path().map_err(map_err)?.as_ref() == Path::new(MANIFEST_ENTRY) {
+				let mut bytes = vec![];
+				entry.read_to_end(&mut bytes).map_err(map_err)?;
+				return Ok(Some(PageManifest::from_bytes(&bytes).map_err(map_err)?));
+			}
+		}
+
+		Ok(None)
+	})
+	.await?
+}
+
+pub async fn restore_backup(
+	node: &Arc<Node>,
+	path: impl AsRef<Path>,
+) -> Result<Header, BackupError> {
+	let path = path.as_ref();
+
+	let (header, _) = open_backup(path).await?;
+
+	// Incremental backups are restored by applying every backup in their chain, in order
+	let chain_paths = if header.parent_id.is_some() {
+		let backups = list_backups(path.parent().unwrap_or(path))
+			.await?
+			.into_iter()
+			.filter(|backup| backup.header.library_id == header.library_id)
+			.collect::<Vec<_>>();
+
+		chain(&backups, &header)?
+			.into_iter()
+			.map(|backup| backup.path.clone())
+			.collect::<Vec<_>>()
+	} else {
+		vec![path.to_path_buf()]
+	};
+
+	let temp_dir = temp_dir()?;
+	let temp_dir_path = temp_dir.path().to_path_buf();
+
+	let mut archives = Vec::with_capacity(chain_paths.len());
+	for backup_path in &chain_paths {
+		archives.push((backup_path.clone(), open_backup(backup_path).await?.1));
+	}
+
+	spawn_blocking(move || unpack_chain(archives, &temp_dir_path)).await??;
+
+	let libraries_dir = &node.libraries.libraries_dir;
+	let library_config_restored_path =
+		libraries_dir.join(format!("{}.sdlibrary", header.library_id));
+	let db_restored_path = libraries_dir.join(format!("{}.db", header.library_id));
+
+	// The restored files are staged next to the library ones, and only moved in place once the
+	// library's database is closed, which may only happen on the next start
+	replace_file(
+		&temp_dir.path().join(CONFIG_ENTRY),
+		&staged_restore_path(&library_config_restored_path),
+	)
+	.await
+	.map_err(|e| {
+		FileIOError::from((
+			&library_config_restored_path,
+			e,
+			"Failed to restore library config file from backup",
+		))
+	})?;
+
+	replace_file(
+		&temp_dir.path().join(DB_ENTRY),
+		&staged_restore_path(&db_restored_path),
+	)
+	.await
+	.map_err(|e| {
+		FileIOError::from((
+			&db_restored_path,
+			e,
+			"Failed to restore library database file from backup",
+		))
+	})?;
+
+	// Taking a safety snapshot of the current library before restoring over it
+	if let Some(library) = node.libraries.get_library(&header.library_id).await {
+		let snapshot_path = do_backup(Uuid::new_v4(), node, &library, BackupKind::Full).await?;
+
+		info!(
+			library_id = %library.id,
+			snapshot_path = %snapshot_path.display(),
+			"Took a snapshot of the library before restoring a backup over it;",
+		);
+
+		node.libraries.unload(&library.id).await?;
+
+		if !wait_for_database_close(library).await {
+			warn!(
+				library_id = %header.library_id,
+				"Library database is still in use, the backup will be restored on the next start;",
+			);
+
+			node.libraries
+				.load(
+					header.library_id,
+					&db_restored_path,
+					&library_config_restored_path,
+					None,
+					true,
+					node,
+				)
+				.await?;
+
+			invalidate_query!(node; node, "backups.getAll");
+
+			return Ok(header);
+		}
+	}
+
+	apply_pending_restore(libraries_dir, header.library_id).await?;
+
+	node.libraries
+		.load(
+			header.library_id,
+			db_restored_path,
+			library_config_restored_path,
+			None,
+			true,
+			node,
+		)
+		.await?;
+
+	invalidate_query!(node; node, "backups.getAll");
+
+	Ok(header)
+}
+
+/// Where a restored library file waits until it can replace `path`
+fn staged_restore_path(path: &Path) -> PathBuf {
+	let mut staged = path.as_os_str().to_owned();
+	staged.push(".restore");
+	PathBuf::from(staged)
+}
+
+/// Drops our handle to an unloaded library and waits for every other one to be dropped too, as its
+/// database connections are only closed then. Returns `false` if they're still open after a while.
+async fn wait_for_database_close(library: Arc<Library>) -> bool {
+	let db = Arc::downgrade(&library.db);
+	drop(library);
+
+	let start = Instant::now();
+
+	while db.strong_count() > 0 {
+		if start.elapsed() > DATABASE_CLOSE_TIMEOUT {
+			return false;
+		}
+
+		sleep(DATABASE_CLOSE_POLL_INTERVAL).await;
+	}
+
+	true
+}
+
+/// Moves the files of a restored backup, staged for the library, in place of the current ones.
+///
+/// Must only be called while the library's database is closed.
+pub(crate) async fn apply_pending_restore(
+	libraries_dir: &Path,
+	library_id: Uuid,
+) -> Result<(), FileIOError> {
+	let db_path = libraries_dir.join(format!("{library_id}.db"));
+	let staged_db_path = staged_restore_path(&db_path);
+
+	if fs::try_exists(&staged_db_path)
+		.await
+		.map_err(|e| FileIOError::from((&staged_db_path, e)))?
+	{
+		// The write-ahead log of the replaced database must never be applied to the restored one
+		for suffix in ["-wal", "-shm"] {
+			let mut log_path = db_path.as_os_str().to_owned();
+			log_path.push(suffix);
+			let log_path = PathBuf::from(log_path);
+
+			match fs::remove_file(&log_path).await {
+				Ok(()) => {}
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+				Err(e) => {
+					return Err(FileIOError::from((
+						&log_path,
+						e,
+						"Failed to remove write-ahead log of the replaced database",
+					)))
+				}
+			}
+		}
+
+		fs::rename(&staged_db_path, &db_path).await.map_err(|e| {
+			FileIOError::from((&db_path, e, "Failed to move restored database in place"))
+		})?;
+	}
+
+	let config_path = libraries_dir.join(format!("{library_id}.sdlibrary"));
+	let staged_config_path = staged_restore_path(&config_path);
+
+	if fs::try_exists(&staged_config_path)
+		.await
+		.map_err(|e| FileIOError::from((&staged_config_path, e)))?
+	{
+		fs::rename(&staged_config_path, &config_path)
+			.await
+			.map_err(|e| {
+				FileIOError::from((&config_path, e, "Failed to move restored config in place"))
+			})?;
+	}
+
+	info!(%library_id, "Restored library files from a backup;");
+
+	Ok(())
+}
+
+/// Rebuilds the library files from a full backup and the incremental ones made on top of it
+fn unpack_chain(
+	archives: Vec<(PathBuf, std::fs::File)>,
+	temp_dir_path: &Path,
+) -> Result<(), BackupError> {
+	let db_path = temp_dir_path.join(DB_ENTRY);
+	let mut last_manifest = None;
+
+	for (path, file) in archives {
+		let map_err = |context: &'static str| {
+			let path = path.clone();
+			move |e: io::Error| FileIOError::from((path, e, context))
+		};
+
+		let mut archive = Archive::new(GzDecoder::new(io::BufReader::new(file)));
+
+		for entry in archive
+			.entries()
+			.map_err(map_err("Failed to unpack backup compressed data"))?
+		{
+			let mut entry = entry.map_err(map_err("Failed to unpack backup compressed data"))?;
+			let entry_path = entry
+				.path()
+				.map_err(map_err("Failed to unpack backup compressed data"))?
+				.into_owned();
+
+			if entry_path == Path::new(DB_DELTA_ENTRY) {
+				PageDelta::read(&mut entry)
+					.and_then(|delta| delta.apply(&db_path))
+					.map_err(map_err("Failed to apply database pages delta"))?;
+			} else if entry_path == Path::new(MANIFEST_ENTRY) {
+				let mut bytes = vec![];
+				entry
+					.read_to_end(&mut bytes)
+					.map_err(map_err("Failed to read backup page manifest"))?;
+				last_manifest = Some(
+					PageManifest::from_bytes(&bytes)
+						.map_err(map_err("Failed to read backup page manifest"))?,
+				);
+			} else {
+				entry
+					.unpack_in(temp_dir_path)
+					.map_err(map_err("Failed to unpack backup compressed data"))?;
+			}
+		}
+	}
+
+	if !db_path.exists() {
+		return Err(BackupError::MissingEntry(DB_ENTRY));
+	}
+
+	if !temp_dir_path.join(CONFIG_ENTRY).exists() {
+		return Err(BackupError::MissingEntry(CONFIG_ENTRY));
+	}
+
+	// Making sure the rebuilt database is exactly the one that was backed up
+	if let Some(manifest) = last_manifest {
+		if PageManifest::from_db(&db_path)
+			.map_err(|e| FileIOError::from((&db_path, e, "Failed to hash restored database")))?
+			!= manifest
+		{
+			return Err(BackupError::IntegrityCheck);
+		}
+	}
+
+	Ok(())
+}
+
+/// Checks a backup header, that its archive decompresses and that its database matches the
+/// page manifest, without restoring anything
+pub async fn verify_backup(path: impl AsRef<Path>) -> Result<Verification, BackupError> {
+	let path = path.as_ref();
+
+	let (header, file) = open_backup(path).await?;
+
+	let mut problems = vec![];
+
+	if let Some(parent_id) = header.parent_id {
+		let parent_found = list_backups(path.parent().unwrap_or(path))
+			.await?
+			.iter()
+			.any(|backup| backup.header.id == parent_id);
+
+		if !parent_found {
+			problems.push(format!("parent backup {parent_id} not found"));
+		}
+	}
+
+	let kind = header.kind;
+
+	let (entries, archive_problems) = spawn_blocking(move || verify_archive(file, kind)).await?;
+	problems.extend(archive_problems);
+
+	Ok(Verification {
+		header,
+		entries,
+		problems,
+	})
+}
+
+fn verify_archive(file: std::fs::File, kind: BackupKind) -> (Vec<String>, Vec<String>) {
+	let mut entries = vec![];
+	let mut problems = vec![];
+
+	let mut manifest = None;
+	let mut db_manifest = None;
+	let mut delta_len = None;
+
+	let mut archive = Archive::new(GzDecoder::new(io::BufReader::new(file)));
+
+	// The gzip decoder checks the CRC at the end of the stream, so reading every entry to its end
+	// is enough to find corrupted archives
+	let result = (|| {
+		for entry in archive.entries()? {
+			let mut entry = entry?;
+			let entry_path = entry.path()?.to_string_lossy().to_string();
+
+			match entry_path.as_str() {
+				MANIFEST_ENTRY => {
+					let mut bytes = vec![];
+					entry.read_to_end(&mut bytes)?;
+					manifest = Some(PageManifest::from_bytes(&bytes)?);
+				}
+				DB_ENTRY => db_manifest = Some(PageManifest::from_reader(&mut entry)?),
+				DB_DELTA_ENTRY => {
+					delta_len = Some(PageDelta::read(&mut entry)?.db_len);
+					io::copy(&mut entry, &mut io::sink())?;
+				}
+				_ => {
+					io::copy(&mut entry, &mut io::sink())?;
+				}
+			}
+
+			entries.push(entry_path);
+		}
+
+		Ok::<_, io::Error>(())
+	})()
+	// Reading any leftover bytes, so the gzip trailer is checked too
+	.and_then(|()| io::copy(&mut archive.into_inner(), &mut io::sink()).map(|_| ()));
+
+	if let Err(e) = result {
+		problems.push(format!("archive is corrupted: {e}"));
+		return (entries, problems);
+	}
+
+	let required = match kind {
+		BackupKind::Full => [CONFIG_ENTRY, DB_ENTRY],
+		BackupKind::Incremental => [CONFIG_ENTRY, DB_DELTA_ENTRY],
+	};
+
+	for entry in required {
+		if !entries.iter().any(|name| name == entry) {
+			problems.push(format!("missing '{entry}' entry"));
+		}
+	}
+
+	if let Some(manifest) = &manifest {
+		if db_manifest.is_some_and(|db_manifest| &db_manifest != manifest) {
+			problems.push("database doesn't match its page manifest".to_string());
+		}
+
+		if delta_len.is_some_and(|db_len| db_len != manifest.db_len) {
+			problems.push("database pages delta doesn't match its page manifest".to_string());
+		}
+	}
+
+	(entries, problems)
+}
+
+/// Deletes the backups of a library that fall outside of the retention policy
+pub async fn apply_retention(
+	node: &Node,
+	library_id: Uuid,
+	policy: RetentionPolicy,
+) -> Result<(), BackupError> {
+	if !policy.is_enabled() {
+		return Ok(());
+	}
+
+	let backups = library_backups(&backups_dir(node), library_id).await?;
+
+	let headers = backups
+		.iter()
+		.map(|backup| backup.header.clone())
+		.collect::<Vec<_>>();
+
+	for header in policy.backups_to_remove(&headers) {
+		let Some(backup) = backups.iter().find(|backup| backup.header.id == header.id) else {
+			continue;
+		};
+
+		debug!(
+			backup_id = %header.id,
+			%library_id,
+			"Removing backup outside of the retention policy;",
+		);
+
+		fs::remove_file(&backup.path)
+			.await
+			.map_err(|e| FileIOError::from((&backup.path, e, "Failed to remove expired backup")))?;
+	}
+
+	Ok(())
+}
+
+/// Periodically makes incremental backups of every library, following the node backup preferences
+pub fn start_scheduler(node: &Arc<Node>) {
+	let node = Arc::clone(node);
+
+	spawn(async move {
+		let mut interval = interval(SCHEDULER_TICK);
+
+		loop {
+			interval.tick().await;
+
+			let preferences = node.config.get().await.preferences.backups;
+
+			let Some(every) = preferences.interval() else {
+				continue;
+			};
+
+			for library in node.libraries.get_all().await {
+				if let Err(e) =
+					scheduled_backup(&node, &library, every, preferences.retention).await
+				{
+					error!(library_id = %library.id, ?e, "Failed to do scheduled backup;");
+				}
+			}
+		}
+	});
+}
+
+async fn scheduled_backup(
+	node: &Node,
+	library: &Library,
+	every: Duration,
+	retention: RetentionPolicy,
+) -> Result<(), BackupError> {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.expect("Time went backwards")
+		.as_millis();
+
+	let is_due = library_backups(&backups_dir(node), library.id)
+		.await?
+		.first()
+		.map_or(true, |latest| {
+			now.saturating_sub(latest.header.timestamp) >= every.as_millis()
+		});
+
+	if !is_due {
+		return Ok(());
+	}
+
+	let path = do_backup(Uuid::new_v4(), node, library, BackupKind::Incremental).await?;
+
+	info!(
+		library_id = %library.id,
+		path = %path.display(),
+		"Scheduled backup created;",
+	);
+
+	apply_retention(node, library.id, retention).await?;
+
+	invalidate_query!(library, "backups.getAll");
+
+	Ok(())
+}
+
+fn temp_dir() -> Result<tempfile::TempDir, FileIOError> {
+	tempdir().map_err(|e| {
+		FileIOError::from((
+			"/tmp",
+			e,
+			"Failed to get a temporary directory to handle backup",
+		))
+	})
+}
+
+fn append_bytes(
+	tar: &mut tar::Builder<impl Write>,
+	name: &str,
+	bytes: &[u8],
+) -> Result<(), io::Error> {
+	let mut header = tar::Header::new_gnu();
+	header.set_size(bytes.len() as u64);
+	header.set_mode(0o644);
+
+	tar.append_data(&mut header, name, bytes)
+}
+
+/// Copies the file with a single rename at the end, so `target` is never left partially written
+async fn replace_file(source: &Path, target: &Path) -> Result<(), io::Error> {
+	let mut temp_target = target.as_os_str().to_owned();
+	temp_target.push(".restoring");
+	let temp_target = PathBuf::from(temp_target);
+
+	fs::copy(source, &temp_target).await?;
+	if let Err(e) = fs::rename(&temp_target, target).await {
+		if let Err(e) = fs::remove_file(&temp_target).await {
+			warn!(path = %temp_target.display(), ?e, "Failed to remove partially restored file;");
+		}
+		return Err(e);
+	}
+
+	Ok(())
+}
@@ -0,0 +1,299 @@
+//! Incremental backups only store the database pages changed since the previous backup.
+//!
+//! Every backup stores a [`PageManifest`] with the hash of each page of the library database,
+//! so the next incremental backup can be diffed against it without reading the previous backup's
+//! database, and restoring one applies its [`PageDelta`] on top of the database of its parent.
+
+use std::{
+	fs::{File, OpenOptions},
+	io::{self, BufReader, Read, Seek, SeekFrom, Write},
+	path::Path,
+};
+
+/// Size of the SQLite database header, which holds the page size
+const SQLITE_HEADER_LEN: usize = 100;
+const HASH_LEN: usize = blake3::OUT_LEN;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageManifest {
+	pub page_size: u32,
+	pub db_len: u64,
+	pub hashes: Vec<[u8; HASH_LEN]>,
+}
+
+/// Pages changed between two versions of a database, along with the new database length
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageDelta {
+	pub page_size: u32,
+	pub db_len: u64,
+	pub pages: Vec<(u64, Vec<u8>)>,
+}
+
+impl PageManifest {
+	pub fn from_db(db_path: impl AsRef<Path>) -> Result<Self, io::Error> {
+		Self::from_reader(BufReader::new(File::open(db_path)?))
+	}
+
+	pub fn from_reader(db: impl Read) -> Result<Self, io::Error> {
+		let mut manifest = Self {
+			page_size: 0,
+			db_len: 0,
+			hashes: vec![],
+		};
+
+		for_each_page(db, |page_size, _, page| {
+			manifest.page_size = page_size;
+			manifest.db_len += page.len() as u64;
+			manifest.hashes.push(*blake3::hash(page).as_bytes());
+		})?;
+
+		Ok(manifest)
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(4 + 8 + self.hashes.len() * HASH_LEN);
+		bytes.extend_from_slice(&self.page_size.to_le_bytes());
+		bytes.extend_from_slice(&self.db_len.to_le_bytes());
+		self.hashes
+			.iter()
+			.for_each(|hash| bytes.extend_from_slice(hash));
+
+		bytes
+	}
+
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, io::Error> {
+		if bytes.len() < 12 || (bytes.len() - 12) % HASH_LEN != 0 {
+			return Err(invalid_data("malformed page manifest"));
+		}
+
+		Ok(Self {
+			page_size: u32::from_le_bytes(bytes[..4].try_into().expect("checked length")),
+			db_len: u64::from_le_bytes(bytes[4..12].try_into().expect("checked length")),
+			hashes: bytes[12..]
+				.chunks_exact(HASH_LEN)
+				.map(|hash| hash.try_into().expect("chunks have the hash length"))
+				.collect(),
+		})
+	}
+}
+
+impl PageDelta {
+	/// Collects the pages of the database that changed since `previous`, returning them along
+	/// with the manifest of the database as it is now
+	pub fn diff(
+		db_path: impl AsRef<Path>,
+		previous: &PageManifest,
+	) -> Result<(Self, PageManifest), io::Error> {
+		let mut delta = Self {
+			page_size: 0,
+			db_len: 0,
+			pages: vec![],
+		};
+		let mut manifest = PageManifest {
+			page_size: 0,
+			db_len: 0,
+			hashes: vec![],
+		};
+
+		for_each_page(
+			BufReader::new(File::open(db_path)?),
+			|page_size, index, page| {
+				let hash = *blake3::hash(page).as_bytes();
+
+				// A different page size changes the offset of every page, so all of them are stored
+				if page_size != previous.page_size
+					|| previous.hashes.get(index as usize) != Some(&hash)
+				{
+					delta.pages.push((index, page.to_vec()));
+				}
+
+				manifest.page_size = page_size;
+				manifest.db_len += page.len() as u64;
+				manifest.hashes.push(hash);
+			},
+		)?;
+
+		delta.page_size = manifest.page_size;
+		delta.db_len = manifest.db_len;
+
+		Ok((delta, manifest))
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> Result<(), io::Error> {
+		writer.write_all(&self.page_size.to_le_bytes())?;
+		writer.write_all(&self.db_len.to_le_bytes())?;
+		writer.write_all(&(self.pages.len() as u64).to_le_bytes())?;
+
+		for (index, page) in &self.pages {
+			writer.write_all(&index.to_le_bytes())?;
+			writer.write_all(&(page.len() as u32).to_le_bytes())?;
+			writer.write_all(page)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn read(reader: &mut impl Read) -> Result<Self, io::Error> {
+		let page_size = read_u32(reader)?;
+		let db_len = read_u64(reader)?;
+		let pages_count = read_u64(reader)?;
+
+		let mut pages = vec![];
+		for _ in 0..pages_count {
+			let index = read_u64(reader)?;
+			let len = read_u32(reader)?;
+
+			if len > page_size {
+				return Err(invalid_data("page delta entry larger than the page size"));
+			}
+
+			let mut page = vec![0; len as usize];
+			reader.read_exact(&mut page)?;
+			pages.push((index, page));
+		}
+
+		Ok(Self {
+			page_size,
+			db_len,
+			pages,
+		})
+	}
+
+	/// Writes the changed pages over the database of the parent backup
+	pub fn apply(&self, db_path: impl AsRef<Path>) -> Result<(), io::Error> {
+		let mut db = OpenOptions::new().write(true).open(db_path)?;
+
+		for (index, page) in &self.pages {
+			db.seek(SeekFrom::Start(index * u64::from(self.page_size)))?;
+			db.write_all(page)?;
+		}
+
+		db.set_len(self.db_len)?;
+		db.sync_all()
+	}
+}
+
+/// Reads the database page by page, passing the page size, index and contents of each one
+fn for_each_page(mut db: impl Read, mut f: impl FnMut(u32, u64, &[u8])) -> Result<(), io::Error> {
+	let mut header = [0u8; SQLITE_HEADER_LEN];
+	match read_up_to(&mut db, &mut header)? {
+		// An empty database doesn't have pages yet
+		0 => return Ok(()),
+		SQLITE_HEADER_LEN => {}
+		_ => return Err(invalid_data("truncated SQLite header")),
+	}
+
+	let page_size = page_size(&header)?;
+
+	let mut page = vec![0; page_size as usize];
+	page[..SQLITE_HEADER_LEN].copy_from_slice(&header);
+	let mut read = SQLITE_HEADER_LEN + read_up_to(&mut db, &mut page[SQLITE_HEADER_LEN..])?;
+	let mut index = 0;
+
+	while read > 0 {
+		f(page_size, index, &page[..read]);
+		index += 1;
+		read = read_up_to(&mut db, &mut page)?;
+	}
+
+	Ok(())
+}
+
+/// The page size is a big-endian u16 at offset 16, where 1 stands for 65536
+fn page_size(header: &[u8; SQLITE_HEADER_LEN]) -> Result<u32, io::Error> {
+	if &header[..16] != b"SQLite format 3\0" {
+		return Err(invalid_data("not a SQLite database"));
+	}
+
+	let page_size = match u16::from_be_bytes([header[16], header[17]]) {
+		1 => 65536,
+		page_size => u32::from(page_size),
+	};
+
+	if !(512..=65536).contains(&page_size) || !page_size.is_power_of_two() {
+		return Err(invalid_data("invalid SQLite page size"));
+	}
+
+	Ok(page_size)
+}
+
+/// Like [`Read::read_exact`], but stopping early at the end of the file
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize, io::Error> {
+	let mut read = 0;
+
+	while read < buf.len() {
+		match reader.read(&mut buf[read..]) {
+			Ok(0) => break,
+			Ok(n) => read += n,
+			Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+			Err(e) => return Err(e),
+		}
+	}
+
+	Ok(read)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, io::Error> {
+	let mut bytes = [0u8; 4];
+	reader.read_exact(&mut bytes)?;
+	Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, io::Error> {
+	let mut bytes = [0u8; 8];
+	reader.read_exact(&mut bytes)?;
+	Ok(u64::from_le_bytes(bytes))
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PAGE_SIZE: usize = 512;
+
+	fn fake_db(pages: &[u8]) -> Vec<u8> {
+		let mut db = pages
+			.iter()
+			.flat_map(|&fill| vec![fill; PAGE_SIZE])
+			.collect::<Vec<_>>();
+		db[..16].copy_from_slice(b"SQLite format 3\0");
+		db[16..18].copy_from_slice(&(PAGE_SIZE as u16).to_be_bytes());
+		db
+	}
+
+	#[test]
+	fn test_delta_round_trip() {
+		let dir = tempfile::tempdir().unwrap();
+		let db_path = dir.path().join("library.db");
+		let restored_path = dir.path().join("restored.db");
+
+		let old_db = fake_db(&[0, 1, 2, 3]);
+		std::fs::write(&db_path, &old_db).unwrap();
+		std::fs::write(&restored_path, &old_db).unwrap();
+		let old_manifest = PageManifest::from_db(&db_path).unwrap();
+
+		// Changing one page and shrinking the database by another
+		std::fs::write(&db_path, fake_db(&[0, 9, 2])).unwrap();
+
+		let (delta, manifest) = PageDelta::diff(&db_path, &old_manifest).unwrap();
+		assert_eq!(delta.pages.len(), 1);
+		assert_eq!(delta.pages[0].0, 1);
+
+		let mut bytes = vec![];
+		delta.write(&mut bytes).unwrap();
+		let read_delta = PageDelta::read(&mut bytes.as_slice()).unwrap();
+		assert_eq!(read_delta, delta);
+
+		read_delta.apply(&restored_path).unwrap();
+		assert_eq!(std::fs::read(&restored_path).unwrap(), fake_db(&[0, 9, 2]));
+		assert_eq!(PageManifest::from_db(&restored_path).unwrap(), manifest);
+		assert_eq!(
+			PageManifest::from_bytes(&manifest.to_bytes()).unwrap(),
+			manifest
+		);
+	}
+}
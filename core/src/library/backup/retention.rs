@@ -0,0 +1,168 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::Header;
+
+/// How many backups of each library are kept, older ones being deleted after each backup.
+///
+/// The latest backup of each of the last `keep_daily` days and `keep_weekly` weeks with backups is
+/// kept, along with every backup that an incremental one being kept was made on top of.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct RetentionPolicy {
+	#[serde(default)]
+	pub keep_daily: Option<u32>,
+	#[serde(default)]
+	pub keep_weekly: Option<u32>,
+}
+
+impl RetentionPolicy {
+	pub const fn is_enabled(&self) -> bool {
+		self.keep_daily.is_some() || self.keep_weekly.is_some()
+	}
+
+	/// Picks the backups that must be deleted, all of them from the same library
+	pub fn backups_to_remove<'h>(&self, backups: &'h [Header]) -> Vec<&'h Header> {
+		if !self.is_enabled() || backups.is_empty() {
+			return vec![];
+		}
+
+		let mut sorted = backups.iter().collect::<Vec<_>>();
+		sorted.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+		// The latest backup is always kept, whatever the policy says
+		let mut keep = HashSet::from([sorted[0].id]);
+
+		let mut keep_latest_per_period =
+			|max_periods: Option<u32>, period: fn(NaiveDate) -> i64| {
+				let Some(max_periods) = max_periods else {
+					return;
+				};
+
+				let mut periods = HashSet::new();
+
+				for backup in &sorted {
+					let Some(date) = date(backup) else {
+						continue;
+					};
+
+					if periods.len() < max_periods as usize && periods.insert(period(date)) {
+						keep.insert(backup.id);
+					}
+				}
+			};
+
+		keep_latest_per_period(self.keep_daily, |date| i64::from(date.num_days_from_ce()));
+		keep_latest_per_period(self.keep_weekly, |date| {
+			let week = date.iso_week();
+			i64::from(week.year()) * 100 + i64::from(week.week())
+		});
+
+		// Incremental backups can't be restored without the chain of backups they were made on
+		let by_id = backups
+			.iter()
+			.map(|backup| (backup.id, backup))
+			.collect::<HashMap<_, _>>();
+
+		for id in keep.clone() {
+			let mut parent_id = by_id.get(&id).and_then(|backup| backup.parent_id);
+			while let Some(id) = parent_id {
+				if !keep.insert(id) {
+					break;
+				}
+				parent_id = by_id.get(&id).and_then(|backup| backup.parent_id);
+			}
+		}
+
+		sorted
+			.into_iter()
+			.filter(|backup| !keep.contains(&backup.id))
+			.collect()
+	}
+}
+
+fn date(backup: &Header) -> Option<NaiveDate> {
+	DateTime::from_timestamp_millis(i64::try_from(backup.timestamp).ok()?)
+		.map(|date_time| date_time.date_naive())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::BackupKind;
+	use super::*;
+
+	use uuid::Uuid;
+
+	const DAY_MS: u128 = 24 * 60 * 60 * 1000;
+
+	fn backup(day: u128, parent: Option<&Header>) -> Header {
+		Header {
+			id: Uuid::new_v4(),
+			// 2024-01-01, a Monday
+			timestamp: 1_704_067_200_000 + day * DAY_MS,
+			library_id: Uuid::nil(),
+			library_name: "Test Library".to_string(),
+			kind: if parent.is_some() {
+				BackupKind::Incremental
+			} else {
+				BackupKind::Full
+			},
+			parent_id: parent.map(|parent| parent.id),
+		}
+	}
+
+	#[test]
+	fn test_keep_daily_and_weekly() {
+		let backups = (0..21).map(|day| backup(day, None)).collect::<Vec<_>>();
+
+		let policy = RetentionPolicy {
+			keep_daily: Some(3),
+			keep_weekly: Some(3),
+		};
+
+		let removed = policy
+			.backups_to_remove(&backups)
+			.into_iter()
+			.map(|backup| backup.id)
+			.collect::<HashSet<_>>();
+
+		// Days 20, 19 and 18 as daily ones, plus the last day of the two previous weeks
+		let kept = backups
+			.iter()
+			.filter(|backup| !removed.contains(&backup.id))
+			.map(|backup| (backup.timestamp - backups[0].timestamp) / DAY_MS)
+			.collect::<HashSet<_>>();
+
+		assert_eq!(kept, HashSet::from([20, 19, 18, 13, 6]));
+	}
+
+	#[test]
+	fn test_keep_incremental_parents() {
+		let full = backup(0, None);
+		let first = backup(10, Some(&full));
+		let second = backup(20, Some(&first));
+		let unrelated = backup(5, None);
+
+		let backups = vec![full, unrelated.clone(), first, second];
+
+		let policy = RetentionPolicy {
+			keep_daily: Some(1),
+			keep_weekly: None,
+		};
+
+		let removed = policy.backups_to_remove(&backups);
+
+		assert_eq!(removed, vec![&unrelated]);
+	}
+
+	#[test]
+	fn test_disabled_policy_keeps_everything() {
+		let backups = (0..5).map(|day| backup(day, None)).collect::<Vec<_>>();
+
+		assert!(RetentionPolicy::default()
+			.backups_to_remove(&backups)
+			.is_empty());
+	}
+}
@@ -0,0 +1,233 @@
+use sd_crypto::{
+	container::{Kdf, RootKey, Salt},
+	CryptoRng, Protected,
+};
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::{fs, io, sync::RwLock, task::spawn_blocking};
+use tracing::debug;
+use uuid::Uuid;
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeyringError {
+	#[error("key not found: <id='{0}'>")]
+	NotFound(Uuid),
+	#[error("key is locked, unlock it with its passphrase first: <id='{0}'>")]
+	Locked(Uuid),
+	#[error("incorrect passphrase")]
+	WrongPassphrase,
+	#[error("stored key hash is invalid: <id='{0}'>")]
+	InvalidHash(Uuid),
+	#[error("key derivation task failed: {0}")]
+	Join(#[from] tokio::task::JoinError),
+	#[error(transparent)]
+	Crypto(#[from] sd_crypto::Error),
+	#[error("error serializing or deserializing the keyring file: {0}")]
+	Json(#[from] serde_json::Error),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+}
+
+impl From<KeyringError> for rspc::Error {
+	fn from(e: KeyringError) -> Self {
+		let code = match e {
+			KeyringError::NotFound(_) => rspc::ErrorCode::NotFound,
+			KeyringError::Locked(_) | KeyringError::WrongPassphrase => rspc::ErrorCode::Forbidden,
+			_ => rspc::ErrorCode::InternalServerError,
+		};
+
+		Self::with_cause(code, e.to_string(), e)
+	}
+}
+
+/// A key as persisted in the keyring file, only the parameters needed to derive it again
+/// from its passphrase and a hash to check the result are stored, never the key itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredKey {
+	id: Uuid,
+	name: String,
+	date_created: DateTime<Utc>,
+	kdf: Kdf,
+	salt: Salt,
+	key_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct KeyInfo {
+	pub id: Uuid,
+	pub name: String,
+	pub date_created: DateTime<Utc>,
+	pub unlocked: bool,
+}
+
+/// Passphrase based keys of a library, used to encrypt and decrypt files into containers.
+///
+/// Keys are stored in `<library_id>.sdkeyring` next to the library database, and are only kept
+/// in memory after being unlocked with their passphrase, until the node shuts down or they are locked again.
+#[derive(Debug)]
+pub struct Keyring {
+	path: PathBuf,
+	stored: RwLock<Vec<StoredKey>>,
+	unlocked: RwLock<HashMap<Uuid, Arc<RootKey>>>,
+}
+
+impl Keyring {
+	pub async fn load(path: impl AsRef<Path>) -> Result<Self, KeyringError> {
+		let path = path.as_ref();
+
+		let stored = match fs::read(path).await {
+			Ok(bytes) => serde_json::from_slice(&bytes)?,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+			Err(e) => return Err(FileIOError::from((path, e)).into()),
+		};
+
+		Ok(Self {
+			path: path.to_path_buf(),
+			stored: RwLock::new(stored),
+			unlocked: RwLock::default(),
+		})
+	}
+
+	pub async fn list(&self) -> Vec<KeyInfo> {
+		let unlocked = self.unlocked.read().await;
+
+		self.stored
+			.read()
+			.await
+			.iter()
+			.map(|key| KeyInfo {
+				id: key.id,
+				name: key.name.clone(),
+				date_created: key.date_created,
+				unlocked: unlocked.contains_key(&key.id),
+			})
+			.collect()
+	}
+
+	/// Create a new key derived from `passphrase`, it is left unlocked
+	pub async fn add(
+		&self,
+		name: String,
+		passphrase: Protected<Vec<u8>>,
+	) -> Result<Uuid, KeyringError> {
+		let salt = Salt::generate(&mut CryptoRng::new()?);
+		let kdf = Kdf::default();
+
+		let root_key = derive(passphrase, kdf, salt).await?;
+
+		let id = Uuid::new_v4();
+
+		let mut stored = self.stored.write().await;
+		stored.push(StoredKey {
+			id,
+			name,
+			date_created: Utc::now(),
+			kdf,
+			salt,
+			key_hash: root_key.to_hash().to_hex().to_string(),
+		});
+
+		if let Err(e) = self.save(&stored).await {
+			stored.pop();
+			return Err(e);
+		}
+
+		self.unlocked.write().await.insert(id, Arc::new(root_key));
+
+		debug!(%id, "Added new key to keyring;");
+
+		Ok(id)
+	}
+
+	pub async fn unlock(
+		&self,
+		id: Uuid,
+		passphrase: Protected<Vec<u8>>,
+	) -> Result<(), KeyringError> {
+		let StoredKey {
+			kdf,
+			salt,
+			key_hash,
+			..
+		} = self
+			.stored
+			.read()
+			.await
+			.iter()
+			.find(|key| key.id == id)
+			.cloned()
+			.ok_or(KeyringError::NotFound(id))?;
+
+		let expected_hash =
+			blake3::Hash::from_hex(key_hash).map_err(|_| KeyringError::InvalidHash(id))?;
+
+		let root_key = derive(passphrase, kdf, salt).await?;
+
+		// `blake3::Hash` equality is constant time
+		if root_key.to_hash() != expected_hash {
+			return Err(KeyringError::WrongPassphrase);
+		}
+
+		self.unlocked.write().await.insert(id, Arc::new(root_key));
+
+		Ok(())
+	}
+
+	pub async fn lock(&self, id: Uuid) {
+		self.unlocked.write().await.remove(&id);
+	}
+
+	pub async fn delete(&self, id: Uuid) -> Result<(), KeyringError> {
+		let mut stored = self.stored.write().await;
+
+		let len = stored.len();
+		stored.retain(|key| key.id != id);
+		if stored.len() == len {
+			return Err(KeyringError::NotFound(id));
+		}
+
+		self.save(&stored).await?;
+		self.unlocked.write().await.remove(&id);
+
+		Ok(())
+	}
+
+	/// Get an unlocked key, to be handed over to encryption and decryption jobs
+	pub async fn get(&self, id: Uuid) -> Result<Arc<RootKey>, KeyringError> {
+		if let Some(root_key) = self.unlocked.read().await.get(&id) {
+			return Ok(Arc::clone(root_key));
+		}
+
+		if self.stored.read().await.iter().any(|key| key.id == id) {
+			Err(KeyringError::Locked(id))
+		} else {
+			Err(KeyringError::NotFound(id))
+		}
+	}
+
+	async fn save(&self, stored: &[StoredKey]) -> Result<(), KeyringError> {
+		fs::write(&self.path, serde_json::to_vec(stored)?)
+			.await
+			.map_err(|e| FileIOError::from((&self.path, e)).into())
+	}
+}
+
+/// Key derivation is purposely slow and memory hungry, so we keep it out of the async runtime
+async fn derive(
+	passphrase: Protected<Vec<u8>>,
+	kdf: Kdf,
+	salt: Salt,
+) -> Result<RootKey, KeyringError> {
+	spawn_blocking(move || RootKey::derive(&passphrase, kdf, salt))
+		.await?
+		.map_err(Into::into)
+}
@@ -4,8 +4,8 @@ use sd_core_file_path_helper::IsolatedFilePathData;
 use sd_core_heavy_lifting::media_processor::ThumbnailKind;
 use sd_core_prisma_helpers::{file_path_to_full_path, CasId};
 
-use sd_p2p::Identity;
-use sd_prisma::prisma::{file_path, location, PrismaClient};
+use sd_p2p::{Identity, RemoteIdentity};
+use sd_prisma::prisma::{file_path, instance, location, PrismaClient};
 use sd_utils::{db::maybe_missing, error::FileIOError};
 
 use std::{
@@ -19,7 +19,7 @@ use tokio::{fs, io, sync::broadcast, sync::RwLock};
 use tracing::warn;
 use uuid::Uuid;
 
-use super::{LibraryConfig, LibraryManagerError};
+use super::{Keyring, LibraryConfig, LibraryManagerError};
 
 // TODO: Finish this
 // pub enum LibraryNew {
@@ -39,11 +39,10 @@ pub struct Library {
 	pub db: Arc<PrismaClient>,
 	pub sync: Arc<sync::Manager>,
 	pub cloud: cloud::State,
-	/// key manager that provides encryption keys to functions that require them
-	// pub key_manager: Arc<KeyManager>,
+	/// keyring that provides encryption keys to functions that require them
+	pub keyring: Arc<Keyring>,
 	/// p2p identity
 	pub identity: Arc<Identity>,
-	// pub orphan_remover: OrphanRemoverActor,
 	// The UUID which matches `config.instance_id`'s primary key.
 	pub instance_uuid: Uuid,
 
@@ -77,6 +76,7 @@ impl Library {
 		config: LibraryConfig,
 		instance_uuid: Uuid,
 		identity: Arc<Identity>,
+		keyring: Arc<Keyring>,
 		db: Arc<PrismaClient>,
 		node: &Arc<Node>,
 		sync: Arc<sync::Manager>,
@@ -90,9 +90,8 @@ impl Library {
 			sync,
 			cloud,
 			db: db.clone(),
-			// key_manager,
+			keyring,
 			identity,
-			// orphan_remover: OrphanRemoverActor::spawn(db),
 			instance_uuid,
 			do_cloud_sync,
 			env: node.env.clone(),
@@ -184,6 +183,30 @@ impl Library {
 		Ok(out)
 	}
 
+	/// Checks if the received identity belongs to one of the instances of this library
+	pub async fn has_instance(&self, identity: &RemoteIdentity) -> bool {
+		self.db
+			.instance()
+			.count(vec![instance::remote_identity::equals(
+				identity.get_bytes().to_vec(),
+			)])
+			.exec()
+			.await
+			.map_or(false, |count| count > 0)
+	}
+
+	/// Checks if the received node identity belongs to the node of one of the instances of this library
+	pub async fn has_node(&self, identity: &RemoteIdentity) -> bool {
+		self.db
+			.instance()
+			.count(vec![instance::node_remote_identity::equals(Some(
+				identity.get_bytes().to_vec(),
+			))])
+			.exec()
+			.await
+			.map_or(false, |count| count > 0)
+	}
+
 	pub fn do_cloud_sync(&self) {
 		if let Err(e) = self.do_cloud_sync.send(()) {
 			warn!(?e, "Error sending cloud resync message;");
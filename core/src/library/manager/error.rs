@@ -1,4 +1,7 @@
-use crate::{library::LibraryConfigError, location::LocationManagerError};
+use crate::{
+	library::{KeyringError, LibraryConfigError},
+	location::LocationManagerError,
+};
 
 use sd_core_indexer_rules::seed::SeederError;
 
@@ -23,8 +26,8 @@ pub enum LibraryManagerError {
 	Uuid(#[from] uuid::Error),
 	#[error("failed to run indexer rules seeder: {0}")]
 	IndexerRulesSeeder(#[from] SeederError),
-	// #[error("failed to initialize the key manager: {0}")]
-	// KeyManager(#[from] sd_crypto::Error),
+	#[error("failed to load the keyring: {0}")]
+	Keyring(#[from] KeyringError),
 	#[error("error migrating the library: {0}")]
 	MigrationError(#[from] db::MigrationError),
 	#[error("invalid library configuration: {0}")]
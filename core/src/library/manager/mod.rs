@@ -1,5 +1,4 @@
 use crate::{
-	api::{utils::InvalidateOperationEvent, CoreEvent},
 	cloud, invalidate_query,
 	location::metadata::{LocationMetadataError, SpacedriveLocationMetadataFile},
 	object::tag,
@@ -8,9 +7,12 @@ use crate::{
 	Node,
 };
 
-use sd_core_sync::SyncMessage;
+use sd_core_sync::{SyncMessage, WrittenRecords};
 use sd_p2p::{Identity, RemoteIdentity};
-use sd_prisma::prisma::{instance, location};
+use sd_prisma::{
+	prisma::{instance, location},
+	prisma_sync,
+};
 use sd_utils::{
 	db,
 	error::{FileIOError, NonUtf8PathError},
@@ -34,10 +36,10 @@ use tokio::{
 	sync::{broadcast, RwLock},
 	time::sleep,
 };
-use tracing::{debug, error, info, instrument, warn};
+use tracing::{debug, error, info, instrument, trace, warn};
 use uuid::Uuid;
 
-use super::{Library, LibraryConfig, LibraryName};
+use super::{backup, Keyring, Library, LibraryConfig, LibraryName};
 
 mod error;
 
@@ -118,6 +120,9 @@ impl Libraries {
 					continue;
 				};
 
+				// A backup restored while the library's database was still in use is applied now
+				backup::apply_pending_restore(&self.libraries_dir, library_id).await?;
+
 				let db_path = config_path.with_extension("db");
 				match fs::metadata(&db_path).await {
 					Ok(_) => {}
@@ -368,6 +373,14 @@ impl Libraries {
 			.try_join()
 			.await?;
 
+		// Not every library has a keyring file, as it is only created when the first key is added
+		let keyring_path = self.libraries_dir.join(format!("{}.sdkeyring", library.id));
+		if let Err(e) = fs::remove_file(&keyring_path).await {
+			if e.kind() != io::ErrorKind::NotFound {
+				return Err(FileIOError::from((keyring_path, e)).into());
+			}
+		}
+
 		// We only remove here after files deletion
 		let library = libraries_write_guard
 			.remove(id)
@@ -380,6 +393,26 @@ impl Libraries {
 		Ok(())
 	}
 
+	/// Removes a library from the loaded ones without touching its files, so they can be replaced
+	pub(crate) async fn unload(&self, id: &Uuid) -> Result<Arc<Library>, LibraryManagerError> {
+		let library = self
+			.libraries
+			.write()
+			.await
+			.remove(id)
+			.ok_or(LibraryManagerError::LibraryNotFound)?;
+
+		self.tx
+			.emit(LibraryManagerEvent::Delete(Arc::clone(&library)))
+			.await;
+
+		info!(%library.id, "Unloaded Library;");
+
+		invalidate_query!(library, "library.list");
+
+		Ok(library)
+	}
+
 	// get_ctx will return the library context for the given library id.
 	pub async fn get_library(&self, library_id: &Uuid) -> Option<Arc<Library>> {
 		self.libraries.read().await.get(library_id).cloned()
@@ -522,8 +555,8 @@ impl Libraries {
 
 		// TODO: Move this reconciliation into P2P and do reconciliation of both local and remote nodes.
 
-		// let key_manager = Arc::new(KeyManager::new(vec![]).await?);
-		// seed_keymanager(&db, &key_manager).await?;
+		let keyring =
+			Arc::new(Keyring::load(self.libraries_dir.join(format!("{id}.sdkeyring"))).await?);
 
 		let actors = Default::default();
 
@@ -545,7 +578,7 @@ impl Libraries {
 			config,
 			instance_id,
 			identity,
-			// key_manager,
+			keyring,
 			db,
 			node,
 			sync_manager,
@@ -568,7 +601,6 @@ impl Libraries {
 			.insert(library.id, Arc::clone(&library));
 
 		if should_seed {
-			// library.orphan_remover.invoke().await;
 			sd_core_indexer_rules::seed::new_or_existing_library(&library.db).await?;
 		}
 
@@ -715,6 +747,28 @@ impl Libraries {
 		Ok(library)
 	}
 
+	/// Generates the sync operations for the data already in the library, then keeps generating
+	/// them for every change. Does nothing if sync was already enabled.
+	pub(crate) async fn enable_sync(&self, library: &Library) -> Result<(), LibraryManagerError> {
+		let config = library.config().await;
+
+		if config.generate_sync_operations.load(Ordering::Relaxed) {
+			return Ok(());
+		}
+
+		sd_core_sync::backfill::backfill_operations(&library.db, &library.sync, config.instance_id)
+			.await?;
+
+		self.edit(
+			library.id,
+			None,
+			MaybeUndefined::Undefined,
+			MaybeUndefined::Undefined,
+			Some(true),
+		)
+		.await
+	}
+
 	pub async fn update_instances(&self, library: Arc<Library>) {
 		self.tx
 			.emit(LibraryManagerEvent::InstancesModified(library))
@@ -744,13 +798,103 @@ async fn sync_rx_actor(
 		};
 
 		match msg {
-			// TODO: Any sync event invalidates the entire React Query cache this is a hacky workaround until the new invalidation system.
-			SyncMessage::Ingested => node.emit(CoreEvent::InvalidateOperation(
-				InvalidateOperationEvent::all(),
-			)),
-			SyncMessage::Created => {
+			SyncMessage::Ingested(ingested) => invalidate_written(&library, &ingested),
+			SyncMessage::Created(created) => {
+				invalidate_written(&library, &created);
 				p2p::sync::originator(library.clone(), &library.sync, &node.p2p).await
 			}
 		}
 	}
 }
+
+/// Invalidates the queries reading the records written by sync operations, so writes from other
+/// instances show up as well as local ones that didn't invalidate their queries themselves.
+fn invalidate_written(library: &Library, written: &WrittenRecords) {
+	for model in written.models() {
+		trace!(
+			model,
+			records_count = written.records(model).len(),
+			"Invalidating queries for written records;"
+		);
+
+		match model {
+			prisma_sync::location::MODEL_ID => {
+				invalidate_query!(library, "locations.list");
+				invalidate_query!(library, "locations.get");
+				invalidate_query!(library, "locations.getWithRules");
+				invalidate_query!(library, "nodes.listLocations");
+			}
+			prisma_sync::file_path::MODEL_ID => {
+				invalidate_query!(library, "search.paths");
+				invalidate_query!(library, "search.pathsCount");
+				invalidate_query!(library, "files.getPath");
+				invalidate_query!(library, "library.statistics");
+			}
+			prisma_sync::object::MODEL_ID => {
+				invalidate_query!(library, "search.objects");
+				invalidate_query!(library, "search.objectsCount");
+				invalidate_query!(library, "search.paths");
+				invalidate_query!(library, "files.get");
+				invalidate_query!(library, "library.kindStatistics");
+			}
+			prisma_sync::exif_data::MODEL_ID => {
+				invalidate_query!(library, "files.getMediaData");
+				invalidate_query!(library, "search.geoClusters");
+			}
+			prisma_sync::tag::MODEL_ID => {
+				invalidate_query!(library, "tags.list");
+				invalidate_query!(library, "tags.get");
+				invalidate_query!(library, "tags.getForObject");
+				invalidate_query!(library, "tags.getWithObjects");
+			}
+			prisma_sync::tag_on_object::MODEL_ID => {
+				invalidate_query!(library, "tags.getForObject");
+				invalidate_query!(library, "tags.getWithObjects");
+				invalidate_query!(library, "search.objects");
+				invalidate_query!(library, "search.paths");
+			}
+			prisma_sync::label::MODEL_ID => {
+				invalidate_query!(library, "labels.list");
+				invalidate_query!(library, "labels.get");
+				invalidate_query!(library, "labels.count");
+				invalidate_query!(library, "labels.listWithThumbnails");
+			}
+			prisma_sync::label_on_object::MODEL_ID => {
+				invalidate_query!(library, "labels.getForObject");
+				invalidate_query!(library, "labels.getWithObjects");
+				invalidate_query!(library, "labels.listWithThumbnails");
+				invalidate_query!(library, "search.objects");
+			}
+			prisma_sync::preference::MODEL_ID => {
+				invalidate_query!(library, "preferences.get");
+			}
+			prisma_sync::saved_search::MODEL_ID => {
+				invalidate_query!(library, "search.saved.list");
+				invalidate_query!(library, "search.saved.get");
+			}
+			prisma_sync::storage_statistics::MODEL_ID => {
+				invalidate_query!(library, "library.statistics");
+			}
+			prisma_sync::album::MODEL_ID => {
+				invalidate_query!(library, "albums.list");
+				invalidate_query!(library, "albums.get");
+				invalidate_query!(library, "albums.getForObject");
+			}
+			prisma_sync::object_in_album::MODEL_ID => {
+				invalidate_query!(library, "albums.getObjects");
+				invalidate_query!(library, "albums.getForObject");
+				invalidate_query!(library, "search.objects");
+			}
+			prisma_sync::space::MODEL_ID => {
+				invalidate_query!(library, "spaces.list");
+				invalidate_query!(library, "spaces.get");
+				invalidate_query!(library, "spaces.getForObject");
+			}
+			prisma_sync::object_in_space::MODEL_ID => {
+				invalidate_query!(library, "spaces.getObjects");
+				invalidate_query!(library, "spaces.getForObject");
+			}
+			_ => warn!(model, "Written records for a model without invalidations;"),
+		}
+	}
+}
@@ -1,4 +1,6 @@
+pub(crate) mod backup;
 mod config;
+mod keyring;
 #[allow(clippy::module_inception)]
 mod library;
 mod manager;
@@ -6,6 +8,7 @@ mod name;
 mod statistics;
 
 pub use config::*;
+pub use keyring::*;
 pub use library::*;
 pub use manager::*;
 pub use name::*;
@@ -106,3 +106,42 @@ pub async fn update_library_statistics(
 
 	Ok(stats)
 }
+
+/// Adds bytes reclaimed by compacting the sync operations to the library statistics
+pub async fn add_sync_operations_reclaimed_bytes(
+	library: &Library,
+	bytes: u64,
+) -> Result<(), LibraryManagerError> {
+	let reclaimed_bytes = library
+		.db
+		.statistics()
+		.find_unique(statistics::id::equals(1))
+		.select(statistics::select!({ sync_operations_reclaimed_bytes }))
+		.exec()
+		.await?
+		.and_then(|stats| stats.sync_operations_reclaimed_bytes.parse::<u64>().ok())
+		.unwrap_or(0)
+		.saturating_add(bytes)
+		.to_string();
+
+	library
+		.db
+		.statistics()
+		.upsert(
+			// Each library is a database so only one of these ever exists
+			statistics::id::equals(1),
+			statistics::create(vec![
+				statistics::id::set(1),
+				statistics::sync_operations_reclaimed_bytes::set(reclaimed_bytes.clone()),
+			]),
+			vec![statistics::sync_operations_reclaimed_bytes::set(
+				reclaimed_bytes,
+			)],
+		)
+		.exec()
+		.await?;
+
+	invalidate_query!(library, "library.statistics");
+
+	Ok(())
+}
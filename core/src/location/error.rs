@@ -78,6 +78,8 @@ pub enum LocationError {
 	MissingField(#[from] MissingFieldError),
 	#[error("invalid location scan state value: {0}")]
 	InvalidScanStateValue(i32),
+	#[error("invalid rescan schedule: {0}")]
+	InvalidRescanSchedule(#[from] super::rescan::RescanScheduleError),
 	#[error(transparent)]
 	Sync(#[from] sd_core_sync::Error),
 }
@@ -96,9 +98,10 @@ impl From<LocationError> for rspc::Error {
 			}
 
 			// User's fault errors
-			NotDirectory(_) | NestedLocation(_) | LocationAlreadyExists(_) => {
-				Self::with_cause(ErrorCode::BadRequest, e.to_string(), e)
-			}
+			NotDirectory(_)
+			| NestedLocation(_)
+			| LocationAlreadyExists(_)
+			| InvalidRescanSchedule(_) => Self::with_cause(ErrorCode::BadRequest, e.to_string(), e),
 
 			// Custom error message is used to differentiate these errors in the frontend
 			// TODO: A better solution would be for rspc to support sending custom data alongside errors
@@ -1,5 +1,6 @@
 use crate::{
 	library::{Library, LibraryId},
+	location::reattach_location,
 	Node,
 };
 
@@ -300,6 +301,22 @@ impl Runner {
 		if let Some(location) = get_location(location_id, &library).await? {
 			// TODO(N): This isn't gonna work with removable media and this will likely permanently break if the DB is restored from a backup.
 			if location.instance_id == Some(library.config().await.instance_id) {
+				match reattach_location(&library, location_id).await {
+					Ok(Some(_)) => {
+						// The watcher is bound to the old path, so we need a new one
+						self.drop_location(
+							location_id,
+							library.id,
+							"Replacing location watcher, as the location was re-attached",
+						);
+						self.forced_unwatch.remove(&key);
+
+						return self.add_location(location_id, library).await;
+					}
+					Ok(None) => {}
+					Err(e) => error!(?e, "Failed to re-attach location to its volume;"),
+				}
+
 				if check_online(&location, &self.node, &library).await?
 					&& !self.forced_unwatch.contains(&key)
 				{
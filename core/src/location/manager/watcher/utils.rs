@@ -6,7 +6,6 @@ use crate::{
 		indexer::reverse_update_directories_sizes, location_with_indexer_rules,
 		manager::LocationManagerError, scan_location_sub_path, update_location_size,
 	},
-	object::validation::hash::file_checksum,
 	Node,
 };
 
@@ -18,6 +17,7 @@ use sd_core_file_path_helper::{
 };
 use sd_core_heavy_lifting::{
 	file_identifier::FileMetadata,
+	file_validator::file_checksum,
 	media_processor::{
 		exif_media_data, ffmpeg_media_data, generate_single_thumbnail, get_thumbnails_directory,
 		ThumbnailKind,
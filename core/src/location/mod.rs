@@ -1,4 +1,11 @@
-use crate::{context::NodeContext, invalidate_query, library::Library, Node};
+use crate::{
+	context::NodeContext,
+	invalidate_query,
+	library::Library,
+	util::MaybeUndefined,
+	volume::{find_volume_for_path, get_volumes},
+	Node,
+};
 
 use sd_core_file_path_helper::{
 	filter_existing_file_path_params, IsolatedFilePathData, IsolatedFilePathDataParts,
@@ -18,6 +25,7 @@ use sd_prisma::{
 };
 use sd_sync::*;
 use sd_utils::{
+	chain_optional_iter,
 	db::{maybe_missing, MissingFieldError},
 	error::{FileIOError, NonUtf8PathError},
 	msgpack, uuid_to_bytes,
@@ -43,6 +51,7 @@ mod error;
 mod manager;
 pub mod metadata;
 pub mod non_indexed;
+pub mod rescan;
 
 pub use error::LocationError;
 pub use manager::{LocationManagerError, Locations};
@@ -290,6 +299,9 @@ pub struct LocationUpdateArgs {
 	hidden: Option<bool>,
 	indexer_rules_ids: Vec<i32>,
 	path: Option<String>,
+	/// Cron expression for periodic rescans, `null` disables them
+	#[serde(default)]
+	rescan_schedule: MaybeUndefined<String>,
 }
 
 impl LocationUpdateArgs {
@@ -304,6 +316,16 @@ impl LocationUpdateArgs {
 
 		let name = self.name.clone();
 
+		let rescan_schedule = match self.rescan_schedule {
+			MaybeUndefined::Undefined => None,
+			MaybeUndefined::Null => Some(None),
+			MaybeUndefined::Value(schedule) => {
+				schedule.parse::<rescan::RescanSchedule>()?;
+				Some(Some(schedule.trim().to_string()))
+			}
+		}
+		.filter(|schedule| location.rescan_schedule != *schedule);
+
 		let (sync_params, db_params): (Vec<_>, Vec<_>) = [
 			self.name
 				.filter(|name| location.name.as_ref() != Some(name))
@@ -337,6 +359,12 @@ impl LocationUpdateArgs {
 					location::path::set(Some(v)),
 				)
 			}),
+			rescan_schedule.map(|v| {
+				(
+					(location::rescan_schedule::NAME, msgpack!(v)),
+					location::rescan_schedule::set(v),
+				)
+			}),
 		]
 		.into_iter()
 		.flatten()
@@ -680,6 +708,174 @@ pub async fn relink_location(
 	Ok(location_id.id)
 }
 
+/// Looks for an offline location on the volume it was created on, as removable media can be
+/// mounted somewhere else, updating the location path if it's found there.
+///
+/// Online locations created before their volume was tracked get it backfilled instead.
+#[instrument(skip(library), fields(library_id = %library.id), err)]
+pub async fn reattach_location(
+	library: &Library,
+	location_id: location::id::Type,
+) -> Result<Option<PathBuf>, LocationError> {
+	let Library { db, sync, id, .. } = library;
+
+	let location = db
+		.location()
+		.find_unique(location::id::equals(location_id))
+		.select(location::select!({ pub_id path volume_identifier volume_path }))
+		.exec()
+		.await?
+		.ok_or(LocationError::IdNotFound(location_id))?;
+
+	if let Some(path) = &location.path {
+		match fs::metadata(path).await {
+			Ok(_) => {
+				if location.volume_identifier.is_none() || location.volume_path.is_none() {
+					backfill_location_volume(library, location_id, location.pub_id, path).await?;
+				}
+
+				return Ok(None);
+			}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+			Err(e) => return Err(FileIOError::from((path, e)).into()),
+		}
+	}
+
+	let (Some(volume_identifier), Some(volume_path)) =
+		(location.volume_identifier, location.volume_path)
+	else {
+		return Ok(None);
+	};
+
+	let mount_points = get_volumes()
+		.await
+		.into_iter()
+		.filter(|volume| volume.identifier.as_ref() == Some(&volume_identifier))
+		.flat_map(|volume| volume.mount_points);
+
+	for mount_point in mount_points {
+		let new_path = mount_point.join(&volume_path);
+
+		if !fs::metadata(&new_path)
+			.await
+			.map(|metadata| metadata.is_dir())
+			.unwrap_or(false)
+		{
+			continue;
+		}
+
+		// A metadata file from another location means this isn't the directory we're looking for
+		if let Some(mut metadata) = SpacedriveLocationMetadataFile::try_load(&new_path).await? {
+			if !metadata
+				.location_pub_id(*id)
+				.is_ok_and(|pub_id| pub_id.as_bytes().as_slice() == location.pub_id.as_slice())
+			{
+				continue;
+			}
+
+			metadata.relink(*id, &new_path).await?;
+		}
+
+		let path = new_path
+			.to_str()
+			.map(str::to_string)
+			.ok_or_else(|| NonUtf8PathError(new_path.as_path().into()))?;
+
+		sync.write_op(
+			db,
+			sync.shared_update(
+				prisma_sync::location::SyncId {
+					pub_id: location.pub_id.clone(),
+				},
+				location::path::NAME,
+				msgpack!(&path),
+			),
+			db.location()
+				.update(
+					location::id::equals(location_id),
+					vec![location::path::set(Some(path))],
+				)
+				.select(location::select!({ id })),
+		)
+		.await?;
+
+		info!(
+			new_path = %new_path.display(),
+			"Location was re-attached to its volume under a new mount point;",
+		);
+
+		invalidate_query!(library, "locations.list");
+		invalidate_query!(library, "locations.get");
+
+		return Ok(Some(new_path));
+	}
+
+	Ok(None)
+}
+
+/// Records the volume of a location that was created before volumes were tracked, so it can be
+/// re-attached if its volume is later mounted somewhere else
+async fn backfill_location_volume(
+	library: &Library,
+	location_id: location::id::Type,
+	location_pub_id: location::pub_id::Type,
+	path: impl AsRef<Path>,
+) -> Result<(), LocationError> {
+	let Library { db, sync, .. } = library;
+
+	let Some((volume_identifier, volume_path)) = location_volume(path).await else {
+		return Ok(());
+	};
+
+	let location_sync_id = prisma_sync::location::SyncId {
+		pub_id: location_pub_id,
+	};
+
+	sync.write_ops(
+		db,
+		(
+			vec![
+				sync.shared_update(
+					location_sync_id.clone(),
+					location::volume_identifier::NAME,
+					msgpack!(&volume_identifier),
+				),
+				sync.shared_update(
+					location_sync_id,
+					location::volume_path::NAME,
+					msgpack!(&volume_path),
+				),
+			],
+			db.location()
+				.update(
+					location::id::equals(location_id),
+					vec![
+						location::volume_identifier::set(Some(volume_identifier)),
+						location::volume_path::set(Some(volume_path)),
+					],
+				)
+				.select(location::select!({ id })),
+		),
+	)
+	.await?;
+
+	debug!(%location_id, "Backfilled the volume of the location;");
+
+	Ok(())
+}
+
+/// Identifier of the volume holding the path, along with the path relative to its mount point
+async fn location_volume(path: impl AsRef<Path>) -> Option<(String, String)> {
+	let path = path.as_ref();
+	let volumes = get_volumes().await;
+	let (volume, mount_point) = find_volume_for_path(&volumes, path)?;
+
+	Some((
+		volume.identifier.clone()?,
+		path.strip_prefix(mount_point).ok()?.to_str()?.to_string(),
+	))
+}
+
 #[derive(Debug)]
 pub struct CreatedLocationResult {
 	pub name: String,
@@ -767,6 +963,8 @@ async fn create_location(
 
 	let date_created = Utc::now();
 
+	let (volume_identifier, volume_path) = location_volume(&path).await.unzip();
+
 	let location = sync
 		.write_ops(
 			db,
@@ -775,17 +973,26 @@ async fn create_location(
 					prisma_sync::location::SyncId {
 						pub_id: location_pub_id.as_bytes().to_vec(),
 					},
-					[
-						(location::name::NAME, msgpack!(&name)),
-						(location::path::NAME, msgpack!(&path)),
-						(location::date_created::NAME, msgpack!(date_created)),
-						// (
-						// 	location::instance::NAME,
-						// 	msgpack!(prisma_sync::instance::SyncId {
-						// 		pub_id: uuid_to_bytes(sync.instance)
-						// 	}),
-						// ),
-					],
+					chain_optional_iter(
+						[
+							(location::name::NAME, msgpack!(&name)),
+							(location::path::NAME, msgpack!(&path)),
+							(location::date_created::NAME, msgpack!(date_created)),
+							// (
+							// 	location::instance::NAME,
+							// 	msgpack!(prisma_sync::instance::SyncId {
+							// 		pub_id: uuid_to_bytes(sync.instance)
+							// 	}),
+							// ),
+						],
+						[
+							option_sync_entry!(
+								volume_identifier.clone(),
+								location::volume_identifier
+							),
+							option_sync_entry!(volume_path.clone(), location::volume_path),
+						],
+					),
 				),
 				db.location()
 					.create(
@@ -795,6 +1002,8 @@ async fn create_location(
 							location::path::set(Some(path)),
 							location::date_created::set(Some(date_created.into())),
 							location::instance_id::set(Some(library.config().await.instance_id)),
+							location::volume_identifier::set(volume_identifier),
+							location::volume_path::set(volume_path),
 							// location::instance::connect(instance::id::equals(
 							// 	library.config.instance_id.as_bytes().to_vec(),
 							// )),
@@ -935,8 +1144,6 @@ pub async fn delete_directory(
 
 	db.file_path().delete_many(children_params).exec().await?;
 
-	// library.orphan_remover.invoke().await;
-
 	invalidate_query!(library, "search.paths");
 	invalidate_query!(library, "search.objects");
 
@@ -188,13 +188,23 @@ pub async fn walk(
 					{
 						matches!(
 							kind,
-							ObjectKind::Image | ObjectKind::Video | ObjectKind::Document
+							ObjectKind::Image
+								| ObjectKind::Video | ObjectKind::Audio
+								| ObjectKind::Document | ObjectKind::Book
+								| ObjectKind::Text | ObjectKind::Code
+								| ObjectKind::Config
 						)
 					}
 
 					#[cfg(not(feature = "ffmpeg"))]
 					{
-						matches!(kind, ObjectKind::Image | ObjectKind::Document)
+						matches!(
+							kind,
+							ObjectKind::Image
+								| ObjectKind::Document | ObjectKind::Book
+								| ObjectKind::Text | ObjectKind::Code
+								| ObjectKind::Config
+						)
 					}
 				};
 
@@ -207,7 +217,12 @@ pub async fn walk(
 									NonIndexedLocationError::from((path, e)).into(),
 								)))
 							}) {
-						if kind == ObjectKind::Document {
+						if matches!(
+							kind,
+							ObjectKind::Document
+								| ObjectKind::Book | ObjectKind::Text
+								| ObjectKind::Code | ObjectKind::Config
+						) {
 							document_thumbnails_to_generate.push(GenerateThumbnailArgs::new(
 								extension.clone(),
 								cas_id.clone(),
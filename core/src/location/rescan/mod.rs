@@ -0,0 +1,240 @@
+use crate::{invalidate_query, library::Library, Node};
+
+use sd_core_heavy_lifting::{job_system::report::Status, JobId, JobName};
+use sd_core_prisma_helpers::location_with_indexer_rules;
+
+use sd_prisma::prisma::{job, location};
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Local, Utc};
+use prisma_client_rust::QueryError;
+use tokio::{spawn, time::interval};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use super::{scan_location, ScanState};
+
+mod schedule;
+
+pub use schedule::{RescanSchedule, RescanScheduleError};
+
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+/// Delay before retrying a failed rescan, doubled on each consecutive failure
+const BACKOFF_BASE: Duration = Duration::from_secs(5 * 60);
+const BACKOFF_MAX: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Rescan jobs dispatched by the scheduler and not finished yet, by library and location
+type PendingRescans = HashMap<(Uuid, location::id::Type), JobId>;
+
+/// Periodically rescans every location with a rescan schedule, as long as it belongs to this
+/// instance and is online
+pub fn start_scheduler(node: &Arc<Node>) {
+	let node = Arc::clone(node);
+
+	spawn(async move {
+		let mut interval = interval(SCHEDULER_TICK);
+		let mut pending = PendingRescans::new();
+
+		loop {
+			interval.tick().await;
+
+			let libraries = node.libraries.get_all().await;
+
+			pending.retain(|(library_id, _), _| {
+				libraries.iter().any(|library| library.id == *library_id)
+			});
+
+			for library in libraries {
+				if let Err(e) = check_pending_rescans(&library, &mut pending).await {
+					error!(library_id = %library.id, ?e, "Failed to check scheduled rescans;");
+				}
+
+				if let Err(e) = dispatch_due_rescans(&node, &library, &mut pending).await {
+					error!(library_id = %library.id, ?e, "Failed to dispatch scheduled rescans;");
+				}
+			}
+		}
+	});
+}
+
+async fn check_pending_rescans(
+	library: &Library,
+	pending: &mut PendingRescans,
+) -> Result<(), QueryError> {
+	let library_pending = pending
+		.iter()
+		.filter(|((library_id, _), _)| *library_id == library.id)
+		.map(|(&(_, location_id), &job_id)| (location_id, job_id))
+		.collect::<Vec<_>>();
+
+	for (location_id, job_id) in library_pending {
+		let status = library
+			.db
+			.job()
+			.find_unique(job::id::equals(job_id.as_bytes().to_vec()))
+			.select(job::select!({ status }))
+			.exec()
+			.await?
+			.and_then(|job| job.status)
+			.and_then(|status| Status::try_from(status).ok());
+
+		let succeeded = match status {
+			Some(Status::Queued | Status::Running | Status::Paused) => continue,
+			Some(Status::Completed | Status::CompletedWithErrors) => true,
+			// A missing report means the job was cleared before we could see how it ended
+			Some(Status::Canceled | Status::Failed) | None => false,
+		};
+
+		pending.remove(&(library.id, location_id));
+
+		record_rescan_result(library, location_id, succeeded).await?;
+	}
+
+	Ok(())
+}
+
+async fn dispatch_due_rescans(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	pending: &mut PendingRescans,
+) -> Result<(), QueryError> {
+	let instance_id = library.config().await.instance_id;
+
+	let locations = library
+		.db
+		.location()
+		.find_many(vec![
+			location::rescan_schedule::not(None),
+			location::instance_id::equals(Some(instance_id)),
+		])
+		.include(location_with_indexer_rules::include())
+		.exec()
+		.await?;
+
+	let now = Utc::now();
+
+	for location in locations {
+		let location_id = location.id;
+
+		if pending.contains_key(&(library.id, location_id)) || !is_due(&location, now) {
+			continue;
+		}
+
+		let is_online = match Uuid::from_slice(&location.pub_id) {
+			Ok(pub_id) => node.locations.is_online(&pub_id).await,
+			Err(_) => false,
+		};
+
+		if !is_online {
+			debug!(%location_id, "Skipping scheduled rescan of offline location;");
+			continue;
+		}
+
+		// Something else is already walking the location, we try again on the next tick
+		if node
+			.job_system
+			.check_running_jobs(
+				vec![
+					JobName::Indexer,
+					JobName::FileIdentifier,
+					JobName::MediaProcessor,
+				],
+				location_id,
+			)
+			.await
+		{
+			continue;
+		}
+
+		library
+			.db
+			.location()
+			.update_many(
+				vec![location::id::equals(location_id)],
+				vec![location::rescan_last_attempt_at::set(Some(now.into()))],
+			)
+			.exec()
+			.await?;
+
+		// Scheduled rescans always walk the whole location again, whatever the last scan reached
+		match scan_location(node, library, location, ScanState::Pending).await {
+			Ok(Some(job_id)) => {
+				info!(%location_id, %job_id, "Dispatched scheduled rescan;");
+				pending.insert((library.id, location_id), job_id);
+			}
+			Ok(None) => {}
+			Err(e) => {
+				warn!(%location_id, ?e, "Failed to dispatch scheduled rescan;");
+				record_rescan_result(library, location_id, false).await?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Failed rescans are retried with an exponential backoff, the others wait for the next time
+/// matching their schedule
+fn is_due(location: &location_with_indexer_rules::Data, now: DateTime<Utc>) -> bool {
+	let Some(schedule) = location.rescan_schedule.as_deref().and_then(|schedule| {
+		schedule
+			.parse::<RescanSchedule>()
+			.map_err(|e| warn!(location_id = %location.id, ?e, "Invalid rescan schedule;"))
+			.ok()
+	}) else {
+		return false;
+	};
+
+	// Schedules are written by users, so they are evaluated in their timezone
+	let Some(last_attempt) = location
+		.rescan_last_attempt_at
+		.map(|last_attempt| last_attempt.with_timezone(&Local))
+	else {
+		// Never verified, so there is no reason to wait
+		return true;
+	};
+
+	if location.rescan_failures > 0 {
+		return now >= last_attempt + backoff(location.rescan_failures);
+	}
+
+	schedule
+		.next_after(&last_attempt)
+		.is_some_and(|next| next <= now)
+}
+
+fn backoff(failures: i32) -> chrono::Duration {
+	// Capping the exponent first, so the multiplication can't overflow
+	let exponent = failures.saturating_sub(1).clamp(0, 16).unsigned_abs();
+
+	chrono::Duration::from_std(BACKOFF_BASE.saturating_mul(1 << exponent).min(BACKOFF_MAX))
+		.expect("backoff is capped to a day")
+}
+
+async fn record_rescan_result(
+	library: &Library,
+	location_id: location::id::Type,
+	succeeded: bool,
+) -> Result<(), QueryError> {
+	let params = if succeeded {
+		vec![
+			location::last_verified_at::set(Some(Utc::now().into())),
+			location::rescan_failures::set(0),
+		]
+	} else {
+		vec![location::rescan_failures::increment(1)]
+	};
+
+	library
+		.db
+		.location()
+		.update_many(vec![location::id::equals(location_id)], params)
+		.exec()
+		.await?;
+
+	invalidate_query!(library, "locations.list");
+	invalidate_query!(library, "locations.get");
+
+	Ok(())
+}
@@ -0,0 +1,281 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike};
+
+/// How far ahead we look for a matching date, expressions like `0 0 30 2 *` never match
+const MAX_LOOKAHEAD_DAYS: usize = 5 * 366;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RescanScheduleError {
+	#[error("expected 5 fields in cron expression, found {0}")]
+	FieldCount(usize),
+	#[error("invalid cron field <field='{field}', value='{value}'>")]
+	InvalidField { field: &'static str, value: String },
+}
+
+/// A cron expression with the usual five fields: `minute hour day-of-month month day-of-week`.
+///
+/// Fields accept `*`, single values, ranges (`1-5`), lists (`1,15`) and steps (`*/15`, `8-18/2`).
+/// Day of week goes from 0 to 7, both 0 and 7 being Sunday. When both day fields are restricted,
+/// a day matching either of them matches, like most cron implementations do.
+/// The `@hourly`, `@daily`, `@weekly` and `@monthly` shorthands are accepted too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RescanSchedule {
+	minutes: u64,
+	hours: u64,
+	days_of_month: u64,
+	months: u64,
+	days_of_week: u64,
+	days_of_month_restricted: bool,
+	days_of_week_restricted: bool,
+}
+
+impl FromStr for RescanSchedule {
+	type Err = RescanScheduleError;
+
+	fn from_str(expression: &str) -> Result<Self, Self::Err> {
+		let expression = match expression.trim() {
+			"@hourly" => "0 * * * *",
+			"@daily" | "@midnight" => "0 0 * * *",
+			"@weekly" => "0 0 * * 0",
+			"@monthly" => "0 0 1 * *",
+			expression => expression,
+		};
+
+		let fields = expression.split_whitespace().collect::<Vec<_>>();
+
+		let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+			return Err(RescanScheduleError::FieldCount(fields.len()));
+		};
+
+		let mut days_of_week_bits = parse_field(days_of_week, "day of week", 0, 7)?;
+		// 7 is an alias for Sunday
+		if days_of_week_bits & (1 << 7) != 0 {
+			days_of_week_bits = (days_of_week_bits & !(1 << 7)) | 1;
+		}
+
+		Ok(Self {
+			minutes: parse_field(minutes, "minute", 0, 59)?,
+			hours: parse_field(hours, "hour", 0, 23)?,
+			days_of_month: parse_field(days_of_month, "day of month", 1, 31)?,
+			months: parse_field(months, "month", 1, 12)?,
+			days_of_week: days_of_week_bits,
+			days_of_month_restricted: !days_of_month.starts_with('*'),
+			days_of_week_restricted: !days_of_week.starts_with('*'),
+		})
+	}
+}
+
+impl RescanSchedule {
+	/// First time matching the schedule strictly after `after`, at a whole minute.
+	///
+	/// Local times skipped by a daylight saving change never match.
+	pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+		let timezone = after.timezone();
+
+		let start = after
+			.naive_local()
+			.with_second(0)
+			.and_then(|start| start.with_nanosecond(0))?
+			+ Duration::minutes(1);
+
+		let mut date = start.date();
+
+		for _ in 0..MAX_LOOKAHEAD_DAYS {
+			if self.matches_date(date) {
+				let is_first_day = date == start.date();
+
+				for hour in (0..24).filter(|&hour| has(self.hours, hour)) {
+					if is_first_day && hour < start.hour() {
+						continue;
+					}
+
+					for minute in (0..60).filter(|&minute| has(self.minutes, minute)) {
+						if is_first_day && hour == start.hour() && minute < start.minute() {
+							continue;
+						}
+
+						if let Some(next) = date
+							.and_hms_opt(hour, minute, 0)
+							.and_then(|naive| timezone.from_local_datetime(&naive).earliest())
+						{
+							return Some(next);
+						}
+					}
+				}
+			}
+
+			date = date.succ_opt()?;
+		}
+
+		None
+	}
+
+	fn matches_date(&self, date: NaiveDate) -> bool {
+		if !has(self.months, date.month()) {
+			return false;
+		}
+
+		let day_of_month = has(self.days_of_month, date.day());
+		let day_of_week = has(self.days_of_week, date.weekday().num_days_from_sunday());
+
+		if self.days_of_month_restricted && self.days_of_week_restricted {
+			day_of_month || day_of_week
+		} else {
+			day_of_month && day_of_week
+		}
+	}
+}
+
+const fn has(bits: u64, value: u32) -> bool {
+	bits & (1 << value) != 0
+}
+
+fn parse_field(
+	field: &str,
+	name: &'static str,
+	min: u32,
+	max: u32,
+) -> Result<u64, RescanScheduleError> {
+	let invalid = || RescanScheduleError::InvalidField {
+		field: name,
+		value: field.to_string(),
+	};
+
+	let parse_value = |value: &str| {
+		value
+			.parse::<u32>()
+			.ok()
+			.filter(|value| (min..=max).contains(value))
+			.ok_or_else(invalid)
+	};
+
+	let mut bits = 0;
+
+	for part in field.split(',') {
+		let (range, step) = match part.split_once('/') {
+			Some((range, step)) => (
+				range,
+				step.parse::<u32>()
+					.ok()
+					.filter(|&step| step > 0)
+					.ok_or_else(invalid)?,
+			),
+			None => (part, 1),
+		};
+
+		let (first, last) = match range {
+			"*" => (min, max),
+			range => match range.split_once('-') {
+				Some((first, last)) => (parse_value(first)?, parse_value(last)?),
+				// `5/15` means every 15 starting at 5
+				None if step > 1 => (parse_value(range)?, max),
+				None => {
+					let value = parse_value(range)?;
+					(value, value)
+				}
+			},
+		};
+
+		if first > last {
+			return Err(invalid());
+		}
+
+		for value in (first..=last).step_by(step as usize) {
+			bits |= 1 << value;
+		}
+	}
+
+	Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use chrono::Utc;
+
+	fn at(date: &str) -> DateTime<Utc> {
+		DateTime::parse_from_rfc3339(date)
+			.expect("valid test date")
+			.with_timezone(&Utc)
+	}
+
+	fn next(expression: &str, after: &str) -> Option<DateTime<Utc>> {
+		expression
+			.parse::<RescanSchedule>()
+			.expect("valid test expression")
+			.next_after(&at(after))
+	}
+
+	#[test]
+	fn test_next_after() {
+		// 2024-01-01 is a Monday
+		assert_eq!(
+			next("*/15 * * * *", "2024-01-01T10:07:30Z"),
+			Some(at("2024-01-01T10:15:00Z"))
+		);
+		assert_eq!(
+			next("0 3 * * *", "2024-01-01T03:00:00Z"),
+			Some(at("2024-01-02T03:00:00Z"))
+		);
+		assert_eq!(
+			next("30 8-18/2 * * 1-5", "2024-01-05T18:31:00Z"),
+			Some(at("2024-01-08T08:30:00Z"))
+		);
+		assert_eq!(
+			next("@weekly", "2024-01-01T00:00:00Z"),
+			Some(at("2024-01-07T00:00:00Z"))
+		);
+		assert_eq!(
+			next("0 0 * * 7", "2024-01-01T00:00:00Z"),
+			Some(at("2024-01-07T00:00:00Z"))
+		);
+		assert_eq!(
+			next("0 0 29 2 *", "2024-03-01T00:00:00Z"),
+			Some(at("2028-02-29T00:00:00Z"))
+		);
+		assert_eq!(next("0 0 30 2 *", "2024-01-01T00:00:00Z"), None);
+	}
+
+	#[test]
+	fn test_restricted_days_match_either() {
+		// The 15th or any Monday
+		let schedule = "0 0 15 * 1".parse::<RescanSchedule>().unwrap();
+
+		assert_eq!(
+			schedule.next_after(&at("2024-01-02T00:00:00Z")),
+			Some(at("2024-01-08T00:00:00Z"))
+		);
+		assert_eq!(
+			schedule.next_after(&at("2024-01-12T00:00:00Z")),
+			Some(at("2024-01-15T00:00:00Z"))
+		);
+		assert_eq!(
+			schedule.next_after(&at("2024-02-13T00:00:00Z")),
+			Some(at("2024-02-15T00:00:00Z"))
+		);
+	}
+
+	#[test]
+	fn test_invalid_expressions() {
+		for expression in [
+			"",
+			"* * * *",
+			"* * * * * *",
+			"60 * * * *",
+			"* 24 * * *",
+			"* * 0 * *",
+			"* * * 13 *",
+			"* * * * 8",
+			"*/0 * * * *",
+			"5-1 * * * *",
+			"a * * * *",
+		] {
+			assert!(
+				expression.parse::<RescanSchedule>().is_err(),
+				"{expression} should be invalid"
+			);
+		}
+	}
+}
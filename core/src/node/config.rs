@@ -1,16 +1,21 @@
 use crate::{
 	api::{notifications::Notification, BackendFeature},
+	library::backup::RetentionPolicy,
 	/*object::media::old_thumbnail::preferences::ThumbnailerPreferences,*/
 	util::version_manager::{Kind, ManagedVersion, VersionManager, VersionManagerError},
 };
 
+use sd_core_heavy_lifting::JobName;
 use sd_p2p::Identity;
+use sd_task_system::TaskSystemConfig;
 use sd_utils::error::FileIOError;
 
 use std::{
-	collections::HashSet,
+	collections::{HashMap, HashSet},
+	num::NonZeroUsize,
 	path::{Path, PathBuf},
 	sync::Arc,
+	time::Duration,
 };
 
 use int_enum::IntEnum;
@@ -84,6 +89,11 @@ pub struct NodeConfigP2P {
 	pub disable_relay: bool,
 	#[serde(default, skip_serializing_if = "skip_if_false")]
 	pub enable_remote_access: bool,
+	/// Extra rspc procedures remote peers are allowed to call, on top of the read-only ones they always have access to.
+	///
+	/// Eg. `files.copyFiles` or `locations.delete`
+	#[serde(default, skip_serializing_if = "HashSet::is_empty")]
+	pub remote_access_allowlist: HashSet<String>,
 	/// A list of peer addresses to try and manually connect to, instead of relying on discovery.
 	///
 	/// All of these are valid values:
@@ -105,6 +115,7 @@ impl Default for NodeConfigP2P {
 			disable_ipv6: true,
 			disable_relay: true,
 			enable_remote_access: false,
+			remote_access_allowlist: Default::default(),
 			manual_peers: Default::default(),
 		}
 	}
@@ -170,7 +181,83 @@ mod identity_serde {
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Type)]
 pub struct NodePreferences {
 	// pub thumbnailer: ThumbnailerPreferences,
-	// TODO(fogodev): introduce preferences to choose how many worker the task system should have
+	#[serde(default)]
+	pub task_system: TaskSystemPreferences,
+	#[serde(default)]
+	pub backups: BackupPreferences,
+	#[serde(default)]
+	pub maintenance: MaintenancePreferences,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Type)]
+pub struct BackupPreferences {
+	/// Hours between scheduled incremental backups of every library, none disables them
+	#[serde(default)]
+	pub interval_hours: Option<u32>,
+	/// Which backups are kept, applied after each backup
+	#[serde(default)]
+	pub retention: RetentionPolicy,
+}
+
+impl BackupPreferences {
+	pub fn interval(&self) -> Option<Duration> {
+		self.interval_hours
+			.filter(|&hours| hours > 0)
+			.map(|hours| Duration::from_secs(u64::from(hours) * 60 * 60))
+	}
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Type)]
+#[serde(default)]
+pub struct MaintenancePreferences {
+	/// Hours between scheduled maintenance runs, none disables them
+	pub interval_hours: Option<u32>,
+	/// Postpones scheduled runs until no jobs are running and the CPU is mostly idle
+	pub only_when_idle: bool,
+}
+
+impl Default for MaintenancePreferences {
+	fn default() -> Self {
+		Self {
+			interval_hours: Some(24),
+			only_when_idle: true,
+		}
+	}
+}
+
+impl MaintenancePreferences {
+	pub fn interval(&self) -> Option<Duration> {
+		self.interval_hours
+			.filter(|&hours| hours > 0)
+			.map(|hours| Duration::from_secs(u64::from(hours) * 60 * 60))
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Type)]
+pub struct TaskSystemPreferences {
+	/// How many workers run tasks in parallel, defaults to half of the available cores
+	#[serde(default)]
+	pub workers_count: Option<u32>,
+	/// How many tasks each kind of job can run at the same time, jobs missing here are uncapped
+	#[serde(default)]
+	pub job_concurrency_limits: HashMap<JobName, u32>,
+}
+
+impl TaskSystemPreferences {
+	pub fn task_system_config(&self) -> TaskSystemConfig {
+		self.workers_count
+			.and_then(|workers_count| NonZeroUsize::new(workers_count as usize))
+			.map(|workers_count| TaskSystemConfig { workers_count })
+			.unwrap_or_default()
+	}
+
+	pub fn job_concurrency_limits(&self) -> impl Iterator<Item = (JobName, NonZeroUsize)> + '_ {
+		self.job_concurrency_limits
+			.iter()
+			.filter_map(|(&job_name, &max_tasks)| {
+				NonZeroUsize::new(max_tasks as usize).map(|max_tasks| (job_name, max_tasks))
+			})
+	}
 }
 
 #[derive(
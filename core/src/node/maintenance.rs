@@ -0,0 +1,441 @@
+use crate::{
+	context::NodeContext,
+	invalidate_query,
+	library::{add_sync_operations_reclaimed_bytes, Library},
+	Node,
+};
+
+use sd_core_heavy_lifting::{
+	maintenance::{
+		database_optimizer, orphan_remover, thumbnails_cleaner, DatabaseOptimizer, OrphanRemover,
+		ThumbnailsCleaner,
+	},
+	media_processor::get_thumbnails_directory,
+	Error,
+};
+use sd_core_sync::CompactionReport;
+use sd_task_system::{IntoTask, TaskHandle, TaskOutput, TaskStatus, TaskSystemError};
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::VecDeque,
+	io,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures_concurrency::future::Join;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sysinfo::{CpuExt, System, SystemExt};
+use tokio::{fs, spawn, sync::RwLock, task::spawn_blocking, time::interval};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const REPORTS_FILE: &str = "maintenance_reports.json";
+/// How many reports are kept, older ones are dropped
+const MAX_REPORTS: usize = 30;
+const SCHEDULER_TICK: Duration = Duration::from_secs(15 * 60);
+/// Global CPU usage percentage under which the machine is considered idle
+const IDLE_CPU_USAGE: f32 = 25.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum MaintenanceError {
+	#[error("a maintenance run is already in progress")]
+	AlreadyRunning,
+	#[error("task system is shutting down")]
+	TaskSystemShutdown,
+	#[error("failed to serialize maintenance reports: {0}")]
+	Serde(#[from] serde_json::Error),
+
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+}
+
+impl From<MaintenanceError> for rspc::Error {
+	fn from(e: MaintenanceError) -> Self {
+		match e {
+			MaintenanceError::AlreadyRunning => {
+				Self::with_cause(rspc::ErrorCode::Conflict, e.to_string(), e)
+			}
+			_ => Self::with_cause(rspc::ErrorCode::InternalServerError, e.to_string(), e),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceTrigger {
+	Scheduled,
+	Manual,
+}
+
+/// What a single maintenance run did
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct MaintenanceReport {
+	pub id: Uuid,
+	pub trigger: MaintenanceTrigger,
+	pub started_at: DateTime<Utc>,
+	pub finished_at: DateTime<Utc>,
+	/// Stale ephemeral thumbnails removed, they are shared by every library
+	pub removed_ephemeral_thumbnails: u64,
+	pub freed_ephemeral_thumbnails_bytes: u64,
+	pub libraries: Vec<LibraryMaintenanceReport>,
+	/// Errors that didn't stop the run
+	pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct LibraryMaintenanceReport {
+	pub library_id: Uuid,
+	pub removed_orphan_objects: u64,
+	pub removed_thumbnails: u64,
+	pub freed_thumbnails_bytes: u64,
+	/// Sync operations removed from the operations log by compaction
+	pub compacted_sync_operations: u64,
+	pub reclaimed_sync_operations_bytes: u64,
+	pub database_size_before: u64,
+	pub database_size_after: u64,
+}
+
+/// Keeps the reports of past maintenance runs, persisted in the node data directory
+pub struct Maintenance {
+	reports_path: PathBuf,
+	/// Newest first
+	reports: RwLock<VecDeque<MaintenanceReport>>,
+	running: AtomicBool,
+}
+
+impl Maintenance {
+	pub async fn new(data_dir: impl AsRef<Path>) -> Self {
+		let reports_path = data_dir.as_ref().join(REPORTS_FILE);
+
+		let reports = match fs::read(&reports_path).await {
+			Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+				warn!(
+					?e,
+					"Failed to parse maintenance reports, starting from scratch;"
+				);
+				VecDeque::new()
+			}),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => VecDeque::new(),
+			Err(e) => {
+				warn!(
+					?e,
+					"Failed to read maintenance reports, starting from scratch;"
+				);
+				VecDeque::new()
+			}
+		};
+
+		Self {
+			reports_path,
+			reports: RwLock::new(reports),
+			running: AtomicBool::new(false),
+		}
+	}
+
+	pub async fn reports(&self) -> Vec<MaintenanceReport> {
+		self.reports.read().await.iter().cloned().collect()
+	}
+
+	pub fn is_running(&self) -> bool {
+		self.running.load(Ordering::Relaxed)
+	}
+
+	async fn last_finished_at(&self) -> Option<DateTime<Utc>> {
+		self.reports
+			.read()
+			.await
+			.front()
+			.map(|report| report.finished_at)
+	}
+
+	async fn push_report(&self, report: MaintenanceReport) -> Result<(), MaintenanceError> {
+		let mut reports = self.reports.write().await;
+
+		reports.push_front(report);
+		reports.truncate(MAX_REPORTS);
+
+		fs::write(&self.reports_path, serde_json::to_vec(&*reports)?)
+			.await
+			.map_err(|e| FileIOError::from((&self.reports_path, e)).into())
+	}
+}
+
+/// Removes orphaned objects and stale thumbnails, compacts the sync operations, then optimizes every
+/// library database
+pub async fn run(
+	node: &Arc<Node>,
+	trigger: MaintenanceTrigger,
+) -> Result<MaintenanceReport, MaintenanceError> {
+	if node.maintenance.running.swap(true, Ordering::Relaxed) {
+		return Err(MaintenanceError::AlreadyRunning);
+	}
+
+	invalidate_query!(node; node, "maintenance.isRunning");
+
+	let res = run_tasks(node, trigger).await;
+
+	node.maintenance.running.store(false, Ordering::Relaxed);
+
+	invalidate_query!(node; node, "maintenance.isRunning");
+
+	let report = res?;
+
+	node.maintenance.push_report(report.clone()).await?;
+
+	invalidate_query!(node; node, "maintenance.reports");
+
+	Ok(report)
+}
+
+async fn run_tasks(
+	node: &Arc<Node>,
+	trigger: MaintenanceTrigger,
+) -> Result<MaintenanceReport, MaintenanceError> {
+	let started_at = Utc::now();
+	let thumbnails_directory = get_thumbnails_directory(node.config.data_directory());
+
+	let mut errors = vec![];
+
+	let ephemeral_handle = dispatch(
+		node,
+		ThumbnailsCleaner::new_ephemeral(thumbnails_directory.clone()),
+	)
+	.await?;
+
+	let mut libraries = vec![];
+
+	// Libraries go one at a time, so maintenance doesn't take over every worker
+	for library in node.libraries.get_all().await {
+		libraries.push(
+			run_library_tasks(node, &library, thumbnails_directory.clone(), &mut errors).await?,
+		);
+	}
+
+	let (removed_ephemeral_thumbnails, freed_ephemeral_thumbnails_bytes) =
+		wait::<thumbnails_cleaner::Output>(ephemeral_handle, &mut errors)
+			.await
+			.map_or((0, 0), |output| {
+				errors.extend(output.errors.into_iter().map(|e| e.to_string()));
+				(output.removed_thumbnails, output.freed_bytes)
+			});
+
+	let report = MaintenanceReport {
+		id: Uuid::new_v4(),
+		trigger,
+		started_at,
+		finished_at: Utc::now(),
+		removed_ephemeral_thumbnails,
+		freed_ephemeral_thumbnails_bytes,
+		libraries,
+		errors,
+	};
+
+	info!(
+		report_id = %report.id,
+		?trigger,
+		errors_count = report.errors.len(),
+		"Maintenance finished;",
+	);
+
+	Ok(report)
+}
+
+async fn run_library_tasks(
+	node: &Node,
+	library: &Library,
+	thumbnails_directory: PathBuf,
+	errors: &mut Vec<String>,
+) -> Result<LibraryMaintenanceReport, MaintenanceError> {
+	let mut report = LibraryMaintenanceReport {
+		library_id: library.id,
+		..Default::default()
+	};
+
+	let mut library_errors = vec![];
+
+	let orphans_handle = dispatch(
+		node,
+		OrphanRemover::new(Arc::clone(&library.db), Arc::clone(&library.sync)),
+	)
+	.await?;
+	let thumbnails_handle = dispatch(
+		node,
+		ThumbnailsCleaner::new_indexed(thumbnails_directory, library.id, Arc::clone(&library.db)),
+	)
+	.await?;
+
+	// Waiting for the others is needed as we only want to vacuum once the orphans are gone
+	let (orphans_res, thumbnails_res) = (orphans_handle, thumbnails_handle).join().await;
+
+	if let Some(output) = output_from::<orphan_remover::Output>(orphans_res, &mut library_errors) {
+		report.removed_orphan_objects = output.removed_objects;
+	}
+
+	if let Some(output) =
+		output_from::<thumbnails_cleaner::Output>(thumbnails_res, &mut library_errors)
+	{
+		report.removed_thumbnails = output.removed_thumbnails;
+		report.freed_thumbnails_bytes = output.freed_bytes;
+		library_errors.extend(output.errors.into_iter().map(|e| e.to_string()));
+	}
+
+	// The cloud doesn't acknowledge the operations it holds, so they must all be kept for it
+	if library.config().await.cloud_id.is_none() {
+		match library.sync.compact_operations().await {
+			Ok(CompactionReport {
+				removed_operations,
+				reclaimed_bytes,
+			}) => {
+				report.compacted_sync_operations = removed_operations;
+				report.reclaimed_sync_operations_bytes = reclaimed_bytes;
+
+				if reclaimed_bytes > 0 {
+					if let Err(e) =
+						add_sync_operations_reclaimed_bytes(library, reclaimed_bytes).await
+					{
+						library_errors.push(e.to_string());
+					}
+				}
+			}
+			Err(e) => library_errors.push(e.to_string()),
+		}
+	}
+
+	let optimizer_handle = dispatch(node, DatabaseOptimizer::new(Arc::clone(&library.db))).await?;
+
+	if let Some(output) =
+		wait::<database_optimizer::Output>(optimizer_handle, &mut library_errors).await
+	{
+		report.database_size_before = output.size_before;
+		report.database_size_after = output.size_after;
+	}
+
+	if report.removed_orphan_objects > 0 {
+		invalidate_query!(library, "search.objects");
+		invalidate_query!(library, "library.statistics");
+	}
+
+	errors.extend(
+		library_errors
+			.into_iter()
+			.map(|e| format!("library {}: {e}", library.id)),
+	);
+
+	Ok(report)
+}
+
+async fn dispatch(
+	node: &Node,
+	task: impl IntoTask<Error>,
+) -> Result<TaskHandle<Error>, MaintenanceError> {
+	node.task_system
+		.dispatch(task)
+		.await
+		.map_err(|_| MaintenanceError::TaskSystemShutdown)
+}
+
+async fn wait<T: 'static>(handle: TaskHandle<Error>, errors: &mut Vec<String>) -> Option<T> {
+	output_from(handle.await, errors)
+}
+
+fn output_from<T: 'static>(
+	res: Result<TaskStatus<Error>, TaskSystemError>,
+	errors: &mut Vec<String>,
+) -> Option<T> {
+	match res {
+		Ok(TaskStatus::Done((_, TaskOutput::Out(out)))) => Some(
+			*out.downcast::<T>()
+				.expect("maintenance tasks always return their own output"),
+		),
+		Ok(TaskStatus::Error(e)) => {
+			errors.push(e.to_string());
+			None
+		}
+		Ok(
+			TaskStatus::Done((_, TaskOutput::Empty))
+			| TaskStatus::Canceled
+			| TaskStatus::ForcedAbortion
+			| TaskStatus::Shutdown(_),
+		) => {
+			errors.push("maintenance task was interrupted".to_string());
+			None
+		}
+		Err(e) => {
+			errors.push(e.to_string());
+			None
+		}
+	}
+}
+
+/// The machine is idle when no job is running in any library and the CPU is mostly unused
+async fn is_idle(node: &Arc<Node>) -> bool {
+	for library in node.libraries.get_all().await {
+		if node.old_jobs.has_active_workers(library.id).await
+			|| node
+				.job_system
+				.has_active_jobs(NodeContext {
+					node: Arc::clone(node),
+					library,
+				})
+				.await
+		{
+			return false;
+		}
+	}
+
+	spawn_blocking(|| {
+		let mut system = System::new();
+		system.refresh_cpu();
+		std::thread::sleep(System::MINIMUM_CPU_UPDATE_INTERVAL);
+		system.refresh_cpu();
+		system.global_cpu_info().cpu_usage()
+	})
+	.await
+	.map_or(false, |cpu_usage| cpu_usage < IDLE_CPU_USAGE)
+}
+
+/// Periodically runs maintenance, following the node maintenance preferences
+pub fn start_scheduler(node: &Arc<Node>) {
+	let node = Arc::clone(node);
+
+	spawn(async move {
+		let mut interval = interval(SCHEDULER_TICK);
+
+		loop {
+			interval.tick().await;
+
+			let preferences = node.config.get().await.preferences.maintenance;
+
+			let Some(every) = preferences.interval() else {
+				continue;
+			};
+
+			let is_due = node
+				.maintenance
+				.last_finished_at()
+				.await
+				.map_or(true, |last| {
+					// A clock that went backwards is treated as due, otherwise we could wait forever
+					(Utc::now() - last)
+						.to_std()
+						.map_or(true, |elapsed| elapsed >= every)
+				});
+
+			if !is_due || (preferences.only_when_idle && !is_idle(&node).await) {
+				continue;
+			}
+
+			if let Err(e) = run(&node, MaintenanceTrigger::Scheduled).await {
+				error!(?e, "Failed to run scheduled maintenance;");
+			}
+		}
+	});
+}
@@ -1,5 +1,6 @@
 pub mod config;
 mod hardware;
+pub mod maintenance;
 mod platform;
 
 pub use hardware::*;
@@ -1,8 +1,8 @@
 use crate::location::LocationError;
 
 use sd_core_file_path_helper::FilePathError;
+use sd_core_heavy_lifting::file_system;
 
-use sd_prisma::prisma::file_path;
 use sd_utils::{
 	db::MissingFieldError,
 	error::{FileIOError, NonUtf8PathError},
@@ -18,12 +18,6 @@ use thiserror::Error;
 pub enum FileSystemJobsError {
 	#[error("Location error: {0}")]
 	Location(#[from] LocationError),
-	#[error("file_path not in database: <path='{}'>", .0.display())]
-	FilePathNotFound(Box<Path>),
-	#[error("file_path id not in database: <id='{0}'>")]
-	FilePathIdNotFound(file_path::id::Type),
-	#[error("failed to create file or folder on disk")]
-	CreateFileOrFolder(FileIOError),
 	#[error("database error: {0}")]
 	Database(#[from] QueryError),
 	#[error(transparent)]
@@ -32,20 +26,19 @@ pub enum FileSystemJobsError {
 	WouldOverwrite(Box<Path>),
 	#[error("missing-field: {0}")]
 	MissingField(#[from] MissingFieldError),
-	#[error("no parent for path, which is supposed to be directory: <path='{}'>", .0.display())]
-	MissingParentPath(Box<Path>),
-	#[error("no stem on file path, but it's supposed to be a file: <path='{}'>", .0.display())]
-	MissingFileStem(Box<Path>),
 	#[error(transparent)]
 	FileIO(#[from] FileIOError),
 	#[error(transparent)]
 	NonUTF8Path(#[from] NonUtf8PathError),
-	#[error("failed to find an available name to avoid duplication: <path='{}'>", .0.display())]
-	FailedToFindAvailableName(Box<Path>),
+	#[error(transparent)]
+	FileSystem(#[from] file_system::Error),
 }
 
 impl From<FileSystemJobsError> for rspc::Error {
 	fn from(e: FileSystemJobsError) -> Self {
-		Self::with_cause(rspc::ErrorCode::InternalServerError, e.to_string(), e)
+		match e {
+			FileSystemJobsError::FileSystem(e) => e.into(),
+			_ => Self::with_cause(rspc::ErrorCode::InternalServerError, e.to_string(), e),
+		}
 	}
 }
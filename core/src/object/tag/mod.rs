@@ -1,9 +1,15 @@
 use crate::library::Library;
 
-use sd_prisma::{prisma::tag, prisma_sync};
+use sd_prisma::{
+	prisma::{tag, PrismaClient},
+	prisma_sync,
+};
 use sd_sync::*;
 
+use std::collections::{HashMap, HashSet, VecDeque};
+
 use chrono::Utc;
+use prisma_client_rust::QueryError;
 use serde::Deserialize;
 use specta::Type;
 use uuid::Uuid;
@@ -14,6 +20,9 @@ pub mod seed;
 pub struct TagCreateArgs {
 	pub name: String,
 	pub color: String,
+	/// Tag to nest the new one under, it's created as a root tag if missing
+	#[serde(default)]
+	pub parent_id: Option<tag::id::Type>,
 }
 
 impl TagCreateArgs {
@@ -23,6 +32,17 @@ impl TagCreateArgs {
 	) -> Result<tag::Data, sd_core_sync::Error> {
 		let pub_id = Uuid::new_v4().as_bytes().to_vec();
 
+		let parent = match self.parent_id {
+			Some(parent_id) => {
+				db.tag()
+					.find_unique(tag::id::equals(parent_id))
+					.select(tag::select!({ id pub_id }))
+					.exec()
+					.await?
+			}
+			None => None,
+		};
+
 		let (sync_params, db_params): (Vec<_>, Vec<_>) = [
 			sync_db_entry!(self.name, tag::name),
 			sync_db_entry!(self.color, tag::color),
@@ -30,6 +50,17 @@ impl TagCreateArgs {
 			sync_db_entry!(Utc::now(), tag::date_created),
 		]
 		.into_iter()
+		.chain(parent.map(|parent| {
+			(
+				(
+					tag::parent::NAME,
+					msgpack!(prisma_sync::tag::SyncId {
+						pub_id: parent.pub_id
+					}),
+				),
+				tag::parent::connect(tag::id::equals(parent.id)),
+			)
+		}))
 		.unzip();
 
 		sync.write_ops(
@@ -47,3 +78,33 @@ impl TagCreateArgs {
 		.await
 	}
 }
+
+/// Ids of the given tags along with the ids of every tag nested under them, at any depth
+pub async fn with_descendants(
+	db: &PrismaClient,
+	tag_ids: impl IntoIterator<Item = tag::id::Type>,
+) -> Result<Vec<tag::id::Type>, QueryError> {
+	let mut children = HashMap::<_, Vec<_>>::new();
+	for tag in db
+		.tag()
+		.find_many(vec![tag::parent_id::not(None)])
+		.select(tag::select!({ id parent_id }))
+		.exec()
+		.await?
+	{
+		if let Some(parent_id) = tag.parent_id {
+			children.entry(parent_id).or_default().push(tag.id);
+		}
+	}
+
+	let mut found = HashSet::new();
+	let mut queue = tag_ids.into_iter().collect::<VecDeque<_>>();
+
+	while let Some(id) = queue.pop_front() {
+		if found.insert(id) {
+			queue.extend(children.get(&id).into_iter().flatten().copied());
+		}
+	}
+
+	Ok(found.into_iter().collect())
+}
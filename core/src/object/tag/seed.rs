@@ -11,18 +11,22 @@ pub async fn new_library(library: &Library) -> Result<(), sd_core_sync::Error> {
 		TagCreateArgs {
 			name: "Keepsafe".to_string(),
 			color: "#D9188E".to_string(),
+			parent_id: None,
 		},
 		TagCreateArgs {
 			name: "Hidden".to_string(),
 			color: "#646278".to_string(),
+			parent_id: None,
 		},
 		TagCreateArgs {
 			name: "Projects".to_string(),
 			color: "#42D097".to_string(),
+			parent_id: None,
 		},
 		TagCreateArgs {
 			name: "Memes".to_string(),
 			color: "#A718D9".to_string(),
+			parent_id: None,
 		},
 	];
 
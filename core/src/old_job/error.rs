@@ -1,9 +1,6 @@
 use crate::{
 	location::{/*indexer::IndexerError,*/ LocationError},
-	object::{
-		fs::error::FileSystemJobsError, /*media::old_media_processor::MediaProcessorError,*/
-		/*old_file_identifier::FileIdentifierJobError,*/ validation::ValidatorError,
-	},
+	object::fs::error::FileSystemJobsError,
 };
 
 // use sd_crypto::Error as CryptoError;
@@ -59,8 +56,6 @@ pub enum JobError {
 
 	// Specific job errors
 	#[error(transparent)]
-	Validator(#[from] ValidatorError),
-	#[error(transparent)]
 	FileSystemJobsError(#[from] FileSystemJobsError),
 	// #[error(transparent)]
 	// CryptoError(#[from] CryptoError),
@@ -1,12 +1,5 @@
 use crate::{
 	library::Library,
-	object::{
-		fs::{
-			old_copy::OldFileCopierJobInit, old_cut::OldFileCutterJobInit,
-			old_delete::OldFileDeleterJobInit, old_erase::OldFileEraserJobInit,
-		},
-		validation::old_validator_job::OldObjectValidatorJobInit,
-	},
 	old_job::{worker::Worker, DynJob, JobError, OldJob},
 	Node,
 };
@@ -374,42 +367,18 @@ impl OldJobs {
 	}
 }
 
-#[macro_use]
-mod macros {
-	macro_rules! dispatch_call_to_job_by_name {
-        ($job_name:expr, T -> $call:expr, default = $default:block, jobs = [ $($job:ty),+ $(,)?]) => {{
-            match $job_name {
-                $(<$job as $crate::old_job::StatefulJob>::NAME => {
-                    type T = $job;
-                    $call
-                },)+
-                _ => $default
-            }
-        }};
-    }
-}
 /// This function is used to initialize a  DynJob from a job report.
+///
+/// All resumable jobs were ported to the new job system, so any report left behind by the old
+/// one can't be resumed anymore.
 fn initialize_resumable_job(
 	job_report: OldJobReport,
-	next_jobs: Option<VecDeque<Box<dyn DynJob>>>,
+	_next_jobs: Option<VecDeque<Box<dyn DynJob>>>,
 ) -> Result<Box<dyn DynJob>, JobError> {
-	dispatch_call_to_job_by_name!(
-		job_report.name.as_str(),
-		T -> OldJob::<T>::new_from_report(job_report, next_jobs),
-		default = {
-			error!(
-				%job_report.name,
-				%job_report.id,
-				"Unknown job type;",
-			);
-			Err(JobError::UnknownJobName(job_report.id, job_report.name))
-		},
-		jobs = [
-			OldObjectValidatorJobInit,
-			OldFileCutterJobInit,
-			OldFileCopierJobInit,
-			OldFileDeleterJobInit,
-			OldFileEraserJobInit,
-		]
-	)
+	error!(
+		%job_report.name,
+		%job_report.id,
+		"Unknown job type;",
+	);
+	Err(JobError::UnknownJobName(job_report.id, job_report.name))
 }
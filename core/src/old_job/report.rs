@@ -1,23 +1,18 @@
-use crate::{
-	library::Library,
-	object::{
-		fs::{
-			old_copy::OldFileCopierJobInit, old_cut::OldFileCutterJobInit,
-			old_delete::OldFileDeleterJobInit, old_erase::OldFileEraserJobInit,
-		},
-		validation::old_validator_job::OldObjectValidatorJobInit,
-	},
-};
+use crate::library::Library;
 
 use sd_core_prisma_helpers::job_without_data;
 
-use sd_prisma::prisma::job;
+use sd_prisma::prisma::{file_path, job, location};
 use sd_utils::db::{maybe_missing, MissingFieldError};
 
-use std::fmt::{Display, Formatter};
+use std::{
+	fmt::{Display, Formatter},
+	path::PathBuf,
+};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
 use specta::Type;
 use tracing::error;
 use uuid::Uuid;
@@ -61,6 +56,45 @@ pub struct OldJobReport {
 	pub estimated_completion: DateTime<Utc>,
 }
 
+// Init arguments of the jobs that were ported to the new job system, kept here only to be able to
+// read the metadata of reports stored by the old job system
+#[derive(Deserialize)]
+struct OldFileCopierJobInit {
+	source_location_id: location::id::Type,
+	target_location_id: location::id::Type,
+	sources_file_path_ids: Vec<file_path::id::Type>,
+	target_location_relative_directory_path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct OldFileCutterJobInit {
+	source_location_id: location::id::Type,
+	target_location_id: location::id::Type,
+	sources_file_path_ids: Vec<file_path::id::Type>,
+	target_location_relative_directory_path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct OldFileDeleterJobInit {
+	location_id: location::id::Type,
+	file_path_ids: Vec<file_path::id::Type>,
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+struct OldFileEraserJobInit {
+	location_id: location::id::Type,
+	file_path_ids: Vec<file_path::id::Type>,
+	#[serde_as(as = "DisplayFromStr")]
+	passes: usize,
+}
+
+#[derive(Deserialize)]
+struct OldObjectValidatorJobInit {
+	location: location::Data,
+	sub_path: Option<PathBuf>,
+}
+
 impl From<OldJobReport> for sd_core_heavy_lifting::job_system::report::Report {
 	fn from(
 		OldJobReport {
@@ -66,6 +66,34 @@ pub enum P2PEvent {
 	SpacedropRejected {
 		id: Uuid,
 	},
+	// Shown on the node inviting another one into a library, to compare with the code of the `PairingRequest`
+	PairingCode {
+		id: Uuid,
+		identity: RemoteIdentity,
+		library_id: Uuid,
+		code: String,
+	},
+	PairingRequest {
+		id: Uuid,
+		identity: RemoteIdentity,
+		peer_name: String,
+		library_name: String,
+		code: String,
+	},
+	PairingComplete {
+		id: Uuid,
+		library_id: Uuid,
+	},
+	PairingRejected {
+		id: Uuid,
+	},
+	PairingTimedOut {
+		id: Uuid,
+	},
+	PairingFailed {
+		id: Uuid,
+		error: String,
+	},
 }
 
 /// A P2P hook which listens for events and sends them over a channel which can be connected to the frontend.
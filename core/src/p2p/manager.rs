@@ -64,6 +64,7 @@ pub struct P2PManager {
 	pub(crate) events: P2PEvents,
 	pub(super) spacedrop_pairing_reqs: Arc<Mutex<HashMap<Uuid, oneshot::Sender<Option<String>>>>>,
 	pub(super) spacedrop_cancellations: Arc<Mutex<HashMap<Uuid, Arc<AtomicBool>>>>,
+	pub(super) pairing_decisions: Arc<Mutex<HashMap<Uuid, oneshot::Sender<bool>>>>,
 	pub(crate) node_config: Arc<config::Manager>,
 	pub listeners: Mutex<Listeners>,
 	relay_config: Mutex<Vec<RelayServerEntry>>,
@@ -94,6 +95,7 @@ impl P2PManager {
 			quic_transport: quic,
 			spacedrop_pairing_reqs: Default::default(),
 			spacedrop_cancellations: Default::default(),
+			pairing_decisions: Default::default(),
 			node_config,
 			listeners: Default::default(),
 			relay_config: Default::default(),
@@ -388,7 +390,17 @@ async fn start(
 					error!("Failed to handle Spacedrop request");
 				}
 				Header::Sync => {
-					let Ok(mut tunnel) = Tunnel::responder(stream).await.map_err(|e| {
+					let Ok(mut tunnel) = Tunnel::responder(stream, |remote| {
+						let node = node.clone();
+						async move {
+							node.libraries
+								.get_library_for_instance(&remote)
+								.await
+								.map(|library| (*library.identity).clone())
+						}
+					})
+					.await
+					.map_err(|e| {
 						error!(?e, "Failed `Tunnel::responder`;");
 					}) else {
 						return;
@@ -423,6 +435,13 @@ async fn start(
 						}
 					};
 				}
+				Header::Pair => {
+					let Err(()) = operations::pairing::receiver(&node, stream).await else {
+						return;
+					};
+
+					error!("Failed to handle pairing request");
+				}
 				Header::RspcRemote => {
 					let remote = stream.remote_identity();
 					let Err(e) = operations::rspc::receiver(stream, &mut service, &node).await
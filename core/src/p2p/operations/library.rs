@@ -6,7 +6,7 @@ use std::{
 
 use sd_core_file_path_helper::IsolatedFilePathData;
 use sd_core_prisma_helpers::file_path_to_handle_p2p_serve_file;
-use sd_p2p::{Identity, RemoteIdentity, UnicastStream, P2P};
+use sd_p2p::{RemoteIdentity, UnicastStream, P2P};
 use sd_p2p_block::{BlockSize, Range, SpaceblockRequest, SpaceblockRequests, Transfer};
 use sd_prisma::prisma::file_path;
 use tokio::{
@@ -16,14 +16,14 @@ use tokio::{
 use tracing::debug;
 use uuid::Uuid;
 
-use crate::{p2p::Header, Node};
+use crate::{library::Library, p2p::Header, Node};
 
 /// Request a file from a remote library
 #[allow(unused)]
 pub async fn request_file(
 	p2p: Arc<P2P>,
 	identity: RemoteIdentity,
-	library_identity: &Identity,
+	library: &Arc<Library>,
 	file_path_id: Uuid,
 	range: Range,
 	output: impl AsyncWrite + Unpin,
@@ -41,7 +41,11 @@ pub async fn request_file(
 		)
 		.await?;
 
-	let mut stream = sd_p2p_tunnel::Tunnel::initiator(stream, library_identity).await?;
+	let mut stream = sd_p2p_tunnel::Tunnel::initiator(stream, &library.identity, |remote| {
+		let library = library.clone();
+		async move { library.has_instance(&remote).await }
+	})
+	.await?;
 
 	let block_size = BlockSize::from_stream(&mut stream).await?;
 	let size = stream.read_u64_le().await?;
@@ -60,7 +64,7 @@ pub async fn request_file(
 		&Arc::new(AtomicBool::new(false)),
 	)
 	.receive(&mut stream, output)
-	.await;
+	.await?;
 
 	Ok(())
 }
@@ -77,7 +81,13 @@ pub(crate) async fn receiver(
 	);
 
 	// The tunnel takes care of authentication and encrypts all traffic to the library to be certain we are talking to a node with the library.
-	let mut stream = sd_p2p_tunnel::Tunnel::responder(stream).await?;
+	let mut stream = sd_p2p_tunnel::Tunnel::responder(stream, |remote| async move {
+		node.libraries
+			.get_library_for_instance(&remote)
+			.await
+			.map(|library| (*library.identity).clone())
+	})
+	.await?;
 
 	let library = node
 		.libraries
@@ -0,0 +1,686 @@
+//! Pairing another node into a library over P2P, without the cloud.
+//!
+//! The flow is:
+//!  - initiator -> responder: [`Header::Pair`], the library and every instance the initiator knows, its own first
+//!  - responder -> initiator: the instance the responder will create for the library
+//!  - both users compare a short code derived from both node and instance identities and accept
+//!  - responder -> initiator: acceptance, signed with the responder instance identity
+//!  - initiator -> responder: confirmation, signed with the initiator instance identity
+//!  - responder -> initiator: done, once the library and its instances were created
+//!
+//! Node identities are authenticated by the transport and the signatures cover the whole transcript, so matching
+//! codes on both screens prove each user is pairing with the device they are looking at.
+//! Once paired, the initiator generates the sync operations of the library if needed and starts syncing.
+
+use crate::{
+	cloud::sync::receive::upsert_instance,
+	library::{Library, LibraryManagerError, LibraryName},
+	p2p::{sync, Header, P2PEvent, P2PManager, PeerMetadata},
+	Node,
+};
+
+use sd_p2p::{Identity, RemoteIdentity, UnicastStream};
+use sd_p2p_proto::{decode, encode};
+use sd_prisma::prisma::instance;
+use sd_utils::uuid_to_bytes;
+
+use std::{
+	collections::HashMap,
+	pin::pin,
+	sync::{Arc, PoisonError},
+	time::Duration,
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{
+	io::{AsyncRead, AsyncWriteExt},
+	sync::oneshot,
+	time::timeout,
+};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// The amount of time users have to compare the codes before the pairing is automatically rejected
+pub(crate) const PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+
+const CODE_CONTEXT: &str = "sd pairing v1 code";
+const INITIATOR_SIGNATURE_LABEL: &[u8] = b"sd pairing v1 initiator signature";
+const RESPONDER_SIGNATURE_LABEL: &[u8] = b"sd pairing v1 responder signature";
+
+#[derive(Debug, Error)]
+pub enum PairingError {
+	#[error("error connecting to peer")]
+	FailedPeerConnection,
+	#[error("error creating stream: {0}")]
+	FailedNewStream(#[from] sd_p2p::NewStreamError),
+	#[error("io error: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("error reading pairing message: {0}")]
+	Decode(#[from] decode::Error),
+	#[error("error decoding pairing message: {0}")]
+	InvalidMessage(#[from] rmp_serde::decode::Error),
+	#[error("unexpected pairing message")]
+	UnexpectedMessage,
+	#[error("peer announced a node identity other than the one it connected with")]
+	NodeIdentityMismatch,
+	#[error("invalid instance signature")]
+	InvalidSignature,
+	#[error("library already exists on this node")]
+	LibraryAlreadyExists,
+	#[error("pairing was rejected")]
+	Rejected,
+	#[error("pairing timed out")]
+	TimedOut,
+
+	#[error(transparent)]
+	LibraryManager(#[from] LibraryManagerError),
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+}
+
+/// Everything needed to create the `Instance` row of a library instance on another node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PairingInstance {
+	uuid: Uuid,
+	identity: RemoteIdentity,
+	node_id: Uuid,
+	node_remote_identity: RemoteIdentity,
+	metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum PairingMessage {
+	Request {
+		id: Uuid,
+		library_id: Uuid,
+		library_name: LibraryName,
+		library_description: Option<String>,
+		nonce: Uuid,
+		/// The initiator instance comes first
+		instances: Vec<PairingInstance>,
+	},
+	Challenge {
+		nonce: Uuid,
+		instance: PairingInstance,
+	},
+	Accepted {
+		signature: Vec<u8>,
+	},
+	Confirmed {
+		signature: Vec<u8>,
+	},
+	Rejected,
+	Done,
+}
+
+impl PairingMessage {
+	async fn from_stream(stream: &mut (impl AsyncRead + Unpin)) -> Result<Self, PairingError> {
+		Ok(rmp_serde::from_slice(&decode::buf(stream).await?)?)
+	}
+
+	fn to_bytes(&self) -> Vec<u8> {
+		let mut buf = vec![];
+		encode::buf(
+			&mut buf,
+			&rmp_serde::to_vec_named(self).expect("pairing messages always serialize"),
+		);
+		buf
+	}
+}
+
+struct Transcript(Vec<u8>);
+
+impl Transcript {
+	fn new(
+		library_id: Uuid,
+		initiator: &PairingInstance,
+		initiator_nonce: Uuid,
+		responder: &PairingInstance,
+		responder_nonce: Uuid,
+	) -> Self {
+		Self(
+			[
+				library_id.as_bytes().as_slice(),
+				&initiator.node_remote_identity.get_bytes(),
+				&initiator.identity.get_bytes(),
+				initiator_nonce.as_bytes(),
+				&responder.node_remote_identity.get_bytes(),
+				&responder.identity.get_bytes(),
+				responder_nonce.as_bytes(),
+			]
+			.concat(),
+		)
+	}
+
+	/// Six digits code shown to both users
+	fn code(&self) -> String {
+		let hash = blake3::derive_key(CODE_CONTEXT, &self.0);
+
+		format!(
+			"{:06}",
+			u32::from_le_bytes([hash[0], hash[1], hash[2], hash[3]]) % 1_000_000
+		)
+	}
+
+	fn sign(&self, identity: &Identity, label: &[u8]) -> Vec<u8> {
+		identity.sign(&[label, &self.0].concat()).to_vec()
+	}
+
+	fn verify(
+		&self,
+		identity: &RemoteIdentity,
+		label: &[u8],
+		signature: &[u8],
+	) -> Result<(), PairingError> {
+		identity
+			.verify(
+				&[label, &self.0].concat(),
+				signature
+					.try_into()
+					.map_err(|_| PairingError::InvalidSignature)?,
+			)
+			.map_err(|_| PairingError::InvalidSignature)
+	}
+}
+
+/// Pairs the node with the given identity into the library, returning the pairing id used by the
+/// pairing events
+pub async fn pair(
+	node: Arc<Node>,
+	identity: RemoteIdentity,
+	library: Arc<Library>,
+) -> Result<Uuid, PairingError> {
+	let peer = node
+		.p2p
+		.p2p
+		.peers()
+		.get(&identity)
+		.ok_or(PairingError::FailedPeerConnection)?
+		.clone();
+
+	let mut stream = peer.new_stream().await?;
+
+	let id = Uuid::new_v4();
+	debug!(pairing_id = %id, peer = %identity, library_id = %library.id, "Starting pairing;");
+
+	tokio::spawn(async move {
+		let res = initiator(&node, &library, id, &mut stream).await;
+
+		if res.is_ok() {
+			// The peer may not advertise the library yet, so it wouldn't be found by the originator
+			sync::originator_with_peer(
+				Arc::clone(&library),
+				Arc::clone(&library.sync),
+				identity,
+				peer,
+			)
+			.await;
+		}
+
+		finish(&node.p2p, id, library.id, res);
+	});
+
+	Ok(id)
+}
+
+async fn initiator(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	id: Uuid,
+	stream: &mut UnicastStream,
+) -> Result<(), PairingError> {
+	let config = library.config().await;
+	let node_config = node.config.get().await;
+
+	let local = PairingInstance {
+		uuid: library.instance_uuid,
+		identity: library.identity.to_remote_identity(),
+		node_id: node_config.id,
+		node_remote_identity: node_config.identity.to_remote_identity(),
+		metadata: node.p2p.peer_metadata(),
+	};
+
+	// The new instance must know every instance that created operations, or it can't ingest them
+	let instances = library
+		.db
+		.instance()
+		.find_many(vec![instance::id::not(config.instance_id)])
+		.exec()
+		.await?
+		.into_iter()
+		.filter_map(|instance| {
+			Some(PairingInstance {
+				uuid: Uuid::from_slice(&instance.pub_id).ok()?,
+				identity: RemoteIdentity::from_bytes(&instance.remote_identity).ok()?,
+				node_id: Uuid::from_slice(&instance.node_id).ok()?,
+				node_remote_identity: RemoteIdentity::from_bytes(
+					instance.node_remote_identity.as_ref()?,
+				)
+				.ok()?,
+				metadata: serde_json::from_slice(instance.metadata.as_ref()?).ok()?,
+			})
+		});
+
+	let nonce = Uuid::new_v4();
+
+	stream.write_all(&Header::Pair.to_bytes()).await?;
+	stream
+		.write_all(
+			&PairingMessage::Request {
+				id,
+				library_id: library.id,
+				library_name: config.name,
+				library_description: config.description,
+				nonce,
+				instances: [local.clone()].into_iter().chain(instances).collect(),
+			}
+			.to_bytes(),
+		)
+		.await?;
+	stream.flush().await?;
+
+	let PairingMessage::Challenge {
+		nonce: remote_nonce,
+		instance: remote,
+	} = PairingMessage::from_stream(stream).await?
+	else {
+		return Err(PairingError::UnexpectedMessage);
+	};
+
+	if remote.node_remote_identity != stream.remote_identity() {
+		return Err(PairingError::NodeIdentityMismatch);
+	}
+
+	let transcript = Transcript::new(library.id, &local, nonce, &remote, remote_nonce);
+
+	let decision = node.p2p.register_pairing(id);
+
+	node.p2p
+		.events
+		.send(P2PEvent::PairingCode {
+			id,
+			identity: stream.remote_identity(),
+			library_id: library.id,
+			code: transcript.code(),
+		})
+		.ok();
+
+	let signature = match timeout(PAIRING_TIMEOUT, decisions(decision, stream)).await {
+		Ok(Ok(Some(PairingMessage::Accepted { signature }))) => signature,
+		Ok(Ok(Some(PairingMessage::Rejected))) => return Err(PairingError::Rejected),
+		Ok(Ok(Some(_))) => return Err(PairingError::UnexpectedMessage),
+		Ok(Ok(None)) => return reject(stream, PairingError::Rejected).await,
+		Ok(Err(e)) => return Err(e),
+		Err(_) => return reject(stream, PairingError::TimedOut).await,
+	};
+
+	transcript.verify(&remote.identity, RESPONDER_SIGNATURE_LABEL, &signature)?;
+
+	stream
+		.write_all(
+			&PairingMessage::Confirmed {
+				signature: transcript.sign(&library.identity, INITIATOR_SIGNATURE_LABEL),
+			}
+			.to_bytes(),
+		)
+		.await?;
+	stream.flush().await?;
+
+	match timeout(PAIRING_TIMEOUT, PairingMessage::from_stream(stream)).await {
+		Ok(Ok(PairingMessage::Done)) => {}
+		Ok(Ok(_)) => return Err(PairingError::UnexpectedMessage),
+		Ok(Err(e)) => return Err(e),
+		Err(_) => return Err(PairingError::TimedOut),
+	}
+
+	upsert_instance(
+		library.id,
+		&library.db,
+		&library.sync,
+		&node.libraries,
+		&remote.uuid,
+		remote.identity,
+		&remote.node_id,
+		remote.node_remote_identity,
+		remote.metadata,
+	)
+	.await?;
+
+	node.libraries.enable_sync(library).await?;
+
+	Ok(())
+}
+
+/// Waits for both the local user decision and the remote one, the first rejection wins.
+///
+/// Returns `None` if the local user rejected the pairing.
+async fn decisions(
+	decision: oneshot::Receiver<bool>,
+	stream: &mut UnicastStream,
+) -> Result<Option<PairingMessage>, PairingError> {
+	let mut response = pin!(PairingMessage::from_stream(stream));
+	let mut decision = pin!(decision);
+
+	tokio::select! {
+		accepted = &mut decision => {
+			if !accepted.unwrap_or(false) {
+				return Ok(None);
+			}
+
+			response.await.map(Some)
+		}
+		response = &mut response => {
+			let response = response?;
+
+			if matches!(response, PairingMessage::Accepted { .. })
+				&& !decision.await.unwrap_or(false)
+			{
+				return Ok(None);
+			}
+
+			Ok(Some(response))
+		}
+	}
+}
+
+pub(crate) async fn receiver(node: &Arc<Node>, mut stream: UnicastStream) -> Result<(), ()> {
+	let mut library_id = None;
+
+	let res = responder(node, &mut stream, &mut library_id).await;
+
+	if let Some((id, library_id)) = library_id {
+		finish(&node.p2p, id, library_id, res);
+	} else if let Err(e) = res {
+		warn!(peer = %stream.remote_identity(), ?e, "Failed to read pairing request;");
+		return Err(());
+	}
+
+	Ok(())
+}
+
+async fn responder(
+	node: &Arc<Node>,
+	stream: &mut UnicastStream,
+	pairing: &mut Option<(Uuid, Uuid)>,
+) -> Result<(), PairingError> {
+	let PairingMessage::Request {
+		id,
+		library_id,
+		library_name,
+		library_description,
+		nonce: remote_nonce,
+		instances,
+	} = PairingMessage::from_stream(stream).await?
+	else {
+		return Err(PairingError::UnexpectedMessage);
+	};
+
+	*pairing = Some((id, library_id));
+
+	let Some(remote) = instances.first().cloned() else {
+		return Err(PairingError::UnexpectedMessage);
+	};
+
+	if remote.node_remote_identity != stream.remote_identity() {
+		return Err(PairingError::NodeIdentityMismatch);
+	}
+
+	info!(
+		pairing_id = %id,
+		%library_id,
+		peer = %stream.remote_identity(),
+		"Received pairing request;",
+	);
+
+	if node.libraries.get_library(&library_id).await.is_some() {
+		return reject(stream, PairingError::LibraryAlreadyExists).await;
+	}
+
+	// The instance is only created once the pairing is confirmed, but its identity is part of the code
+	let identity = Identity::new();
+	let node_config = node.config.get().await;
+
+	let local = PairingInstance {
+		uuid: Uuid::new_v4(),
+		identity: identity.to_remote_identity(),
+		node_id: node_config.id,
+		node_remote_identity: node_config.identity.to_remote_identity(),
+		metadata: node.p2p.peer_metadata(),
+	};
+
+	let nonce = Uuid::new_v4();
+
+	stream
+		.write_all(
+			&PairingMessage::Challenge {
+				nonce,
+				instance: local.clone(),
+			}
+			.to_bytes(),
+		)
+		.await?;
+	stream.flush().await?;
+
+	let transcript = Transcript::new(library_id, &remote, remote_nonce, &local, nonce);
+
+	let decision = node.p2p.register_pairing(id);
+
+	node.p2p
+		.events
+		.send(P2PEvent::PairingRequest {
+			id,
+			identity: stream.remote_identity(),
+			peer_name: PeerMetadata::from_hashmap(&remote.metadata)
+				.map_or_else(|_| "Unknown".into(), |metadata| metadata.name),
+			library_name: library_name.to_string(),
+			code: transcript.code(),
+		})
+		.ok();
+
+	match timeout(PAIRING_TIMEOUT, decision).await {
+		Ok(Ok(true)) => {}
+		Ok(Ok(false) | Err(_)) => return reject(stream, PairingError::Rejected).await,
+		Err(_) => return reject(stream, PairingError::TimedOut).await,
+	}
+
+	stream
+		.write_all(
+			&PairingMessage::Accepted {
+				signature: transcript.sign(&identity, RESPONDER_SIGNATURE_LABEL),
+			}
+			.to_bytes(),
+		)
+		.await?;
+	stream.flush().await?;
+
+	match timeout(PAIRING_TIMEOUT, PairingMessage::from_stream(stream)).await {
+		Ok(Ok(PairingMessage::Confirmed { signature })) => {
+			transcript.verify(&remote.identity, INITIATOR_SIGNATURE_LABEL, &signature)?;
+		}
+		Ok(Ok(PairingMessage::Rejected)) => return Err(PairingError::Rejected),
+		Ok(Ok(_)) => return Err(PairingError::UnexpectedMessage),
+		Ok(Err(e)) => return Err(e),
+		Err(_) => return Err(PairingError::TimedOut),
+	}
+
+	let now = Utc::now().fixed_offset();
+
+	// Nothing is seeded, the library contents arrive through sync
+	let library = node
+		.libraries
+		.create_with_uuid(
+			library_id,
+			library_name,
+			library_description,
+			false,
+			Some(instance::Create {
+				pub_id: uuid_to_bytes(&local.uuid),
+				remote_identity: local.identity.get_bytes().to_vec(),
+				node_id: local.node_id.as_bytes().to_vec(),
+				last_seen: now,
+				date_created: now,
+				_params: vec![
+					instance::identity::set(Some(identity.to_bytes())),
+					instance::metadata::set(Some(
+						serde_json::to_vec(&local.metadata).expect("invalid node metadata"),
+					)),
+				],
+			}),
+			node,
+			true,
+		)
+		.await?;
+
+	for instance in instances {
+		upsert_instance(
+			library.id,
+			&library.db,
+			&library.sync,
+			&node.libraries,
+			&instance.uuid,
+			instance.identity,
+			&instance.node_id,
+			instance.node_remote_identity,
+			instance.metadata,
+		)
+		.await?;
+	}
+
+	stream.write_all(&PairingMessage::Done.to_bytes()).await?;
+	stream.flush().await?;
+
+	Ok(())
+}
+
+/// Tells the other side the pairing is over before failing with `e`
+async fn reject(stream: &mut UnicastStream, e: PairingError) -> Result<(), PairingError> {
+	stream
+		.write_all(&PairingMessage::Rejected.to_bytes())
+		.await
+		.ok();
+	stream.flush().await.ok();
+
+	Err(e)
+}
+
+fn finish(p2p: &P2PManager, id: Uuid, library_id: Uuid, res: Result<(), PairingError>) {
+	p2p.pairing_decisions
+		.lock()
+		.unwrap_or_else(PoisonError::into_inner)
+		.remove(&id);
+
+	let event = match res {
+		Ok(()) => {
+			info!(pairing_id = %id, %library_id, "Pairing completed;");
+			P2PEvent::PairingComplete { id, library_id }
+		}
+		Err(PairingError::Rejected) => {
+			debug!(pairing_id = %id, "Pairing was rejected;");
+			P2PEvent::PairingRejected { id }
+		}
+		Err(PairingError::TimedOut) => {
+			debug!(pairing_id = %id, "Pairing timed out;");
+			P2PEvent::PairingTimedOut { id }
+		}
+		Err(e) => {
+			warn!(pairing_id = %id, ?e, "Pairing failed;");
+			P2PEvent::PairingFailed {
+				id,
+				error: e.to_string(),
+			}
+		}
+	};
+
+	p2p.events.send(event).ok();
+}
+
+// TODO: Move these off the manager
+impl P2PManager {
+	fn register_pairing(&self, id: Uuid) -> oneshot::Receiver<bool> {
+		let (tx, rx) = oneshot::channel();
+
+		self.pairing_decisions
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.insert(id, tx);
+
+		rx
+	}
+
+	/// Accepts or rejects a pairing, once the user compared the codes shown on both nodes
+	pub fn decide_pairing(&self, id: Uuid, accepted: bool) {
+		if let Some(chan) = self
+			.pairing_decisions
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove(&id)
+		{
+			chan.send(accepted).ok();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn instance(identity: &Identity, node_identity: &Identity) -> PairingInstance {
+		PairingInstance {
+			uuid: Uuid::new_v4(),
+			identity: identity.to_remote_identity(),
+			node_id: Uuid::new_v4(),
+			node_remote_identity: node_identity.to_remote_identity(),
+			metadata: HashMap::new(),
+		}
+	}
+
+	#[test]
+	fn test_transcript() {
+		let (initiator_identity, responder_identity) = (Identity::new(), Identity::new());
+		let initiator = instance(&initiator_identity, &Identity::new());
+		let responder = instance(&responder_identity, &Identity::new());
+		let (library_id, initiator_nonce, responder_nonce) =
+			(Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+		let transcript = Transcript::new(
+			library_id,
+			&initiator,
+			initiator_nonce,
+			&responder,
+			responder_nonce,
+		);
+
+		let code = transcript.code();
+		assert_eq!(code.len(), 6);
+		assert!(code.chars().all(|c| c.is_ascii_digit()));
+
+		// A different responder instance gets a different code, unless we are very unlucky
+		let impostor = instance(&Identity::new(), &Identity::new());
+		assert_ne!(
+			Transcript::new(
+				library_id,
+				&initiator,
+				initiator_nonce,
+				&impostor,
+				responder_nonce
+			)
+			.code(),
+			code
+		);
+
+		let signature = transcript.sign(&responder_identity, RESPONDER_SIGNATURE_LABEL);
+		assert!(transcript
+			.verify(&responder.identity, RESPONDER_SIGNATURE_LABEL, &signature)
+			.is_ok());
+
+		// Signatures can't be reflected back to the other side
+		assert!(transcript
+			.verify(&responder.identity, INITIATOR_SIGNATURE_LABEL, &signature)
+			.is_err());
+		assert!(transcript
+			.verify(&initiator.identity, RESPONDER_SIGNATURE_LABEL, &signature)
+			.is_err());
+	}
+}
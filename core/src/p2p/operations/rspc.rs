@@ -1,13 +1,58 @@
-use std::{error::Error, sync::Arc};
+use std::{collections::HashMap, convert::Infallible, error::Error, sync::Arc};
 
-use axum::{body::Body, http, Router};
-use hyper::{server::conn::Http, Response};
+use axum::{
+	body::Body,
+	extract::Query,
+	http::{self, Method, Request, StatusCode},
+	response::IntoResponse,
+	Router,
+};
+use hyper::{server::conn::Http, service::service_fn, Response};
 use sd_p2p::{RemoteIdentity, UnicastStream, P2P};
+use serde_json::Value;
 use tokio::io::AsyncWriteExt;
-use tracing::debug;
+use tower_service::Service;
+use tracing::{debug, warn};
+use uuid::Uuid;
 
 use crate::{p2p::Header, Node};
 
+/// The procedures a remote peer can always call, which allow browsing a library and fetching its files but never
+/// modifying it. Anything else must be granted through [`NodeConfigP2P::remote_access_allowlist`].
+///
+/// [`NodeConfigP2P::remote_access_allowlist`]: crate::node::config::NodeConfigP2P::remote_access_allowlist
+const DEFAULT_REMOTE_PROCEDURES: &[&str] = &[
+	"buildInfo",
+	"nodeState",
+	"library.list",
+	"library.statistics",
+	"library.kindStatistics",
+	"locations.list",
+	"locations.get",
+	"files.get",
+	"files.getPath",
+	"files.getMediaData",
+	"search.paths",
+	"search.pathsCount",
+	"search.objects",
+	"search.objectsCount",
+	"tags.list",
+	"tags.get",
+	"tags.getForObject",
+	"tags.getWithObjects",
+	"labels.list",
+	"labels.get",
+	"labels.getForObject",
+	"labels.getWithObjects",
+];
+
+/// The procedures from [`DEFAULT_REMOTE_PROCEDURES`] that aren't called on a library, any peer that is an instance of
+/// one of our libraries can call them. Every other procedure must be given the library it is called on.
+const NODE_REMOTE_PROCEDURES: &[&str] = &["buildInfo", "nodeState", "library.list"];
+
+/// The maximum size of a request body we are willing to buffer to authorise a request.
+const MAX_REQUEST_BODY_SIZE: usize = 1024 * 1024; // 1MB
+
 /// Transfer an rspc query to a remote node.
 pub async fn remote_rspc(
 	p2p: Arc<P2P>,
@@ -36,24 +81,157 @@ pub async fn remote_rspc(
 pub(crate) async fn receiver(
 	stream: UnicastStream,
 	service: &mut Router,
-	node: &Node,
+	node: &Arc<Node>,
 ) -> Result<(), Box<dyn Error>> {
-	debug!(
-		peer = %stream.remote_identity(),
-		"Received http request from;",
-	);
+	let remote = stream.remote_identity();
+	debug!(peer = %remote, "Received http request from;");
 
-	// TODO: Authentication
-	#[allow(clippy::todo)]
 	if !node.config.get().await.p2p.enable_remote_access {
-		todo!("No way buddy!");
+		return Err("Remote access is disabled".into());
 	}
 
 	Http::new()
 		.http1_only(true)
 		.http1_keep_alive(true)
-		.serve_connection(stream, service)
+		.serve_connection(
+			stream,
+			service_fn(|request| {
+				let node = Arc::clone(node);
+				let mut service = service.clone();
+
+				async move {
+					match authorise(&node, &remote, request).await {
+						Ok(request) => service.call(request).await,
+						Err(status) => Ok::<_, Infallible>(status.into_response()),
+					}
+				}
+			}),
+		)
 		.with_upgrades()
 		.await
 		.map_err(Into::into)
 }
+
+/// Checks the remote peer is allowed to make the request, returning it so it can be served.
+///
+/// Remote peers must be running an instance of the library they are accessing, and can only call the procedures
+/// from [`DEFAULT_REMOTE_PROCEDURES`] or the ones granted in the node config. Requests that don't name a library are
+/// denied, unless they are for one of the [`NODE_REMOTE_PROCEDURES`].
+async fn authorise(
+	node: &Node,
+	remote: &RemoteIdentity,
+	request: Request<Body>,
+) -> Result<Request<Body>, StatusCode> {
+	let path = request.uri().path().to_owned();
+
+	if let Some(procedure) = path.strip_prefix("/rspc/") {
+		// Websockets multiplex any procedure over a single connection so we can't authorise them
+		if procedure == "ws" || !is_procedure_allowed(node, procedure).await {
+			warn!(peer = %remote, %procedure, "Remote peer is not allowed to call procedure;");
+			return Err(StatusCode::FORBIDDEN);
+		}
+
+		let (parts, body) = request.into_parts();
+		let body = hyper::body::to_bytes(http_body::Limited::new(body, MAX_REQUEST_BODY_SIZE))
+			.await
+			.map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+
+		let input = if parts.method == Method::GET {
+			Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+				.ok()
+				.and_then(|Query(mut query)| query.remove("input"))
+				.and_then(|input| serde_json::from_str::<Value>(&input).ok())
+		} else {
+			serde_json::from_slice::<Value>(&body).ok()
+		};
+
+		let library_id = input
+			.as_ref()
+			.and_then(|input| input.get("library_id"))
+			.map(|library_id| {
+				library_id
+					.as_str()
+					.and_then(|library_id| Uuid::parse_str(library_id).ok())
+					.ok_or(StatusCode::BAD_REQUEST)
+			})
+			.transpose()?;
+
+		match library_id {
+			Some(library_id) => authorise_library(node, remote, library_id).await?,
+			None if NODE_REMOTE_PROCEDURES.contains(&procedure) => {
+				authorise_any_library(node, remote).await?
+			}
+			None => {
+				warn!(peer = %remote, %procedure, "Remote peer called a procedure without a library;");
+				return Err(StatusCode::FORBIDDEN);
+			}
+		}
+
+		Ok(Request::from_parts(parts, Body::from(body)))
+	} else if let Some(path) = path.strip_prefix("/uri/") {
+		let mut segments = path.split('/');
+		match segments.next() {
+			Some("file" | "thumbnail" | "thumbstrip" | "video-preview") => {}
+			// Any other route could read files outside of a library
+			_ => return Err(StatusCode::FORBIDDEN),
+		}
+
+		// Thumbnails of ephemeral files aren't tied to a library, so they can't be shared
+		let library_id = segments
+			.next()
+			.filter(|segment| *segment != "ephemeral")
+			.ok_or(StatusCode::FORBIDDEN)
+			.and_then(|library_id| {
+				Uuid::parse_str(library_id).map_err(|_| StatusCode::BAD_REQUEST)
+			})?;
+
+		authorise_library(node, remote, library_id).await?;
+
+		Ok(request)
+	} else {
+		Err(StatusCode::FORBIDDEN)
+	}
+}
+
+async fn is_procedure_allowed(node: &Node, procedure: &str) -> bool {
+	DEFAULT_REMOTE_PROCEDURES.contains(&procedure)
+		|| node
+			.config
+			.get()
+			.await
+			.p2p
+			.remote_access_allowlist
+			.contains(procedure)
+}
+
+/// Requests targeting a library require the remote peer to have an instance of it.
+async fn authorise_library(
+	node: &Node,
+	remote: &RemoteIdentity,
+	library_id: Uuid,
+) -> Result<(), StatusCode> {
+	let library = node
+		.libraries
+		.get_library(&library_id)
+		.await
+		.ok_or(StatusCode::NOT_FOUND)?;
+
+	if library.has_node(remote).await {
+		return Ok(());
+	}
+
+	warn!(peer = %remote, %library_id, "Remote peer isn't an instance of the library;");
+	Err(StatusCode::FORBIDDEN)
+}
+
+/// Requests for the [`NODE_REMOTE_PROCEDURES`] require the remote peer to have an instance of any library we have.
+async fn authorise_any_library(node: &Node, remote: &RemoteIdentity) -> Result<(), StatusCode> {
+	for library in node.libraries.get_all().await {
+		if library.has_node(remote).await {
+			return Ok(());
+		}
+	}
+
+	warn!(peer = %remote, "Remote peer isn't an instance of any library;");
+	Err(StatusCode::FORBIDDEN)
+}
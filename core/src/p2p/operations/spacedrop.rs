@@ -1,6 +1,7 @@
 use std::{
 	borrow::Cow,
-	path::PathBuf,
+	ffi::OsString,
+	path::{Path, PathBuf},
 	sync::{
 		atomic::{AtomicBool, Ordering},
 		Arc, PoisonError,
@@ -11,10 +12,12 @@ use std::{
 use crate::p2p::{Header, P2PEvent, P2PManager};
 use futures::future::join_all;
 use sd_p2p::{RemoteIdentity, UnicastStream};
-use sd_p2p_block::{BlockSize, Range, SpaceblockRequest, SpaceblockRequests, Transfer};
+use sd_p2p_block::{
+	BlockSize, Range, SpaceblockRequest, SpaceblockRequests, Transfer, TransferError,
+};
 use thiserror::Error;
 use tokio::{
-	fs::{create_dir_all, File},
+	fs::{self, create_dir_all, File, OpenOptions},
 	io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
 	sync::oneshot,
 	time::{sleep, Instant},
@@ -122,9 +125,15 @@ pub async fn spacedrop(
 				p2p.events.send(P2PEvent::SpacedropRejected { id }).ok();
 				return;
 			}
-			Ok(1) => {}                 // Okay
-			Ok(_) => todo!(),           // TODO: Proper error
-			Err(e) => todo!("{:?}", e), // TODO: Proper error
+			Ok(1) => {} // Okay
+			Ok(response) => {
+				debug!(spacedrop_id = %id, peer = %identity, %response, "Invalid Spacedrop response;");
+				return;
+			}
+			Err(e) => {
+				debug!(spacedrop_id = %id, peer = %identity, ?e, "Failed to read Spacedrop response;");
+				return;
+			}
 		}
 
 		let cancelled = Arc::new(AtomicBool::new(false));
@@ -297,17 +306,17 @@ pub(crate) async fn receiver(
 						// TODO: make sure the other peer times out or we retry???
 					})?;
 
-					let names = req.requests.iter().map(|req| req.name.clone()).collect::<Vec<_>>();
+					let files = req.requests.iter().map(|req| (req.name.clone(), req.size)).collect::<Vec<_>>();
 					let mut transfer = Transfer::new(&req, |percent| {
 						this.events.send(P2PEvent::SpacedropProgress { id, percent }).ok();
 					}, &cancelled);
 
 					let file_path = PathBuf::from(file_path);
-					let names_len = names.len();
-					for file_name in names {
+					let files_len = files.len();
+					for (file_name, size) in files {
 						 // When transferring more than 1 file we wanna join the incoming file name to the directory provided by the user
 						 let mut path = file_path.clone();
-						 if names_len != 1 {
+						 if files_len != 1 {
 							// We know the `file_path` will be a directory so we can just push the file name to it
 							path.push(&file_name);
 						}
@@ -333,30 +342,73 @@ pub(crate) async fn receiver(
 							})?;
 						}
 
-						let f = File::create(&path).await.map_err(|e| {
-							error!(
-								spacedrop_id = %id,
-								creating_file_at = %path.display(),
-								?e,
-								"Error creating file;",
-							);
+						// Data is received into a partial file, so if the transfer drops we can resume from it next time
+						let part_path = part_path(&path);
+						let f = OpenOptions::new()
+							.read(true)
+							.write(true)
+							.create(true)
+							.truncate(false)
+							.open(&part_path)
+							.await
+							.map_err(|e| {
+								error!(
+									spacedrop_id = %id,
+									creating_file_at = %part_path.display(),
+									?e,
+									"Error creating file;",
+								);
 
-							// TODO: Send error to the frontend
+								// TODO: Send error to the frontend
 
-							// TODO: Send error to remote peer
-						})?;
+								// TODO: Send error to remote peer
+							})?;
 						let f = BufWriter::new(f);
-						if let Err(e) = transfer.receive(&mut stream, f).await {
+						if let Err(e) = transfer.resume(&mut stream, f).await {
 							error!(
 								spacedrop_id = %id,
 								%file_name,
 								?e,
 								"Error receiving file;");
 
+							// If the transfer was just interrupted we keep the partial file around to resume from it,
+							// otherwise its contents can't be trusted
+							if !matches!(e, TransferError::Io(_)) {
+								fs::remove_file(&part_path).await.ok();
+							}
+
 							// TODO: Send error to frontend
 
 							break;
 						}
+
+						// Cancelling the transfer also returns successfully, so we only keep complete files
+						match fs::metadata(&part_path).await {
+							Ok(metadata) if metadata.len() == size => {
+								fs::rename(&part_path, &path).await.map_err(|e| {
+									error!(
+										spacedrop_id = %id,
+										from = %part_path.display(),
+										to = %path.display(),
+										?e,
+										"Error renaming received file;",
+									);
+								})?;
+							}
+							Ok(_) => {
+								info!(spacedrop_id = %id, %file_name, "Transfer didn't complete;");
+								break;
+							}
+							Err(e) => {
+								error!(
+									spacedrop_id = %id,
+									path = %part_path.display(),
+									?e,
+									"Error reading received file metadata;",
+								);
+								break;
+							}
+						}
 					}
 
 					info!(spacedrop_id = %id, "Completed;");
@@ -380,3 +432,10 @@ pub(crate) async fn receiver(
 
 	Ok(())
 }
+
+/// The path a file is received into until its transfer is completed and verified.
+fn part_path(path: &Path) -> PathBuf {
+	let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+	file_name.push(".sdpart");
+	path.with_file_name(file_name)
+}
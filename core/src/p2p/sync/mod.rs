@@ -5,8 +5,11 @@ use crate::{
 	sync::{self, GetOpsArgs},
 };
 
+use sd_p2p::{Peer, RemoteIdentity};
 use sd_p2p_proto::{decode, encode};
+use sd_prisma::prisma::instance;
 use sd_sync::CompressedCRDTOperations;
+use sd_utils::from_bytes_to_uuid;
 
 use std::sync::Arc;
 
@@ -18,7 +21,7 @@ use super::P2PManager;
 mod proto;
 pub use proto::*;
 
-pub use originator::run as originator;
+pub use originator::{run as originator, run_with_peer as originator_with_peer};
 mod originator {
 	use crate::p2p::Header;
 
@@ -95,40 +98,81 @@ mod originator {
 				continue;
 			};
 
-			let sync = sync.clone();
+			tokio::spawn(run_with_peer(
+				library.clone(),
+				sync.clone(),
+				remote_identity,
+				peer,
+			));
+		}
+	}
+
+	/// Alerts a single peer of new sync events, even if it doesn't advertise the library yet,
+	/// like right after pairing
+	pub async fn run_with_peer(
+		library: Arc<Library>,
+		sync: Arc<sync::Manager>,
+		remote_identity: RemoteIdentity,
+		peer: Arc<Peer>,
+	) {
+		debug!(
+			?remote_identity,
+			%library.id,
+			"Alerting peer of new sync events for library;"
+		);
+
+		let mut stream = peer.new_stream().await.unwrap();
 
+		stream.write_all(&Header::Sync.to_bytes()).await.unwrap();
+
+		// Unknown peers and bad signatures are rejected by the handshake, that's not a bug
+		let Ok(mut tunnel) = Tunnel::initiator(stream, &library.identity, |remote| {
 			let library = library.clone();
-			tokio::spawn(async move {
-				debug!(
-					?remote_identity,
-					%library.id,
-					"Alerting peer of new sync events for library;"
-				);
-
-				let mut stream = peer.new_stream().await.unwrap();
-
-				stream.write_all(&Header::Sync.to_bytes()).await.unwrap();
-
-				let mut tunnel = Tunnel::initiator(stream, &library.identity).await.unwrap();
-
-				tunnel
-					.write_all(&SyncMessage::NewOperations.to_bytes())
-					.await
-					.unwrap();
-				tunnel.flush().await.unwrap();
-
-				while let Ok(rx::MainRequest::GetOperations(args)) =
-					rx::MainRequest::from_stream(&mut tunnel).await
-				{
-					let ops = sync.get_ops(args).await.unwrap();
-
-					tunnel
-						.write_all(&tx::Operations(CompressedCRDTOperations::new(ops)).to_bytes())
-						.await
-						.unwrap();
-					tunnel.flush().await.unwrap();
+			async move { library.has_instance(&remote).await }
+		})
+		.await
+		.map_err(|e| {
+			warn!(?e, ?remote_identity, "Failed `Tunnel::initiator`;");
+		}) else {
+			return;
+		};
+
+		tunnel
+			.write_all(&SyncMessage::NewOperations.to_bytes())
+			.await
+			.unwrap();
+		tunnel.flush().await.unwrap();
+
+		let remote_instance = library
+			.db
+			.instance()
+			.find_first(vec![instance::remote_identity::equals(
+				tunnel.library_remote_identity().get_bytes().to_vec(),
+			)])
+			.select(instance::select!({ pub_id }))
+			.exec()
+			.await
+			.ok()
+			.flatten()
+			.map(|instance| from_bytes_to_uuid(&instance.pub_id));
+
+		while let Ok(rx::MainRequest::GetOperations(args)) =
+			rx::MainRequest::from_stream(&mut tunnel).await
+		{
+			// The peer only asks for operations newer than the ones it already has
+			if let Some(remote_instance) = remote_instance {
+				if let Err(e) = sync.acknowledge(remote_instance, &args.clocks).await {
+					warn!(?e, "Failed to record sync acknowledgement;");
 				}
-			});
+			}
+
+			let ops = sync.get_ops(args).await.unwrap();
+
+			tunnel
+				.write_all(&tx::Operations(CompressedCRDTOperations::new(ops)).to_bytes())
+				.await
+				.unwrap();
+			tunnel.flush().await.unwrap();
 		}
 	}
 }
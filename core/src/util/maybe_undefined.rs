@@ -32,6 +32,12 @@ impl<T> MaybeUndefined<T> {
 	}
 }
 
+impl<T> Default for MaybeUndefined<T> {
+	fn default() -> Self {
+		Self::Undefined
+	}
+}
+
 impl<T> From<MaybeUndefined<T>> for Option<Option<T>> {
 	fn from(v: MaybeUndefined<T>) -> Option<Option<T>> {
 		match v {
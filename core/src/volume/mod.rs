@@ -13,7 +13,7 @@ use sd_utils::{msgpack, uuid_to_bytes};
 use std::{
 	fmt::Display,
 	hash::{Hash, Hasher},
-	path::PathBuf,
+	path::{Path, PathBuf},
 	sync::{Arc, OnceLock},
 };
 
@@ -66,6 +66,8 @@ pub struct Volume {
 	pub disk_type: DiskType,
 	pub file_system: Option<String>,
 	pub is_root_filesystem: bool,
+	/// Identifies the file system across mounts, like its UUID or serial number
+	pub identifier: Option<String>,
 }
 
 impl Hash for Volume {
@@ -77,6 +79,7 @@ impl Hash for Volume {
 		});
 		self.disk_type.hash(state);
 		self.file_system.hash(state);
+		self.identifier.hash(state);
 	}
 }
 
@@ -85,6 +88,7 @@ impl PartialEq for Volume {
 		self.name == other.name
 			&& self.disk_type == other.disk_type
 			&& self.file_system == other.file_system
+			&& self.identifier == other.identifier
 			// Leaving mount points for last because O(n * m)
 			&& self
 				.mount_points
@@ -111,9 +115,53 @@ impl From<VolumeError> for rspc::Error {
 	}
 }
 
+/// Finds the volume holding the given path, along with the mount point the path is under
+pub fn find_volume_for_path<'v>(
+	volumes: &'v [Volume],
+	path: impl AsRef<Path>,
+) -> Option<(&'v Volume, &'v Path)> {
+	let path = path.as_ref();
+
+	volumes
+		.iter()
+		.flat_map(|volume| {
+			volume
+				.mount_points
+				.iter()
+				.map(move |mount_point| (volume, mount_point.as_path()))
+		})
+		.filter(|(_, mount_point)| path.starts_with(mount_point))
+		// Volumes can be mounted inside other volumes, so the deepest mount point wins
+		.max_by_key(|(_, mount_point)| mount_point.components().count())
+}
+
+/// Maps each device to the UUID of the file system it holds
+#[cfg(target_os = "linux")]
+async fn file_system_uuids() -> std::collections::HashMap<PathBuf, String> {
+	let mut uuids = std::collections::HashMap::new();
+
+	let mut entries = match tokio::fs::read_dir("/dev/disk/by-uuid").await {
+		Ok(entries) => entries,
+		Err(e) => {
+			error!(?e, "Failed to read file system UUIDs;");
+			return uuids;
+		}
+	};
+
+	while let Ok(Some(entry)) = entries.next_entry().await {
+		if let Ok(device) = tokio::fs::canonicalize(entry.path()).await {
+			uuids.insert(device, entry.file_name().to_string_lossy().to_string());
+		}
+	}
+
+	uuids
+}
+
 #[cfg(target_os = "linux")]
 pub async fn get_volumes() -> Vec<Volume> {
-	use std::{collections::HashMap, path::Path};
+	use std::collections::HashMap;
+
+	let file_system_uuids = file_system_uuids().await;
 
 	let mut sys = sys_guard().lock().await;
 	sys.refresh_disks_list();
@@ -130,6 +178,8 @@ pub async fn get_volumes() -> Vec<Volume> {
 		let available_capacity = disk.available_space();
 		let is_root_filesystem = mount_point.is_absolute() && mount_point.parent().is_none();
 
+		let mut identifier = None;
+
 		let mut disk_path: PathBuf = PathBuf::from(disk_name);
 		if file_system.as_ref().map(|fs| fs == "ZFS").unwrap_or(false) {
 			// Use a custom path for ZFS disks to avoid conflicts with normal disks paths
@@ -149,6 +199,8 @@ pub async fn get_volumes() -> Vec<Volume> {
 				Ok(real_path) => real_path,
 			};
 
+			identifier = file_system_uuids.get(&real_path).cloned();
+
 			// Check if disk is a symlink to another disk
 			if real_path != disk_path {
 				// Disk is a symlink to another disk, assign it to the same volume
@@ -220,6 +272,7 @@ pub async fn get_volumes() -> Vec<Volume> {
 			total_capacity,
 			available_capacity,
 			is_root_filesystem,
+			identifier,
 		});
 	}
 
@@ -273,6 +326,7 @@ pub async fn get_volumes() -> Vec<Volume> {
 			total_capacity: total_space,
 			available_capacity: free_space,
 			is_root_filesystem: true,
+			identifier: None,
 		});
 	}
 
@@ -300,6 +354,66 @@ struct HDIUtilInfo {
 	images: Vec<ImageInfo>,
 }
 
+#[cfg(target_os = "macos")]
+#[derive(Deserialize)]
+struct DiskUtilInfo {
+	#[serde(rename = "VolumeUUID")]
+	volume_uuid: Option<String>,
+}
+
+#[cfg(target_os = "macos")]
+async fn volume_identifier(mount_point: &Path) -> Option<String> {
+	let output = tokio::process::Command::new("diskutil")
+		.args(["info", "-plist"])
+		.arg(mount_point)
+		.output()
+		.await
+		.map_err(|e| error!(?e, "Failed to execute diskutil;"))
+		.ok()?;
+
+	if !output.status.success() {
+		error!("Command diskutil return error");
+		return None;
+	}
+
+	plist::from_bytes::<DiskUtilInfo>(&output.stdout)
+		.map_err(|e| error!(?e, "Failed to parse diskutil output;"))
+		.ok()?
+		.volume_uuid
+}
+
+#[cfg(windows)]
+async fn volume_identifier(mount_point: &Path) -> Option<String> {
+	use windows::{core::HSTRING, Win32::Storage::FileSystem::GetVolumeInformationW};
+
+	let mut serial_number = 0u32;
+
+	// SAFETY: the root path is a valid null terminated string and we only ask for the serial number
+	unsafe {
+		GetVolumeInformationW(
+			&HSTRING::from(mount_point.as_os_str()),
+			None,
+			Some(std::ptr::addr_of_mut!(serial_number)),
+			None,
+			None,
+			None,
+		)
+	}
+	.map_err(|e| error!(?e, "Failed to get volume serial number;"))
+	.ok()?;
+
+	Some(format!(
+		"{:04X}-{:04X}",
+		serial_number >> 16,
+		serial_number & 0xFFFF
+	))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "ios", target_os = "macos", windows)))]
+async fn volume_identifier(_mount_point: &Path) -> Option<String> {
+	None
+}
+
 // Android does not work via sysinfo and JNI is a pain to maintain. Therefore, we use React-Native-FS to get the volume data of the device.
 // We leave the function though to be built for Android because otherwise, the build will fail.
 #[cfg(not(any(target_os = "linux", target_os = "ios")))]
@@ -435,6 +549,8 @@ pub async fn get_volumes() -> Vec<Volume> {
 			name = "Unknown".to_string()
 		}
 
+		let identifier = volume_identifier(&mount_point).await;
+
 		Some(Volume {
 			name,
 			disk_type: if disk.is_removable() {
@@ -451,6 +567,7 @@ pub async fn get_volumes() -> Vec<Volume> {
 			total_capacity,
 			available_capacity,
 			is_root_filesystem,
+			identifier,
 		})
 	}))
 	.await
@@ -0,0 +1,286 @@
+use crate::{
+	cloud::secret_key::SecretKey,
+	primitives::{OneShotNonce, StreamNonce},
+	Error,
+};
+
+use aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Tag, XChaCha20Poly1305};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use zeroize::Zeroizing;
+
+use super::kdf::{Kdf, RootKey, Salt};
+
+/// Magic bytes at the start of every container, used to detect encrypted files regardless of their extension
+pub const MAGIC_BYTES: [u8; 8] = *b"sdcrypt\0";
+
+const KEY_LEN: usize = 32;
+const WRAPPED_KEY_LEN: usize = KEY_LEN + size_of::<Tag>();
+
+/// Offset where the wrapping nonce starts, everything before it is authenticated along with the wrapped key
+const AUTHENTICATED_LEN: usize = MAGIC_BYTES.len()
+	+ 1 // version
+	+ 1 // kdf id
+	+ 3 * size_of::<u32>() // kdf parameters
+	+ Salt::LEN
+	+ size_of::<StreamNonce>();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Version {
+	V1 = 1,
+}
+
+impl TryFrom<u8> for Version {
+	type Error = Error;
+
+	fn try_from(value: u8) -> Result<Self, Self::Error> {
+		match value {
+			1 => Ok(Self::V1),
+			_ => Err(Error::UnsupportedVersion(value)),
+		}
+	}
+}
+
+impl From<Version> for u8 {
+	fn from(version: Version) -> Self {
+		match version {
+			Version::V1 => 1,
+		}
+	}
+}
+
+/// Fixed size header written at the start of every container.
+///
+/// Layout of a [`Version::V1`] header, with all integers in little endian:
+///
+/// | Size | Field                                                         |
+/// |------|---------------------------------------------------------------|
+/// | 8    | [`MAGIC_BYTES`]                                               |
+/// | 1    | Version                                                       |
+/// | 1    | KDF identifier                                                |
+/// | 12   | KDF parameters, three `u32`                                   |
+/// | 16   | Salt used with the KDF                                        |
+/// | 20   | STREAM nonce used for the file contents                       |
+/// | 24   | Nonce used to wrap the file key                               |
+/// | 48   | File key wrapped with the key derived from the passphrase     |
+///
+/// The wrapped key is authenticated along with every field before it, so tampering with the
+/// header makes the file key unrecoverable.
+#[derive(Debug, Clone)]
+pub struct Header {
+	version: Version,
+	kdf: Kdf,
+	salt: Salt,
+	nonce: StreamNonce,
+	key_nonce: OneShotNonce,
+	wrapped_key: [u8; WRAPPED_KEY_LEN],
+}
+
+impl Header {
+	pub const SIZE: usize = AUTHENTICATED_LEN + size_of::<OneShotNonce>() + WRAPPED_KEY_LEN;
+
+	/// Create a new header for a file encrypted with `file_key` and `nonce`, wrapping the
+	/// file key with `root_key`, which must be the key derived by `kdf` with `salt`.
+	pub(super) fn new(
+		kdf: Kdf,
+		salt: Salt,
+		nonce: StreamNonce,
+		key_nonce: OneShotNonce,
+		root_key: &SecretKey,
+		file_key: &SecretKey,
+	) -> Result<Self, Error> {
+		let mut header = Self {
+			version: Version::V1,
+			kdf,
+			salt,
+			nonce,
+			key_nonce,
+			wrapped_key: [0; WRAPPED_KEY_LEN],
+		};
+
+		let wrapped_key = XChaCha20Poly1305::new(&root_key.0)
+			.encrypt(
+				&header.key_nonce,
+				Payload {
+					msg: &file_key.0,
+					aad: &header.authenticated_bytes(),
+				},
+			)
+			.map_err(|aead::Error| Error::Encrypt)?;
+
+		header.wrapped_key.copy_from_slice(&wrapped_key);
+
+		Ok(header)
+	}
+
+	#[must_use]
+	pub const fn version(&self) -> Version {
+		self.version
+	}
+
+	#[must_use]
+	pub const fn kdf(&self) -> &Kdf {
+		&self.kdf
+	}
+
+	#[must_use]
+	pub const fn salt(&self) -> &Salt {
+		&self.salt
+	}
+
+	/// Check if this header was created with a [`RootKey`] derived with the same parameters as `root_key`.
+	///
+	/// This doesn't mean the passphrase is the same, only that trying to decrypt with it makes sense.
+	#[must_use]
+	pub fn is_wrapped_by(&self, root_key: &RootKey) -> bool {
+		self.kdf == *root_key.kdf() && self.salt == *root_key.salt()
+	}
+
+	pub(super) const fn nonce(&self) -> &StreamNonce {
+		&self.nonce
+	}
+
+	/// Recover the file key, `root_key` must be derived with this header's [`Kdf`] and [`Salt`]
+	pub(super) fn unwrap_key(&self, root_key: &SecretKey) -> Result<SecretKey, Error> {
+		let file_key = Zeroizing::new(
+			XChaCha20Poly1305::new(&root_key.0)
+				.decrypt(
+					&self.key_nonce,
+					Payload {
+						msg: &self.wrapped_key,
+						aad: &self.authenticated_bytes(),
+					},
+				)
+				.map_err(|aead::Error| Error::Decrypt)?,
+		);
+
+		let mut key = Zeroizing::new([0u8; KEY_LEN]);
+		if file_key.len() != KEY_LEN {
+			return Err(Error::Decrypt);
+		}
+		key.copy_from_slice(&file_key);
+
+		Ok(SecretKey::new(*key))
+	}
+
+	fn authenticated_bytes(&self) -> [u8; AUTHENTICATED_LEN] {
+		let mut bytes = [0u8; AUTHENTICATED_LEN];
+		let mut offset = 0;
+
+		let mut put = |field: &[u8]| {
+			bytes[offset..offset + field.len()].copy_from_slice(field);
+			offset += field.len();
+		};
+
+		put(&MAGIC_BYTES);
+		put(&[u8::from(self.version)]);
+
+		match self.kdf {
+			Kdf::Argon2id {
+				memory_cost,
+				time_cost,
+				parallelism,
+			} => {
+				put(&[Kdf::ARGON2ID_ID]);
+				put(&memory_cost.to_le_bytes());
+				put(&time_cost.to_le_bytes());
+				put(&parallelism.to_le_bytes());
+			}
+		}
+
+		put(&self.salt.0);
+		put(&self.nonce);
+
+		bytes
+	}
+
+	#[must_use]
+	pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+		let mut bytes = [0u8; Self::SIZE];
+
+		bytes[..AUTHENTICATED_LEN].copy_from_slice(&self.authenticated_bytes());
+		bytes[AUTHENTICATED_LEN..Self::SIZE - WRAPPED_KEY_LEN].copy_from_slice(&self.key_nonce);
+		bytes[Self::SIZE - WRAPPED_KEY_LEN..].copy_from_slice(&self.wrapped_key);
+
+		bytes
+	}
+
+	pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Result<Self, Error> {
+		let (magic, rest) = bytes.split_at(MAGIC_BYTES.len());
+		if magic != MAGIC_BYTES {
+			return Err(Error::NotAContainer);
+		}
+
+		let version = Version::try_from(rest[0])?;
+
+		let read_u32 = |offset: usize| {
+			let mut int = [0u8; size_of::<u32>()];
+			int.copy_from_slice(&rest[offset..offset + size_of::<u32>()]);
+			u32::from_le_bytes(int)
+		};
+
+		let kdf = match rest[1] {
+			Kdf::ARGON2ID_ID => Kdf::Argon2id {
+				memory_cost: read_u32(2),
+				time_cost: read_u32(6),
+				parallelism: read_u32(10),
+			},
+			id => return Err(Error::UnsupportedKdf(id)),
+		};
+
+		let rest = &rest[14..];
+
+		let (salt_bytes, rest) = rest.split_at(Salt::LEN);
+		let mut salt = Salt([0; Salt::LEN]);
+		salt.0.copy_from_slice(salt_bytes);
+
+		let (nonce_bytes, rest) = rest.split_at(size_of::<StreamNonce>());
+		let mut nonce = StreamNonce::default();
+		nonce.copy_from_slice(nonce_bytes);
+
+		let (key_nonce_bytes, wrapped_key_bytes) = rest.split_at(size_of::<OneShotNonce>());
+		let mut key_nonce = OneShotNonce::default();
+		key_nonce.copy_from_slice(key_nonce_bytes);
+
+		let mut wrapped_key = [0; WRAPPED_KEY_LEN];
+		wrapped_key.copy_from_slice(wrapped_key_bytes);
+
+		Ok(Self {
+			version,
+			kdf,
+			salt,
+			nonce,
+			key_nonce,
+			wrapped_key,
+		})
+	}
+
+	/// Read a header from the start of a container, leaving the reader at the start of the encrypted contents
+	pub async fn read(mut reader: impl AsyncRead + Unpin + Send) -> Result<Self, Error> {
+		let mut bytes = [0u8; Self::SIZE];
+
+		reader
+			.read_exact(&mut bytes)
+			.await
+			.map_err(|e| match e.kind() {
+				std::io::ErrorKind::UnexpectedEof => Error::NotAContainer,
+				_ => Error::DecryptIo {
+					context: "Reading container header",
+					source: e,
+				},
+			})?;
+
+		Self::from_bytes(&bytes)
+	}
+
+	pub async fn write(&self, mut writer: impl AsyncWrite + Unpin + Send) -> Result<(), Error> {
+		writer
+			.write_all(&self.to_bytes())
+			.await
+			.map_err(|e| Error::EncryptIo {
+				context: "Writing container header",
+				source: e,
+			})
+	}
+}
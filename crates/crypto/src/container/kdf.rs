@@ -0,0 +1,151 @@
+use crate::{cloud::secret_key::SecretKey, rng::CryptoRng, Error, Protected};
+
+use std::fmt;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use blake3::Hash;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Salt used to derive a key from a passphrase, it doesn't need to be secret but must be unique
+/// for each passphrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Salt(pub(super) [u8; Self::LEN]);
+
+impl Salt {
+	pub const LEN: usize = 16;
+
+	#[inline]
+	#[must_use]
+	pub fn generate(rng: &mut CryptoRng) -> Self {
+		Self(rng.generate_fixed())
+	}
+}
+
+impl Serialize for Salt {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		serdect::array::serialize_hex_lower_or_bin(&self.0, serializer)
+	}
+}
+
+impl<'de> Deserialize<'de> for Salt {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let mut buf = [0u8; Self::LEN];
+		serdect::array::deserialize_hex_or_bin(&mut buf, deserializer)?;
+		Ok(Self(buf))
+	}
+}
+
+/// Key derivation function used to turn a passphrase into a [`SecretKey`], along with its parameters.
+///
+/// The parameters are stored in every container header, so changing the defaults never breaks
+/// files that were already encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kdf {
+	Argon2id {
+		/// Memory size in KiB
+		memory_cost: u32,
+		/// Number of iterations
+		time_cost: u32,
+		/// Degree of parallelism
+		parallelism: u32,
+	},
+}
+
+impl Default for Kdf {
+	fn default() -> Self {
+		Self::Argon2id {
+			memory_cost: 64 * 1024,
+			time_cost: 3,
+			parallelism: 1,
+		}
+	}
+}
+
+impl Kdf {
+	pub(super) const ARGON2ID_ID: u8 = 1;
+
+	/// Derive a [`SecretKey`] from the received passphrase and salt.
+	///
+	/// This is purposely slow and memory hungry, so it should be called from a blocking context.
+	pub fn derive(&self, passphrase: &Protected<Vec<u8>>, salt: &Salt) -> Result<SecretKey, Error> {
+		match *self {
+			Self::Argon2id {
+				memory_cost,
+				time_cost,
+				parallelism,
+			} => {
+				let mut key = Zeroizing::new([0u8; 32]);
+
+				Argon2::new(
+					Algorithm::Argon2id,
+					Version::V0x13,
+					Params::new(memory_cost, time_cost, parallelism, Some(key.len()))
+						.map_err(Error::KeyDerivation)?,
+				)
+				.hash_password_into(passphrase.expose(), &salt.0, key.as_mut())
+				.map_err(Error::KeyDerivation)?;
+
+				Ok(SecretKey::new(*key))
+			}
+		}
+	}
+}
+
+/// A [`SecretKey`] derived from a passphrase, along with the [`Kdf`] and [`Salt`] used to derive it.
+///
+/// It is the key that wraps the random key of every file in a container.
+#[derive(Clone)]
+pub struct RootKey {
+	key: SecretKey,
+	kdf: Kdf,
+	salt: Salt,
+}
+
+impl fmt::Debug for RootKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RootKey")
+			.field("kdf", &self.kdf)
+			.field("salt", &self.salt)
+			.finish_non_exhaustive()
+	}
+}
+
+impl RootKey {
+	/// This is purposely slow and memory hungry, so it should be called from a blocking context.
+	pub fn derive(passphrase: &Protected<Vec<u8>>, kdf: Kdf, salt: Salt) -> Result<Self, Error> {
+		Ok(Self {
+			key: kdf.derive(passphrase, &salt)?,
+			kdf,
+			salt,
+		})
+	}
+
+	#[must_use]
+	pub const fn kdf(&self) -> &Kdf {
+		&self.kdf
+	}
+
+	#[must_use]
+	pub const fn salt(&self) -> &Salt {
+		&self.salt
+	}
+
+	/// Hash of the derived key, useful to check if a passphrase is correct without storing the key itself
+	#[must_use]
+	pub fn to_hash(&self) -> Hash {
+		self.key.to_hash()
+	}
+
+	pub(super) const fn key(&self) -> &SecretKey {
+		&self.key
+	}
+}
@@ -0,0 +1,212 @@
+//! Self-describing container format for files encrypted by Spacedrive.
+//!
+//! A container is a fixed size [`Header`] followed by the file contents encrypted with
+//! XChaCha20-Poly1305 STREAM, using the same block layout as [`StreamEncryption`].
+//!
+//! Every file is encrypted with its own random key, which is stored in the header wrapped by
+//! a key derived from the user's passphrase. The header also holds the [`Kdf`] parameters and
+//! [`Salt`] used for that derivation, so the passphrase is all that is needed to decrypt a container.
+
+use crate::{
+	cloud::{decrypt::StreamDecryption, encrypt::StreamEncryption, secret_key::SecretKey},
+	primitives::OneShotNonce,
+	rng::CryptoRng,
+	Error,
+};
+
+use std::pin::pin;
+
+use futures::StreamExt;
+use rand::RngCore;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+mod header;
+mod kdf;
+
+pub use header::{Header, Version, MAGIC_BYTES};
+pub use kdf::{Kdf, RootKey, Salt};
+
+/// Encrypt everything read from `reader` into `writer` as a container, wrapping the file key with `root_key`.
+///
+/// The root key is received already derived so the expensive derivation is done only once for many files.
+pub async fn encrypt(
+	root_key: &RootKey,
+	reader: impl AsyncRead + Unpin + Send,
+	mut writer: impl AsyncWrite + Unpin + Send,
+	rng: &mut CryptoRng,
+) -> Result<(), Error> {
+	let file_key = SecretKey::generate(rng);
+
+	let mut key_nonce = OneShotNonce::default();
+	rng.fill_bytes(&mut key_nonce);
+
+	let (nonce, stream) = StreamEncryption::encrypt(&file_key, reader, rng);
+
+	Header::new(
+		*root_key.kdf(),
+		*root_key.salt(),
+		nonce,
+		key_nonce,
+		root_key.key(),
+		&file_key,
+	)?
+	.write(&mut writer)
+	.await?;
+
+	let mut stream = pin!(stream);
+
+	while let Some(res) = stream.next().await {
+		writer
+			.write_all(&res?)
+			.await
+			.map_err(|e| Error::EncryptIo {
+				context: "Writing encrypted block to writer",
+				source: e,
+			})?;
+	}
+
+	writer.flush().await.map_err(|e| Error::EncryptIo {
+		context: "Flushing writer",
+		source: e,
+	})
+}
+
+/// Decrypt the contents of a container into `writer`.
+///
+/// The `header` must have been already read from `reader` with [`Header::read`], and `root_key`
+/// must be derived with the header's [`Kdf`] and [`Salt`], which can be checked with [`Header::is_wrapped_by`].
+pub async fn decrypt(
+	root_key: &RootKey,
+	header: &Header,
+	reader: impl AsyncRead + Unpin + Send,
+	writer: impl AsyncWrite + Unpin + Send,
+) -> Result<(), Error> {
+	let file_key = header.unwrap_key(root_key.key())?;
+
+	StreamDecryption::decrypt(&file_key, header.nonce(), reader, writer).await
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{primitives::EncryptedBlock, Protected};
+
+	use super::*;
+
+	const TEST_KDF: Kdf = Kdf::Argon2id {
+		memory_cost: 64,
+		time_cost: 1,
+		parallelism: 1,
+	};
+
+	async fn encrypt_message(
+		message: &[u8],
+		passphrase: &Protected<Vec<u8>>,
+		rng: &mut CryptoRng,
+	) -> Vec<u8> {
+		let root_key = RootKey::derive(passphrase, TEST_KDF, Salt::generate(rng)).unwrap();
+
+		let mut container = vec![];
+		encrypt(&root_key, message, &mut container, rng)
+			.await
+			.unwrap();
+
+		container
+	}
+
+	async fn decrypt_container(
+		mut container: &[u8],
+		passphrase: &Protected<Vec<u8>>,
+	) -> Result<Vec<u8>, Error> {
+		let header = Header::read(&mut container).await?;
+		let root_key = RootKey::derive(passphrase, *header.kdf(), *header.salt())?;
+		assert!(header.is_wrapped_by(&root_key));
+
+		let mut message = vec![];
+		decrypt(&root_key, &header, container, &mut message).await?;
+
+		Ok(message)
+	}
+
+	#[tokio::test]
+	async fn container_roundtrip() {
+		let mut rng = CryptoRng::new().unwrap();
+		let passphrase = Protected::new(b"correct horse battery staple".to_vec());
+
+		let mut message =
+			vec![0u8; EncryptedBlock::PLAIN_TEXT_SIZE * 2 + EncryptedBlock::PLAIN_TEXT_SIZE / 3];
+		rng.fill_bytes(&mut message);
+
+		let container = encrypt_message(&message, &passphrase, &mut rng).await;
+
+		assert_eq!(&container[..MAGIC_BYTES.len()], &MAGIC_BYTES);
+		assert_eq!(
+			decrypt_container(&container, &passphrase).await.unwrap(),
+			message
+		);
+	}
+
+	#[tokio::test]
+	async fn container_wrong_passphrase() {
+		let mut rng = CryptoRng::new().unwrap();
+
+		let container = encrypt_message(
+			b"Nao sei, soh sei que foi assim",
+			&Protected::new(b"right".to_vec()),
+			&mut rng,
+		)
+		.await;
+
+		assert!(matches!(
+			decrypt_container(&container, &Protected::new(b"wrong".to_vec())).await,
+			Err(Error::Decrypt)
+		));
+	}
+
+	#[tokio::test]
+	async fn container_tampered_header() {
+		let mut rng = CryptoRng::new().unwrap();
+		let passphrase = Protected::new(b"passphrase".to_vec());
+
+		let mut container = encrypt_message(b"Auto da Compadecida", &passphrase, &mut rng).await;
+
+		// Flipping a bit of the STREAM nonce must be caught when unwrapping the file key
+		container[Header::SIZE - 80] ^= 1;
+
+		assert!(matches!(
+			decrypt_container(&container, &passphrase).await,
+			Err(Error::Decrypt)
+		));
+	}
+
+	#[tokio::test]
+	async fn not_a_container() {
+		assert!(matches!(
+			decrypt_container(b"just some plain text", &Protected::new(vec![])).await,
+			Err(Error::NotAContainer)
+		));
+	}
+
+	#[test]
+	fn header_roundtrip() {
+		let mut rng = CryptoRng::new().unwrap();
+		let root_key = SecretKey::generate(&mut rng);
+		let file_key = SecretKey::generate(&mut rng);
+
+		let header = Header::new(
+			Kdf::default(),
+			Salt::generate(&mut rng),
+			Default::default(),
+			Default::default(),
+			&root_key,
+			&file_key,
+		)
+		.unwrap();
+
+		let parsed = Header::from_bytes(&header.to_bytes()).unwrap();
+
+		assert_eq!(parsed.version(), Version::V1);
+		assert_eq!(parsed.kdf(), &Kdf::default());
+		assert_eq!(parsed.salt(), header.salt());
+		assert_eq!(parsed.unwrap_key(&root_key).unwrap(), file_key);
+	}
+}
@@ -37,6 +37,16 @@ pub enum Error {
 		source: io::Error,
 	},
 
+	/// Container errors
+	#[error("not a Spacedrive encrypted container")]
+	NotAContainer,
+	#[error("unsupported container version: {0}")]
+	UnsupportedVersion(u8),
+	#[error("unsupported key derivation function: <id='{0}'>")]
+	UnsupportedKdf(u8),
+	#[error("key derivation error: {0}")]
+	KeyDerivation(argon2::Error),
+
 	#[error("hex error: {0}")]
 	Hex(#[from] hex::FromHexError),
 
@@ -30,6 +30,7 @@
 
 // pub mod crypto;
 pub mod cloud;
+pub mod container;
 pub mod ct;
 pub mod erase;
 pub mod error;
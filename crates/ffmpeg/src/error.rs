@@ -44,8 +44,14 @@ pub enum Error {
 	FrameDecodeError,
 	#[error("Failed to seek video")]
 	SeekError,
+	#[error("No picture attached to the file")]
+	NoAttachedPicture,
 	#[error("Seek not allowed")]
 	SeekNotAllowed,
+	#[error("Transcoding was aborted")]
+	TranscodeAborted,
+	#[error("No audio or video stream can be transcoded to the requested format")]
+	NothingToTranscode,
 
 	#[error(transparent)]
 	FileIO(#[from] FileIOError),
@@ -1,5 +1,5 @@
 use std::{
-	ffi::{CStr, CString},
+	ffi::{c_char, CStr, CString},
 	ptr,
 };
 
@@ -8,9 +8,11 @@ use crate::{
 	utils::check_error, Error,
 };
 use ffmpeg_sys_next::{
-	avfilter_get_by_name, avfilter_graph_alloc, avfilter_graph_config,
-	avfilter_graph_create_filter, avfilter_graph_free, avfilter_link, AVFilterContext,
-	AVFilterGraph, AVRational,
+	av_buffersink_set_frame_size, av_channel_layout_describe, av_get_pix_fmt_name,
+	av_get_sample_fmt_name, avfilter_get_by_name, avfilter_graph_alloc, avfilter_graph_config,
+	avfilter_graph_create_filter, avfilter_graph_free, avfilter_link, AVChannelLayout,
+	AVFilterContext, AVFilterGraph, AVPixelFormat, AVRational, AVSampleFormat,
+	AV_CODEC_CAP_VARIABLE_FRAME_SIZE,
 };
 
 pub struct FFmpegFilterGraph(*mut AVFilterGraph);
@@ -157,6 +159,185 @@ impl<'a> FFmpegFilterGraph {
 		Ok((filter_graph, filter_source_ctx, filter_sink_ctx))
 	}
 
+	/// Graph converting decoded video frames to the size and pixel format expected by the encoder
+	pub(crate) fn transcode_video_graph(
+		time_base: &AVRational,
+		decoder_ctx: &FFmpegCodecContext,
+		encoder_ctx: &FFmpegCodecContext,
+	) -> Result<(Self, &'a mut AVFilterContext, &'a mut AVFilterContext), Error> {
+		let mut filter_graph = Self::new()?;
+
+		let decoder = decoder_ctx.as_ref();
+		let encoder = encoder_ctx.as_ref();
+
+		let args = format!(
+			"video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+			decoder.width,
+			decoder.height,
+			// AVPixelFormat is an i32 enum, so it's safe to cast it to i32
+			decoder.pix_fmt as i32,
+			time_base.num,
+			time_base.den,
+			decoder.sample_aspect_ratio.num,
+			i32::max(decoder.sample_aspect_ratio.den, 1)
+		);
+
+		let mut filter_source = ptr::null_mut();
+		filter_graph.setup_filter(
+			&mut filter_source,
+			c"buffer",
+			c"transcode_buffer",
+			Some(CString::new(args)?.as_c_str()),
+			"Failed to create filter source",
+		)?;
+		let filter_source_ctx = unsafe { filter_source.as_mut() }.ok_or(FFmpegError::NullError)?;
+
+		let mut filter_sink = ptr::null_mut();
+		filter_graph.setup_filter(
+			&mut filter_sink,
+			c"buffersink",
+			c"transcode_buffersink",
+			None,
+			"Failed to create filter sink",
+		)?;
+		let filter_sink_ctx = unsafe { filter_sink.as_mut() }.ok_or(FFmpegError::NullError)?;
+
+		let mut scale_filter = ptr::null_mut();
+		filter_graph.setup_filter(
+			&mut scale_filter,
+			c"scale",
+			c"transcode_scale",
+			Some(CString::new(format!("w={}:h={}", encoder.width, encoder.height))?.as_c_str()),
+			"Failed to create scale filter",
+		)?;
+
+		let mut format_filter = ptr::null_mut();
+		filter_graph.setup_filter(
+			&mut format_filter,
+			c"format",
+			c"transcode_format",
+			Some(CString::new(format!("pix_fmts={}", pix_fmt_name(encoder.pix_fmt)?))?.as_c_str()),
+			"Failed to create format filter",
+		)?;
+
+		Self::link(
+			format_filter,
+			0,
+			filter_sink_ctx,
+			0,
+			"Failed to link final filter",
+		)?;
+
+		Self::link(
+			scale_filter,
+			0,
+			format_filter,
+			0,
+			"Failed to link scale filter",
+		)?;
+
+		Self::link(
+			filter_source_ctx,
+			0,
+			scale_filter,
+			0,
+			"Failed to link source filter",
+		)?;
+
+		filter_graph.config()?;
+
+		Ok((filter_graph, filter_source_ctx, filter_sink_ctx))
+	}
+
+	/// Graph converting decoded audio frames to the sample format, sample rate and channel layout
+	/// expected by the encoder, also splitting them in frames of the size it requires
+	pub(crate) fn transcode_audio_graph(
+		time_base: &AVRational,
+		decoder_ctx: &FFmpegCodecContext,
+		encoder_ctx: &FFmpegCodecContext,
+	) -> Result<(Self, &'a mut AVFilterContext, &'a mut AVFilterContext), Error> {
+		let mut filter_graph = Self::new()?;
+
+		let decoder = decoder_ctx.as_ref();
+		let encoder = encoder_ctx.as_ref();
+
+		let args = format!(
+			"time_base={}/{}:sample_rate={}:sample_fmt={}:channel_layout={}",
+			time_base.num,
+			time_base.den,
+			decoder.sample_rate,
+			sample_fmt_name(decoder.sample_fmt)?,
+			channel_layout_description(&decoder.ch_layout)?,
+		);
+
+		let mut filter_source = ptr::null_mut();
+		filter_graph.setup_filter(
+			&mut filter_source,
+			c"abuffer",
+			c"transcode_abuffer",
+			Some(CString::new(args)?.as_c_str()),
+			"Failed to create filter source",
+		)?;
+		let filter_source_ctx = unsafe { filter_source.as_mut() }.ok_or(FFmpegError::NullError)?;
+
+		let mut filter_sink = ptr::null_mut();
+		filter_graph.setup_filter(
+			&mut filter_sink,
+			c"abuffersink",
+			c"transcode_abuffersink",
+			None,
+			"Failed to create filter sink",
+		)?;
+		let filter_sink_ctx = unsafe { filter_sink.as_mut() }.ok_or(FFmpegError::NullError)?;
+
+		let mut format_filter = ptr::null_mut();
+		filter_graph.setup_filter(
+			&mut format_filter,
+			c"aformat",
+			c"transcode_aformat",
+			Some(
+				CString::new(format!(
+					"sample_fmts={}:sample_rates={}:channel_layouts={}",
+					sample_fmt_name(encoder.sample_fmt)?,
+					encoder.sample_rate,
+					channel_layout_description(&encoder.ch_layout)?,
+				))?
+				.as_c_str(),
+			),
+			"Failed to create audio format filter",
+		)?;
+
+		Self::link(
+			format_filter,
+			0,
+			filter_sink_ctx,
+			0,
+			"Failed to link final filter",
+		)?;
+
+		Self::link(
+			filter_source_ctx,
+			0,
+			format_filter,
+			0,
+			"Failed to link source filter",
+		)?;
+
+		filter_graph.config()?;
+
+		// Most audio encoders only accept frames with an exact number of samples
+		let variable_frame_size = unsafe { encoder.codec.as_ref() }.is_some_and(|codec| {
+			(codec.capabilities.unsigned_abs() & AV_CODEC_CAP_VARIABLE_FRAME_SIZE) != 0
+		});
+		if !variable_frame_size && encoder.frame_size > 0 {
+			unsafe {
+				av_buffersink_set_frame_size(filter_sink_ctx, encoder.frame_size.unsigned_abs());
+			}
+		}
+
+		Ok((filter_graph, filter_source_ctx, filter_sink_ctx))
+	}
+
 	pub(crate) fn as_mut(&mut self) -> &mut AVFilterGraph {
 		unsafe { self.0.as_mut() }.expect("initialized on struct creation")
 	}
@@ -254,3 +435,36 @@ fn thumb_scale_filter_args(
 
 	scale
 }
+
+fn pix_fmt_name(pix_fmt: AVPixelFormat) -> Result<String, FFmpegError> {
+	unsafe { av_get_pix_fmt_name(pix_fmt).as_ref() }
+		.map(|name| {
+			unsafe { CStr::from_ptr(name) }
+				.to_string_lossy()
+				.to_string()
+		})
+		.ok_or(FFmpegError::NullError)
+}
+
+fn sample_fmt_name(sample_fmt: AVSampleFormat) -> Result<String, FFmpegError> {
+	unsafe { av_get_sample_fmt_name(sample_fmt).as_ref() }
+		.map(|name| {
+			unsafe { CStr::from_ptr(name) }
+				.to_string_lossy()
+				.to_string()
+		})
+		.ok_or(FFmpegError::NullError)
+}
+
+fn channel_layout_description(ch_layout: &AVChannelLayout) -> Result<String, Error> {
+	let mut buffer: [c_char; 128] = [0; 128];
+
+	check_error(
+		unsafe { av_channel_layout_describe(ch_layout, buffer.as_mut_ptr(), buffer.len()) },
+		"Failed to describe channel layout",
+	)?;
+
+	Ok(unsafe { CStr::from_ptr(buffer.as_ptr()) }
+		.to_string_lossy()
+		.to_string())
+}
@@ -191,6 +191,42 @@ impl FFmpegFormatContext {
 		Err(FFmpegError::StreamNotFound)?
 	}
 
+	/// Finds a picture attached to the file, like the cover art of an audio file, preferring the front cover
+	pub(crate) fn find_attached_picture_stream(&self) -> Result<&mut AVStream, Error> {
+		let mut attached_pictures = vec![];
+
+		'outer: for stream_idx in 0..self.as_ref().nb_streams {
+			let Some(stream) = self.stream(stream_idx) else {
+				continue;
+			};
+
+			if stream.disposition & AV_DISPOSITION_ATTACHED_PIC == 0 {
+				continue;
+			}
+
+			if let Some(metadata) = unsafe { stream.metadata.as_mut() }
+				.map(|metadata| FFmpegDictionary::new(Some(metadata)))
+			{
+				for (key, value) in &metadata {
+					if key == "comment" && value.as_deref() == Some("Cover (front)") {
+						attached_pictures.insert(0, stream_idx);
+						continue 'outer;
+					}
+				}
+			}
+
+			attached_pictures.push(stream_idx);
+		}
+
+		for stream_index in attached_pictures {
+			if let Some(stream) = self.stream(stream_index) {
+				return Ok(stream);
+			}
+		}
+
+		Err(Error::NoAttachedPicture)
+	}
+
 	fn formats(&self) -> Vec<String> {
 		unsafe { self.as_ref().iformat.as_ref() }
 			.and_then(|format| unsafe { format.name.as_ref() })
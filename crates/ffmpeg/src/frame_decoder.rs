@@ -65,16 +65,7 @@ impl FrameDecoder {
 
 		let preferred_stream_id = u32::try_from(video_stream.index)?;
 
-		let video_codec = unsafe { video_stream.codecpar.as_ref() }
-			.and_then(|codecpar| unsafe { avcodec_find_decoder(codecpar.codec_id).as_ref() })
-			.ok_or(FFmpegError::DecoderNotFound)?;
-
-		let mut video_codec_context = FFmpegCodecContext::new()?;
-		video_codec_context.parameters_to_context(
-			unsafe { video_stream.codecpar.as_ref() }.ok_or(FFmpegError::NullError)?,
-		)?;
-		video_codec_context.as_mut().workaround_bugs = 1;
-		video_codec_context.open2(video_codec)?;
+		let video_codec_context = open_codec(video_stream)?;
 
 		let frame = unsafe { av_frame_alloc() };
 		if frame.is_null() {
@@ -92,6 +83,31 @@ impl FrameDecoder {
 		})
 	}
 
+	/// Decodes the picture attached to the file instead of its video, e.g. the cover art of an audio file
+	pub(crate) fn new_attached_picture(filename: impl AsRef<Path>) -> Result<Self, Error> {
+		let mut format_context =
+			FFmpegFormatContext::open_file(from_path(filename.as_ref())?.as_c_str())?;
+
+		// No probe score check here, as we never decode the audio streams the check protects us from
+		format_context.find_stream_info()?;
+
+		let picture_stream = format_context.find_attached_picture_stream()?;
+
+		let preferred_stream_id = u32::try_from(picture_stream.index)?;
+
+		let codec_context = open_codec(picture_stream)?;
+
+		Ok(Self {
+			format_ctx: format_context,
+			preferred_stream_id,
+			codec_ctx: codec_context,
+			frame: FFmpegFrame::new()?,
+			packet: ptr::null_mut(),
+			allow_seek: false,
+			embedded: true,
+		})
+	}
+
 	pub(crate) fn use_embedded(&mut self) -> bool {
 		self.embedded
 	}
@@ -110,12 +126,14 @@ impl FrameDecoder {
 		Ok(())
 	}
 
-	pub(crate) fn seek(&mut self, seconds: i64) -> Result<(), Error> {
+	pub(crate) fn seek(&mut self, seconds: f64) -> Result<(), Error> {
 		if !self.allow_seek {
 			return Ok(());
 		}
 
-		let timestamp = i64::from(AV_TIME_BASE).checked_mul(seconds).unwrap_or(0);
+		#[allow(clippy::cast_possible_truncation)]
+		// This conversion is ok because we don't worry much about precision here
+		let timestamp = (seconds * f64::from(AV_TIME_BASE)).round() as i64;
 
 		check_error(
 			unsafe { av_seek_frame(self.format_ctx.as_mut(), -1, timestamp, 0) },
@@ -281,6 +299,21 @@ impl FrameDecoder {
 	}
 }
 
+fn open_codec(stream: &AVStream) -> Result<FFmpegCodecContext, Error> {
+	let codec = unsafe { stream.codecpar.as_ref() }
+		.and_then(|codecpar| unsafe { avcodec_find_decoder(codecpar.codec_id).as_ref() })
+		.ok_or(FFmpegError::DecoderNotFound)?;
+
+	let mut codec_context = FFmpegCodecContext::new()?;
+	codec_context.parameters_to_context(
+		unsafe { stream.codecpar.as_ref() }.ok_or(FFmpegError::NullError)?,
+	)?;
+	codec_context.as_mut().workaround_bugs = 1;
+	codec_context.open2(codec)?;
+
+	Ok(codec_context)
+}
+
 impl Drop for FrameDecoder {
 	fn drop(&mut self) {
 		unsafe {
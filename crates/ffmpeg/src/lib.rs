@@ -29,7 +29,10 @@
 
 use crate::{format_ctx::FFmpegFormatContext, frame_decoder::FrameDecoder, utils::from_path};
 
-use std::path::Path;
+use std::{
+	path::Path,
+	sync::{atomic::AtomicBool, Arc},
+};
 
 use ffmpeg_sys_next::{av_log_set_level, AV_LOG_FATAL};
 
@@ -41,14 +44,16 @@ mod format_ctx;
 mod frame_decoder;
 pub mod model;
 mod thumbnailer;
+mod transcoder;
 mod utils;
 mod video_frame;
 
 pub use error::Error;
 pub use frame_decoder::ThumbnailSize;
 pub use model::FFmpegMediaData;
-pub use thumbnailer::ThumbnailerBuilder;
+pub use thumbnailer::{Frame, ThumbnailerBuilder};
 use tokio::task::spawn_blocking;
+pub use transcoder::TranscodeOptions;
 
 /// Helper function to generate retrieve media data from from a video/audio file
 pub async fn probe(filename: impl AsRef<Path> + Send) -> Result<FFmpegMediaData, Error> {
@@ -97,6 +102,62 @@ pub async fn to_thumbnail(
 		.await
 }
 
+/// Helper function to generate a thumbnail file from the picture attached to a file, like the
+/// cover art of an audio file
+pub async fn attached_picture_to_thumbnail(
+	file_path: impl AsRef<Path> + Send,
+	output_thumbnail_path: impl AsRef<Path> + Send,
+	size: ThumbnailSize,
+	quality: f32,
+) -> Result<(), Error> {
+	// Reduce the amount of logs generated by FFmpeg
+	unsafe { av_log_set_level(AV_LOG_FATAL) };
+
+	ThumbnailerBuilder::new()
+		.size(size)
+		.quality(quality)?
+		.build()
+		.process_attached_picture(file_path, output_thumbnail_path)
+		.await
+}
+
+/// Helper function to take `count` evenly spaced frames from a video file, like the ones used
+/// to scrub through it
+pub async fn to_frames(
+	video_file_path: impl AsRef<Path> + Send,
+	count: usize,
+	size: ThumbnailSize,
+) -> Result<Vec<Frame>, Error> {
+	// Reduce the amount of logs generated by FFmpeg
+	unsafe { av_log_set_level(AV_LOG_FATAL) };
+
+	ThumbnailerBuilder::new()
+		.size(size)
+		.build()
+		.process_frames(video_file_path, count)
+		.await
+}
+
+/// Helper function to transcode a video or audio file, the output format is guessed from the
+/// output file extension. Setting `abort` stops the transcoding early, leaving a partial output
+/// file behind for the caller to remove.
+pub async fn transcode(
+	input_path: impl AsRef<Path> + Send,
+	output_path: impl AsRef<Path> + Send,
+	options: TranscodeOptions,
+	abort: Arc<AtomicBool>,
+) -> Result<(), Error> {
+	// Reduce the amount of logs generated by FFmpeg
+	unsafe { av_log_set_level(AV_LOG_FATAL) };
+
+	spawn_blocking({
+		let input_path = input_path.as_ref().to_path_buf();
+		let output_path = output_path.as_ref().to_path_buf();
+		move || transcoder::transcode(&input_path, &output_path, options, &abort)
+	})
+	.await?
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -22,27 +22,43 @@ impl Thumbnailer {
 		video_file_path: impl AsRef<Path> + Send,
 		output_thumbnail_path: impl AsRef<Path> + Send,
 	) -> Result<(), Error> {
-		let output_thumbnail_path = output_thumbnail_path.as_ref();
-		let path = output_thumbnail_path.parent().ok_or_else(|| {
-			FileIOError::from((
-				output_thumbnail_path,
-				io::Error::new(
-					io::ErrorKind::InvalidInput,
-					"Cannot determine parent directory",
-				),
-			))
-		})?;
-
-		fs::create_dir_all(path)
-			.await
-			.map_err(|e| FileIOError::from((path, e)))?;
-
-		fs::write(
-			output_thumbnail_path,
-			&*self.process_to_webp_bytes(video_file_path).await?,
+		write_thumbnail(
+			output_thumbnail_path.as_ref(),
+			&self.process_to_webp_bytes(video_file_path).await?,
 		)
 		.await
-		.map_err(|e| FileIOError::from((output_thumbnail_path, e)).into())
+	}
+
+	/// Processes the picture attached to an input file, like the cover art of an audio file, and write
+	/// to file system a thumbnail with webp format
+	pub(crate) async fn process_attached_picture(
+		&self,
+		file_path: impl AsRef<Path> + Send,
+		output_thumbnail_path: impl AsRef<Path> + Send,
+	) -> Result<(), Error> {
+		let size = self.builder.size;
+		let maintain_aspect_ratio = self.builder.maintain_aspect_ratio;
+		let quality = self.builder.quality;
+
+		let webp = spawn_blocking({
+			let file_path = file_path.as_ref().to_path_buf();
+			move || -> Result<Vec<u8>, Error> {
+				let mut decoder = FrameDecoder::new_attached_picture(&file_path)?;
+
+				decoder.decode_video_frame()?;
+
+				encode_frame(
+					&mut decoder,
+					&file_path,
+					size,
+					maintain_aspect_ratio,
+					quality,
+				)
+			}
+		})
+		.await??;
+
+		write_thumbnail(output_thumbnail_path.as_ref(), &webp).await
 	}
 
 	/// Processes an video input file and returns a webp encoded thumbnail as bytes
@@ -73,15 +89,7 @@ impl Thumbnailer {
 					let result = decoder
 						.get_duration_secs()
 						.ok_or(Error::NoVideoDuration)
-						.and_then(|duration| {
-							decoder.seek(
-								#[allow(clippy::cast_possible_truncation)]
-								{
-									// This conversion is ok because we don't worry much about precision here
-									(duration * f64::from(seek_percentage)).round() as i64
-								},
-							)
-						});
+						.and_then(|duration| decoder.seek(duration * f64::from(seek_percentage)));
 
 					if let Err(err) = result {
 						error!(
@@ -97,37 +105,146 @@ impl Thumbnailer {
 					}
 				}
 
-				let video_frame =
-					decoder.get_scaled_video_frame(Some(size), maintain_aspect_ratio)?;
-
-				let mut image = DynamicImage::ImageRgb8(
-					RgbImage::from_raw(video_frame.width, video_frame.height, video_frame.data)
-						.ok_or(Error::CorruptVideo(video_file_path.into_boxed_path()))?,
-				);
-
-				let image = if video_frame.rotation < -135.0 {
-					imageops::rotate180_in_place(&mut image);
-					image
-				} else if video_frame.rotation > 45.0 && video_frame.rotation < 135.0 {
-					image.rotate270()
-				} else if video_frame.rotation < -45.0 && video_frame.rotation > -135.0 {
-					image.rotate90()
-				} else {
-					image
-				};
-
-				// Type WebPMemory is !Send, which makes the Future in this function !Send,
-				// this make us `deref` to have a `&[u8]` and then `to_owned` to make a Vec<u8>
-				// which implies on a unwanted clone...
-				Ok(Encoder::from_image(&image)
-					.expect("Should not fail as the underlining DynamicImage is an RgbImage")
-					.encode(quality)
-					.deref()
-					.to_vec())
+				encode_frame(
+					&mut decoder,
+					&video_file_path,
+					size,
+					maintain_aspect_ratio,
+					quality,
+				)
 			}
 		})
 		.await?
 	}
+
+	/// Processes an video input file and returns `count` frames evenly spaced along it, each one
+	/// taken from the middle of its share of the video
+	pub(crate) async fn process_frames(
+		&self,
+		video_file_path: impl AsRef<Path> + Send,
+		count: usize,
+	) -> Result<Vec<Frame>, Error> {
+		let size = self.builder.size;
+		let maintain_aspect_ratio = self.builder.maintain_aspect_ratio;
+
+		spawn_blocking({
+			let video_file_path = video_file_path.as_ref().to_path_buf();
+			move || -> Result<Vec<Frame>, Error> {
+				// Embedded metadata is a single picture, so it's useless here
+				let mut decoder = FrameDecoder::new(&video_file_path, true, false)?;
+				decoder.decode_video_frame()?;
+
+				let duration = decoder.get_duration_secs().ok_or(Error::NoVideoDuration)?;
+
+				let mut frames = Vec::with_capacity(count);
+
+				for idx in 0..count {
+					#[allow(clippy::cast_precision_loss)]
+					// SAFETY: we're never asking for more than a few dozens of frames
+					let timestamp = duration * (idx as f64 + 0.5) / count as f64;
+
+					if let Err(err) = decoder.seek(timestamp) {
+						error!(
+							"Failed to seek {} to {timestamp}s: {err:#?}",
+							video_file_path.to_string_lossy()
+						);
+						// Re-instantiating decoder to avoid possible segfault, as on `process_to_webp_bytes`
+						decoder = FrameDecoder::new(&video_file_path, true, false)?;
+						decoder.decode_video_frame()?;
+						continue;
+					}
+
+					frames.push(Frame {
+						timestamp,
+						image: frame_to_image(
+							&mut decoder,
+							&video_file_path,
+							size,
+							maintain_aspect_ratio,
+						)?,
+					});
+				}
+
+				if frames.is_empty() {
+					return Err(Error::SeekError);
+				}
+
+				Ok(frames)
+			}
+		})
+		.await?
+	}
+}
+
+/// A frame taken from a video, along with its position in seconds
+#[derive(Debug, Clone)]
+pub struct Frame {
+	pub timestamp: f64,
+	pub image: DynamicImage,
+}
+
+fn frame_to_image(
+	decoder: &mut FrameDecoder,
+	file_path: &Path,
+	size: ThumbnailSize,
+	maintain_aspect_ratio: bool,
+) -> Result<DynamicImage, Error> {
+	let video_frame = decoder.get_scaled_video_frame(Some(size), maintain_aspect_ratio)?;
+
+	let mut image = DynamicImage::ImageRgb8(
+		RgbImage::from_raw(video_frame.width, video_frame.height, video_frame.data)
+			.ok_or_else(|| Error::CorruptVideo(file_path.into()))?,
+	);
+
+	Ok(if video_frame.rotation < -135.0 {
+		imageops::rotate180_in_place(&mut image);
+		image
+	} else if video_frame.rotation > 45.0 && video_frame.rotation < 135.0 {
+		image.rotate270()
+	} else if video_frame.rotation < -45.0 && video_frame.rotation > -135.0 {
+		image.rotate90()
+	} else {
+		image
+	})
+}
+
+fn encode_frame(
+	decoder: &mut FrameDecoder,
+	file_path: &Path,
+	size: ThumbnailSize,
+	maintain_aspect_ratio: bool,
+	quality: f32,
+) -> Result<Vec<u8>, Error> {
+	let image = frame_to_image(decoder, file_path, size, maintain_aspect_ratio)?;
+
+	// Type WebPMemory is !Send, which makes the Future in this function !Send,
+	// this make us `deref` to have a `&[u8]` and then `to_owned` to make a Vec<u8>
+	// which implies on a unwanted clone...
+	Ok(Encoder::from_image(&image)
+		.expect("Should not fail as the underlining DynamicImage is an RgbImage")
+		.encode(quality)
+		.deref()
+		.to_vec())
+}
+
+async fn write_thumbnail(output_thumbnail_path: &Path, webp: &[u8]) -> Result<(), Error> {
+	let path = output_thumbnail_path.parent().ok_or_else(|| {
+		FileIOError::from((
+			output_thumbnail_path,
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"Cannot determine parent directory",
+			),
+		))
+	})?;
+
+	fs::create_dir_all(path)
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?;
+
+	fs::write(output_thumbnail_path, webp)
+		.await
+		.map_err(|e| FileIOError::from((output_thumbnail_path, e)).into())
 }
 
 /// `ThumbnailerBuilder` struct holds data to build a `Thumbnailer` struct, exposing many methods
@@ -0,0 +1,656 @@
+use crate::{
+	codec_ctx::FFmpegCodecContext,
+	error::{Error, FFmpegError},
+	filter_graph::FFmpegFilterGraph,
+	format_ctx::FFmpegFormatContext,
+	utils::{check_error, from_path},
+	video_frame::FFmpegFrame,
+};
+
+use std::{
+	ffi::{c_int, CStr},
+	path::Path,
+	ptr,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+use ffmpeg_sys_next::{
+	av_buffersink_get_frame, av_buffersink_get_time_base, av_buffersrc_write_frame,
+	av_channel_layout_compare, av_channel_layout_copy, av_channel_layout_default,
+	av_find_best_stream, av_frame_unref, av_guess_frame_rate, av_interleaved_write_frame,
+	av_packet_alloc, av_packet_free, av_packet_rescale_ts, av_packet_unref, av_rescale_q,
+	av_write_trailer, avcodec_find_best_pix_fmt_of_list, avcodec_find_decoder,
+	avcodec_find_encoder, avcodec_parameters_from_context, avformat_alloc_output_context2,
+	avformat_free_context, avformat_new_stream, avformat_write_header, avio_closep, avio_open,
+	AVChannelLayout, AVChannelOrder, AVCodec, AVCodecID, AVFilterContext, AVFormatContext, AVFrame,
+	AVMediaType, AVOutputFormat, AVPacket, AVPictureType, AVRational, AVERROR, AVERROR_EOF,
+	AVFMT_GLOBALHEADER, AVFMT_NOFILE, AVIO_FLAG_WRITE, AV_CODEC_FLAG_GLOBAL_HEADER,
+	AV_DISPOSITION_ATTACHED_PIC, AV_NOPTS_VALUE, EAGAIN,
+};
+
+/// Used when the input doesn't have a sensible frame rate, like variable frame rate streams
+const DEFAULT_FRAME_RATE: AVRational = AVRational { num: 30, den: 1 };
+const MAX_FRAME_RATE: c_int = 240;
+
+/// Options for [`transcode`](crate::transcode), the output container and codecs are the defaults
+/// of the format guessed from the output file extension
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TranscodeOptions {
+	/// Videos taller than this are scaled down, keeping their aspect ratio
+	pub max_height: Option<u32>,
+	/// Video bit rate in bits per second, the encoder default is used if `None`
+	pub video_bit_rate: Option<i64>,
+	/// Audio bit rate in bits per second, the encoder default is used if `None`
+	pub audio_bit_rate: Option<i64>,
+	/// Drop the video stream, keeping only the audio
+	pub audio_only: bool,
+}
+
+pub(crate) fn transcode(
+	input_path: &Path,
+	output_path: &Path,
+	options: TranscodeOptions,
+	abort: &AtomicBool,
+) -> Result<(), Error> {
+	let mut input = FFmpegFormatContext::open_file(from_path(input_path)?.as_c_str())?;
+	input.find_stream_info()?;
+
+	let output_filename = from_path(output_path)?;
+	let mut output = OutputContext::create(&output_filename)?;
+
+	let mut transcoders = Vec::with_capacity(2);
+
+	if !options.audio_only && output.oformat().video_codec != AVCodecID::AV_CODEC_ID_NONE {
+		if let Some(index) = find_best_stream(&mut input, AVMediaType::AVMEDIA_TYPE_VIDEO) {
+			transcoders.push(StreamTranscoder::video(
+				&mut input,
+				index,
+				&mut output,
+				&options,
+			)?);
+		}
+	}
+
+	if output.oformat().audio_codec != AVCodecID::AV_CODEC_ID_NONE {
+		if let Some(index) = find_best_stream(&mut input, AVMediaType::AVMEDIA_TYPE_AUDIO) {
+			transcoders.push(StreamTranscoder::audio(
+				&mut input,
+				index,
+				&mut output,
+				&options,
+			)?);
+		}
+	}
+
+	if transcoders.is_empty() {
+		return Err(Error::NothingToTranscode);
+	}
+
+	output.open(&output_filename)?;
+
+	let mut packet = Packet::new()?;
+	loop {
+		if abort.load(Ordering::Relaxed) {
+			return Err(Error::TranscodeAborted);
+		}
+
+		match input.read_frame(packet.as_ptr()) {
+			Ok(_) => {}
+			Err(Error::FFmpegWithReason(FFmpegError::Eof, _)) => break,
+			Err(e) => return Err(e),
+		}
+
+		let stream_index = packet.as_mut().stream_index;
+		let res = transcoders
+			.iter_mut()
+			.find(|transcoder| transcoder.input_index == stream_index)
+			.map_or(Ok(()), |transcoder| {
+				transcoder.decode(packet.as_ptr(), &mut output)
+			});
+
+		packet.unref();
+		res?;
+	}
+
+	for transcoder in &mut transcoders {
+		transcoder.flush(&mut output)?;
+	}
+
+	output.write_trailer()
+}
+
+/// Pick the stream that would be played by default, ignoring cover arts which are stored as
+/// single frame video streams
+fn find_best_stream(input: &mut FFmpegFormatContext, media_type: AVMediaType) -> Option<u32> {
+	let index =
+		unsafe { av_find_best_stream(input.as_mut(), media_type, -1, -1, ptr::null_mut(), 0) };
+
+	let index = u32::try_from(index).ok()?;
+
+	input
+		.stream(index)
+		.filter(|stream| stream.disposition & AV_DISPOSITION_ATTACHED_PIC == 0)
+		.map(|_| index)
+}
+
+/// Decodes, filters and encodes a single stream of the input into the output
+struct StreamTranscoder {
+	input_index: c_int,
+	output_index: c_int,
+	decoder: FFmpegCodecContext,
+	encoder: FFmpegCodecContext,
+	// Owns the filter contexts below
+	_filter_graph: FFmpegFilterGraph,
+	filter_source: *mut AVFilterContext,
+	filter_sink: *mut AVFilterContext,
+	decoded_frame: FFmpegFrame,
+	filtered_frame: FFmpegFrame,
+	packet: Packet,
+}
+
+impl StreamTranscoder {
+	fn video(
+		input: &mut FFmpegFormatContext,
+		input_index: u32,
+		output: &mut OutputContext,
+		options: &TranscodeOptions,
+	) -> Result<Self, Error> {
+		let (decoder, time_base) = open_decoder(input, input_index)?;
+
+		let codec = unsafe { avcodec_find_encoder(output.oformat().video_codec).as_ref() }
+			.ok_or(FFmpegError::EncoderNotFound)?;
+
+		let mut encoder = FFmpegCodecContext::for_codec(codec)?;
+		{
+			let decoder = decoder.as_ref();
+			let encoder = encoder.as_mut();
+
+			(encoder.width, encoder.height) =
+				scaled_dimensions(decoder.width, decoder.height, options.max_height);
+			encoder.sample_aspect_ratio = decoder.sample_aspect_ratio;
+
+			encoder.pix_fmt = if codec.pix_fmts.is_null() {
+				decoder.pix_fmt
+			} else {
+				unsafe {
+					avcodec_find_best_pix_fmt_of_list(
+						codec.pix_fmts,
+						decoder.pix_fmt,
+						0,
+						ptr::null_mut(),
+					)
+				}
+			};
+
+			let frame_rate = if decoder.framerate.num > 0
+				&& decoder.framerate.den > 0
+				&& decoder.framerate.num / decoder.framerate.den <= MAX_FRAME_RATE
+			{
+				decoder.framerate
+			} else {
+				DEFAULT_FRAME_RATE
+			};
+			encoder.framerate = frame_rate;
+			encoder.time_base = AVRational {
+				num: frame_rate.den,
+				den: frame_rate.num,
+			};
+
+			if let Some(bit_rate) = options.video_bit_rate {
+				encoder.bit_rate = bit_rate;
+			}
+		}
+
+		output.prepare_encoder(&mut encoder);
+		encoder.open2(codec)?;
+
+		let output_index = output.new_stream(&encoder)?;
+
+		let (filter_graph, filter_source, filter_sink) =
+			FFmpegFilterGraph::transcode_video_graph(&time_base, &decoder, &encoder)?;
+
+		Self::new(
+			input_index,
+			output_index,
+			decoder,
+			encoder,
+			filter_graph,
+			filter_source,
+			filter_sink,
+		)
+	}
+
+	fn audio(
+		input: &mut FFmpegFormatContext,
+		input_index: u32,
+		output: &mut OutputContext,
+		options: &TranscodeOptions,
+	) -> Result<Self, Error> {
+		let (mut decoder, time_base) = open_decoder(input, input_index)?;
+
+		// Filters need to know where each channel is, not only how many channels there are
+		let ch_layout = &mut decoder.as_mut().ch_layout;
+		if ch_layout.order == AVChannelOrder::AV_CHANNEL_ORDER_UNSPEC {
+			let nb_channels = ch_layout.nb_channels;
+			unsafe { av_channel_layout_default(ch_layout, nb_channels) };
+		}
+
+		let codec = unsafe { avcodec_find_encoder(output.oformat().audio_codec).as_ref() }
+			.ok_or(FFmpegError::EncoderNotFound)?;
+
+		let mut encoder = FFmpegCodecContext::for_codec(codec)?;
+		{
+			let decoder = decoder.as_ref();
+			let encoder = encoder.as_mut();
+
+			encoder.sample_fmt = unsafe { codec.sample_fmts.as_ref() }
+				.copied()
+				.unwrap_or(decoder.sample_fmt);
+			encoder.sample_rate = supported_sample_rate(codec, decoder.sample_rate);
+			encoder.time_base = AVRational {
+				num: 1,
+				den: encoder.sample_rate,
+			};
+
+			if is_channel_layout_supported(codec, &decoder.ch_layout) {
+				check_error(
+					unsafe { av_channel_layout_copy(&mut encoder.ch_layout, &decoder.ch_layout) },
+					"Failed to copy channel layout to encoder",
+				)?;
+			} else {
+				// Mostly encoders limited to stereo, like mp3
+				unsafe { av_channel_layout_default(&mut encoder.ch_layout, 2) };
+			}
+
+			if let Some(bit_rate) = options.audio_bit_rate {
+				encoder.bit_rate = bit_rate;
+			}
+		}
+
+		output.prepare_encoder(&mut encoder);
+		encoder.open2(codec)?;
+
+		let output_index = output.new_stream(&encoder)?;
+
+		let (filter_graph, filter_source, filter_sink) =
+			FFmpegFilterGraph::transcode_audio_graph(&time_base, &decoder, &encoder)?;
+
+		Self::new(
+			input_index,
+			output_index,
+			decoder,
+			encoder,
+			filter_graph,
+			filter_source,
+			filter_sink,
+		)
+	}
+
+	fn new(
+		input_index: u32,
+		output_index: c_int,
+		decoder: FFmpegCodecContext,
+		encoder: FFmpegCodecContext,
+		filter_graph: FFmpegFilterGraph,
+		filter_source: &mut AVFilterContext,
+		filter_sink: &mut AVFilterContext,
+	) -> Result<Self, Error> {
+		Ok(Self {
+			input_index: c_int::try_from(input_index)?,
+			output_index,
+			decoder,
+			encoder,
+			_filter_graph: filter_graph,
+			filter_source: ptr::from_mut(filter_source),
+			filter_sink: ptr::from_mut(filter_sink),
+			decoded_frame: FFmpegFrame::new()?,
+			filtered_frame: FFmpegFrame::new()?,
+			packet: Packet::new()?,
+		})
+	}
+
+	/// Decode a packet read from the input, or drain the decoder if `packet` is null
+	fn decode(&mut self, packet: *mut AVPacket, output: &mut OutputContext) -> Result<(), Error> {
+		match self.decoder.send_packet(packet) {
+			Ok(_) | Err(FFmpegError::Again) => {}
+			// Skip corrupt packets instead of failing the whole file, like the ffmpeg cli does
+			Err(FFmpegError::InvalidData) => return Ok(()),
+			Err(e) => {
+				return Err(Error::FFmpegWithReason(
+					e,
+					"Failed to send packet to decoder".to_string(),
+				))
+			}
+		}
+
+		loop {
+			match self.decoder.receive_frame(self.decoded_frame.as_mut()) {
+				Ok(true) => {}
+				Ok(false) | Err(FFmpegError::Again) => return Ok(()),
+				Err(e) => {
+					return Err(Error::FFmpegWithReason(
+						e,
+						"Failed to receive frame from decoder".to_string(),
+					))
+				}
+			}
+
+			let frame = self.decoded_frame.as_mut();
+			frame.pts = frame.best_effort_timestamp;
+
+			let frame = ptr::from_mut(frame);
+			let res = self.filter(frame, output);
+			unsafe { av_frame_unref(frame) };
+			res?;
+		}
+	}
+
+	/// Push a decoded frame through the filter graph, or flush it if `frame` is null
+	fn filter(&mut self, frame: *const AVFrame, output: &mut OutputContext) -> Result<(), Error> {
+		check_error(
+			unsafe { av_buffersrc_write_frame(self.filter_source, frame) },
+			"Failed to write frame to filter graph",
+		)?;
+
+		loop {
+			match unsafe { av_buffersink_get_frame(self.filter_sink, self.filtered_frame.as_mut()) }
+			{
+				ret if ret == AVERROR(EAGAIN) || ret == AVERROR_EOF => return Ok(()),
+				ret => check_error(ret, "Failed to get frame from filter graph")?,
+			}
+
+			let frame = self.filtered_frame.as_mut();
+			// Let the encoder decide which frames are key frames
+			frame.pict_type = AVPictureType::AV_PICTURE_TYPE_NONE;
+			if frame.pts != AV_NOPTS_VALUE {
+				frame.pts = unsafe {
+					av_rescale_q(
+						frame.pts,
+						av_buffersink_get_time_base(self.filter_sink),
+						self.encoder.as_ref().time_base,
+					)
+				};
+			}
+
+			let frame = ptr::from_mut(frame);
+			let res = self.encode(frame, output);
+			unsafe { av_frame_unref(frame) };
+			res?;
+		}
+	}
+
+	/// Encode a filtered frame and write the resulting packets, or drain the encoder if `frame`
+	/// is null
+	fn encode(&mut self, frame: *const AVFrame, output: &mut OutputContext) -> Result<(), Error> {
+		if let Err(e) = self.encoder.send_frame(frame) {
+			return Err(Error::FFmpegWithReason(
+				e,
+				"Failed to send frame to encoder".to_string(),
+			));
+		}
+
+		loop {
+			match self.encoder.receive_packet(self.packet.as_ptr()) {
+				Ok(true) => {}
+				Ok(false) | Err(FFmpegError::Again) => return Ok(()),
+				Err(e) => {
+					return Err(Error::FFmpegWithReason(
+						e,
+						"Failed to receive packet from encoder".to_string(),
+					))
+				}
+			}
+
+			let output_time_base = output.stream_time_base(self.output_index)?;
+
+			let packet = self.packet.as_mut();
+			packet.stream_index = self.output_index;
+			unsafe {
+				av_packet_rescale_ts(packet, self.encoder.as_ref().time_base, output_time_base);
+			}
+
+			output.write_packet(packet)?;
+		}
+	}
+
+	fn flush(&mut self, output: &mut OutputContext) -> Result<(), Error> {
+		self.decode(ptr::null_mut(), output)?;
+		self.filter(ptr::null(), output)?;
+		self.encode(ptr::null(), output)
+	}
+}
+
+fn open_decoder(
+	input: &mut FFmpegFormatContext,
+	index: u32,
+) -> Result<(FFmpegCodecContext, AVRational), Error> {
+	let stream = ptr::from_mut(input.stream(index).ok_or(FFmpegError::StreamNotFound)?);
+
+	let frame_rate = unsafe { av_guess_frame_rate(input.as_mut(), stream, ptr::null_mut()) };
+
+	let stream = unsafe { &*stream };
+	let codec_params = unsafe { stream.codecpar.as_ref() }.ok_or(FFmpegError::NullError)?;
+
+	let codec = unsafe { avcodec_find_decoder(codec_params.codec_id).as_ref() }
+		.ok_or(FFmpegError::DecoderNotFound)?;
+
+	let mut decoder = FFmpegCodecContext::new()?;
+	decoder.parameters_to_context(codec_params)?;
+	decoder.as_mut().pkt_timebase = stream.time_base;
+	if codec_params.codec_type == AVMediaType::AVMEDIA_TYPE_VIDEO {
+		decoder.as_mut().framerate = frame_rate;
+	}
+	decoder.open2(codec)?;
+
+	Ok((decoder, stream.time_base))
+}
+
+/// Scale down to `max_height` keeping the aspect ratio, and round to even dimensions, as most
+/// encoders require them for chroma subsampled pixel formats
+fn scaled_dimensions(width: c_int, height: c_int, max_height: Option<u32>) -> (c_int, c_int) {
+	let (width, height) = match max_height.and_then(|max_height| c_int::try_from(max_height).ok()) {
+		Some(max_height) if height > max_height => (
+			c_int::try_from(i64::from(width) * i64::from(max_height) / i64::from(height))
+				.unwrap_or(width),
+			max_height,
+		),
+		_ => (width, height),
+	};
+
+	((width & !1).max(2), (height & !1).max(2))
+}
+
+/// Keep the input sample rate if the encoder supports it, otherwise use the closest one
+fn supported_sample_rate(codec: &AVCodec, sample_rate: c_int) -> c_int {
+	if codec.supported_samplerates.is_null() {
+		return sample_rate;
+	}
+
+	let mut supported = vec![];
+	for i in 0.. {
+		match unsafe { *codec.supported_samplerates.add(i) } {
+			0 => break,
+			rate => supported.push(rate),
+		}
+	}
+
+	if supported.contains(&sample_rate) {
+		sample_rate
+	} else {
+		supported
+			.into_iter()
+			.min_by_key(|rate| (rate - sample_rate).abs())
+			.unwrap_or(sample_rate)
+	}
+}
+
+fn is_channel_layout_supported(codec: &AVCodec, ch_layout: &AVChannelLayout) -> bool {
+	if codec.ch_layouts.is_null() {
+		return true;
+	}
+
+	for i in 0.. {
+		let supported = unsafe { &*codec.ch_layouts.add(i) };
+		if supported.nb_channels == 0 {
+			return false;
+		}
+
+		if unsafe { av_channel_layout_compare(supported, ch_layout) } == 0 {
+			return true;
+		}
+	}
+
+	false
+}
+
+struct Packet(*mut AVPacket);
+
+impl Packet {
+	fn new() -> Result<Self, FFmpegError> {
+		let ptr = unsafe { av_packet_alloc() };
+		if ptr.is_null() {
+			return Err(FFmpegError::FrameAllocation);
+		}
+		Ok(Self(ptr))
+	}
+
+	const fn as_ptr(&self) -> *mut AVPacket {
+		self.0
+	}
+
+	fn as_mut(&mut self) -> &mut AVPacket {
+		unsafe { self.0.as_mut() }.expect("initialized on struct creation")
+	}
+
+	fn unref(&mut self) {
+		unsafe { av_packet_unref(self.0) };
+	}
+}
+
+impl Drop for Packet {
+	fn drop(&mut self) {
+		if !self.0.is_null() {
+			unsafe { av_packet_free(&mut self.0) };
+			self.0 = ptr::null_mut();
+		}
+	}
+}
+
+struct OutputContext(*mut AVFormatContext);
+
+impl OutputContext {
+	fn create(filename: &CStr) -> Result<Self, Error> {
+		let mut ptr = ptr::null_mut();
+
+		check_error(
+			unsafe {
+				avformat_alloc_output_context2(
+					&mut ptr,
+					ptr::null(),
+					ptr::null(),
+					filename.as_ptr(),
+				)
+			},
+			"Failed to guess the output format from the file extension",
+		)?;
+
+		if ptr.is_null() {
+			return Err(FFmpegError::MuxerNotFound.into());
+		}
+
+		Ok(Self(ptr))
+	}
+
+	fn as_ref(&self) -> &AVFormatContext {
+		unsafe { self.0.as_ref() }.expect("initialized on struct creation")
+	}
+
+	fn as_mut(&mut self) -> &mut AVFormatContext {
+		unsafe { self.0.as_mut() }.expect("initialized on struct creation")
+	}
+
+	fn oformat(&self) -> &AVOutputFormat {
+		unsafe { self.as_ref().oformat.as_ref() }.expect("set on output context allocation")
+	}
+
+	fn needs_file(&self) -> bool {
+		self.oformat().flags & AVFMT_NOFILE == 0
+	}
+
+	/// Some containers store codec headers once, instead of in every key frame
+	fn prepare_encoder(&self, encoder: &mut FFmpegCodecContext) {
+		if self.oformat().flags & AVFMT_GLOBALHEADER != 0 {
+			#[allow(clippy::cast_possible_wrap)]
+			{
+				// SAFETY: AV_CODEC_FLAG_GLOBAL_HEADER is 1 << 22, so it fits in an i32
+				encoder.as_mut().flags |= AV_CODEC_FLAG_GLOBAL_HEADER as c_int;
+			}
+		}
+	}
+
+	fn new_stream(&mut self, encoder: &FFmpegCodecContext) -> Result<c_int, Error> {
+		let stream = unsafe { avformat_new_stream(self.as_mut(), ptr::null()).as_mut() }
+			.ok_or(FFmpegError::NullError)?;
+
+		check_error(
+			unsafe { avcodec_parameters_from_context(stream.codecpar, encoder.as_ref()) },
+			"Failed to copy encoder parameters to output stream",
+		)?;
+		stream.time_base = encoder.as_ref().time_base;
+
+		Ok(stream.index)
+	}
+
+	/// The muxer may change the time base of a stream when writing the header
+	fn stream_time_base(&self, index: c_int) -> Result<AVRational, FFmpegError> {
+		let ctx = self.as_ref();
+
+		usize::try_from(index)
+			.ok()
+			.filter(|&index| u32::try_from(index).is_ok_and(|index| index < ctx.nb_streams))
+			.and_then(|index| unsafe { (*ctx.streams.add(index)).as_ref() })
+			.map(|stream| stream.time_base)
+			.ok_or(FFmpegError::StreamNotFound)
+	}
+
+	fn open(&mut self, filename: &CStr) -> Result<(), Error> {
+		if self.needs_file() {
+			check_error(
+				unsafe { avio_open(&mut self.as_mut().pb, filename.as_ptr(), AVIO_FLAG_WRITE) },
+				"Failed to open output file",
+			)?;
+		}
+
+		check_error(
+			unsafe { avformat_write_header(self.as_mut(), ptr::null_mut()) },
+			"Failed to write output header",
+		)
+	}
+
+	fn write_packet(&mut self, packet: &mut AVPacket) -> Result<(), Error> {
+		// The packet is unreferenced by FFmpeg, even on failure
+		check_error(
+			unsafe { av_interleaved_write_frame(self.as_mut(), packet) },
+			"Failed to write packet to output",
+		)
+	}
+
+	fn write_trailer(&mut self) -> Result<(), Error> {
+		check_error(
+			unsafe { av_write_trailer(self.as_mut()) },
+			"Failed to write output trailer",
+		)
+	}
+}
+
+impl Drop for OutputContext {
+	fn drop(&mut self) {
+		if !self.0.is_null() {
+			if self.needs_file() {
+				unsafe { avio_closep(&mut self.as_mut().pb) };
+			}
+			unsafe { avformat_free_context(self.0) };
+			self.0 = ptr::null_mut();
+		}
+	}
+}
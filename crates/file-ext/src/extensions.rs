@@ -179,7 +179,7 @@ extension_category_enum! {
 
 // text file extensions
 extension_category_enum! {
-	TextExtension _ALL_TEXT_EXTENSIONS {
+	TextExtension ALL_TEXT_EXTENSIONS {
 		Txt,
 		Rtf,
 		Md,
@@ -188,7 +188,7 @@ extension_category_enum! {
 }
 // config file extensions
 extension_category_enum! {
-	ConfigExtension _ALL_CONFIG_EXTENSIONS {
+	ConfigExtension ALL_CONFIG_EXTENSIONS {
 		Ini,
 		Json,
 		Yaml,
@@ -213,6 +213,8 @@ extension_category_enum! {
 		Container = [0x73, 0x64, 0x62, 0x6F, 0x78],
 		// Spacedrive block storage,
 		Block = [0x73, 0x64, 0x62, 0x6C, 0x6F, 0x63, 0x6B],
+		// Spacedrive encrypted file container, must match `sd_crypto::container::MAGIC_BYTES`
+		Sdenc = [0x73, 0x64, 0x63, 0x72, 0x79, 0x70, 0x74, 0x00],
 	}
 }
 
@@ -248,7 +250,7 @@ extension_category_enum! {
 
 // code extensions
 extension_category_enum! {
-	CodeExtension _ALL_CODE_EXTENSIONS {
+	CodeExtension ALL_CODE_EXTENSIONS {
 		// AppleScript
 		Scpt,
 		Scptd,
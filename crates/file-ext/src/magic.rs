@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use crate::extensions::{CodeExtension, Extension, VideoExtension};
+use crate::extensions::{CodeExtension, EncryptedExtension, Extension, VideoExtension};
 use std::{ffi::OsStr, io::SeekFrom, path::Path};
 
 use tokio::{
@@ -177,14 +177,22 @@ impl Extension {
 		path: impl AsRef<Path>,
 		always_check_magic_bytes: bool,
 	) -> Option<Self> {
-		let ext_str = path.as_ref().extension().and_then(OsStr::to_str)?;
-
-		let ext = Self::from_str(ext_str)?;
-
 		let Ok(ref mut file) = File::open(&path).await else {
 			return None;
 		};
 
+		let Some((ext_str, ext)) = path
+			.as_ref()
+			.extension()
+			.and_then(OsStr::to_str)
+			.and_then(|ext_str| Self::from_str(ext_str).map(|ext| (ext_str, ext)))
+		else {
+			// Files encrypted by Spacedrive are recognized by their header even if they were renamed
+			return verify_magic_bytes(EncryptedExtension::Sdenc, file)
+				.await
+				.map(Self::Encrypted);
+		};
+
 		match ext {
 			// we don't need to check the magic bytes unless there is conflict
 			// always_check_magic_bytes forces the check for tests
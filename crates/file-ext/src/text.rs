@@ -163,7 +163,8 @@ enum UCS16 {
 }
 
 fn looks_ucs16(buf: &[u8]) -> Option<UCS16> {
-	if buf.len() % 2 == 0 {
+	// A trailing odd byte is ignored, it's most likely a code unit cut by a partial read
+	if buf.len() < 2 {
 		return None;
 	}
 
@@ -227,7 +228,7 @@ enum UCS32 {
 }
 
 fn looks_ucs32(buf: &[u8]) -> Option<UCS32> {
-	if buf.len() % 4 == 0 {
+	if buf.len() < 4 {
 		return None;
 	}
 
@@ -295,3 +296,88 @@ pub fn is_text(data: &[u8], partial: bool) -> Option<&'static str> {
 		None
 	}
 }
+
+/// Converts text in one of the charsets reported by [`is_text`] to UTF-8, dropping any byte order mark.
+/// Incomplete or invalid sequences, like the ones left at the end of a partial read, become U+FFFD
+#[must_use]
+pub fn decode(data: &[u8], charset: &str) -> Option<String> {
+	match charset {
+		"utf-8" => Some(
+			String::from_utf8_lossy(data.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(data))
+				.into_owned(),
+		),
+		"utf-16be" | "utf-16le" => {
+			let units = data
+				.chunks_exact(2)
+				.map(|chunk| {
+					if charset == "utf-16be" {
+						u16::from_be_bytes([chunk[0], chunk[1]])
+					} else {
+						u16::from_le_bytes([chunk[0], chunk[1]])
+					}
+				})
+				.skip_while(|&unit| unit == 0xfeff);
+
+			Some(
+				char::decode_utf16(units)
+					.map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+					.collect(),
+			)
+		}
+		"utf-32be" | "utf-32le" => Some(
+			data.chunks_exact(4)
+				.map(|chunk| {
+					if charset == "utf-32be" {
+						u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])
+					} else {
+						u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])
+					}
+				})
+				.skip_while(|&c| c == 0xfeff)
+				.map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+				.collect(),
+		),
+		// Latin-1 code points map one to one to the first 256 Unicode code points
+		"iso-8859-1" => Some(data.iter().copied().map(char::from).collect()),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_decode() {
+		assert_eq!(
+			decode(b"\xef\xbb\xbfol\xc3\xa1", "utf-8").as_deref(),
+			Some("olá")
+		);
+		// Sequence cut in half by a partial read
+		assert_eq!(decode(b"ol\xc3", "utf-8").as_deref(), Some("ol\u{fffd}"));
+		assert_eq!(
+			decode(b"\xff\xfeo\0l\0\xe1\0", "utf-16le").as_deref(),
+			Some("olá")
+		);
+		assert_eq!(
+			decode(b"\xfe\xff\0o\0l\0\xe1", "utf-16be").as_deref(),
+			Some("olá")
+		);
+		assert_eq!(
+			decode(b"\0\0\xfe\xff\0\0\0o", "utf-32be").as_deref(),
+			Some("o")
+		);
+		assert_eq!(decode(b"ol\xe1", "iso-8859-1").as_deref(), Some("olá"));
+		assert_eq!(decode(b"ola", "ebcdic"), None);
+	}
+
+	#[test]
+	fn test_is_text() {
+		assert_eq!(is_text(b"hello\n", false), Some("utf-8"));
+		assert_eq!(is_text(b"\xff\xfeh\0i\0", false), Some("utf-16le"));
+		assert_eq!(is_text(b"\xfe\xff\0h\0i\0", true), Some("utf-16be"));
+		assert_eq!(is_text(b"\0\0\xfe\xff\0\0\0h", false), Some("utf-32be"));
+		assert_eq!(is_text(b"\x80", false), None);
+		assert_eq!(is_text(b"\x7f\x00\x01", false), None);
+	}
+}
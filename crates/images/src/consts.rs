@@ -17,6 +17,10 @@ pub const GENERIC_EXTENSIONS: [&str; 17] = [
 ];
 pub const SVG_EXTENSIONS: [&str; 2] = ["svg", "svgz"];
 pub const PDF_EXTENSIONS: [&str; 1] = ["pdf"];
+/// Camera RAW formats and TIFF itself, which we read the embedded JPEG previews from
+pub const RAW_EXTENSIONS: [&str; 9] = [
+	"tiff", "tif", "dng", "cr2", "dcr", "nwr", "nef", "arw", "rw2",
+];
 #[cfg(feature = "heif")]
 pub const HEIF_EXTENSIONS: [&str; 8] = [
 	"hif", "heif", "heifs", "heic", "heics", "avif", "avci", "avcs",
@@ -3,6 +3,7 @@ use crate::{
 	error::{Error, Result},
 	generic::GenericHandler,
 	pdf::PdfHandler,
+	raw::RawHandler,
 	svg::SvgHandler,
 	ImageHandler,
 };
@@ -47,6 +48,15 @@ fn match_to_handler(ext: Option<&OsStr>) -> Result<Box<dyn ImageHandler>> {
 		handler = Some(Box::new(HeifHandler {}));
 	}
 
+	// Takes precedence over the generic handler for TIFF files
+	if consts::RAW_EXTENSIONS
+		.iter()
+		.map(OsString::from)
+		.any(|x| x == ext)
+	{
+		handler = Some(Box::new(RawHandler {}));
+	}
+
 	if consts::SVG_EXTENSIONS
 		.iter()
 		.map(OsString::from)
@@ -36,6 +36,7 @@ mod handler;
 #[cfg(feature = "heif")]
 mod heif;
 mod pdf;
+mod raw;
 mod svg;
 
 use consts::MAXIMUM_FILE_SIZE;
@@ -45,6 +46,7 @@ pub use consts::{all_compatible_extensions, ConvertibleExtension};
 pub use error::{Error, Result};
 pub use handler::{convert_image, format_image};
 pub use image::DynamicImage;
+pub use svg::render_svg;
 
 pub trait ImageHandler {
 	#[inline]
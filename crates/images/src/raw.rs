@@ -0,0 +1,414 @@
+pub use crate::error::Result;
+use crate::{consts::MAXIMUM_FILE_SIZE, generic::GenericHandler, Error, ImageHandler};
+use image::{DynamicImage, ImageFormat};
+use std::{
+	collections::HashSet,
+	fs::File,
+	io::{BufReader, Read, Seek, SeekFrom},
+	path::Path,
+};
+
+/// Camera RAW files are TIFF containers holding the sensor data along with JPEG previews rendered
+/// by the camera, which are way easier to handle and good enough for thumbnails.
+///
+/// Plain TIFF files rarely have a preview, so we fall back to decoding them.
+pub struct RawHandler {}
+
+impl ImageHandler for RawHandler {
+	fn handle_image(&self, path: &Path) -> Result<DynamicImage> {
+		let preview = File::open(path)
+			.and_then(|file| embedded_jpeg_preview(&mut BufReader::new(file)))
+			.map_err(|e| Error::Io(e, path.to_path_buf().into_boxed_path()))?;
+
+		match preview {
+			Some(preview) => Ok(image::load_from_memory_with_format(
+				&preview,
+				ImageFormat::Jpeg,
+			)?),
+			None => GenericHandler {}.handle_image(path),
+		}
+	}
+}
+
+const TIFF_MAGIC: u16 = 42;
+/// Panasonic RW2 files use their own magic number
+const RW2_MAGIC: u16 = 0x55;
+
+/// We never follow more IFDs than this, as corrupt files can have them pointing at each other
+const MAX_IFDS: usize = 32;
+/// Enough to skip the APP segments (EXIF, XMP, ICC...) found before the frame header
+const JPEG_HEADER_SCAN_SIZE: u64 = 256 * 1024;
+
+const TAG_NEW_SUBFILE_TYPE: u16 = 0x00FE;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_INTERCHANGE_FORMAT: u16 = 0x0201;
+const TAG_JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 0x0202;
+/// Panasonic `JpgFromRaw`, a whole JPEG file stored as the value of the tag
+const TAG_JPG_FROM_RAW: u16 = 0x002E;
+
+const COMPRESSION_OLD_JPEG: u32 = 6;
+const COMPRESSION_JPEG: u32 = 7;
+
+#[derive(Debug, Clone, Copy)]
+enum ByteOrder {
+	Little,
+	Big,
+}
+
+struct TiffReader<'r, R> {
+	reader: &'r mut R,
+	byte_order: ByteOrder,
+}
+
+impl<R: Read + Seek> TiffReader<'_, R> {
+	fn read_u16(&mut self) -> std::io::Result<u16> {
+		let mut buf = [0; 2];
+		self.reader.read_exact(&mut buf)?;
+
+		Ok(match self.byte_order {
+			ByteOrder::Little => u16::from_le_bytes(buf),
+			ByteOrder::Big => u16::from_be_bytes(buf),
+		})
+	}
+
+	fn read_u32(&mut self) -> std::io::Result<u32> {
+		let mut buf = [0; 4];
+		self.reader.read_exact(&mut buf)?;
+
+		Ok(match self.byte_order {
+			ByteOrder::Little => u32::from_le_bytes(buf),
+			ByteOrder::Big => u32::from_be_bytes(buf),
+		})
+	}
+}
+
+#[derive(Debug)]
+struct Entry {
+	tag: u16,
+	field_type: u16,
+	count: u32,
+	/// Offset of the value or of the inlined value itself, as read from the file
+	value_offset: u32,
+	/// Where `value_offset` is in the file, as inlined values are read from there
+	position: u64,
+}
+
+impl Entry {
+	fn value_size(&self) -> Option<u64> {
+		let size = match self.field_type {
+			// BYTE, ASCII, SBYTE, UNDEFINED
+			1 | 2 | 6 | 7 => 1,
+			// SHORT, SSHORT
+			3 | 8 => 2,
+			// LONG, SLONG, FLOAT, IFD
+			4 | 9 | 11 | 13 => 4,
+			// RATIONAL, SRATIONAL, DOUBLE
+			5 | 10 | 12 => 8,
+			_ => return None,
+		};
+
+		Some(size * u64::from(self.count))
+	}
+
+	fn data_offset(&self) -> Option<u64> {
+		self.value_size().map(|size| {
+			if size <= 4 {
+				self.position
+			} else {
+				u64::from(self.value_offset)
+			}
+		})
+	}
+
+	/// Reads SHORT and LONG values, the only integer types used by the tags we care about
+	fn read_values<R: Read + Seek>(
+		&self,
+		tiff: &mut TiffReader<'_, R>,
+	) -> std::io::Result<Vec<u32>> {
+		let Some(offset) = self.data_offset() else {
+			return Ok(vec![]);
+		};
+
+		tiff.reader.seek(SeekFrom::Start(offset))?;
+
+		// Nothing we care about has more values than this, so we avoid huge allocations on corrupt files
+		(0..self.count.min(64))
+			.map(|_| match self.field_type {
+				3 => tiff.read_u16().map(u32::from),
+				4 | 13 => tiff.read_u32(),
+				_ => Err(std::io::ErrorKind::InvalidData.into()),
+			})
+			.collect()
+	}
+}
+
+/// Finds the largest JPEG preview embedded in a TIFF based file, which we can decode.
+///
+/// Returns `None` if the file isn't TIFF based or if it doesn't have any usable preview.
+pub(crate) fn embedded_jpeg_preview<R: Read + Seek>(
+	reader: &mut R,
+) -> std::io::Result<Option<Vec<u8>>> {
+	let mut header = [0; 4];
+	if reader.read_exact(&mut header).is_err() {
+		return Ok(None);
+	}
+
+	let byte_order = match &header[..2] {
+		b"II" => ByteOrder::Little,
+		b"MM" => ByteOrder::Big,
+		_ => return Ok(None),
+	};
+
+	let mut tiff = TiffReader { reader, byte_order };
+
+	let magic = match byte_order {
+		ByteOrder::Little => u16::from_le_bytes([header[2], header[3]]),
+		ByteOrder::Big => u16::from_be_bytes([header[2], header[3]]),
+	};
+
+	if magic != TIFF_MAGIC && magic != RW2_MAGIC {
+		return Ok(None);
+	}
+
+	let mut candidates = vec![];
+	let mut visited = HashSet::new();
+	let mut pending = vec![u64::from(tiff.read_u32()?)];
+
+	while let Some(ifd_offset) = pending.pop() {
+		if ifd_offset == 0 || visited.len() >= MAX_IFDS || !visited.insert(ifd_offset) {
+			continue;
+		}
+
+		// Corrupt IFDs only make us miss some previews
+		let Ok((entries, next_ifd)) = read_ifd(&mut tiff, ifd_offset) else {
+			continue;
+		};
+
+		pending.push(next_ifd);
+
+		candidates.extend(ifd_candidates(&mut tiff, &entries, &mut pending));
+	}
+
+	// Bigger previews first, as they make better thumbnails
+	candidates.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+
+	for (offset, length) in candidates {
+		if length > MAXIMUM_FILE_SIZE || !is_decodable_jpeg(tiff.reader, offset, length)? {
+			continue;
+		}
+
+		tiff.reader.seek(SeekFrom::Start(offset))?;
+
+		let mut preview = vec![];
+		tiff.reader
+			.by_ref()
+			.take(length)
+			.read_to_end(&mut preview)?;
+
+		if preview.len() as u64 == length {
+			return Ok(Some(preview));
+		}
+	}
+
+	Ok(None)
+}
+
+fn read_ifd<R: Read + Seek>(
+	tiff: &mut TiffReader<'_, R>,
+	ifd_offset: u64,
+) -> std::io::Result<(Vec<Entry>, u64)> {
+	tiff.reader.seek(SeekFrom::Start(ifd_offset))?;
+
+	let entries = (0..tiff.read_u16()?)
+		.map(|_| {
+			Ok(Entry {
+				tag: tiff.read_u16()?,
+				field_type: tiff.read_u16()?,
+				count: tiff.read_u32()?,
+				position: tiff.reader.stream_position()?,
+				value_offset: tiff.read_u32()?,
+			})
+		})
+		.collect::<std::io::Result<Vec<_>>>()?;
+
+	let next_ifd = u64::from(tiff.read_u32()?);
+
+	Ok((entries, next_ifd))
+}
+
+/// Offsets and lengths of the JPEG streams referenced by an IFD, queueing its sub IFDs
+fn ifd_candidates<R: Read + Seek>(
+	tiff: &mut TiffReader<'_, R>,
+	entries: &[Entry],
+	pending: &mut Vec<u64>,
+) -> Vec<(u64, u64)> {
+	let mut value = |tag| {
+		entries
+			.iter()
+			.find(|entry| entry.tag == tag)
+			.and_then(|entry| entry.read_values(tiff).ok())
+	};
+
+	let mut candidates = vec![];
+
+	if let Some(sub_ifds) = value(TAG_SUB_IFDS) {
+		pending.extend(sub_ifds.into_iter().map(u64::from));
+	}
+
+	if let (Some(&[offset]), Some(&[length])) = (
+		value(TAG_JPEG_INTERCHANGE_FORMAT).as_deref(),
+		value(TAG_JPEG_INTERCHANGE_FORMAT_LENGTH).as_deref(),
+	) {
+		candidates.push((u64::from(offset), u64::from(length)));
+	}
+
+	// JPEG compressed images stored in a single strip, the ones with a lossless compression
+	// (the sensor data) are filtered out later when checking the JPEG frame type
+	if let (Some(&[compression]), Some(&[offset]), Some(&[length])) = (
+		value(TAG_COMPRESSION).as_deref(),
+		value(TAG_STRIP_OFFSETS).as_deref(),
+		value(TAG_STRIP_BYTE_COUNTS).as_deref(),
+	) {
+		if compression == COMPRESSION_OLD_JPEG
+			|| (compression == COMPRESSION_JPEG
+				// DNG previews are marked as reduced resolution images
+				&& value(TAG_NEW_SUBFILE_TYPE).is_some_and(|subfile_type| {
+					subfile_type.first().is_some_and(|&subfile_type| subfile_type & 1 != 0)
+				})) {
+			candidates.push((u64::from(offset), u64::from(length)));
+		}
+	}
+
+	if let Some(entry) = entries.iter().find(|entry| entry.tag == TAG_JPG_FROM_RAW) {
+		if let Some(offset) = entry.data_offset() {
+			candidates.push((offset, u64::from(entry.count)));
+		}
+	}
+
+	candidates
+}
+
+/// Checks that the JPEG stream uses a baseline or progressive frame, as the `image` crate can't
+/// decode the lossless ones holding the sensor data
+fn is_decodable_jpeg<R: Read + Seek>(
+	reader: &mut R,
+	offset: u64,
+	length: u64,
+) -> std::io::Result<bool> {
+	if reader.seek(SeekFrom::Start(offset)).is_err() {
+		return Ok(false);
+	}
+
+	let mut header = vec![];
+	reader
+		.by_ref()
+		.take(length.min(JPEG_HEADER_SCAN_SIZE))
+		.read_to_end(&mut header)?;
+
+	if !header.starts_with(&[0xFF, 0xD8]) {
+		return Ok(false);
+	}
+
+	let mut position = 2;
+
+	while let Some(&[0xFF, marker, length_high, length_low]) = header.get(position..position + 4) {
+		match marker {
+			// SOF0 (baseline), SOF1 (extended sequential) and SOF2 (progressive)
+			0xC0..=0xC2 => return Ok(true),
+			// Other frame types, or the start of the scan without any frame
+			0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA => return Ok(false),
+			// Fill bytes
+			0xFF => position += 1,
+			_ => position += 2 + usize::from(u16::from_be_bytes([length_high, length_low])),
+		}
+	}
+
+	Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Cursor;
+
+	/// Minimal JPEG header with a frame of the given type
+	fn jpeg(frame_marker: u8, padding: usize) -> Vec<u8> {
+		let mut jpeg = vec![0xFF, 0xD8, 0xFF, frame_marker, 0x00, 0x02];
+		jpeg.resize(jpeg.len() + padding, 0);
+		jpeg.extend([0xFF, 0xD9]);
+		jpeg
+	}
+
+	/// Little endian TIFF with an IFD for each given JPEG, pointing at it through the
+	/// `JPEGInterchangeFormat` tags
+	fn tiff(jpegs: &[Vec<u8>]) -> Vec<u8> {
+		let ifd_size = 2 + 2 * 12 + 4;
+		let data_start = 8 + ifd_size * jpegs.len();
+
+		let mut tiff = b"II".to_vec();
+		tiff.extend(TIFF_MAGIC.to_le_bytes());
+		tiff.extend(8_u32.to_le_bytes());
+
+		let mut data_offset = data_start;
+		for (idx, jpeg) in jpegs.iter().enumerate() {
+			tiff.extend(2_u16.to_le_bytes());
+			for (tag, value) in [
+				(TAG_JPEG_INTERCHANGE_FORMAT, data_offset),
+				(TAG_JPEG_INTERCHANGE_FORMAT_LENGTH, jpeg.len()),
+			] {
+				tiff.extend(tag.to_le_bytes());
+				tiff.extend(4_u16.to_le_bytes());
+				tiff.extend(1_u32.to_le_bytes());
+				tiff.extend(u32::try_from(value).expect("small test file").to_le_bytes());
+			}
+
+			let next_ifd = if idx + 1 == jpegs.len() {
+				0
+			} else {
+				8 + ifd_size * (idx + 1)
+			};
+			tiff.extend(
+				u32::try_from(next_ifd)
+					.expect("small test file")
+					.to_le_bytes(),
+			);
+
+			data_offset += jpeg.len();
+		}
+
+		for jpeg in jpegs {
+			tiff.extend(jpeg);
+		}
+
+		tiff
+	}
+
+	#[test]
+	fn test_embedded_jpeg_preview() {
+		let thumbnail = jpeg(0xC0, 16);
+		let preview = jpeg(0xC2, 64);
+		// Bigger, but lossless like the sensor data
+		let sensor_data = jpeg(0xC3, 256);
+
+		let file = tiff(&[thumbnail, sensor_data, preview.clone()]);
+
+		assert_eq!(
+			embedded_jpeg_preview(&mut Cursor::new(file)).expect("in memory"),
+			Some(preview)
+		);
+
+		assert_eq!(
+			embedded_jpeg_preview(&mut Cursor::new(tiff(&[jpeg(0xC3, 16)]))).expect("in memory"),
+			None
+		);
+
+		assert_eq!(
+			embedded_jpeg_preview(&mut Cursor::new(b"not a tiff file".to_vec()))
+				.expect("in memory"),
+			None
+		);
+	}
+}
@@ -10,11 +10,15 @@
 #![warn(clippy::unwrap_used, clippy::panic)]
 
 use std::{
-	io,
+	io::{self, SeekFrom},
 	sync::atomic::{AtomicBool, Ordering},
 };
 
-use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use sd_p2p_proto::{decode, encode};
+use thiserror::Error;
+use tokio::io::{
+	AsyncBufRead, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt,
+};
 use tracing::debug;
 
 mod block;
@@ -25,10 +29,18 @@ pub use block::*;
 pub use block_size::*;
 pub use sb_request::*;
 
+/// Length of the BLAKE3 digest sent at the end of each file
+pub const DIGEST_LEN: usize = blake3::OUT_LEN;
+
+/// Messages sent from the sender to the receiver
 #[derive(Debug, PartialEq, Eq)]
 pub enum Msg<'a> {
 	Block(Block<'a>),
 	Cancelled,
+	/// The sender hit an error and won't send anything else for this transfer
+	Error(String),
+	/// All blocks were sent, this is the BLAKE3 digest of the whole requested range
+	Digest([u8; DIGEST_LEN]),
 }
 
 impl<'a> Msg<'a> {
@@ -40,6 +52,16 @@ impl<'a> Msg<'a> {
 		match discriminator {
 			0 => Ok(Msg::Block(Block::from_stream(stream, data_buf).await?)),
 			1 => Ok(Msg::Cancelled),
+			2 => {
+				Ok(Msg::Error(decode::string(stream).await.map_err(|e| {
+					io::Error::new(io::ErrorKind::InvalidData, e)
+				})?))
+			}
+			3 => {
+				let mut digest = [0; DIGEST_LEN];
+				stream.read_exact(&mut digest).await?;
+				Ok(Msg::Digest(digest))
+			}
 			_ => Err(io::Error::new(
 				io::ErrorKind::Other,
 				"Invalid 'Msg' discriminator!",
@@ -57,10 +79,85 @@ impl<'a> Msg<'a> {
 				bytes
 			}
 			Msg::Cancelled => vec![1],
+			Msg::Error(error) => {
+				let mut bytes = vec![2];
+				encode::string(&mut bytes, error);
+				bytes
+			}
+			Msg::Digest(digest) => {
+				let mut bytes = vec![3];
+				bytes.extend_from_slice(digest);
+				bytes
+			}
 		}
 	}
 }
 
+/// Responses sent from the receiver to the sender after each [`Msg`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Ack {
+	/// The block was written, send the next one
+	Continue,
+	Cancelled,
+	/// The digest matched, the file was transferred successfully
+	Complete,
+	/// The receiver hit an error and won't accept anything else for this transfer
+	Error(String),
+}
+
+impl Ack {
+	pub async fn from_stream(stream: &mut (impl AsyncRead + Unpin)) -> Result<Self, io::Error> {
+		match stream.read_u8().await? {
+			0 => Ok(Self::Continue),
+			1 => Ok(Self::Cancelled),
+			2 => Ok(Self::Complete),
+			3 => {
+				Ok(Self::Error(decode::string(stream).await.map_err(|e| {
+					io::Error::new(io::ErrorKind::InvalidData, e)
+				})?))
+			}
+			_ => Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"Invalid 'Ack' discriminator!",
+			)),
+		}
+	}
+
+	#[must_use]
+	pub fn to_bytes(&self) -> Vec<u8> {
+		match self {
+			Self::Continue => vec![0],
+			Self::Cancelled => vec![1],
+			Self::Complete => vec![2],
+			Self::Error(error) => {
+				let mut bytes = vec![3];
+				encode::string(&mut bytes, error);
+				bytes
+			}
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum TransferError {
+	#[error("io error: {0}")]
+	Io(#[from] io::Error),
+	#[error("all requested files were already transferred")]
+	NoMoreRequests,
+	#[error("the file was modified while being sent")]
+	FileModified,
+	#[error("invalid resume offset {offset} for a range of {len} bytes")]
+	InvalidResumeOffset { offset: u64, len: u64 },
+	#[error("received block at offset {got} but expected offset {expected}")]
+	UnexpectedBlock { expected: u64, got: u64 },
+	#[error("received an unexpected message from the remote peer")]
+	UnexpectedMessage,
+	#[error("integrity check failed for file '{0}'")]
+	DigestMismatch(String),
+	#[error("remote peer error: {0}")]
+	Remote(String),
+}
+
 /// TODO
 pub struct Transfer<'a, F> {
 	reqs: &'a SpaceblockRequests,
@@ -76,52 +173,79 @@ impl<'a, F> Transfer<'a, F>
 where
 	F: Fn(u8) + 'a,
 {
-	// TODO: Handle `req.range` correctly in this code
-
 	pub fn new(req: &'a SpaceblockRequests, on_progress: F, cancelled: &'a AtomicBool) -> Self {
 		Self {
 			reqs: req,
 			on_progress,
 			total_offset: 0,
-			total_bytes: req.requests.iter().map(|req| req.size).sum(),
+			total_bytes: req
+				.requests
+				.iter()
+				.map(|req| {
+					let range = req.byte_range();
+					range.end - range.start
+				})
+				.sum(),
 			i: 0,
 			cancelled,
 		}
 	}
 
 	// TODO: Should `new` take in the streams too cause this means we `Stream` `SpaceblockRequest` could get outta sync.
+	/// Send the next file of the request.
+	///
+	/// Only the requested range of `file` is sent, skipping any bytes the receiver reports it already holds.
 	pub async fn send(
 		&mut self,
 		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
-		mut file: (impl AsyncBufRead + Unpin),
-	) -> Result<(), io::Error> {
+		mut file: impl AsyncBufRead + AsyncSeek + Unpin,
+	) -> Result<(), TransferError> {
+		let req = self.next_request()?;
+		let range = req.byte_range();
+		let len = range.end - range.start;
+
 		// We manually implement what is basically a `BufReader` so we have more control
 		let mut buf = vec![0u8; self.reqs.block_size.size() as usize];
-		let mut offset: u64 = 0;
+		let mut hasher = blake3::Hasher::new();
+
+		// Resume handshake, the receiver tells us how much of the range it already holds
+		let held = stream.read_u64_le().await?;
+		if held > len {
+			return send_error(
+				stream,
+				TransferError::InvalidResumeOffset { offset: held, len },
+			)
+			.await;
+		}
 
-		loop {
+		file.seek(SeekFrom::Start(range.start)).await?;
+
+		// The bytes the receiver already holds aren't sent again but are still part of the digest
+		let mut offset = 0;
+		while offset < held {
+			let read = read_chunk(&mut file, &mut buf, held - offset).await?;
+			if read == 0 {
+				return send_error(stream, TransferError::FileModified).await;
+			}
+
+			hasher.update(&buf[..read]);
+			offset += read as u64;
+		}
+		self.report_progress(held);
+
+		while offset < len {
 			if self.cancelled.load(Ordering::Relaxed) {
 				stream.write_all(&Msg::Cancelled.to_bytes()).await?;
 				stream.flush().await?;
 				return Ok(());
 			}
 
-			let read = file.read(&mut buf[..]).await?;
-			self.total_offset += read as u64;
-			(self.on_progress)(
-				((self.total_offset as f64 / self.total_bytes as f64) * 100.0) as u8,
-			); // SAFETY: Percent must be between 0 and 100
-
+			let read = read_chunk(&mut file, &mut buf, len - offset).await?;
 			if read == 0 {
-				// The file may have been modified during sender on the sender and we don't account for that.
-				// TODO: Error handling + send error to remote
-				assert!(
-					(offset + read as u64) == self.reqs.requests[self.i].size,
-					"File sending has stopped but it doesn't match the expected length!"
-				);
-
-				return Ok(());
+				// The file was truncated while we were sending it
+				return send_error(stream, TransferError::FileModified).await;
 			}
+			hasher.update(&buf[..read]);
 
 			let block = Block {
 				offset,
@@ -136,96 +260,240 @@ where
 
 			stream.write_all(&Msg::Block(block).to_bytes()).await?;
 			stream.flush().await?;
+			self.report_progress(read as u64);
 
-			match stream.read_u8().await? {
-				// Continue sending
-				0 => {}
-				// Cancelled by user
-				1 => {
+			match Ack::from_stream(stream).await? {
+				Ack::Continue => {}
+				Ack::Cancelled => {
 					debug!("Receiver cancelled Spacedrop transfer!");
 					return Ok(());
 				}
-				// Transfer complete
-				2 => return Ok(()),
-				_ => todo!(),
+				Ack::Error(e) => return Err(TransferError::Remote(e)),
+				Ack::Complete => return send_error(stream, TransferError::UnexpectedMessage).await,
 			}
 		}
+
+		stream
+			.write_all(&Msg::Digest(*hasher.finalize().as_bytes()).to_bytes())
+			.await?;
+		stream.flush().await?;
+
+		match Ack::from_stream(stream).await? {
+			Ack::Complete => Ok(()),
+			Ack::Cancelled => {
+				debug!("Receiver cancelled Spacedrop transfer!");
+				Ok(())
+			}
+			Ack::Error(e) => Err(TransferError::Remote(e)),
+			Ack::Continue => Err(TransferError::UnexpectedMessage),
+		}
 	}
 
 	// TODO: Timeout on receiving/sending
+	/// Receive the next file of the request into `file`, from the start of the requested range.
 	pub async fn receive(
 		&mut self,
 		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
-		mut file: (impl AsyncWrite + Unpin),
-		// TODO: Proper error type
-	) -> Result<(), io::Error> {
+		file: impl AsyncWrite + Unpin,
+	) -> Result<(), TransferError> {
+		self.receive_from(stream, file, 0, blake3::Hasher::new())
+			.await
+	}
+
+	/// Receive the next file of the request, continuing a previous attempt.
+	///
+	/// `file` must only hold data of the requested range written by a previous attempt. It's read to know how many
+	/// bytes we already have, so only the missing ones are requested from the sender. If that data doesn't
+	/// match the sender's file the final integrity check fails.
+	pub async fn resume(
+		&mut self,
+		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+		mut file: impl AsyncRead + AsyncWrite + AsyncSeek + Unpin,
+	) -> Result<(), TransferError> {
+		let range = self.current_request()?.byte_range();
+		let len = range.end - range.start;
+
+		let mut buf = vec![0u8; self.reqs.block_size.size() as usize];
+		let mut hasher = blake3::Hasher::new();
+		let mut held = 0;
+
+		file.seek(SeekFrom::Start(0)).await?;
+		loop {
+			let read = read_chunk(&mut file, &mut buf, len - held).await?;
+			if read == 0 {
+				break;
+			}
+
+			hasher.update(&buf[..read]);
+			held += read as u64;
+		}
+		file.seek(SeekFrom::Start(held)).await?;
+
+		self.receive_from(stream, file, held, hasher).await
+	}
+
+	async fn receive_from(
+		&mut self,
+		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+		mut file: impl AsyncWrite + Unpin,
+		held: u64,
+		mut hasher: blake3::Hasher,
+	) -> Result<(), TransferError> {
+		let req = self.next_request()?;
+		let range = req.byte_range();
+		let len = range.end - range.start;
+
 		// We manually implement what is basically a `BufReader` so we have more control
 		let mut data_buf = vec![0u8; self.reqs.block_size.size() as usize];
-		let mut offset: u64 = 0;
 
-		if self.reqs.requests[self.i].size == 0 {
-			self.i += 1;
-			return Ok(());
-		}
+		stream.write_all(&held.to_le_bytes()).await?;
+		stream.flush().await?;
+		self.report_progress(held);
 
 		// TODO: Prevent loop being a DOS vector
-		loop {
+		let mut offset = held;
+		while offset < len {
 			if self.cancelled.load(Ordering::Relaxed) {
-				stream.write_u8(1).await?;
+				stream.write_all(&Ack::Cancelled.to_bytes()).await?;
 				stream.flush().await?;
 				return Ok(());
 			}
 
 			// TODO: Timeout if nothing is being received
-			let msg = Msg::from_stream(stream, &mut data_buf).await?;
-			match msg {
+			match Msg::from_stream(stream, &mut data_buf).await? {
 				Msg::Block(block) => {
-					self.total_offset += block.size;
-					(self.on_progress)(
-						((self.total_offset as f64 / self.total_bytes as f64) * 100.0) as u8,
-					); // SAFETY: Percent must be between 0 and 100
+					if block.offset != offset || block.size > len - offset {
+						return send_ack_error(
+							stream,
+							TransferError::UnexpectedBlock {
+								expected: offset,
+								got: block.offset,
+							},
+						)
+						.await;
+					}
 
 					debug!(
 						"Received block at offset {} of size {}",
 						block.offset, block.size
 					);
-					offset += block.size;
 
-					file.write_all(&data_buf[..block.size as usize]).await?;
+					let data = &data_buf[..block.size as usize];
+					file.write_all(data).await?;
+					hasher.update(data);
+					offset += block.size;
+					self.report_progress(block.size);
 
-					let req = self.reqs.requests.get(self.i).ok_or_else(|| {
-						debug!("Vector read out of bounds!");
-						io::ErrorKind::Other
-					})?;
-					// TODO: Should this be `read == 0`
-					if offset == req.size {
-						break;
+					if self.cancelled.load(Ordering::Relaxed) {
+						stream.write_all(&Ack::Cancelled.to_bytes()).await?;
+						stream.flush().await?;
+						return Ok(());
 					}
 
-					stream
-						.write_u8(u8::from(self.cancelled.load(Ordering::Relaxed)))
-						.await?;
+					stream.write_all(&Ack::Continue.to_bytes()).await?;
 					stream.flush().await?;
 				}
 				Msg::Cancelled => {
 					debug!("Sender cancelled Spacedrop transfer!");
 					return Ok(());
 				}
+				Msg::Error(e) => return Err(TransferError::Remote(e)),
+				Msg::Digest(_) => {
+					return send_ack_error(stream, TransferError::UnexpectedMessage).await
+				}
 			}
 		}
 
-		stream.write_u8(2).await?;
-		stream.flush().await?;
 		file.flush().await?;
+
+		match Msg::from_stream(stream, &mut data_buf).await? {
+			Msg::Digest(digest) if hasher.finalize() == digest => {
+				stream.write_all(&Ack::Complete.to_bytes()).await?;
+				stream.flush().await?;
+				Ok(())
+			}
+			Msg::Digest(_) => {
+				send_ack_error(stream, TransferError::DigestMismatch(req.name.clone())).await
+			}
+			Msg::Cancelled => {
+				debug!("Sender cancelled Spacedrop transfer!");
+				Ok(())
+			}
+			Msg::Error(e) => Err(TransferError::Remote(e)),
+			Msg::Block(_) => send_ack_error(stream, TransferError::UnexpectedMessage).await,
+		}
+	}
+
+	fn current_request(&self) -> Result<&'a SpaceblockRequest, TransferError> {
+		self.reqs
+			.requests
+			.get(self.i)
+			.ok_or(TransferError::NoMoreRequests)
+	}
+
+	fn next_request(&mut self) -> Result<&'a SpaceblockRequest, TransferError> {
+		let req = self.current_request()?;
 		self.i += 1;
+		Ok(req)
+	}
 
-		Ok(())
+	fn report_progress(&mut self, bytes: u64) {
+		self.total_offset += bytes;
+		(self.on_progress)(if self.total_bytes == 0 {
+			100
+		} else {
+			((self.total_offset as f64 / self.total_bytes as f64) * 100.0) as u8
+		}); // SAFETY: Percent must be between 0 and 100
 	}
 }
 
+/// Read at most `remaining` bytes into `buf`
+async fn read_chunk(
+	file: &mut (impl AsyncRead + Unpin),
+	buf: &mut [u8],
+	remaining: u64,
+) -> Result<usize, io::Error> {
+	let len = buf
+		.len()
+		.min(usize::try_from(remaining).unwrap_or(usize::MAX));
+	file.read(&mut buf[..len]).await
+}
+
+/// Let the receiver know why we are stopping, so it doesn't wait for more data
+async fn send_error(
+	stream: &mut (impl AsyncWrite + Unpin),
+	e: TransferError,
+) -> Result<(), TransferError> {
+	// We are already failing, so errors while notifying the remote peer are ignored
+	stream
+		.write_all(&Msg::Error(e.to_string()).to_bytes())
+		.await
+		.ok();
+	stream.flush().await.ok();
+	Err(e)
+}
+
+/// Let the sender know why we are stopping, so it doesn't keep sending data
+async fn send_ack_error(
+	stream: &mut (impl AsyncWrite + Unpin),
+	e: TransferError,
+) -> Result<(), TransferError> {
+	// We are already failing, so errors while notifying the remote peer are ignored
+	stream
+		.write_all(&Ack::Error(e.to_string()).to_bytes())
+		.await
+		.ok();
+	stream.flush().await.ok();
+	Err(e)
+}
+
 #[cfg(test)]
 mod tests {
-	use std::{io::Cursor, mem, sync::Arc};
+	use std::{
+		io::Cursor,
+		mem,
+		sync::{Arc, Mutex},
+	};
 
 	use tokio::{io::BufReader, sync::oneshot};
 	use uuid::Uuid;
@@ -438,6 +706,179 @@ mod tests {
 		assert_eq!(result, Vec::<u8>::new()); // Cancelled by sender so no data
 	}
 
+	#[tokio::test]
+	async fn test_spaceblock_partial_range() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let block_size = BlockSize::_128KiB;
+		let data = (0..block_size.size() * 3)
+			.map(|i| (i % 251) as u8)
+			.collect::<Vec<_>>();
+		let range = 1000..(block_size.size() as u64 * 2 + 42);
+
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size,
+			requests: vec![SpaceblockRequest {
+				name: "Demo".to_string(),
+				size: data.len() as u64,
+				range: Range::Partial(range.clone()),
+			}],
+		};
+
+		tokio::spawn({
+			let req = req.clone();
+			let data = data.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await
+			}
+		});
+
+		let mut result = Vec::new();
+		Transfer::new(&req, |_| {}, &Default::default())
+			.receive(&mut server, &mut result)
+			.await
+			.unwrap();
+
+		assert_eq!(result, data[range.start as usize..range.end as usize]);
+	}
+
+	#[tokio::test]
+	async fn test_spaceblock_resume() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let block_size = BlockSize::_128KiB;
+		let data = (0..block_size.size() * 3)
+			.map(|i| (i % 251) as u8)
+			.collect::<Vec<_>>();
+		let held = block_size.size() as usize + 10;
+
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size,
+			requests: vec![SpaceblockRequest {
+				name: "Demo".to_string(),
+				size: data.len() as u64,
+				range: Range::Full,
+			}],
+		};
+
+		let sent_progress = Arc::new(Mutex::new(Vec::new()));
+		let sender = tokio::spawn({
+			let req = req.clone();
+			let data = data.clone();
+			let sent_progress = sent_progress.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				Transfer::new(
+					&req,
+					|percent| sent_progress.lock().unwrap().push(percent),
+					&Default::default(),
+				)
+				.send(&mut client, file)
+				.await
+			}
+		});
+
+		// A previous attempt dropped after receiving part of the file
+		let mut partial = Cursor::new(data[..held].to_vec());
+		Transfer::new(&req, |_| {}, &Default::default())
+			.resume(&mut server, &mut partial)
+			.await
+			.unwrap();
+		sender.await.unwrap().unwrap();
+
+		assert_eq!(partial.into_inner(), data);
+		// The bytes the receiver already had are accounted for before any block is sent
+		let sent_progress = sent_progress.lock().unwrap();
+		assert_eq!(sent_progress.first(), Some(&33));
+		assert_eq!(sent_progress.last(), Some(&100));
+	}
+
+	#[tokio::test]
+	async fn test_spaceblock_resume_digest_mismatch() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let data = b"Spacedrive".to_vec();
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size: BlockSize::from_file_size(data.len() as u64),
+			requests: vec![SpaceblockRequest {
+				name: "Demo".to_string(),
+				size: data.len() as u64,
+				range: Range::Full,
+			}],
+		};
+
+		let sender = tokio::spawn({
+			let req = req.clone();
+			let data = data.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await
+			}
+		});
+
+		// The data from the previous attempt doesn't match the file being sent
+		let mut partial = Cursor::new(b"Spice".to_vec());
+		let result = Transfer::new(&req, |_| {}, &Default::default())
+			.resume(&mut server, &mut partial)
+			.await;
+
+		assert!(matches!(result, Err(TransferError::DigestMismatch(_))));
+		assert!(matches!(
+			sender.await.unwrap(),
+			Err(TransferError::Remote(_))
+		));
+	}
+
+	#[tokio::test]
+	async fn test_spaceblock_file_truncated_while_sending() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let data = b"Spacedrive".to_vec();
+		let req = SpaceblockRequests {
+			id: Uuid::new_v4(),
+			block_size: BlockSize::from_file_size(data.len() as u64),
+			requests: vec![SpaceblockRequest {
+				name: "Demo".to_string(),
+				// The file got smaller after the request was sent
+				size: data.len() as u64 * 2,
+				range: Range::Full,
+			}],
+		};
+
+		let sender = tokio::spawn({
+			let req = req.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await
+			}
+		});
+
+		let mut result = Vec::new();
+		let receiver_result = Transfer::new(&req, |_| {}, &Default::default())
+			.receive(&mut server, &mut result)
+			.await;
+
+		assert!(matches!(
+			sender.await.unwrap(),
+			Err(TransferError::FileModified)
+		));
+		assert!(matches!(receiver_result, Err(TransferError::Remote(_))));
+	}
+
 	#[tokio::test]
 	async fn test_msg() {
 		let block = Block {
@@ -465,5 +906,33 @@ mod tests {
 			.await
 			.unwrap();
 		assert_eq!(msg, msg2);
+
+		let msg = Msg::Error("Spacedrive".to_string());
+		let bytes = msg.to_bytes();
+		let msg2 = Msg::from_stream(&mut Cursor::new(bytes), &mut [0u8; 64])
+			.await
+			.unwrap();
+		assert_eq!(msg, msg2);
+
+		let msg = Msg::Digest(*blake3::hash(b"Spacedrive").as_bytes());
+		let bytes = msg.to_bytes();
+		let msg2 = Msg::from_stream(&mut Cursor::new(bytes), &mut [0u8; 64])
+			.await
+			.unwrap();
+		assert_eq!(msg, msg2);
+	}
+
+	#[tokio::test]
+	async fn test_ack() {
+		for ack in [
+			Ack::Continue,
+			Ack::Cancelled,
+			Ack::Complete,
+			Ack::Error("Spacedrive".to_string()),
+		] {
+			let bytes = ack.to_bytes();
+			let ack2 = Ack::from_stream(&mut Cursor::new(bytes)).await.unwrap();
+			assert_eq!(ack, ack2);
+		}
 	}
 }
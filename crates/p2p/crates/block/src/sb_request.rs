@@ -143,6 +143,18 @@ pub enum SpaceblockRequestError {
 }
 
 impl SpaceblockRequest {
+	/// The range of bytes of the file being transferred, clamped to the file size.
+	#[must_use]
+	pub fn byte_range(&self) -> std::ops::Range<u64> {
+		match &self.range {
+			Range::Full => 0..self.size,
+			Range::Partial(range) => {
+				let end = range.end.min(self.size);
+				range.start.min(end)..end
+			}
+		}
+	}
+
 	pub async fn from_stream(
 		stream: &mut (impl AsyncRead + Unpin),
 	) -> Result<Self, SpaceblockRequestError> {
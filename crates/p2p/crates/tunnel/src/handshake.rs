@@ -0,0 +1,271 @@
+//! Authenticated key exchange used to establish a [`Tunnel`](crate::Tunnel).
+//!
+//! The flow is:
+//!  - initiator -> responder: discriminator, initiator library identity, initiator ephemeral key
+//!  - responder -> initiator: responder library identity, responder ephemeral key, responder signature
+//!  - initiator -> responder: initiator signature
+//!
+//! Both signatures cover the whole transcript (both library identities and both ephemeral keys), each with its own label,
+//! so they can't be replayed into another session or reflected back to the other side.
+
+use std::future::Future;
+
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+use rand_core::OsRng;
+use sd_p2p::{Identity, RemoteIdentity, UnicastStream, REMOTE_IDENTITY_LEN, SIGNATURE_LEN};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::TunnelError;
+
+const DISCRIMINATOR: u8 = b'T';
+const EPHEMERAL_KEY_LEN: usize = 32;
+const TRANSCRIPT_LEN: usize = 2 * (REMOTE_IDENTITY_LEN + EPHEMERAL_KEY_LEN);
+
+const RESPONDER_SIGNATURE_LABEL: &[u8] = b"sd-p2p-tunnel v1 responder signature";
+const INITIATOR_SIGNATURE_LABEL: &[u8] = b"sd-p2p-tunnel v1 initiator signature";
+const INITIATOR_TO_RESPONDER_KEY_CONTEXT: &str = "sd-p2p-tunnel v1 initiator to responder key";
+const RESPONDER_TO_INITIATOR_KEY_CONTEXT: &str = "sd-p2p-tunnel v1 responder to initiator key";
+
+pub(crate) struct SessionKeys {
+	pub(crate) send: ChaCha20Poly1305,
+	pub(crate) recv: ChaCha20Poly1305,
+}
+
+struct Transcript([u8; TRANSCRIPT_LEN]);
+
+impl Transcript {
+	fn new(
+		initiator_identity: &RemoteIdentity,
+		initiator_ephemeral: &PublicKey,
+		responder_identity: &RemoteIdentity,
+		responder_ephemeral: &PublicKey,
+	) -> Self {
+		let mut transcript = [0; TRANSCRIPT_LEN];
+		for (chunk, bytes) in transcript.chunks_exact_mut(REMOTE_IDENTITY_LEN).zip([
+			&initiator_identity.get_bytes(),
+			initiator_ephemeral.as_bytes(),
+			&responder_identity.get_bytes(),
+			responder_ephemeral.as_bytes(),
+		]) {
+			chunk.copy_from_slice(bytes);
+		}
+
+		Self(transcript)
+	}
+
+	fn signed_message(&self, label: &[u8]) -> Vec<u8> {
+		[label, &self.0].concat()
+	}
+
+	fn session_keys(
+		&self,
+		ephemeral: EphemeralSecret,
+		remote_ephemeral: &PublicKey,
+	) -> Result<(ChaCha20Poly1305, ChaCha20Poly1305), TunnelError> {
+		let shared_secret = ephemeral.diffie_hellman(remote_ephemeral);
+		if !shared_secret.was_contributory() {
+			return Err(TunnelError::InsecureKeyExchange);
+		}
+
+		let key_material = [shared_secret.as_bytes().as_slice(), &self.0].concat();
+
+		Ok((
+			ChaCha20Poly1305::new(
+				&blake3::derive_key(INITIATOR_TO_RESPONDER_KEY_CONTEXT, &key_material).into(),
+			),
+			ChaCha20Poly1305::new(
+				&blake3::derive_key(RESPONDER_TO_INITIATOR_KEY_CONTEXT, &key_material).into(),
+			),
+		))
+	}
+}
+
+pub(crate) async fn initiator<F, Fut>(
+	stream: &mut UnicastStream,
+	library_identity: &Identity,
+	is_library_instance: F,
+) -> Result<(RemoteIdentity, SessionKeys), TunnelError>
+where
+	F: FnOnce(RemoteIdentity) -> Fut,
+	Fut: Future<Output = bool>,
+{
+	initiator_with_claimed_identity(
+		stream,
+		library_identity,
+		library_identity.to_remote_identity(),
+		is_library_instance,
+	)
+	.await
+}
+
+/// Split from [`initiator`] so tests can present an identity we don't hold the private key for.
+async fn initiator_with_claimed_identity<F, Fut>(
+	stream: &mut UnicastStream,
+	library_identity: &Identity,
+	claimed_identity: RemoteIdentity,
+	is_library_instance: F,
+) -> Result<(RemoteIdentity, SessionKeys), TunnelError>
+where
+	F: FnOnce(RemoteIdentity) -> Fut,
+	Fut: Future<Output = bool>,
+{
+	stream
+		.write_all(&[DISCRIMINATOR])
+		.await
+		.map_err(|_| TunnelError::DiscriminatorWriteError)?;
+
+	let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+	let ephemeral_public = PublicKey::from(&ephemeral);
+
+	stream
+		.write_all(&[claimed_identity.get_bytes(), ephemeral_public.to_bytes()].concat())
+		.await
+		.map_err(TunnelError::ErrorSendingHandshake)?;
+	stream
+		.flush()
+		.await
+		.map_err(TunnelError::ErrorSendingHandshake)?;
+
+	let responder_identity = read_identity(stream).await?;
+	let responder_ephemeral = read_ephemeral_key(stream).await?;
+	let responder_signature = read_signature(stream).await?;
+
+	if !is_library_instance(responder_identity).await {
+		return Err(TunnelError::UnknownLibraryIdentity(Box::new(
+			responder_identity,
+		)));
+	}
+
+	let transcript = Transcript::new(
+		&claimed_identity,
+		&ephemeral_public,
+		&responder_identity,
+		&responder_ephemeral,
+	);
+
+	responder_identity
+		.verify(
+			&transcript.signed_message(RESPONDER_SIGNATURE_LABEL),
+			&responder_signature,
+		)
+		.map_err(|_| TunnelError::InvalidHandshakeSignature(Box::new(responder_identity)))?;
+
+	stream
+		.write_all(&library_identity.sign(&transcript.signed_message(INITIATOR_SIGNATURE_LABEL)))
+		.await
+		.map_err(TunnelError::ErrorSendingHandshake)?;
+	stream
+		.flush()
+		.await
+		.map_err(TunnelError::ErrorSendingHandshake)?;
+
+	let (send, recv) = transcript.session_keys(ephemeral, &responder_ephemeral)?;
+
+	Ok((responder_identity, SessionKeys { send, recv }))
+}
+
+pub(crate) async fn responder<F, Fut>(
+	stream: &mut UnicastStream,
+	find_library_identity: F,
+) -> Result<(RemoteIdentity, SessionKeys), TunnelError>
+where
+	F: FnOnce(RemoteIdentity) -> Fut,
+	Fut: Future<Output = Option<Identity>>,
+{
+	let discriminator = stream
+		.read_u8()
+		.await
+		.map_err(|_| TunnelError::DiscriminatorReadError)?;
+	if discriminator != DISCRIMINATOR {
+		return Err(TunnelError::InvalidDiscriminator);
+	}
+
+	let initiator_identity = read_identity(stream).await?;
+	let initiator_ephemeral = read_ephemeral_key(stream).await?;
+
+	let library_identity = find_library_identity(initiator_identity).await.ok_or(
+		TunnelError::UnknownLibraryIdentity(Box::new(initiator_identity)),
+	)?;
+	let responder_identity = library_identity.to_remote_identity();
+
+	let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+	let ephemeral_public = PublicKey::from(&ephemeral);
+
+	let transcript = Transcript::new(
+		&initiator_identity,
+		&initiator_ephemeral,
+		&responder_identity,
+		&ephemeral_public,
+	);
+
+	stream
+		.write_all(
+			&[
+				responder_identity.get_bytes().as_slice(),
+				ephemeral_public.as_bytes(),
+				&library_identity.sign(&transcript.signed_message(RESPONDER_SIGNATURE_LABEL)),
+			]
+			.concat(),
+		)
+		.await
+		.map_err(TunnelError::ErrorSendingHandshake)?;
+	stream
+		.flush()
+		.await
+		.map_err(TunnelError::ErrorSendingHandshake)?;
+
+	// The initiator proves it holds the private key of the identity it presented
+	initiator_identity
+		.verify(
+			&transcript.signed_message(INITIATOR_SIGNATURE_LABEL),
+			&read_signature(stream).await?,
+		)
+		.map_err(|_| TunnelError::InvalidHandshakeSignature(Box::new(initiator_identity)))?;
+
+	let (recv, send) = transcript.session_keys(ephemeral, &initiator_ephemeral)?;
+
+	Ok((initiator_identity, SessionKeys { send, recv }))
+}
+
+async fn read_identity(stream: &mut UnicastStream) -> Result<RemoteIdentity, TunnelError> {
+	let mut bytes = [0; REMOTE_IDENTITY_LEN];
+	stream
+		.read_exact(&mut bytes)
+		.await
+		.map_err(TunnelError::ErrorReceivingHandshake)?;
+
+	RemoteIdentity::from_bytes(&bytes).map_err(TunnelError::ErrorDecodingLibraryIdentity)
+}
+
+async fn read_ephemeral_key(stream: &mut UnicastStream) -> Result<PublicKey, TunnelError> {
+	let mut bytes = [0; EPHEMERAL_KEY_LEN];
+	stream
+		.read_exact(&mut bytes)
+		.await
+		.map_err(TunnelError::ErrorReceivingHandshake)?;
+
+	Ok(PublicKey::from(bytes))
+}
+
+async fn read_signature(stream: &mut UnicastStream) -> Result<[u8; SIGNATURE_LEN], TunnelError> {
+	let mut bytes = [0; SIGNATURE_LEN];
+	stream
+		.read_exact(&mut bytes)
+		.await
+		.map_err(TunnelError::ErrorReceivingHandshake)?;
+
+	Ok(bytes)
+}
+
+#[cfg(test)]
+pub(crate) async fn initiator_claiming(
+	stream: &mut UnicastStream,
+	library_identity: &Identity,
+	claimed_identity: RemoteIdentity,
+) -> Result<(RemoteIdentity, SessionKeys), TunnelError> {
+	initiator_with_claimed_identity(stream, library_identity, claimed_identity, |_| async {
+		true
+	})
+	.await
+}
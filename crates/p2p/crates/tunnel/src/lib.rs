@@ -1,18 +1,29 @@
 //! A system for creating encrypted tunnels between peers over untrusted connections.
 
 use std::{
+	fmt,
+	future::Future,
 	io,
 	pin::Pin,
-	task::{Context, Poll},
+	task::{ready, Context, Poll},
 };
 
-use sd_p2p_proto::{decode, encode};
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
 use thiserror::Error;
 
 use sd_p2p::{Identity, IdentityErr, RemoteIdentity, UnicastStream};
 
+mod handshake;
+
+/// Maximum amount of plaintext carried by a single encrypted frame.
+const MAX_FRAME_PLAINTEXT_LEN: usize = 64 * 1024;
+/// Size of the Poly1305 authentication tag appended to every frame.
+const TAG_LEN: usize = 16;
+/// Size of the little endian length prefix in front of every frame.
+const FRAME_HEADER_LEN: usize = 4;
+
 #[derive(Debug, Error)]
 pub enum TunnelError {
 	#[error("Error writing discriminator.")]
@@ -21,12 +32,18 @@ pub enum TunnelError {
 	DiscriminatorReadError,
 	#[error("Invalid discriminator. Is this stream actually a tunnel?")]
 	InvalidDiscriminator,
-	#[error("Error sending library id: {0:?}")]
-	ErrorSendingLibraryId(io::Error),
-	#[error("Error receiving library identity: {0:?}")]
-	ErrorReceivingLibraryIdentity(decode::Error),
+	#[error("Error sending handshake: {0:?}")]
+	ErrorSendingHandshake(io::Error),
+	#[error("Error receiving handshake: {0:?}")]
+	ErrorReceivingHandshake(io::Error),
 	#[error("Error decoding library identity: {0:?}")]
 	ErrorDecodingLibraryIdentity(IdentityErr),
+	#[error("The library identity '{0}' is not known to us")]
+	UnknownLibraryIdentity(Box<RemoteIdentity>),
+	#[error("The peer failed to prove it holds the library identity '{0}'")]
+	InvalidHandshakeSignature(Box<RemoteIdentity>),
+	#[error("The key exchange resulted in an insecure shared secret")]
+	InsecureKeyExchange,
 }
 
 /// An encrypted tunnel between two libraries.
@@ -37,66 +54,101 @@ pub enum TunnelError {
 ///     node <-> attacker node <-> node
 /// The attackers node can't break TLS but if they get in the middle they can present their own node identity to each side and then intercept library related traffic.
 /// To avoid that we use this tunnel to encrypt all library related traffic so it can only be decoded by another instance of the same library.
-#[derive(Debug)]
+///
+/// Both ends perform an ephemeral X25519 key exchange where each side signs the handshake transcript with its library `Identity`,
+/// so a peer that doesn't hold the private key of an instance of the library can't complete the handshake.
+/// All data is then sent as length prefixed ChaCha20-Poly1305 frames, with a separate key and nonce counter for each direction.
 pub struct Tunnel {
 	stream: UnicastStream,
 	library_remote_id: RemoteIdentity,
+
+	// Read side
+	recv_cipher: ChaCha20Poly1305,
+	recv_nonce: u64,
+	read_frame_len: Option<usize>,
+	read_buf: Vec<u8>,
+	read_filled: usize,
+	plaintext: Vec<u8>,
+	plaintext_pos: usize,
+
+	// Write side
+	send_cipher: ChaCha20Poly1305,
+	send_nonce: u64,
+	write_buf: Vec<u8>,
+	write_pos: usize,
+}
+
+impl fmt::Debug for Tunnel {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Tunnel")
+			.field("stream", &self.stream)
+			.field("library_remote_id", &self.library_remote_id)
+			.finish_non_exhaustive()
+	}
 }
 
 impl Tunnel {
 	/// Create a new tunnel.
 	///
 	/// This should be used by the node that initiated the request which this tunnel is used for.
-	pub async fn initiator(
+	///
+	/// `is_library_instance` is called with the library identity presented by the remote peer and must only
+	/// resolve to `true` if it's an instance of the same library as `library_identity`.
+	pub async fn initiator<F, Fut>(
 		mut stream: UnicastStream,
 		library_identity: &Identity,
-	) -> Result<Self, TunnelError> {
-		stream
-			.write_all(&[b'T'])
-			.await
-			.map_err(|_| TunnelError::DiscriminatorWriteError)?;
+		is_library_instance: F,
+	) -> Result<Self, TunnelError>
+	where
+		F: FnOnce(RemoteIdentity) -> Fut,
+		Fut: Future<Output = bool>,
+	{
+		let (library_remote_id, keys) =
+			handshake::initiator(&mut stream, library_identity, is_library_instance).await?;
 
-		let mut buf = vec![];
-		encode::buf(&mut buf, &library_identity.to_remote_identity().get_bytes());
-		stream
-			.write_all(&buf)
-			.await
-			.map_err(TunnelError::ErrorSendingLibraryId)?;
-
-		// TODO: Do encryption things
-
-		Ok(Self {
-			stream,
-			library_remote_id: library_identity.to_remote_identity(),
-		})
+		Ok(Self::new(stream, library_remote_id, keys))
 	}
 
 	/// Create a new tunnel.
 	///
 	/// This should be used by the node that responded to the request which this tunnel is used for.
-	pub async fn responder(mut stream: UnicastStream) -> Result<Self, TunnelError> {
-		let discriminator = stream
-			.read_u8()
-			.await
-			.map_err(|_| TunnelError::DiscriminatorReadError)?;
-		if discriminator != b'T' {
-			return Err(TunnelError::InvalidDiscriminator);
-		}
-
-		// TODO: Blindly decoding this from the stream is not secure. We need a cryptographic handshake here to prove the peer on the other ends is holding the private key.
-		let library_remote_id = decode::buf(&mut stream)
-			.await
-			.map_err(TunnelError::ErrorReceivingLibraryIdentity)?;
-
-		let library_remote_id = RemoteIdentity::from_bytes(&library_remote_id)
-			.map_err(TunnelError::ErrorDecodingLibraryIdentity)?;
+	///
+	/// `find_library_identity` is called with the library identity presented by the remote peer and must resolve
+	/// to our own identity within the same library, or `None` if we don't have that library.
+	pub async fn responder<F, Fut>(
+		mut stream: UnicastStream,
+		find_library_identity: F,
+	) -> Result<Self, TunnelError>
+	where
+		F: FnOnce(RemoteIdentity) -> Fut,
+		Fut: Future<Output = Option<Identity>>,
+	{
+		let (library_remote_id, keys) =
+			handshake::responder(&mut stream, find_library_identity).await?;
 
-		// TODO: Do encryption things
+		Ok(Self::new(stream, library_remote_id, keys))
+	}
 
-		Ok(Self {
-			library_remote_id,
+	fn new(
+		stream: UnicastStream,
+		library_remote_id: RemoteIdentity,
+		handshake::SessionKeys { send, recv }: handshake::SessionKeys,
+	) -> Self {
+		Self {
 			stream,
-		})
+			library_remote_id,
+			recv_cipher: recv,
+			recv_nonce: 0,
+			read_frame_len: None,
+			read_buf: vec![0; FRAME_HEADER_LEN],
+			read_filled: 0,
+			plaintext: Vec::new(),
+			plaintext_pos: 0,
+			send_cipher: send,
+			send_nonce: 0,
+			write_buf: Vec::new(),
+			write_pos: 0,
+		}
 	}
 
 	/// Get the `RemoteIdentity` of the peer on the other end of the tunnel.
@@ -108,6 +160,38 @@ impl Tunnel {
 	pub fn library_remote_identity(&self) -> RemoteIdentity {
 		self.library_remote_id
 	}
+
+	/// Write out any already encrypted frame which is still pending.
+	fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		while self.write_pos < self.write_buf.len() {
+			let written = ready!(
+				Pin::new(&mut self.stream).poll_write(cx, &self.write_buf[self.write_pos..])
+			)?;
+
+			if written == 0 {
+				return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+			}
+
+			self.write_pos += written;
+		}
+
+		self.write_buf.clear();
+		self.write_pos = 0;
+
+		Poll::Ready(Ok(()))
+	}
+}
+
+/// Each direction has its own key, so a simple counter is enough to never reuse a nonce.
+fn next_nonce(counter: &mut u64) -> io::Result<chacha20poly1305::Nonce> {
+	let mut nonce = chacha20poly1305::Nonce::default();
+	nonce[..8].copy_from_slice(&counter.to_le_bytes());
+
+	*counter = counter
+		.checked_add(1)
+		.ok_or_else(|| io::Error::other("tunnel nonce exhausted"))?;
+
+	Ok(nonce)
 }
 
 impl AsyncRead for Tunnel {
@@ -116,9 +200,75 @@ impl AsyncRead for Tunnel {
 		cx: &mut Context<'_>,
 		buf: &mut ReadBuf<'_>,
 	) -> Poll<io::Result<()>> {
-		// TODO: Do decryption
+		let this = self.get_mut();
+
+		loop {
+			if this.plaintext_pos < this.plaintext.len() {
+				let len = buf
+					.remaining()
+					.min(this.plaintext.len() - this.plaintext_pos);
+				buf.put_slice(&this.plaintext[this.plaintext_pos..this.plaintext_pos + len]);
+				this.plaintext_pos += len;
+
+				return Poll::Ready(Ok(()));
+			}
+
+			while this.read_filled < this.read_buf.len() {
+				let mut read_buf = ReadBuf::new(&mut this.read_buf[this.read_filled..]);
+				ready!(Pin::new(&mut this.stream).poll_read(cx, &mut read_buf))?;
+
+				let read = read_buf.filled().len();
+				if read == 0 {
+					return Poll::Ready(
+						if this.read_filled == 0 && this.read_frame_len.is_none() {
+							// Clean EOF between frames
+							Ok(())
+						} else {
+							Err(io::ErrorKind::UnexpectedEof.into())
+						},
+					);
+				}
+
+				this.read_filled += read;
+			}
+
+			match this.read_frame_len.take() {
+				None => {
+					let len = u32::from_le_bytes(
+						this.read_buf[..FRAME_HEADER_LEN]
+							.try_into()
+							.expect("header buffer has the right length"),
+					) as usize;
 
-		Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+					if !(TAG_LEN..=MAX_FRAME_PLAINTEXT_LEN + TAG_LEN).contains(&len) {
+						return Poll::Ready(Err(io::Error::new(
+							io::ErrorKind::InvalidData,
+							format!("invalid tunnel frame length: {len}"),
+						)));
+					}
+
+					this.read_frame_len = Some(len);
+					this.read_buf.resize(len, 0);
+				}
+				Some(_) => {
+					let nonce = next_nonce(&mut this.recv_nonce)?;
+					this.plaintext = this
+						.recv_cipher
+						.decrypt(&nonce, this.read_buf.as_slice())
+						.map_err(|_| {
+							io::Error::new(
+								io::ErrorKind::InvalidData,
+								"failed to decrypt tunnel frame",
+							)
+						})?;
+					this.plaintext_pos = 0;
+
+					this.read_buf.resize(FRAME_HEADER_LEN, 0);
+				}
+			}
+
+			this.read_filled = 0;
+		}
 	}
 }
 
@@ -128,16 +278,167 @@ impl AsyncWrite for Tunnel {
 		cx: &mut Context<'_>,
 		buf: &[u8],
 	) -> Poll<io::Result<usize>> {
-		// TODO: Do encryption
+		let this = self.get_mut();
+
+		// We only hold one frame at a time, so the previous one must be sent before accepting more data
+		ready!(this.poll_drain(cx))?;
+
+		if buf.is_empty() {
+			return Poll::Ready(Ok(0));
+		}
+
+		let len = buf.len().min(MAX_FRAME_PLAINTEXT_LEN);
+		let nonce = next_nonce(&mut this.send_nonce)?;
+		let ciphertext = this
+			.send_cipher
+			.encrypt(&nonce, &buf[..len])
+			.map_err(|_| io::Error::other("failed to encrypt tunnel frame"))?;
+
+		this.write_buf.reserve(FRAME_HEADER_LEN + ciphertext.len());
+		this.write_buf
+			.extend_from_slice(&(ciphertext.len() as u32).to_le_bytes());
+		this.write_buf.extend_from_slice(&ciphertext);
+
+		// The frame is now owned by us, so we can report the data as written even if the
+		// underlying stream isn't ready yet. It will be sent on the next write or flush.
+		if let Poll::Ready(Err(e)) = this.poll_drain(cx) {
+			return Poll::Ready(Err(e));
+		}
 
-		Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+		Poll::Ready(Ok(len))
 	}
 
 	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+		let this = self.get_mut();
+		ready!(this.poll_drain(cx))?;
+		Pin::new(&mut this.stream).poll_flush(cx)
 	}
 
 	fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+		let this = self.get_mut();
+		ready!(this.poll_drain(cx))?;
+		Pin::new(&mut this.stream).poll_shutdown(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+	fn stream_pair() -> (UnicastStream, UnicastStream) {
+		let (a, b) = duplex(1024);
+		(
+			UnicastStream::new(Identity::new().to_remote_identity(), a),
+			UnicastStream::new(Identity::new().to_remote_identity(), b),
+		)
+	}
+
+	#[tokio::test]
+	async fn test_tunnel_roundtrip() {
+		let initiator_identity = Identity::new();
+		let responder_identity = Identity::new();
+		let (initiator_stream, responder_stream) = stream_pair();
+
+		let expected_initiator = initiator_identity.to_remote_identity();
+		let expected_responder = responder_identity.to_remote_identity();
+		let responder = tokio::spawn(async move {
+			let mut tunnel = Tunnel::responder(responder_stream, |remote| async move {
+				(remote == expected_initiator).then_some(responder_identity)
+			})
+			.await
+			.unwrap();
+			assert_eq!(tunnel.library_remote_identity(), expected_initiator);
+
+			let mut data = vec![0; 200_000];
+			tunnel.read_exact(&mut data).await.unwrap();
+			tunnel.write_all(&data).await.unwrap();
+			tunnel.flush().await.unwrap();
+		});
+
+		let mut tunnel =
+			Tunnel::initiator(initiator_stream, &initiator_identity, |remote| async move {
+				remote == expected_responder
+			})
+			.await
+			.unwrap();
+		assert_eq!(tunnel.library_remote_identity(), expected_responder);
+
+		// Bigger than a single frame to exercise the framing
+		let data = (0..200_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+		tunnel.write_all(&data).await.unwrap();
+		tunnel.flush().await.unwrap();
+
+		let mut echoed = vec![0; data.len()];
+		tunnel.read_exact(&mut echoed).await.unwrap();
+		assert_eq!(data, echoed);
+
+		responder.await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_responder_rejects_unknown_library() {
+		let (initiator_stream, responder_stream) = stream_pair();
+
+		let responder =
+			tokio::spawn(
+				async move { Tunnel::responder(responder_stream, |_| async { None }).await },
+			);
+
+		let initiator =
+			Tunnel::initiator(initiator_stream, &Identity::new(), |_| async { true }).await;
+
+		assert!(matches!(
+			responder.await.unwrap(),
+			Err(TunnelError::UnknownLibraryIdentity(_))
+		));
+		assert!(initiator.is_err());
+	}
+
+	#[tokio::test]
+	async fn test_initiator_rejects_wrong_library_identity() {
+		let initiator_identity = Identity::new();
+		let legit_responder = Identity::new().to_remote_identity();
+		let (initiator_stream, responder_stream) = stream_pair();
+
+		// The responder holds an identity which isn't an instance of the initiator's library
+		let responder = tokio::spawn(async move {
+			Tunnel::responder(responder_stream, |_| async { Some(Identity::new()) }).await
+		});
+
+		let initiator =
+			Tunnel::initiator(initiator_stream, &initiator_identity, |remote| async move {
+				remote == legit_responder
+			})
+			.await;
+
+		assert!(matches!(
+			initiator,
+			Err(TunnelError::UnknownLibraryIdentity(_))
+		));
+		assert!(responder.await.unwrap().is_err());
+	}
+
+	#[tokio::test]
+	async fn test_impersonating_library_identity_fails() {
+		let attacker_identity = Identity::new();
+		let victim_identity = Identity::new().to_remote_identity();
+		let (mut attacker_stream, responder_stream) = stream_pair();
+
+		let responder = tokio::spawn(async move {
+			Tunnel::responder(responder_stream, |_| async { Some(Identity::new()) }).await
+		});
+
+		// The attacker presents the library identity of a legit instance without holding its private key.
+		// From the attacker's side the handshake looks fine, but the responder must refuse it.
+		handshake::initiator_claiming(&mut attacker_stream, &attacker_identity, victim_identity)
+			.await
+			.ok();
+
+		assert!(matches!(
+			responder.await.unwrap(),
+			Err(TunnelError::InvalidHandshakeSignature(identity)) if *identity == victim_identity
+		));
 	}
 }
@@ -6,7 +6,7 @@ use std::{
 };
 
 use base64::{engine::general_purpose, Engine};
-use ed25519_dalek::{VerifyingKey, SECRET_KEY_LENGTH};
+use ed25519_dalek::{Signature, Signer, VerifyingKey, SECRET_KEY_LENGTH};
 use rand_core::OsRng;
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -14,6 +14,7 @@ use thiserror::Error;
 use zeroize::ZeroizeOnDrop;
 
 pub const REMOTE_IDENTITY_LEN: usize = 32;
+pub const SIGNATURE_LEN: usize = ed25519_dalek::SIGNATURE_LENGTH;
 
 #[derive(Debug, Error)]
 #[error(transparent)]
@@ -22,6 +23,8 @@ pub enum IdentityErr {
 	Dalek(#[from] ed25519_dalek::ed25519::Error),
 	#[error("Invalid key length")]
 	InvalidKeyLength,
+	#[error("Invalid signature")]
+	InvalidSignature,
 }
 
 /// TODO
@@ -63,6 +66,12 @@ impl Identity {
 	pub fn to_remote_identity(&self) -> RemoteIdentity {
 		RemoteIdentity(self.0.verifying_key())
 	}
+
+	/// Sign a message so the holder of the matching [`RemoteIdentity`] can verify it came from us.
+	#[must_use]
+	pub fn sign(&self, message: &[u8]) -> [u8; SIGNATURE_LEN] {
+		self.0.sign(message).to_bytes()
+	}
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Type)]
@@ -163,6 +172,13 @@ impl RemoteIdentity {
 	pub fn verifying_key(&self) -> VerifyingKey {
 		self.0
 	}
+
+	/// Verify a signature produced by [`Identity::sign`] with the private key of this identity.
+	pub fn verify(&self, message: &[u8], signature: &[u8; SIGNATURE_LEN]) -> Result<(), IdentityErr> {
+		self.0
+			.verify_strict(message, &Signature::from_bytes(signature))
+			.map_err(|_| IdentityErr::InvalidSignature)
+	}
 }
 
 impl From<ed25519_dalek::SigningKey> for Identity {
@@ -10,7 +10,7 @@ mod smart_guards;
 mod stream;
 
 pub use hook::{HookEvent, HookId, ListenerId, ShutdownGuard};
-pub use identity::{Identity, IdentityErr, RemoteIdentity};
+pub use identity::{Identity, IdentityErr, RemoteIdentity, REMOTE_IDENTITY_LEN, SIGNATURE_LEN};
 pub use p2p::{Listener, P2P};
 pub use peer::{ConnectionRequest, Peer, PeerConnectionCandidate};
 pub use smart_guards::SmartWriteGuard;
@@ -88,14 +88,17 @@
 #![allow(clippy::missing_errors_doc, clippy::module_name_repetitions)]
 
 mod error;
+mod limit;
 mod message;
 mod system;
 mod task;
 mod worker;
 
 pub use error::{DispatcherShutdownError, RunError, SystemError as TaskSystemError};
+pub use limit::ConcurrencyLimit;
 pub use system::{
 	BaseDispatcher as BaseTaskDispatcher, Dispatcher as TaskDispatcher, System as TaskSystem,
+	SystemConfig as TaskSystemConfig,
 };
 pub use task::{
 	AnyTaskOutput, CancelTaskOnDrop, ExecStatus, Interrupter, InterrupterFuture, InterruptionKind,
@@ -0,0 +1,78 @@
+use std::{
+	num::NonZeroUsize,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+};
+
+/// Caps how many tasks sharing this limit can run at the same time across all workers.
+///
+/// Tasks over the limit stay enqueued in their workers until a running one stops, so a limit
+/// never keeps a worker from running tasks without limits or with other limits.
+/// It can be attached to tasks with [`BaseDispatcher::with_concurrency_limit`].
+///
+/// [`BaseDispatcher::with_concurrency_limit`]: crate::BaseTaskDispatcher::with_concurrency_limit
+#[derive(Debug)]
+pub struct ConcurrencyLimit {
+	max: AtomicUsize,
+	running: AtomicUsize,
+}
+
+impl ConcurrencyLimit {
+	#[must_use]
+	pub fn new(max: NonZeroUsize) -> Arc<Self> {
+		Arc::new(Self {
+			max: AtomicUsize::new(max.get()),
+			running: AtomicUsize::new(0),
+		})
+	}
+
+	/// Returns the maximum number of tasks that can run at the same time.
+	pub fn max(&self) -> usize {
+		self.max.load(Ordering::Acquire)
+	}
+
+	/// Changes the maximum number of tasks that can run at the same time.
+	///
+	/// Running tasks are never interrupted, so lowering the limit takes effect as they stop.
+	pub fn set_max(&self, max: NonZeroUsize) {
+		self.max.store(max.get(), Ordering::Release);
+	}
+
+	/// Returns the number of tasks currently running under this limit.
+	pub fn running(&self) -> usize {
+		self.running.load(Ordering::Acquire)
+	}
+
+	/// Returns `true` if no other task under this limit can start right now.
+	pub fn is_full(&self) -> bool {
+		self.running() >= self.max()
+	}
+
+	pub(crate) fn try_acquire(self: &Arc<Self>) -> Option<ConcurrencyPermit> {
+		if self.running.fetch_add(1, Ordering::AcqRel) < self.max() {
+			Some(ConcurrencyPermit::Limited(Arc::clone(self)))
+		} else {
+			// Giving back the slot we just took, as the limit was already reached
+			self.running.fetch_sub(1, Ordering::AcqRel);
+			None
+		}
+	}
+}
+
+/// Held by a worker while a task is running, releasing its slot in the task's [`ConcurrencyLimit`] on drop.
+#[derive(Debug)]
+pub enum ConcurrencyPermit {
+	/// The task doesn't have a concurrency limit
+	Unlimited,
+	Limited(Arc<ConcurrencyLimit>),
+}
+
+impl Drop for ConcurrencyPermit {
+	fn drop(&mut self) {
+		if let Self::Limited(limit) = self {
+			limit.running.fetch_sub(1, Ordering::AcqRel);
+		}
+	}
+}
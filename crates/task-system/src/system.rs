@@ -6,8 +6,8 @@ use std::{
 	num::NonZeroUsize,
 	pin::pin,
 	sync::{
-		atomic::{AtomicBool, Ordering},
-		Arc,
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Arc, RwLock,
 	},
 };
 
@@ -19,18 +19,75 @@ use tracing::{error, info, instrument, trace, warn, Instrument};
 
 use super::{
 	error::{DispatcherShutdownError, RunError, SystemError},
+	limit::ConcurrencyLimit,
 	message::SystemMessage,
 	task::{IntoTask, Task, TaskHandle, TaskId, TaskWorktable},
-	worker::{AtomicWorkerId, WorkStealer, Worker, WorkerBuilder},
+	worker::{AtomicWorkerId, WorkStealer, Worker, WorkerBuilder, WorkerId},
 };
 
+/// Settings used to create a task [`System`].
+#[derive(Debug, Clone, Copy)]
+pub struct SystemConfig {
+	/// Number of workers running tasks in parallel, it can be changed later with
+	/// [`System::resize_workers`]
+	pub workers_count: NonZeroUsize,
+}
+
+impl Default for SystemConfig {
+	/// Uses half of the available parallelism in the user's machine, so the system doesn't starve
+	/// the rest of the application
+	fn default() -> Self {
+		Self {
+			workers_count: NonZeroUsize::new(available_parallelism().get() / 2)
+				.unwrap_or(NonZeroUsize::MIN),
+		}
+	}
+}
+
+fn available_parallelism() -> NonZeroUsize {
+	std::thread::available_parallelism().unwrap_or_else(|e| {
+		error!(?e, "Failed to get available parallelism in the job system");
+		NonZeroUsize::MIN
+	})
+}
+
+/// The workers shared between the system, its dispatchers and the message processing task.
+///
+/// Workers are only appended and never removed, so the worker ids stored in the worktables of
+/// tasks always point to a valid worker. Shrinking the system retires the workers with an id
+/// greater or equal to the active count instead, see [`System::resize_workers`].
+#[derive(Debug)]
+struct Workers<E: RunError> {
+	list: RwLock<Arc<Vec<Arc<Worker<E>>>>>,
+	active_count: Arc<AtomicUsize>,
+}
+
+impl<E: RunError> Workers<E> {
+	fn get(&self, worker_id: WorkerId) -> Arc<Worker<E>> {
+		Arc::clone(&self.list.read().expect("workers lock poisoned")[worker_id])
+	}
+
+	fn active_count(&self) -> usize {
+		self.active_count.load(Ordering::Acquire)
+	}
+
+	/// The list of workers along with how many of them are active, read under the same lock as
+	/// resizes update both, so the active ones are always in the returned list
+	fn all_with_active_count(&self) -> (Arc<Vec<Arc<Worker<E>>>>, usize) {
+		let list = self.list.read().expect("workers lock poisoned");
+		(Arc::clone(&list), self.active_count())
+	}
+}
+
 /// The task system is the main entry point for the library, it is responsible for creating and managing the workers
 /// and dispatching tasks to them.
 ///
 /// It also provides a way to shutdown the system returning all pending and running tasks.
 /// It uses internal mutability so it can be shared without hassles using [`Arc`].
 pub struct System<E: RunError> {
-	workers: Arc<Vec<Worker<E>>>,
+	workers: Arc<Workers<E>>,
+	comm: SystemComm,
+	work_stealer: WorkStealer<E>,
 	msgs_tx: chan::Sender<SystemMessage>,
 	dispatcher: BaseDispatcher<E>,
 	handle: RefCell<Option<JoinHandle<()>>>,
@@ -38,19 +95,17 @@ pub struct System<E: RunError> {
 }
 
 impl<E: RunError> System<E> {
-	/// Created a new task system with a number of workers equal to the available parallelism in the user's machine.
+	/// Created a new task system with a number of workers equal to half of the available parallelism in the
+	/// user's machine.
+	#[must_use]
 	pub fn new() -> Self {
-		// TODO: Using only the half of available cores, make this configurable on runtime in the future
-		let workers_count = usize::max(
-			std::thread::available_parallelism().map_or_else(
-				|e| {
-					error!(?e, "Failed to get available parallelism in the job system");
-					1
-				},
-				NonZeroUsize::get,
-			) / 2,
-			1,
-		);
+		Self::with_config(SystemConfig::default())
+	}
+
+	/// Creates a new task system with the given settings.
+	#[must_use]
+	pub fn with_config(SystemConfig { workers_count }: SystemConfig) -> Self {
+		let workers_count = workers_count.get();
 
 		let (msgs_tx, msgs_rx) = chan::bounded(8);
 		let system_comm = SystemComm(msgs_tx.clone());
@@ -59,30 +114,28 @@ impl<E: RunError> System<E> {
 			.map(WorkerBuilder::new)
 			.unzip::<_, _, Vec<_>, Vec<_>>();
 
-		let task_stealer = WorkStealer::new(worker_comms);
+		let active_count = Arc::new(AtomicUsize::new(workers_count));
 
-		let idle_workers = Arc::new((0..workers_count).map(|_| AtomicBool::new(true)).collect());
+		let work_stealer = WorkStealer::new(worker_comms, Arc::clone(&active_count));
 
-		let workers = Arc::new(
-			workers_builders
-				.into_iter()
-				.map(|builder| builder.build(system_comm.clone(), task_stealer.clone()))
-				.collect::<Vec<_>>(),
-		);
+		let workers = Arc::new(Workers {
+			list: RwLock::new(Arc::new(
+				workers_builders
+					.into_iter()
+					.map(|builder| {
+						Arc::new(builder.build(system_comm.clone(), work_stealer.clone()))
+					})
+					.collect(),
+			)),
+			active_count,
+		});
 
 		let handle = spawn({
 			let workers = Arc::clone(&workers);
-			let idle_workers = Arc::clone(&idle_workers);
 
 			async move {
 				trace!("Task System message processing task starting...");
-				while let Err(e) = spawn(Self::run(
-					Arc::clone(&workers),
-					Arc::clone(&idle_workers),
-					msgs_rx.clone(),
-				))
-				.await
-				{
+				while let Err(e) = spawn(Self::run(Arc::clone(&workers), msgs_rx.clone())).await {
 					if e.is_panic() {
 						error!(?e, "Task system panicked");
 					} else {
@@ -102,11 +155,13 @@ impl<E: RunError> System<E> {
 
 		Self {
 			workers: Arc::clone(&workers),
+			comm: system_comm,
+			work_stealer,
 			msgs_tx,
 			dispatcher: BaseDispatcher {
 				workers,
-				idle_workers,
 				last_worker_id: Arc::new(AtomicWorkerId::new(0)),
+				concurrency_limit: None,
 				has_shutdown: Arc::clone(&has_shutdown),
 			},
 			handle: RefCell::new(Some(handle)),
@@ -116,7 +171,61 @@ impl<E: RunError> System<E> {
 
 	/// Returns the number of workers in the system.
 	pub fn workers_count(&self) -> usize {
-		self.workers.len()
+		self.workers.active_count()
+	}
+
+	/// Changes the number of workers running tasks, while the system keeps running.
+	///
+	/// Growing reactivates previously retired workers before spawning new ones. Shrinking retires
+	/// the workers with the highest ids: they finish the task they're running and stop receiving
+	/// new ones, while their pending tasks are stolen by the remaining workers, so no task is lost
+	/// or interrupted.
+	///
+	/// # Panics
+	///
+	/// If the workers lock is poisoned, which can only happen if a previous resize panicked.
+	pub fn resize_workers(&self, workers_count: NonZeroUsize) {
+		if self.has_shutdown.load(Ordering::Acquire) {
+			warn!("Trying to resize the workers of a task system that was already shutdown");
+			return;
+		}
+
+		let workers_count = workers_count.get();
+
+		let mut list = self.workers.list.write().expect("workers lock poisoned");
+
+		let old_workers_count = self.workers.active_count();
+		let spawned_workers_count = workers_count.saturating_sub(list.len());
+
+		if spawned_workers_count > 0 {
+			let mut new_list = list.as_ref().clone();
+
+			new_list.extend((list.len()..workers_count).map(|worker_id| {
+				let (builder, worker_comm) = WorkerBuilder::new(worker_id);
+				self.work_stealer.add_worker_comm(worker_comm);
+				Arc::new(builder.build(self.comm.clone(), self.work_stealer.clone()))
+			}));
+
+			*list = Arc::new(new_list);
+		}
+
+		// Storing while holding the write lock, so dispatchers never see an active worker
+		// that isn't in the list yet
+		self.workers
+			.active_count
+			.store(workers_count, Ordering::Release);
+
+		info!(
+			%old_workers_count,
+			%workers_count,
+			%spawned_workers_count,
+			retired_workers_count = list.len() - workers_count,
+			idle_workers_count = list[..workers_count]
+				.iter()
+				.filter(|worker| worker.is_idle())
+				.count(),
+			"Task system workers resized;",
+		);
 	}
 
 	/// Dispatches a task to the system, the task will be assigned to a worker and executed as soon as possible.
@@ -145,21 +254,17 @@ impl<E: RunError> System<E> {
 		self.dispatcher.clone()
 	}
 
-	async fn run(
-		workers: Arc<Vec<Worker<E>>>,
-		idle_workers: Arc<Vec<AtomicBool>>,
-		msgs_rx: chan::Receiver<SystemMessage>,
-	) {
+	async fn run(workers: Arc<Workers<E>>, msgs_rx: chan::Receiver<SystemMessage>) {
 		let mut msg_stream = pin!(msgs_rx);
 
 		while let Some(msg) = msg_stream.next().await {
 			match msg {
 				SystemMessage::IdleReport(worker_id) => {
-					idle_workers[worker_id].store(true, Ordering::Relaxed);
+					workers.get(worker_id).set_idle(true);
 				}
 
 				SystemMessage::WorkingReport(worker_id) => {
-					idle_workers[worker_id].store(false, Ordering::Relaxed);
+					workers.get(worker_id).set_idle(false);
 				}
 
 				SystemMessage::ResumeTask {
@@ -221,7 +326,9 @@ impl<E: RunError> System<E> {
 			.ok()
 			.and_then(|mut maybe_handle| maybe_handle.take())
 		{
+			// Retired workers are also shutdown, as they can still have pending tasks
 			self.workers
+				.all()
 				.iter()
 				.map(|worker| async move { worker.shutdown().await })
 				.collect::<Vec<_>>()
@@ -253,7 +360,7 @@ impl<E: RunError> System<E> {
 
 #[instrument(skip(workers, ack))]
 fn dispatch_resume_request<E: RunError>(
-	workers: &Arc<Vec<Worker<E>>>,
+	workers: &Arc<Workers<E>>,
 	task_id: TaskId,
 	task_work_table: Arc<TaskWorktable>,
 	ack: oneshot::Sender<Result<(), SystemError>>,
@@ -265,7 +372,8 @@ fn dispatch_resume_request<E: RunError>(
 			async move {
 				let (tx, rx) = oneshot::channel();
 				let first_attempt_worker_id = task_work_table.worker_id();
-				workers[first_attempt_worker_id]
+				workers
+					.get(first_attempt_worker_id)
 					.resume_task(task_id, tx)
 					.await;
 				let res = rx
@@ -277,7 +385,8 @@ fn dispatch_resume_request<E: RunError>(
 						%first_attempt_worker_id,
 						"Failed the first try to resume a not running task, trying again",
 					);
-					workers[task_work_table.worker_id()]
+					workers
+						.get(task_work_table.worker_id())
 						.resume_task(task_id, ack)
 						.await;
 				} else {
@@ -293,19 +402,20 @@ fn dispatch_resume_request<E: RunError>(
 
 #[instrument(skip(workers, ack, task_work_table))]
 fn dispatch_pause_not_running_task_request<E: RunError>(
-	workers: &Arc<Vec<Worker<E>>>,
+	workers: &Arc<Workers<E>>,
 	task_id: TaskId,
 	task_work_table: Arc<TaskWorktable>,
 	ack: oneshot::Sender<Result<(), SystemError>>,
 ) {
 	spawn(
 		{
-			let workers: Arc<Vec<Worker<E>>> = Arc::clone(workers);
+			let workers = Arc::clone(workers);
 
 			async move {
 				let (tx, rx) = oneshot::channel();
 				let first_attempt_worker_id = task_work_table.worker_id();
-				workers[first_attempt_worker_id]
+				workers
+					.get(first_attempt_worker_id)
 					.pause_not_running_task(task_id, tx)
 					.await;
 				let res = rx
@@ -317,7 +427,8 @@ fn dispatch_pause_not_running_task_request<E: RunError>(
 						%first_attempt_worker_id,
 						"Failed the first try to pause a not running task, trying again",
 					);
-					workers[task_work_table.worker_id()]
+					workers
+						.get(task_work_table.worker_id())
 						.pause_not_running_task(task_id, ack)
 						.await;
 				} else {
@@ -332,7 +443,7 @@ fn dispatch_pause_not_running_task_request<E: RunError>(
 
 #[instrument(skip(workers, ack))]
 fn dispatch_cancel_not_running_task_request<E: RunError>(
-	workers: &Arc<Vec<Worker<E>>>,
+	workers: &Arc<Workers<E>>,
 	task_id: TaskId,
 	task_work_table: Arc<TaskWorktable>,
 	ack: oneshot::Sender<Result<(), SystemError>>,
@@ -344,7 +455,8 @@ fn dispatch_cancel_not_running_task_request<E: RunError>(
 			async move {
 				let (tx, rx) = oneshot::channel();
 				let first_attempt_worker_id = task_work_table.worker_id();
-				workers[first_attempt_worker_id]
+				workers
+					.get(first_attempt_worker_id)
 					.cancel_not_running_task(task_id, tx)
 					.await;
 				let res = rx
@@ -362,7 +474,8 @@ fn dispatch_cancel_not_running_task_request<E: RunError>(
 						%first_attempt_worker_id,
 						"Failed the first try to cancel a not running task, trying again",
 					);
-					workers[task_work_table.worker_id()]
+					workers
+						.get(task_work_table.worker_id())
 						.cancel_not_running_task(task_id, ack)
 						.await;
 				} else {
@@ -379,7 +492,7 @@ fn dispatch_cancel_not_running_task_request<E: RunError>(
 
 #[instrument(skip(workers, ack))]
 fn dispatch_force_abortion_task_request<E: RunError>(
-	workers: &Arc<Vec<Worker<E>>>,
+	workers: &Arc<Workers<E>>,
 	task_id: TaskId,
 	task_work_table: Arc<TaskWorktable>,
 	ack: oneshot::Sender<Result<(), SystemError>>,
@@ -391,7 +504,8 @@ fn dispatch_force_abortion_task_request<E: RunError>(
 			async move {
 				let (tx, rx) = oneshot::channel();
 				let first_attempt_worker_id = task_work_table.worker_id();
-				workers[first_attempt_worker_id]
+				workers
+					.get(first_attempt_worker_id)
 					.force_task_abortion(task_id, tx)
 					.await;
 				let res = rx.await.expect(
@@ -403,7 +517,8 @@ fn dispatch_force_abortion_task_request<E: RunError>(
 						%first_attempt_worker_id,
 						"Failed the first try to force abortion of a not running task, trying again",
 					);
-					workers[task_work_table.worker_id()]
+					workers
+						.get(task_work_table.worker_id())
 						.force_task_abortion(task_id, ack)
 						.await;
 				} else {
@@ -418,8 +533,7 @@ fn dispatch_force_abortion_task_request<E: RunError>(
 	trace!("Task system aborted task");
 }
 
-/// The default implementation of the task system will create a system with a number of workers equal to the available
-/// parallelism in the user's machine.
+/// The default implementation of the task system will create a system with the default [`SystemConfig`].
 impl<E: RunError> Default for System<E> {
 	fn default() -> Self {
 		Self::new()
@@ -556,9 +670,9 @@ impl SystemComm {
 /// It uses [`Arc`] internally so it can be cheaply cloned and put inside tasks so tasks can dispatch other tasks.
 #[derive(Debug)]
 pub struct BaseDispatcher<E: RunError> {
-	workers: Arc<Vec<Worker<E>>>,
-	idle_workers: Arc<Vec<AtomicBool>>,
+	workers: Arc<Workers<E>>,
 	last_worker_id: Arc<AtomicWorkerId>,
+	concurrency_limit: Option<Arc<ConcurrencyLimit>>,
 	has_shutdown: Arc<AtomicBool>,
 }
 
@@ -610,8 +724,8 @@ impl<E: RunError> Clone for BaseDispatcher<E> {
 	fn clone(&self) -> Self {
 		Self {
 			workers: Arc::clone(&self.workers),
-			idle_workers: Arc::clone(&self.idle_workers),
 			last_worker_id: Arc::clone(&self.last_worker_id),
+			concurrency_limit: self.concurrency_limit.clone(),
 			has_shutdown: Arc::clone(&self.has_shutdown),
 		}
 	}
@@ -629,18 +743,24 @@ impl<E: RunError> Dispatcher<E> for BaseDispatcher<E> {
 			return Err(DispatcherShutdownError(vec![task]));
 		}
 
+		// Only dispatching to active workers, the retired ones are being drained
+		let active_count = self.workers.active_count();
+
 		let worker_id = self
 				.last_worker_id
 				.fetch_update(Ordering::Release, Ordering::Acquire, |last_worker_id| {
-					Some((last_worker_id + 1) % self.workers.len())
+					Some((last_worker_id + 1) % active_count)
 				})
-				.expect("we hardcoded the update function to always return Some(next_worker_id) through dispatcher");
+				.expect("we hardcoded the update function to always return Some(next_worker_id) through dispatcher")
+				% active_count;
 
 		trace!(%worker_id, task_id = %task.id(), "Dispatching task to worker");
 
-		let handle = self.workers[worker_id].add_task(task).await;
+		let worker = self.workers.get(worker_id);
+
+		let handle = worker.add_task(task, self.concurrency_limit.clone()).await;
 
-		self.idle_workers[worker_id].store(false, Ordering::Relaxed);
+		worker.set_idle(false);
 
 		Ok(handle)
 	}
@@ -653,11 +773,20 @@ impl<E: RunError> Dispatcher<E> for BaseDispatcher<E> {
 			return Err(DispatcherShutdownError(into_tasks.into_iter().collect()));
 		}
 
+		let (workers, active_count) = self.workers.all_with_active_count();
+
 		let (handles, workers_ids_set) = into_tasks
 			.into_iter()
-			.zip((0..self.workers.len()).cycle())
-			.map(|(task, worker_id)| async move {
-				(self.workers[worker_id].add_task(task).await, worker_id)
+			// Only dispatching to active workers, the retired ones are being drained
+			.zip((0..active_count).cycle())
+			.map(|(task, worker_id)| {
+				let worker = &workers[worker_id];
+				async move {
+					(
+						worker.add_task(task, self.concurrency_limit.clone()).await,
+						worker_id,
+					)
+				}
 			})
 			.collect::<Vec<_>>()
 			.join()
@@ -666,7 +795,7 @@ impl<E: RunError> Dispatcher<E> for BaseDispatcher<E> {
 			.unzip::<_, _, Vec<_>, HashSet<_>>();
 
 		for worker_id in workers_ids_set {
-			self.idle_workers[worker_id].store(false, Ordering::Relaxed);
+			workers[worker_id].set_idle(false);
 		}
 
 		Ok(handles)
@@ -677,6 +806,16 @@ impl<E: RunError> BaseDispatcher<E> {
 	/// Returns the number of workers in the system.
 	#[must_use]
 	pub fn workers_count(&self) -> usize {
-		self.workers.len()
+		self.workers.active_count()
+	}
+
+	/// Returns a dispatcher whose tasks share the given [`ConcurrencyLimit`], other dispatchers
+	/// created from the same system aren't affected.
+	#[must_use]
+	pub fn with_concurrency_limit(&self, concurrency_limit: Arc<ConcurrencyLimit>) -> Self {
+		Self {
+			concurrency_limit: Some(concurrency_limit),
+			..self.clone()
+		}
 	}
 }
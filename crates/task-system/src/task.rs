@@ -20,6 +20,7 @@ use uuid::Uuid;
 
 use super::{
 	error::{RunError, SystemError},
+	limit::{ConcurrencyLimit, ConcurrencyPermit},
 	system::SystemComm,
 	worker::{AtomicWorkerId, WorkerId},
 };
@@ -878,6 +879,7 @@ pub struct TaskWorkState<E: RunError> {
 	pub(crate) worktable: Arc<TaskWorktable>,
 	pub(crate) done_tx: PanicOnSenderDrop<E>,
 	pub(crate) interrupter: Arc<Interrupter>,
+	pub(crate) concurrency_limit: Option<Arc<ConcurrencyLimit>>,
 }
 
 impl<E: RunError> TaskWorkState<E> {
@@ -890,6 +892,24 @@ impl<E: RunError> TaskWorkState<E> {
 	pub fn kind(&self) -> PendingTaskKind {
 		PendingTaskKind::with_priority(self.task.with_priority())
 	}
+
+	/// Returns `true` if the task can't start right now due to its concurrency limit
+	#[inline]
+	pub fn is_blocked(&self) -> bool {
+		self.concurrency_limit
+			.as_ref()
+			.is_some_and(|limit| limit.is_full())
+	}
+
+	/// Returns [`None`] if the task can't start right now due to its concurrency limit, otherwise
+	/// returns the permit that must be held while the task runs
+	#[inline]
+	pub fn try_acquire_permit(&self) -> Option<ConcurrencyPermit> {
+		self.concurrency_limit.as_ref().map_or(
+			Some(ConcurrencyPermit::Unlimited),
+			ConcurrencyLimit::try_acquire,
+		)
+	}
 }
 
 #[derive(Debug)]
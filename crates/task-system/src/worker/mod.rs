@@ -1,6 +1,9 @@
 use std::{
 	cell::RefCell,
-	sync::{atomic::AtomicUsize, Arc},
+	sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Arc, RwLock,
+	},
 	time::Duration,
 };
 
@@ -10,6 +13,7 @@ use tracing::{error, info, instrument, trace, warn, Instrument};
 
 use super::{
 	error::{RunError, SystemError},
+	limit::ConcurrencyLimit,
 	message::{StoleTaskMessage, TaskRunnerOutput, WorkerMessage},
 	system::SystemComm,
 	task::{
@@ -91,6 +95,7 @@ impl<E: RunError> WorkerBuilder<E> {
 			id,
 			system_comm,
 			msgs_tx,
+			is_idle: AtomicBool::new(true),
 			handle: RefCell::new(Some(handle)),
 		}
 	}
@@ -101,11 +106,16 @@ pub struct Worker<E: RunError> {
 	pub id: usize,
 	system_comm: SystemComm,
 	msgs_tx: chan::Sender<WorkerMessage<E>>,
+	is_idle: AtomicBool,
 	handle: RefCell<Option<JoinHandle<()>>>,
 }
 
 impl<E: RunError> Worker<E> {
-	pub async fn add_task(&self, new_task: Box<dyn Task<E>>) -> TaskHandle<E> {
+	pub async fn add_task(
+		&self,
+		new_task: Box<dyn Task<E>>,
+		concurrency_limit: Option<Arc<ConcurrencyLimit>>,
+	) -> TaskHandle<E> {
 		let (done_tx, done_rx) = oneshot::channel();
 
 		let (interrupt_tx, interrupt_rx) = chan::bounded(1);
@@ -120,6 +130,7 @@ impl<E: RunError> Worker<E> {
 				worktable: Arc::clone(&worktable),
 				interrupter: Arc::new(Interrupter::new(interrupt_rx)),
 				done_tx: PanicOnSenderDrop::new(task_id, done_tx),
+				concurrency_limit,
 			}))
 			.await
 			.expect("Worker channel closed trying to add task");
@@ -134,6 +145,14 @@ impl<E: RunError> Worker<E> {
 		}
 	}
 
+	pub fn is_idle(&self) -> bool {
+		self.is_idle.load(Ordering::Relaxed)
+	}
+
+	pub fn set_idle(&self, is_idle: bool) {
+		self.is_idle.store(is_idle, Ordering::Relaxed);
+	}
+
 	pub async fn resume_task(
 		&self,
 		task_id: TaskId,
@@ -210,12 +229,20 @@ impl<E: RunError> Worker<E> {
 /// receiving `&self` which is called once, and we also use `try_borrow_mut` so we never panic
 unsafe impl<E: RunError> Sync for Worker<E> {}
 
-#[derive(Clone)]
 pub struct WorkerComm<E: RunError> {
 	worker_id: WorkerId,
 	msgs_tx: chan::Sender<WorkerMessage<E>>,
 }
 
+impl<E: RunError> Clone for WorkerComm<E> {
+	fn clone(&self) -> Self {
+		Self {
+			worker_id: self.worker_id,
+			msgs_tx: self.msgs_tx.clone(),
+		}
+	}
+}
+
 impl<E: RunError> WorkerComm<E> {
 	pub async fn steal_task(
 		&self,
@@ -238,51 +265,78 @@ impl<E: RunError> WorkerComm<E> {
 	}
 }
 
+/// Lets idle workers steal tasks from the others.
+///
+/// Workers with an id greater or equal to the active workers count are retired: they can't steal,
+/// but their pending tasks can still be stolen by active workers, which is how they are drained.
 pub struct WorkStealer<E: RunError> {
-	worker_comms: Arc<Vec<WorkerComm<E>>>,
+	worker_comms: Arc<RwLock<Vec<WorkerComm<E>>>>,
+	active_workers_count: Arc<AtomicUsize>,
 }
 
 impl<E: RunError> Clone for WorkStealer<E> {
 	fn clone(&self) -> Self {
 		Self {
 			worker_comms: Arc::clone(&self.worker_comms),
+			active_workers_count: Arc::clone(&self.active_workers_count),
 		}
 	}
 }
 
 impl<E: RunError> WorkStealer<E> {
-	pub fn new(worker_comms: Vec<WorkerComm<E>>) -> Self {
+	pub fn new(worker_comms: Vec<WorkerComm<E>>, active_workers_count: Arc<AtomicUsize>) -> Self {
 		Self {
-			worker_comms: Arc::new(worker_comms),
+			worker_comms: Arc::new(RwLock::new(worker_comms)),
+			active_workers_count,
 		}
 	}
 
+	pub fn add_worker_comm(&self, worker_comm: WorkerComm<E>) {
+		self.worker_comms
+			.write()
+			.expect("work stealer lock poisoned")
+			.push(worker_comm);
+	}
+
+	pub fn is_retired(&self, worker_id: WorkerId) -> bool {
+		worker_id >= self.active_workers_count.load(Ordering::Acquire)
+	}
+
 	#[instrument(skip(self, stolen_task_tx))]
 	pub async fn steal(
 		&self,
 		stealer_id: WorkerId,
 		stolen_task_tx: &chan::Sender<Option<StoleTaskMessage<E>>>,
 	) {
-		let total_workers = self.worker_comms.len();
-
-		for worker_comm in self
-			.worker_comms
-			.iter()
-			// Cycling over the workers
-			.cycle()
-			// Starting from the next worker id
-			.skip(stealer_id)
-			// Taking the total amount of workers
-			.take(total_workers)
-			// Removing the current worker as we can't steal from ourselves
-			.filter(|worker_comm| worker_comm.worker_id != stealer_id)
-		{
-			if worker_comm
-				.steal_task(stealer_id, stolen_task_tx.clone())
-				.await
+		if self.is_retired(stealer_id) {
+			trace!("Retired workers don't steal tasks");
+		} else {
+			let worker_comms = self
+				.worker_comms
+				.read()
+				.expect("work stealer lock poisoned")
+				.clone();
+
+			let total_workers = worker_comms.len();
+
+			for worker_comm in worker_comms
+				.iter()
+				// Cycling over the workers
+				.cycle()
+				// Starting from the next worker id
+				.skip(stealer_id)
+				// Taking the total amount of workers
+				.take(total_workers)
+				// Removing the current worker as we can't steal from ourselves
+				.filter(|worker_comm| worker_comm.worker_id != stealer_id)
 			{
-				trace!(stolen_worker_id = worker_comm.worker_id, "Stole a task");
-				return;
+				if worker_comm
+					.steal_task(stealer_id, stolen_task_tx.clone())
+					.await
+				{
+					trace!(stolen_worker_id = worker_comm.worker_id, "Stole a task");
+					return;
+				}
 			}
 		}
 
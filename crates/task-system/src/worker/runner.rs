@@ -25,6 +25,7 @@ use tracing::{debug, error, instrument, trace, warn, Instrument};
 use super::{
 	super::{
 		error::{RunError, SystemError},
+		limit::{ConcurrencyLimit, ConcurrencyPermit},
 		message::{StoleTaskMessage, TaskOutputMessage},
 		system::SystemComm,
 		task::{
@@ -56,6 +57,8 @@ struct RunningTask {
 	id: TaskId,
 	kind: PendingTaskKind,
 	handle: JoinHandle<Result<(), Box<dyn Any + Send>>>,
+	// Released when the task stops running, letting other tasks under the same limit start
+	_permit: ConcurrencyPermit,
 }
 
 enum WaitingSuspendedTask {
@@ -382,6 +385,7 @@ impl<E: RunError> Runner<E> {
 		task_id: TaskId,
 		task_kind: PendingTaskKind,
 		task_work_state: TaskWorkState<E>,
+		permit: ConcurrencyPermit,
 	) {
 		trace!("Idle worker will process the new task");
 		let handle = self.spawn_task_runner(task_id, task_work_state);
@@ -390,6 +394,7 @@ impl<E: RunError> Runner<E> {
 			id: task_id,
 			kind: task_kind,
 			handle,
+			_permit: permit,
 		});
 
 		// Doesn't need to report working back to system as it already registered
@@ -461,8 +466,18 @@ impl<E: RunError> Runner<E> {
 		task_work_state: TaskWorkState<E>,
 	) -> TaskAddStatus {
 		if self.is_idle {
-			self.add_task_when_idle(task_id, task_kind, task_work_state);
-			TaskAddStatus::Running
+			if let Some(permit) = self.try_acquire_permit(&task_work_state) {
+				self.add_task_when_idle(task_id, task_kind, task_work_state, permit);
+				TaskAddStatus::Running
+			} else {
+				trace!("Worker is retired or the task reached its concurrency limit");
+				self.enqueue_task(task_kind, task_work_state);
+				TaskAddStatus::Enqueued
+			}
+		} else if self.work_stealer.is_retired(self.worker_id) {
+			trace!("Worker is retired, will enqueue new task to be stolen");
+			self.enqueue_task(task_kind, task_work_state);
+			TaskAddStatus::Enqueued
 		} else {
 			trace!("Worker is busy");
 
@@ -588,27 +603,11 @@ impl<E: RunError> Runner<E> {
 		} = self;
 
 		if is_idle {
-			trace!("Worker is idle, no tasks to shutdown");
+			trace!("Worker is idle, no running task to shutdown");
 			assert!(
 				current_task_handle.is_none(),
 				"can't shutdown with a running task if we're idle"
 			);
-			assert!(
-				tasks.is_empty(),
-				"can't shutdown with pending tasks if we're idle"
-			);
-			assert!(
-				priority_tasks.is_empty(),
-				"can't shutdown with priority tasks if we're idle"
-			);
-			assert!(
-				suspended_task.is_none(),
-				"can't shutdown with a suspended task if we're idle"
-			);
-
-			paused_tasks
-				.into_values()
-				.for_each(send_shutdown_task_response);
 		} else {
 			trace!("Worker is busy, will shutdown tasks");
 
@@ -648,15 +647,17 @@ impl<E: RunError> Runner<E> {
 				)
 				.await;
 			}
-
-			priority_tasks
-				.into_iter()
-				.chain(suspended_task.into_iter())
-				.chain(paused_tasks.into_values())
-				.chain(tasks.into_iter())
-				.for_each(send_shutdown_task_response);
 		}
 
+		// Retired workers and tasks blocked by their concurrency limit
+		// can also leave pending tasks in idle workers
+		priority_tasks
+			.into_iter()
+			.chain(suspended_task.into_iter())
+			.chain(paused_tasks.into_values())
+			.chain(tasks.into_iter())
+			.for_each(send_shutdown_task_response);
+
 		trace!("Worker shutdown process completed");
 
 		if tx.send(()).is_err() {
@@ -721,19 +722,66 @@ impl<E: RunError> Runner<E> {
 		}
 	}
 
-	pub(super) fn get_next_task(&mut self) -> Option<(PendingTaskKind, TaskWorkState<E>)> {
-		if let Some(task) = self.priority_tasks.pop_front() {
-			return Some((PendingTaskKind::Priority, task));
+	/// Retired workers don't start new tasks, leaving them to be stolen by active workers
+	#[inline]
+	fn try_acquire_permit(&self, task_work_state: &TaskWorkState<E>) -> Option<ConcurrencyPermit> {
+		if self.work_stealer.is_retired(self.worker_id) {
+			None
+		} else {
+			task_work_state.try_acquire_permit()
+		}
+	}
+
+	#[inline]
+	fn enqueue_task(&mut self, task_kind: PendingTaskKind, task_work_state: TaskWorkState<E>) {
+		match task_kind {
+			PendingTaskKind::Normal => self.tasks.push_back(task_work_state),
+			PendingTaskKind::Priority => self.priority_tasks.push_back(task_work_state),
+			PendingTaskKind::Suspended => {
+				if self.suspended_task.is_none() {
+					self.suspended_task = Some(task_work_state);
+				} else {
+					self.priority_tasks.push_front(task_work_state);
+				}
+			}
+		}
+	}
+
+	/// Takes the next task following the queues priorities, skipping the tasks for which `select`
+	/// returns [`None`]
+	fn take_next_task<T>(
+		&mut self,
+		select: impl Fn(&TaskWorkState<E>) -> Option<T>,
+	) -> Option<(PendingTaskKind, TaskWorkState<E>, T)> {
+		if let Some((task, selected)) = take_from_queue(&mut self.priority_tasks, &select) {
+			return Some((PendingTaskKind::Priority, task, selected));
 		}
 
-		if let Some(task) = self.suspended_task.take() {
+		if let Some(selected) = self.suspended_task.as_ref().and_then(&select) {
+			let task = self.suspended_task.take().expect("we just checked it");
 			task.worktable.set_unpause();
-			return Some((PendingTaskKind::Suspended, task));
+			return Some((PendingTaskKind::Suspended, task, selected));
 		}
 
-		self.tasks
-			.pop_front()
-			.map(|task| (PendingTaskKind::Normal, task))
+		take_from_queue(&mut self.tasks, &select)
+			.map(|(task, selected)| (PendingTaskKind::Normal, task, selected))
+	}
+
+	/// Tasks blocked by their concurrency limit aren't handed to stealers, as they wouldn't be able
+	/// to run them either
+	pub(super) fn get_next_task(&mut self) -> Option<(PendingTaskKind, TaskWorkState<E>)> {
+		self.take_next_task(|task_work_state| (!task_work_state.is_blocked()).then_some(()))
+			.map(|(kind, task_work_state, ())| (kind, task_work_state))
+	}
+
+	fn get_next_runnable_task(
+		&mut self,
+	) -> Option<(PendingTaskKind, TaskWorkState<E>, ConcurrencyPermit)> {
+		if self.work_stealer.is_retired(self.worker_id) {
+			return None;
+		}
+
+		self.take_next_task(TaskWorkState::try_acquire_permit)
 	}
 
 	#[instrument(skip_all)]
@@ -827,7 +875,7 @@ impl<E: RunError> Runner<E> {
 			}
 		}
 
-		if let Some((next_task_kind, task_work_state)) = self.get_next_task() {
+		if let Some((next_task_kind, task_work_state, permit)) = self.get_next_runnable_task() {
 			let next_task_id = task_work_state.id();
 
 			trace!(%next_task_id, ?next_task_kind, "Dispatching next task");
@@ -838,6 +886,7 @@ impl<E: RunError> Runner<E> {
 				id: next_task_id,
 				kind: next_task_kind,
 				handle,
+				_permit: permit,
 			});
 		} else {
 			self.is_idle = true;
@@ -901,11 +950,37 @@ impl<E: RunError> Runner<E> {
 	#[instrument(skip(self))]
 	pub(super) fn idle_check(&mut self) {
 		if self.is_idle {
+			if self.run_pending_task_when_idle() {
+				return;
+			}
+
 			if self.current_steal_task_handle.is_none() {
 				self.steal_attempt();
 			}
 
-			self.idle_memory_cleanup();
+			if self.tasks.is_empty()
+				&& self.priority_tasks.is_empty()
+				&& self.suspended_task.is_none()
+			{
+				self.idle_memory_cleanup();
+			}
+		}
+	}
+
+	/// Idle workers can still have pending tasks, enqueued while the worker was retired or while
+	/// they were blocked by their concurrency limit
+	fn run_pending_task_when_idle(&mut self) -> bool {
+		if let Some((task_kind, task_work_state, permit)) = self.get_next_runnable_task() {
+			let task_id = task_work_state.id();
+
+			trace!(%task_id, ?task_kind, "Idle worker will run a pending task");
+
+			self.system_comm.working_report(self.worker_id);
+			self.add_task_when_idle(task_id, task_kind, task_work_state, permit);
+
+			true
+		} else {
+			false
 		}
 	}
 
@@ -1119,12 +1194,13 @@ type PartialTaskWorkState<E> = (
 	Arc<TaskWorktable>,
 	PanicOnSenderDrop<E>,
 	Arc<Interrupter>,
+	Option<Arc<ConcurrencyLimit>>,
 );
 
 async fn emit_task_completed_message<E: RunError>(
 	run_task_output: RunTaskOutput<E>,
 	has_suspended: Arc<AtomicBool>,
-	(task_id, worktable, done_tx, interrupter): PartialTaskWorkState<E>,
+	(task_id, worktable, done_tx, interrupter, concurrency_limit): PartialTaskWorkState<E>,
 	task_output_tx: chan::Sender<TaskOutputMessage<E>>,
 ) {
 	match run_task_output {
@@ -1159,6 +1235,7 @@ async fn emit_task_completed_message<E: RunError>(
 							worktable,
 							done_tx,
 							interrupter,
+							concurrency_limit,
 						},
 						status: internal_status,
 					})
@@ -1198,6 +1275,7 @@ async fn run_single_task<E: RunError>(
 		worktable,
 		interrupter,
 		done_tx,
+		concurrency_limit,
 	}: TaskWorkState<E>,
 	task_output_tx: chan::Sender<TaskOutputMessage<E>>,
 	suspend_rx: oneshot::Receiver<()>,
@@ -1242,7 +1320,7 @@ async fn run_single_task<E: RunError>(
 			emit_task_completed_message(
 				run_task_output,
 				has_suspended,
-				(task_id, worktable, done_tx, interrupter),
+				(task_id, worktable, done_tx, interrupter, concurrency_limit),
 				task_output_tx,
 			)
 			.await;
@@ -1392,6 +1470,19 @@ fn send_forced_abortion_task_response<E: RunError>(
 	}
 }
 
+fn take_from_queue<E: RunError, T>(
+	queue: &mut VecDeque<TaskWorkState<E>>,
+	select: impl Fn(&TaskWorkState<E>) -> Option<T>,
+) -> Option<(TaskWorkState<E>, T)> {
+	queue
+		.iter()
+		.enumerate()
+		.find_map(|(index, task_work_state)| {
+			select(task_work_state).map(|selected| (index, selected))
+		})
+		.map(|(index, selected)| (queue.remove(index).expect("we just found it"), selected))
+}
+
 fn dispatch_steal_request<E: RunError>(
 	worker_id: WorkerId,
 	work_stealer: WorkStealer<E>,
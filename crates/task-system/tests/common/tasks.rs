@@ -1,5 +1,9 @@
 use std::{
 	future::{pending, IntoFuture},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
 	time::Duration,
 };
 
@@ -339,3 +343,39 @@ impl Task<SampleError> for WaitSignalTask {
 		}
 	}
 }
+
+/// Keeps track of the maximum number of probe tasks that were running at the same time
+#[derive(Debug)]
+pub struct ConcurrencyProbeTask {
+	id: TaskId,
+	running: Arc<AtomicUsize>,
+	max_running: Arc<AtomicUsize>,
+}
+
+impl ConcurrencyProbeTask {
+	pub fn new(running: Arc<AtomicUsize>, max_running: Arc<AtomicUsize>) -> Self {
+		Self {
+			id: TaskId::new_v4(),
+			running,
+			max_running,
+		}
+	}
+}
+
+#[async_trait]
+impl Task<SampleError> for ConcurrencyProbeTask {
+	fn id(&self) -> TaskId {
+		self.id
+	}
+
+	async fn run(&mut self, _interrupter: &Interrupter) -> Result<ExecStatus, SampleError> {
+		let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+		self.max_running.fetch_max(running, Ordering::SeqCst);
+
+		sleep(Duration::from_millis(10)).await;
+
+		self.running.fetch_sub(1, Ordering::SeqCst);
+
+		Ok(ExecStatus::Done(TaskOutput::Empty))
+	}
+}
@@ -1,6 +1,17 @@
-use sd_task_system::{TaskHandle, TaskOutput, TaskStatus, TaskSystem};
+use sd_task_system::{
+	ConcurrencyLimit, TaskDispatcher, TaskHandle, TaskOutput, TaskStatus, TaskSystem,
+	TaskSystemConfig,
+};
 
-use std::{collections::VecDeque, time::Duration};
+use std::{
+	collections::VecDeque,
+	num::NonZeroUsize,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
 
 use futures_concurrency::future::Join;
 use rand::Rng;
@@ -14,7 +25,8 @@ mod common;
 use common::{
 	actors::SampleActor,
 	tasks::{
-		BogusTask, BrokenTask, NeverTask, PauseOnceTask, ReadyTask, SampleError, WaitSignalTask,
+		BogusTask, BrokenTask, ConcurrencyProbeTask, NeverTask, PauseOnceTask, ReadyTask,
+		SampleError, TimeTask, WaitSignalTask,
 	},
 };
 
@@ -303,3 +315,124 @@ async fn steal_test() {
 
 	system.shutdown().await;
 }
+
+#[tokio::test]
+#[traced_test]
+async fn resize_test() {
+	let system = TaskSystem::with_config(TaskSystemConfig {
+		workers_count: NonZeroUsize::new(4).unwrap(),
+	});
+
+	let handles = system
+		.dispatch_many((0..100).map(|_| TimeTask::new(Duration::from_millis(5), false)))
+		.await
+		.unwrap();
+
+	info!("Retiring half of the workers with pending tasks, which must be stolen by the others");
+
+	system.resize_workers(NonZeroUsize::new(2).unwrap());
+
+	assert_eq!(system.workers_count(), 2);
+
+	handles.join().await.into_iter().for_each(|res| {
+		assert!(matches!(res, Ok(TaskStatus::Done(_))));
+	});
+
+	info!("Reactivating the retired workers and spawning new ones");
+
+	system.resize_workers(NonZeroUsize::new(8).unwrap());
+
+	assert_eq!(system.workers_count(), 8);
+
+	let handles = system
+		.dispatch_many((0..100).map(|_| ReadyTask::default()))
+		.await
+		.unwrap();
+
+	handles.join().await.into_iter().for_each(|res| {
+		assert!(matches!(
+			res,
+			Ok(TaskStatus::Done((_task_id, TaskOutput::Empty)))
+		));
+	});
+
+	system.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[traced_test]
+async fn resize_while_dispatching_test() {
+	let system = TaskSystem::with_config(TaskSystemConfig {
+		workers_count: NonZeroUsize::new(1).unwrap(),
+	});
+
+	let dispatchers = (0..4)
+		.map(|_| {
+			let dispatcher = system.get_dispatcher();
+			tokio::spawn(async move {
+				let mut handles = vec![];
+				for _ in 0..50 {
+					handles.extend(
+						dispatcher
+							.dispatch_many((0..10).map(|_| ReadyTask::default()))
+							.await
+							.unwrap(),
+					);
+				}
+				handles
+			})
+		})
+		.collect::<Vec<_>>();
+
+	// Growing and shrinking the pool while the dispatchers pick workers for their tasks
+	for workers_count in (1..=8).chain((1..8).rev()).cycle().take(200) {
+		system.resize_workers(NonZeroUsize::new(workers_count).unwrap());
+		tokio::task::yield_now().await;
+	}
+
+	for handles in dispatchers.join().await {
+		handles.unwrap().join().await.into_iter().for_each(|res| {
+			assert!(matches!(
+				res,
+				Ok(TaskStatus::Done((_task_id, TaskOutput::Empty)))
+			));
+		});
+	}
+
+	system.shutdown().await;
+}
+
+#[tokio::test]
+#[traced_test]
+async fn concurrency_limit_test() {
+	let system = TaskSystem::with_config(TaskSystemConfig {
+		workers_count: NonZeroUsize::new(4).unwrap(),
+	});
+
+	let limit = ConcurrencyLimit::new(NonZeroUsize::new(2).unwrap());
+
+	let running = Arc::new(AtomicUsize::new(0));
+	let max_running = Arc::new(AtomicUsize::new(0));
+
+	let handles = system
+		.get_dispatcher()
+		.with_concurrency_limit(Arc::clone(&limit))
+		.dispatch_many(
+			(0..40)
+				.map(|_| ConcurrencyProbeTask::new(Arc::clone(&running), Arc::clone(&max_running))),
+		)
+		.await
+		.unwrap();
+
+	handles.join().await.into_iter().for_each(|res| {
+		assert!(matches!(
+			res,
+			Ok(TaskStatus::Done((_task_id, TaskOutput::Empty)))
+		));
+	});
+
+	assert!(max_running.load(Ordering::SeqCst) <= 2);
+	assert_eq!(limit.running(), 0);
+
+	system.shutdown().await;
+}
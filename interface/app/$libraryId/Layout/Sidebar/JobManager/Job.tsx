@@ -1,4 +1,6 @@
 import {
+	ArchiveBox,
+	ArrowsClockwise,
 	Copy,
 	Fingerprint,
 	Folder,
@@ -6,6 +8,8 @@ import {
 	Image,
 	Info,
 	Lightning,
+	Lock,
+	LockOpen,
 	Scissors,
 	Trash
 } from '@phosphor-icons/react';
@@ -33,7 +37,13 @@ export const JobIcon: Record<JobName, Icon> = {
 	Delete: Trash,
 	Erase: Trash,
 	Move: Scissors,
-	FileValidator: Fingerprint
+	FileValidator: Fingerprint,
+	DuplicateFinder: Fingerprint,
+	Encrypt: Lock,
+	Decrypt: LockOpen,
+	Compress: ArchiveBox,
+	Extract: ArchiveBox,
+	Convert: ArrowsClockwise
 };
 
 // Jobs like deleting and copying files do not have simplied job names
@@ -454,9 +454,12 @@ export const filterRegistry = [
 		translationKey: 'tag',
 		icon: CircleDashed,
 		extract: (arg) => {
-			if ('object' in arg && 'tags' in arg.object) return arg.object.tags;
+			if ('object' in arg && 'tags' in arg.object) {
+				const { tags } = arg.object;
+				return 'tags' in tags ? tags.tags : tags;
+			}
 		},
-		create: (tags) => ({ object: { tags } }),
+		create: (tags) => ({ object: { tags: { tags, includeDescendants: true } } }),
 		argsToOptions(values, options) {
 			return values
 				.map((value) => {
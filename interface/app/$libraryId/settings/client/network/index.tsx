@@ -95,6 +95,7 @@ export const Component = () => {
 				p2p_relay_disabled: value.relay_disabled ?? null,
 				p2p_discovery: value.discovery ?? null,
 				p2p_remote_access: value.enable_remote_access ?? null,
+				p2p_remote_access_allowlist: null,
 				p2p_manual_peers: value.p2p_manual_peers?.flatMap((v) => (v ? [v] : [])) ?? null
 				// image_labeler_version: null
 			});
@@ -22,7 +22,11 @@ const ruleKinds: UnionToTuple<RuleKind> = [
 	'RejectFilesByGlob',
 	'AcceptIfChildrenDirectoriesArePresent',
 	'RejectIfChildrenDirectoriesArePresent',
-	'IgnoredByGit'
+	'IgnoredByGit',
+	'RejectFilesLargerThan',
+	'AcceptFilesModifiedWithin',
+	'RejectFilesWithExtendedAttributes',
+	'RejectFilesByKind'
 ];
 const ruleKindEnum = z.enum(ruleKinds);
 
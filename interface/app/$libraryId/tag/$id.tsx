@@ -30,7 +30,10 @@ export function Component() {
 
 	const search = useSearchFromSearchParams({ defaultTarget: 'objects' });
 
-	const defaultFilters = useMemo(() => [{ object: { tags: { in: [tag.id] } } }], [tag.id]);
+	const defaultFilters = useMemo(
+		() => [{ object: { tags: { tags: { in: [tag.id] }, includeDescendants: true } } }],
+		[tag.id]
+	);
 
 	const items = useSearchExplorerQuery({
 		search,
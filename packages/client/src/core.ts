@@ -3,21 +3,30 @@
 
 export type Procedures = {
     queries: 
+        { key: "albums.get", input: LibraryArgs<number>, result: Album | null } | 
+        { key: "albums.getForObject", input: LibraryArgs<number>, result: Album[] } | 
+        { key: "albums.getObjects", input: LibraryArgs<number>, result: Object[] } | 
+        { key: "albums.list", input: LibraryArgs<null>, result: Album[] } | 
         { key: "auth.me", input: never, result: { id: string; email: string } } | 
         { key: "backups.getAll", input: never, result: GetAll } | 
+        { key: "backups.verify", input: string, result: Verification } | 
         { key: "buildInfo", input: never, result: BuildInfo } | 
         { key: "cloud.getApiOrigin", input: never, result: string } | 
         { key: "cloud.library.get", input: LibraryArgs<null>, result: CloudLibrary | null } | 
         { key: "cloud.library.list", input: never, result: CloudLibrary[] } | 
         { key: "cloud.locations.list", input: never, result: CloudLocation[] } | 
         { key: "ephemeralFiles.getMediaData", input: string, result: MediaData | null } | 
+        { key: "files.archiveEntries", input: LibraryArgs<number>, result: ArchiveEntry[] } | 
+        { key: "files.duplicates", input: LibraryArgs<DuplicatesArgs>, result: DuplicateSet[] } | 
         { key: "files.get", input: LibraryArgs<number>, result: ObjectWithFilePaths2 | null } | 
         { key: "files.getConvertibleImageExtensions", input: never, result: string[] } | 
         { key: "files.getMediaData", input: LibraryArgs<number>, result: MediaData } | 
         { key: "files.getPath", input: LibraryArgs<number>, result: string | null } | 
+        { key: "files.getTextSnippet", input: LibraryArgs<number>, result: TextSnippet | null } | 
         { key: "invalidation.test-invalidate", input: never, result: number } | 
         { key: "jobs.isActive", input: LibraryArgs<null>, result: boolean } | 
         { key: "jobs.reports", input: LibraryArgs<null>, result: JobGroup[] } | 
+        { key: "keys.list", input: LibraryArgs<null>, result: KeyInfo[] } | 
         { key: "labels.count", input: LibraryArgs<null>, result: number } | 
         { key: "labels.get", input: LibraryArgs<number>, result: Label | null } | 
         { key: "labels.getForObject", input: LibraryArgs<number>, result: Label[] } | 
@@ -34,6 +43,8 @@ export type Procedures = {
         { key: "locations.indexer_rules.listForLocation", input: LibraryArgs<number>, result: IndexerRule[] } | 
         { key: "locations.list", input: LibraryArgs<null>, result: Location[] } | 
         { key: "locations.systemLocations", input: never, result: SystemLocations } | 
+        { key: "maintenance.isRunning", input: never, result: boolean } | 
+        { key: "maintenance.reports", input: never, result: MaintenanceReport[] } | 
         { key: "models.image_detection.list", input: never, result: string[] } | 
         { key: "nodeState", input: never, result: NodeState } | 
         { key: "nodes.listLocations", input: LibraryArgs<string | null>, result: ExplorerItem[] } | 
@@ -43,12 +54,17 @@ export type Procedures = {
         { key: "p2p.listeners", input: never, result: Listeners } | 
         { key: "p2p.state", input: never, result: JsonValue } | 
         { key: "preferences.get", input: LibraryArgs<null>, result: LibraryPreferences } | 
+        { key: "search.geoClusters", input: LibraryArgs<{ zoom: number; bounds?: GeoBoundingBox | null; filters?: SearchFilterArgs[] }>, result: GeoCluster[] } | 
         { key: "search.objects", input: LibraryArgs<ObjectSearchArgs>, result: SearchData<ExplorerItem> } | 
         { key: "search.objectsCount", input: LibraryArgs<{ filters?: SearchFilterArgs[] }>, result: number } | 
         { key: "search.paths", input: LibraryArgs<FilePathSearchArgs>, result: SearchData<ExplorerItem> } | 
         { key: "search.pathsCount", input: LibraryArgs<{ filters?: SearchFilterArgs[] }>, result: number } | 
         { key: "search.saved.get", input: LibraryArgs<number>, result: SavedSearch | null } | 
         { key: "search.saved.list", input: LibraryArgs<null>, result: SavedSearch[] } | 
+        { key: "spaces.get", input: LibraryArgs<number>, result: Space | null } | 
+        { key: "spaces.getForObject", input: LibraryArgs<number>, result: Space[] } | 
+        { key: "spaces.getObjects", input: LibraryArgs<number>, result: Object[] } | 
+        { key: "spaces.list", input: LibraryArgs<null>, result: Space[] } | 
         { key: "sync.enabled", input: LibraryArgs<null>, result: boolean } | 
         { key: "sync.messages", input: LibraryArgs<null>, result: CRDTOperation[] } | 
         { key: "tags.get", input: LibraryArgs<number>, result: Tag | null } | 
@@ -57,10 +73,18 @@ export type Procedures = {
         { key: "tags.list", input: LibraryArgs<null>, result: Tag[] } | 
         { key: "volumes.list", input: never, result: Volume[] },
     mutations: 
+        { key: "albums.addObjects", input: LibraryArgs<AlbumObjectsArgs>, result: null } | 
+        { key: "albums.create", input: LibraryArgs<AlbumCreateArgs>, result: Album } | 
+        { key: "albums.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "albums.removeObjects", input: LibraryArgs<AlbumObjectsArgs>, result: null } | 
+        { key: "albums.reorder", input: LibraryArgs<AlbumObjectsArgs>, result: null } | 
+        { key: "albums.setCover", input: LibraryArgs<AlbumSetCoverArgs>, result: null } | 
+        { key: "albums.update", input: LibraryArgs<AlbumUpdateArgs>, result: null } | 
         { key: "api.sendFeedback", input: Feedback, result: null } | 
         { key: "auth.logout", input: never, result: null } | 
         { key: "backups.backup", input: LibraryArgs<null>, result: string } | 
         { key: "backups.delete", input: string, result: null } | 
+        { key: "backups.incrementalBackup", input: LibraryArgs<null>, result: string } | 
         { key: "backups.restore", input: string, result: null } | 
         { key: "cloud.library.create", input: LibraryArgs<null>, result: null } | 
         { key: "cloud.library.join", input: string, result: LibraryConfigWrapped } | 
@@ -76,13 +100,18 @@ export type Procedures = {
         { key: "ephemeralFiles.moveToTrash", input: LibraryArgs<string[]>, result: null } | 
         { key: "ephemeralFiles.renameFile", input: LibraryArgs<EphemeralRenameFileArgs>, result: null } | 
         { key: "files.convertImage", input: LibraryArgs<ConvertImageArgs>, result: null } | 
-        { key: "files.copyFiles", input: LibraryArgs<OldFileCopierJobInit>, result: null } | 
+        { key: "files.compressFiles", input: LibraryArgs<CompressFilesArgs>, result: null } | 
+        { key: "files.convertFiles", input: LibraryArgs<ConvertFilesArgs>, result: null } | 
+        { key: "files.copyFiles", input: LibraryArgs<CopyOrMoveFilesArgs>, result: null } | 
         { key: "files.createFile", input: LibraryArgs<CreateFileArgs>, result: string } | 
         { key: "files.createFolder", input: LibraryArgs<CreateFolderArgs>, result: string } | 
-        { key: "files.cutFiles", input: LibraryArgs<OldFileCutterJobInit>, result: null } | 
-        { key: "files.deleteFiles", input: LibraryArgs<OldFileDeleterJobInit>, result: null } | 
-        { key: "files.eraseFiles", input: LibraryArgs<OldFileEraserJobInit>, result: null } | 
-        { key: "files.moveToTrash", input: LibraryArgs<OldFileDeleterJobInit>, result: null } | 
+        { key: "files.cutFiles", input: LibraryArgs<CopyOrMoveFilesArgs>, result: null } | 
+        { key: "files.decryptFiles", input: LibraryArgs<CryptoFilesArgs>, result: null } | 
+        { key: "files.deleteFiles", input: LibraryArgs<DeleteFilesArgs>, result: null } | 
+        { key: "files.encryptFiles", input: LibraryArgs<CryptoFilesArgs>, result: null } | 
+        { key: "files.eraseFiles", input: LibraryArgs<EraseFilesArgs>, result: null } | 
+        { key: "files.extractArchive", input: LibraryArgs<ExtractArchiveArgs>, result: null } | 
+        { key: "files.moveToTrash", input: LibraryArgs<DeleteFilesArgs>, result: null } | 
         { key: "files.removeAccessTime", input: LibraryArgs<number[]>, result: null } | 
         { key: "files.renameFile", input: LibraryArgs<RenameFileArgs>, result: null } | 
         { key: "files.setFavorite", input: LibraryArgs<SetFavoriteArgs>, result: null } | 
@@ -92,11 +121,16 @@ export type Procedures = {
         { key: "jobs.cancel", input: LibraryArgs<string>, result: null } | 
         { key: "jobs.clear", input: LibraryArgs<string>, result: null } | 
         { key: "jobs.clearAll", input: LibraryArgs<null>, result: null } | 
+        { key: "jobs.findDuplicates", input: LibraryArgs<FindDuplicatesArgs>, result: string } | 
         { key: "jobs.generateThumbsForLocation", input: LibraryArgs<GenerateThumbsForLocationArgs>, result: string } | 
         { key: "jobs.identifyUniqueFiles", input: LibraryArgs<IdentifyUniqueFilesArgs>, result: string } | 
-        { key: "jobs.objectValidator", input: LibraryArgs<ObjectValidatorArgs>, result: null } | 
+        { key: "jobs.objectValidator", input: LibraryArgs<ObjectValidatorArgs>, result: string } | 
         { key: "jobs.pause", input: LibraryArgs<string>, result: null } | 
         { key: "jobs.resume", input: LibraryArgs<string>, result: null } | 
+        { key: "keys.add", input: LibraryArgs<AddKeyArgs>, result: string } | 
+        { key: "keys.delete", input: LibraryArgs<string>, result: null } | 
+        { key: "keys.lock", input: LibraryArgs<string>, result: null } | 
+        { key: "keys.unlock", input: LibraryArgs<UnlockKeyArgs>, result: null } | 
         { key: "labels.delete", input: LibraryArgs<number>, result: null } | 
         { key: "library.create", input: CreateLibraryArgs, result: LibraryConfigWrapped } | 
         { key: "library.delete", input: string, result: null } | 
@@ -113,25 +147,39 @@ export type Procedures = {
         { key: "locations.relink", input: LibraryArgs<string>, result: number } | 
         { key: "locations.subPathRescan", input: LibraryArgs<RescanArgs>, result: string | null } | 
         { key: "locations.update", input: LibraryArgs<LocationUpdateArgs>, result: null } | 
+        { key: "maintenance.run", input: never, result: null } | 
         { key: "nodes.edit", input: ChangeNodeNameArgs, result: null } | 
+        { key: "nodes.updateBackupPreferences", input: BackupPreferences, result: null } | 
+        { key: "nodes.updateMaintenancePreferences", input: MaintenancePreferences, result: null } | 
+        { key: "nodes.updateTaskSystemPreferences", input: TaskSystemPreferences, result: null } | 
         { key: "nodes.updateThumbnailerPreferences", input: UpdateThumbnailerPreferences, result: null } | 
+        { key: "p2p.acceptPairing", input: [string, boolean], result: null } | 
         { key: "p2p.acceptSpacedrop", input: [string, string | null], result: null } | 
         { key: "p2p.cancelSpacedrop", input: string, result: null } | 
         { key: "p2p.debugConnect", input: RemoteIdentity, result: string } | 
+        { key: "p2p.pair", input: LibraryArgs<RemoteIdentity>, result: string } | 
         { key: "p2p.spacedrop", input: SpacedropArgs, result: string } | 
         { key: "preferences.update", input: LibraryArgs<LibraryPreferences>, result: null } | 
         { key: "search.saved.create", input: LibraryArgs<{ name: string; target?: SearchTarget; search?: string | null; filters?: string | null; description?: string | null; icon?: string | null }>, result: null } | 
         { key: "search.saved.delete", input: LibraryArgs<number>, result: null } | 
         { key: "search.saved.update", input: LibraryArgs<[number, Args]>, result: null } | 
+        { key: "spaces.addObjects", input: LibraryArgs<SpaceObjectsArgs>, result: null } | 
+        { key: "spaces.create", input: LibraryArgs<SpaceCreateArgs>, result: Space } | 
+        { key: "spaces.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "spaces.removeObjects", input: LibraryArgs<SpaceObjectsArgs>, result: null } | 
+        { key: "spaces.reorder", input: LibraryArgs<SpaceObjectsArgs>, result: null } | 
+        { key: "spaces.setCover", input: LibraryArgs<SpaceSetCoverArgs>, result: null } | 
+        { key: "spaces.update", input: LibraryArgs<SpaceUpdateArgs>, result: null } | 
         { key: "sync.backfill", input: LibraryArgs<null>, result: null } | 
         { key: "tags.assign", input: LibraryArgs<{ targets: Target[]; tag_id: number; unassign: boolean }>, result: null } | 
         { key: "tags.create", input: LibraryArgs<TagCreateArgs>, result: Tag } | 
         { key: "tags.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "tags.setParent", input: LibraryArgs<TagSetParentArgs>, result: null } | 
         { key: "tags.update", input: LibraryArgs<TagUpdateArgs>, result: null } | 
         { key: "toggleFeatureFlag", input: BackendFeature, result: null },
     subscriptions: 
         { key: "auth.loginSession", input: never, result: Response } | 
-        { key: "invalidation.listen", input: never, result: InvalidateOperationEvent[] } | 
+        { key: "invalidation.listen", input: string | null, result: InvalidateOperationEvent[] } | 
         { key: "jobs.newFilePathIdentified", input: LibraryArgs<null>, result: number[] } | 
         { key: "jobs.newThumbnail", input: LibraryArgs<null>, result: ThumbKey } | 
         { key: "jobs.progress", input: LibraryArgs<null>, result: JobProgressEvent } | 
@@ -146,8 +194,37 @@ export type Procedures = {
         { key: "sync.newMessage", input: LibraryArgs<null>, result: null }
 };
 
+export type AddKeyArgs = { name: string; passphrase: string }
+
+export type Album = { id: number; pub_id: number[]; name: string | null; is_hidden: boolean | null; date_created: string | null; date_modified: string | null; cover_object_id: number | null }
+
+export type AlbumCreateArgs = { name: string }
+
+export type AlbumObjectsArgs = { id: number; object_ids: number[] }
+
+export type AlbumSetCoverArgs = { id: number; 
+/**
+ * Removes the cover when missing
+ */
+object_id: number | null }
+
+export type AlbumUpdateArgs = { id: number; name: string | null; is_hidden: boolean | null }
+
+/**
+ * An entry inside an archive, as listed without extracting it
+ */
+export type ArchiveEntry = { path: string; is_dir: boolean; 
+/**
+ * Uncompressed size of the entry
+ */
+size: [number, number]; date_modified: string | null }
+
+export type ArchiveFormat = "zip" | "tar" | "tar_zst"
+
 export type Args = { search?: string | null; filters?: string | null; name?: string | null; icon?: string | null; description?: string | null }
 
+export type AudioFormat = "mp3" | "m4a" | "ogg" | "opus" | "flac" | "wav"
+
 export type AudioProps = { delay: number; padding: number; sample_rate: number | null; sample_format: string | null; bit_per_sample: number | null; channel_layout: string | null }
 
 /**
@@ -157,7 +234,19 @@ export type AudioProps = { delay: number; padding: number; sample_rate: number |
  */
 export type BackendFeature = "cloudSync"
 
-export type Backup = ({ id: string; timestamp: string; library_id: string; library_name: string }) & { path: string }
+export type Backup = ({ id: string; timestamp: string; library_id: string; library_name: string; kind: BackupKind; parent_id: string | null }) & { path: string }
+
+export type BackupKind = "full" | "incremental"
+
+export type BackupPreferences = { 
+/**
+ * Hours between scheduled incremental backups of every library, none disables them
+ */
+interval_hours?: number | null; 
+/**
+ * Which backups are kept, applied after each backup
+ */
+retention?: RetentionPolicy }
 
 export type BuildInfo = { version: string; commit: string }
 
@@ -169,7 +258,7 @@ export type CameraData = { device_make: string | null; device_model: string | nu
 
 export type CasId = string
 
-export type ChangeNodeNameArgs = { name: string | null; p2p_port: Port | null; p2p_disabled: boolean | null; p2p_ipv6_disabled: boolean | null; p2p_relay_disabled: boolean | null; p2p_discovery: P2PDiscoveryState | null; p2p_remote_access: boolean | null; p2p_manual_peers: string[] | null }
+export type ChangeNodeNameArgs = { name: string | null; p2p_port: Port | null; p2p_disabled: boolean | null; p2p_ipv6_disabled: boolean | null; p2p_relay_disabled: boolean | null; p2p_discovery: P2PDiscoveryState | null; p2p_remote_access: boolean | null; p2p_remote_access_allowlist: string[] | null; p2p_manual_peers: string[] | null }
 
 export type Chapter = { id: number; start: [number, number]; end: [number, number]; time_base_den: number; time_base_num: number; metadata: Metadata }
 
@@ -201,16 +290,29 @@ export type Composite =
  */
 "Live"
 
+export type CompressFilesArgs = { location_id: number; file_path_ids: number[]; format: ArchiveFormat; name: string | null }
+
 /**
  * The method used for the connection with this peer.
  * *Technically* you can have multiple under the hood but this simplifies things for the UX.
  */
 export type ConnectionMethod = "Relay" | "Local" | "Disconnected"
 
+export type ConvertFilesArgs = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string; options: ConvertOptions }
+
 export type ConvertImageArgs = { location_id: number; file_path_id: number; delete_src: boolean; desired_extension: ConvertibleExtension; quality_percentage: number | null }
 
+/**
+ * Everything that defines how the selected files will be converted
+ */
+export type ConvertOptions = { target: ConvertTarget; size: SizePreset; quality: QualityPreset }
+
+export type ConvertTarget = { image: ImageFormat } | { video: VideoFormat } | { audio: AudioFormat }
+
 export type ConvertibleExtension = "bmp" | "dib" | "ff" | "gif" | "ico" | "jpg" | "jpeg" | "png" | "pnm" | "qoi" | "tga" | "icb" | "vda" | "vst" | "tiff" | "tif" | "hif" | "heif" | "heifs" | "heic" | "heics" | "avif" | "avci" | "avcs" | "svg" | "svgz" | "pdf" | "webp"
 
+export type CopyOrMoveFilesArgs = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string }
+
 export type CreateEphemeralFileArgs = { path: string; context: EphemeralFileCreateContextTypes; name: string | null }
 
 export type CreateEphemeralFolderArgs = { path: string; name: string | null }
@@ -221,10 +323,14 @@ export type CreateFolderArgs = { location_id: number; sub_path: string | null; n
 
 export type CreateLibraryArgs = { name: LibraryName; default_locations: DefaultLocations | null }
 
+export type CryptoFilesArgs = { location_id: number; file_path_ids: number[]; key_id: string }
+
 export type CursorOrderItem<T> = { order: SortOrder; data: T }
 
 export type DefaultLocations = { desktop: boolean; documents: boolean; downloads: boolean; pictures: boolean; music: boolean; videos: boolean }
 
+export type DeleteFilesArgs = { location_id: number; file_path_ids: number[] }
+
 /**
  * The method used for the discovery of this peer.
  * *Technically* you can have multiple under the hood but this simplifies things for the UX.
@@ -233,6 +339,18 @@ export type DiscoveryMethod = "Relay" | "Local" | "Manual"
 
 export type DiskType = "SSD" | "HDD" | "Removable"
 
+export type DuplicateSet = { integrity_checksum: string; file_path_ids: number[]; 
+/**
+ * Size of each one of the files in the set
+ */
+size_in_bytes: [number, number]; 
+/**
+ * Bytes that would be freed by keeping only one of the files in the set
+ */
+wasted_bytes: [number, number] }
+
+export type DuplicatesArgs = { cursor: string | null; take: number }
+
 export type DoubleClickAction = "openFile" | "quickPreview"
 
 export type EditLibraryArgs = { id: string; name: LibraryName | null; description: MaybeUndefined<string> }
@@ -253,6 +371,12 @@ export type EphemeralRenameMany = { from_pattern: FromPattern; to_pattern: strin
 
 export type EphemeralRenameOne = { from_path: string; to: string }
 
+export type EraseFilesArgs = { location_id: number; file_path_ids: number[]; passes: number; 
+/**
+ * Rename files to random names before unlinking them, so their names don't linger on disk
+ */
+rename_before_unlink?: boolean }
+
 export type Error = { code: ErrorCode; message: string }
 
 /**
@@ -270,6 +394,8 @@ export type ExplorerLayout = "grid" | "list" | "media"
 
 export type ExplorerSettings<TOrder> = { layoutMode: ExplorerLayout | null; gridItemSize: number | null; gridGap: number | null; mediaColumns: number | null; mediaAspectSquare: boolean | null; mediaViewWithDescendants: boolean | null; openOnDoubleClick: DoubleClickAction | null; showBytesInGridView: boolean | null; colVisibility: { [key in string]: boolean } | null; colSizes: { [key in string]: number } | null; listViewIconSize: string | null; listViewTextSize: string | null; order?: TOrder | null; showHiddenFiles?: boolean }
 
+export type ExtractArchiveArgs = { location_id: number; file_path_id: number }
+
 export type FFmpegMetadata = { formats: string[]; duration: [number, number] | null; start_time: [number, number] | null; bit_rate: [number, number]; chapters: Chapter[]; programs: Program[]; metadata: Metadata }
 
 export type Feedback = { message: string; emoji: number }
@@ -280,6 +406,8 @@ export type FfmpegMediaChapter = { chapter_id: number; start: number[]; end: num
 
 export type FfmpegMediaVideoProps = { id: number; pixel_format: string | null; color_range: string | null; bits_per_channel: number | null; color_space: string | null; color_primaries: string | null; color_transfer: string | null; field_order: string | null; chroma_location: string | null; width: number; height: number; aspect_ratio_num: number | null; aspect_ratio_Den: number | null; properties: string | null; codec_id: number }
 
+export type FindDuplicatesArgs = { id: number; path: string }
+
 export type FileCreateContextTypes = "empty" | "text"
 
 export type FilePath = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null }
@@ -290,7 +418,7 @@ export type FilePathCursorVariant = "none" | { name: CursorOrderItem<string> } |
 
 export type FilePathFilterArgs = { locations: InOrNotIn<number> } | { path: { location_id: number; path: string; include_descendants: boolean } } | { name: TextMatch } | { extension: InOrNotIn<string> } | { createdAt: Range<string> } | { modifiedAt: Range<string> } | { indexedAt: Range<string> } | { hidden: boolean }
 
-export type FilePathForFrontend = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; tags: ({ object_id: number; tag_id: number; tag: Tag; date_created: string | null })[]; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null }
+export type FilePathForFrontend = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; has_thumbstrip: boolean | null; has_video_preview: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; tags: ({ object_id: number; tag_id: number; tag: Tag; date_created: string | null })[]; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null }
 
 export type FilePathObjectCursor = { dateAccessed: CursorOrderItem<string> } | { kind: CursorOrderItem<number> }
 
@@ -349,12 +477,46 @@ export type FullRescanArgs = { location_id: number; reidentify_objects: boolean
 
 export type GenerateThumbsForLocationArgs = { id: number; path: string; regenerate?: boolean }
 
+/**
+ * Area of the map, in degrees. `west` is greater than `east` when the area crosses the
+ * antimeridian.
+ */
+export type GeoBoundingBox = { north: number; south: number; east: number; west: number }
+
+/**
+ * Photos grouped in a cell of the clustering grid
+ */
+export type GeoCluster = { 
+/**
+ * Mean latitude of the photos in this cell
+ */
+latitude: number; 
+/**
+ * Mean longitude of the photos in this cell
+ */
+longitude: number; count: number; 
+/**
+ * Area of the grid cell, to search for the photos in it
+ */
+bounds: GeoBoundingBox }
+
+export type GeoFilter = { boundingBox: GeoBoundingBox } | { radius: GeoRadius }
+
+/**
+ * Circle around a point, with its radius in meters
+ */
+export type GeoRadius = { latitude: number; longitude: number; radiusMeters: number }
+
 export type GetAll = { backups: Backup[]; directory: string }
 
 export type HardwareModel = "Other" | "MacStudio" | "MacBookAir" | "MacBookPro" | "MacBook" | "MacMini" | "MacPro" | "IMac" | "IMacPro" | "IPad" | "IPhone" | "Simulator" | "Android"
 
+export type Header = { id: string; timestamp: string; library_id: string; library_name: string; kind: BackupKind; parent_id: string | null }
+
 export type IdentifyUniqueFilesArgs = { id: number; path: string }
 
+export type ImageFormat = "jpeg" | "png" | "webp" | "gif" | "bmp" | "tiff"
+
 export type InOrNotIn<T> = { in: T[] } | { notIn: T[] }
 
 export type IndexerRule = { id: number; pub_id: number[]; name: string | null; default: boolean | null; rules_per_kind: number[] | null; date_created: string | null; date_modified: string | null }
@@ -368,19 +530,28 @@ export type IndexerRule = { id: number; pub_id: number[]; name: string | null; d
  * 
  * In case of `RuleKind::AcceptIfChildrenDirectoriesArePresent` or `RuleKind::RejectIfChildrenDirectoriesArePresent` the
  * `parameters` field must be a vector of strings containing the names of the directories.
+ * 
+ * In case of `RuleKind::RejectFilesLargerThan` it must hold a single size in bytes, and in case of
+ * `RuleKind::AcceptFilesModifiedWithin` a single number of seconds.
+ * 
+ * In case of `RuleKind::RejectFilesWithExtendedAttributes` it must hold the attribute names, like
+ * `com.apple.quarantine` or `user.sd.ignore`, and in case of `RuleKind::RejectFilesByKind` the
+ * object kind names, like `Executable`.
  */
 export type IndexerRuleCreateArgs = { name: string; dry_run: boolean; rules: ([RuleKind, string[]])[] }
 
-export type InvalidateOperationEvent = { type: "single"; data: SingleInvalidateOperationEvent } | { type: "all" }
+export type InvalidateOperationEvent = { type: "single"; data: SingleInvalidateOperationEvent }
 
 export type JobGroup = { id: string; running_job_id: string | null; action: string | null; status: Status; created_at: string; jobs: Report[] }
 
-export type JobName = "Indexer" | "FileIdentifier" | "MediaProcessor" | "Copy" | "Move" | "Delete" | "Erase" | "FileValidator"
+export type JobName = "Indexer" | "FileIdentifier" | "MediaProcessor" | "Copy" | "Move" | "Delete" | "Erase" | "FileValidator" | "DuplicateFinder" | "Encrypt" | "Decrypt" | "Compress" | "Extract" | "Convert"
 
 export type JobProgressEvent = { id: string; library_id: string; task_count: number; completed_task_count: number; phase: string; message: string; info: string; estimated_completion: string }
 
 export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
 
+export type KeyInfo = { id: string; name: string; date_created: string; unlocked: boolean }
+
 export type KindStatistic = { kind: number; name: string; count: [number, number]; total_bytes: [number, number] }
 
 export type KindStatistics = { statistics: { [key in number]: KindStatistic }; total_identified_files: number; total_unidentified_files: number }
@@ -420,6 +591,12 @@ export type LibraryConfigVersion = "V0" | "V1" | "V2" | "V3" | "V4" | "V5" | "V6
 
 export type LibraryConfigWrapped = { uuid: string; instance_id: string; instance_public_key: RemoteIdentity; config: LibraryConfig }
 
+export type LibraryMaintenanceReport = { library_id: string; removed_orphan_objects: number; removed_thumbnails: number; freed_thumbnails_bytes: number; 
+/**
+ * Sync operations removed from the operations log by compaction
+ */
+compacted_sync_operations: number; reclaimed_sync_operations_bytes: number; database_size_before: number; database_size_after: number }
+
 export type LibraryName = string
 
 export type LibraryPreferences = { location?: { [key in string]: LocationSettings }; tag?: { [key in string]: TagSettings } }
@@ -430,7 +607,7 @@ export type ListenerState = { type: "Listening" } | { type: "Error"; error: stri
 
 export type Listeners = { ipv4: ListenerState; ipv6: ListenerState; relay: ListenerState }
 
-export type Location = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; scan_state: number; instance_id: number | null }
+export type Location = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; scan_state: number; volume_identifier: string | null; volume_path: string | null; rescan_schedule: string | null; rescan_last_attempt_at: string | null; rescan_failures: number; last_verified_at: string | null; instance_id: number | null }
 
 /**
  * `LocationCreateArgs` is the argument received from the client using `rspc` to create a new location.
@@ -449,9 +626,38 @@ export type LocationSettings = { explorer: ExplorerSettings<FilePathOrder> }
  * It is important to note that only the indexer rule ids in this vector will be used from now on.
  * Old rules that aren't in this vector will be purged.
  */
-export type LocationUpdateArgs = { id: number; name: string | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; indexer_rules_ids: number[]; path: string | null }
+export type LocationUpdateArgs = { id: number; name: string | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; indexer_rules_ids: number[]; path: string | null; 
+/**
+ * Cron expression for periodic rescans, `null` disables them
+ */
+rescan_schedule?: MaybeUndefined<string> }
 
-export type LocationWithIndexerRule = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; instance_id: number | null; indexer_rules: IndexerRule[] }
+export type LocationWithIndexerRule = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; rescan_schedule: string | null; last_verified_at: string | null; instance_id: number | null; indexer_rules: IndexerRule[] }
+
+export type MaintenancePreferences = { 
+/**
+ * Hours between scheduled maintenance runs, none disables them
+ */
+interval_hours?: number | null; 
+/**
+ * Postpones scheduled runs until no jobs are running and the CPU is mostly idle
+ */
+only_when_idle?: boolean }
+
+/**
+ * What a single maintenance run did
+ */
+export type MaintenanceReport = { id: string; trigger: MaintenanceTrigger; started_at: string; finished_at: string; 
+/**
+ * Stale ephemeral thumbnails removed, they are shared by every library
+ */
+removed_ephemeral_thumbnails: number; freed_ephemeral_thumbnails_bytes: number; libraries: LibraryMaintenanceReport[]; 
+/**
+ * Errors that didn't stop the run
+ */
+errors: string[] }
+
+export type MaintenanceTrigger = "scheduled" | "manual"
 
 export type MaybeUndefined<T> = null | T
 
@@ -468,6 +674,12 @@ export type MediaLocation = { latitude: number; longitude: number; pluscode: Plu
 export type Metadata = { album: string | null; album_artist: string | null; artist: string | null; comment: string | null; composer: string | null; copyright: string | null; creation_time: string | null; date: string | null; disc: number | null; encoder: string | null; encoded_by: string | null; filename: string | null; genre: string | null; language: string | null; performer: string | null; publisher: string | null; service_name: string | null; service_provider: string | null; title: string | null; track: number | null; variant_bit_rate: number | null; custom: { [key in string]: string } }
 
 export type NodeConfigP2P = { discovery?: P2PDiscoveryState; port: Port; disabled: boolean; disable_ipv6: boolean; disable_relay: boolean; enable_remote_access: boolean; 
+/**
+ * Extra rspc procedures remote peers are allowed to call, on top of the read-only ones they always have access to.
+ * 
+ * Eg. `files.copyFiles` or `locations.delete`
+ */
+remote_access_allowlist?: string[]; 
 /**
  * A list of peer addresses to try and manually connect to, instead of relying on discovery.
  * 
@@ -480,7 +692,7 @@ export type NodeConfigP2P = { discovery?: P2PDiscoveryState; port: Port; disable
  */
 manual_peers?: string[] }
 
-export type NodePreferences = Record<string, never>
+export type NodePreferences = { task_system?: TaskSystemPreferences; backups?: BackupPreferences; maintenance?: MaintenancePreferences }
 
 export type NodeState = ({ 
 /**
@@ -492,17 +704,29 @@ id: string;
  */
 name: string; identity: RemoteIdentity; p2p: NodeConfigP2P; features: BackendFeature[]; preferences: NodePreferences; image_labeler_version: string | null }) & { data_path: string; device_model: string | null; is_in_docker: boolean }
 
-export type NonCriticalError = { indexer: NonCriticalIndexerError } | { file_identifier: NonCriticalFileIdentifierError } | { media_processor: NonCriticalMediaProcessorError }
+export type NonCriticalArchiveError = { append: [string, string] } | { extract: [string, string] } | { unsafe_entry_path: string }
+
+export type NonCriticalConvertError = { unsupported_source: string } | { find_available_name: [string, string] } | { convert: [string, string, string] } | { link_to_source: [string, string] }
+
+export type NonCriticalError = { indexer: NonCriticalIndexerError } | { file_identifier: NonCriticalFileIdentifierError } | { media_processor: NonCriticalMediaProcessorError } | { file_system: NonCriticalFileSystemError } | { file_validator: NonCriticalFileValidatorError } | { file_crypto: NonCriticalFileCryptoError } | { archive: NonCriticalArchiveError } | { convert: NonCriticalConvertError } | { maintenance: NonCriticalMaintenanceError }
+
+export type NonCriticalFileCryptoError = { find_available_name: [string, string] } | { encrypt: [string, string, string] } | { decrypt: [string, string, string] } | { not_a_container: string } | { different_key: string }
 
 export type NonCriticalFileIdentifierError = { failed_to_extract_file_metadata: string } | { failed_to_extract_isolated_file_path_data: { file_path_pub_id: string; error: string } } | { file_path_without_is_dir_field: number }
 
+export type NonCriticalFileSystemError = { file_path_without_is_dir_field: number } | { read_directory: [string, string] } | { create_directory: [string, string] } | { find_available_name: [string, string] } | { would_overwrite: string } | { copy: [string, string, string] } | { move: [string, string, string] } | { delete: [string, string] } | { erase: [string, string] } | { unreliable_erasure: [string, string] } | { remove_from_database: [string, string] }
+
+export type NonCriticalFileValidatorError = { failed_to_extract_isolated_file_path_data: [number, string] } | { failed_to_generate_checksum: [string, string] }
+
 export type NonCriticalIndexerError = { failed_directory_entry: string } | { metadata: string } | { indexer_rule: string } | { file_path_metadata: string } | { fetch_already_existing_file_path_ids: string } | { fetch_file_paths_to_remove: string } | { iso_file_path: string } | { dispatch_keep_walking: string } | { missing_file_path_data: string }
 
+export type NonCriticalMaintenanceError = { read_thumbnails_directory: [string, string] } | { remove_thumbnail: [string, string] }
+
 export type NonCriticalMediaDataExtractorError = { FailedToExtractImageMediaData: [string, string] } | { FilePathMissingObjectId: number } | { FailedToConstructIsolatedFilePathData: [number, string] }
 
 export type NonCriticalMediaProcessorError = { media_data_extractor: NonCriticalMediaDataExtractorError } | { thumbnailer: NonCriticalThumbnailerError }
 
-export type NonCriticalThumbnailerError = { MissingCasId: number } | { FailedToExtractIsolatedFilePathData: [number, string] } | { VideoThumbnailGenerationFailed: [string, string] } | { FormatImage: [string, string] } | { WebPEncoding: [string, string] } | { PanicWhileGeneratingThumbnail: [string, string] } | { CreateShardDirectory: string } | { SaveThumbnail: [string, string] } | { TaskTimeout: string }
+export type NonCriticalThumbnailerError = { MissingCasId: number } | { FailedToExtractIsolatedFilePathData: [number, string] } | { VideoThumbnailGenerationFailed: [string, string] } | { VideoPreviewGenerationFailed: [string, string] } | { AudioThumbnailGenerationFailed: [string, string] } | { BookCoverExtraction: [string, string] } | { TextPreview: [string, string] } | { FormatImage: [string, string] } | { WebPEncoding: [string, string] } | { PanicWhileGeneratingThumbnail: [string, string] } | { CreateShardDirectory: string } | { SaveThumbnail: [string, string] } | { TaskTimeout: string } | { MarkVideoPreviews: string }
 
 export type NonIndexedPathItem = { path: string; name: string; extension: string; kind: number; is_dir: boolean; date_created: string; date_modified: string; size_in_bytes_bytes: number[]; hidden: boolean }
 
@@ -521,11 +745,11 @@ export type NotificationId = { type: "library"; id: [string, number] } | { type:
 
 export type NotificationKind = "info" | "success" | "error" | "warning"
 
-export type Object = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null }
+export type Object = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; has_thumbstrip: boolean | null; has_video_preview: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null }
 
 export type ObjectCursor = "none" | { dateAccessed: CursorOrderItem<string> } | { kind: CursorOrderItem<number> }
 
-export type ObjectFilterArgs = { favorite: boolean } | { hidden: ObjectHiddenFilter } | { kind: InOrNotIn<number> } | { tags: InOrNotIn<number> } | { labels: InOrNotIn<number> } | { dateAccessed: Range<string> }
+export type ObjectFilterArgs = { favorite: boolean } | { hidden: ObjectHiddenFilter } | { kind: InOrNotIn<number> } | { tags: TagsFilter } | { labels: InOrNotIn<number> } | { albums: InOrNotIn<number> } | { dateAccessed: Range<string> } | { mediaLocation: GeoFilter }
 
 export type ObjectHiddenFilter = "exclude" | "include"
 
@@ -535,17 +759,9 @@ export type ObjectSearchArgs = { take: number; orderAndPagination?: OrderAndPagi
 
 export type ObjectValidatorArgs = { id: number; path: string }
 
-export type ObjectWithFilePaths = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: ({ id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; ffmpeg_data: { id: number; formats: string; bit_rate: number[]; duration: number[] | null; start_time: number[] | null; chapters: FfmpegMediaChapter[]; programs: ({ program_id: number; streams: ({ stream_id: number; name: string | null; codec: { id: number; kind: string | null; sub_kind: string | null; tag: string | null; name: string | null; profile: string | null; bit_rate: number; video_props: FfmpegMediaVideoProps | null; audio_props: FfmpegMediaAudioProps | null; stream_id: number; program_id: number; ffmpeg_data_id: number } | null; aspect_ratio_num: number; aspect_ratio_den: number; frames_per_second_num: number; frames_per_second_den: number; time_base_real_den: number; time_base_real_num: number; dispositions: string | null; title: string | null; encoder: string | null; language: string | null; duration: number[] | null; metadata: number[] | null; program_id: number; ffmpeg_data_id: number })[]; name: string | null; metadata: number[] | null; ffmpeg_data_id: number })[]; title: string | null; creation_time: string | null; date: string | null; album_artist: string | null; disc: string | null; track: string | null; album: string | null; artist: string | null; metadata: number[] | null; object_id: number } | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null })[] }
+export type ObjectWithFilePaths = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; has_thumbstrip: boolean | null; has_video_preview: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: ({ id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; has_thumbstrip: boolean | null; has_video_preview: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; ffmpeg_data: { id: number; formats: string; bit_rate: number[]; duration: number[] | null; start_time: number[] | null; chapters: FfmpegMediaChapter[]; programs: ({ program_id: number; streams: ({ stream_id: number; name: string | null; codec: { id: number; kind: string | null; sub_kind: string | null; tag: string | null; name: string | null; profile: string | null; bit_rate: number; video_props: FfmpegMediaVideoProps | null; audio_props: FfmpegMediaAudioProps | null; stream_id: number; program_id: number; ffmpeg_data_id: number } | null; aspect_ratio_num: number; aspect_ratio_den: number; frames_per_second_num: number; frames_per_second_den: number; time_base_real_den: number; time_base_real_num: number; dispositions: string | null; title: string | null; encoder: string | null; language: string | null; duration: number[] | null; metadata: number[] | null; program_id: number; ffmpeg_data_id: number })[]; name: string | null; metadata: number[] | null; ffmpeg_data_id: number })[]; title: string | null; creation_time: string | null; date: string | null; album_artist: string | null; disc: string | null; track: string | null; album: string | null; artist: string | null; metadata: number[] | null; object_id: number } | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null })[] }
 
-export type ObjectWithFilePaths2 = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: ({ id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; ffmpeg_data: { id: number; formats: string; bit_rate: number[]; duration: number[] | null; start_time: number[] | null; chapters: FfmpegMediaChapter[]; programs: ({ program_id: number; streams: ({ stream_id: number; name: string | null; codec: { id: number; kind: string | null; sub_kind: string | null; tag: string | null; name: string | null; profile: string | null; bit_rate: number; video_props: FfmpegMediaVideoProps | null; audio_props: FfmpegMediaAudioProps | null; stream_id: number; program_id: number; ffmpeg_data_id: number } | null; aspect_ratio_num: number; aspect_ratio_den: number; frames_per_second_num: number; frames_per_second_den: number; time_base_real_den: number; time_base_real_num: number; dispositions: string | null; title: string | null; encoder: string | null; language: string | null; duration: number[] | null; metadata: number[] | null; program_id: number; ffmpeg_data_id: number })[]; name: string | null; metadata: number[] | null; ffmpeg_data_id: number })[]; title: string | null; creation_time: string | null; date: string | null; album_artist: string | null; disc: string | null; track: string | null; album: string | null; artist: string | null; metadata: number[] | null; object_id: number } | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null })[] }
-
-export type OldFileCopierJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string }
-
-export type OldFileCutterJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string }
-
-export type OldFileDeleterJobInit = { location_id: number; file_path_ids: number[] }
-
-export type OldFileEraserJobInit = { location_id: number; file_path_ids: number[]; passes: string }
+export type ObjectWithFilePaths2 = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; has_thumbstrip: boolean | null; has_video_preview: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: ({ id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; has_thumbstrip: boolean | null; has_video_preview: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; ffmpeg_data: { id: number; formats: string; bit_rate: number[]; duration: number[] | null; start_time: number[] | null; chapters: FfmpegMediaChapter[]; programs: ({ program_id: number; streams: ({ stream_id: number; name: string | null; codec: { id: number; kind: string | null; sub_kind: string | null; tag: string | null; name: string | null; profile: string | null; bit_rate: number; video_props: FfmpegMediaVideoProps | null; audio_props: FfmpegMediaAudioProps | null; stream_id: number; program_id: number; ffmpeg_data_id: number } | null; aspect_ratio_num: number; aspect_ratio_den: number; frames_per_second_num: number; frames_per_second_den: number; time_base_real_den: number; time_base_real_num: number; dispositions: string | null; title: string | null; encoder: string | null; language: string | null; duration: number[] | null; metadata: number[] | null; program_id: number; ffmpeg_data_id: number })[]; name: string | null; metadata: number[] | null; ffmpeg_data_id: number })[]; title: string | null; creation_time: string | null; date: string | null; album_artist: string | null; disc: string | null; track: string | null; album: string | null; artist: string | null; metadata: number[] | null; object_id: number } | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null })[] }
 
 /**
  * Represents the operating system which the remote peer is running.
@@ -559,7 +775,7 @@ export type Orientation = "Normal" | "CW90" | "CW180" | "CW270" | "MirroredVerti
 
 export type P2PDiscoveryState = "Everyone" | "ContactsOnly" | "Disabled"
 
-export type P2PEvent = { type: "PeerChange"; identity: RemoteIdentity; connection: ConnectionMethod; discovery: DiscoveryMethod; metadata: PeerMetadata; addrs: string[] } | { type: "PeerDelete"; identity: RemoteIdentity } | { type: "SpacedropRequest"; id: string; identity: RemoteIdentity; peer_name: string; files: string[] } | { type: "SpacedropProgress"; id: string; percent: number } | { type: "SpacedropTimedOut"; id: string } | { type: "SpacedropRejected"; id: string }
+export type P2PEvent = { type: "PeerChange"; identity: RemoteIdentity; connection: ConnectionMethod; discovery: DiscoveryMethod; metadata: PeerMetadata; addrs: string[] } | { type: "PeerDelete"; identity: RemoteIdentity } | { type: "SpacedropRequest"; id: string; identity: RemoteIdentity; peer_name: string; files: string[] } | { type: "SpacedropProgress"; id: string; percent: number } | { type: "SpacedropTimedOut"; id: string } | { type: "SpacedropRejected"; id: string } | { type: "PairingCode"; id: string; identity: RemoteIdentity; library_id: string; code: string } | { type: "PairingRequest"; id: string; identity: RemoteIdentity; peer_name: string; library_name: string; code: string } | { type: "PairingComplete"; id: string; library_id: string } | { type: "PairingRejected"; id: string } | { type: "PairingTimedOut"; id: string } | { type: "PairingFailed"; id: string; error: string }
 
 export type PeerMetadata = { name: string; operating_system: OperatingSystem | null; device_model: HardwareModel | null; version: string | null }
 
@@ -571,6 +787,8 @@ export type Program = { id: number; name: string | null; streams: Stream[]; meta
 
 export type Props = { Video: VideoProps } | { Audio: AudioProps } | { Subtitle: SubtitleProps }
 
+export type QualityPreset = "low" | "medium" | "high" | "maximum"
+
 export type Range<T> = { from: T } | { to: T }
 
 export type RemoteIdentity = string
@@ -589,7 +807,7 @@ export type ReportInputMetadata = { type: "location"; data: Location } | { type:
 
 export type ReportMetadata = { type: "input"; metadata: ReportInputMetadata } | { type: "output"; metadata: ReportOutputMetadata }
 
-export type ReportOutputMetadata = { type: "metrics"; data: { [key in string]: JsonValue } } | { type: "indexer"; data: { total_paths: [number, number] } } | { type: "file_identifier"; data: { total_orphan_paths: [number, number]; total_objects_created: [number, number]; total_objects_linked: [number, number] } } | { type: "media_processor"; data: { media_data_extracted: [number, number]; media_data_skipped: [number, number]; thumbnails_generated: [number, number]; thumbnails_skipped: [number, number] } } | { type: "copier"; data: { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string } } | { type: "mover"; data: { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string } } | { type: "deleter"; data: { location_id: number; file_path_ids: number[] } } | { type: "eraser"; data: { location_id: number; file_path_ids: number[]; passes: number } } | { type: "file_validator"; data: { location_id: number; sub_path: string | null } }
+export type ReportOutputMetadata = { type: "metrics"; data: { [key in string]: JsonValue } } | { type: "indexer"; data: { total_paths: [number, number] } } | { type: "file_identifier"; data: { total_orphan_paths: [number, number]; total_objects_created: [number, number]; total_objects_linked: [number, number] } } | { type: "media_processor"; data: { media_data_extracted: [number, number]; media_data_skipped: [number, number]; thumbnails_generated: [number, number]; thumbnails_skipped: [number, number] } } | { type: "copier"; data: { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string } } | { type: "mover"; data: { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string } } | { type: "deleter"; data: { location_id: number; file_path_ids: number[] } } | { type: "eraser"; data: { location_id: number; file_path_ids: number[]; passes: number } } | { type: "file_validator"; data: { location_id: number; sub_path: string | null } } | { type: "duplicate_finder"; data: { duplicate_sets: [number, number]; wasted_bytes: [number, number]; split_objects: [number, number] } }
 
 export type RescanArgs = { location_id: number; sub_path: string }
 
@@ -597,7 +815,15 @@ export type Resolution = { width: number; height: number }
 
 export type Response = { Start: { user_code: string; verification_url: string; verification_url_complete: string } } | "Complete" | { Error: string }
 
-export type RuleKind = "AcceptFilesByGlob" | "RejectFilesByGlob" | "AcceptIfChildrenDirectoriesArePresent" | "RejectIfChildrenDirectoriesArePresent" | "IgnoredByGit"
+/**
+ * How many backups of each library are kept, older ones being deleted after each backup.
+ * 
+ * The latest backup of each of the last `keep_daily` days and `keep_weekly` weeks with backups is
+ * kept, along with every backup that an incremental one being kept was made on top of.
+ */
+export type RetentionPolicy = { keep_daily?: number | null; keep_weekly?: number | null }
+
+export type RuleKind = "AcceptFilesByGlob" | "RejectFilesByGlob" | "AcceptIfChildrenDirectoriesArePresent" | "RejectIfChildrenDirectoriesArePresent" | "IgnoredByGit" | "RejectFilesLargerThan" | "AcceptFilesModifiedWithin" | "RejectFilesWithExtendedAttributes" | "RejectFilesByKind"
 
 export type SavedSearch = { id: number; pub_id: number[]; target: string | null; search: string | null; filters: string | null; name: string | null; icon: string | null; description: string | null; date_created: string | null; date_modified: string | null }
 
@@ -615,13 +841,36 @@ export type SingleInvalidateOperationEvent = {
 /**
  * This fields are intentionally private.
  */
-key: string; arg: JsonValue; result: JsonValue | null }
+key: string; arg: JsonValue; result: JsonValue | null; 
+/**
+ * The library the invalidated query belongs to, `None` for node wide queries.
+ */
+library_id: string | null }
+
+/**
+ * Upper bound for the output dimensions, files are only ever scaled down
+ */
+export type SizePreset = "original" | "uhd" | "full_hd" | "hd" | "sd"
 
 export type SortOrder = "Asc" | "Desc"
 
+export type Space = { id: number; pub_id: number[]; name: string | null; description: string | null; date_created: string | null; date_modified: string | null; cover_object_id: number | null }
+
+export type SpaceCreateArgs = { name: string; description?: string | null }
+
+export type SpaceObjectsArgs = { id: number; object_ids: number[] }
+
+export type SpaceSetCoverArgs = { id: number; 
+/**
+ * Removes the cover when missing
+ */
+object_id: number | null }
+
+export type SpaceUpdateArgs = { id: number; name: string | null; description: string | null }
+
 export type SpacedropArgs = { identity: RemoteIdentity; file_path: string[] }
 
-export type Statistics = { id: number; date_captured: string; total_object_count: number; library_db_size: string; total_local_bytes_used: string; total_local_bytes_capacity: string; total_local_bytes_free: string; total_library_bytes: string; total_library_unique_bytes: string; total_library_preview_media_bytes: string }
+export type Statistics = { id: number; date_captured: string; total_object_count: number; library_db_size: string; total_local_bytes_used: string; total_local_bytes_capacity: string; total_local_bytes_free: string; total_library_bytes: string; total_library_unique_bytes: string; total_library_preview_media_bytes: string; sync_operations_reclaimed_bytes: string }
 
 export type StatisticsResponse = { statistics: Statistics | null }
 
@@ -635,26 +884,85 @@ export type SyncStatus = { ingest: boolean; cloud_send: boolean; cloud_receive:
 
 export type SystemLocations = { desktop: string | null; documents: string | null; downloads: string | null; pictures: string | null; music: string | null; videos: string | null }
 
-export type Tag = { id: number; pub_id: number[]; name: string | null; color: string | null; is_hidden: boolean | null; date_created: string | null; date_modified: string | null }
+export type Tag = { id: number; pub_id: number[]; name: string | null; color: string | null; is_hidden: boolean | null; date_created: string | null; date_modified: string | null; parent_id: number | null }
 
-export type TagCreateArgs = { name: string; color: string }
+export type TagCreateArgs = { name: string; color: string; 
+/**
+ * Tag to nest the new one under, it's created as a root tag if missing
+ */
+parent_id?: number | null }
+
+export type TagSetParentArgs = { id: number; 
+/**
+ * Makes the tag a root one when missing
+ */
+parent_id: number | null }
 
 export type TagSettings = { explorer: ExplorerSettings<ObjectOrder> }
 
 export type TagUpdateArgs = { id: number; name: string | null; color: string | null }
 
+/**
+ * Older saved searches hold just the tags, without the `includeDescendants` flag
+ */
+export type TagsFilter = { tags: InOrNotIn<number>; includeDescendants?: boolean } | InOrNotIn<number>
+
 export type Target = { Object: number } | { FilePath: number }
 
+export type TaskSystemPreferences = { 
+/**
+ * How many workers run tasks in parallel, defaults to half of the available cores
+ */
+workers_count?: number | null; 
+/**
+ * How many tasks each kind of job can run at the same time, jobs missing here are uncapped
+ */
+job_concurrency_limits?: { [key in JobName]: number } }
+
 export type TextMatch = { contains: string } | { startsWith: string } | { endsWith: string } | { equals: string }
 
+export type TextSnippet = { 
+/**
+ * Content of the file converted to UTF-8
+ */
+text: string; 
+/**
+ * Charset the file was written in, as detected from its content
+ */
+encoding: string; 
+/**
+ * Whether the file is bigger than what we read from it
+ */
+truncated: boolean }
+
 /**
  * This type is used to pass the relevant data to the frontend so it can request the thumbnail.
  * Tt supports extending the shard hex to support deeper directory structures in the future
  */
 export type ThumbKey = { shard_hex: string; cas_id: CasId; base_directory_str: string }
 
+export type UnlockKeyArgs = { id: string; passphrase: string }
+
 export type UpdateThumbnailerPreferences = Record<string, never>
 
+/**
+ * Video containers, the codecs used are FFmpeg's defaults for each container
+ */
+/**
+ * Result of checking a backup without restoring it
+ */
+export type Verification = { header: Header; entries: string[]; 
+/**
+ * Every problem found, the backup can be restored only if there are none
+ */
+problems: string[] }
+
+export type VideoFormat = "mp4" | "webm" | "mkv"
+
 export type VideoProps = { pixel_format: string | null; color_range: string | null; bits_per_channel: number | null; color_space: string | null; color_primaries: string | null; color_transfer: string | null; field_order: string | null; chroma_location: string | null; width: number; height: number; aspect_ratio_num: number | null; aspect_ratio_den: number | null; properties: string[] }
 
-export type Volume = { name: string; mount_points: string[]; total_capacity: string; available_capacity: string; disk_type: DiskType; file_system: string | null; is_root_filesystem: boolean }
+export type Volume = { name: string; mount_points: string[]; total_capacity: string; available_capacity: string; disk_type: DiskType; file_system: string | null; is_root_filesystem: boolean; 
+/**
+ * Identifies the file system across mounts, like its UUID or serial number
+ */
+identifier: string | null }
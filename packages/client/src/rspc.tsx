@@ -11,6 +11,7 @@ import { match, P } from 'ts-pattern';
 
 import { LibraryArgs, Procedures } from './core';
 import { currentLibraryCache } from './hooks';
+import { useSelector } from './lib';
 
 type NonLibraryProcedure<T extends keyof Procedures> =
 	| Exclude<Procedures[T], { input: LibraryArgs<any> }>
@@ -99,9 +100,13 @@ export const useLibraryQuery = libraryHooks.useQuery;
 export const useLibraryMutation = libraryHooks.useMutation;
 export const useLibrarySubscription = libraryHooks.useSubscription;
 
+const selectLibraryId = (cache: typeof currentLibraryCache) => cache.id;
+
 export function useInvalidateQuery() {
 	const context = nonLibraryHooks.useContext();
-	useBridgeSubscription(['invalidation.listen'], {
+	const libraryId = useSelector(currentLibraryCache, selectLibraryId);
+
+	useBridgeSubscription(['invalidation.listen', libraryId], {
 		onData: (ops) => {
 			for (const op of ops) {
 				match(op)
@@ -117,9 +122,6 @@ export function useInvalidateQuery() {
 							context.queryClient.invalidateQueries(key);
 						}
 					})
-					.with({ type: 'all' }, (op) => {
-						context.queryClient.invalidateQueries();
-					})
 					.exhaustive();
 			}
 		}
@@ -298,6 +298,74 @@ export function useJobInfo(job: Report, realtimeUpdate: JobProgressEvent | null)
 				} ${plural(completedTaskCount, 'file')}`,
 				textItems: [[{ text: job.status }]]
 			};
+		case 'Encrypt':
+			return {
+				...data,
+				name: `${isQueued ? 'Encrypt' : isRunning ? 'Encrypting' : 'Encrypted'} ${
+					!isQueued ? completedTaskCount : ''
+				} ${plural(completedTaskCount, 'file')}`,
+				textItems: [[{ text: job.status }]]
+			};
+		case 'Decrypt':
+			return {
+				...data,
+				name: `${isQueued ? 'Decrypt' : isRunning ? 'Decrypting' : 'Decrypted'} ${
+					!isQueued ? completedTaskCount : ''
+				} ${plural(completedTaskCount, 'file')}`,
+				textItems: [[{ text: job.status }]]
+			};
+		case 'Compress':
+			return {
+				...data,
+				name: `${isQueued ? 'Compress' : isRunning ? 'Compressing' : 'Compressed'} files`,
+				textItems: [
+					[{ text: isRunning ? realtimeUpdate?.message ?? job.status : job.status }]
+				]
+			};
+		case 'Extract':
+			return {
+				...data,
+				name: `${isQueued ? 'Extract' : isRunning ? 'Extracting' : 'Extracted'} archive`,
+				textItems: [
+					[{ text: isRunning ? realtimeUpdate?.message ?? job.status : job.status }]
+				]
+			};
+		case 'Convert':
+			return {
+				...data,
+				name: `${isQueued ? 'Convert' : isRunning ? 'Converting' : 'Converted'} files`,
+				textItems: [
+					[{ text: isRunning ? realtimeUpdate?.message ?? job.status : job.status }]
+				]
+			};
+		case 'DuplicateFinder': {
+			let duplicateSets = 0n;
+			let wastedBytes = 0n;
+			for (const metadata of output) {
+				if (metadata.type === 'duplicate_finder') {
+					duplicateSets = uint32ArrayToBigInt(metadata.data.duplicate_sets);
+					wastedBytes = uint32ArrayToBigInt(metadata.data.wasted_bytes);
+				}
+			}
+
+			return {
+				...data,
+				name: `${isQueued ? 'Find' : isRunning ? 'Finding' : 'Found'} duplicate files`,
+				textItems: [
+					[
+						{
+							text:
+								isQueued || isRunning
+									? job.status
+									: `${formatNumber(duplicateSets)} ${plural(
+											duplicateSets,
+											'set'
+										)} of duplicates, ${humanizeSize(wastedBytes)} wasted`
+						}
+					]
+				]
+			};
+		}
 		default:
 			return {
 				...data,